@@ -0,0 +1,253 @@
+// Constant-time software AES.
+//
+// There are no lookup tables: SubBytes is evaluated as a circuit over
+// bit planes (bit i of every state byte packed into word i), inverting in
+// GF(2^8) as x^254 and then applying the affine map. MixColumns uses a
+// masked xtime. Every operation is a fixed sequence of shifts, ands and
+// xors, independent of the key and data.
+
+// GF(2^8) multiplication by x, without a data dependent branch.
+#[inline]
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ (((b >> 7) & 1) * 0x1b)
+}
+
+// Transposes up to 32 bytes into 8 bit planes.
+fn pack(s: &[u8]) -> [u32, ..8] {
+    let mut p = [0u32, ..8];
+    for (k, &b) in s.iter().enumerate() {
+        for i in range(0, 8u) {
+            p[i] |= ((b >> i) & 1) as u32 << k;
+        }
+    }
+    p
+}
+
+fn unpack(p: &[u32, ..8], s: &mut [u8]) {
+    for k in range(0, s.len()) {
+        let mut b = 0u8;
+        for i in range(0, 8u) {
+            b |= ((p[i] >> k) & 1) as u8 << i;
+        }
+        s[k] = b;
+    }
+}
+
+// Bitsliced multiplication modulo x^8 + x^4 + x^3 + x + 1.
+fn gf_mul(a: &[u32, ..8], b: &[u32, ..8]) -> [u32, ..8] {
+    let mut t = [0u32, ..15];
+    for i in range(0, 8u) {
+        for j in range(0, 8u) {
+            t[i + j] ^= a[i] & b[j];
+        }
+    }
+    for k in range(8, 15u).rev() {
+        t[k - 4] ^= t[k];
+        t[k - 5] ^= t[k];
+        t[k - 7] ^= t[k];
+        t[k - 8] ^= t[k];
+    }
+    let mut ret = [0u32, ..8];
+    for i in range(0, 8u) {
+        ret[i] = t[i];
+    }
+    ret
+}
+
+// x^254, which is the multiplicative inverse for x != 0 and 0 for x == 0.
+fn gf_inv(x: &[u32, ..8]) -> [u32, ..8] {
+    let x2 = gf_mul(x, x);
+    let x3 = gf_mul(&x2, x);
+    let x6 = gf_mul(&x3, &x3);
+    let x12 = gf_mul(&x6, &x6);
+    let x15 = gf_mul(&x12, &x3);
+    let x30 = gf_mul(&x15, &x15);
+    let x60 = gf_mul(&x30, &x30);
+    let x120 = gf_mul(&x60, &x60);
+    let x240 = gf_mul(&x120, &x120);
+    let x252 = gf_mul(&x240, &x12);
+    gf_mul(&x252, &x2)
+}
+
+/// Applies the S-box to every byte of s (at most 32 bytes).
+pub fn sub_bytes(s: &mut [u8]) {
+    let b = gf_inv(&pack(s));
+    let mut o = [0u32, ..8];
+    for i in range(0, 8u) {
+        let c = if (0x63 >> i) & 1 == 1 { 0xffffffff } else { 0 };
+        o[i] = b[i] ^ b[(i + 7) % 8] ^ b[(i + 6) % 8] ^ b[(i + 5) % 8] ^ b[(i + 4) % 8] ^ c;
+    }
+    unpack(&o, s);
+}
+
+/// Applies the inverse S-box to every byte of s (at most 32 bytes).
+pub fn inv_sub_bytes(s: &mut [u8]) {
+    let x = pack(s);
+    let mut b = [0u32, ..8];
+    for i in range(0, 8u) {
+        let c = if (0x05 >> i) & 1 == 1 { 0xffffffff } else { 0 };
+        b[i] = x[(i + 7) % 8] ^ x[(i + 5) % 8] ^ x[(i + 2) % 8] ^ c;
+    }
+    unpack(&gf_inv(&b), s);
+}
+
+fn shift_rows(s: &mut [u8]) {
+    let mut t = [0u8, ..16];
+    for c in range(0, 4u) {
+        for r in range(0, 4u) {
+            t[r + 4 * c] = s[r + 4 * ((c + r) % 4)];
+        }
+    }
+    s.copy_from(t.as_slice());
+}
+
+fn inv_shift_rows(s: &mut [u8]) {
+    let mut t = [0u8, ..16];
+    for c in range(0, 4u) {
+        for r in range(0, 4u) {
+            t[r + 4 * ((c + r) % 4)] = s[r + 4 * c];
+        }
+    }
+    s.copy_from(t.as_slice());
+}
+
+fn mix_column(a: &mut [u8]) {
+    let (a0, a1, a2, a3) = (a[0], a[1], a[2], a[3]);
+    a[0] = xtime(a0) ^ xtime(a1) ^ a1 ^ a2 ^ a3;
+    a[1] = a0 ^ xtime(a1) ^ xtime(a2) ^ a2 ^ a3;
+    a[2] = a0 ^ a1 ^ xtime(a2) ^ xtime(a3) ^ a3;
+    a[3] = xtime(a0) ^ a0 ^ a1 ^ a2 ^ xtime(a3);
+}
+
+// InvMixColumns is MixColumns after multiplying by 4x^2 + 5 (FIPS-197
+// section 5.3.3 factored as in the AES book).
+fn inv_mix_column(a: &mut [u8]) {
+    let u = xtime(xtime(a[0] ^ a[2]));
+    let v = xtime(xtime(a[1] ^ a[3]));
+    a[0] ^= u;
+    a[1] ^= v;
+    a[2] ^= u;
+    a[3] ^= v;
+    mix_column(a);
+}
+
+fn mix_columns(s: &mut [u8]) {
+    for c in s.mut_chunks(4) {
+        mix_column(c);
+    }
+}
+
+/// Applies InvMixColumns to a 16-byte block.
+pub fn inv_mix_columns(s: &mut [u8]) {
+    for c in s.mut_chunks(4) {
+        inv_mix_column(c);
+    }
+}
+
+fn add_round_key(s: &mut [u8], k: &[u8]) {
+    for i in range(0, 16u) {
+        s[i] ^= k[i];
+    }
+}
+
+/// Expands a 16, 24 or 32 byte key into 16 * (rounds + 1) bytes of round
+/// keys, in the order they are used for encryption.
+pub fn expand_key(key: &[u8]) -> ~[u8] {
+    let nk = key.len() / 4;
+    let rounds = nk + 6;
+    let mut w = Vec::with_capacity(16 * (rounds + 1));
+    w.push_all(key);
+    let mut rcon = 1u8;
+    for i in range(nk, 4 * (rounds + 1)) {
+        let mut t = [0u8, ..4];
+        t.as_mut_slice().copy_from(w.as_slice().slice(4 * i - 4, 4 * i));
+        if i % nk == 0 {
+            t = [t[1], t[2], t[3], t[0]];
+            sub_bytes(t.as_mut_slice());
+            t[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if nk > 6 && i % nk == 4 {
+            sub_bytes(t.as_mut_slice());
+        }
+        for j in range(0, 4u) {
+            let b = *w.get(4 * (i - nk) + j) ^ t[j];
+            w.push(b);
+        }
+    }
+    w.as_slice().to_owned()
+}
+
+pub fn encrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    let mut st = [0u8, ..16];
+    let s = st.as_mut_slice();
+    s.copy_from(src.slice_to(16));
+    add_round_key(s, rk.slice(0, 16));
+    for r in range(1, rounds) {
+        sub_bytes(s);
+        shift_rows(s);
+        mix_columns(s);
+        add_round_key(s, rk.slice(16 * r, 16 * r + 16));
+    }
+    sub_bytes(s);
+    shift_rows(s);
+    add_round_key(s, rk.slice(16 * rounds, 16 * rounds + 16));
+    dst.copy_from(s);
+}
+
+pub fn decrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    let mut st = [0u8, ..16];
+    let s = st.as_mut_slice();
+    s.copy_from(src.slice_to(16));
+    add_round_key(s, rk.slice(16 * rounds, 16 * rounds + 16));
+    for r in range(1, rounds).rev() {
+        inv_shift_rows(s);
+        inv_sub_bytes(s);
+        add_round_key(s, rk.slice(16 * r, 16 * r + 16));
+        inv_mix_columns(s);
+    }
+    inv_shift_rows(s);
+    inv_sub_bytes(s);
+    add_round_key(s, rk.slice(0, 16));
+    dst.copy_from(s);
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sbox() {
+        let mut s = [0x00u8, 0x01, 0x53, 0xff];
+        sub_bytes(s.as_mut_slice());
+        assert_eq!(s.as_slice(), [0x63u8, 0x7c, 0xed, 0x16].as_slice());
+        inv_sub_bytes(s.as_mut_slice());
+        assert_eq!(s.as_slice(), [0x00u8, 0x01, 0x53, 0xff].as_slice());
+
+        // the S-box is a permutation
+        let mut seen = [false, ..256];
+        for i in range(0, 8u) {
+            let mut s = [0u8, ..32];
+            for j in range(0, 32u) {
+                s[j] = (32 * i + j) as u8;
+            }
+            sub_bytes(s.as_mut_slice());
+            for &b in s.iter() {
+                assert!(!seen[b as uint]);
+                seen[b as uint] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn test_expand_key() {
+        // FIPS-197 appendix A.1
+        let key = [0x2bu8, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+                   0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+        let w = expand_key(key.as_slice());
+        assert_eq!(w.len(), 176);
+        let last = [0xd0u8, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89,
+                    0xe1, 0x3f, 0x0c, 0xc8, 0xb6, 0x63, 0x0c, 0xa6];
+        assert_eq!(w.slice_from(160), last.as_slice());
+    }
+}
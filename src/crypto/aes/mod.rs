@@ -0,0 +1,222 @@
+// AES as defined in FIPS 197, after Go's crypto/aes.
+//
+// The AES-NI or ARMv8 AES path is used when the CPU has it; otherwise the
+// constant-time software implementation in generic.rs is used.
+
+use super::cipher::Block;
+
+/// The AES block size in bytes.
+pub static BlockSize: uint = 16;
+
+mod generic;
+mod ni;
+
+#[deriving(Clone)]
+pub struct Aes {
+    rounds: uint,
+    enc: ~[u8],
+    dec: ~[u8],
+    ni: bool,
+}
+
+impl Aes {
+    /// Creates a new AES cipher. The key argument should be the AES key,
+    /// either 16, 24, or 32 bytes to select AES-128, AES-192, or AES-256.
+    pub fn new(key: &[u8]) -> Result<Aes, ~str> {
+        match key.len() {
+            16 | 24 | 32 => {}
+            n => return Err(format!("crypto/aes: invalid key size {}", n)),
+        }
+        let rounds = key.len() / 4 + 6;
+        let enc = generic::expand_key(key);
+        let dec = ni::inverse_keys(enc.as_slice(), rounds);
+        Ok(Aes { rounds: rounds, enc: enc, dec: dec, ni: ni::available() })
+    }
+}
+
+impl Block for Aes {
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+
+    fn encrypt(&self, dst: &mut [u8], src: &[u8]) {
+        if src.len() < BlockSize {
+            fail!("crypto/aes: input not full block");
+        }
+        if dst.len() < BlockSize {
+            fail!("crypto/aes: output not full block");
+        }
+        if self.ni {
+            ni::encrypt(self.enc.as_slice(), self.rounds, dst, src);
+        } else {
+            generic::encrypt(self.enc.as_slice(), self.rounds, dst, src);
+        }
+    }
+
+    fn decrypt(&self, dst: &mut [u8], src: &[u8]) {
+        if src.len() < BlockSize {
+            fail!("crypto/aes: input not full block");
+        }
+        if dst.len() < BlockSize {
+            fail!("crypto/aes: output not full block");
+        }
+        if self.ni {
+            ni::decrypt(self.dec.as_slice(), self.rounds, dst, src);
+        } else {
+            generic::decrypt(self.enc.as_slice(), self.rounds, dst, src);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::ni;
+    use super::super::cipher::Block;
+
+    // FIPS 197 appendix B and C
+    static vectors: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("2b7e151628aed2a6abf7158809cf4f3c",
+         "3243f6a8885a308d313198a2e0370734",
+         "3925841d02dc09fbdc118597196a0b32"),
+        ("000102030405060708090a0b0c0d0e0f",
+         "00112233445566778899aabbccddeeff",
+         "69c4e0d86a7b0430d8cdb78070b4c55a"),
+        ("000102030405060708090a0b0c0d0e0f1011121314151617",
+         "00112233445566778899aabbccddeeff",
+         "dda97ca4864cdfe06eaf70a0ec0d7191"),
+        ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+         "00112233445566778899aabbccddeeff",
+         "8ea2b7ca516745bfeafc49904b496089"),
+    ];
+
+    // AESAVS appendix B, GFSbox: a zero key and chosen plaintexts
+    static gfsbox: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("00000000000000000000000000000000",
+         "f34481ec3cc627bacd5dc3fb08f273e6",
+         "0336763e966d92595a567cc9ce537f5e"),
+        ("00000000000000000000000000000000",
+         "9798c4640bad75c7c3227db910174e72",
+         "a9a1631bf4996954ebc093957b234589"),
+        ("00000000000000000000000000000000",
+         "96ab5c2ff612d9dfaae8c31f30c42168",
+         "ff4f8391a6a40ca5b25d23bedd44a597"),
+        ("000000000000000000000000000000000000000000000000",
+         "1b077a6af4b7f98229de786d7516b639",
+         "275cfc0413d8ccb70513c3859b1d0f72"),
+        ("000000000000000000000000000000000000000000000000",
+         "9c2d8842e5f48f57648205d39a239af1",
+         "c9b8135ff1b5adc413dfd053b21bd96d"),
+        ("000000000000000000000000000000000000000000000000",
+         "bff52510095f518ecca60af4205444bb",
+         "4a3650c3371ce2eb35e389a171427440"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "014730f80ac625fe84f026c60bfd547d",
+         "5c9d844ed46f9885085e5d6a4f94c7d7"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "0b24af36193ce4665f2825d7b4749c98",
+         "a9ff75bd7cf6613d3731c77c3b6d0c04"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "761c1fe41a18acf20d241650611d90f1",
+         "623a52fcea5d443e48d9181ab32c7421"),
+    ];
+
+    // AESAVS appendix C, KeySbox: chosen keys and a zero plaintext
+    static keysbox: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("10a58869d74be5a374cf867cfb473859",
+         "00000000000000000000000000000000",
+         "6d251e6944b051e04eaa6fb4dbf78465"),
+        ("caea65cdbb75e9169ecd22ebe6e54675",
+         "00000000000000000000000000000000",
+         "6e29201190152df4ee058139def610bb"),
+        ("a2e2fa9baf7d20822ca9f0542f764a41",
+         "00000000000000000000000000000000",
+         "c3b44b95d9d2f25670eee9a0de099fa3"),
+        ("e9f065d7c13573587f7875357dfbb16c53489f6a4bd0f7cd",
+         "00000000000000000000000000000000",
+         "0956259c9cd5cfd0181cca53380cde06"),
+        ("15d20f6ebc7e649fd95b76b107e6daba967c8a9484797f29",
+         "00000000000000000000000000000000",
+         "8e4e18424e591a3d5b6f0876f16f8594"),
+        ("28d46cffa158533194214a91e712fc2b45b518076675affd910edeca5f41ac64",
+         "00000000000000000000000000000000",
+         "4bf3b0a69aeb6657794f2901b1440ad4"),
+        ("c1cc358b449909a19436cfbb3f852ef8bcb5ed12ac7058325f56e6099aab1a1c",
+         "00000000000000000000000000000000",
+         "352065272169abf9856843927d0674fd"),
+    ];
+
+    // AESAVS appendix D, VarTxt: a zero key and plaintexts with 1, 64 and
+    // 128 leading one bits
+    static vartxt: &'static [(&'static str, &'static str, &'static str)] = &[
+        ("00000000000000000000000000000000",
+         "80000000000000000000000000000000",
+         "3ad78e726c1ec02b7ebfe92b23d9ec34"),
+        ("00000000000000000000000000000000",
+         "ffffffffffffffff0000000000000000",
+         "f807c3e7985fe0f5a50e2cdb25c5109e"),
+        ("00000000000000000000000000000000",
+         "ffffffffffffffffffffffffffffffff",
+         "3f5b8cc9ea855a0afa7347d23e8d664e"),
+        ("000000000000000000000000000000000000000000000000",
+         "80000000000000000000000000000000",
+         "6cd02513e8d4dc986b4afe087a60bd0c"),
+        ("000000000000000000000000000000000000000000000000",
+         "ffffffffffffffff0000000000000000",
+         "93baaffb35fbe739c17c6ac22eecf18f"),
+        ("000000000000000000000000000000000000000000000000",
+         "ffffffffffffffffffffffffffffffff",
+         "b13db4da1f718bc6904797c82bcf2d32"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "80000000000000000000000000000000",
+         "ddc6bf790c15760d8d9aeb6f9a75fd4e"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "ffffffffffffffff0000000000000000",
+         "9b58dbfd77fe5aca9cfc190cd1b82d19"),
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "ffffffffffffffffffffffffffffffff",
+         "acdace8078a32b1a182bfa4987ca1347"),
+    ];
+
+    fn check(c: &Aes, pt: &[u8], ct: &str) {
+        let mut out = [0u8, ..16];
+        c.encrypt(out.as_mut_slice(), pt);
+        assert_eq!(out.as_slice().to_hex(), ct.to_owned());
+        let mut back = [0u8, ..16];
+        c.decrypt(back.as_mut_slice(), out.as_slice());
+        assert_eq!(back.as_slice(), pt);
+    }
+
+    #[test]
+    fn test_aes_generic() {
+        for v in [vectors, gfsbox, keysbox, vartxt].iter() {
+            for &(key, pt, ct) in v.iter() {
+                let mut c = Aes::new(key.from_hex().unwrap().as_slice()).unwrap();
+                c.ni = false;
+                check(&c, pt.from_hex().unwrap().as_slice(), ct);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aes_ni() {
+        if !ni::available() {
+            return;
+        }
+        for v in [vectors, gfsbox, keysbox, vartxt].iter() {
+            for &(key, pt, ct) in v.iter() {
+                let c = Aes::new(key.from_hex().unwrap().as_slice()).unwrap();
+                assert!(c.ni);
+                check(&c, pt.from_hex().unwrap().as_slice(), ct);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aes_key_size() {
+        assert_eq!(Aes::new([0u8, ..15]).err(), Some(~"crypto/aes: invalid key size 15"));
+        assert!(Aes::new([0u8, ..33]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+// AES-NI and ARMv8 AES implementations, used when the CPU reports support
+// for them.
+//
+// The round keys are the ones from the software key schedule. Decryption
+// uses the equivalent inverse cipher, so its keys are the encryption keys
+// in reverse order with InvMixColumns applied to the inner ones.
+
+use super::generic;
+use super::super::cpu;
+
+/// Reports whether the CPU supports the AES-NI or ARMv8 AES instructions.
+pub fn available() -> bool {
+    cpu::has_aes()
+}
+
+/// Derives the decryption keys of the equivalent inverse cipher.
+pub fn inverse_keys(enc: &[u8], rounds: uint) -> ~[u8] {
+    let mut dec = Vec::with_capacity(enc.len());
+    for i in range(0, rounds + 1) {
+        let k = enc.slice(16 * (rounds - i), 16 * (rounds - i) + 16);
+        let mut t = [0u8, ..16];
+        t.as_mut_slice().copy_from(k);
+        if i != 0 && i != rounds {
+            generic::inv_mix_columns(t.as_mut_slice());
+        }
+        dec.push_all(t.as_slice());
+    }
+    dec.as_slice().to_owned()
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn encrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    let last = rk.slice_from(16 * rounds).as_ptr();
+    unsafe {
+        asm!("movdqu ($1), %xmm0
+              movdqu ($2), %xmm1
+              pxor %xmm1, %xmm0
+              movdqu 16($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 32($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 48($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 64($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 80($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 96($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 112($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 128($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 144($2), %xmm1
+              aesenc %xmm1, %xmm0
+              cmpq $$10, $3
+              je 1f
+              movdqu 160($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 176($2), %xmm1
+              aesenc %xmm1, %xmm0
+              cmpq $$12, $3
+              je 1f
+              movdqu 192($2), %xmm1
+              aesenc %xmm1, %xmm0
+              movdqu 208($2), %xmm1
+              aesenc %xmm1, %xmm0
+              1:
+              movdqu ($4), %xmm1
+              aesenclast %xmm1, %xmm0
+              movdqu %xmm0, ($0)"
+             :
+             : "r"(dst.as_mut_ptr()), "r"(src.as_ptr()), "r"(rk.as_ptr()),
+               "r"(rounds as u64), "r"(last)
+             : "xmm0", "xmm1", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn decrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    let last = rk.slice_from(16 * rounds).as_ptr();
+    unsafe {
+        asm!("movdqu ($1), %xmm0
+              movdqu ($2), %xmm1
+              pxor %xmm1, %xmm0
+              movdqu 16($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 32($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 48($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 64($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 80($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 96($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 112($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 128($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 144($2), %xmm1
+              aesdec %xmm1, %xmm0
+              cmpq $$10, $3
+              je 1f
+              movdqu 160($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 176($2), %xmm1
+              aesdec %xmm1, %xmm0
+              cmpq $$12, $3
+              je 1f
+              movdqu 192($2), %xmm1
+              aesdec %xmm1, %xmm0
+              movdqu 208($2), %xmm1
+              aesdec %xmm1, %xmm0
+              1:
+              movdqu ($4), %xmm1
+              aesdeclast %xmm1, %xmm0
+              movdqu %xmm0, ($0)"
+             :
+             : "r"(dst.as_mut_ptr()), "r"(src.as_ptr()), "r"(rk.as_ptr()),
+               "r"(rounds as u64), "r"(last)
+             : "xmm0", "xmm1", "memory", "cc"
+             : "volatile");
+    }
+}
+
+// AESE xors in the round key before SubBytes and ShiftRows, so each round
+// key is consumed one instruction earlier than by AESENC and the last one is
+// a plain xor. AESD and AESIMC line up with the same inverse-cipher keys.
+#[cfg(target_arch = "aarch64")]
+pub fn encrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    unsafe {
+        asm!("ld1 {v0.16b}, [$1]
+              mov x9, $2
+              sub x10, $3, #1
+              1:
+              ld1 {v1.16b}, [x9], #16
+              aese v0.16b, v1.16b
+              aesmc v0.16b, v0.16b
+              subs x10, x10, #1
+              b.ne 1b
+              ld1 {v1.16b, v2.16b}, [x9]
+              aese v0.16b, v1.16b
+              eor v0.16b, v0.16b, v2.16b
+              st1 {v0.16b}, [$0]"
+             :
+             : "r"(dst.as_mut_ptr()), "r"(src.as_ptr()), "r"(rk.as_ptr()),
+               "r"(rounds as u64)
+             : "v0", "v1", "v2", "x9", "x10", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn decrypt(rk: &[u8], rounds: uint, dst: &mut [u8], src: &[u8]) {
+    unsafe {
+        asm!("ld1 {v0.16b}, [$1]
+              mov x9, $2
+              sub x10, $3, #1
+              1:
+              ld1 {v1.16b}, [x9], #16
+              aesd v0.16b, v1.16b
+              aesimc v0.16b, v0.16b
+              subs x10, x10, #1
+              b.ne 1b
+              ld1 {v1.16b, v2.16b}, [x9]
+              aesd v0.16b, v1.16b
+              eor v0.16b, v0.16b, v2.16b
+              st1 {v0.16b}, [$0]"
+             :
+             : "r"(dst.as_mut_ptr()), "r"(src.as_ptr()), "r"(rk.as_ptr()),
+               "r"(rounds as u64)
+             : "v0", "v1", "v2", "x9", "x10", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"), not(target_arch = "aarch64"))]
+pub fn encrypt(_rk: &[u8], _rounds: uint, _dst: &mut [u8], _src: &[u8]) {
+    unreachable!();
+}
+
+#[cfg(not(target_arch = "x86_64"), not(target_arch = "aarch64"))]
+pub fn decrypt(_rk: &[u8], _rounds: uint, _dst: &mut [u8], _src: &[u8]) {
+    unreachable!();
+}
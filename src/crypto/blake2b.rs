@@ -0,0 +1,420 @@
+// The BLAKE2b hash algorithm defined by RFC 7693 and the extendable output
+// function (XOF) BLAKE2Xb.
+//
+// BLAKE2b is optimized for 64-bit platforms and produces digests of any
+// size between 1 and 64 bytes. A non-empty key turns it into a MAC.
+
+use std::cmp::min;
+use std::io;
+use std::io::IoResult;
+use super::super::hash::*;
+
+
+pub static BlockSize: uint = 128;
+/// The hash size of BLAKE2b-512 in bytes.
+pub static Size: uint = 64;
+/// The hash size of BLAKE2b-384 in bytes.
+pub static Size384: uint = 48;
+/// The hash size of BLAKE2b-256 in bytes.
+pub static Size256: uint = 32;
+
+/// Passed to `Xof::new` when the output length is not known in advance.
+pub static OutputLengthUnknown: u32 = 0xFFFFFFFF;
+// an XOF of unknown length produces at most 2^32 blocks, 256 GiB
+static maxOutputLength: u64 = (1 << 32) * 64;
+
+static iv: [u64, ..8] = [0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+                         0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179];
+
+static Sigma: [[uint, ..16], ..10] = [[ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15],
+                                      [14, 10,  4,  8,  9, 15, 13,  6,  1, 12,  0,  2, 11,  7,  5,  3],
+                                      [11,  8, 12,  0,  5,  2, 15, 13, 10, 14,  3,  6,  7,  1,  9,  4],
+                                      [ 7,  9,  3,  1, 13, 12, 11, 14,  2,  6,  5, 10,  4,  0, 15,  8],
+                                      [ 9,  0,  5,  7,  2,  4, 10, 15, 14,  1, 11, 12,  6,  8,  3, 13],
+                                      [ 2, 12,  6, 10,  0, 11,  8,  3,  4, 13,  7,  5, 15, 14,  1,  9],
+                                      [12,  5,  1, 15, 14, 13,  4, 10,  0,  7,  6,  3,  9,  2,  8, 11],
+                                      [13, 11,  7, 14, 12,  1,  3,  9,  5,  0, 15,  4,  8,  6,  2, 10],
+                                      [ 6, 15, 14,  9, 11,  3,  0,  8, 12,  2, 13,  7,  1,  4, 10,  5],
+                                      [10,  2,  8,  4,  7,  6,  1,  5, 15, 11,  9, 14,  3, 12, 13,  0]];
+
+
+#[inline]
+fn rotr64(x: u64, n: uint) -> u64 {
+    (x >> n) | (x << (64 - n))
+}
+
+#[inline]
+fn g(v: &mut [u64, ..16], a: uint, b: uint, c: uint, d: uint, x: u64, y: u64) {
+    v[a] = v[a] + v[b] + x;
+    v[d] = rotr64(v[d] ^ v[a], 32);
+    v[c] = v[c] + v[d];
+    v[b] = rotr64(v[b] ^ v[c], 24);
+    v[a] = v[a] + v[b] + y;
+    v[d] = rotr64(v[d] ^ v[a], 16);
+    v[c] = v[c] + v[d];
+    v[b] = rotr64(v[b] ^ v[c], 63);
+}
+
+
+pub struct Blake2b {
+    h: [u64, ..8],
+    c: [u64, ..2],
+    size: uint,
+    block: [u8, ..128],
+    offset: uint,
+    key: ~[u8],
+    // the parameter block, XORed into the IV on reset
+    param: [u64, ..8],
+}
+
+// deep clone
+impl Clone for Blake2b {
+    fn clone(&self) -> Blake2b {
+        Blake2b {
+            h: self.h,
+            c: self.c,
+            size: self.size,
+            block: self.block,
+            offset: self.offset,
+            key: self.key.clone(),
+            param: self.param,
+        }
+    }
+}
+
+impl Blake2b {
+    /// Returns a new BLAKE2b computing a checksum of `size` bytes, which
+    /// must be between 1 and 64. The key may be empty or at most 64 bytes.
+    pub fn new(size: uint, key: &[u8]) -> Result<Blake2b, ~str> {
+        if size < 1 || size > Size {
+            return Err(~"blake2b: invalid hash size");
+        }
+        if key.len() > Size {
+            return Err(~"blake2b: invalid key size");
+        }
+        let p0 = size as u64 | (key.len() as u64 << 8) | 1 << 16 | 1 << 24;
+        Ok(Blake2b::with_param(size, key, [p0, 0, 0, 0, 0, 0, 0, 0]))
+    }
+
+    /// BLAKE2b-512, optionally keyed.
+    pub fn new512(key: &[u8]) -> Result<Blake2b, ~str> {
+        Blake2b::new(Size, key)
+    }
+
+    /// BLAKE2b-384, optionally keyed.
+    pub fn new384(key: &[u8]) -> Result<Blake2b, ~str> {
+        Blake2b::new(Size384, key)
+    }
+
+    /// BLAKE2b-256, optionally keyed.
+    pub fn new256(key: &[u8]) -> Result<Blake2b, ~str> {
+        Blake2b::new(Size256, key)
+    }
+
+    fn with_param(size: uint, key: &[u8], param: [u64, ..8]) -> Blake2b {
+        let mut ret = Blake2b { h: [0u64, ..8],
+                                c: [0u64, ..2],
+                                size: size,
+                                block: [0u8, ..128],
+                                offset: 0,
+                                key: key.to_owned(),
+                                param: param };
+        ret.reset();
+        ret
+    }
+
+    fn increment(&mut self, n: uint) {
+        self.c[0] += n as u64;
+        if self.c[0] < n as u64 {
+            self.c[1] += 1;
+        }
+    }
+
+    fn compress(&mut self, last: bool) {
+        let mut m = [0u64, ..16];
+        for i in range(0, 16u) {
+            let mut w = 0u64;
+            for j in range(0, 8u) {
+                w |= (self.block[8 * i + j] as u64) << (8 * j);
+            }
+            m[i] = w;
+        }
+        let mut v = [0u64, ..16];
+        for i in range(0, 8u) {
+            v[i] = self.h[i];
+            v[i + 8] = iv[i];
+        }
+        v[12] ^= self.c[0];
+        v[13] ^= self.c[1];
+        if last {
+            v[14] = ! v[14];
+        }
+        for i in range(0, 12u) {
+            let s = &Sigma[i % 10];
+            g(&mut v, 0, 4,  8, 12, m[s[0]],  m[s[1]]);
+            g(&mut v, 1, 5,  9, 13, m[s[2]],  m[s[3]]);
+            g(&mut v, 2, 6, 10, 14, m[s[4]],  m[s[5]]);
+            g(&mut v, 3, 7, 11, 15, m[s[6]],  m[s[7]]);
+            g(&mut v, 0, 5, 10, 15, m[s[8]],  m[s[9]]);
+            g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+            g(&mut v, 2, 7,  8, 13, m[s[12]], m[s[13]]);
+            g(&mut v, 3, 4,  9, 14, m[s[14]], m[s[15]]);
+        }
+        for i in range(0, 8u) {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+
+    fn checksum(&mut self) -> ~[u8] {
+        let offset = self.offset;
+        self.increment(offset);
+        for i in range(offset, BlockSize) {
+            self.block[i] = 0;
+        }
+        self.compress(true);
+        let mut ret = Vec::with_capacity(Size);
+        for i in range(0, Size) {
+            ret.push((self.h[i / 8] >> (8 * (i % 8))) as u8);
+        }
+        ret.as_slice().slice_to(self.size).to_owned()
+    }
+}
+
+impl Writer for Blake2b {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let mut p = buf;
+        while p.len() > 0 {
+            // the last block is kept back, it has to be compressed
+            // with the finalization flag set
+            if self.offset == BlockSize {
+                self.increment(BlockSize);
+                self.compress(false);
+                self.offset = 0;
+            }
+            let n = min(BlockSize - self.offset, p.len());
+            for i in range(0, n) {
+                self.block[self.offset + i] = p[i];
+            }
+            self.offset += n;
+            p = p.slice_from(n);
+        }
+        Ok(())
+    }
+}
+
+impl Hash for Blake2b {
+    fn reset(&mut self) {
+        for i in range(0, 8u) {
+            self.h[i] = iv[i] ^ self.param[i];
+        }
+        self.c = [0u64, ..2];
+        self.block = [0u8, ..128];
+        self.offset = 0;
+        if self.key.len() > 0 {
+            for i in range(0, self.key.len()) {
+                self.block[i] = self.key[i];
+            }
+            self.offset = BlockSize;
+        }
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut h = self.clone();
+        h.checksum()
+    }
+
+    fn size(&self) -> uint {
+        self.size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+/// Returns the BLAKE2b-512 checksum of the data.
+pub fn sum512(data: &[u8]) -> ~[u8] {
+    let mut h = Blake2b::new512([]).unwrap();
+    h.write(data);
+    h.digest()
+}
+
+/// Returns the BLAKE2b-384 checksum of the data.
+pub fn sum384(data: &[u8]) -> ~[u8] {
+    let mut h = Blake2b::new384([]).unwrap();
+    h.write(data);
+    h.digest()
+}
+
+/// Returns the BLAKE2b-256 checksum of the data.
+pub fn sum256(data: &[u8]) -> ~[u8] {
+    let mut h = Blake2b::new256([]).unwrap();
+    h.write(data);
+    h.digest()
+}
+
+
+/// BLAKE2Xb, a variable-output-length hash. Write the input, then read
+/// the output. The output length is either fixed on creation or unknown.
+pub struct Xof {
+    d: Blake2b,
+    length: u32,
+    remaining: u64,
+    root: ~[u8],   // the root hash, computed on the first read
+    block: ~[u8],  // the output node being read
+    node: u32,
+    offset: uint,
+}
+
+impl Clone for Xof {
+    fn clone(&self) -> Xof {
+        Xof {
+            d: self.d.clone(),
+            length: self.length,
+            remaining: self.remaining,
+            root: self.root.clone(),
+            block: self.block.clone(),
+            node: self.node,
+            offset: self.offset,
+        }
+    }
+}
+
+impl Xof {
+    /// Returns a new XOF producing `size` bytes, or an unbounded stream if
+    /// `size` is `OutputLengthUnknown`. The key may be at most 64 bytes.
+    pub fn new(size: u32, key: &[u8]) -> Result<Xof, ~str> {
+        if size == 0 {
+            return Err(~"blake2b: XOF length must not be zero");
+        }
+        if key.len() > Size {
+            return Err(~"blake2b: invalid key size");
+        }
+        let p0 = Size as u64 | (key.len() as u64 << 8) | 1 << 16 | 1 << 24;
+        let d = Blake2b::with_param(Size, key, [p0, size as u64 << 32, 0, 0, 0, 0, 0, 0]);
+        let mut ret = Xof { d: d, length: size, remaining: 0, root: ~[], block: ~[], node: 0, offset: 0 };
+        ret.reset();
+        Ok(ret)
+    }
+
+    /// Discards the input and any output, as if newly created.
+    pub fn reset(&mut self) {
+        self.d.reset();
+        self.remaining = if self.length == OutputLengthUnknown {
+            maxOutputLength
+        } else {
+            self.length as u64
+        };
+        self.root = ~[];
+        self.block = ~[];
+        self.node = 0;
+        self.offset = 0;
+    }
+
+    // Output node i is an unkeyed BLAKE2b of the root hash, with the node
+    // offset set to i and at most 64 bytes of output.
+    fn next_block(&mut self) {
+        let size = min(Size as u64, self.remaining) as uint;
+        let param = [size as u64 | (Size as u64 << 32),
+                     self.node as u64 | (self.length as u64 << 32),
+                     Size as u64 << 8, 0, 0, 0, 0, 0];
+        let mut h = Blake2b::with_param(size, [], param);
+        h.write(self.root.as_slice());
+        self.block = h.digest();
+        self.node += 1;
+        self.offset = 0;
+    }
+}
+
+impl Writer for Xof {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        if self.root.len() > 0 {
+            fail!("blake2b: write to XOF after read");
+        }
+        self.d.write(buf)
+    }
+}
+
+impl Reader for Xof {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        if self.remaining == 0 {
+            return Err(io::standard_error(io::EndOfFile));
+        }
+        if self.root.len() == 0 {
+            self.root = self.d.digest();
+        }
+        let n = min(buf.len() as u64, self.remaining) as uint;
+        for i in range(0, n) {
+            if self.offset == self.block.len() {
+                self.next_block();
+            }
+            buf[i] = self.block[self.offset];
+            self.offset += 1;
+            self.remaining -= 1;
+        }
+        Ok(n)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::super::hash::*;
+
+    #[test]
+    fn test_blake2b() {
+        // RFC 7693, appendix A
+        assert_eq!(sum512(bytes!("abc")).to_hex(), ~"ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923");
+        let mut h = Blake2b::new512([]).unwrap();
+        assert_eq!(h.hexdigest(), ~"786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce");
+        h.write_str("welcome to china".repeat(10001));
+        assert_eq!(h.hexdigest(), ~"9a95f3f16c3e183b6ae3eee67ca99f1a49d0f2d59f428971b79773cdc3ab6790b4a6b9e57a31476f4275f734fcebcf8e88755aa1d43aea9e842577b1c4d102cc");
+    }
+
+    #[test]
+    fn test_blake2b_sizes() {
+        assert_eq!(sum256(bytes!("abc")).to_hex(), ~"bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319");
+        let mut h = Blake2b::new(20, []).unwrap();
+        h.write_str("abc");
+        assert_eq!(h.size(), 20);
+        assert_eq!(h.hexdigest(), ~"384264f676f39536840523f284921cdc68b6846b");
+        assert!(Blake2b::new(0, []).is_err());
+        assert!(Blake2b::new(65, []).is_err());
+    }
+
+    #[test]
+    fn test_blake2b_keyed() {
+        // from the BLAKE2 reference KAT: key 00..3f, input 00..fe
+        let key: ~[u8] = range(0u, 64).map(|i| i as u8).collect();
+        let data: ~[u8] = range(0u, 255).map(|i| i as u8).collect();
+        let mut h = Blake2b::new512(key.as_slice()).unwrap();
+        h.write(data.slice_to(100));
+        h.write(data.slice_from(100));
+        assert_eq!(h.hexdigest(), ~"142709d62e28fcccd0af97fad0f8465b971e82201dc51070faa0372aa43e92484be1c1e73ba10906d5d1853db6a4106e0a7bf9800d373d6dee2d46d62ef2a461");
+        h.reset();
+        h.write(data.as_slice());
+        assert_eq!(h.hexdigest(), ~"142709d62e28fcccd0af97fad0f8465b971e82201dc51070faa0372aa43e92484be1c1e73ba10906d5d1853db6a4106e0a7bf9800d373d6dee2d46d62ef2a461");
+        assert!(Blake2b::new512(data.slice_to(65)).is_err());
+    }
+
+    #[test]
+    fn test_blake2xb() {
+        let mut x = Xof::new(100, []).unwrap();
+        x.write_str("abc");
+        assert_eq!(x.read_bytes(100).unwrap().to_hex(), ~"e0f82b71c07860b65be612d2633becc46596a6c12a8772b561adec35721b7a5c44a7e075e8a3bc8c4fc8390a197be2085b4aa4385c207f24e46415defc659afd73bacb288080b10849aeea386c60cd3fa04c9bcbfeebaed6e98634d696b9d5bdef0ad2c5");
+        assert!(x.read_bytes(1).is_err());
+
+        let key: ~[u8] = range(0u, 64).map(|i| i as u8).collect();
+        let mut x = Xof::new(64, key.as_slice()).unwrap();
+        x.write_str("abc");
+        assert_eq!(x.read_bytes(64).unwrap().to_hex(), ~"0a8fb2ada9bc3e3419ed3b0a592bc3bc8e027505d6f7187175cc8cb788fdd2c04238db9a767b45d72e04f5c303effc527b84bc4a9e2ac3ed3358ef11d30f7c7f");
+
+        let mut x = Xof::new(OutputLengthUnknown, []).unwrap();
+        x.write_str("abc");
+        let mut out = Vec::from_slice(x.read_bytes(30).unwrap().as_slice());
+        out.push_all(x.read_bytes(50).unwrap().as_slice());
+        assert_eq!(out.as_slice().to_hex(), ~"ae080c1efbcf7f60ed52a04161d02b7ee63bed362534f0661da02c6e40cd208946d066b86b3dff620e57acea9cd72d3056cf6cb0c18341452a17ce2cced67b702669bf0bed358c1b708e97de2533b294");
+    }
+}
@@ -0,0 +1,397 @@
+// The BLAKE2s hash algorithm defined by RFC 7693 and the extendable output
+// function (XOF) BLAKE2Xs.
+//
+// BLAKE2s is optimized for 8- to 32-bit platforms and produces digests of
+// any size between 1 and 32 bytes. A non-empty key turns it into a MAC.
+
+use std::cmp::min;
+use std::io;
+use std::io::IoResult;
+use super::super::hash::*;
+
+
+pub static BlockSize: uint = 64;
+/// The hash size of BLAKE2s-256 in bytes.
+pub static Size: uint = 32;
+/// The hash size of BLAKE2s-128 in bytes.
+pub static Size128: uint = 16;
+
+/// Passed to `Xof::new` when the output length is not known in advance.
+pub static OutputLengthUnknown: u16 = 0xFFFF;
+// an XOF of unknown length produces at most 2^32 blocks, 128 GiB
+static maxOutputLength: u64 = (1 << 32) * 32;
+
+static iv: [u32, ..8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                         0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+static Sigma: [[uint, ..16], ..10] = [[ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15],
+                                      [14, 10,  4,  8,  9, 15, 13,  6,  1, 12,  0,  2, 11,  7,  5,  3],
+                                      [11,  8, 12,  0,  5,  2, 15, 13, 10, 14,  3,  6,  7,  1,  9,  4],
+                                      [ 7,  9,  3,  1, 13, 12, 11, 14,  2,  6,  5, 10,  4,  0, 15,  8],
+                                      [ 9,  0,  5,  7,  2,  4, 10, 15, 14,  1, 11, 12,  6,  8,  3, 13],
+                                      [ 2, 12,  6, 10,  0, 11,  8,  3,  4, 13,  7,  5, 15, 14,  1,  9],
+                                      [12,  5,  1, 15, 14, 13,  4, 10,  0,  7,  6,  3,  9,  2,  8, 11],
+                                      [13, 11,  7, 14, 12,  1,  3,  9,  5,  0, 15,  4,  8,  6,  2, 10],
+                                      [ 6, 15, 14,  9, 11,  3,  0,  8, 12,  2, 13,  7,  1,  4, 10,  5],
+                                      [10,  2,  8,  4,  7,  6,  1,  5, 15, 11,  9, 14,  3, 12, 13,  0]];
+
+
+#[inline]
+fn rotr32(x: u32, n: uint) -> u32 {
+    (x >> n) | (x << (32 - n))
+}
+
+#[inline]
+fn g(v: &mut [u32, ..16], a: uint, b: uint, c: uint, d: uint, x: u32, y: u32) {
+    v[a] = v[a] + v[b] + x;
+    v[d] = rotr32(v[d] ^ v[a], 16);
+    v[c] = v[c] + v[d];
+    v[b] = rotr32(v[b] ^ v[c], 12);
+    v[a] = v[a] + v[b] + y;
+    v[d] = rotr32(v[d] ^ v[a], 8);
+    v[c] = v[c] + v[d];
+    v[b] = rotr32(v[b] ^ v[c], 7);
+}
+
+
+pub struct Blake2s {
+    h: [u32, ..8],
+    c: [u32, ..2],
+    size: uint,
+    block: [u8, ..64],
+    offset: uint,
+    key: ~[u8],
+    // the parameter block, XORed into the IV on reset
+    param: [u32, ..8],
+}
+
+// deep clone
+impl Clone for Blake2s {
+    fn clone(&self) -> Blake2s {
+        Blake2s {
+            h: self.h,
+            c: self.c,
+            size: self.size,
+            block: self.block,
+            offset: self.offset,
+            key: self.key.clone(),
+            param: self.param,
+        }
+    }
+}
+
+impl Blake2s {
+    /// Returns a new BLAKE2s computing a checksum of `size` bytes, which
+    /// must be between 1 and 32. The key may be empty or at most 32 bytes.
+    pub fn new(size: uint, key: &[u8]) -> Result<Blake2s, ~str> {
+        if size < 1 || size > Size {
+            return Err(~"blake2s: invalid hash size");
+        }
+        if key.len() > Size {
+            return Err(~"blake2s: invalid key size");
+        }
+        let p0 = size as u32 | (key.len() as u32 << 8) | 1 << 16 | 1 << 24;
+        Ok(Blake2s::with_param(size, key, [p0, 0, 0, 0, 0, 0, 0, 0]))
+    }
+
+    /// BLAKE2s-256, optionally keyed.
+    pub fn new256(key: &[u8]) -> Result<Blake2s, ~str> {
+        Blake2s::new(Size, key)
+    }
+
+    /// BLAKE2s-128, which should only be used keyed, as a MAC.
+    pub fn new128(key: &[u8]) -> Result<Blake2s, ~str> {
+        Blake2s::new(Size128, key)
+    }
+
+    fn with_param(size: uint, key: &[u8], param: [u32, ..8]) -> Blake2s {
+        let mut ret = Blake2s { h: [0u32, ..8],
+                                c: [0u32, ..2],
+                                size: size,
+                                block: [0u8, ..64],
+                                offset: 0,
+                                key: key.to_owned(),
+                                param: param };
+        ret.reset();
+        ret
+    }
+
+    fn increment(&mut self, n: uint) {
+        self.c[0] += n as u32;
+        if self.c[0] < n as u32 {
+            self.c[1] += 1;
+        }
+    }
+
+    fn compress(&mut self, last: bool) {
+        let mut m = [0u32, ..16];
+        for i in range(0, 16u) {
+            let mut w = 0u32;
+            for j in range(0, 4u) {
+                w |= (self.block[4 * i + j] as u32) << (8 * j);
+            }
+            m[i] = w;
+        }
+        let mut v = [0u32, ..16];
+        for i in range(0, 8u) {
+            v[i] = self.h[i];
+            v[i + 8] = iv[i];
+        }
+        v[12] ^= self.c[0];
+        v[13] ^= self.c[1];
+        if last {
+            v[14] = ! v[14];
+        }
+        for i in range(0, 10u) {
+            let s = &Sigma[i];
+            g(&mut v, 0, 4,  8, 12, m[s[0]],  m[s[1]]);
+            g(&mut v, 1, 5,  9, 13, m[s[2]],  m[s[3]]);
+            g(&mut v, 2, 6, 10, 14, m[s[4]],  m[s[5]]);
+            g(&mut v, 3, 7, 11, 15, m[s[6]],  m[s[7]]);
+            g(&mut v, 0, 5, 10, 15, m[s[8]],  m[s[9]]);
+            g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+            g(&mut v, 2, 7,  8, 13, m[s[12]], m[s[13]]);
+            g(&mut v, 3, 4,  9, 14, m[s[14]], m[s[15]]);
+        }
+        for i in range(0, 8u) {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+
+    fn checksum(&mut self) -> ~[u8] {
+        let offset = self.offset;
+        self.increment(offset);
+        for i in range(offset, BlockSize) {
+            self.block[i] = 0;
+        }
+        self.compress(true);
+        let mut ret = Vec::with_capacity(Size);
+        for i in range(0, Size) {
+            ret.push((self.h[i / 4] >> (8 * (i % 4))) as u8);
+        }
+        ret.as_slice().slice_to(self.size).to_owned()
+    }
+}
+
+impl Writer for Blake2s {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let mut p = buf;
+        while p.len() > 0 {
+            // the last block is kept back, it has to be compressed
+            // with the finalization flag set
+            if self.offset == BlockSize {
+                self.increment(BlockSize);
+                self.compress(false);
+                self.offset = 0;
+            }
+            let n = min(BlockSize - self.offset, p.len());
+            for i in range(0, n) {
+                self.block[self.offset + i] = p[i];
+            }
+            self.offset += n;
+            p = p.slice_from(n);
+        }
+        Ok(())
+    }
+}
+
+impl Hash for Blake2s {
+    fn reset(&mut self) {
+        for i in range(0, 8u) {
+            self.h[i] = iv[i] ^ self.param[i];
+        }
+        self.c = [0u32, ..2];
+        self.block = [0u8, ..64];
+        self.offset = 0;
+        if self.key.len() > 0 {
+            for i in range(0, self.key.len()) {
+                self.block[i] = self.key[i];
+            }
+            self.offset = BlockSize;
+        }
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut h = self.clone();
+        h.checksum()
+    }
+
+    fn size(&self) -> uint {
+        self.size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+/// Returns the BLAKE2s-256 checksum of the data.
+pub fn sum256(data: &[u8]) -> ~[u8] {
+    let mut h = Blake2s::new256([]).unwrap();
+    h.write(data);
+    h.digest()
+}
+
+
+/// BLAKE2Xs, a variable-output-length hash. Write the input, then read
+/// the output. The output length is either fixed on creation or unknown.
+pub struct Xof {
+    d: Blake2s,
+    length: u16,
+    remaining: u64,
+    root: ~[u8],   // the root hash, computed on the first read
+    block: ~[u8],  // the output node being read
+    node: u32,
+    offset: uint,
+}
+
+impl Clone for Xof {
+    fn clone(&self) -> Xof {
+        Xof {
+            d: self.d.clone(),
+            length: self.length,
+            remaining: self.remaining,
+            root: self.root.clone(),
+            block: self.block.clone(),
+            node: self.node,
+            offset: self.offset,
+        }
+    }
+}
+
+impl Xof {
+    /// Returns a new XOF producing `size` bytes, or an unbounded stream if
+    /// `size` is `OutputLengthUnknown`. The key may be at most 32 bytes.
+    pub fn new(size: u16, key: &[u8]) -> Result<Xof, ~str> {
+        if size == 0 {
+            return Err(~"blake2s: XOF length must not be zero");
+        }
+        if key.len() > Size {
+            return Err(~"blake2s: invalid key size");
+        }
+        let p0 = Size as u32 | (key.len() as u32 << 8) | 1 << 16 | 1 << 24;
+        let d = Blake2s::with_param(Size, key, [p0, 0, 0, size as u32, 0, 0, 0, 0]);
+        let mut ret = Xof { d: d, length: size, remaining: 0, root: ~[], block: ~[], node: 0, offset: 0 };
+        ret.reset();
+        Ok(ret)
+    }
+
+    /// Discards the input and any output, as if newly created.
+    pub fn reset(&mut self) {
+        self.d.reset();
+        self.remaining = if self.length == OutputLengthUnknown {
+            maxOutputLength
+        } else {
+            self.length as u64
+        };
+        self.root = ~[];
+        self.block = ~[];
+        self.node = 0;
+        self.offset = 0;
+    }
+
+    // Output node i is an unkeyed BLAKE2s of the root hash, with the node
+    // offset set to i and at most 32 bytes of output.
+    fn next_block(&mut self) {
+        let size = min(Size as u64, self.remaining) as uint;
+        let param = [size as u32, Size as u32, self.node,
+                     self.length as u32 | (Size as u32 << 24), 0, 0, 0, 0];
+        let mut h = Blake2s::with_param(size, [], param);
+        h.write(self.root.as_slice());
+        self.block = h.digest();
+        self.node += 1;
+        self.offset = 0;
+    }
+}
+
+impl Writer for Xof {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        if self.root.len() > 0 {
+            fail!("blake2s: write to XOF after read");
+        }
+        self.d.write(buf)
+    }
+}
+
+impl Reader for Xof {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        if self.remaining == 0 {
+            return Err(io::standard_error(io::EndOfFile));
+        }
+        if self.root.len() == 0 {
+            self.root = self.d.digest();
+        }
+        let n = min(buf.len() as u64, self.remaining) as uint;
+        for i in range(0, n) {
+            if self.offset == self.block.len() {
+                self.next_block();
+            }
+            buf[i] = self.block[self.offset];
+            self.offset += 1;
+            self.remaining -= 1;
+        }
+        Ok(n)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::super::hash::*;
+
+    #[test]
+    fn test_blake2s() {
+        // RFC 7693, appendix B
+        assert_eq!(sum256(bytes!("abc")).to_hex(), ~"508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982");
+        let mut h = Blake2s::new256([]).unwrap();
+        assert_eq!(h.hexdigest(), ~"69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9");
+        h.write_str("welcome to china".repeat(10001));
+        assert_eq!(h.hexdigest(), ~"2f39bf02193853847916b12e3c6a6b04c5328712368413212a9ad366eec025c8");
+    }
+
+    #[test]
+    fn test_blake2s_sizes() {
+        let mut h = Blake2s::new(16, []).unwrap();
+        h.write_str("abc");
+        assert_eq!(h.size(), 16);
+        assert_eq!(h.hexdigest(), ~"aa4938119b1dc7b87cbad0ffd200d0ae");
+        assert!(Blake2s::new(0, []).is_err());
+        assert!(Blake2s::new(33, []).is_err());
+    }
+
+    #[test]
+    fn test_blake2s_keyed() {
+        // from the BLAKE2 reference KAT: key 00..1f, input 00..fe
+        let key: ~[u8] = range(0u, 32).map(|i| i as u8).collect();
+        let data: ~[u8] = range(0u, 255).map(|i| i as u8).collect();
+        let mut h = Blake2s::new256(key.as_slice()).unwrap();
+        h.write(data.slice_to(64));
+        h.write(data.slice_from(64));
+        assert_eq!(h.hexdigest(), ~"3fb735061abc519dfe979e54c1ee5bfad0a9d858b3315bad34bde999efd724dd");
+        h.reset();
+        h.write(data.as_slice());
+        assert_eq!(h.hexdigest(), ~"3fb735061abc519dfe979e54c1ee5bfad0a9d858b3315bad34bde999efd724dd");
+        assert!(Blake2s::new256(data.slice_to(33)).is_err());
+    }
+
+    #[test]
+    fn test_blake2xs() {
+        let mut x = Xof::new(50, []).unwrap();
+        x.write_str("abc");
+        assert_eq!(x.read_bytes(50).unwrap().to_hex(), ~"3fbda980e22b1efa091320b6b802a41ed42f6017b56c4f9c7c5928facc12e95efdd10ec995d157f202caacbf63f1a0de9504");
+        assert!(x.read_bytes(1).is_err());
+
+        let key: ~[u8] = range(0u, 32).map(|i| i as u8).collect();
+        let mut x = Xof::new(32, key.as_slice()).unwrap();
+        x.write_str("abc");
+        assert_eq!(x.read_bytes(32).unwrap().to_hex(), ~"fc098fc04067afc968aa08046fca4d6053350444dcc3d89b645cdc0d68cbd7e1");
+
+        let mut x = Xof::new(OutputLengthUnknown, []).unwrap();
+        x.write_str("abc");
+        let mut out = Vec::from_slice(x.read_bytes(7).unwrap().as_slice());
+        out.push_all(x.read_bytes(33).unwrap().as_slice());
+        assert_eq!(out.as_slice().to_hex(), ~"bf5c4f309fde8a62195bc8364ceea81e84eb9330579270c5737b9300085b61495576fef12a5cfa71");
+    }
+}
@@ -0,0 +1,601 @@
+// The BLAKE3 hash function, with its keyed hash and key derivation modes and
+// extendable output.
+//
+// Input is split into 1 KiB chunks which form the leaves of a binary tree.
+// Whole subtrees are independent of each other, so `update_parallel` hashes
+// large inputs on several tasks, while `write` stays incremental.
+
+use std::cmp::min;
+use std::io::IoResult;
+use std::slice;
+use sync::Future;
+use super::super::hash::*;
+
+
+pub static Size: uint = 32;
+pub static KeySize: uint = 32;
+pub static BlockSize: uint = 64;
+pub static ChunkLen: uint = 1024;
+
+static ChunkStart: u32 = 1 << 0;
+static ChunkEnd: u32 = 1 << 1;
+static Parent: u32 = 1 << 2;
+static Root: u32 = 1 << 3;
+static KeyedHash: u32 = 1 << 4;
+static DeriveKeyContext: u32 = 1 << 5;
+static DeriveKeyMaterial: u32 = 1 << 6;
+
+static iv: [u32, ..8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                         0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+static MsgPermutation: [uint, ..16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+
+#[inline]
+fn rotr32(x: u32, n: uint) -> u32 {
+    (x >> n) | (x << (32 - n))
+}
+
+#[inline]
+fn g(v: &mut [u32, ..16], a: uint, b: uint, c: uint, d: uint, x: u32, y: u32) {
+    v[a] = v[a] + v[b] + x;
+    v[d] = rotr32(v[d] ^ v[a], 16);
+    v[c] = v[c] + v[d];
+    v[b] = rotr32(v[b] ^ v[c], 12);
+    v[a] = v[a] + v[b] + y;
+    v[d] = rotr32(v[d] ^ v[a], 8);
+    v[c] = v[c] + v[d];
+    v[b] = rotr32(v[b] ^ v[c], 7);
+}
+
+fn compress(cv: &[u32, ..8], block_words: &[u32, ..16], counter: u64,
+            block_len: u32, flags: u32) -> [u32, ..16] {
+    let mut v = [cv[0], cv[1], cv[2], cv[3], cv[4], cv[5], cv[6], cv[7],
+                 iv[0], iv[1], iv[2], iv[3],
+                 counter as u32, (counter >> 32) as u32, block_len, flags];
+    let mut m = *block_words;
+    for round in range(0, 7u) {
+        g(&mut v, 0, 4,  8, 12, m[0],  m[1]);
+        g(&mut v, 1, 5,  9, 13, m[2],  m[3]);
+        g(&mut v, 2, 6, 10, 14, m[4],  m[5]);
+        g(&mut v, 3, 7, 11, 15, m[6],  m[7]);
+        g(&mut v, 0, 5, 10, 15, m[8],  m[9]);
+        g(&mut v, 1, 6, 11, 12, m[10], m[11]);
+        g(&mut v, 2, 7,  8, 13, m[12], m[13]);
+        g(&mut v, 3, 4,  9, 14, m[14], m[15]);
+        if round < 6 {
+            let mut permuted = [0u32, ..16];
+            for i in range(0, 16u) {
+                permuted[i] = m[MsgPermutation[i]];
+            }
+            m = permuted;
+        }
+    }
+    for i in range(0, 8u) {
+        v[i] ^= v[i + 8];
+        v[i + 8] ^= cv[i];
+    }
+    v
+}
+
+fn first_8_words(v: [u32, ..16]) -> [u32, ..8] {
+    [v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]]
+}
+
+fn words_from_le_bytes(p: &[u8], words: &mut [u32]) {
+    for i in range(0, words.len()) {
+        let j = i * 4;
+        words[i] = p[j] as u32 | p[j+1] as u32 << 8 | p[j+2] as u32 << 16 | p[j+3] as u32 << 24;
+    }
+}
+
+
+// The state just before the final compression of a node. Chunks and
+// parents both end in one, and only the root is compressed with the Root
+// flag, possibly several times for extended output.
+struct Output {
+    input_cv: [u32, ..8],
+    block_words: [u32, ..16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32, ..8] {
+        first_8_words(compress(&self.input_cv, &self.block_words, self.counter,
+                               self.block_len, self.flags))
+    }
+
+    fn root_output_block(&self, output_block_counter: u64) -> [u32, ..16] {
+        compress(&self.input_cv, &self.block_words, output_block_counter,
+                 self.block_len, self.flags | Root)
+    }
+}
+
+fn parent_output(left: [u32, ..8], right: [u32, ..8], key: [u32, ..8], flags: u32) -> Output {
+    let mut block_words = [0u32, ..16];
+    for i in range(0, 8u) {
+        block_words[i] = left[i];
+        block_words[i + 8] = right[i];
+    }
+    Output { input_cv: key, block_words: block_words, counter: 0, block_len: BlockSize as u32,
+             flags: Parent | flags }
+}
+
+fn parent_cv(left: [u32, ..8], right: [u32, ..8], key: [u32, ..8], flags: u32) -> [u32, ..8] {
+    parent_output(left, right, key, flags).chaining_value()
+}
+
+
+struct ChunkState {
+    chaining_value: [u32, ..8],
+    chunk_counter: u64,
+    block: [u8, ..64],
+    block_len: uint,
+    blocks_compressed: uint,
+    flags: u32,
+}
+
+impl ChunkState {
+    fn new(key: [u32, ..8], chunk_counter: u64, flags: u32) -> ChunkState {
+        ChunkState { chaining_value: key, chunk_counter: chunk_counter, block: [0u8, ..64],
+                     block_len: 0, blocks_compressed: 0, flags: flags }
+    }
+
+    fn len(&self) -> uint {
+        BlockSize * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { ChunkStart } else { 0 }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let mut input = input;
+        while input.len() > 0 {
+            // a full block is only compressed once more input arrives,
+            // the last one needs the ChunkEnd flag
+            if self.block_len == BlockSize {
+                let mut block_words = [0u32, ..16];
+                words_from_le_bytes(self.block.as_slice(), block_words.as_mut_slice());
+                let flags = self.flags | self.start_flag();
+                self.chaining_value = first_8_words(compress(&self.chaining_value, &block_words,
+                                                             self.chunk_counter,
+                                                             BlockSize as u32, flags));
+                self.blocks_compressed += 1;
+                self.block = [0u8, ..64];
+                self.block_len = 0;
+            }
+            let take = min(BlockSize - self.block_len, input.len());
+            for i in range(0, take) {
+                self.block[self.block_len + i] = input[i];
+            }
+            self.block_len += take;
+            input = input.slice_from(take);
+        }
+    }
+
+    fn output(&self) -> Output {
+        let mut block_words = [0u32, ..16];
+        words_from_le_bytes(self.block.as_slice(), block_words.as_mut_slice());
+        Output { input_cv: self.chaining_value, block_words: block_words,
+                 counter: self.chunk_counter, block_len: self.block_len as u32,
+                 flags: self.flags | self.start_flag() | ChunkEnd }
+    }
+}
+
+impl Clone for ChunkState {
+    fn clone(&self) -> ChunkState {
+        ChunkState {
+            chaining_value: self.chaining_value,
+            chunk_counter: self.chunk_counter,
+            block: self.block,
+            block_len: self.block_len,
+            blocks_compressed: self.blocks_compressed,
+            flags: self.flags,
+        }
+    }
+}
+
+
+// Chaining value of a whole subtree whose chunks start at `counter`,
+// never the root. Up to `threads` tasks take part.
+fn subtree_cv(input: &[u8], key: [u32, ..8], counter: u64, flags: u32, threads: uint) -> [u32, ..8] {
+    if input.len() <= ChunkLen {
+        let mut chunk = ChunkState::new(key, counter, flags);
+        chunk.update(input);
+        return chunk.output().chaining_value();
+    }
+    let (left, right) = subtree_halves(input, key, counter, flags, threads);
+    parent_cv(left, right, key, flags)
+}
+
+// Chaining values of the left and right children of a subtree. The left
+// one holds the largest power of two of chunks that leaves the right one
+// non-empty.
+fn subtree_halves(input: &[u8], key: [u32, ..8], counter: u64, flags: u32,
+                  threads: uint) -> ([u32, ..8], [u32, ..8]) {
+    let mut left_len = ChunkLen;
+    while 2 * left_len < input.len() {
+        left_len *= 2;
+    }
+    let (left, right) = (input.slice_to(left_len), input.slice_from(left_len));
+    let right_counter = counter + (left_len / ChunkLen) as u64;
+    if threads < 2 {
+        return (subtree_cv(left, key, counter, flags, 1),
+                subtree_cv(right, key, right_counter, flags, 1));
+    }
+
+    // The right half is hashed on another task, which only gets its
+    // address. The future is always joined before `input` goes away.
+    let (addr, len) = (right.as_ptr() as uint, right.len());
+    let other = threads / 2;
+    let future = Future::spawn(proc() {
+        unsafe {
+            slice::raw::buf_as_slice(addr as *u8, len, |right| {
+                subtree_cv(right, key, right_counter, flags, other)
+            })
+        }
+    });
+    let left_cv = subtree_cv(left, key, counter, flags, threads - other);
+    (left_cv, future.unwrap())
+}
+
+fn largest_power_of_two_leq(n: uint) -> uint {
+    let mut p = 1u;
+    while 2 * p <= n {
+        p *= 2;
+    }
+    p
+}
+
+
+pub struct Blake3 {
+    key: [u32, ..8],
+    chunk: ChunkState,
+    // chaining values of complete subtrees, merged lazily: the newest ones
+    // are only combined once it's known they aren't the root
+    cv_stack: Vec<[u32, ..8]>,
+    flags: u32,
+}
+
+// deep clone
+impl Clone for Blake3 {
+    fn clone(&self) -> Blake3 {
+        Blake3 {
+            key: self.key,
+            chunk: self.chunk.clone(),
+            cv_stack: self.cv_stack.clone(),
+            flags: self.flags,
+        }
+    }
+}
+
+impl Blake3 {
+    /// Returns a new BLAKE3 in the default hashing mode.
+    pub fn new() -> Blake3 {
+        Blake3::with_key(iv, 0)
+    }
+
+    /// Returns a new BLAKE3 in the keyed hashing mode, a MAC.
+    /// The key must be exactly 32 bytes.
+    pub fn new_keyed(key: &[u8]) -> Blake3 {
+        if key.len() != KeySize {
+            fail!("blake3: invalid key size");
+        }
+        let mut key_words = [0u32, ..8];
+        words_from_le_bytes(key, key_words.as_mut_slice());
+        Blake3::with_key(key_words, KeyedHash)
+    }
+
+    /// Returns a new BLAKE3 in the key derivation mode. The context string
+    /// should be hardcoded, globally unique and application-specific; the
+    /// key material is then written to the hasher.
+    pub fn new_derive_key(context: &str) -> Blake3 {
+        let mut context_hasher = Blake3::with_key(iv, DeriveKeyContext);
+        context_hasher.write_str(context);
+        let context_key = context_hasher.digest();
+        let mut key_words = [0u32, ..8];
+        words_from_le_bytes(context_key.as_slice(), key_words.as_mut_slice());
+        Blake3::with_key(key_words, DeriveKeyMaterial)
+    }
+
+    fn with_key(key: [u32, ..8], flags: u32) -> Blake3 {
+        Blake3 { key: key, chunk: ChunkState::new(key, 0, flags), cv_stack: Vec::new(), flags: flags }
+    }
+
+    fn merge_cv_stack(stack: &mut Vec<[u32, ..8]>, total_chunks: u64, key: [u32, ..8], flags: u32) {
+        let mut post_merge_len = 0u;
+        let mut n = total_chunks;
+        while n != 0 {
+            post_merge_len += (n & 1) as uint;
+            n >>= 1;
+        }
+        while stack.len() > post_merge_len {
+            let right = stack.pop().unwrap();
+            let left = stack.pop().unwrap();
+            stack.push(parent_cv(left, right, key, flags));
+        }
+    }
+
+    // `chunk_counter` is the index of the first chunk under `cv`.
+    fn push_cv(&mut self, cv: [u32, ..8], chunk_counter: u64) {
+        Blake3::merge_cv_stack(&mut self.cv_stack, chunk_counter, self.key, self.flags);
+        self.cv_stack.push(cv);
+    }
+
+    /// Adds input to the hash state, hashing whole subtrees on up to
+    /// `threads` tasks. The result is the same as writing it.
+    pub fn update_parallel(&mut self, input: &[u8], threads: uint) {
+        let mut input = input;
+
+        // complete the current chunk first
+        if self.chunk.len() > 0 {
+            let take = min(ChunkLen - self.chunk.len(), input.len());
+            self.chunk.update(input.slice_to(take));
+            input = input.slice_from(take);
+            if input.len() == 0 {
+                return;
+            }
+            let cv = self.chunk.output().chaining_value();
+            let counter = self.chunk.chunk_counter;
+            self.push_cv(cv, counter);
+            self.chunk = ChunkState::new(self.key, counter + 1, self.flags);
+        }
+
+        // then the largest whole subtrees the position allows, as long as
+        // some input is left after them
+        while input.len() > ChunkLen {
+            let counter = self.chunk.chunk_counter;
+            let mut subtree_len = largest_power_of_two_leq(input.len());
+            while ((subtree_len - 1) as u64) & (counter * ChunkLen as u64) != 0 {
+                subtree_len /= 2;
+            }
+            let subtree_chunks = (subtree_len / ChunkLen) as u64;
+            if subtree_len <= ChunkLen {
+                let cv = subtree_cv(input.slice_to(subtree_len), self.key, counter, self.flags, 1);
+                self.push_cv(cv, counter);
+            } else {
+                // push both halves, the subtree might turn out to be the root
+                let (left, right) = subtree_halves(input.slice_to(subtree_len), self.key,
+                                                   counter, self.flags, threads);
+                self.push_cv(left, counter);
+                self.push_cv(right, counter + subtree_chunks / 2);
+            }
+            self.chunk = ChunkState::new(self.key, counter + subtree_chunks, self.flags);
+            input = input.slice_from(subtree_len);
+        }
+
+        self.chunk.update(input);
+    }
+
+    fn final_output(&self) -> Output {
+        let mut stack = self.cv_stack.clone();
+        let mut output = if self.chunk.len() > 0 || stack.len() == 0 {
+            Blake3::merge_cv_stack(&mut stack, self.chunk.chunk_counter, self.key, self.flags);
+            self.chunk.output()
+        } else {
+            // the input ended with the two halves of a subtree
+            let right = stack.pop().unwrap();
+            let left = stack.pop().unwrap();
+            parent_output(left, right, self.key, self.flags)
+        };
+        while stack.len() > 0 {
+            let left = stack.pop().unwrap();
+            output = parent_output(left, output.chaining_value(), self.key, self.flags);
+        }
+        output
+    }
+
+    /// Finishes the hash, returning a reader for an arbitrary amount of
+    /// output. The first 32 bytes are the same as `digest()`.
+    pub fn xof(&self) -> OutputReader {
+        OutputReader { output: self.final_output(), position: 0 }
+    }
+}
+
+impl Writer for Blake3 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.update_parallel(buf, 1);
+        Ok(())
+    }
+}
+
+impl Hash for Blake3 {
+    fn reset(&mut self) {
+        self.chunk = ChunkState::new(self.key, 0, self.flags);
+        self.cv_stack = Vec::new();
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut ret = slice::from_elem(Size, 0u8);
+        let mut r = self.xof();
+        r.read(ret.as_mut_slice());
+        ret
+    }
+
+    fn size(&self) -> uint {
+        Size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+
+/// Extended output of a finished BLAKE3, see `Blake3::xof`.
+pub struct OutputReader {
+    output: Output,
+    position: u64,
+}
+
+impl OutputReader {
+    /// Moves to the given byte position in the output stream.
+    pub fn seek(&mut self, position: u64) {
+        self.position = position;
+    }
+}
+
+impl Reader for OutputReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        let mut i = 0u;
+        while i < buf.len() {
+            let block = self.output.root_output_block(self.position / 64);
+            let mut offset = (self.position % 64) as uint;
+            while offset < 64 && i < buf.len() {
+                buf[i] = (block[offset / 4] >> (8 * (offset % 4))) as u8;
+                offset += 1;
+                i += 1;
+            }
+            self.position = (self.position & !63) + offset as u64;
+        }
+        Ok(buf.len())
+    }
+}
+
+
+/// Returns the BLAKE3 hash of the data.
+pub fn hash(data: &[u8]) -> ~[u8] {
+    let mut h = Blake3::new();
+    h.write(data);
+    h.digest()
+}
+
+/// Returns the BLAKE3 hash of the data, computed on up to `threads` tasks.
+pub fn hash_parallel(data: &[u8], threads: uint) -> ~[u8] {
+    let mut h = Blake3::new();
+    h.update_parallel(data, threads);
+    h.digest()
+}
+
+/// Derives a subkey of `len` bytes from the key material for the given
+/// context string.
+pub fn derive_key(context: &str, key_material: &[u8], len: uint) -> ~[u8] {
+    let mut h = Blake3::new_derive_key(context);
+    h.write(key_material);
+    h.xof().read_bytes(len).unwrap()
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::super::hash::*;
+
+    // the official test vectors use this input pattern
+    fn input(n: uint) -> ~[u8] {
+        range(0, n).map(|i| (i % 251) as u8).collect()
+    }
+
+    static TestKey: &'static str = "whats the Elvish word for friend";
+    static TestContext: &'static str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+    static Vectors: &'static [(uint, &'static str, &'static str, &'static str)] = &[
+        (0, "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+         "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26",
+         "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d"),
+        (1, "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213",
+         "6d7878dfff2f485635d39013278ae14f1454b8c0a3a2d34bc1ab38228a80c95b",
+         "b3e2e340a117a499c6cf2398a19ee0d29cca2bb7404c73063382693bf66cb06c"),
+        (1023, "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11",
+         "c951ecdf03288d0fcc96ee3413563d8a6d3589547f2c2fb36d9786470f1b9d6e",
+         "74a16c1c3d44368a86e1ca6df64be6a2f64cce8f09220787450722d85725dea5"),
+        (1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+         "75c46f6f3d9eb4f55ecaaee480db732e6c2105546f1e675003687c31719c7ba4",
+         "7356cd7720d5b66b6d0697eb3177d9f8d73a4a5c5e968896eb6a689684302706"),
+        (1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+         "357dc55de0c7e382c900fd6e320acc04146be01db6a8ce7210b7189bd664ea69",
+         "effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb"),
+        (2048, "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+         "879cf1fa2ea0e79126cb1063617a05b6ad9d0b696d0d757cf053439f60a99dd1",
+         "7b2945cb4fef70885cc5d78a87bf6f6207dd901ff239201351ffac04e1088a23"),
+        (2049, "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030",
+         "9f29700902f7c86e514ddc4df1e3049f258b2472b6dd5267f61bf13983b78dd5",
+         "2ea477c5515cc3dd606512ee72bb3e0e758cfae7232826f35fb98ca1bcbdf273"),
+        (3072, "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2",
+         "044a0e7b172a312dc02a4c9a818c036ffa2776368d7f528268d2e6b5df191770",
+         "050df97f8c2ead654d9bb3ab8c9178edcd902a32f8495949feadcc1e0480c46b"),
+        (4097, "9b4052b38f1c5fc8b1f9ff7ac7b27cd242487b3d890d15c96a1c25b8aa0fb995",
+         "00df940cd36bb9fa7cbbc3556744e0dbc8191401afe70520ba292ee3ca80abbc",
+         "aca51029626b55fda7117b42a7c211f8c6e9ba4fe5b7a8ca922f34299500ead8"),
+        (5120, "9cadc15fed8b5d854562b26a9536d9707cadeda9b143978f319ab34230535833",
+         "2c493e48e9b9bf31e0553a22b23503c0a3388f035cece68eb438d22fa1943e20",
+         "7a7acac8a02adcf3038d74cdd1d34527de8a0fcc0ee3399d1262397ce5817f60"),
+        (8193, "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b",
+         "954a2a75420c8d6547e3ba5b98d963e6fa6491addc8c023189cc519821b4a1f5",
+         "af1e0346e389b17c23200270a64aa4e1ead98c61695d917de7d5b00491c9b0f1"),
+        (31744, "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47",
+         "efa53b389ab67c593dba624d898d0f7353ab99e4ac9d42302ee64cbf9939a419",
+         "39772aef80e0ebe60596361e45b061e8f417429d529171b6764468c22928e28e"),
+        (102400, "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085",
+         "1c35d1a5811083fd7119f5d5d1ba027b4d01c0c6c49fb6ff2cf75393ea5db4a7",
+         "4652cff7a3f385a6103b5c260fc1593e13c778dbe608efb092fe7ee69df6e9c6"),
+    ];
+
+    #[test]
+    fn test_blake3() {
+        for &(n, hash_hex, keyed_hex, derive_hex) in Vectors.iter() {
+            let data = input(n);
+            assert_eq!(hash(data.as_slice()).to_hex().as_slice(), hash_hex);
+
+            let mut h = Blake3::new_keyed(TestKey.as_bytes());
+            h.write(data.as_slice());
+            assert_eq!(h.hexdigest().as_slice(), keyed_hex);
+
+            assert_eq!(derive_key(TestContext, data.as_slice(), 32).to_hex().as_slice(), derive_hex);
+        }
+    }
+
+    #[test]
+    fn test_blake3_incremental() {
+        for &(n, hash_hex, _, _) in Vectors.iter() {
+            let data = input(n);
+            for &step in [1u, 63, 64, 1000, 1024, 1025, 4096].iter() {
+                let mut h = Blake3::new();
+                for p in data.chunks(step) {
+                    h.write(p);
+                }
+                assert_eq!(h.hexdigest().as_slice(), hash_hex);
+            }
+            let mut h = Blake3::new();
+            h.write_str("garbage");
+            h.reset();
+            h.write(data.as_slice());
+            assert_eq!(h.hexdigest().as_slice(), hash_hex);
+        }
+    }
+
+    #[test]
+    fn test_blake3_parallel() {
+        for &(n, hash_hex, keyed_hex, _) in Vectors.iter() {
+            let data = input(n);
+            assert_eq!(hash_parallel(data.as_slice(), 4).to_hex().as_slice(), hash_hex);
+
+            // an unaligned start, then parallel updates
+            let mut h = Blake3::new_keyed(TestKey.as_bytes());
+            let split = if n > 1000 { 1000 } else { n };
+            h.write(data.slice_to(split));
+            h.update_parallel(data.slice_from(split), 3);
+            assert_eq!(h.hexdigest().as_slice(), keyed_hex);
+        }
+    }
+
+    #[test]
+    fn test_blake3_xof() {
+        let data = input(1025);
+        let mut h = Blake3::new();
+        h.write(data.as_slice());
+        let mut r = h.xof();
+        assert_eq!(r.read_bytes(131).unwrap().to_hex(), ~"d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444f4c4a22b4b399155358a994e52bf255de60035742ec71bd08ac275a1b51cc6bfe332b0ef84b409108cda080e6269ed4b3e2c3f7d722aa4cdc98d16deb554e5627be8f955c98e1d5f9565a9194cad0c4285f93700062d9595adb992ae68ff12800ab67a");
+        r.seek(100);
+        assert_eq!(r.read_bytes(31).unwrap().to_hex(), ~"c98e1d5f9565a9194cad0c4285f93700062d9595adb992ae68ff12800ab67a");
+
+        let mut h = Blake3::new_keyed(TestKey.as_bytes());
+        h.write(data.as_slice());
+        assert_eq!(h.xof().read_bytes(131).unwrap().to_hex(), ~"357dc55de0c7e382c900fd6e320acc04146be01db6a8ce7210b7189bd664ea69362396b77fdc0d2634a552970843722066c3c15902ae5097e00ff53f1e116f1cd5352720113a837ab2452cafbde4d54085d9cf5d21ca613071551b25d52e69d6c81123872b6f19cd3bc1333edf0c52b94de23ba772cf82636cff4542540a7738d5b930");
+        assert_eq!(derive_key(TestContext, data.as_slice(), 131).to_hex(), ~"effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb5d31013a167509e9066273ab6e2123bc835b408b067d88f96addb550d96b6852dad38e320b9d940f86db74d398c770f462118b35d2724efa13da97194491d96dd37c3c09cbef665953f2ee85ec83d88b88d11547a6f911c8217cca46defa2751e7f3ad");
+    }
+}
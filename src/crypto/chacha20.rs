@@ -0,0 +1,1001 @@
+// The ChaCha20 and XChaCha20 stream ciphers, RFC 8439 and
+// draft-irtf-cfrg-xchacha, after golang.org/x/crypto/chacha20.
+//
+// On x86-64 the block function runs in SSE2 with one row of the state per
+// register, and runs of four blocks are computed together in SSE2, or of
+// eight in AVX2 where the CPU has it, with one word of the state of each
+// block per register; elsewhere the portable code is used.
+
+use std::cmp;
+use super::cipher::Stream;
+use super::cpu;
+
+/// The size of the key used by this cipher, in bytes.
+pub static KeySize: uint = 32;
+
+/// The size of the nonce used with the standard variant of this cipher,
+/// in bytes.
+pub static NonceSize: uint = 12;
+
+/// The size of the nonce used with the XChaCha20 variant of this cipher,
+/// in bytes.
+pub static NonceSizeX: uint = 24;
+
+static blockSize: uint = 64;
+
+// The key stream buffered by a Cipher: one run of the widest multi-block
+// code.
+static bufSize: uint = 8 * blockSize;
+
+// "expand 32-byte k"
+static j0: u32 = 0x61707865;
+static j1: u32 = 0x3320646e;
+static j2: u32 = 0x79622d32;
+static j3: u32 = 0x6b206574;
+
+#[inline]
+fn rotl32(x: u32, n: uint) -> u32 {
+    (x << n) | (x >> (32 - n))
+}
+
+#[inline]
+fn le32(b: &[u8]) -> u32 {
+    b[0] as u32 | b[1] as u32 << 8 | b[2] as u32 << 16 | b[3] as u32 << 24
+}
+
+#[inline]
+fn quarter_round(s: &mut [u32, ..16], a: uint, b: uint, c: uint, d: uint) {
+    s[a] += s[b]; s[d] = rotl32(s[d] ^ s[a], 16);
+    s[c] += s[d]; s[b] = rotl32(s[b] ^ s[c], 12);
+    s[a] += s[b]; s[d] = rotl32(s[d] ^ s[a], 8);
+    s[c] += s[d]; s[b] = rotl32(s[b] ^ s[c], 7);
+}
+
+fn rounds(s: &mut [u32, ..16]) {
+    for _ in range(0, 10u) {
+        quarter_round(s, 0, 4, 8, 12);
+        quarter_round(s, 1, 5, 9, 13);
+        quarter_round(s, 2, 6, 10, 14);
+        quarter_round(s, 3, 7, 11, 15);
+        quarter_round(s, 0, 5, 10, 15);
+        quarter_round(s, 1, 6, 11, 12);
+        quarter_round(s, 2, 7, 8, 13);
+        quarter_round(s, 3, 4, 9, 14);
+    }
+}
+
+fn block_generic(state: &[u32, ..16], out: &mut [u8, ..64]) {
+    let mut x = *state;
+    rounds(&mut x);
+    for i in range(0, 16u) {
+        let v = x[i] + state[i];
+        out[4 * i] = v as u8;
+        out[4 * i + 1] = (v >> 8) as u8;
+        out[4 * i + 2] = (v >> 16) as u8;
+        out[4 * i + 3] = (v >> 24) as u8;
+    }
+}
+
+// The state rows sit in xmm0-xmm3. A column round is four quarter rounds
+// at once; rotating rows 1-3 by one, two and three words with pshufd
+// lines the diagonals up as columns for the diagonal round. The words are
+// stored little-endian, which is already the keystream byte order.
+#[cfg(target_arch = "x86_64")]
+fn block_sse2(state: &[u32, ..16], out: &mut [u8, ..64]) {
+    unsafe {
+        asm!("movdqu ($1), %xmm0
+              movdqu 16($1), %xmm1
+              movdqu 32($1), %xmm2
+              movdqu 48($1), %xmm3
+              mov $$10, %ecx
+              1:
+              paddd %xmm1, %xmm0
+              pxor %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pslld $$16, %xmm3
+              psrld $$16, %xmm4
+              por %xmm4, %xmm3
+              paddd %xmm3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqa %xmm1, %xmm4
+              pslld $$12, %xmm1
+              psrld $$20, %xmm4
+              por %xmm4, %xmm1
+              paddd %xmm1, %xmm0
+              pxor %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pslld $$8, %xmm3
+              psrld $$24, %xmm4
+              por %xmm4, %xmm3
+              paddd %xmm3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqa %xmm1, %xmm4
+              pslld $$7, %xmm1
+              psrld $$25, %xmm4
+              por %xmm4, %xmm1
+              pshufd $$0x39, %xmm1, %xmm1
+              pshufd $$0x4e, %xmm2, %xmm2
+              pshufd $$0x93, %xmm3, %xmm3
+              paddd %xmm1, %xmm0
+              pxor %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pslld $$16, %xmm3
+              psrld $$16, %xmm4
+              por %xmm4, %xmm3
+              paddd %xmm3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqa %xmm1, %xmm4
+              pslld $$12, %xmm1
+              psrld $$20, %xmm4
+              por %xmm4, %xmm1
+              paddd %xmm1, %xmm0
+              pxor %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pslld $$8, %xmm3
+              psrld $$24, %xmm4
+              por %xmm4, %xmm3
+              paddd %xmm3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqa %xmm1, %xmm4
+              pslld $$7, %xmm1
+              psrld $$25, %xmm4
+              por %xmm4, %xmm1
+              pshufd $$0x93, %xmm1, %xmm1
+              pshufd $$0x4e, %xmm2, %xmm2
+              pshufd $$0x39, %xmm3, %xmm3
+              dec %ecx
+              jnz 1b
+              movdqu ($1), %xmm4
+              paddd %xmm4, %xmm0
+              movdqu %xmm0, ($0)
+              movdqu 16($1), %xmm4
+              paddd %xmm4, %xmm1
+              movdqu %xmm1, 16($0)
+              movdqu 32($1), %xmm4
+              paddd %xmm4, %xmm2
+              movdqu %xmm2, 32($0)
+              movdqu 48($1), %xmm4
+              paddd %xmm4, %xmm3
+              movdqu %xmm3, 48($0)"
+             :
+             : "r"(out.as_mut_ptr()), "r"(state.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "rcx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block(state: &[u32, ..16], out: &mut [u8, ..64]) {
+    block_sse2(state, out)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block(state: &[u32, ..16], out: &mut [u8, ..64]) {
+    block_generic(state, out)
+}
+
+// The double rounds over 4 blocks at once, with word j of block k in x[4*j + k].
+// Rows 0-7 and 12-15 stay in xmm0-xmm7 and xmm12-xmm15; rows 8-11 are
+// kept in x and loaded into xmm8 for each quarter round, with xmm9 as the
+// scratch register for the rotations.
+#[cfg(target_arch = "x86_64")]
+fn rounds_sse2(x: &mut [u32]) {
+    assert!(x.len() == 16 * 4);
+    unsafe {
+        asm!("movdqu ($0), %xmm0
+              movdqu 16($0), %xmm1
+              movdqu 32($0), %xmm2
+              movdqu 48($0), %xmm3
+              movdqu 64($0), %xmm4
+              movdqu 80($0), %xmm5
+              movdqu 96($0), %xmm6
+              movdqu 112($0), %xmm7
+              movdqu 192($0), %xmm12
+              movdqu 208($0), %xmm13
+              movdqu 224($0), %xmm14
+              movdqu 240($0), %xmm15
+              mov $$10, %ecx
+              1:
+              movdqu 128($0), %xmm8
+              paddd %xmm4, %xmm0
+              pxor %xmm0, %xmm12
+              movdqa %xmm12, %xmm9
+              pslld $$16, %xmm12
+              psrld $$16, %xmm9
+              por %xmm9, %xmm12
+              paddd %xmm12, %xmm8
+              pxor %xmm8, %xmm4
+              movdqa %xmm4, %xmm9
+              pslld $$12, %xmm4
+              psrld $$20, %xmm9
+              por %xmm9, %xmm4
+              paddd %xmm4, %xmm0
+              pxor %xmm0, %xmm12
+              movdqa %xmm12, %xmm9
+              pslld $$8, %xmm12
+              psrld $$24, %xmm9
+              por %xmm9, %xmm12
+              paddd %xmm12, %xmm8
+              pxor %xmm8, %xmm4
+              movdqa %xmm4, %xmm9
+              pslld $$7, %xmm4
+              psrld $$25, %xmm9
+              por %xmm9, %xmm4
+              movdqu %xmm8, 128($0)
+              movdqu 144($0), %xmm8
+              paddd %xmm5, %xmm1
+              pxor %xmm1, %xmm13
+              movdqa %xmm13, %xmm9
+              pslld $$16, %xmm13
+              psrld $$16, %xmm9
+              por %xmm9, %xmm13
+              paddd %xmm13, %xmm8
+              pxor %xmm8, %xmm5
+              movdqa %xmm5, %xmm9
+              pslld $$12, %xmm5
+              psrld $$20, %xmm9
+              por %xmm9, %xmm5
+              paddd %xmm5, %xmm1
+              pxor %xmm1, %xmm13
+              movdqa %xmm13, %xmm9
+              pslld $$8, %xmm13
+              psrld $$24, %xmm9
+              por %xmm9, %xmm13
+              paddd %xmm13, %xmm8
+              pxor %xmm8, %xmm5
+              movdqa %xmm5, %xmm9
+              pslld $$7, %xmm5
+              psrld $$25, %xmm9
+              por %xmm9, %xmm5
+              movdqu %xmm8, 144($0)
+              movdqu 160($0), %xmm8
+              paddd %xmm6, %xmm2
+              pxor %xmm2, %xmm14
+              movdqa %xmm14, %xmm9
+              pslld $$16, %xmm14
+              psrld $$16, %xmm9
+              por %xmm9, %xmm14
+              paddd %xmm14, %xmm8
+              pxor %xmm8, %xmm6
+              movdqa %xmm6, %xmm9
+              pslld $$12, %xmm6
+              psrld $$20, %xmm9
+              por %xmm9, %xmm6
+              paddd %xmm6, %xmm2
+              pxor %xmm2, %xmm14
+              movdqa %xmm14, %xmm9
+              pslld $$8, %xmm14
+              psrld $$24, %xmm9
+              por %xmm9, %xmm14
+              paddd %xmm14, %xmm8
+              pxor %xmm8, %xmm6
+              movdqa %xmm6, %xmm9
+              pslld $$7, %xmm6
+              psrld $$25, %xmm9
+              por %xmm9, %xmm6
+              movdqu %xmm8, 160($0)
+              movdqu 176($0), %xmm8
+              paddd %xmm7, %xmm3
+              pxor %xmm3, %xmm15
+              movdqa %xmm15, %xmm9
+              pslld $$16, %xmm15
+              psrld $$16, %xmm9
+              por %xmm9, %xmm15
+              paddd %xmm15, %xmm8
+              pxor %xmm8, %xmm7
+              movdqa %xmm7, %xmm9
+              pslld $$12, %xmm7
+              psrld $$20, %xmm9
+              por %xmm9, %xmm7
+              paddd %xmm7, %xmm3
+              pxor %xmm3, %xmm15
+              movdqa %xmm15, %xmm9
+              pslld $$8, %xmm15
+              psrld $$24, %xmm9
+              por %xmm9, %xmm15
+              paddd %xmm15, %xmm8
+              pxor %xmm8, %xmm7
+              movdqa %xmm7, %xmm9
+              pslld $$7, %xmm7
+              psrld $$25, %xmm9
+              por %xmm9, %xmm7
+              movdqu %xmm8, 176($0)
+              movdqu 160($0), %xmm8
+              paddd %xmm5, %xmm0
+              pxor %xmm0, %xmm15
+              movdqa %xmm15, %xmm9
+              pslld $$16, %xmm15
+              psrld $$16, %xmm9
+              por %xmm9, %xmm15
+              paddd %xmm15, %xmm8
+              pxor %xmm8, %xmm5
+              movdqa %xmm5, %xmm9
+              pslld $$12, %xmm5
+              psrld $$20, %xmm9
+              por %xmm9, %xmm5
+              paddd %xmm5, %xmm0
+              pxor %xmm0, %xmm15
+              movdqa %xmm15, %xmm9
+              pslld $$8, %xmm15
+              psrld $$24, %xmm9
+              por %xmm9, %xmm15
+              paddd %xmm15, %xmm8
+              pxor %xmm8, %xmm5
+              movdqa %xmm5, %xmm9
+              pslld $$7, %xmm5
+              psrld $$25, %xmm9
+              por %xmm9, %xmm5
+              movdqu %xmm8, 160($0)
+              movdqu 176($0), %xmm8
+              paddd %xmm6, %xmm1
+              pxor %xmm1, %xmm12
+              movdqa %xmm12, %xmm9
+              pslld $$16, %xmm12
+              psrld $$16, %xmm9
+              por %xmm9, %xmm12
+              paddd %xmm12, %xmm8
+              pxor %xmm8, %xmm6
+              movdqa %xmm6, %xmm9
+              pslld $$12, %xmm6
+              psrld $$20, %xmm9
+              por %xmm9, %xmm6
+              paddd %xmm6, %xmm1
+              pxor %xmm1, %xmm12
+              movdqa %xmm12, %xmm9
+              pslld $$8, %xmm12
+              psrld $$24, %xmm9
+              por %xmm9, %xmm12
+              paddd %xmm12, %xmm8
+              pxor %xmm8, %xmm6
+              movdqa %xmm6, %xmm9
+              pslld $$7, %xmm6
+              psrld $$25, %xmm9
+              por %xmm9, %xmm6
+              movdqu %xmm8, 176($0)
+              movdqu 128($0), %xmm8
+              paddd %xmm7, %xmm2
+              pxor %xmm2, %xmm13
+              movdqa %xmm13, %xmm9
+              pslld $$16, %xmm13
+              psrld $$16, %xmm9
+              por %xmm9, %xmm13
+              paddd %xmm13, %xmm8
+              pxor %xmm8, %xmm7
+              movdqa %xmm7, %xmm9
+              pslld $$12, %xmm7
+              psrld $$20, %xmm9
+              por %xmm9, %xmm7
+              paddd %xmm7, %xmm2
+              pxor %xmm2, %xmm13
+              movdqa %xmm13, %xmm9
+              pslld $$8, %xmm13
+              psrld $$24, %xmm9
+              por %xmm9, %xmm13
+              paddd %xmm13, %xmm8
+              pxor %xmm8, %xmm7
+              movdqa %xmm7, %xmm9
+              pslld $$7, %xmm7
+              psrld $$25, %xmm9
+              por %xmm9, %xmm7
+              movdqu %xmm8, 128($0)
+              movdqu 144($0), %xmm8
+              paddd %xmm4, %xmm3
+              pxor %xmm3, %xmm14
+              movdqa %xmm14, %xmm9
+              pslld $$16, %xmm14
+              psrld $$16, %xmm9
+              por %xmm9, %xmm14
+              paddd %xmm14, %xmm8
+              pxor %xmm8, %xmm4
+              movdqa %xmm4, %xmm9
+              pslld $$12, %xmm4
+              psrld $$20, %xmm9
+              por %xmm9, %xmm4
+              paddd %xmm4, %xmm3
+              pxor %xmm3, %xmm14
+              movdqa %xmm14, %xmm9
+              pslld $$8, %xmm14
+              psrld $$24, %xmm9
+              por %xmm9, %xmm14
+              paddd %xmm14, %xmm8
+              pxor %xmm8, %xmm4
+              movdqa %xmm4, %xmm9
+              pslld $$7, %xmm4
+              psrld $$25, %xmm9
+              por %xmm9, %xmm4
+              movdqu %xmm8, 144($0)
+              dec %ecx
+              jnz 1b
+              movdqu %xmm0, ($0)
+              movdqu %xmm1, 16($0)
+              movdqu %xmm2, 32($0)
+              movdqu %xmm3, 48($0)
+              movdqu %xmm4, 64($0)
+              movdqu %xmm5, 80($0)
+              movdqu %xmm6, 96($0)
+              movdqu %xmm7, 112($0)
+              movdqu %xmm12, 192($0)
+              movdqu %xmm13, 208($0)
+              movdqu %xmm14, 224($0)
+              movdqu %xmm15, 240($0)"
+             :
+             : "r"(x.as_mut_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8",
+               "xmm9", "xmm12", "xmm13", "xmm14", "xmm15", "rcx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+// rounds_sse2 over 8 blocks in the ymm registers, with word j of block k
+// in x[8*j + k].
+#[cfg(target_arch = "x86_64")]
+fn rounds_avx2(x: &mut [u32]) {
+    assert!(x.len() == 16 * 8);
+    unsafe {
+        asm!("vmovdqu ($0), %ymm0
+              vmovdqu 32($0), %ymm1
+              vmovdqu 64($0), %ymm2
+              vmovdqu 96($0), %ymm3
+              vmovdqu 128($0), %ymm4
+              vmovdqu 160($0), %ymm5
+              vmovdqu 192($0), %ymm6
+              vmovdqu 224($0), %ymm7
+              vmovdqu 384($0), %ymm12
+              vmovdqu 416($0), %ymm13
+              vmovdqu 448($0), %ymm14
+              vmovdqu 480($0), %ymm15
+              mov $$10, %ecx
+              1:
+              vmovdqu 256($0), %ymm8
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpxor %ymm0, %ymm12, %ymm12
+              vpslld $$16, %ymm12, %ymm9
+              vpsrld $$16, %ymm12, %ymm12
+              vpor %ymm9, %ymm12, %ymm12
+              vpaddd %ymm12, %ymm8, %ymm8
+              vpxor %ymm8, %ymm4, %ymm4
+              vpslld $$12, %ymm4, %ymm9
+              vpsrld $$20, %ymm4, %ymm4
+              vpor %ymm9, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpxor %ymm0, %ymm12, %ymm12
+              vpslld $$8, %ymm12, %ymm9
+              vpsrld $$24, %ymm12, %ymm12
+              vpor %ymm9, %ymm12, %ymm12
+              vpaddd %ymm12, %ymm8, %ymm8
+              vpxor %ymm8, %ymm4, %ymm4
+              vpslld $$7, %ymm4, %ymm9
+              vpsrld $$25, %ymm4, %ymm4
+              vpor %ymm9, %ymm4, %ymm4
+              vmovdqu %ymm8, 256($0)
+              vmovdqu 288($0), %ymm8
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpxor %ymm1, %ymm13, %ymm13
+              vpslld $$16, %ymm13, %ymm9
+              vpsrld $$16, %ymm13, %ymm13
+              vpor %ymm9, %ymm13, %ymm13
+              vpaddd %ymm13, %ymm8, %ymm8
+              vpxor %ymm8, %ymm5, %ymm5
+              vpslld $$12, %ymm5, %ymm9
+              vpsrld $$20, %ymm5, %ymm5
+              vpor %ymm9, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpxor %ymm1, %ymm13, %ymm13
+              vpslld $$8, %ymm13, %ymm9
+              vpsrld $$24, %ymm13, %ymm13
+              vpor %ymm9, %ymm13, %ymm13
+              vpaddd %ymm13, %ymm8, %ymm8
+              vpxor %ymm8, %ymm5, %ymm5
+              vpslld $$7, %ymm5, %ymm9
+              vpsrld $$25, %ymm5, %ymm5
+              vpor %ymm9, %ymm5, %ymm5
+              vmovdqu %ymm8, 288($0)
+              vmovdqu 320($0), %ymm8
+              vpaddd %ymm6, %ymm2, %ymm2
+              vpxor %ymm2, %ymm14, %ymm14
+              vpslld $$16, %ymm14, %ymm9
+              vpsrld $$16, %ymm14, %ymm14
+              vpor %ymm9, %ymm14, %ymm14
+              vpaddd %ymm14, %ymm8, %ymm8
+              vpxor %ymm8, %ymm6, %ymm6
+              vpslld $$12, %ymm6, %ymm9
+              vpsrld $$20, %ymm6, %ymm6
+              vpor %ymm9, %ymm6, %ymm6
+              vpaddd %ymm6, %ymm2, %ymm2
+              vpxor %ymm2, %ymm14, %ymm14
+              vpslld $$8, %ymm14, %ymm9
+              vpsrld $$24, %ymm14, %ymm14
+              vpor %ymm9, %ymm14, %ymm14
+              vpaddd %ymm14, %ymm8, %ymm8
+              vpxor %ymm8, %ymm6, %ymm6
+              vpslld $$7, %ymm6, %ymm9
+              vpsrld $$25, %ymm6, %ymm6
+              vpor %ymm9, %ymm6, %ymm6
+              vmovdqu %ymm8, 320($0)
+              vmovdqu 352($0), %ymm8
+              vpaddd %ymm7, %ymm3, %ymm3
+              vpxor %ymm3, %ymm15, %ymm15
+              vpslld $$16, %ymm15, %ymm9
+              vpsrld $$16, %ymm15, %ymm15
+              vpor %ymm9, %ymm15, %ymm15
+              vpaddd %ymm15, %ymm8, %ymm8
+              vpxor %ymm8, %ymm7, %ymm7
+              vpslld $$12, %ymm7, %ymm9
+              vpsrld $$20, %ymm7, %ymm7
+              vpor %ymm9, %ymm7, %ymm7
+              vpaddd %ymm7, %ymm3, %ymm3
+              vpxor %ymm3, %ymm15, %ymm15
+              vpslld $$8, %ymm15, %ymm9
+              vpsrld $$24, %ymm15, %ymm15
+              vpor %ymm9, %ymm15, %ymm15
+              vpaddd %ymm15, %ymm8, %ymm8
+              vpxor %ymm8, %ymm7, %ymm7
+              vpslld $$7, %ymm7, %ymm9
+              vpsrld $$25, %ymm7, %ymm7
+              vpor %ymm9, %ymm7, %ymm7
+              vmovdqu %ymm8, 352($0)
+              vmovdqu 320($0), %ymm8
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpxor %ymm0, %ymm15, %ymm15
+              vpslld $$16, %ymm15, %ymm9
+              vpsrld $$16, %ymm15, %ymm15
+              vpor %ymm9, %ymm15, %ymm15
+              vpaddd %ymm15, %ymm8, %ymm8
+              vpxor %ymm8, %ymm5, %ymm5
+              vpslld $$12, %ymm5, %ymm9
+              vpsrld $$20, %ymm5, %ymm5
+              vpor %ymm9, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpxor %ymm0, %ymm15, %ymm15
+              vpslld $$8, %ymm15, %ymm9
+              vpsrld $$24, %ymm15, %ymm15
+              vpor %ymm9, %ymm15, %ymm15
+              vpaddd %ymm15, %ymm8, %ymm8
+              vpxor %ymm8, %ymm5, %ymm5
+              vpslld $$7, %ymm5, %ymm9
+              vpsrld $$25, %ymm5, %ymm5
+              vpor %ymm9, %ymm5, %ymm5
+              vmovdqu %ymm8, 320($0)
+              vmovdqu 352($0), %ymm8
+              vpaddd %ymm6, %ymm1, %ymm1
+              vpxor %ymm1, %ymm12, %ymm12
+              vpslld $$16, %ymm12, %ymm9
+              vpsrld $$16, %ymm12, %ymm12
+              vpor %ymm9, %ymm12, %ymm12
+              vpaddd %ymm12, %ymm8, %ymm8
+              vpxor %ymm8, %ymm6, %ymm6
+              vpslld $$12, %ymm6, %ymm9
+              vpsrld $$20, %ymm6, %ymm6
+              vpor %ymm9, %ymm6, %ymm6
+              vpaddd %ymm6, %ymm1, %ymm1
+              vpxor %ymm1, %ymm12, %ymm12
+              vpslld $$8, %ymm12, %ymm9
+              vpsrld $$24, %ymm12, %ymm12
+              vpor %ymm9, %ymm12, %ymm12
+              vpaddd %ymm12, %ymm8, %ymm8
+              vpxor %ymm8, %ymm6, %ymm6
+              vpslld $$7, %ymm6, %ymm9
+              vpsrld $$25, %ymm6, %ymm6
+              vpor %ymm9, %ymm6, %ymm6
+              vmovdqu %ymm8, 352($0)
+              vmovdqu 256($0), %ymm8
+              vpaddd %ymm7, %ymm2, %ymm2
+              vpxor %ymm2, %ymm13, %ymm13
+              vpslld $$16, %ymm13, %ymm9
+              vpsrld $$16, %ymm13, %ymm13
+              vpor %ymm9, %ymm13, %ymm13
+              vpaddd %ymm13, %ymm8, %ymm8
+              vpxor %ymm8, %ymm7, %ymm7
+              vpslld $$12, %ymm7, %ymm9
+              vpsrld $$20, %ymm7, %ymm7
+              vpor %ymm9, %ymm7, %ymm7
+              vpaddd %ymm7, %ymm2, %ymm2
+              vpxor %ymm2, %ymm13, %ymm13
+              vpslld $$8, %ymm13, %ymm9
+              vpsrld $$24, %ymm13, %ymm13
+              vpor %ymm9, %ymm13, %ymm13
+              vpaddd %ymm13, %ymm8, %ymm8
+              vpxor %ymm8, %ymm7, %ymm7
+              vpslld $$7, %ymm7, %ymm9
+              vpsrld $$25, %ymm7, %ymm7
+              vpor %ymm9, %ymm7, %ymm7
+              vmovdqu %ymm8, 256($0)
+              vmovdqu 288($0), %ymm8
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpxor %ymm3, %ymm14, %ymm14
+              vpslld $$16, %ymm14, %ymm9
+              vpsrld $$16, %ymm14, %ymm14
+              vpor %ymm9, %ymm14, %ymm14
+              vpaddd %ymm14, %ymm8, %ymm8
+              vpxor %ymm8, %ymm4, %ymm4
+              vpslld $$12, %ymm4, %ymm9
+              vpsrld $$20, %ymm4, %ymm4
+              vpor %ymm9, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpxor %ymm3, %ymm14, %ymm14
+              vpslld $$8, %ymm14, %ymm9
+              vpsrld $$24, %ymm14, %ymm14
+              vpor %ymm9, %ymm14, %ymm14
+              vpaddd %ymm14, %ymm8, %ymm8
+              vpxor %ymm8, %ymm4, %ymm4
+              vpslld $$7, %ymm4, %ymm9
+              vpsrld $$25, %ymm4, %ymm4
+              vpor %ymm9, %ymm4, %ymm4
+              vmovdqu %ymm8, 288($0)
+              dec %ecx
+              jnz 1b
+              vmovdqu %ymm0, ($0)
+              vmovdqu %ymm1, 32($0)
+              vmovdqu %ymm2, 64($0)
+              vmovdqu %ymm3, 96($0)
+              vmovdqu %ymm4, 128($0)
+              vmovdqu %ymm5, 160($0)
+              vmovdqu %ymm6, 192($0)
+              vmovdqu %ymm7, 224($0)
+              vmovdqu %ymm12, 384($0)
+              vmovdqu %ymm13, 416($0)
+              vmovdqu %ymm14, 448($0)
+              vmovdqu %ymm15, 480($0)
+              vzeroupper"
+             :
+             : "r"(x.as_mut_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8",
+               "xmm9", "xmm12", "xmm13", "xmm14", "xmm15", "rcx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+// Computes lanes blocks, 4 or 8, starting at the counter of state.
+#[cfg(target_arch = "x86_64")]
+fn blocks_vertical(state: &[u32, ..16], lanes: uint, out: &mut [u8]) {
+    let mut x = [0u32, ..16 * 8];
+    for j in range(0, 16u) {
+        for k in range(0, lanes) {
+            x[lanes * j + k] = state[j];
+        }
+    }
+    for k in range(0, lanes) {
+        x[lanes * 12 + k] += k as u32;
+    }
+    let mut y = x;
+    if lanes == 8 {
+        rounds_avx2(y.mut_slice_to(16 * 8));
+    } else {
+        rounds_sse2(y.mut_slice_to(16 * 4));
+    }
+    for k in range(0, lanes) {
+        for j in range(0, 16u) {
+            let v = y[lanes * j + k] + x[lanes * j + k];
+            let o = blockSize * k + 4 * j;
+            out[o] = v as u8;
+            out[o + 1] = (v >> 8) as u8;
+            out[o + 2] = (v >> 16) as u8;
+            out[o + 3] = (v >> 24) as u8;
+        }
+    }
+}
+
+// Computes the leading blocks of blocks that the multi-block code covers
+// and returns how many it did.
+#[cfg(target_arch = "x86_64")]
+fn blocks_simd(state: &[u32, ..16], n: uint, avx2: bool, out: &mut [u8]) -> uint {
+    let mut s = *state;
+    let mut i = 0;
+    if avx2 && n >= 8 {
+        blocks_vertical(&s, 8, out.mut_slice_to(8 * blockSize));
+        i = 8;
+    }
+    while n - i >= 4 {
+        s[12] = state[12] + i as u32;
+        blocks_vertical(&s, 4, out.mut_slice(i * blockSize, (i + 4) * blockSize));
+        i += 4;
+    }
+    i
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn blocks_simd(_: &[u32, ..16], _: uint, _: bool, _: &mut [u8]) -> uint {
+    0
+}
+
+// Writes n blocks of key stream to out, starting at the counter of state.
+// The counter must not wrap within them.
+fn blocks(state: &[u32, ..16], n: uint, avx2: bool, out: &mut [u8]) {
+    assert!(out.len() == n * blockSize && state[12] as u64 + n as u64 <= 1 << 32);
+    let mut i = blocks_simd(state, n, avx2, out);
+    let mut s = *state;
+    let mut b = [0u8, ..64];
+    while i < n {
+        s[12] = state[12] + i as u32;
+        block(&s, &mut b);
+        out.mut_slice(i * blockSize, (i + 1) * blockSize).copy_from(b.as_slice());
+        i += 1;
+    }
+}
+
+/// A stateful instance of ChaCha20 or XChaCha20 using a particular key
+/// and nonce.
+pub struct Cipher {
+    state: [u32, ..16],
+    // buffered keystream; the last `len` bytes are unused
+    buf: [u8, ..bufSize],
+    len: uint,
+    // set once the 32-bit block counter has wrapped
+    overflow: bool,
+    avx2: bool,
+}
+
+impl Cipher {
+    /// Creates a new ChaCha20 stream cipher with the given 32 bytes key
+    /// and a 12 or 24 bytes nonce. If a 24 bytes nonce is provided, the
+    /// XChaCha20 construction will be used.
+    ///
+    /// The returned cipher is unauthenticated; most users should use the
+    /// chacha20poly1305 AEAD instead.
+    pub fn new_unauthenticated_cipher(key: &[u8], nonce: &[u8]) -> Result<Cipher, ~str> {
+        if key.len() != KeySize {
+            return Err(~"chacha20: wrong key size");
+        }
+        let mut k = [0u8, ..32];
+        k.as_mut_slice().copy_from(key);
+        let mut n = [0u8, ..12];
+        if nonce.len() == NonceSizeX {
+            let sub = try!(hchacha20(key, nonce.slice_to(16)));
+            k.as_mut_slice().copy_from(sub.as_slice());
+            n.mut_slice_from(4).copy_from(nonce.slice_from(16));
+        } else if nonce.len() == NonceSize {
+            n.as_mut_slice().copy_from(nonce);
+        } else {
+            return Err(~"chacha20: wrong nonce size");
+        }
+
+        let mut state = [0u32, ..16];
+        state[0] = j0;
+        state[1] = j1;
+        state[2] = j2;
+        state[3] = j3;
+        for i in range(0, 8u) {
+            state[4 + i] = le32(k.slice_from(4 * i));
+        }
+        for i in range(0, 3u) {
+            state[13 + i] = le32(n.slice_from(4 * i));
+        }
+        Ok(Cipher {
+            state: state,
+            buf: [0u8, ..bufSize],
+            len: 0,
+            overflow: false,
+            avx2: cpu::has_avx2(),
+        })
+    }
+
+    /// Sets the block counter, moving the key stream to byte 64 * counter.
+    /// Any unused key stream from the current block is discarded.
+    ///
+    /// The counter may move backwards; reusing key stream is then up to
+    /// the caller.
+    pub fn set_counter(&mut self, counter: u32) {
+        self.state[12] = counter;
+        self.len = 0;
+        self.overflow = false;
+    }
+
+    fn refill(&mut self) {
+        if self.overflow {
+            fail!("chacha20: counter overflow");
+        }
+        // Stop at the last block before the counter wraps.
+        let n = cmp::min(bufSize / blockSize, ((1u64 << 32) - self.state[12] as u64) as uint);
+        blocks(&self.state, n, self.avx2, self.buf.mut_slice_from(bufSize - n * blockSize));
+        self.state[12] += n as u32;
+        if self.state[12] == 0 {
+            self.overflow = true;
+        }
+        self.len = n * blockSize;
+    }
+}
+
+impl Stream for Cipher {
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
+        if dst.len() < src.len() {
+            fail!("chacha20: output smaller than input");
+        }
+        let mut i = 0;
+        while i < src.len() {
+            if self.len == 0 {
+                self.refill();
+            }
+            let n = cmp::min(self.len, src.len() - i);
+            {
+                let ks = self.buf.slice_from(bufSize - self.len);
+                for j in range(0, n) {
+                    dst[i + j] = src[i + j] ^ ks[j];
+                }
+            }
+            self.len -= n;
+            i += n;
+        }
+    }
+}
+
+/// Derives a subkey from key and the first 16 bytes of a nonce with the
+/// HChaCha20 function, as used by XChaCha20. It can also be used as a
+/// KDF for keys from X25519.
+pub fn hchacha20(key: &[u8], nonce: &[u8]) -> Result<~[u8], ~str> {
+    if key.len() != KeySize {
+        return Err(~"chacha20: wrong HChaCha20 key size");
+    }
+    if nonce.len() != 16 {
+        return Err(~"chacha20: wrong HChaCha20 nonce size");
+    }
+    let mut s = [0u32, ..16];
+    s[0] = j0;
+    s[1] = j1;
+    s[2] = j2;
+    s[3] = j3;
+    for i in range(0, 8u) {
+        s[4 + i] = le32(key.slice_from(4 * i));
+    }
+    for i in range(0, 4u) {
+        s[12 + i] = le32(nonce.slice_from(4 * i));
+    }
+    rounds(&mut s);
+    let mut out = Vec::with_capacity(32);
+    for &i in [0u, 1, 2, 3, 12, 13, 14, 15].iter() {
+        for j in range(0, 4u) {
+            out.push((s[i] >> (8 * j)) as u8);
+        }
+    }
+    Ok(out.as_slice().to_owned())
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::{block, block_generic, blocks};
+    use super::super::cpu;
+    use super::super::cipher::Stream;
+
+    fn key() -> ~[u8] {
+        range(0, 32u).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_block_function() {
+        // RFC 8439 section 2.3.2
+        let nonce = "000000090000004a00000000".from_hex().unwrap();
+        let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), nonce.as_slice()).unwrap();
+        c.set_counter(1);
+        let mut ks = [0u8, ..64];
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..64]);
+        assert_eq!(ks.as_slice().to_hex(), ~"10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e");
+    }
+
+    #[test]
+    fn test_encryption() {
+        // RFC 8439 section 2.4.2
+        let pt = bytes!("Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.");
+        let nonce = "000000000000004a00000000".from_hex().unwrap();
+        let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), nonce.as_slice()).unwrap();
+        c.set_counter(1);
+        let mut ct = Vec::from_elem(pt.len(), 0u8);
+        c.xor_key_stream(ct.mut_slice_to(10), pt.slice_to(10));
+        c.xor_key_stream(ct.mut_slice_from(10), pt.slice_from(10));
+        assert_eq!(ct.as_slice().to_hex(), ~"6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d");
+    }
+
+    #[test]
+    fn test_set_counter() {
+        let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), [0u8, ..12]).unwrap();
+        let mut ks = [0u8, ..70];
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..70]);
+        c.set_counter(5);
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..70]);
+        assert_eq!(ks.as_slice().to_hex(), ~"0be7ffa5fa90293ceda7b19d2a9741d1545f1ec0adf49ca599aca44e3567c05a206ffc953274f6e500ff395d44ff12b27a067f5c5178b1a42a1bb03748b79504fe1dadd8a354");
+
+        // the last block before the counter wraps is still usable
+        c.set_counter(0xffffffff);
+        let mut ks = [0u8, ..16];
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..16]);
+        assert_eq!(ks.as_slice().to_hex(), ~"1ce0deb8925fccea2d5587e850054559");
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_counter_overflow() {
+        let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), [0u8, ..12]).unwrap();
+        c.set_counter(0xffffffff);
+        let mut ks = [0u8, ..65];
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..65]);
+    }
+
+    #[test]
+    fn test_xchacha20() {
+        let nonce: ~[u8] = range(0, 24u).map(|i| i as u8).collect();
+        let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), nonce.as_slice()).unwrap();
+        let mut ks = [0u8, ..80];
+        c.xor_key_stream(ks.as_mut_slice(), [0u8, ..80]);
+        assert_eq!(ks.as_slice().to_hex(), ~"e53a61cef151e81401067de33adfc02e90ab205361b49b539fda7f0e63b1bc7d68fbee56c9c20c39960e595f3ea76c979804d08cfa728e66cb5f766b840ec61f9ec20f7f90d28dae334426cecb52a8e8");
+    }
+
+    #[test]
+    fn test_hchacha20() {
+        // draft-irtf-cfrg-xchacha section 2.2.1
+        let nonce = "000000090000004a0000000031415927".from_hex().unwrap();
+        let out = hchacha20(key().as_slice(), nonce.as_slice()).unwrap();
+        assert_eq!(out.as_slice().to_hex(), ~"82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc");
+    }
+
+    #[test]
+    fn test_block_generic() {
+        let mut state = [0u32, ..16];
+        let mut a = [0u8, ..64];
+        let mut b = [0u8, ..64];
+        for i in range(0, 50u32) {
+            for j in range(0, 16u) {
+                state[j] = (i + 1) * 0x9e3779b9 ^ (j as u32 * 0x7f4a7c15);
+            }
+            block(&state, &mut a);
+            block_generic(&state, &mut b);
+            assert_eq!(a.as_slice(), b.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_blocks() {
+        let mut state = [0u32, ..16];
+        for j in range(0, 16u) {
+            state[j] = j as u32 * 0x9e3779b9 ^ 0x7f4a7c15;
+        }
+        let mut b = [0u8, ..64];
+        for &avx2 in [false, cpu::has_avx2()].iter() {
+            for &ctr in [0u32, 7, 0xfffffff8].iter() {
+                for n in range(1, 9u) {
+                    state[12] = ctr;
+                    let mut out = Vec::from_elem(n * 64, 0u8);
+                    blocks(&state, n, avx2, out.as_mut_slice());
+                    for i in range(0, n) {
+                        state[12] = ctr + i as u32;
+                        block_generic(&state, &mut b);
+                        assert_eq!(out.slice(64 * i, 64 * i + 64), b.as_slice());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor_key_stream_chunks() {
+        // Uneven writes across buffer refills, up to the counter wrapping.
+        let nonce = [7u8, ..12];
+        let mut state = Cipher::new_unauthenticated_cipher(key().as_slice(), nonce).unwrap().state;
+        let mut want = Vec::new();
+        let mut b = [0u8, ..64];
+        for i in range(0, 32u32) {
+            state[12] = 0xffffffe0 + i;
+            block_generic(&state, &mut b);
+            want.push_all(b.as_slice());
+        }
+        let zero = [0u8, ..2048];
+        for &avx2 in [false, cpu::has_avx2()].iter() {
+            let mut c = Cipher::new_unauthenticated_cipher(key().as_slice(), nonce).unwrap();
+            c.avx2 = avx2;
+            c.set_counter(0xffffffe0);
+            let mut got = Vec::from_elem(2048, 0u8);
+            let mut i = 0;
+            for &n in [1u, 63, 64, 200, 512, 3, 600, 605].iter() {
+                c.xor_key_stream(got.mut_slice(i, i + n), zero.slice_to(n));
+                i += n;
+            }
+            assert_eq!(got.as_slice(), want.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_bad_sizes() {
+        assert!(Cipher::new_unauthenticated_cipher([0u8, ..31], [0u8, ..12]).is_err());
+        assert!(Cipher::new_unauthenticated_cipher([0u8, ..32], [0u8, ..16]).is_err());
+    }
+}
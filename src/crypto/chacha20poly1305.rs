@@ -0,0 +1,274 @@
+// The ChaCha20-Poly1305 AEAD and its extended nonce variant
+// XChaCha20-Poly1305, as specified in RFC 8439 and draft-irtf-cfrg-xchacha,
+// after golang.org/x/crypto/chacha20poly1305.
+//
+// Neither needs AES hardware, so they are the AEADs to prefer on machines
+// without AES-NI.
+
+use std::slice;
+use super::chacha20;
+use super::cipher::{AEAD, Stream};
+use super::poly1305;
+use super::subtle::constant_time_compare;
+
+/// The size of the key used by this AEAD, in bytes.
+pub static KeySize: uint = 32;
+
+/// The size of the nonce used with the standard variant of this AEAD,
+/// in bytes.
+///
+/// Note that this is too short to be safely generated at random if the
+/// same key is reused more than 2^32 times.
+pub static NonceSize: uint = 12;
+
+/// The size of the nonce used with the XChaCha20-Poly1305 variant of this
+/// AEAD, in bytes. It is long enough to be generated at random.
+pub static NonceSizeX: uint = 24;
+
+/// The size of the authentication tag, in bytes.
+pub static Overhead: uint = 16;
+
+/// ChaCha20-Poly1305 with 12-byte nonces.
+#[deriving(Clone)]
+pub struct ChaCha20Poly1305 {
+    key: [u8, ..32],
+}
+
+/// XChaCha20-Poly1305 with 24-byte nonces.
+#[deriving(Clone)]
+pub struct XChaCha20Poly1305 {
+    key: [u8, ..32],
+}
+
+fn copy_key(key: &[u8]) -> Result<[u8, ..32], ~str> {
+    if key.len() != KeySize {
+        return Err(~"chacha20poly1305: bad key length");
+    }
+    let mut k = [0u8, ..32];
+    k.as_mut_slice().copy_from(key);
+    Ok(k)
+}
+
+impl ChaCha20Poly1305 {
+    /// Returns a ChaCha20-Poly1305 AEAD that uses the given 256-bit key.
+    pub fn new(key: &[u8]) -> Result<ChaCha20Poly1305, ~str> {
+        Ok(ChaCha20Poly1305 { key: try!(copy_key(key)) })
+    }
+}
+
+impl XChaCha20Poly1305 {
+    /// Returns an XChaCha20-Poly1305 AEAD that uses the given 256-bit key.
+    ///
+    /// XChaCha20-Poly1305 is a ChaCha20-Poly1305 variant that takes a
+    /// longer nonce, suitable to be generated randomly without risk of
+    /// collisions. It should be preferred when nonce uniqueness cannot be
+    /// trivially ensured, or whenever nonces are randomly generated.
+    pub fn new(key: &[u8]) -> Result<XChaCha20Poly1305, ~str> {
+        Ok(XChaCha20Poly1305 { key: try!(copy_key(key)) })
+    }
+}
+
+// Sets up the stream for nonce and derives the one-time Poly1305 key from
+// the first block. The stream is left at block 1, ready for the message.
+fn setup(key: &[u8, ..32], nonce: &[u8]) -> (chacha20::Cipher, poly1305::Mac) {
+    let mut s = chacha20::Cipher::new_unauthenticated_cipher(key.as_slice(), nonce).unwrap();
+    let mut poly_key = [0u8, ..32];
+    s.xor_key_stream(poly_key.as_mut_slice(), [0u8, ..32]);
+    s.set_counter(1);
+    (s, poly1305::Mac::new(&poly_key))
+}
+
+fn write_padded(m: &mut poly1305::Mac, b: &[u8]) {
+    m.write(b).unwrap();
+    if b.len() % 16 != 0 {
+        m.write([0u8, ..16].slice_from(b.len() % 16)).unwrap();
+    }
+}
+
+fn write_lengths(m: &mut poly1305::Mac, ad_len: uint, ct_len: uint) {
+    let mut buf = [0u8, ..16];
+    for i in range(0, 8u) {
+        buf[i] = (ad_len as u64 >> (8 * i)) as u8;
+        buf[8 + i] = (ct_len as u64 >> (8 * i)) as u8;
+    }
+    m.write(buf).unwrap();
+}
+
+fn seal(key: &[u8, ..32], dst: &[u8], nonce: &[u8], plaintext: &[u8],
+        additional_data: &[u8]) -> ~[u8] {
+    if plaintext.len() as u64 > (1u64 << 38) - 64 {
+        fail!("chacha20poly1305: plaintext too large");
+    }
+    let (mut s, mut m) = setup(key, nonce);
+    let mut ct = slice::from_elem(plaintext.len(), 0u8);
+    s.xor_key_stream(ct.as_mut_slice(), plaintext);
+
+    write_padded(&mut m, additional_data);
+    write_padded(&mut m, ct.as_slice());
+    write_lengths(&mut m, additional_data.len(), ct.len());
+
+    let mut ret = Vec::with_capacity(dst.len() + ct.len() + Overhead);
+    ret.push_all(dst);
+    ret.push_all(ct.as_slice());
+    ret.push_all(m.sum().as_slice());
+    ret.as_slice().to_owned()
+}
+
+fn open(key: &[u8, ..32], dst: &[u8], nonce: &[u8], ciphertext: &[u8],
+        additional_data: &[u8]) -> Result<~[u8], ~str> {
+    if ciphertext.len() < Overhead {
+        return Err(~"chacha20poly1305: message authentication failed");
+    }
+    if ciphertext.len() as u64 > (1u64 << 38) - 48 {
+        fail!("chacha20poly1305: ciphertext too large");
+    }
+    let (ct, tag) = (ciphertext.slice_to(ciphertext.len() - Overhead),
+                     ciphertext.slice_from(ciphertext.len() - Overhead));
+    let (mut s, mut m) = setup(key, nonce);
+
+    write_padded(&mut m, additional_data);
+    write_padded(&mut m, ct);
+    write_lengths(&mut m, additional_data.len(), ct.len());
+    if !constant_time_compare(m.sum().as_slice(), tag) {
+        return Err(~"chacha20poly1305: message authentication failed");
+    }
+
+    let mut pt = slice::from_elem(ct.len(), 0u8);
+    s.xor_key_stream(pt.as_mut_slice(), ct);
+    let mut ret = Vec::with_capacity(dst.len() + pt.len());
+    ret.push_all(dst);
+    ret.push_all(pt.as_slice());
+    Ok(ret.as_slice().to_owned())
+}
+
+impl AEAD for ChaCha20Poly1305 {
+    fn nonce_size(&self) -> uint {
+        NonceSize
+    }
+
+    fn overhead(&self) -> uint {
+        Overhead
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != NonceSize {
+            fail!("chacha20poly1305: bad nonce length passed to Seal");
+        }
+        seal(&self.key, dst, nonce, plaintext, additional_data)
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != NonceSize {
+            fail!("chacha20poly1305: bad nonce length passed to Open");
+        }
+        open(&self.key, dst, nonce, ciphertext, additional_data)
+    }
+}
+
+impl AEAD for XChaCha20Poly1305 {
+    fn nonce_size(&self) -> uint {
+        NonceSizeX
+    }
+
+    fn overhead(&self) -> uint {
+        Overhead
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != NonceSizeX {
+            fail!("xchacha20poly1305: bad nonce length passed to Seal");
+        }
+        seal(&self.key, dst, nonce, plaintext, additional_data)
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != NonceSizeX {
+            fail!("xchacha20poly1305: bad nonce length passed to Open");
+        }
+        open(&self.key, dst, nonce, ciphertext, additional_data)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::cipher::AEAD;
+
+    static sunscreen: &'static str = "Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+    fn check<A: AEAD>(a: &A, nonce: &[u8], ad: &[u8], pt: &[u8], expected: &str) {
+        let sealed = a.seal([], nonce, pt, ad);
+        assert_eq!(sealed.as_slice().to_hex(), expected.to_owned());
+        assert_eq!(a.open([], nonce, sealed.as_slice(), ad).unwrap().as_slice(), pt);
+
+        // dst is kept as a prefix
+        let sealed2 = a.seal([1u8, 2, 3], nonce, pt, ad);
+        assert_eq!(sealed2.slice_to(3), [1u8, 2, 3].as_slice());
+        assert_eq!(sealed2.slice_from(3), sealed.as_slice());
+
+        let mut bad = sealed.clone();
+        bad[0] ^= 1;
+        assert_eq!(a.open([], nonce, bad.as_slice(), ad).err(),
+                   Some(~"chacha20poly1305: message authentication failed"));
+        let mut bad = sealed.clone();
+        let n = bad.len();
+        bad[n - 1] ^= 0x80;
+        assert!(a.open([], nonce, bad.as_slice(), ad).is_err());
+        assert!(a.open([], nonce, sealed.as_slice(), [0u8]).is_err());
+        assert!(a.open([], nonce, sealed.slice_to(15), ad).is_err());
+    }
+
+    #[test]
+    fn test_rfc8439() {
+        // RFC 8439 section 2.8.2
+        let key = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f".from_hex().unwrap();
+        let nonce = "070000004041424344454647".from_hex().unwrap();
+        let ad = "50515253c0c1c2c3c4c5c6c7".from_hex().unwrap();
+        let a = ChaCha20Poly1305::new(key.as_slice()).unwrap();
+        check(&a, nonce.as_slice(), ad.as_slice(), sunscreen.as_bytes(),
+              "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+               3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+               92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+               3ff4def08e4b7a9de576d26586cec64b6116\
+               1ae10b594f09e26a7e902ecbd0600691");
+    }
+
+    #[test]
+    fn test_empty() {
+        let a = ChaCha20Poly1305::new([0u8, ..32]).unwrap();
+        check(&a, [0u8, ..12], [], [], "4eb972c9a8fb3a1b382bb4d36f5ffad1");
+    }
+
+    #[test]
+    fn test_xchacha20poly1305() {
+        // draft-irtf-cfrg-xchacha appendix A.3.1
+        let key = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f".from_hex().unwrap();
+        let nonce = "404142434445464748494a4b4c4d4e4f5051525354555657".from_hex().unwrap();
+        let ad = "50515253c0c1c2c3c4c5c6c7".from_hex().unwrap();
+        let a = XChaCha20Poly1305::new(key.as_slice()).unwrap();
+        check(&a, nonce.as_slice(), ad.as_slice(), sunscreen.as_bytes(),
+              "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+               731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+               2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+               21f9664c97637da9768812f615c68b13b52e\
+               c0875924c1c7987947deafd8780acf49");
+    }
+
+    #[test]
+    fn test_key_size() {
+        assert_eq!(ChaCha20Poly1305::new([0u8, ..16]).err(),
+                   Some(~"chacha20poly1305: bad key length"));
+        assert!(XChaCha20Poly1305::new([0u8, ..33]).is_err());
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_bad_nonce() {
+        let a = ChaCha20Poly1305::new([0u8, ..32]).unwrap();
+        a.seal([], [0u8, ..24], [], []);
+    }
+}
@@ -0,0 +1,174 @@
+// Cipher block chaining (CBC) mode.
+//
+// CBC provides confidentiality by xoring (chaining) each plaintext block
+// with the previous ciphertext block before applying the block cipher.
+//
+// See NIST SP 800-38A, pp 10-11
+
+use std::slice;
+use super::*;
+use super::super::subtle::xor_bytes;
+
+/// A BlockMode which encrypts in cipher block chaining mode.
+pub struct CbcEncrypter<B> {
+    b: B,
+    iv: ~[u8],
+}
+
+impl<B: Block> CbcEncrypter<B> {
+    /// Creates a CbcEncrypter using the given Block. The length of iv must
+    /// be the same as the Block's block size.
+    pub fn new(b: B, iv: &[u8]) -> CbcEncrypter<B> {
+        if iv.len() != b.block_size() {
+            fail!("cipher.NewCBCEncrypter: IV length must equal block size");
+        }
+        CbcEncrypter { b: b, iv: iv.to_owned() }
+    }
+
+    /// Resets the IV, for reusing the encrypter with a fresh message.
+    pub fn set_iv(&mut self, iv: &[u8]) {
+        if iv.len() != self.iv.len() {
+            fail!("cipher: incorrect length IV");
+        }
+        self.iv.as_mut_slice().copy_from(iv);
+    }
+}
+
+impl<B: Block> BlockMode for CbcEncrypter<B> {
+    fn block_size(&self) -> uint {
+        self.b.block_size()
+    }
+
+    fn crypt_blocks(&mut self, dst: &mut [u8], src: &[u8]) {
+        let bs = self.b.block_size();
+        if src.len() % bs != 0 {
+            fail!("crypto/cipher: input not full blocks");
+        }
+        if dst.len() < src.len() {
+            fail!("crypto/cipher: output smaller than input");
+        }
+        let mut tmp = slice::from_elem(bs, 0u8);
+        for (i, p) in src.chunks(bs).enumerate() {
+            xor_bytes(tmp.as_mut_slice(), p, self.iv.as_slice());
+            let out = dst.mut_slice(i * bs, (i + 1) * bs);
+            self.b.encrypt(out, tmp.as_slice());
+            self.iv.as_mut_slice().copy_from(out);
+        }
+    }
+}
+
+/// A BlockMode which decrypts in cipher block chaining mode.
+pub struct CbcDecrypter<B> {
+    b: B,
+    iv: ~[u8],
+}
+
+impl<B: Block> CbcDecrypter<B> {
+    /// Creates a CbcDecrypter using the given Block. The length of iv must
+    /// be the same as the Block's block size and must match the iv used
+    /// to encrypt the data.
+    pub fn new(b: B, iv: &[u8]) -> CbcDecrypter<B> {
+        if iv.len() != b.block_size() {
+            fail!("cipher.NewCBCDecrypter: IV length must equal block size");
+        }
+        CbcDecrypter { b: b, iv: iv.to_owned() }
+    }
+
+    /// Resets the IV, for reusing the decrypter with a fresh message.
+    pub fn set_iv(&mut self, iv: &[u8]) {
+        if iv.len() != self.iv.len() {
+            fail!("cipher: incorrect length IV");
+        }
+        self.iv.as_mut_slice().copy_from(iv);
+    }
+}
+
+impl<B: Block> BlockMode for CbcDecrypter<B> {
+    fn block_size(&self) -> uint {
+        self.b.block_size()
+    }
+
+    fn crypt_blocks(&mut self, dst: &mut [u8], src: &[u8]) {
+        let bs = self.b.block_size();
+        if src.len() % bs != 0 {
+            fail!("crypto/cipher: input not full blocks");
+        }
+        if dst.len() < src.len() {
+            fail!("crypto/cipher: output smaller than input");
+        }
+        for (i, c) in src.chunks(bs).enumerate() {
+            let out = dst.mut_slice(i * bs, (i + 1) * bs);
+            self.b.decrypt(out, c);
+            for j in range(0, bs) {
+                out[j] ^= self.iv[j];
+            }
+            self.iv.as_mut_slice().copy_from(c);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::*;
+    use super::super::super::aes::Aes;
+    use super::super::test::{Toy, iv, plaintext};
+    use super::super::test::{sp800_38a_key128, sp800_38a_key256, sp800_38a_iv, sp800_38a_plaintext};
+
+    #[test]
+    fn test_cbc_toy() {
+        let p = plaintext(64);
+        let mut c = [0u8, ..64];
+        let mut e = CbcEncrypter::new(Toy::new(), iv().as_slice());
+        e.crypt_blocks(c.as_mut_slice(), p.as_slice());
+        assert_eq!(c.as_slice().to_hex(), ~"d4dcc4c4c4bcb4b4b49ca40404fcf4e42b2a15202f1ec9cc43323db8b756514419c7dd5f1917958f69270dcfd9f7e5df62eb0ce5b69770815a63744d9ebf4879");
+
+        // the same data in two calls chains through the saved iv
+        let mut d = CbcDecrypter::new(Toy::new(), iv().as_slice());
+        let mut q = [0u8, ..64];
+        d.crypt_blocks(q.mut_slice_to(16), c.slice_to(16));
+        d.crypt_blocks(q.mut_slice_from(16), c.slice_from(16));
+        assert_eq!(q.as_slice(), p.as_slice());
+
+        e.set_iv(iv().as_slice());
+        let mut c2 = [0u8, ..64];
+        e.crypt_blocks(c2.as_mut_slice(), p.as_slice());
+        assert_eq!(c2.as_slice(), c.as_slice());
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_cbc_partial_block() {
+        let mut e = CbcEncrypter::new(Toy::new(), iv().as_slice());
+        let mut c = [0u8, ..20];
+        e.crypt_blocks(c.as_mut_slice(), plaintext(20).as_slice());
+    }
+
+    #[test]
+    fn test_cbc_aes() {
+        let tests = [
+            (sp800_38a_key128,
+             "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+              73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7"),
+            (sp800_38a_key256,
+             "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+              39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b"),
+        ];
+        let ivec = sp800_38a_iv.from_hex().unwrap();
+        let p = sp800_38a_plaintext.from_hex().unwrap();
+        for &(key, ct) in tests.iter() {
+            let key = key.from_hex().unwrap();
+            let mut c = [0u8, ..64];
+            let mut e = CbcEncrypter::new(Aes::new(key.as_slice()).unwrap(), ivec.as_slice());
+            e.crypt_blocks(c.as_mut_slice(), p.as_slice());
+            assert_eq!(c.as_slice().to_hex(), ct.to_owned());
+
+            let mut q = [0u8, ..64];
+            let mut d = CbcDecrypter::new(Aes::new(key.as_slice()).unwrap(), ivec.as_slice());
+            d.crypt_blocks(q.as_mut_slice(), c.as_slice());
+            assert_eq!(q.as_slice(), p.as_slice());
+        }
+    }
+}
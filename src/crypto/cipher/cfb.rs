@@ -0,0 +1,104 @@
+// Cipher feedback (CFB) mode, with a feedback size equal to the block
+// size.
+//
+// See NIST SP 800-38A, pp 11-13
+
+use std::slice;
+use super::*;
+
+/// A Stream which encrypts or decrypts using the given Block in cipher
+/// feedback mode.
+pub struct Cfb<B> {
+    b: B,
+    next: ~[u8],
+    out: ~[u8],
+    out_used: uint,
+    decrypt: bool,
+}
+
+impl<B: Block> Cfb<B> {
+    /// Returns a Stream which encrypts with cipher feedback mode. The
+    /// length of iv must be the same as the Block's block size.
+    pub fn new_encrypter(b: B, iv: &[u8]) -> Cfb<B> {
+        Cfb::new(b, iv, false)
+    }
+
+    /// Returns a Stream which decrypts with cipher feedback mode. The
+    /// length of iv must be the same as the Block's block size.
+    pub fn new_decrypter(b: B, iv: &[u8]) -> Cfb<B> {
+        Cfb::new(b, iv, true)
+    }
+
+    fn new(b: B, iv: &[u8], decrypt: bool) -> Cfb<B> {
+        let bs = b.block_size();
+        if iv.len() != bs {
+            fail!("cipher.newCFB: IV length must equal block size");
+        }
+        Cfb { b: b, next: iv.to_owned(), out: slice::from_elem(bs, 0u8),
+              out_used: bs, decrypt: decrypt }
+    }
+}
+
+impl<B: Block> Stream for Cfb<B> {
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
+        if dst.len() < src.len() {
+            fail!("crypto/cipher: output smaller than input");
+        }
+        for i in range(0, src.len()) {
+            if self.out_used == self.out.len() {
+                self.b.encrypt(self.out.as_mut_slice(), self.next.as_slice());
+                self.out_used = 0;
+            }
+            let c = src[i] ^ self.out[self.out_used];
+            // the ciphertext is fed back into the next block
+            self.next[self.out_used] = if self.decrypt { src[i] } else { c };
+            dst[i] = c;
+            self.out_used += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::*;
+    use super::super::super::aes::Aes;
+    use super::super::test::{Toy, iv, plaintext};
+    use super::super::test::{sp800_38a_key128, sp800_38a_iv, sp800_38a_plaintext};
+
+    #[test]
+    fn test_cfb_toy() {
+        let p = plaintext(50);
+        let mut c = [0u8, ..50];
+        let mut s = Cfb::new_encrypter(Toy::new(), iv().as_slice());
+        s.xor_key_stream(c.mut_slice_to(21), p.slice_to(21));
+        s.xor_key_stream(c.mut_slice_from(21), p.slice_from(21));
+        assert_eq!(c.as_slice().to_hex(), ~"f5f3e9e71d232937c54349575d5369975750b942c3c4c5c6cfd811cacb3c2dce262c36281e1cdec0260ce6585efc1e804ebb");
+
+        let mut s = Cfb::new_decrypter(Toy::new(), iv().as_slice());
+        let mut q = [0u8, ..50];
+        s.xor_key_stream(q.mut_slice_to(3), c.slice_to(3));
+        s.xor_key_stream(q.mut_slice_from(3), c.slice_from(3));
+        assert_eq!(q.as_slice(), p.as_slice());
+    }
+
+    #[test]
+    fn test_cfb_aes() {
+        let ct = "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+                  26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6";
+        let key = sp800_38a_key128.from_hex().unwrap();
+        let ivec = sp800_38a_iv.from_hex().unwrap();
+        let p = sp800_38a_plaintext.from_hex().unwrap();
+        let mut c = [0u8, ..64];
+        let mut s = Cfb::new_encrypter(Aes::new(key.as_slice()).unwrap(), ivec.as_slice());
+        s.xor_key_stream(c.as_mut_slice(), p.as_slice());
+        assert_eq!(c.as_slice().to_hex(), ct.to_owned());
+
+        let mut q = [0u8, ..64];
+        let mut s = Cfb::new_decrypter(Aes::new(key.as_slice()).unwrap(), ivec.as_slice());
+        s.xor_key_stream(q.as_mut_slice(), c.as_slice());
+        assert_eq!(q.as_slice(), p.as_slice());
+    }
+}
@@ -0,0 +1,115 @@
+// Counter (CTR) mode.
+//
+// CTR converts a block cipher into a stream cipher by repeatedly
+// encrypting an incrementing counter and xoring the resulting stream of
+// data with the input.
+//
+// See NIST SP 800-38A, pp 13-15
+
+use std::slice;
+use super::*;
+
+/// A Stream which encrypts or decrypts using the given Block in counter
+/// mode.
+pub struct Ctr<B> {
+    b: B,
+    ctr: ~[u8],
+    out: ~[u8],
+    out_used: uint,
+}
+
+impl<B: Block> Ctr<B> {
+    /// Creates a Ctr stream. The length of iv must be the same as the
+    /// Block's block size.
+    pub fn new(b: B, iv: &[u8]) -> Ctr<B> {
+        let bs = b.block_size();
+        if iv.len() != bs {
+            fail!("cipher.NewCTR: IV length must equal block size");
+        }
+        Ctr { b: b, ctr: iv.to_owned(), out: slice::from_elem(bs, 0u8), out_used: bs }
+    }
+
+    fn refill(&mut self) {
+        self.b.encrypt(self.out.as_mut_slice(), self.ctr.as_slice());
+        // increment the counter, big-endian over the whole block
+        for i in range(0, self.ctr.len()).rev() {
+            self.ctr[i] += 1;
+            if self.ctr[i] != 0 {
+                break;
+            }
+        }
+        self.out_used = 0;
+    }
+}
+
+impl<B: Block> Stream for Ctr<B> {
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
+        if dst.len() < src.len() {
+            fail!("crypto/cipher: output smaller than input");
+        }
+        for i in range(0, src.len()) {
+            if self.out_used == self.out.len() {
+                self.refill();
+            }
+            dst[i] = src[i] ^ self.out[self.out_used];
+            self.out_used += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::*;
+    use super::super::super::aes::Aes;
+    use super::super::test::{Toy, iv, plaintext};
+    use super::super::test::{sp800_38a_key128, sp800_38a_key256, sp800_38a_counter, sp800_38a_plaintext};
+
+    #[test]
+    fn test_ctr_toy() {
+        let p = plaintext(50);
+        let mut c = [0u8, ..50];
+        let mut s = Ctr::new(Toy::new(), iv().as_slice());
+        // odd-sized writes straddle block boundaries
+        s.xor_key_stream(c.mut_slice_to(7), p.slice_to(7));
+        s.xor_key_stream(c.mut_slice(7, 40), p.slice(7, 40));
+        s.xor_key_stream(c.mut_slice_from(40), p.slice_from(40));
+        assert_eq!(c.as_slice().to_hex(), ~"f5f3e9e71d232937c54349575d536997858379778d9399a755b2acc7cdc3d92715130907fd030917e5223f373d3349b7a5a3");
+
+        let mut s = Ctr::new(Toy::new(), iv().as_slice());
+        let mut q = [0u8, ..50];
+        s.xor_key_stream(q.as_mut_slice(), c.as_slice());
+        assert_eq!(q.as_slice(), p.as_slice());
+    }
+
+    #[test]
+    fn test_ctr_wrap() {
+        let mut s = Ctr::new(Toy::new(), [0xffu8, ..16]);
+        let mut ks = [0u8, ..32];
+        s.xor_key_stream(ks.as_mut_slice(), [0u8, ..32]);
+        assert_eq!(ks.as_slice().to_hex(), ~"000000000000000000000000000000f001030507090b0d0f11131517191b1d0f");
+    }
+
+    #[test]
+    fn test_ctr_aes() {
+        let tests = [
+            (sp800_38a_key128,
+             "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+              5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"),
+            (sp800_38a_key256,
+             "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+              2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6"),
+        ];
+        let nonce = sp800_38a_counter.from_hex().unwrap();
+        let p = sp800_38a_plaintext.from_hex().unwrap();
+        for &(key, ct) in tests.iter() {
+            let key = key.from_hex().unwrap();
+            let mut c = [0u8, ..64];
+            let mut s = Ctr::new(Aes::new(key.as_slice()).unwrap(), nonce.as_slice());
+            s.xor_key_stream(c.as_mut_slice(), p.as_slice());
+            assert_eq!(c.as_slice().to_hex(), ct.to_owned());
+        }
+    }
+}
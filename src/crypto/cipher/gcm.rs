@@ -0,0 +1,389 @@
+// Galois/Counter Mode (GCM), NIST SP 800-38D.
+//
+// GHASH multiplies with PCLMULQDQ when the CPU has it, and otherwise with
+// a constant-time bit-serial loop.
+
+use std::slice;
+use super::*;
+use super::super::cpu;
+use super::super::ghash::{mul_generic, mul_clmul};
+use super::super::rand;
+use super::super::subtle::constant_time_compare;
+
+static gcmBlockSize: uint = 16;
+static gcmStandardNonceSize: uint = 12;
+static gcmTagSize: uint = 16;
+static gcmMinimumTagSize: uint = 12;
+
+/// An AEAD which runs a 128-bit block cipher in Galois/Counter Mode.
+pub struct Gcm<B> {
+    cipher: B,
+    nonce_size: uint,
+    tag_size: uint,
+    // the hash key, E(K, 0^128)
+    h: [u8, ..16],
+    clmul: bool,
+}
+
+impl<B: Block> Gcm<B> {
+    /// Returns the given 128-bit block cipher wrapped in Galois Counter
+    /// Mode with the standard nonce length (12 bytes) and tag length (16
+    /// bytes).
+    pub fn new(b: B) -> Result<Gcm<B>, ~str> {
+        Gcm::new_with_sizes(b, gcmStandardNonceSize, gcmTagSize)
+    }
+
+    /// Like new, but accepts nonces of the given length, which must not be
+    /// zero. Only use this if you need compatibility with an existing
+    /// cryptosystem that uses non-standard nonce lengths.
+    pub fn new_with_nonce_size(b: B, size: uint) -> Result<Gcm<B>, ~str> {
+        Gcm::new_with_sizes(b, size, gcmTagSize)
+    }
+
+    /// Like new, but generates tags of the given length, between 12 and 16
+    /// bytes. Only use this if you need compatibility with an existing
+    /// cryptosystem that uses non-standard tag lengths.
+    pub fn new_with_tag_size(b: B, size: uint) -> Result<Gcm<B>, ~str> {
+        Gcm::new_with_sizes(b, gcmStandardNonceSize, size)
+    }
+
+    fn new_with_sizes(b: B, nonce_size: uint, tag_size: uint) -> Result<Gcm<B>, ~str> {
+        if tag_size < gcmMinimumTagSize || tag_size > gcmBlockSize {
+            return Err(~"cipher: incorrect tag size given to GCM");
+        }
+        if nonce_size == 0 {
+            return Err(~"cipher: the nonce can't have zero length");
+        }
+        if b.block_size() != gcmBlockSize {
+            return Err(~"cipher: NewGCM requires 128-bit block cipher");
+        }
+        let mut h = [0u8, ..16];
+        b.encrypt(h.as_mut_slice(), [0u8, ..16]);
+        Ok(Gcm { cipher: b, nonce_size: nonce_size, tag_size: tag_size,
+                 h: h, clmul: cpu::has_pclmulqdq() })
+    }
+
+    // y = y * H
+    fn mul(&self, y: &mut [u8]) {
+        if self.clmul {
+            mul_clmul(y, self.h.as_slice());
+        } else {
+            mul_generic(y, self.h.as_slice());
+        }
+    }
+
+    // Absorbs data into the GHASH state y, zero padding the last block.
+    fn update(&self, y: &mut [u8], data: &[u8]) {
+        for p in data.chunks(gcmBlockSize) {
+            for i in range(0, p.len()) {
+                y[i] ^= p[i];
+            }
+            self.mul(y);
+        }
+    }
+
+    // The pre-counter block J0: the nonce with a 32-bit counter of 1 for
+    // 96-bit nonces, and GHASH of the nonce otherwise.
+    fn derive_counter(&self, nonce: &[u8]) -> [u8, ..16] {
+        let mut counter = [0u8, ..16];
+        if nonce.len() == gcmStandardNonceSize {
+            counter.as_mut_slice().copy_from(nonce);
+            counter[15] = 1;
+        } else {
+            self.update(counter.as_mut_slice(), nonce);
+            let bits = (nonce.len() as u64) << 3;
+            for i in range(0, 8u) {
+                counter[8 + i] ^= (bits >> (56 - 8 * i)) as u8;
+            }
+            self.mul(counter.as_mut_slice());
+        }
+        counter
+    }
+
+    // Encrypts input with the counter, incrementing its low 32 bits per block.
+    fn counter_crypt(&self, out: &mut [u8], input: &[u8], counter: &mut [u8, ..16]) {
+        let mut mask = [0u8, ..16];
+        for (i, p) in input.chunks(gcmBlockSize).enumerate() {
+            self.cipher.encrypt(mask.as_mut_slice(), counter.as_slice());
+            inc32(counter);
+            for j in range(0, p.len()) {
+                out[i * gcmBlockSize + j] = p[j] ^ mask[j];
+            }
+        }
+    }
+
+    fn auth(&self, ciphertext: &[u8], additional_data: &[u8], tag_mask: &[u8, ..16]) -> [u8, ..16] {
+        let mut y = [0u8, ..16];
+        self.update(y.as_mut_slice(), additional_data);
+        self.update(y.as_mut_slice(), ciphertext);
+        let ad_bits = (additional_data.len() as u64) << 3;
+        let ct_bits = (ciphertext.len() as u64) << 3;
+        for i in range(0, 8u) {
+            y[i] ^= (ad_bits >> (56 - 8 * i)) as u8;
+            y[8 + i] ^= (ct_bits >> (56 - 8 * i)) as u8;
+        }
+        self.mul(y.as_mut_slice());
+        for i in range(0, 16u) {
+            y[i] ^= tag_mask[i];
+        }
+        y
+    }
+}
+
+impl<B: Block> AEAD for Gcm<B> {
+    fn nonce_size(&self) -> uint {
+        self.nonce_size
+    }
+
+    fn overhead(&self) -> uint {
+        self.tag_size
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != self.nonce_size {
+            fail!("crypto/cipher: incorrect nonce length given to GCM");
+        }
+        if plaintext.len() as u64 > ((1u64 << 32) - 2) * gcmBlockSize as u64 {
+            fail!("crypto/cipher: message too large for GCM");
+        }
+        let mut counter = self.derive_counter(nonce);
+        let mut tag_mask = [0u8, ..16];
+        self.cipher.encrypt(tag_mask.as_mut_slice(), counter.as_slice());
+        inc32(&mut counter);
+
+        let mut ct = slice::from_elem(plaintext.len(), 0u8);
+        self.counter_crypt(ct.as_mut_slice(), plaintext, &mut counter);
+        let tag = self.auth(ct.as_slice(), additional_data, &tag_mask);
+
+        let mut ret = Vec::with_capacity(dst.len() + ct.len() + self.tag_size);
+        ret.push_all(dst);
+        ret.push_all(ct.as_slice());
+        ret.push_all(tag.slice_to(self.tag_size));
+        ret.as_slice().to_owned()
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != self.nonce_size {
+            fail!("crypto/cipher: incorrect nonce length given to GCM");
+        }
+        if ciphertext.len() < self.tag_size ||
+           ciphertext.len() as u64 > ((1u64 << 32) - 2) * gcmBlockSize as u64 + self.tag_size as u64 {
+            return Err(~"cipher: message authentication failed");
+        }
+        let (ct, tag) = (ciphertext.slice_to(ciphertext.len() - self.tag_size),
+                         ciphertext.slice_from(ciphertext.len() - self.tag_size));
+
+        let mut counter = self.derive_counter(nonce);
+        let mut tag_mask = [0u8, ..16];
+        self.cipher.encrypt(tag_mask.as_mut_slice(), counter.as_slice());
+        inc32(&mut counter);
+
+        let expected = self.auth(ct, additional_data, &tag_mask);
+        if !constant_time_compare(expected.slice_to(self.tag_size), tag) {
+            return Err(~"cipher: message authentication failed");
+        }
+
+        let mut pt = slice::from_elem(ct.len(), 0u8);
+        self.counter_crypt(pt.as_mut_slice(), ct, &mut counter);
+        let mut ret = Vec::with_capacity(dst.len() + pt.len());
+        ret.push_all(dst);
+        ret.push_all(pt.as_slice());
+        Ok(ret.as_slice().to_owned())
+    }
+}
+
+/// AES-GCM with random 96-bit nonces generated by seal and prepended to
+/// the ciphertext, like Go's NewGCMWithRandomNonce. nonce_size() is zero
+/// and seal and open must be given an empty nonce.
+///
+/// With random nonces, a single key should not encrypt more than 2^32
+/// messages.
+pub struct GcmWithRandomNonce<B> {
+    gcm: Gcm<B>,
+}
+
+impl<B: Block> GcmWithRandomNonce<B> {
+    pub fn new(b: B) -> Result<GcmWithRandomNonce<B>, ~str> {
+        let gcm = try!(Gcm::new(b));
+        Ok(GcmWithRandomNonce { gcm: gcm })
+    }
+}
+
+impl<B: Block> AEAD for GcmWithRandomNonce<B> {
+    fn nonce_size(&self) -> uint {
+        0
+    }
+
+    fn overhead(&self) -> uint {
+        gcmStandardNonceSize + self.gcm.tag_size
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != 0 {
+            fail!("crypto/cipher: non-empty nonce passed to GCMWithRandomNonce");
+        }
+        let mut nonce = [0u8, ..12];
+        rand::read(nonce.as_mut_slice());
+
+        let mut ret = Vec::from_slice(dst);
+        ret.push_all(nonce.as_slice());
+        let ret = ret.as_slice().to_owned();
+        self.gcm.seal(ret.as_slice(), nonce.as_slice(), plaintext, additional_data)
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != 0 {
+            fail!("crypto/cipher: non-empty nonce passed to GCMWithRandomNonce");
+        }
+        if ciphertext.len() < gcmStandardNonceSize + self.gcm.tag_size {
+            return Err(~"cipher: message authentication failed");
+        }
+        self.gcm.open(dst, ciphertext.slice_to(gcmStandardNonceSize),
+                      ciphertext.slice_from(gcmStandardNonceSize), additional_data)
+    }
+}
+
+// Increments the rightmost 32 bits of the counter block, modulo 2^32.
+fn inc32(counter: &mut [u8, ..16]) {
+    for i in range(12, 16u).rev() {
+        counter[i] += 1;
+        if counter[i] != 0 {
+            break;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::*;
+    use super::super::super::aes::Aes;
+    use super::super::super::cpu;
+
+    // Test cases from McGrew and Viega, "The Galois/Counter Mode of
+    // Operation (GCM)", as used by NIST's GCM validation.
+    static tests: &'static [(&'static str, &'static str, &'static str, &'static str, &'static str)] = &[
+        // key, nonce, plaintext, additional data, ciphertext || tag
+        ("00000000000000000000000000000000",
+         "000000000000000000000000",
+         "",
+         "",
+         "58e2fccefa7e3061367f1d57a4e7455a"),
+        ("00000000000000000000000000000000",
+         "000000000000000000000000",
+         "00000000000000000000000000000000",
+         "",
+         "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf"),
+        ("feffe9928665731c6d6a8f9467308308",
+         "cafebabefacedbaddecaf888",
+         "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+          1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+         "",
+         "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+          21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985\
+          4d5c2af327cd64a62cf35abd2ba6fab4"),
+        ("feffe9928665731c6d6a8f9467308308",
+         "cafebabefacedbaddecaf888",
+         "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+          1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+         "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+         "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+          21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+          5bc94fbc3221a5db94fae95ae7121a47"),
+        ("feffe9928665731c6d6a8f9467308308",
+         "cafebabefacedbad",
+         "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+          1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+         "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+         "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+          73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598\
+          3612d2e79e3b0785561be14aaca2fccb"),
+        ("feffe9928665731c6d6a8f9467308308",
+         "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+          c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+         "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+          1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+         "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+         "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+          01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5\
+          619cc5aefffe0bfa462af43c1699d050"),
+        ("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+         "cafebabefacedbaddecaf888",
+         "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+          1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+         "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+         "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+          8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+          76fc6ece0f4e1768cddf8853bb2d551b"),
+    ];
+
+    fn run(clmul: bool) {
+        for &(key, nonce, pt, ad, ct) in tests.iter() {
+            let key = key.from_hex().unwrap();
+            let nonce = nonce.from_hex().unwrap();
+            let pt = pt.from_hex().unwrap();
+            let ad = ad.from_hex().unwrap();
+            let aes = Aes::new(key.as_slice()).unwrap();
+            let mut g = Gcm::new_with_nonce_size(aes, nonce.len()).unwrap();
+            g.clmul = clmul;
+            let out = g.seal([], nonce.as_slice(), pt.as_slice(), ad.as_slice());
+            assert_eq!(out.as_slice().to_hex(), ct.to_owned());
+            let back = g.open([], nonce.as_slice(), out.as_slice(), ad.as_slice()).unwrap();
+            assert_eq!(back.as_slice(), pt.as_slice());
+
+            // any change to the ciphertext, tag or additional data fails
+            let mut bad = out.clone();
+            bad[0] ^= 0x80;
+            assert!(g.open([], nonce.as_slice(), bad.as_slice(), ad.as_slice()).is_err());
+            let mut bad_ad = ad.clone();
+            bad_ad.push(0);
+            assert!(g.open([], nonce.as_slice(), out.as_slice(), bad_ad.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_gcm_generic() {
+        run(false);
+    }
+
+    #[test]
+    fn test_gcm_clmul() {
+        if cpu::has_pclmulqdq() {
+            run(true);
+        }
+    }
+
+    #[test]
+    fn test_gcm_tag_size() {
+        let key = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308".from_hex().unwrap();
+        let nonce = "cafebabefacedbaddecaf888".from_hex().unwrap();
+        let g = Gcm::new_with_tag_size(Aes::new(key.as_slice()).unwrap(), 12).unwrap();
+        assert_eq!(g.overhead(), 12);
+        let out = g.seal(bytes!("prefix"), nonce.as_slice(), [], []);
+        assert_eq!(out.slice_from(6).to_hex(), ~"fd2caa16a5832e76aa132c14");
+        assert_eq!(out.slice_to(6), bytes!("prefix"));
+        assert!(g.open([], nonce.as_slice(), out.slice_from(6), []).unwrap().is_empty());
+
+        assert!(Gcm::new_with_tag_size(Aes::new(key.as_slice()).unwrap(), 11).is_err());
+        assert!(Gcm::new_with_tag_size(Aes::new(key.as_slice()).unwrap(), 17).is_err());
+        assert!(Gcm::new_with_nonce_size(Aes::new(key.as_slice()).unwrap(), 0).is_err());
+    }
+
+    #[test]
+    fn test_gcm_random_nonce() {
+        let key = [7u8, ..32];
+        let g = GcmWithRandomNonce::new(Aes::new(key).unwrap()).unwrap();
+        assert_eq!(g.nonce_size(), 0);
+        assert_eq!(g.overhead(), 28);
+        let a = g.seal([], [], bytes!("hello"), bytes!("ad"));
+        let b = g.seal([], [], bytes!("hello"), bytes!("ad"));
+        assert_eq!(a.len(), 5 + 28);
+        assert!(a != b);
+        assert_eq!(g.open([], [], a.as_slice(), bytes!("ad")).unwrap().as_slice(), bytes!("hello"));
+        assert!(g.open([], [], a.slice_to(27), bytes!("ad")).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Reader and Writer adapters that run the data through a Stream.
+
+use std::io::IoResult;
+use std::slice;
+use super::*;
+
+/// Wraps a Stream into a Reader. It calls xor_key_stream to process each
+/// slice of data which passes through.
+pub struct StreamReader<S, R> {
+    s: S,
+    r: R,
+}
+
+impl<S: Stream, R: Reader> StreamReader<S, R> {
+    pub fn new(s: S, r: R) -> StreamReader<S, R> {
+        StreamReader { s: s, r: r }
+    }
+
+    /// Returns the wrapped Reader.
+    pub fn unwrap(self) -> R {
+        self.r
+    }
+}
+
+impl<S: Stream, R: Reader> Reader for StreamReader<S, R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        let n = try!(self.r.read(buf));
+        let src = buf.slice_to(n).to_owned();
+        self.s.xor_key_stream(buf.mut_slice_to(n), src.as_slice());
+        Ok(n)
+    }
+}
+
+/// Wraps a Stream into a Writer. It calls xor_key_stream to process each
+/// slice of data which passes through.
+pub struct StreamWriter<S, W> {
+    s: S,
+    w: W,
+}
+
+impl<S: Stream, W: Writer> StreamWriter<S, W> {
+    pub fn new(s: S, w: W) -> StreamWriter<S, W> {
+        StreamWriter { s: s, w: w }
+    }
+
+    /// Returns the wrapped Writer.
+    pub fn unwrap(self) -> W {
+        self.w
+    }
+}
+
+impl<S: Stream, W: Writer> Writer for StreamWriter<S, W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let mut c = slice::from_elem(buf.len(), 0u8);
+        self.s.xor_key_stream(c.as_mut_slice(), buf);
+        self.w.write(c.as_slice())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.w.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::{MemReader, MemWriter};
+    use super::*;
+    use super::super::ctr::Ctr;
+    use super::super::test::{Toy, iv, plaintext};
+
+    #[test]
+    fn test_stream_io() {
+        let p = plaintext(100);
+        let mut w = StreamWriter::new(Ctr::new(Toy::new(), iv().as_slice()), MemWriter::new());
+        w.write(p.slice_to(10)).unwrap();
+        w.write(p.slice_from(10)).unwrap();
+        let c = w.unwrap().unwrap();
+        assert!(c.as_slice() != p.as_slice());
+
+        let mut r = StreamReader::new(Ctr::new(Toy::new(), iv().as_slice()), MemReader::new(c));
+        let q = r.read_to_end().unwrap();
+        assert_eq!(q.as_slice(), p.as_slice());
+    }
+}
@@ -0,0 +1,136 @@
+// Standard block cipher modes that can be wrapped around low-level block
+// cipher implementations, after Go's crypto/cipher.
+
+/// A block cipher using a given key. It provides the capability to
+/// encrypt or decrypt individual blocks; the mode implementations extend
+/// that capability to streams of blocks.
+pub trait Block {
+    /// Returns the cipher's block size.
+    fn block_size(&self) -> uint;
+
+    /// Encrypts the first block in src into dst.
+    fn encrypt(&self, dst: &mut [u8], src: &[u8]);
+
+    /// Decrypts the first block in src into dst.
+    fn decrypt(&self, dst: &mut [u8], src: &[u8]);
+}
+
+/// A stream cipher.
+pub trait Stream {
+    /// XORs each byte in the given slice with a byte from the cipher's key
+    /// stream. dst must be at least as long as src.
+    ///
+    /// Multiple calls behave as if the concatenation of the src buffers
+    /// was passed in a single run.
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]);
+}
+
+/// A block cipher running in a block-based mode (CBC, ECB etc).
+pub trait BlockMode {
+    /// Returns the mode's block size.
+    fn block_size(&self) -> uint;
+
+    /// Encrypts or decrypts a number of blocks. The length of src must be
+    /// a multiple of the block size and dst must be at least as long.
+    fn crypt_blocks(&mut self, dst: &mut [u8], src: &[u8]);
+}
+
+/// A block cipher mode that provides authenticated encryption with
+/// associated data.
+pub trait AEAD {
+    /// The size of the nonce that must be passed to seal and open.
+    fn nonce_size(&self) -> uint;
+
+    /// The maximum difference between the lengths of a plaintext and its
+    /// ciphertext.
+    fn overhead(&self) -> uint;
+
+    /// Encrypts and authenticates plaintext, authenticates the additional
+    /// data and appends the result to dst.
+    ///
+    /// The nonce must be nonce_size() bytes long and unique for all time,
+    /// for a given key.
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8];
+
+    /// Decrypts and authenticates ciphertext, authenticates the additional
+    /// data and, if successful, appends the resulting plaintext to dst.
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str>;
+}
+
+pub mod cbc;
+pub mod ctr;
+pub mod cfb;
+pub mod ofb;
+pub mod io;
+pub mod gcm;
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A trivially weak 16-byte block cipher, enough to check that the
+    // modes chain blocks correctly. The vectors in the mode tests come
+    // from a straightforward reference written against the same cipher.
+    pub struct Toy {
+        key: [u8, ..16],
+    }
+
+    impl Toy {
+        pub fn new() -> Toy {
+            let mut key = [0u8, ..16];
+            for i in range(0, 16u) {
+                key[i] = i as u8;
+            }
+            Toy { key: key }
+        }
+    }
+
+    impl Block for Toy {
+        fn block_size(&self) -> uint {
+            16
+        }
+
+        fn encrypt(&self, dst: &mut [u8], src: &[u8]) {
+            for i in range(0, 16u) {
+                dst[i] = (src[(i + 5) % 16] ^ self.key[i]) + self.key[(i + 1) % 16];
+            }
+        }
+
+        fn decrypt(&self, dst: &mut [u8], src: &[u8]) {
+            for i in range(0, 16u) {
+                dst[(i + 5) % 16] = (src[i] - self.key[(i + 1) % 16]) ^ self.key[i];
+            }
+        }
+    }
+
+    pub fn iv() -> ~[u8] {
+        range(0xf0, 0x100u).map(|i| i as u8).collect()
+    }
+
+    pub fn plaintext(n: uint) -> ~[u8] {
+        range(0, n).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    // NIST SP 800-38A appendix F
+    pub static sp800_38a_key128: &'static str = "2b7e151628aed2a6abf7158809cf4f3c";
+    pub static sp800_38a_key256: &'static str =
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+    pub static sp800_38a_iv: &'static str = "000102030405060708090a0b0c0d0e0f";
+    pub static sp800_38a_counter: &'static str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+    pub static sp800_38a_plaintext: &'static str =
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_toy() {
+        let c = Toy::new();
+        let p = plaintext(16);
+        let mut x = [0u8, ..16];
+        let mut y = [0u8, ..16];
+        c.encrypt(x.as_mut_slice(), p.as_slice());
+        c.decrypt(y.as_mut_slice(), x.as_slice());
+        assert_eq!(y.as_slice(), p.as_slice());
+    }
+}
@@ -0,0 +1,83 @@
+// Output feedback (OFB) mode.
+//
+// See NIST SP 800-38A, pp 13-15
+
+use std::slice;
+use super::*;
+
+/// A Stream which encrypts or decrypts using the given Block in output
+/// feedback mode.
+pub struct Ofb<B> {
+    b: B,
+    cipher: ~[u8],
+    out: ~[u8],
+    out_used: uint,
+}
+
+impl<B: Block> Ofb<B> {
+    /// Creates an Ofb stream. The length of iv must be the same as the
+    /// Block's block size.
+    pub fn new(b: B, iv: &[u8]) -> Ofb<B> {
+        let bs = b.block_size();
+        if iv.len() != bs {
+            fail!("cipher.NewOFB: IV length must equal block size");
+        }
+        Ofb { b: b, cipher: iv.to_owned(), out: slice::from_elem(bs, 0u8), out_used: bs }
+    }
+}
+
+impl<B: Block> Stream for Ofb<B> {
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
+        if dst.len() < src.len() {
+            fail!("crypto/cipher: output smaller than input");
+        }
+        for i in range(0, src.len()) {
+            if self.out_used == self.out.len() {
+                self.b.encrypt(self.out.as_mut_slice(), self.cipher.as_slice());
+                self.cipher.as_mut_slice().copy_from(self.out.as_slice());
+                self.out_used = 0;
+            }
+            dst[i] = src[i] ^ self.out[self.out_used];
+            self.out_used += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::*;
+    use super::super::super::aes::Aes;
+    use super::super::test::{Toy, iv, plaintext};
+    use super::super::test::{sp800_38a_key128, sp800_38a_iv, sp800_38a_plaintext};
+
+    #[test]
+    fn test_ofb_toy() {
+        let p = plaintext(50);
+        let mut c = [0u8, ..50];
+        let mut s = Ofb::new(Toy::new(), iv().as_slice());
+        s.xor_key_stream(c.mut_slice_to(33), p.slice_to(33));
+        s.xor_key_stream(c.mut_slice_from(33), p.slice_from(33));
+        assert_eq!(c.as_slice().to_hex(), ~"f5f3e9e71d232937c54349575d536997757d8589859d95b1a5bd45c9c5cdd5d1efe1ebe9eff91b190f313b292f295b495343");
+
+        let mut s = Ofb::new(Toy::new(), iv().as_slice());
+        let mut q = [0u8, ..50];
+        s.xor_key_stream(q.as_mut_slice(), c.as_slice());
+        assert_eq!(q.as_slice(), p.as_slice());
+    }
+
+    #[test]
+    fn test_ofb_aes() {
+        let ct = "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+                  9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e";
+        let key = sp800_38a_key128.from_hex().unwrap();
+        let ivec = sp800_38a_iv.from_hex().unwrap();
+        let p = sp800_38a_plaintext.from_hex().unwrap();
+        let mut c = [0u8, ..64];
+        let mut s = Ofb::new(Aes::new(key.as_slice()).unwrap(), ivec.as_slice());
+        s.xor_key_stream(c.as_mut_slice(), p.as_slice());
+        assert_eq!(c.as_slice().to_hex(), ct.to_owned());
+    }
+}
@@ -0,0 +1,176 @@
+// The CMAC block cipher mode for authentication, NIST SP 800-38B. With
+// AES it is the AES-CMAC of RFC 4493.
+//
+// Receivers should be careful to use hmac::equal, or subtle, to compare
+// MACs in order to avoid timing side-channels.
+
+use std::io::IoResult;
+use std::slice;
+use super::cipher::Block;
+use super::super::hash::Hash;
+
+/// A CMAC computed with the given block cipher, which must have a 64 or
+/// 128-bit block. The MAC is the size of one block.
+#[deriving(Clone)]
+pub struct Cmac<B> {
+    cipher: B,
+    k1: ~[u8],
+    k2: ~[u8],
+    // the chaining value, and the last block of input, which can only
+    // be processed once it is known whether more input follows
+    x: ~[u8],
+    buf: ~[u8],
+    buf_len: uint,
+}
+
+// Multiplies by x in GF(2^n) for n the bit length of b, with the constant
+// R_b of SP 800-38B section 5.3.
+fn dbl(b: &mut [u8]) {
+    let rb = if b.len() == 16 { 0x87u8 } else { 0x1bu8 };
+    let msb = b[0] >> 7;
+    let n = b.len();
+    for i in range(0, n - 1) {
+        b[i] = (b[i] << 1) | (b[i + 1] >> 7);
+    }
+    b[n - 1] = (b[n - 1] << 1) ^ (rb & (0u8 - msb));
+}
+
+impl<B: Block> Cmac<B> {
+    /// Returns a new CMAC keyed with the given block cipher.
+    pub fn new(b: B) -> Result<Cmac<B>, ~str> {
+        let bs = b.block_size();
+        if bs != 8 && bs != 16 {
+            return Err(~"cmac: block size must be 64 or 128 bits");
+        }
+        let mut k1 = slice::from_elem(bs, 0u8);
+        b.encrypt(k1.as_mut_slice(), slice::from_elem(bs, 0u8).as_slice());
+        dbl(k1.as_mut_slice());
+        let mut k2 = k1.clone();
+        dbl(k2.as_mut_slice());
+        Ok(Cmac { cipher: b, k1: k1, k2: k2, x: slice::from_elem(bs, 0u8),
+                  buf: slice::from_elem(bs, 0u8), buf_len: 0 })
+    }
+
+    // x = E(K, x ^ buf)
+    fn block(&mut self) {
+        let bs = self.x.len();
+        for i in range(0, bs) {
+            self.x[i] ^= self.buf[i];
+        }
+        let y = self.x.clone();
+        self.cipher.encrypt(self.x.as_mut_slice(), y.as_slice());
+        self.buf_len = 0;
+    }
+}
+
+impl<B: Block> Writer for Cmac<B> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let bs = self.x.len();
+        for &b in buf.iter() {
+            if self.buf_len == bs {
+                self.block();
+            }
+            self.buf[self.buf_len] = b;
+            self.buf_len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<B: Block> Hash for Cmac<B> {
+    fn reset(&mut self) {
+        for i in range(0, self.x.len()) {
+            self.x[i] = 0;
+        }
+        self.buf_len = 0;
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let bs = self.x.len();
+        let mut last = slice::from_elem(bs, 0u8);
+        last.mut_slice_to(self.buf_len).copy_from(self.buf.slice_to(self.buf_len));
+        // a complete final block is masked with K1, a partial (or empty)
+        // one is padded with 10* and masked with K2
+        let k = if self.buf_len == bs {
+            self.k1.as_slice()
+        } else {
+            last[self.buf_len] = 0x80;
+            self.k2.as_slice()
+        };
+        for i in range(0, bs) {
+            last[i] ^= self.x[i] ^ k[i];
+        }
+        let mut out = slice::from_elem(bs, 0u8);
+        self.cipher.encrypt(out.as_mut_slice(), last.as_slice());
+        out
+    }
+
+    fn size(&self) -> uint {
+        self.x.len()
+    }
+
+    fn block_size(&self) -> uint {
+        self.x.len()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::FromHex;
+    use super::*;
+    use super::super::aes::Aes;
+    use super::super::des::TripleDes;
+    use super::super::super::hash::Hash;
+
+    static message: &'static str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                    30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_aes_cmac() {
+        // RFC 4493 section 4
+        let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap();
+        let msg = message.from_hex().unwrap();
+        let tests = [
+            (0u, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ];
+        let mut h = Cmac::new(Aes::new(key.as_slice()).unwrap()).unwrap();
+        assert_eq!(h.size(), 16);
+        for &(n, expected) in tests.iter() {
+            h.reset();
+            h.write(msg.slice_to(n)).unwrap();
+            assert_eq!(h.hexdigest(), expected.to_owned());
+
+            // byte at a time, with digest in the middle
+            h.reset();
+            for (i, b) in msg.slice_to(n).iter().enumerate() {
+                h.write([*b]).unwrap();
+                if i == 20 {
+                    h.digest();
+                }
+            }
+            assert_eq!(h.hexdigest(), expected.to_owned());
+        }
+    }
+
+    #[test]
+    fn test_tdes_cmac() {
+        // NIST SP 800-38B appendix D.2, three-key TDEA
+        let key = "8aa83bf8cbda10620bc1bf19fbb6cd58bc313d4a371ca8b5".from_hex().unwrap();
+        let msg = message.from_hex().unwrap();
+        let tests = [
+            (0u, "b7a688e122ffaf95"),
+            (16, "286d394673448197"),
+            (20, "743ddbe0ce2dc2ed"),
+            (32, "33e6b1092400eae5"),
+        ];
+        for &(n, expected) in tests.iter() {
+            let mut h = Cmac::new(TripleDes::new(key.as_slice()).unwrap()).unwrap();
+            h.write(msg.slice_to(n)).unwrap();
+            assert_eq!(h.hexdigest(), expected.to_owned());
+        }
+    }
+}
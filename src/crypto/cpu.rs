@@ -0,0 +1,159 @@
+// CPU feature detection for the assembly fast paths, after Go's
+// internal/cpu. x86-64 is detected with CPUID, and AArch64 Linux from the
+// hardware capabilities the kernel passes in the auxiliary vector;
+// everywhere else the features report false and the portable code is used.
+
+#[cfg(target_arch = "x86_64")]
+fn cpuid(leaf: u32, sub: u32) -> (u32, u32, u32, u32) {
+    let (eax, ebx, ecx, edx): (u32, u32, u32, u32);
+    unsafe {
+        asm!("cpuid"
+             : "={eax}"(eax), "={ebx}"(ebx), "={ecx}"(ecx), "={edx}"(edx)
+             : "{eax}"(leaf), "{ecx}"(sub)
+             :
+             : "volatile");
+    }
+    (eax, ebx, ecx, edx)
+}
+
+// The XCR0 register: which register states the OS saves on a context
+// switch. Only readable when CPUID reports OSXSAVE.
+#[cfg(target_arch = "x86_64")]
+fn xgetbv() -> u64 {
+    let (eax, edx): (u32, u32);
+    unsafe {
+        asm!("xgetbv"
+             : "={eax}"(eax), "={edx}"(edx)
+             : "{ecx}"(0u32)
+             :
+             : "volatile");
+    }
+    (edx as u64 << 32) | eax as u64
+}
+
+// Reports whether the OS supports XSAVE and saves all the register states
+// in mask.
+#[cfg(target_arch = "x86_64")]
+fn os_saves(mask: u64) -> bool {
+    let (_, _, ecx, _) = cpuid(1, 0);
+    ecx & (1 << 27) != 0 && xgetbv() & mask == mask
+}
+
+/// Reports whether the CPU has the AES-NI instructions.
+#[cfg(target_arch = "x86_64")]
+pub fn has_aes() -> bool {
+    let (_, _, ecx, _) = cpuid(1, 0);
+    ecx & (1 << 25) != 0
+}
+
+/// Reports whether the CPU has PCLMULQDQ (and SSSE3, which the GHASH code
+/// also uses).
+#[cfg(target_arch = "x86_64")]
+pub fn has_pclmulqdq() -> bool {
+    let (_, _, ecx, _) = cpuid(1, 0);
+    ecx & (1 << 1) != 0 && ecx & (1 << 9) != 0
+}
+
+/// Reports whether the CPU has the SHA extensions (and SSSE3 and SSE4.1,
+/// which the SHA-1 and SHA-256 code also uses).
+#[cfg(target_arch = "x86_64")]
+pub fn has_sha() -> bool {
+    let (max, _, _, _) = cpuid(0, 0);
+    if max < 7 {
+        return false;
+    }
+    let (_, _, ecx1, _) = cpuid(1, 0);
+    let (_, ebx7, _, _) = cpuid(7, 0);
+    ebx7 & (1 << 29) != 0 && ecx1 & (1 << 9) != 0 && ecx1 & (1 << 19) != 0
+}
+
+/// Reports whether the CPU has AVX2 and the OS saves the YMM registers.
+#[cfg(target_arch = "x86_64")]
+pub fn has_avx2() -> bool {
+    let (max, _, _, _) = cpuid(0, 0);
+    if max < 7 || !os_saves(0x6) {
+        return false;
+    }
+    let (_, ebx, _, _) = cpuid(7, 0);
+    ebx & (1 << 5) != 0
+}
+
+/// Reports whether the CPU has AVX-512F and the OS saves the ZMM and mask
+/// registers.
+#[cfg(target_arch = "x86_64")]
+pub fn has_avx512f() -> bool {
+    let (max, _, _, _) = cpuid(0, 0);
+    if max < 7 || !os_saves(0xe6) {
+        return false;
+    }
+    let (_, ebx, _, _) = cpuid(7, 0);
+    ebx & (1 << 16) != 0
+}
+
+// The AArch64 hardware capabilities, AT_HWCAP in the auxiliary vector.
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+fn hwcap() -> u64 {
+    extern {
+        fn getauxval(typ: ::std::libc::c_ulong) -> ::std::libc::c_ulong;
+    }
+    static AT_HWCAP: ::std::libc::c_ulong = 16;
+    unsafe { getauxval(AT_HWCAP) as u64 }
+}
+
+#[cfg(not(target_os = "linux"), target_arch = "aarch64")]
+fn hwcap() -> u64 {
+    0
+}
+
+/// Reports whether the CPU has the ARMv8 AES instructions.
+#[cfg(target_arch = "aarch64")]
+pub fn has_aes() -> bool {
+    hwcap() & (1 << 3) != 0
+}
+
+/// Reports whether the CPU has the ARMv8 SHA1 instructions.
+#[cfg(target_arch = "aarch64")]
+pub fn has_sha1() -> bool {
+    hwcap() & (1 << 5) != 0
+}
+
+/// Reports whether the CPU has the ARMv8 SHA256 instructions.
+#[cfg(target_arch = "aarch64")]
+pub fn has_sha2() -> bool {
+    hwcap() & (1 << 6) != 0
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+pub fn has_sha1() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+pub fn has_sha2() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"), not(target_arch = "aarch64"))]
+pub fn has_aes() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn has_pclmulqdq() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn has_sha() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn has_avx2() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn has_avx512f() -> bool {
+    false
+}
@@ -0,0 +1,363 @@
+// The Data Encryption Standard (DES) and the Triple Data Encryption
+// Algorithm (TDEA), as defined in FIPS 46-3 and NIST SP 800-67, after
+// Go's crypto/des.
+//
+// DES is cryptographically broken and should not be used for secure
+// applications. Its 56-bit key can be found by exhaustive search, and
+// both DES and Triple-DES have a 64-bit block, which is too small for
+// large amounts of data under one key. They are here only to read data
+// produced by legacy systems.
+//
+// This implementation follows the standard bit by bit and makes no
+// attempt to be fast or to run in constant time.
+
+use super::cipher::Block;
+
+/// The DES block size in bytes.
+pub static BlockSize: uint = 8;
+
+// Permutation tables from FIPS 46-3. Entries are 1-based bit positions,
+// counted from the most significant bit of the input.
+
+static initialPermutation: [u8, ..64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4,
+    62, 54, 46, 38, 30, 22, 14, 6, 64, 56, 48, 40, 32, 24, 16, 8,
+    57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3,
+    61, 53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+static finalPermutation: [u8, ..64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31,
+    38, 6, 46, 14, 54, 22, 62, 30, 37, 5, 45, 13, 53, 21, 61, 29,
+    36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+static expansionFunction: [u8, ..48] = [
+    32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9,
+    8, 9, 10, 11, 12, 13, 12, 13, 14, 15, 16, 17,
+    16, 17, 18, 19, 20, 21, 20, 21, 22, 23, 24, 25,
+    24, 25, 26, 27, 28, 29, 28, 29, 30, 31, 32, 1,
+];
+
+static permutationFunction: [u8, ..32] = [
+    16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10,
+    2, 8, 24, 14, 32, 27, 3, 9, 19, 13, 30, 6, 22, 11, 4, 25,
+];
+
+static permutedChoice1: [u8, ..56] = [
+    57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18,
+    10, 2, 59, 51, 43, 35, 27, 19, 11, 3, 60, 52, 44, 36,
+    63, 55, 47, 39, 31, 23, 15, 7, 62, 54, 46, 38, 30, 22,
+    14, 6, 61, 53, 45, 37, 29, 21, 13, 5, 28, 20, 12, 4,
+];
+
+static permutedChoice2: [u8, ..48] = [
+    14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10,
+    23, 19, 12, 4, 26, 8, 16, 7, 27, 20, 13, 2,
+    41, 52, 31, 37, 47, 55, 30, 40, 51, 45, 33, 48,
+    44, 49, 39, 56, 34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+// left rotations of the key halves before each round
+static ksRotations: [uint, ..16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+// the eight S-boxes, indexed by box, row and column
+static sBoxes: [[[u8, ..16], ..4], ..8] = [
+    [
+        [14,  4, 13,  1,  2, 15, 11,  8,  3, 10,  6, 12,  5,  9,  0,  7],
+        [ 0, 15,  7,  4, 14,  2, 13,  1, 10,  6, 12, 11,  9,  5,  3,  8],
+        [ 4,  1, 14,  8, 13,  6,  2, 11, 15, 12,  9,  7,  3, 10,  5,  0],
+        [15, 12,  8,  2,  4,  9,  1,  7,  5, 11,  3, 14, 10,  0,  6, 13],
+    ],
+    [
+        [15,  1,  8, 14,  6, 11,  3,  4,  9,  7,  2, 13, 12,  0,  5, 10],
+        [ 3, 13,  4,  7, 15,  2,  8, 14, 12,  0,  1, 10,  6,  9, 11,  5],
+        [ 0, 14,  7, 11, 10,  4, 13,  1,  5,  8, 12,  6,  9,  3,  2, 15],
+        [13,  8, 10,  1,  3, 15,  4,  2, 11,  6,  7, 12,  0,  5, 14,  9],
+    ],
+    [
+        [10,  0,  9, 14,  6,  3, 15,  5,  1, 13, 12,  7, 11,  4,  2,  8],
+        [13,  7,  0,  9,  3,  4,  6, 10,  2,  8,  5, 14, 12, 11, 15,  1],
+        [13,  6,  4,  9,  8, 15,  3,  0, 11,  1,  2, 12,  5, 10, 14,  7],
+        [ 1, 10, 13,  0,  6,  9,  8,  7,  4, 15, 14,  3, 11,  5,  2, 12],
+    ],
+    [
+        [ 7, 13, 14,  3,  0,  6,  9, 10,  1,  2,  8,  5, 11, 12,  4, 15],
+        [13,  8, 11,  5,  6, 15,  0,  3,  4,  7,  2, 12,  1, 10, 14,  9],
+        [10,  6,  9,  0, 12, 11,  7, 13, 15,  1,  3, 14,  5,  2,  8,  4],
+        [ 3, 15,  0,  6, 10,  1, 13,  8,  9,  4,  5, 11, 12,  7,  2, 14],
+    ],
+    [
+        [ 2, 12,  4,  1,  7, 10, 11,  6,  8,  5,  3, 15, 13,  0, 14,  9],
+        [14, 11,  2, 12,  4,  7, 13,  1,  5,  0, 15, 10,  3,  9,  8,  6],
+        [ 4,  2,  1, 11, 10, 13,  7,  8, 15,  9, 12,  5,  6,  3,  0, 14],
+        [11,  8, 12,  7,  1, 14,  2, 13,  6, 15,  0,  9, 10,  4,  5,  3],
+    ],
+    [
+        [12,  1, 10, 15,  9,  2,  6,  8,  0, 13,  3,  4, 14,  7,  5, 11],
+        [10, 15,  4,  2,  7, 12,  9,  5,  6,  1, 13, 14,  0, 11,  3,  8],
+        [ 9, 14, 15,  5,  2,  8, 12,  3,  7,  0,  4, 10,  1, 13, 11,  6],
+        [ 4,  3,  2, 12,  9,  5, 15, 10, 11, 14,  1,  7,  6,  0,  8, 13],
+    ],
+    [
+        [ 4, 11,  2, 14, 15,  0,  8, 13,  3, 12,  9,  7,  5, 10,  6,  1],
+        [13,  0, 11,  7,  4,  9,  1, 10, 14,  3,  5, 12,  2, 15,  8,  6],
+        [ 1,  4, 11, 13, 12,  3,  7, 14, 10, 15,  6,  8,  0,  5,  9,  2],
+        [ 6, 11, 13,  8,  1,  4, 10,  7,  9,  5,  0, 15, 14,  2,  3, 12],
+    ],
+    [
+        [13,  2,  8,  4,  6, 15, 11,  1, 10,  9,  3, 14,  5,  0, 12,  7],
+        [ 1, 15, 13,  8, 10,  3,  7,  4, 12,  5,  6, 11,  0, 14,  9,  2],
+        [ 7, 11,  4,  1,  9, 12, 14,  2,  0,  6, 10, 13, 15,  3,  5,  8],
+        [ 2,  1, 14,  7,  4, 10,  8, 13, 15, 12,  9,  0,  3,  5,  6, 11],
+    ],
+];
+
+// Builds a new value from the bits of src, a value of the given width,
+// in the order listed by table.
+fn permute(src: u64, width: uint, table: &[u8]) -> u64 {
+    let mut out = 0u64;
+    for &p in table.iter() {
+        out = (out << 1) | ((src >> (width - p as uint)) & 1);
+    }
+    out
+}
+
+fn load64(b: &[u8]) -> u64 {
+    let mut v = 0u64;
+    for i in range(0, 8u) {
+        v = (v << 8) | b[i] as u64;
+    }
+    v
+}
+
+fn store64(b: &mut [u8], v: u64) {
+    for i in range(0, 8u) {
+        b[i] = (v >> (56 - 8 * i)) as u8;
+    }
+}
+
+// Creates the 16 48-bit round keys. The parity bits of the key are
+// ignored.
+fn key_schedule(key: &[u8]) -> [u64, ..16] {
+    let k = permute(load64(key), 64, permutedChoice1.as_slice());
+    let (mut c, mut d) = ((k >> 28) as u32, (k & 0xfffffff) as u32);
+    let mut subkeys = [0u64, ..16];
+    for i in range(0, 16u) {
+        let s = ksRotations[i];
+        c = ((c << s) | (c >> (28 - s))) & 0xfffffff;
+        d = ((d << s) | (d >> (28 - s))) & 0xfffffff;
+        subkeys[i] = permute((c as u64 << 28) | d as u64, 56, permutedChoice2.as_slice());
+    }
+    subkeys
+}
+
+// The cipher function f(R, K).
+fn feistel(r: u32, k: u64) -> u32 {
+    let e = permute(r as u64, 32, expansionFunction.as_slice()) ^ k;
+    let mut out = 0u64;
+    for i in range(0, 8u) {
+        let six = ((e >> (42 - 6 * i)) & 0x3f) as uint;
+        let row = ((six >> 4) & 2) | (six & 1);
+        let col = (six >> 1) & 0xf;
+        out = (out << 4) | sBoxes[i][row][col] as u64;
+    }
+    permute(out, 32, permutationFunction.as_slice()) as u32
+}
+
+fn crypt_block(subkeys: &[u64, ..16], dst: &mut [u8], src: &[u8], decrypt: bool) {
+    let b = permute(load64(src), 64, initialPermutation.as_slice());
+    let (mut l, mut r) = ((b >> 32) as u32, b as u32);
+    for i in range(0, 16u) {
+        let k = if decrypt { subkeys[15 - i] } else { subkeys[i] };
+        let t = l ^ feistel(r, k);
+        l = r;
+        r = t;
+    }
+    // the halves are swapped after the last round
+    let pre = (r as u64 << 32) | l as u64;
+    store64(dst, permute(pre, 64, finalPermutation.as_slice()));
+}
+
+fn check_sizes(dst: &[u8], src: &[u8]) {
+    if src.len() < BlockSize {
+        fail!("crypto/des: input not full block");
+    }
+    if dst.len() < BlockSize {
+        fail!("crypto/des: output not full block");
+    }
+}
+
+/// A DES block cipher with a single 8-byte key.
+#[deriving(Clone)]
+pub struct Des {
+    subkeys: [u64, ..16],
+}
+
+impl Des {
+    /// Creates a DES cipher from an 8-byte key.
+    ///
+    /// DES is broken; use it only to interoperate with legacy systems.
+    #[deprecated = "DES is insecure; use it only for legacy interoperability"]
+    pub fn new(key: &[u8]) -> Result<Des, ~str> {
+        if key.len() != 8 {
+            return Err(format!("crypto/des: invalid key size {}", key.len()));
+        }
+        Ok(Des { subkeys: key_schedule(key) })
+    }
+}
+
+impl Block for Des {
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+
+    fn encrypt(&self, dst: &mut [u8], src: &[u8]) {
+        check_sizes(dst, src);
+        crypt_block(&self.subkeys, dst, src, false);
+    }
+
+    fn decrypt(&self, dst: &mut [u8], src: &[u8]) {
+        check_sizes(dst, src);
+        crypt_block(&self.subkeys, dst, src, true);
+    }
+}
+
+/// Triple-DES in encrypt-decrypt-encrypt (EDE) form.
+#[deriving(Clone)]
+pub struct TripleDes {
+    cipher1: Des,
+    cipher2: Des,
+    cipher3: Des,
+}
+
+impl TripleDes {
+    /// Creates a Triple-DES cipher. A 24-byte key holds three independent
+    /// DES keys (keying option 1, EDE3); a 16-byte key holds two, with the
+    /// first reused as the third (keying option 2, EDE2).
+    ///
+    /// Triple-DES is deprecated by NIST; use it only to interoperate with
+    /// legacy systems.
+    #[deprecated = "Triple-DES is insecure; use it only for legacy interoperability"]
+    pub fn new(key: &[u8]) -> Result<TripleDes, ~str> {
+        let (k1, k2, k3) = match key.len() {
+            24 => (key.slice(0, 8), key.slice(8, 16), key.slice(16, 24)),
+            16 => (key.slice(0, 8), key.slice(8, 16), key.slice(0, 8)),
+            n => return Err(format!("crypto/des: invalid key size {}", n)),
+        };
+        Ok(TripleDes {
+            cipher1: Des { subkeys: key_schedule(k1) },
+            cipher2: Des { subkeys: key_schedule(k2) },
+            cipher3: Des { subkeys: key_schedule(k3) },
+        })
+    }
+}
+
+impl Block for TripleDes {
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+
+    fn encrypt(&self, dst: &mut [u8], src: &[u8]) {
+        check_sizes(dst, src);
+        let mut t = [0u8, ..8];
+        let mut u = [0u8, ..8];
+        crypt_block(&self.cipher1.subkeys, t.as_mut_slice(), src, false);
+        crypt_block(&self.cipher2.subkeys, u.as_mut_slice(), t.as_slice(), true);
+        crypt_block(&self.cipher3.subkeys, dst, u.as_slice(), false);
+    }
+
+    fn decrypt(&self, dst: &mut [u8], src: &[u8]) {
+        check_sizes(dst, src);
+        let mut t = [0u8, ..8];
+        let mut u = [0u8, ..8];
+        crypt_block(&self.cipher3.subkeys, t.as_mut_slice(), src, true);
+        crypt_block(&self.cipher2.subkeys, u.as_mut_slice(), t.as_slice(), false);
+        crypt_block(&self.cipher1.subkeys, dst, u.as_slice(), true);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::cipher::{Block, BlockMode};
+    use super::super::cipher::cbc::{CbcEncrypter, CbcDecrypter};
+
+    static fips81Plaintext: &'static str = "Now is the time for all ";
+
+    fn ecb<B: Block>(c: &B, src: &[u8], decrypt: bool) -> ~[u8] {
+        let mut out = src.to_owned();
+        for (d, s) in out.mut_chunks(BlockSize).zip(src.chunks(BlockSize)) {
+            if decrypt {
+                c.decrypt(d, s);
+            } else {
+                c.encrypt(d, s);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_des_ecb() {
+        // FIPS 81 appendix B, table B1
+        let c = Des::new("0123456789abcdef".from_hex().unwrap().as_slice()).unwrap();
+        let pt = fips81Plaintext.as_bytes();
+        let ct = ecb(&c, pt, false);
+        assert_eq!(ct.as_slice().to_hex(), ~"3fa40e8a984d48156a271787ab8883f9893d51ec4b563b53");
+        assert_eq!(ecb(&c, ct.as_slice(), true).as_slice(), pt);
+    }
+
+    #[test]
+    fn test_des_cbc() {
+        // FIPS 81 appendix C, table C1
+        let key = "0123456789abcdef".from_hex().unwrap();
+        let ivec = "1234567890abcdef".from_hex().unwrap();
+        let pt = fips81Plaintext.as_bytes();
+        let mut ct = [0u8, ..24];
+        let mut m = CbcEncrypter::new(Des::new(key.as_slice()).unwrap(), ivec.as_slice());
+        m.crypt_blocks(ct.as_mut_slice(), pt);
+        assert_eq!(ct.as_slice().to_hex(), ~"e5c7cdde872bf27c43e934008c389c0f683788499a7c05f6");
+
+        let mut back = [0u8, ..24];
+        let mut m = CbcDecrypter::new(Des::new(key.as_slice()).unwrap(), ivec.as_slice());
+        m.crypt_blocks(back.as_mut_slice(), ct.as_slice());
+        assert_eq!(back.as_slice(), pt);
+    }
+
+    #[test]
+    fn test_triple_des() {
+        // NIST SP 800-67 example, and the same with keying option 2
+        let key = "0123456789abcdef23456789abcdef01456789abcdef0123".from_hex().unwrap();
+        let pt = bytes!("The qufck brown fox jump");
+        let tests = [
+            (key.as_slice(), "a826fd8ce53b855fcce21c8112256fe668d5c05dd9b6b900"),
+            (key.slice_to(16), "c44862f70cf2fbdc9077d0909fa91b884cabd61fc58e0cbb"),
+        ];
+        for &(k, expected) in tests.iter() {
+            let c = TripleDes::new(k).unwrap();
+            let ct = ecb(&c, pt, false);
+            assert_eq!(ct.as_slice().to_hex(), expected.to_owned());
+            assert_eq!(ecb(&c, ct.as_slice(), true).as_slice(), pt);
+        }
+
+        // with three equal keys Triple-DES is single DES
+        let k = "0123456789abcdef".from_hex().unwrap();
+        let mut k3 = Vec::new();
+        for _ in range(0, 3u) {
+            k3.push_all(k.as_slice());
+        }
+        let c = TripleDes::new(k3.as_slice()).unwrap();
+        let d = Des::new(k.as_slice()).unwrap();
+        assert_eq!(ecb(&c, fips81Plaintext.as_bytes(), false), ecb(&d, fips81Plaintext.as_bytes(), false));
+    }
+
+    #[test]
+    fn test_key_size() {
+        assert_eq!(Des::new([0u8, ..7]).err(), Some(~"crypto/des: invalid key size 7"));
+        assert!(TripleDes::new([0u8, ..8]).is_err());
+        assert!(TripleDes::new([0u8, ..32]).is_err());
+    }
+}
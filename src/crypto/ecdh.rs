@@ -0,0 +1,415 @@
+// Elliptic Curve Diffie-Hellman over the NIST curves (SEC 1, Version
+// 2.0, Section 3.3.1) and X25519 (RFC 7748), after Go's crypto/ecdh.
+//
+// Keys are kept in their byte encodings and validated when they are
+// created, so that ecdh only fails for the X25519 inputs that RFC 7748
+// allows but that would produce an all-zero shared secret.
+
+use std::any::Any;
+use std::fmt;
+use std::io::IoResult;
+use std::slice;
+use super::elliptic;
+use super::elliptic::Point;
+use super::edwards25519::field::Element;
+use super::subtle::constant_time_compare;
+
+/// A curve for ECDH key agreement.
+#[deriving(Eq, Clone)]
+pub enum Curve {
+    /// X25519, the Diffie-Hellman function over Curve25519 of RFC 7748.
+    X25519,
+    /// ECDH over NIST P-256, also known as secp256r1 or prime256v1.
+    P256,
+    /// ECDH over NIST P-384, also known as secp384r1.
+    P384,
+    /// ECDH over NIST P-521, also known as secp521r1.
+    P521,
+}
+
+// The size of X25519 scalars and points.
+static X25519Size: uint = 32;
+
+// The canonical Curve25519 generator, u = 9.
+static X25519Basepoint: [u8, ..32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+impl Curve {
+    // Returns the underlying NIST curve, or None for X25519.
+    fn nist(&self) -> Option<&'static elliptic::Curve> {
+        match *self {
+            X25519 => None,
+            P256 => Some(elliptic::p256()),
+            P384 => Some(elliptic::p384()),
+            P521 => Some(elliptic::p521()),
+        }
+    }
+
+    /// Generates a random private key, reading randomness from rand.
+    pub fn generate_key<R: Reader>(&self, rand: &mut R) -> IoResult<PrivateKey> {
+        match self.nist() {
+            Some(c) => {
+                let (k, _, _) = try!(elliptic::generate_key(c, rand));
+                Ok(self.new_private_key(k).unwrap())
+            }
+            None => {
+                let mut k = [0u8, ..X25519Size];
+                try!(rand.fill(k));
+                Ok(self.new_private_key(k).unwrap())
+            }
+        }
+    }
+
+    /// Checks that key is valid and returns a PrivateKey.
+    ///
+    /// For NIST curves, this follows SEC 1, Version 2.0, Section 2.3.6,
+    /// which amounts to decoding the bytes as a fixed length big endian
+    /// integer and checking that the result is lower than the order of
+    /// the curve. The zero private key is also rejected, as the encoding
+    /// of the corresponding public key would be irregular.
+    ///
+    /// For X25519, this only checks the scalar length.
+    pub fn new_private_key(&self, key: &[u8]) -> Result<PrivateKey, ~str> {
+        let public = match self.nist() {
+            Some(c) => {
+                if key.len() != c.n.byte_len() {
+                    return Err(~"crypto/ecdh: invalid private key size");
+                }
+                match c.n.set_bytes(key) {
+                    Some(ref k) if c.n.is_zero(k) == 0 => {}
+                    _ => return Err(~"crypto/ecdh: invalid private key"),
+                }
+                Point::scalar_base_mult(c, key).bytes()
+            }
+            None => {
+                if key.len() != X25519Size {
+                    return Err(~"crypto/ecdh: invalid private key size");
+                }
+                x25519(key, X25519Basepoint)
+            }
+        };
+        Ok(PrivateKey {
+            curve: *self,
+            key: key.to_owned(),
+            public_key: PublicKey { curve: *self, key: public },
+        })
+    }
+
+    /// Checks that key is valid and returns a PublicKey.
+    ///
+    /// For NIST curves, this decodes an uncompressed point according to
+    /// SEC 1, Version 2.0, Section 2.3.4. Compressed encodings and the
+    /// point at infinity are rejected.
+    ///
+    /// For X25519, this only checks the u-coordinate length. Adversarially
+    /// selected public keys can cause PrivateKey::ecdh to return an error.
+    pub fn new_public_key(&self, key: &[u8]) -> Result<PublicKey, ~str> {
+        match self.nist() {
+            Some(c) => {
+                if key.len() == 0 || key[0] != 4 || Point::set_bytes(c, key).is_none() {
+                    return Err(~"crypto/ecdh: invalid public key");
+                }
+            }
+            None => {
+                if key.len() != X25519Size {
+                    return Err(~"crypto/ecdh: invalid public key");
+                }
+            }
+        }
+        Ok(PublicKey { curve: *self, key: key.to_owned() })
+    }
+}
+
+impl fmt::Show for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            X25519 => "X25519",
+            P256 => "P-256",
+            P384 => "P-384",
+            P521 => "P-521",
+        };
+        f.buf.write_str(name)
+    }
+}
+
+/// An ECDH public key, usually a peer's ECDH share sent over the wire.
+#[deriving(Clone, Eq)]
+pub struct PublicKey {
+    curve: Curve,
+    key: ~[u8],
+}
+
+impl PublicKey {
+    /// Returns the curve of the key.
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// Returns a copy of the encoding of the public key: the uncompressed
+    /// point of SEC 1, Version 2.0, Section 2.3.3 for NIST curves, and the
+    /// 32-byte u-coordinate for X25519.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.clone()
+    }
+}
+
+impl super::PublicKey for PublicKey {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// An ECDH private key, usually kept secret.
+#[deriving(Clone)]
+pub struct PrivateKey {
+    curve: Curve,
+    key: ~[u8],
+    public_key: PublicKey,
+}
+
+impl PrivateKey {
+    /// Returns the curve of the key.
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// Returns a copy of the encoding of the private key: the big-endian
+    /// scalar of SEC 1, Version 2.0, Section 2.3.7 for NIST curves, and
+    /// the 32-byte scalar for X25519.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.clone()
+    }
+
+    /// Returns the public key corresponding to the key.
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    /// Reports whether x has the same curve and value as the key, in
+    /// constant time.
+    pub fn equal(&self, x: &PrivateKey) -> bool {
+        self.curve == x.curve && constant_time_compare(self.key.as_slice(), x.key.as_slice())
+    }
+
+    /// Performs an ECDH exchange and returns the shared secret. The key
+    /// and remote must use the same curve.
+    ///
+    /// For NIST curves, this performs ECDH as specified in SEC 1, Version
+    /// 2.0, Section 3.3.1, and returns the x-coordinate encoded according
+    /// to SEC 1, Version 2.0, Section 2.3.5. The result is never the point
+    /// at infinity, as the curves have prime order.
+    ///
+    /// For X25519, this performs ECDH as specified in RFC 7748, Section 6.1.
+    /// If the result is the all-zero value, ecdh returns an error, as the
+    /// remote key was of low order.
+    pub fn ecdh(&self, remote: &PublicKey) -> Result<~[u8], ~str> {
+        if self.curve != remote.curve {
+            return Err(~"crypto/ecdh: private key and public key curves do not match");
+        }
+        match self.curve.nist() {
+            Some(c) => {
+                let p = match Point::set_bytes(c, remote.key.as_slice()) {
+                    Some(p) => p,
+                    None => return Err(~"crypto/ecdh: invalid public key"),
+                };
+                match p.scalar_mult(self.key.as_slice()).bytes_x() {
+                    Some(x) => Ok(x),
+                    None => Err(~"crypto/ecdh: invalid shared secret"),
+                }
+            }
+            None => {
+                let out = x25519(self.key.as_slice(), remote.key.as_slice());
+                if constant_time_compare(out, [0u8, ..X25519Size]) {
+                    return Err(~"crypto/ecdh: bad X25519 remote ECDH input: low order point");
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+impl super::PrivateKey for PrivateKey {}
+
+/// Returns the X25519 function of RFC 7748, Section 5: the u-coordinate
+/// of the product of the clamped scalar and the point with u-coordinate
+/// point, both 32 bytes long. The most significant bit of point is
+/// ignored, and non-canonical values are accepted. The result may be all
+/// zeroes if point has low order.
+pub fn x25519(scalar: &[u8], point: &[u8]) -> ~[u8] {
+    if scalar.len() != X25519Size || point.len() != X25519Size {
+        fail!("crypto/ecdh: bad X25519 input size");
+    }
+    let mut k = slice::from_elem(X25519Size, 0u8);
+    k.copy_from(scalar);
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    let x1 = Element::set_bytes(point);
+    let mut x2 = Element::one();
+    let mut z2 = Element::zero();
+    let mut x3 = x1;
+    let mut z3 = Element::one();
+    let a24 = Element::from_u32(121665);
+
+    // The Montgomery ladder of RFC 7748, Section 5.
+    let mut swap = 0u32;
+    for t in range(0, 255u).rev() {
+        let bit = ((k[t / 8] >> (t % 8)) & 1) as u32;
+        swap ^= bit;
+        Element::swap(swap, &mut x2, &mut x3);
+        Element::swap(swap, &mut z2, &mut z3);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.subtract(&z2);
+        let bb = b.square();
+        let e = aa.subtract(&bb);
+        let c = x3.add(&z3);
+        let d = x3.subtract(&z3);
+        let da = d.multiply(&a);
+        let cb = c.multiply(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.multiply(&da.subtract(&cb).square());
+        x2 = aa.multiply(&bb);
+        z2 = e.multiply(&aa.add(&a24.multiply(&e)));
+    }
+    Element::swap(swap, &mut x2, &mut x3);
+    Element::swap(swap, &mut z2, &mut z3);
+
+    x2.multiply(&z2.invert()).bytes()
+}
+
+
+#[cfg(test)]
+mod test {
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::rand::Reader;
+
+    fn hex(s: &str) -> ~[u8] {
+        s.from_hex().unwrap()
+    }
+
+    #[test]
+    fn test_x25519() {
+        // RFC 7748, Section 5.2
+        let out = x25519(hex("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+                         hex("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c"));
+        assert_eq!(out.to_hex(),
+                   ~"c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552");
+
+        // RFC 7748, Section 6.1
+        let alice = X25519.new_private_key(
+            hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a")).unwrap();
+        let bob = X25519.new_private_key(
+            hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb")).unwrap();
+        assert_eq!(alice.public_key().bytes().to_hex(),
+                   ~"8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a");
+        assert_eq!(bob.public_key().bytes().to_hex(),
+                   ~"de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+        let shared = "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742";
+        assert_eq!(alice.ecdh(&bob.public_key()).unwrap().to_hex(), shared.to_owned());
+        assert_eq!(bob.ecdh(&alice.public_key()).unwrap().to_hex(), shared.to_owned());
+    }
+
+    #[test]
+    fn test_x25519_low_order() {
+        let key = X25519.generate_key(&mut Reader).unwrap();
+        let low_order = [
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800",
+            "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        ];
+        for u in low_order.iter() {
+            let remote = X25519.new_public_key(hex(*u)).unwrap();
+            assert!(key.ecdh(&remote).is_err());
+        }
+        assert!(X25519.new_public_key([9u8, ..31]).is_err());
+        assert!(X25519.new_private_key([9u8, ..33]).is_err());
+    }
+
+    #[test]
+    fn test_nist() {
+        // computed with OpenSSL
+        let tests = [
+            (P256,
+             "011b862273dabbc6e05644ba71a84320b7d3d47a1d1d18350c36936f721fd5d3",
+             "0483b17299aeabab7aeba9d894b314163c7f64840617b4d96b204dbe870da4e2ed\
+              28585f9fdc214c98046cc88eb0a9419e0a1efe241bacd68c43d079c8d71b219d",
+             "3c671dcd86154e060b65903d838242e1307a1cd6c0804064c4bab669b827b582"),
+            (P384,
+             "0081227c34c068eaaac949c20bca78fecb6a92377ec6dad27087b487cf73ecf4\
+              774303058ffa3fa3a171ce54c92079d1",
+             "04328420b3e07eaab8c81bcb148624fe465c7a4fad33955c32157e57d84c5ea89d\
+              6c3ba3777c4128283f9dfc0b789db561414d696d8ec55d084de519f3560dd151\
+              043293ae1e12efe7ce2dca927be18586e343b0e0fc1d9f39c0a6e751b586a061",
+             "1c6ae44cf7491f8dcb6015978cd8248a36a6bebd6bb039d7969df8863a24c5ef\
+              97697facc97693934b7ae32f7c2a0b9d"),
+            (P521,
+             "01d1163744cc586c430a5e493bf76f1a9cbe51776ad0e3b166d5d333014f1bfc\
+              ed415c992adc9df065a37ef9fa76e2800e1b095db9e286b0165daefd649389f0\
+              87d1",
+             "0400ed79d2ae0f0420693f5ddea7ac7cbfe9b49c635b7514429e72831765ea06a0\
+              437ede616a92a10274f2fde32a28c7ea13a6402eec790a36533f9de6d816aaad\
+              fdb300c06959e5c7181bb5bf5909d72f34a8c3bba866a0a88cf59734b8281ca4\
+              2a154ae5cdaae00ca537c61c2c1b5d722421a5bc9220d1b65f1da8778ce2679c\
+              c2c5dff2",
+             "00a9d1172a205f6e7bdff0276ff6d43233d52a5c99f1712bffd79923f89b9930\
+              37ceb4f96b6676acc7335814ae96042ee5a9c5f6c302a0cc7054ba20b40c48a4\
+              34f2"),
+        ];
+        for &(curve, k, remote, shared) in tests.iter() {
+            let key = curve.new_private_key(hex(k)).unwrap();
+            assert_eq!(key.bytes().to_hex(), k.to_owned());
+            let remote = curve.new_public_key(hex(remote)).unwrap();
+            assert_eq!(key.ecdh(&remote).unwrap().to_hex(), shared.to_owned());
+        }
+    }
+
+    #[test]
+    fn test_key_validation() {
+        for &curve in [P256, P384, P521].iter() {
+            let key = curve.generate_key(&mut Reader).unwrap();
+            let public = key.public_key().bytes();
+            assert!(curve.new_public_key(public).unwrap() == key.public_key());
+
+            // compressed points, the point at infinity and points off the
+            // curve are rejected
+            let n = (public.len() - 1) / 2;
+            let mut compressed = public.slice_to(1 + n).to_owned();
+            compressed[0] = 2 | (public[public.len() - 1] & 1);
+            assert!(curve.new_public_key(compressed).is_err());
+            assert!(curve.new_public_key([0u8]).is_err());
+            assert!(curve.new_public_key([]).is_err());
+            let mut bad = public.clone();
+            bad[n] ^= 1;
+            assert!(curve.new_public_key(bad).is_err());
+
+            // zero and scalars not below the order are rejected
+            let size = key.bytes().len();
+            assert!(curve.new_private_key(::std::slice::from_elem(size, 0u8)).is_err());
+            assert!(curve.new_private_key(::std::slice::from_elem(size, 0xffu8)).is_err());
+            assert!(curve.new_private_key(key.bytes().slice_from(1)).is_err());
+
+            let other = curve.generate_key(&mut Reader).unwrap();
+            assert_eq!(key.ecdh(&other.public_key()), other.ecdh(&key.public_key()));
+            assert!(key.equal(&curve.new_private_key(key.bytes()).unwrap()));
+            assert!(!key.equal(&other));
+        }
+        let x = X25519.generate_key(&mut Reader).unwrap();
+        let p = P256.generate_key(&mut Reader).unwrap();
+        assert!(x.ecdh(&p.public_key()).is_err());
+        assert_eq!(format!("{} {}", X25519, P521), ~"X25519 P-521");
+
+        let public = x.public_key();
+        let public = &public as &PublicKeyTrait;
+        assert!(public.as_any().as_ref::<PublicKey>() == Some(&x.public_key()));
+    }
+}
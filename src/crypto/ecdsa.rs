@@ -0,0 +1,1667 @@
+// The Elliptic Curve Digital Signature Algorithm, as defined in FIPS
+// 186-5 and SEC 1, Version 2.0, after Go's crypto/ecdsa.
+//
+// Signatures are produced either hedged, with a nonce derived from the
+// private key, the message hash and fresh randomness, or
+// deterministically, as specified in RFC 6979. They are encoded either as
+// the ASN.1 DER sequence of r and s used by X.509 and TLS, or as the
+// fixed-length concatenation r || s used by JOSE and COSE.
+//
+// All arithmetic on secret values is done in constant time with the
+// fields and points of crypto::elliptic.
+
+use std::any::Any;
+use std::io::IoResult;
+use std::slice;
+use hash::Hash;
+use math::big::Int;
+use super::{HashFunc, SignerOpts, SHA1, SHA224, SHA256, SHA384, SHA512};
+use super::elliptic::{Curve, Point};
+use super::elliptic::field::Elem;
+use super::rand::drbg::HmacDrbg;
+use super::sha1::Sha1;
+use super::sha2::{Sha2, Sha_224, Sha_256, Sha_384, Sha_512};
+
+/// An ECDSA public key.
+#[deriving(Clone, Eq)]
+pub struct PublicKey {
+    pub curve: &'static Curve,
+    pub x: Int,
+    pub y: Int,
+}
+
+impl PublicKey {
+    /// Returns the uncompressed encoding of the key, as specified in
+    /// SEC 1, Version 2.0, Section 2.3.3.
+    pub fn bytes(&self) -> ~[u8] {
+        match self.point() {
+            Some(p) => p.bytes(),
+            None => fail!("crypto/ecdsa: invalid public key"),
+        }
+    }
+
+    // Returns the key as a point, or None if it is not a valid point on
+    // the curve other than the point at infinity.
+    fn point(&self) -> Option<Point<'static>> {
+        let n = self.curve.p.byte_len();
+        if self.x.sign() < 0 || self.y.sign() < 0 ||
+            self.x.bit_len() > self.curve.p.bits || self.y.bit_len() > self.curve.p.bits {
+            return None;
+        }
+        let mut buf = slice::from_elem(1 + 2 * n, 0u8);
+        buf[0] = 4;
+        self.x.fill_bytes(buf.mut_slice(1, 1 + n));
+        self.y.fill_bytes(buf.mut_slice_from(1 + n));
+        Point::set_bytes(self.curve, buf)
+    }
+}
+
+impl super::PublicKey for PublicKey {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// An ECDSA private key.
+#[deriving(Clone)]
+pub struct PrivateKey {
+    pub public_key: PublicKey,
+    pub d: Int,
+}
+
+impl PrivateKey {
+    /// Returns the public key corresponding to the private key.
+    pub fn public(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    /// Returns the private scalar, big-endian and padded to the length of
+    /// the curve's order.
+    pub fn bytes(&self) -> ~[u8] {
+        let mut out = slice::from_elem(self.public_key.curve.n.byte_len(), 0u8);
+        self.d.fill_bytes(out);
+        out
+    }
+
+    // Returns the private scalar, or None if it is not in [1, n-1].
+    fn scalar(&self) -> Option<Elem> {
+        let c = self.public_key.curve;
+        if self.d.sign() <= 0 || self.d.bit_len() > c.n.bits {
+            return None;
+        }
+        match c.n.set_bytes(self.bytes()) {
+            Some(ref d) if c.n.is_zero(d) == 0 => Some(*d),
+            _ => None,
+        }
+    }
+}
+
+impl super::PrivateKey for PrivateKey {}
+
+impl super::Signer for PrivateKey {
+    fn public(&self) -> ~super::PublicKey {
+        ~self.public_key.clone() as ~super::PublicKey
+    }
+
+    /// Signs digest with the key, returning an ASN.1 encoded signature.
+    /// The signature is hedged with entropy from rand; opts is not used
+    /// beyond the caller's hashing of digest.
+    fn sign(&self, rand: &mut Reader, digest: &[u8], _opts: &SignerOpts) -> Result<~[u8], ~str> {
+        let mut rand = rand;
+        sign_asn1(&mut rand, self, digest)
+    }
+}
+
+/// Generates a new private key for the given curve, reading randomness
+/// from rand.
+pub fn generate_key<R: Reader>(curve: &'static Curve, rand: &mut R) -> IoResult<PrivateKey> {
+    let (k, x, y) = try!(super::elliptic::generate_key(curve, rand));
+    Ok(PrivateKey {
+        public_key: PublicKey { curve: curve, x: x, y: y },
+        d: Int::from_bytes(k),
+    })
+}
+
+// Shifts b right by s < 8 bits, in place.
+fn rsh(b: &mut [u8], s: uint) {
+    if s == 0 {
+        return;
+    }
+    for i in range(0, b.len()).rev() {
+        let hi = if i > 0 { b[i - 1] << (8 - s) } else { 0 };
+        b[i] = (b[i] >> s) | hi;
+    }
+}
+
+// Converts a hash into a scalar, following SEC 1, Version 2.0, Section
+// 4.1.3, point 5, and RFC 6979, Section 2.3.2 (bits2octets): the hash is
+// truncated to the bit length of the order and reduced modulo it.
+fn hash_to_scalar(c: &Curve, hash: &[u8]) -> Elem {
+    let size = c.n.byte_len();
+    let hash = if hash.len() > size { hash.slice_to(size) } else { hash };
+    let mut e = slice::from_elem(size, 0u8);
+    e.mut_slice_from(size - hash.len()).copy_from(hash);
+    if hash.len() * 8 > c.n.bits {
+        rsh(e, hash.len() * 8 - c.n.bits);
+    }
+    c.n.set_bytes_reduced(e)
+}
+
+// Draws nonces from drbg, as specified in RFC 6979, Section 3.2, steps h.1
+// to h.3, and returns the first signature with r and s both non-zero.
+fn sign_with_drbg<H: Hash + Clone>(drbg: &mut HmacDrbg<H>, key: &PrivateKey, d: &Elem,
+                                   hash: &[u8]) -> Result<(Int, Int), ~str> {
+    let c = key.public_key.curve;
+    let f = &c.n;
+    let mut buf = slice::from_elem(f.byte_len(), 0u8);
+    loop {
+        try!(drbg.generate(buf, []));
+        rsh(buf, f.byte_len() * 8 - f.bits);
+        let k = match f.set_bytes(buf) {
+            Some(k) => k,
+            None => continue,
+        };
+        if f.is_zero(&k) == 1 {
+            continue;
+        }
+
+        // r = x(k * G) mod n
+        let p = Point::scalar_base_mult(c, f.bytes(&k));
+        let x = match p.bytes_x() {
+            Some(x) => x,
+            None => continue,
+        };
+        let r = f.set_bytes_reduced(x);
+        if f.is_zero(&r) == 1 {
+            continue;
+        }
+
+        // s = k⁻¹ * (e + r * d) mod n
+        let e = hash_to_scalar(c, hash);
+        let s = f.mul(&f.invert(&k), &f.add(&e, &f.mul(&r, d)));
+        if f.is_zero(&s) == 1 {
+            continue;
+        }
+        return Ok((Int::from_bytes(f.bytes(&r)), Int::from_bytes(f.bytes(&s))));
+    }
+}
+
+/// Signs a hash (which should be the result of hashing a larger message)
+/// using the private key. If the hash is longer than the bit length of
+/// the curve's order, it is truncated to that length. It returns the
+/// signature as a pair of integers.
+///
+/// The nonce is derived with HMAC_DRBG over SHA-512 from fresh randomness
+/// read from rand together with the private key and the hash, so that a
+/// weak random source does not leak the key.
+pub fn sign<R: Reader>(rand: &mut R, key: &PrivateKey, hash: &[u8])
+                       -> Result<(Int, Int), ~str> {
+    let c = key.public_key.curve;
+    let d = match key.scalar() {
+        Some(d) => d,
+        None => return Err(~"crypto/ecdsa: invalid private key"),
+    };
+    let mut entropy = slice::from_elem(c.n.byte_len(), 0u8);
+    match rand.fill(entropy) {
+        Ok(()) => {}
+        Err(e) => return Err(format!("crypto/ecdsa: reading randomness failed: {}", e)),
+    }
+    let e = c.n.bytes(&hash_to_scalar(c, hash));
+    let mut drbg = HmacDrbg::new_seeded(Sha2::new(Sha_512), entropy, key.bytes(), e);
+    sign_with_drbg(&mut drbg, key, &d, hash)
+}
+
+/// Signs a hash using the private key with a deterministic nonce, as
+/// specified in RFC 6979. hash_func is the function that produced hash,
+/// and is also used for the nonce derivation; it must be one of SHA1,
+/// SHA224, SHA256, SHA384 or SHA512.
+pub fn sign_deterministic(key: &PrivateKey, hash_func: HashFunc, hash: &[u8])
+                          -> Result<(Int, Int), ~str> {
+    let c = key.public_key.curve;
+    let d = match key.scalar() {
+        Some(d) => d,
+        None => return Err(~"crypto/ecdsa: invalid private key"),
+    };
+    // RFC 6979, Section 3.2, steps b to g: seed K and V with
+    // int2octets(x) || bits2octets(h1).
+    let x = key.bytes();
+    let h1 = c.n.bytes(&hash_to_scalar(c, hash));
+    match hash_func {
+        SHA1 => sign_with_drbg(&mut HmacDrbg::new_seeded(Sha1::new(), x, h1, []), key, &d, hash),
+        SHA224 => sign_with_drbg(&mut HmacDrbg::new_seeded(Sha2::new(Sha_224), x, h1, []),
+                                 key, &d, hash),
+        SHA256 => sign_with_drbg(&mut HmacDrbg::new_seeded(Sha2::new(Sha_256), x, h1, []),
+                                 key, &d, hash),
+        SHA384 => sign_with_drbg(&mut HmacDrbg::new_seeded(Sha2::new(Sha_384), x, h1, []),
+                                 key, &d, hash),
+        SHA512 => sign_with_drbg(&mut HmacDrbg::new_seeded(Sha2::new(Sha_512), x, h1, []),
+                                 key, &d, hash),
+        _ => Err(~"crypto/ecdsa: unsupported hash function for deterministic signing"),
+    }
+}
+
+/// Verifies the signature in r, s of hash using the public key. Its
+/// return value records whether the signature is valid. Invalid keys and
+/// out of range values of r and s are reported as invalid signatures.
+pub fn verify(key: &PublicKey, hash: &[u8], r: &Int, s: &Int) -> bool {
+    let c = key.curve;
+    let f = &c.n;
+    let q = match key.point() {
+        Some(q) => q,
+        None => return false,
+    };
+    let scalar = |v: &Int| -> Option<Elem> {
+        if v.sign() <= 0 || v.bit_len() > f.bits {
+            return None;
+        }
+        let mut b = slice::from_elem(f.byte_len(), 0u8);
+        v.fill_bytes(b);
+        f.set_bytes(b)
+    };
+    let (r, s) = match (scalar(r), scalar(s)) {
+        (Some(r), Some(s)) => (r, s),
+        _ => return false,
+    };
+
+    // u1 = e * s⁻¹ mod n, u2 = r * s⁻¹ mod n
+    let w = f.invert(&s);
+    let e = hash_to_scalar(c, hash);
+    let u1 = f.mul(&e, &w);
+    let u2 = f.mul(&r, &w);
+
+    // x(u1 * G + u2 * Q) mod n == r
+    let p = Point::scalar_base_mult(c, f.bytes(&u1)).add(&q.scalar_mult(f.bytes(&u2)));
+    match p.bytes_x() {
+        Some(x) => f.equal(&f.set_bytes_reduced(x), &r) == 1,
+        None => false,
+    }
+}
+
+/// Signs a hash using the private key, hedged with entropy from rand, and
+/// returns the ASN.1 DER encoded signature.
+pub fn sign_asn1<R: Reader>(rand: &mut R, key: &PrivateKey, hash: &[u8])
+                            -> Result<~[u8], ~str> {
+    let (r, s) = try!(sign(rand, key, hash));
+    Ok(encode_asn1(&r, &s))
+}
+
+/// Verifies the ASN.1 DER encoded signature sig of hash using the public
+/// key. Signatures that are not strictly DER encoded are rejected.
+pub fn verify_asn1(key: &PublicKey, hash: &[u8], sig: &[u8]) -> bool {
+    match parse_asn1(sig) {
+        Some((r, s)) => verify(key, hash, &r, &s),
+        None => false,
+    }
+}
+
+/// Signs a hash using the private key, hedged with entropy from rand, and
+/// returns the signature as r || s, each padded to the length of the
+/// curve's order, as used by JWS (RFC 7518) and COSE (RFC 9053).
+pub fn sign_raw<R: Reader>(rand: &mut R, key: &PrivateKey, hash: &[u8])
+                           -> Result<~[u8], ~str> {
+    let (r, s) = try!(sign(rand, key, hash));
+    Ok(encode_raw(key.public_key.curve, &r, &s))
+}
+
+/// Verifies the signature sig of hash, encoded as r || s, using the
+/// public key.
+pub fn verify_raw(key: &PublicKey, hash: &[u8], sig: &[u8]) -> bool {
+    let n = key.curve.n.byte_len();
+    if sig.len() != 2 * n {
+        return false;
+    }
+    let r = Int::from_bytes(sig.slice_to(n));
+    let s = Int::from_bytes(sig.slice_from(n));
+    verify(key, hash, &r, &s)
+}
+
+/// Returns r and s encoded as r || s, each padded to the length of the
+/// curve's order. Fails if either value does not fit.
+pub fn encode_raw(curve: &Curve, r: &Int, s: &Int) -> ~[u8] {
+    let n = curve.n.byte_len();
+    let mut out = slice::from_elem(2 * n, 0u8);
+    r.fill_bytes(out.mut_slice_to(n));
+    s.fill_bytes(out.mut_slice_from(n));
+    out
+}
+
+// Appends an ASN.1 length in DER form.
+fn push_asn1_len(out: &mut ~[u8], len: uint) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len < 0x100 {
+        out.push(0x81);
+        out.push(len as u8);
+    } else {
+        out.push(0x82);
+        out.push((len >> 8) as u8);
+        out.push(len as u8);
+    }
+}
+
+// Appends a non-negative ASN.1 INTEGER in DER form.
+fn push_asn1_int(out: &mut ~[u8], v: &Int) {
+    let b = v.bytes();
+    let pad = b.len() == 0 || b[0] & 0x80 != 0;
+    out.push(0x02);
+    push_asn1_len(out, b.len() + pad as uint);
+    if pad {
+        out.push(0);
+    }
+    out.push_all(b);
+}
+
+/// Returns r and s encoded as the ASN.1 DER sequence
+/// Ecdsa-Sig-Value ::= SEQUENCE { r INTEGER, s INTEGER }.
+pub fn encode_asn1(r: &Int, s: &Int) -> ~[u8] {
+    let mut body = ~[];
+    push_asn1_int(&mut body, r);
+    push_asn1_int(&mut body, s);
+    let mut out = ~[0x30u8];
+    push_asn1_len(&mut out, body.len());
+    out.push_all(body);
+    out
+}
+
+// Reads a DER element with the given tag from the front of b, returning
+// its contents and the rest of b.
+fn read_asn1<'a>(b: &'a [u8], tag: u8) -> Option<(&'a [u8], &'a [u8])> {
+    if b.len() < 2 || b[0] != tag {
+        return None;
+    }
+    let (len, off) = match b[1] {
+        l if l < 0x80 => (l as uint, 2),
+        0x81 if b.len() >= 3 && b[2] >= 0x80 => (b[2] as uint, 3),
+        0x82 if b.len() >= 4 && b[2] != 0 => ((b[2] as uint << 8) | b[3] as uint, 4),
+        _ => return None,
+    };
+    if b.len() - off < len {
+        return None;
+    }
+    Some((b.slice(off, off + len), b.slice_from(off + len)))
+}
+
+// Reads a positive DER INTEGER from the front of b.
+fn read_asn1_int<'a>(b: &'a [u8]) -> Option<(Int, &'a [u8])> {
+    let (v, rest) = match read_asn1(b, 0x02) {
+        Some(x) => x,
+        None => return None,
+    };
+    // no empty, negative or non-minimally encoded integers
+    if v.len() == 0 || v[0] & 0x80 != 0 || (v.len() > 1 && v[0] == 0 && v[1] & 0x80 == 0) {
+        return None;
+    }
+    Some((Int::from_bytes(v), rest))
+}
+
+/// Parses an ASN.1 DER encoded Ecdsa-Sig-Value into r and s. It returns
+/// None if the encoding is not strict DER or has trailing data.
+pub fn parse_asn1(sig: &[u8]) -> Option<(Int, Int)> {
+    let body = match read_asn1(sig, 0x30) {
+        Some((body, rest)) if rest.len() == 0 => body,
+        _ => return None,
+    };
+    let (r, body) = match read_asn1_int(body) {
+        Some(x) => x,
+        None => return None,
+    };
+    match read_asn1_int(body) {
+        Some((s, rest)) if rest.len() == 0 => Some((r, s)),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use IoReader = std::io::Reader;
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use math::big::Int;
+    use super::*;
+    use super::super::{HashFunc, SHA1, SHA256, SHA384, SHA512, Signer, SignerOpts};
+    use super::super::elliptic::{Curve, p256, p384, p521};
+    use super::super::rand::Reader;
+
+    fn int(s: &str) -> Int {
+        Int::from_str_radix(s, 16).unwrap()
+    }
+
+    fn digest(h: HashFunc, msg: &[u8]) -> ~[u8] {
+        let mut d = h.new();
+        d.write(msg).unwrap();
+        d.digest()
+    }
+
+    fn key(c: &'static Curve, d: &str, x: &str, y: &str) -> PrivateKey {
+        PrivateKey { public_key: PublicKey { curve: c, x: int(x), y: int(y) }, d: int(d) }
+    }
+
+    fn rfc6979_keys() -> ~[PrivateKey] {
+        // RFC 6979, Appendix A.2.5 to A.2.7
+        ~[
+            key(p256(),
+                "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+                "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6",
+                "7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299"),
+            key(p384(),
+                "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba9aa47740787137d8\
+                 96d5724e4c70a825f872c9ea60d2edf5",
+                "ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e06aae5286b300c64\
+                 def8f0ea9055866064a254515480bc13",
+                "8015d9b72d7d57244ea8ef9ac0c621896708a59367f9dfb9f54ca84b3f1c9db1\
+                 288b231c3ae0d4fe7344fd2533264720"),
+            key(p521(),
+                "00fad06daa62ba3b25d2fb40133da757205de67f5bb0018fee8c86e1b68c7e75\
+                 caa896eb32f1f47c70855836a6d16fcc1466f6d8fbec67db89ec0c08b0e996b8\
+                 3538",
+                "01894550d0785932e00eaa23b694f213f8c3121f86dc97a04e5a7167db4e5bcd\
+                 371123d46e45db6b5d5370a7f20fb633155d38ffa16d2bd761dcac474b9a2f50\
+                 23a4",
+                "00493101c962cd4d2fddf782285e64584139c2f91b47f87ff82354d6630f746a\
+                 28a0db25741b5b34a828008b22acc23f924faafbd4d33f81ea66956dfeaa2bfd\
+                 fcf5"),
+        ]
+    }
+
+    #[test]
+    fn test_rfc6979() {
+        let keys = rfc6979_keys();
+        let tests = [
+            (0u, SHA256, "sample",
+             "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+             "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8"),
+            (0, SHA256, "test",
+             "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367",
+             "019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083"),
+            (1, SHA384, "sample",
+             "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa73d64c4ea95ad133c\
+              81a648152e44acf96e36dd1e80fabe46",
+             "99ef4aeb15f178cea1fe40db2603138f130e740a19624526203b6351d0a3a94f\
+              a329c145786e679e7b82c71a38628ac8"),
+            (1, SHA384, "test",
+             "8203b63d3c853e8d77227fb377bcf7b7b772e97892a80f36ab775d509d7a5feb\
+              0542a7f0812998da8f1dd3ca3cf023db",
+             "ddd0760448d42d8a43af45af836fce4de8be06b485e9b61b827c2f13173923e0\
+              6a739f040649a667bf3b828246baa5a5"),
+            (2, SHA512, "sample",
+             "c328fafcbd79dd77850370c46325d987cb525569fb63c5d3bc53950e6d4c5f17\
+              4e25a1ee9017b5d450606add152b534931d7d4e8455cc91f9b15bf05ec36e377fa",
+             "617cce7cf5064806c467f678d3b4080d6f1cc50af26ca209417308281b68af28\
+              2623eaa63e5b5c0723d8b8c37ff0777b1a20f8ccb1dccc43997f1ee0e44da4a67a"),
+            (2, SHA512, "test",
+             "013e99020abf5cee7525d16b69b229652ab6bdf2affcaef38773b4b7d08725f1\
+              0cdb93482fdcc54edcee91eca4166b2a7c6265ef0ce2bd7051b7cef945babd47ee6d",
+             "01fbd0013c674aa79cb39849527916ce301c66ea7ce8b80682786ad60f98f7e7\
+              8a19ca69eff5c57400e3b3a0ad66ce0978214d13baf4e9ac60752f7b155e2de4dce3"),
+        ];
+        for &(i, hf, msg, r, s) in tests.iter() {
+            let key = &keys[i];
+            let c = key.public_key.curve;
+            assert_eq!(c.scalar_base_mult(key.bytes()),
+                       (key.public_key.x.clone(), key.public_key.y.clone()));
+            let hash = digest(hf, msg.as_bytes());
+            let (gr, gs) = sign_deterministic(key, hf, hash).unwrap();
+            assert_eq!(gr, int(r));
+            assert_eq!(gs, int(s));
+            assert!(verify(&key.public_key, hash, &gr, &gs));
+            assert!(!verify(&key.public_key, hash, &gs, &gr));
+            let raw = encode_raw(c, &gr, &gs);
+            assert!(verify_raw(&key.public_key, hash, raw));
+            let der = encode_asn1(&gr, &gs);
+            assert_eq!(parse_asn1(der), Some((gr, gs)));
+            assert!(verify_asn1(&key.public_key, hash, der));
+        }
+    }
+
+    #[test]
+    fn test_asn1_encoding() {
+        // the P-521 "sample" signature from above, whose length needs the
+        // long form and whose s has one leading zero byte fewer than r
+        let der = "308187024200c328fafcbd79dd77850370c46325d987cb525569fb63c5d3bc\
+                   53950e6d4c5f174e25a1ee9017b5d450606add152b534931d7d4e8455cc91f\
+                   9b15bf05ec36e377fa0241617cce7cf5064806c467f678d3b4080d6f1cc50a\
+                   f26ca209417308281b68af282623eaa63e5b5c0723d8b8c37ff0777b1a20f8\
+                   ccb1dccc43997f1ee0e44da4a67a".from_hex().unwrap();
+        let (r, s) = parse_asn1(der).unwrap();
+        assert_eq!(encode_asn1(&r, &s), der);
+        let key = &rfc6979_keys()[2];
+        assert!(verify_asn1(&key.public_key, digest(SHA512, bytes!("sample")), der));
+
+        assert_eq!(encode_asn1(&Int::new(1), &Int::new(0x80)).to_hex(), ~"300702010102020080");
+        let bad = [
+            "",
+            "3007020101020101",         // wrong sequence length
+            "300602010102010100",       // trailing data after the sequence
+            "300702010102010100",       // trailing data inside the sequence
+            "30060201ff020101",         // negative r
+            "300702020001020101",       // non-minimally encoded r
+            "30050200020101",           // empty r
+            "3003020101",               // missing s
+            "310602010102010101",       // wrong tag
+            "308106020101020101",       // non-minimally encoded length
+        ];
+        for b in bad.iter() {
+            let b = b.from_hex().unwrap();
+            assert!(parse_asn1(b).is_none(), "{}", b.to_hex());
+        }
+        assert_eq!(parse_asn1("3006020101020101".from_hex().unwrap()),
+                   Some((Int::new(1), Int::new(1))));
+    }
+
+    // Test cases from Project Wycheproof (github.com/google/wycheproof),
+    // as (x, y) of the public key and the cases for it: (id, valid, message,
+    // signature). The P-256 cases are a subset of
+    // testvectors_v1/ecdsa_secp256r1_sha256_test.json, and id is their tcId.
+    static P256Wycheproof: &'static [(&'static str, &'static str,
+                                      &'static [(uint, bool, &'static str, &'static str)])] = &[
+        ("04aaec73635726f213fb8a9e64da3b8632e41495a944d0045b522eba7240fad5",
+         "87d9315798aaa3a5ba01775787ced05eaaf7b4e09fc81d6d1aa546e8365d525d", &[
+            (1, true, "",
+             "3045022100b292a619339f6e567a305c951c0dcbcc42d16e47f219f9e98e76e0\
+              9d8770b34a02200177e60492c5a8242f76f07bfe3661bde59ec2a17ce5bd2dab\
+              2abebdf89a62e2"),
+            (3, true, "313233343030",
+             "3046022100a8ea150cb80125d7381c4c1f1da8e9de2711f9917060406a73d790\
+              4519e51388022100f3ab9fa68bd47973a73b2d40480c2ba50c22c9d76ec21725\
+              7288293285449b86"),
+        ]),
+        ("2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838",
+         "c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e", &[
+            (5, true, "313233343030",
+             "304402202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e1802204cd60b855d442f5b3c7b11eb6c4e0ae7525fe710fab9aa7c77a6\
+              7f79e6fadd76"),
+            (6, false, "313233343030",
+             "304402202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e180220b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c13\
+              4b49156847db"),
+            (7, true, "313233343030",
+             "304502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (8, false, "313233343030",
+             "30814502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6a\
+              f9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (9, false, "313233343030",
+             "3082004502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (12, false, "313233343030",
+             "3085010000004502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee8\
+              59b8cc6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139c\
+              ac5df4087c134b49156847db"),
+            (19, false, "313233343030",
+             "30ff02202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (20, false, "313233343030",
+             "308002202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (21, false, "313233343030",
+             ""),
+            (23, false, "313233343030",
+             "304702202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db0000"),
+            (24, false, "313233343030",
+             "3047000002202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (27, false, "313233343030",
+             "304a498177304502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee8\
+              59b8cc6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139c\
+              ac5df4087c134b49156847db"),
+            (29, false, "313233343030",
+             "3047304502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db0004deadbeef"),
+            (35, false, "313233343030",
+             "3080304502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db0000"),
+            (38, false, "313233343030",
+             "2e4502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (47, false, "313233343030",
+             "3082104602202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db0000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              00000000000000000000"),
+            (48, false, "313233343030",
+             "308002202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db0000"),
+            (54, false, "313233343030",
+             "3047300002202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (56, false, "313233343030",
+             "304802202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db020100"),
+            (60, false, "313233343030",
+             "3047304502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (63, false, "313233343030",
+             "30432ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9bd5c\
+              2e19022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c134b\
+              49156847db"),
+            (67, false, "313233343030",
+             "30460281202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6a\
+              f9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (69, false, "313233343030",
+             "304502212ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (71, false, "313233343030",
+             "304a028501000000202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee8\
+              59b8cc6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139c\
+              ac5df4087c134b49156847db"),
+            (77, false, "313233343030",
+             "304d0288ffffffffffffffff2ba3a8be6b94d5ec80a6d9d1190a436effe50d85\
+              a1eee859b8cc6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a\
+              87139cac5df4087c134b49156847db"),
+            (80, false, "313233343030",
+             "3023022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c134b\
+              49156847db"),
+            (84, false, "313233343030",
+             "3047022200002ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc\
+              6af9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (94, false, "313233343030",
+             "30250500022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (102, false, "313233343030",
+             "3045022029a3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (106, false, "313233343030",
+             "30821048028210212ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859\
+              b8cc6af9bd5c2e18000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000000000000000000000000\
+              000000000000000000022100b329f479a2bbd0a5c384ee1493b1f5186a87139c\
+              ac5df4087c134b49156847db"),
+            (107, false, "313233343030",
+             "30460221ff2ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6a\
+              f9bd5c2e18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (108, false, "313233343030",
+             "3026090180022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (109, false, "313233343030",
+             "3026020100022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (114, false, "313233343030",
+             "304602202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e1802812100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (122, false, "313233343030",
+             "304902202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e180284ffffffff00b329f479a2bbd0a5c384ee1493b1f5186a87139cac\
+              5df4087c134b49156847db"),
+            (127, false, "313233343030",
+             "304702202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18022300b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db0000"),
+            (143, false, "313233343030",
+             "302402202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e180200"),
+            (149, false, "313233343030",
+             "304602202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e180222ff00b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (151, false, "313233343030",
+             "302502202ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9\
+              bd5c2e18020100"),
+            (152, false, "313233343030",
+             "30460221012ba3a8bd6b94d5ed80a6d9d1190a436ebccc0833490686deac8635\
+              bcb9bf5369022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (153, false, "313233343030",
+             "30460221ff2ba3a8bf6b94d5eb80a6d9d1190a436f42fe12d7fad749d4c512a0\
+              36c0f908c7022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (154, false, "313233343030",
+             "3047022201002ba3a7be6b94d6ec80a6d9d1190a432be6dfbb2cb98d6d4d7297\
+              2df620817f18022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4\
+              087c134b49156847db"),
+            (156, false, "313233343030",
+             "3046022100d45c5740946b2a147f59262ee6f5bc90bd01ed280528b62b3aed5f\
+              c93f06f739022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (160, false, "313233343030",
+             "3046022101b329f478a2bbd0a6c384ee1493b1f518276e0e4a5375928d6fcd16\
+              0c11cb6d2c022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (161, false, "313233343030",
+             "30450220b329f47aa2bbd0a4c384ee1493b1f518ada018ef0546558388598086\
+              1905228a022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df4087c\
+              134b49156847db"),
+            (163, false, "313233343030",
+             "30460221ff4cd60b865d442f5a3c7b11eb6c4e0ae79578ec6353a20bf783ecb4\
+              b6ea97b825022100b329f479a2bbd0a5c384ee1493b1f5186a87139cac5df408\
+              7c134b49156847db"),
+            (168, false, "313233343030",
+             "3006020100020100"),
+            (177, false, "313233343030",
+             "3006020101020101"),
+            (180, false, "313233343030",
+             "3026020101022100ffffffff00000000ffffffffffffffffbce6faada7179e84\
+              f3b9cac2fc632550"),
+            (193, false, "313233343030",
+             "3026022100ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9ca\
+              c2fc632551020101"),
+            (204, false, "313233343030",
+             "3046022100ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9ca\
+              c2fc632550022100ffffffff00000000ffffffffffffffffbce6faada7179e84\
+              f3b9cac2fc632550"),
+            (232, false, "313233343030",
+             "3008020100090380fe01"),
+            (279, false, "313233343030",
+             "3006010101010101"),
+            (294, false, "313233343030",
+             "30083003020100020100"),
+            (295, true, "3639383139",
+             "3044022064a1aab5000d0e804f3e2fc02bdee9be8ff312334e2ba16d11547c97\
+              711c898e02206af015971cc30be6d1a206d4e013e0997772a2f91d73286ffd68\
+              3b9bb2cf4f1b"),
+            (296, true, "343236343739373234",
+             "3044022016aea964a2f6506d6f78c81c91fc7e8bded7d397738448de1e19a0ec\
+              580bf2660220252cd762130c6667cfe8b7bc47d27d78391e8e80c578d1cd38c3\
+              ff033be928e9"),
+        ]),
+        ("0ad99500288d466940031d72a9f5445a4d43784640855bf0a69874d2de5fe103",
+         "c5011e6ef2c42dcd50d5d3d29f99ae6eba2c80c9244f4c5422f0979ff0c3ba5e", &[
+            (350, true, "313233343030",
+             "303502104319055358e8617b0c46353d039cdaab022100ffffffff00000000ff\
+              ffffffffffffffbce6faada7179e84f3b9cac2fc63254e"),
+            (351, false, "313233343030",
+             "3046022100ffffffff00000001000000000000000000000000ffffffffffffff\
+              fffffffffc022100ffffffff00000000ffffffffffffffffbce6faada7179e84\
+              f3b9cac2fc63254e"),
+        ]),
+        ("ab05fd9d0de26b9ce6f4819652d9fc69193d0aa398f0fba8013e09c582204554",
+         "19235271228c786759095d12b75af0692dd4103f19f6a8c32f49435a1e9b8d45", &[
+            (352, true, "313233343030",
+             "3046022100ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9ca\
+              c2fc63254f022100ffffffff00000000ffffffffffffffffbce6faada7179e84\
+              f3b9cac2fc63254e"),
+        ]),
+        ("a71af64de5126a4a4e02b7922d66ce9415ce88a4c9d25514d91082c8725ac957",
+         "5d47723c8fbe580bb369fec9c2665d8e30a435b9932645482e7c9f11e872296b", &[
+            (355, true, "313233343030",
+             "3006020105020101"),
+        ]),
+        ("d03eb09913cc20c6a8d0070f0d8d2a7f63527fafa44117fce6bd1ef2aa4ae3c4",
+         "6d5df3f45ac58fa334c6d102381b3120b7a2455600dcaff3d1a845514f12bf46", &[
+            (363, false, "313233343030",
+             "3026022100ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9ca\
+              c2fc632557020107"),
+        ]),
+        ("3a72476291571193b4d109b2c37b59f2807e8fe9cffd804eacded903e77ca0da",
+         "592dbc74fee0ca7508cc7bc282b0c51a143286ff53c60131668e7a0929e4ed04", &[
+            (364, false, "313233343030",
+             "3026020106022100ffffffff00000000ffffffffffffffffbce6faada7179e84\
+              f3b9cac2fc75fbd8"),
+        ]),
+        ("083539fbee44625e3acaafa2fcb41349392cef0633a1b8fabecee0c133b10e99",
+         "915c1ebe7bf00df8535196770a58047ae2a402f26326bb7d41d4d7616337911e", &[
+            (370, true, "313233343030",
+             "3045022100ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9ca\
+              c2fc6324d50220555555550000000055555555555555553ef7a8e48d07df81a6\
+              93439654210c70"),
+        ]),
+        ("8aeb368a7027a4d64abdea37390c0c1d6a26f399e2d9734de1eb3d0e19373874",
+         "05bd13834715e1dbae9b875cf07bd55e1b6691c7f7536aef3b19bf7a4adf576d", &[
+            (375, true, "313233343030",
+             "30250220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c70020101"),
+            (376, false, "313233343030",
+             "30250220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c70020100"),
+        ]),
+        ("61722eaba731c697c7a9ba4d0afdbb5713d8aa12b0eab601bb33dbaf792c5adc",
+         "272cd993b2b663aba5b3a26c101182ff178684945e83879e71598b95fe647dfc", &[
+            (377, true, "313233343030",
+             "30440220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c70022002f676969f451a8ccafa4c4f09791810e6d632dbd60b1d5540f3\
+              284fbe1889b0"),
+        ]),
+        ("b533d4695dd5b8c5e07757e55e6e516f7e2c88fa0239e23f60e8ec07dd70f287",
+         "1b134ee58cc583278456863f33c3a85d881f7d4a39850143e29d4eaf009afe47", &[
+            (392, false, "313233343030",
+             "304402207fffffff800000007fffffffffffffffde737d56d38bcf4279dce561\
+              7e3192a80220555555550000000055555555555555553ef7a8e48d07df81a693\
+              439654210c70"),
+        ]),
+        ("f50d371b91bfb1d7d14e1323523bc3aa8cbf2c57f9e284de628c8b4536787b86",
+         "f94ad887ac94d527247cd2e7d0c8b1291c553c9730405380b14cbb209f5fa2dd", &[
+            (393, true, "313233343030",
+             "304402207fffffff800000007fffffffffffffffde737d56d38bcf4279dce561\
+              7e3192a902207fffffff800000007fffffffffffffffde737d56d38bcf4279dc\
+              e5617e3192a8"),
+        ]),
+        ("69da0364734d2e530fece94019265fefb781a0f1b08f6c8897bdf6557927c8b8",
+         "66d2d3c7dcd518b23d726960f069ad71a933d86ef8abbcce8b20f71e2a847002", &[
+            (395, true, "313233343030",
+             "30450220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c70022100bb5a52f42f9c9261ed4361f59422a1e30036e7c32b270c8807\
+              a419feca605023"),
+        ]),
+        ("cf04ea77e9622523d894b93ff52dc3027b31959503b6fa3890e5e04263f922f1",
+         "e8528fb7c006b3983c8b8400e57b4ed71740c2f3975438821199bedeaecab2e9", &[
+            (398, true, "313233343030",
+             "30450220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c70022100aaaaaaaa00000000aaaaaaaaaaaaaaaa7def51c91a0fbf034d\
+              26872ca84218e1"),
+        ]),
+        ("db7a2c8a1ab573e5929dc24077b508d7e683d49227996bda3e9f78dbeff77350",
+         "4f417f3bc9a88075c2e0aadd5a13311730cf7cc76a82f11a36eaf08a6c99a206", &[
+            (399, true, "313233343030",
+             "304502207fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffd022100e91e1ba60fdedb76a46bcb51dc0b8b4b7e019f0a28721885fa\
+              5d3a8196623397"),
+        ]),
+        ("67e6f659cdde869a2f65f094e94e5b4dfad636bbf95192feeed01b0f3deb7460",
+         "a37e0a51f258b7aeb51dfe592f5cfd5685bbe58712c8d9233c62886437c38ba0", &[
+            (413, true, "313233343030",
+             "304402207fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffd02207fffffffaaaaaaaaffffffffffffffffe9a2538f37b28a2c513d\
+              ee40fecbb71a"),
+        ]),
+        ("5b812fd521aafa69835a849cce6fbdeb6983b442d2444fe70e134c027fc46963",
+         "838a40f2a36092e9004e92d8d940cf5638550ce672ce8b8d4e15eba5499249e9", &[
+            (427, true, "313233343030",
+             "304502206f2347cab7dd76858fe0555ac3bc99048c4aacafdfb6bcbe05ea6c42\
+              c4934569022100bb726660235793aa9957a61e76e00c2c435109cf9a15dd624d\
+              53f4301047856b"),
+        ]),
+        ("5b812fd521aafa69835a849cce6fbdeb6983b442d2444fe70e134c027fc46963",
+         "7c75bf0c5c9f6d17ffb16d2726bf30a9c7aaf31a8d317472b1ea145ab66db616", &[
+            (428, false, "313233343030",
+             "304502206f2347cab7dd76858fe0555ac3bc99048c4aacafdfb6bcbe05ea6c42\
+              c4934569022100bb726660235793aa9957a61e76e00c2c435109cf9a15dd624d\
+              53f4301047856b"),
+        ]),
+        ("6adda82b90261b0f319faa0d878665a6b6da497f09c903176222c34acfef72a6",
+         "47e6f50dcc40ad5d9b59f7602bb222fad71a41bf5e1f9df4959a364c62e488d9", &[
+            (429, false, "313233343030",
+             "30250201010220555555550000000055555555555555553ef7a8e48d07df81a6\
+              93439654210c70"),
+        ]),
+        ("dd86d3b5f4a13e8511083b78002081c53ff467f11ebd98a51a633db76665d250",
+         "45d5c8200c89f2fa10d849349226d21d8dfaed6ff8d5cb3e1b7e17474ebc18f7", &[
+            (431, false, "313233343030",
+             "30440220555555550000000055555555555555553ef7a8e48d07df81a6934396\
+              54210c7002203333333300000000333333333333333325c7cbbc549e52e763f1\
+              f55a327a3aa9"),
+        ]),
+        ("4fea55b32cb32aca0c12c4cd0abfb4e64b0f5a516e578c016591a93f5a0fbcc5",
+         "d7d3fd10b2be668c547b212f6bb14c88f0fecd38a8a4b2c785ed3be62ce4b280", &[
+            (432, true, "313233343030",
+             "304402207cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc\
+              476699780220555555550000000055555555555555553ef7a8e48d07df81a693\
+              439654210c70"),
+        ]),
+        ("c6a771527024227792170a6f8eee735bf32b7f98af669ead299802e32d7c3107",
+         "bc3b4b5e65ab887bbd343572b3e5619261fe3a073e2ffd78412f726867db589e", &[
+            (433, true, "313233343030",
+             "304502207cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc\
+              47669978022100b6db6db6249249254924924924924924625bd7a09bec4ca81b\
+              cdd9f8fd6b63cc"),
+        ]),
+        ("0d935bf9ffc115a527735f729ca8a4ca23ee01a4894adf0e3415ac84e808bb34",
+         "3195a3762fea29ed38912bd9ea6c4fde70c3050893a4375850ce61d82eba33c5", &[
+            (437, true, "313233343030",
+             "304402207cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc\
+              47669978022016a4502e2781e11ac82cbc9d1edd8c981584d13e18411e2f6e04\
+              78c34416e3bb"),
+        ]),
+        ("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+         "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5", &[
+            (444, false, "313233343030",
+             "3045022100bb5a52f42f9c9261ed4361f59422a1e30036e7c32b270c8807a419\
+              feca6050230220249249246db6db6ddb6db6db6db6db6dad4591868595a8ee6b\
+              f5f864ff7be0c2"),
+        ]),
+        ("4f337ccfd67726a805e4f1600ae2849df3807eca117380239fbd816900000000",
+         "ed9dea124cc8c396416411e988c30f427eb504af43a3146cd5df7ea60666d685", &[
+            (448, true, "4d657373616765",
+             "3046022100d434e262a49eab7781e353a3565e482550dd0fd5defa013c7f2974\
+              5eff3569f10221009b0c0a93f267fb6052fd8077be769c2b98953195d7bc10de\
+              844218305c6ba17a"),
+        ]),
+        ("fffffff948081e6a0458dd8f9e738f2665ff9059ad6aac0708318c4ca9a7a4f5",
+         "5a8abcba2dda8474311ee54149b973cae0c0fb89557ad0bf78e6529a1663bd73", &[
+            (460, true, "4d657373616765",
+             "30440220766456dce1857c906f9996af729339464d27e9d98edc2d0e3b760297\
+              067421f60220402385ecadae0d8081dccaf5d19037ec4e55376eced699e93646\
+              bfbbf19d0b41"),
+        ]),
+        ("03fa15f963949d5f03a6f5c7f86f9e0015eeb23aebbff1173937ba748e",
+         "1099872070e8e87c555fa13659cca5d7fadcfcb0023ea889548ca48af2ba7e71", &[
+            (463, true, "4d657373616765",
+             "3046022100b7c81457d4aeb6aa65957098569f0479710ad7f6595d5874c35a93\
+              d12a5dd4c7022100b7961a0b652878c2d568069a432ca18a1a9199f2ca574dad\
+              4b9e3a05c0a1cdb3"),
+        ]),
+        ("bcbb2914c79f045eaa6ecbbc612816b3be5d2d6796707d8125e9f851c18af015",
+         "1352bb4a0fa2ea4cceb9ab63dd684ade5a1127bcf300a698a7193bc2", &[
+            (466, true, "4d657373616765",
+             "3044022031230428405560dcb88fb5a646836aea9b23a23dd973dcbe8014c87b\
+              8b20eb0702200f9344d6e812ce166646747694a41b0aaf97374e19f3c5fb8bd7\
+              ae3d9bd0beff"),
+        ]),
+    ];
+
+    // The P-384 cases are a subset of ecdsa_secp384r1_sha384_test.json as
+    // converted by RustCrypto's p384 crate (src/test_vectors/data/
+    // wycheproof.blb), which drops the tcIds; id is the position of the case
+    // in that file.
+    static P384Wycheproof: &'static [(&'static str, &'static str,
+                                      &'static [(uint, bool, &'static str, &'static str)])] = &[
+        ("2da57dda1089276a543f9ffdac0bff0d976cad71eb7280e7d9bfd9fee4bdb2f2\
+          0f47ff888274389772d98cc5752138aa",
+         "4b6d054d69dcf3e25ec49df870715e34883b1836197d76f8ad962e78f6571bbc\
+          7407b0d6091f9e4d88f014274406174f", &[
+            (0, true, "313233343030",
+             "3064023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d702301840da9fc1d2f8f8900c\
+              f485d5413b8c2574ee3a8d4ca03995ca30240e09513805bf6209b58ac7aa9cff\
+              54eecd82b9f1"),
+            (1, true, "313233343030",
+             "3065023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f82"),
+            (2, false, "313233343030",
+             "308165023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca\
+              28e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d0707\
+              6ff30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd0\
+              4fecc47bff426f82"),
+            (3, false, "313233343030",
+             "30820065023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2\
+              ca28e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d07\
+              076ff30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325df\
+              d04fecc47bff426f82"),
+            (4, false, "313233343030",
+             "3066023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f82"),
+            (5, false, "313233343030",
+             "3064023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f82"),
+            (20, false, "313233343030",
+             "3065023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f820000"),
+            (35, false, "313233343030",
+             "306d023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d72239aa00bb00cd00023100e7\
+              bf25603e2d07076ff30b7a2abec473da8b11c572b35fc631991d5de62ddca752\
+              5aaba89325dfd04fecc47bff426f82"),
+            (50, false, "313233343030",
+             "3000"),
+            (65, false, "313233343030",
+             "308198023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca\
+              28e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e2d0707\
+              6ff30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd0\
+              4fecc47bff426f82023100e7bf25603e2d07076ff30b7a2abec473da8b11c572\
+              b35fc631991d5de62ddca7525aaba89325dfd04fecc47bff426f82"),
+            (80, false, "313233343030",
+             "30690284ffffffff12b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19\
+              daf2ca28e3113083ba8e4ae4cc45a0320abd3394f1c548d7023100e7bf25603e\
+              2d07076ff30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba893\
+              25dfd04fecc47bff426f82"),
+            (95, false, "313233343030",
+             "3067023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d70000023100e7bf25603e2d07\
+              076ff30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325df\
+              d04fecc47bff426f82"),
+            (110, false, "313233343030",
+             "3065023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7043100e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f82"),
+            (125, false, "313233343030",
+             "3036090180023100e7bf25603e2d07076ff30b7a2abec473da8b11c572b35fc6\
+              31991d5de62ddca7525aaba89325dfd04fecc47bff426f82"),
+            (140, false, "313233343030",
+             "3065023012b30abef6b5476fe6b612ae557c0425661e26b44b1bfe19daf2ca28\
+              e3113083ba8e4ae4cc45a0320abd3394f1c548d7023101e7bf25603e2d07076f\
+              f30b7a2abec473da8b11c572b35fc631991d5de62ddca7525aaba89325dfd04f\
+              ecc47bff426f82"),
+            (155, false, "313233343030",
+             "3036020101023100ffffffffffffffffffffffffffffffffffffffffffffffff\
+              c7634d81f4372ddf581a0db248b0a77aecec196accc52973"),
+            (170, false, "313233343030",
+             "30080201ff090380fe01"),
+            (185, false, "313233343030",
+             "3066023100ffffffffffffffffffffffffffffffffffffffffffffffffc7634d\
+              81f4372ddf581a0db248b0a77aecec196accc52972023100ffffffffffffffff\
+              ffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77a\
+              ecec196accc52973"),
+            (200, false, "313233343030",
+             "3038023100ffffffffffffffffffffffffffffffffffffffffffffffffc7634d\
+              81f4372ddf581a0db248b0a77aecec196accc52974090380fe01"),
+            (215, false, "313233343030",
+             "3066023100ffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffeffffffff000000000000000100000000023100ffffffffffffffff\
+              ffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77a\
+              ecec196accc52973"),
+            (230, true, "3130333633303731",
+             "3065023100e14f41a5fc83aa4725a9ea60ab5b0b9de27f519af4b557a601f1fe\
+              e0243f8eee5180f8c531414f3473f4457430cb7a2602301047ed2bf1f98e3ce9\
+              3e8fdbdc63cc79f238998fee74e1bb6cd708694950bbffe3945066064da043f0\
+              4d7083d0a596ec"),
+            (245, true, "323332393736343130",
+             "306402300b13b8fd10fa7b42169137588ad3f557539a4e9206f3a1f1fe9202b0\
+              690defded2be18147f5b2da9285c0e7349735ea302300478ad317b22a247bf93\
+              34719b4c8ee84acf134515db77e6141c75d08961e1e51eaca29836744103de0f\
+              6a4c798d3eeb"),
+            (260, true, "323535333538333333",
+             "3065023054bf7adc8548e7cae270e7b097f16b5e315158d21b0e652ce1cfe4b3\
+              3126ba4a65bf227b4cddcaf22d33d82478937b20023100bfc1b8f1d02846a42f\
+              31e1bd10ba334065459f712a3bbc76005d6c6488889f88c0983f4834d0bf2249\
+              dbf0a6db760701"),
+            (275, true, "32343735353135303630",
+             "306402303730dfd0985de77decdd358a544b47f418d3fab42481530d5d514859\
+              894c6f23b729af72b44686058de29687b34b3b0c023065bdfaf0ac217a80b82e\
+              b09c9f59c5c8cfbf50a6eb979a8f5f63eab9bd38ee0938e4b23102112033b230\
+              a14ad2790e3f"),
+            (290, true, "32393332303032353932",
+             "30630230447539941dc350767fc841083d25d9247a0807e1e22e0bb9d94f504f\
+              721981b413d521efbd75e4fe831ee26338cf3de3022f395ab27ea782cee4be53\
+              e06c7616bbd41d6926b18d219d75d5979f13cba2f52101019b0ec0a41ffdbf29\
+              ef73ddba70"),
+            (305, true, "34383037363230373132",
+             "30660231009b66d122a315095b2b66ccb97272c476a2d760e827fdea05732d63\
+              4df3d066569c984dd941aad5f5dec4c2e1b7b94a0002310096c32403c85bc3d0\
+              ee87f96a600182796dce53d54d7467ae660a42b87bb70792f14650ac28a5fa47\
+              ce9ca4d3b2c25878"),
+        ]),
+        ("4bf4e52f958427ebb5915fb8c9595551b4d3a3fdab67badd9d6c3093f425ba43\
+          630df71f42f0eb7ceaa94d9f6448a85d",
+         "d30331588249fd2fdc0b309ec7ed8481bc16f27800c13d7db700fc82e1b1c854\
+          5aa0c0d3b56e3bfe789fc18a916887c2", &[
+            (315, true, "313233343030",
+             "304d0218389cb27e0bc8d21fa7e5f24cb74f58851313e696333ad68b023100ff\
+              ffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf58\
+              1a0db248b0a77aecec196accc52970"),
+        ]),
+        ("a8380cd35026e13bf87be693cdb6e75a82d765b4019b529e8d277c4af6c9db27\
+          ebb5d3f86e88add9d5b61186f04c83a9",
+         "92a187507c737325d2cc624acef3cd036bfa99e0c1518be65c88bb51f900f941\
+          23acabad81d15130d3ade7ff7e4364e1", &[
+            (319, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100b6b681dc484f4f020f\
+              d3f7e626d88edc6ded1b382ef3e143d60887b51394260832d4d8f2ef70458f9f\
+              a90e38c2e19e4f"),
+        ]),
+        ("e2f87f72e3c66c73037fe77607d42ad2d9c4cc159893b4b9b8b0365d3a7766db\
+          e8678b02e2b68f58e5a4f7681061a390",
+         "e38f2142818542bef6b2bc3a2c4f43c95e5259d6bd5401531378c7ca125a1f6c\
+          c609d4fadfc5c9a99358ee77ff780c8d", &[
+            (323, false, "313233343030",
+             "3036023100ffffffffffffffffffffffffffffffffffffffffffffffffc7634d\
+              81f4372ddf581a0db248b0a77aecec196accc52975020103"),
+        ]),
+        ("fc6984dd6830d1485fb2581a45a791d8dca2c727c73d3d44c89f0082c1868af5\
+          ca74b4ca4ae22802640a9ebfe8c7ae12",
+         "998d63a5b5ad1b72b899f0b132e4952aaa19d41fdeea48b1ed6b8358dd1db207\
+          fd66e01453ad40f67b836adc802d5fe8", &[
+            (327, true, "313233343030",
+             "3041020d1033e67e37b32b445580bf4efb02302ad52ad52ad52ad52ad52ad52a\
+              d52ad52ad52ad52ad52ad5215c51b320e460542f9cc38968ccdf4263684004eb\
+              79a452"),
+        ]),
+        ("bd3d91f003e18adbea73079d4eba23b91fc17fcec14c9eb15a193fbc9ca39c8c\
+          747cd7a2c9623e05dd587ccbb8ab4c44",
+         "3adb0a0706aa5ea7a68042082fccefc979612a7a1a3d694b00793b03f89bff86\
+          6a8b97c8e77990c29360ce795036c764", &[
+            (331, true, "313233343030",
+             "3035023055555555555555555555555555555555555555555555555542766f2b\
+              5167b9f51d5e0490c2e58d28f9a40878eeec6326020101"),
+        ]),
+        ("941e6cfa356e572dcccaeb594b06955d99dc4bf07958fc98ffa17de11c7521bf\
+          2c7aa8ff260952fcb7aac078ede67b47",
+         "90a78a0296b041a10f003df1998da4cc4a1614ebcbf5d239431f33d90d3023ed\
+          c1802e8db6dabcbae67cc314da2aabab", &[
+            (335, true, "313233343030",
+             "306402307fffffffffffffffffffffffffffffffffffffffffffffffe3b1a6c0\
+              fa1b96efac0d06d9245853bd76760cb5666294b902307fffffffffffffffffff\
+              ffffffffffffffffffffffffffffe3b1a6c0fa1b96efac0d06d9245853bd7676\
+              0cb5666294ba"),
+        ]),
+        ("ee24ab8a34d05af684939357f32759cc5a14f3c717529a20aea8e0c5965d8a41\
+          e68925f688471994b72021ba51b28c09",
+         "0a55693c92ad0cbae9edcf515e2b4c060b888d82c81e4a3b6a173b62ed04a46f\
+          a95db1a2f3949980fba2e371263c4fa9", &[
+            (339, true, "313233343030",
+             "3065023055555555555555555555555555555555555555555555555542766f2b\
+              5167b9f51d5e0490c2e58d28f9a40878eeec6326023100aaaaaaaaaaaaaaaaaa\
+              aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa84ecde56a2cf73ea3abc092185cb1a51f3\
+              4810f1ddd8c64d"),
+        ]),
+        ("f421541311c94fdd79fc298f8ab1a3adfd08029fdad439a94d4cea11f7e799bc\
+          439609f2fb7be3f349d55e484d0a0d36",
+         "b35330bbdbec1e75f2984483d96bf210d722c1830292ffc35a2f6a21a4b50519\
+          f565f024bbccc97228a2f8ad8fadc0d5", &[
+            (343, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100b39af4a81ee4ae7906\
+              4ed80f27e1432e84845f15ece399f2a43d2505a0a8c72c5731f4fd967420b100\
+              0e3f75502ed7b7"),
+        ]),
+        ("040771e3390216fed2c6208bdf5bfea83ab1915b166e626569f12efd410a39b7\
+          e7c76f70f0012843a26debf4ccc33dda",
+         "e5bc5f7e62d054eac31cd022afdb71b7c638f24c30cbad0ef35ed2fc9917f356\
+          e9c3f04391b21d1035274b81537fcbf3", &[
+            (347, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100bb51cd3ba8eb201f53\
+              ddb4e34e08c0ff7dff9378106784d798d5a3440bd6dc34be3a0eaef8776619a0\
+              c97fefb15720b3"),
+        ]),
+        ("f052dfc27bf8a6d36f3739f239b981f5b53fe08d999ec683b01e43e759615620\
+          6ba08b8b9f59229e2fbdce05f1e40f99",
+         "90f0fdfb7029f9b3e8c6144dad0339208b7cdcb3820a554259db9d27afdd18f4\
+          a750296c59bad6b62df076f90d53be0d", &[
+            (351, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100942848586b534105dd\
+              d1ca77df72e1251140f412e97b62afbf85d4822309176b5965453dee3fab709e\
+              14156b3dfcecca"),
+        ]),
+        ("4d3fc5dcfaf741113cda3ce2f8dff4c912143e4d36314c361d7ed5656b68448b\
+          cca114ba9e8124281234660b7726ddcd",
+         "680ddfef7ea07bfbcede10803d38d7211631ca11466078819eb66e11921ab7ff\
+          a3c4560c732e77595fd408e917dd9afc", &[
+            (355, true, "313233343030",
+             "306402307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd02304c7d219db9af94ce7fff\
+              ffffffffffffffffffffffffffffef15cf1058c8d8ba1e634c4122db95ec1fac\
+              d4bb13ebf09a"),
+        ]),
+        ("bc26eec95e26c980bc0334264cbcfc26b897c3571c96ce9ab2a67b49bb0f26a6\
+          272fdc27806d7a4c572ae0f78149f1f3",
+         "c8af5f41b99d2066018165513fb3b55e4255dcd0659647ed55e1e2602cae4efb\
+          d6eae1dfe2ff63e2c748d4acc7430139", &[
+            (359, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100a27627627627627627\
+              6276276276276276276276276276273d7228d4f84b769be0fd57b97e4c1ebcae\
+              9a5f635e80e9df"),
+        ]),
+        ("41fa8765b19d3108031e28c9a781a385c9c10b2bfd42e6437e5c4bd711cf2a03\
+          1750847d17a82f9376a30ae182a6d6e7",
+         "1c20af96324147d4155a4d0c867ca8e36eba204fbed2087e0fcbdc8baabe07bb\
+          3123f9f7259e771cd9f1ad17d1a23787", &[
+            (363, true, "313233343030",
+             "306502307fffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              fffffffffffffffffffffffffffffffffffffffd023100dfea06865526cea11c\
+              0f9eb9512b41fa9581d0f6cb7db9680336151dce79de818cdf33c879da322740\
+              416d1e5ae532fa"),
+        ]),
+        ("6e3c68be53aade81ef89e096d841e2845a23331e7ec8a6a839d58d07fa016c09\
+          73ed75de4f99177bfdc74db566e9d15a",
+         "4972ea08e577ce1f61c13a6ca1bad1deef2982ee01a2826f002b769f2c46098d\
+          3baff068a405d09ca3840d2fafe4e46e", &[
+            (367, false, "313233343030",
+             "3065023101000000000000000000000000000000000000000000000000000000\
+              0000000000000000000000000000000000000000000230333333333333333333\
+              33333333333333333333333333333327e0a919fda4a2c644d202bd41bcee4bc8\
+              fc05155c276eb0"),
+        ]),
+        ("892dac0e700fc29d1802d9a449a6f56b2172cb1b7d881013cd3b31c0edb052f2\
+          d340c8995a4477bcb9225fec15667233",
+         "cc6c34ae17445444516fd8fd22ee83f79eb0771ebff6677ac5d4e089f87a1c72\
+          df957acb24492adcd7c3816b8e0c75b1", &[
+            (371, true, "313233343030",
+             "3064023008d999057ba3d2d969260045c55b97f089025959a6f434d651d207d1\
+              9fb96e9e4fe0e86ebe0e64f85b96a9c75295df61023066666666666666666666\
+              66666666666666666666666666664fc15233fb49458c89a4057a8379dc9791f8\
+              0a2ab84edd61"),
+        ]),
+        ("34d74ec088bab6c6323968d1f468993812f690d6edca5b97604d718e12b8cdfd\
+          d96d42e57d33afe312f0ee3c3d0a13f7",
+         "86f4922bb2c13bdf7752a3ecb69393e997bd65461c46867ebeef6296b23f2c56\
+          df63acfde648f3f5002dbc239ffd1582", &[
+            (375, true, "313233343030",
+             "3065023100aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741\
+              e082542a385502f25dbf55296c3a545e3872760ab70230555555555555555555\
+              55555555555555555555555555555542766f2b5167b9f51d5e0490c2e58d28f9\
+              a40878eeec6326"),
+        ]),
+        ("2783c1be922fce155864ecb41d0a316e193a55843e80192f1fe556772f3debd0\
+          4b9fc93c27bc6f353938886a40441994",
+         "1a352cec336946424fa3c208ea7105f5549edde8688abd305344bf4f66dda7ea\
+          bcda6f8557c9af88109804d702e9670b", &[
+            (379, true, "313233343030",
+             "3066023100aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741\
+              e082542a385502f25dbf55296c3a545e3872760ab7023100db6db6db6db6db6d\
+              b6db6db6db6db6db6db6db6db6db6db6aae76701acc1950894a89e068772d8b2\
+              81eef136f8a8fef5"),
+        ]),
+        ("aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a38\
+          5502f25dbf55296c3a545e3872760ab7",
+         "c9e821b569d9d390a26167406d6d23d6070be242d765eb831625ceec4a0f473e\
+          f59f4e30e2817e6285bce2846f15f1a0", &[
+            (383, false, "313233343030",
+             "3065023100f9b127f0d81ebcd17b7ba0ea131c660d340b05ce557c82160e0f79\
+              3de07d38179023942871acb7002dfafdfffc8deace0230249249249249249249\
+              2492492492492492492492492492491c7be680477598d6c3716fabc13dcec86a\
+              fd2833d41c2a7e"),
+        ]),
+        ("29bdb76d5fa741bfd70233cb3a66cc7d44beb3b0663d92a8136650478bcefb61\
+          ef182e155a54345a5e8e5e88f064e5bc",
+         "9a525ab7f764dad3dae1468c2b419f3b62b9ba917d5e8c4fb1ec47404a3fc764\
+          74b2713081be9db4c00e043ada9fc4a3", &[
+            (387, true, "313233343030",
+             "30650230234503fcca578121986d96be07fbc8da5d894ed8588c6dbcdbe974b4\
+              b813b21c52d20a8928f2e2fdac14705b0705498c023100cd7b9b766b97b53d1a\
+              80fc0b760af16a11bf4a59c7c367c6c7275dfb6e18a88091eed3734bf5cf41b3\
+              dc6fecd6d3baaf"),
+        ]),
+        ("ffffffffaa63f1a239ac70197c6ebfcea5756dc012123f82c51fa874d66028be\
+          00e976a1080606737cc75c40bdfe4aac",
+         "acbd85389088a62a6398384c22b52d492f23f46e4a27a4724ad55551da5c4834\
+          38095a247cb0c3378f1f52c3425ff9f1", &[
+            (391, true, "4d657373616765",
+             "30650230441800ea9377c27865be000ad008eb3d7502bdd105824b26d15cf3d0\
+              6452969a9d0607a915a8fe989215fc4d61af6e05023100dce29faa5137f75ad7\
+              7e03918c8ee6747cc7a39b0a69f8b915654cac4cf4bfd9c87cc46ae1631b5c6b\
+              aebd4fc08ff8fd"),
+        ]),
+        ("1099bb45100f55f5a85cca3de2b3bd5e250f4f6fad6631a3156c2e52a33d7d61\
+          5dd279f79f8b4baff7c713ac00000000",
+         "e6c9b736a8929f2ed7be0c753a54cbb48b8469e0411eaf93a4a82459ba0b681b\
+          ba8f5fb383b4906d4901a3303e2f1557", &[
+            (395, true, "4d657373616765",
+             "3065023100d4a8f3b0b4d3a5769e3a0bbc644b35f1d509355ed1fe401e170f66\
+              7b661f693b32598e8c143a817a958982845042bb48023004cc07578bbd1981db\
+              f6e8a97a354c98d41b8b6f6e8a2c2b1763c7c2a29d79e24f8476075c9aed9aec\
+              6c64dff50461ae"),
+        ]),
+        ("2b089edd754169010145f263f334fc167cc19dae8225970ae19cc8cb7ec73593\
+          d6a465c370f5478b0e539d69",
+         "d1951d597b56a67345acb25809581f07cd0eb78d9538a3f8a65f300e68a1eb78\
+          507df76de650e8f8ee63a5f0c5687c98", &[
+            (399, true, "4d657373616765",
+             "3065023100913eecc559b3cf7108a65d6cc3076bfdf36c6f94dcc6693d066904\
+              70f34a2e81564241e1de5f5f51421de30af467f10f0230649bd3717244e8ef3c\
+              6b0eda983f84dca5ea86d1bec15386b9c473ec43a8cd0ba558eee819f791d9ff\
+              9272b9afd59551"),
+        ]),
+        ("fb01baad5f0b8f79b9cd104d12aab9310146add7d6b4c022d87ae6711178b94d\
+          618ca7b3af13854b1c588879e877b336",
+         "208b3f5ad3b3937acc9d606cc5ececab4a701f75ed42957ea4d7858d33f5c26c\
+          6ae20a9cccda56996700d6b4", &[
+            (403, true, "4d657373616765",
+             "3065023100e19a4646f0ed8a271fe86ba533f8be4fd81bbf4674716f668efa89\
+              a40cac51eec2a6cfbd92327d25efe91ca4ff712bc502304a86b2e8e12378e633\
+              dec2691e3b1eed4e932cc48b28e45fa3d464cc0e948c02cc9decf2bb43b25937\
+              fcf37e9ad86ef0"),
+        ]),
+    ];
+
+    fn check_wycheproof(c: &'static Curve, h: HashFunc,
+                        groups: &[(&str, &str, &[(uint, bool, &str, &str)])]) {
+        for &(x, y, cases) in groups.iter() {
+            let public_key = PublicKey { curve: c, x: int(x), y: int(y) };
+            for &(id, valid, msg, sig) in cases.iter() {
+                let hash = digest(h, msg.from_hex().unwrap());
+                let sig = sig.from_hex().unwrap();
+                assert!(verify_asn1(&public_key, hash, sig) == valid, "{}: case {}", c.name, id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wycheproof() {
+        check_wycheproof(p256(), SHA256, P256Wycheproof);
+        check_wycheproof(p384(), SHA384, P384Wycheproof);
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        for &c in [p256(), p384(), p521()].iter() {
+            let key = generate_key(c, &mut Reader).unwrap();
+            let hash = digest(SHA256, bytes!("testing"));
+            let (r, s) = sign(&mut Reader, &key, hash).unwrap();
+            assert!(verify(&key.public_key, hash, &r, &s));
+            // hedged signatures differ
+            let (r2, _) = sign(&mut Reader, &key, hash).unwrap();
+            assert!(r != r2);
+
+            let mut bad = hash.clone();
+            bad[0] ^= 0xff;
+            assert!(!verify(&key.public_key, bad, &r, &s));
+
+            // r and s must be in [1, n-1]
+            let n = c.params().n;
+            assert!(!verify(&key.public_key, hash, &Int::new(0), &s));
+            assert!(!verify(&key.public_key, hash, &r, &Int::new(0)));
+            assert!(!verify(&key.public_key, hash, &(r + n), &s));
+            assert!(!verify(&key.public_key, hash, &r, &(s + n)));
+            assert!(!verify(&key.public_key, hash, &-r, &s));
+
+            let sig = sign_asn1(&mut Reader, &key, hash).unwrap();
+            assert!(verify_asn1(&key.public_key, hash, sig));
+            let sig = sign_raw(&mut Reader, &key, hash).unwrap();
+            assert_eq!(sig.len(), 2 * c.n.byte_len());
+            assert!(verify_raw(&key.public_key, hash, sig));
+            assert!(!verify_raw(&key.public_key, hash, sig.slice_from(1)));
+
+            // hashes longer than the order are truncated
+            let long = digest(SHA512, bytes!("testing"));
+            let (r, s) = sign_deterministic(&key, SHA512, long).unwrap();
+            assert!(verify(&key.public_key, long, &r, &s));
+            let (r, s) = sign_deterministic(&key, SHA1, hash).unwrap();
+            assert!(verify(&key.public_key, hash, &r, &s));
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let key = &rfc6979_keys()[0];
+        let hash = digest(SHA256, bytes!("sample"));
+        let (r, s) = sign_deterministic(key, SHA256, hash).unwrap();
+
+        // a public key off the curve, or on another curve
+        let mut bad = key.public_key.clone();
+        bad.y = bad.y + Int::new(1);
+        assert!(!verify(&bad, hash, &r, &s));
+        let mut bad = key.public_key.clone();
+        bad.curve = p384();
+        assert!(!verify(&bad, hash, &r, &s));
+        let mut bad = key.public_key.clone();
+        bad.x = Int::new(0);
+        bad.y = Int::new(0);
+        assert!(!verify(&bad, hash, &r, &s));
+
+        // private scalars outside [1, n-1]
+        let mut bad = key.clone();
+        bad.d = Int::new(0);
+        assert!(sign(&mut Reader, &bad, hash).is_err());
+        bad.d = p256().params().n;
+        assert!(sign_deterministic(&bad, SHA256, hash).is_err());
+    }
+
+    #[test]
+    fn test_signer() {
+        let key = &rfc6979_keys()[1];
+        let hash = digest(SHA384, bytes!("sample"));
+        let mut rand = Reader;
+        let sig = key.sign(&mut rand as &mut IoReader, hash, &SHA384 as &SignerOpts).unwrap();
+        assert!(verify_asn1(&key.public_key, hash, sig));
+        let public = (key as &Signer).public();
+        assert!(public.as_any().as_ref::<PublicKey>() == Some(&key.public_key));
+    }
+}
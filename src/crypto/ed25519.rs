@@ -0,0 +1,477 @@
+// The Ed25519 signature algorithm, as specified in RFC 8032, after Go's
+// crypto/ed25519, including the Ed25519ph and Ed25519ctx variants.
+//
+// Verification comes in two modes. Strict follows RFC 8032, Section
+// 5.1.7, rejecting non-canonical encodings of the public key and of R and
+// checking the cofactorless equation. ZIP215 follows the rules of Zcash's
+// ZIP 215, accepting any encoding of a valid point and checking the
+// cofactored equation, so that all implementations agree on which
+// signatures are valid, including in batch verification. Both reject
+// non-canonical S.
+
+use std::any::Any;
+use std::io::IoResult;
+use hash::Hash;
+use super::{HashFunc, SignerOpts, SHA512};
+use super::edwards25519::Point;
+use super::edwards25519::scalar::Scalar;
+use super::sha2::{Sha2, Sha_512};
+
+/// The size, in bytes, of public keys as used in this package.
+pub static PublicKeySize: uint = 32;
+/// The size, in bytes, of private keys as used in this package.
+pub static PrivateKeySize: uint = 64;
+/// The size, in bytes, of signatures generated and verified by this
+/// package.
+pub static SignatureSize: uint = 64;
+/// The size, in bytes, of private key seeds. These are the private key
+/// representations used by RFC 8032.
+pub static SeedSize: uint = 32;
+
+static DomPrefix: &'static [u8] = bytes!("SigEd25519 no Ed25519 collisions");
+
+/// An Ed25519 public key.
+#[deriving(Clone, Eq)]
+pub struct PublicKey {
+    key: ~[u8],
+}
+
+impl PublicKey {
+    /// Returns the public key encoded by the PublicKeySize bytes of b. The
+    /// encoding is only checked when it is used.
+    pub fn new(b: &[u8]) -> Result<PublicKey, ~str> {
+        if b.len() != PublicKeySize {
+            return Err(format!("crypto/ed25519: bad public key length: {}", b.len()));
+        }
+        Ok(PublicKey { key: b.to_owned() })
+    }
+
+    /// Returns the encoding of the key.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.clone()
+    }
+}
+
+impl super::PublicKey for PublicKey {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// An Ed25519 private key: the RFC 8032 seed followed by the public key.
+#[deriving(Clone)]
+pub struct PrivateKey {
+    key: ~[u8],
+}
+
+impl PrivateKey {
+    /// Returns the private key encoded by the PrivateKeySize bytes of b,
+    /// the seed followed by the public key, as returned by bytes.
+    pub fn new(b: &[u8]) -> Result<PrivateKey, ~str> {
+        if b.len() != PrivateKeySize {
+            return Err(format!("crypto/ed25519: bad private key length: {}", b.len()));
+        }
+        Ok(PrivateKey { key: b.to_owned() })
+    }
+
+    /// Calculates a private key from a seed. Fails if seed is not
+    /// SeedSize bytes long. RFC 8032's private keys correspond to seeds
+    /// in this package.
+    pub fn from_seed(seed: &[u8]) -> PrivateKey {
+        if seed.len() != SeedSize {
+            fail!("crypto/ed25519: bad seed length: {}", seed.len());
+        }
+        let h = sha512([seed]);
+        let s = Scalar::set_bytes_with_clamping(h.slice_to(32));
+        let a = Point::scalar_base_mult(&s);
+        PrivateKey { key: seed.to_owned() + a.bytes() }
+    }
+
+    /// Returns the private key seed corresponding to the key.
+    pub fn seed(&self) -> ~[u8] {
+        self.key.slice_to(SeedSize).to_owned()
+    }
+
+    /// Returns the public key corresponding to the key.
+    pub fn public(&self) -> PublicKey {
+        PublicKey { key: self.key.slice_from(SeedSize).to_owned() }
+    }
+
+    /// Returns the encoding of the key: the seed followed by the public
+    /// key.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.clone()
+    }
+}
+
+impl super::PrivateKey for PrivateKey {}
+
+impl super::Signer for PrivateKey {
+    fn public(&self) -> ~super::PublicKey {
+        ~PublicKey { key: self.key.slice_from(SeedSize).to_owned() } as ~super::PublicKey
+    }
+
+    /// Signs the given message with the key. rand is ignored, as Ed25519
+    /// signatures are deterministic.
+    ///
+    /// If opts.hash_func() is SHA512, the pre-hashed variant Ed25519ph is
+    /// used and message is expected to be a SHA-512 hash; otherwise
+    /// opts.hash_func() must be None (the message is not hashed), and
+    /// plain Ed25519 is used. opts can be an Options to select Ed25519ctx
+    /// or to provide a context string for Ed25519ph.
+    fn sign(&self, _rand: &mut Reader, message: &[u8], opts: &SignerOpts)
+            -> Result<~[u8], ~str> {
+        match opts.as_any().as_ref::<Options>() {
+            Some(o) => sign_with_options(self, message, o),
+            None => {
+                let o = Options { hash: opts.hash_func(), context: ~[], mode: Strict };
+                sign_with_options(self, message, &o)
+            }
+        }
+    }
+}
+
+/// The rules used to verify a signature.
+#[deriving(Eq, Clone, Show)]
+pub enum VerificationMode {
+    /// The rules of RFC 8032, Section 5.1.7, with the canonical encodings
+    /// of Section 5.1.3.
+    Strict,
+    /// The rules of ZIP 215, which accept non-canonical point encodings
+    /// and check the cofactored equation.
+    ZIP215,
+}
+
+/// Options for the Ed25519 variants. It can be used with
+/// PrivateKey::sign, sign_with_options or verify_with_options.
+#[deriving(Clone)]
+pub struct Options {
+    /// None for Ed25519 or Ed25519ctx, and SHA512 for Ed25519ph.
+    pub hash: Option<HashFunc>,
+    /// The context string for Ed25519ctx or Ed25519ph, at most 255
+    /// bytes. It must be empty for plain Ed25519.
+    pub context: ~[u8],
+    /// The verification rules; ignored when signing.
+    pub mode: VerificationMode,
+}
+
+impl SignerOpts for Options {
+    fn hash_func(&self) -> Option<HashFunc> {
+        self.hash
+    }
+
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+fn sha512(parts: &[&[u8]]) -> ~[u8] {
+    let mut h = Sha2::new(Sha_512);
+    for p in parts.iter() {
+        h.write(*p).unwrap();
+    }
+    h.digest()
+}
+
+// Returns dom2(phflag, context) from RFC 8032, Section 5.1, which
+// prefixes the hashed inputs of the Ed25519ph and Ed25519ctx variants,
+// after checking the message and context against opts.
+fn dom(message: &[u8], opts: &Options) -> Result<~[u8], ~str> {
+    if opts.context.len() > 255 {
+        return Err(format!("crypto/ed25519: bad context length: {}", opts.context.len()));
+    }
+    let flag = match opts.hash {
+        Some(SHA512) => {
+            if message.len() != 64 {
+                return Err(format!("crypto/ed25519: bad Ed25519ph message hash length: {}",
+                                   message.len()));
+            }
+            1u8
+        }
+        None if opts.context.len() > 0 => 0,
+        None => return Ok(~[]),
+        Some(_) => return Err(~"crypto/ed25519: expected opts.hash None (unhashed message, \
+                                for standard Ed25519) or SHA512 (for Ed25519ph)"),
+    };
+    let mut d = DomPrefix.to_owned();
+    d.push(flag);
+    d.push(opts.context.len() as u8);
+    d.push_all(opts.context.as_slice());
+    Ok(d)
+}
+
+/// Generates a public/private key pair using entropy from rand.
+pub fn generate_key<R: Reader>(rand: &mut R) -> IoResult<(PublicKey, PrivateKey)> {
+    let mut seed = [0u8, ..32];
+    try!(rand.fill(seed));
+    let key = PrivateKey::from_seed(seed);
+    Ok((key.public(), key))
+}
+
+fn sign_with_dom(key: &PrivateKey, message: &[u8], dom: &[u8]) -> ~[u8] {
+    let (seed, public) = (key.key.slice_to(SeedSize), key.key.slice_from(SeedSize));
+    let h = sha512([seed]);
+    let s = Scalar::set_bytes_with_clamping(h.slice_to(32));
+    let prefix = h.slice_from(32);
+
+    let r = Scalar::set_uniform_bytes(sha512([dom, prefix, message]));
+    let big_r = Point::scalar_base_mult(&r).bytes();
+
+    let k = Scalar::set_uniform_bytes(sha512([dom, big_r.as_slice(), public, message]));
+    let big_s = Scalar::multiply_add(&k, &s, &r);
+
+    big_r + big_s.bytes()
+}
+
+/// Signs the message with the key and returns a signature.
+pub fn sign(key: &PrivateKey, message: &[u8]) -> ~[u8] {
+    sign_with_dom(key, message, [])
+}
+
+/// Signs the message with the key, using the variant selected by opts:
+/// Ed25519ph if opts.hash is SHA512, in which case message must be its
+/// SHA-512 hash; Ed25519ctx if opts.hash is None and opts.context is
+/// set; and plain Ed25519 otherwise.
+pub fn sign_with_options(key: &PrivateKey, message: &[u8], opts: &Options)
+                         -> Result<~[u8], ~str> {
+    let dom = try!(dom(message, opts));
+    Ok(sign_with_dom(key, message, dom))
+}
+
+fn verify_with_dom(public: &PublicKey, message: &[u8], sig: &[u8], dom: &[u8],
+                   mode: VerificationMode) -> bool {
+    if sig.len() != SignatureSize {
+        return false;
+    }
+    let a = match mode {
+        Strict => Point::set_canonical_bytes(public.key.as_slice()),
+        ZIP215 => Point::set_bytes(public.key.as_slice()),
+    };
+    let a = match a {
+        Some(a) => a,
+        None => return false,
+    };
+    let s = match Scalar::set_canonical_bytes(sig.slice_from(32)) {
+        Some(s) => s,
+        None => return false,
+    };
+    let big_r = sig.slice_to(32);
+    let k = Scalar::set_uniform_bytes(sha512([dom, big_r, public.key.as_slice(), message]));
+
+    // [S]B - [k]A = R
+    let r = Point::double_scalar_base_mult(&k, &a.negate(), &s);
+    match mode {
+        Strict => r.bytes().as_slice() == big_r,
+        ZIP215 => match Point::set_bytes(big_r) {
+            Some(big_r) => r.subtract(&big_r).mult_by_cofactor().is_identity() == 1,
+            None => false,
+        },
+    }
+}
+
+/// Reports whether sig is a valid signature of message by public, with
+/// the Strict rules.
+pub fn verify(public: &PublicKey, message: &[u8], sig: &[u8]) -> bool {
+    verify_with_dom(public, message, sig, [], Strict)
+}
+
+/// Reports whether sig is a valid signature of message by public, using
+/// the variant and the verification rules selected by opts, as for
+/// sign_with_options. An error is returned for invalid options or an
+/// invalid signature.
+pub fn verify_with_options(public: &PublicKey, message: &[u8], sig: &[u8], opts: &Options)
+                           -> Result<(), ~str> {
+    let dom = try!(dom(message, opts));
+    if !verify_with_dom(public, message, sig, dom, opts.mode) {
+        return Err(~"crypto/ed25519: invalid signature");
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use IoReader = std::io::Reader;
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use super::*;
+    use super::super::{HashFunc, SHA256, SHA512, Signer, SignerOpts, sign_message};
+    use super::super::rand::Reader;
+
+    fn hex(s: &str) -> ~[u8] {
+        s.from_hex().unwrap()
+    }
+
+    fn opts(hash: Option<HashFunc>, context: &[u8], mode: VerificationMode) -> Options {
+        Options { hash: hash, context: context.to_owned(), mode: mode }
+    }
+
+    #[test]
+    fn test_rfc8032() {
+        // RFC 8032, Section 7.1
+        let tests = [
+            ("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+             "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+             "",
+             "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+              5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"),
+            ("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+             "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+             "72",
+             "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+              085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"),
+            ("c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+             "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+             "af82",
+             "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+              18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"),
+            // TEST SHA(abc)
+            ("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+             "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+             "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+              2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+             "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b589\
+              09351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704"),
+        ];
+        for &(seed, public, msg, sig) in tests.iter() {
+            let key = PrivateKey::from_seed(hex(seed));
+            assert_eq!(key.public().bytes().to_hex(), public.to_owned());
+            assert_eq!(key.seed().to_hex(), seed.to_owned());
+            assert_eq!(key.bytes().to_hex(), seed.to_owned() + public);
+            let got = sign(&key, hex(msg));
+            assert_eq!(got.to_hex(), sig.to_owned());
+            let public = PublicKey::new(hex(public)).unwrap();
+            assert!(verify(&public, hex(msg), got));
+            assert!(verify_with_options(&public, hex(msg), got,
+                                        &opts(None, [], ZIP215)).is_ok());
+            let mut bad = got.clone();
+            bad[5] ^= 1;
+            assert!(!verify(&public, hex(msg), bad));
+            assert!(!verify(&public, hex(msg) + bytes!("!"), got));
+        }
+    }
+
+    #[test]
+    fn test_rfc8032_variants() {
+        // RFC 8032, Section 7.2, the first test with context "foo"
+        let key = PrivateKey::from_seed(
+            hex("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6"));
+        assert_eq!(key.public().bytes().to_hex(),
+                   ~"dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292");
+        let msg = hex("f726936d19c800494e3fdaff20b276a8");
+        let o = opts(None, bytes!("foo"), Strict);
+        let sig = sign_with_options(&key, msg, &o).unwrap();
+        assert_eq!(sig.to_hex(), ~"55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a\
+                                   8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d");
+        assert!(verify_with_options(&key.public(), msg, sig, &o).is_ok());
+        assert!(verify_with_options(&key.public(), msg, sig, &opts(None, bytes!("bar"), Strict))
+                .is_err());
+        assert!(!verify(&key.public(), msg, sig));
+
+        // RFC 8032, Section 7.3
+        let key = PrivateKey::from_seed(
+            hex("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42"));
+        let mut h = SHA512.new();
+        h.write(bytes!("abc")).unwrap();
+        let hashed = h.digest();
+        let o = opts(Some(SHA512), [], Strict);
+        let sig = sign_with_options(&key, hashed, &o).unwrap();
+        assert_eq!(sig.to_hex(), ~"98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+                                   31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406");
+        assert!(verify_with_options(&key.public(), hashed, sig, &o).is_ok());
+        assert!(!verify(&key.public(), hashed, sig));
+
+        // invalid options
+        assert!(sign_with_options(&key, bytes!("abc"), &o).is_err());
+        assert!(sign_with_options(&key, hashed, &opts(Some(SHA256), [], Strict)).is_err());
+        assert!(sign_with_options(&key, hashed, &opts(None, [0u8, ..256], Strict)).is_err());
+        assert!(verify_with_options(&key.public(), bytes!("abc"), sig, &o).is_err());
+    }
+
+    #[test]
+    fn test_verification_modes() {
+        // a key with a component of order 8, which only the cofactored
+        // equation of ZIP 215 accepts
+        let public = PublicKey::new(
+            hex("b502ff3d92e31d8190b4aa4ea0414005167fad089c4de9dac8a2fc850fed4f58")).unwrap();
+        let msg = hex("5a6361736800");
+        let sig = hex("ebc9798efcde71b20088d8c54407d596869868df64621437ec8488782d8670cb\
+                       2351e598298e0504c3453d6833e798e2a76b57b3a7007cd9121d11a77569fa0c");
+        assert!(!verify(&public, msg, sig));
+        assert!(verify_with_options(&public, msg, sig, &opts(None, [], ZIP215)).is_ok());
+
+        // non-canonical encodings of the identity as A and as R, with S = 0
+        let identity = "0100000000000000000000000000000000000000000000000000000000000000";
+        let non_canonical = "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+        let zero = "0000000000000000000000000000000000000000000000000000000000000000";
+        let tests = [
+            (non_canonical, identity, false),
+            (identity, non_canonical, false),
+            (identity, identity, true),
+        ];
+        for &(a, r, strict) in tests.iter() {
+            let public = PublicKey::new(hex(a)).unwrap();
+            let sig = hex(r) + hex(zero);
+            assert_eq!(verify(&public, bytes!("x"), sig), strict);
+            assert!(verify_with_options(&public, bytes!("x"), sig, &opts(None, [], ZIP215))
+                    .is_ok());
+        }
+
+        // S must be below the group order in both modes
+        let key = PrivateKey::from_seed([7u8, ..32]);
+        let sig = sign(&key, bytes!("x"));
+        let order = hex("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+        let mut s = 0u;
+        let mut bad = sig.clone();
+        for i in range(0, 32u) {
+            s += bad[32 + i] as uint + order[i] as uint;
+            bad[32 + i] = s as u8;
+            s >>= 8;
+        }
+        assert!(verify(&key.public(), bytes!("x"), sig));
+        assert!(!verify(&key.public(), bytes!("x"), bad));
+        assert!(verify_with_options(&key.public(), bytes!("x"), bad, &opts(None, [], ZIP215))
+                .is_err());
+        assert!(!verify(&key.public(), bytes!("x"), sig.slice_to(63)));
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let (public, key) = generate_key(&mut Reader).unwrap();
+        assert!(public == key.public());
+        assert_eq!(key.bytes().len(), PrivateKeySize);
+        let sig = sign(&key, bytes!("test message"));
+        assert_eq!(sig.len(), SignatureSize);
+        assert!(verify(&public, bytes!("test message"), sig));
+        assert!(PublicKey::new([0u8, ..31]).is_err());
+        assert!(PrivateKey::new(key.bytes().slice_to(32)).is_err());
+        assert!(PrivateKey::new(key.bytes()).unwrap().public() == public);
+    }
+
+    #[test]
+    fn test_signer() {
+        let key = PrivateKey::from_seed([1u8, ..32]);
+        let signer = &key as &Signer;
+        let mut rand = Reader;
+        let rand = &mut rand as &mut IoReader;
+
+        let public = signer.public();
+        assert!(public.as_any().as_ref::<PublicKey>() == Some(&key.public()));
+
+        let msg = bytes!("hello, world");
+        let no_hash = opts(None, [], Strict);
+        let sig = signer.sign(rand, msg, &no_hash as &SignerOpts).unwrap();
+        assert_eq!(sig, sign(&key, msg));
+        assert_eq!(sign_message(signer, rand, msg, &no_hash as &SignerOpts).unwrap(), sig);
+
+        let mut h = SHA512.new();
+        h.write(msg).unwrap();
+        let hashed = h.digest();
+        let sig = signer.sign(rand, hashed, &SHA512 as &SignerOpts).unwrap();
+        assert!(verify_with_options(&key.public(), hashed, sig,
+                                    &opts(Some(SHA512), [], Strict)).is_ok());
+        assert!(signer.sign(rand, hashed, &SHA256 as &SignerOpts).is_err());
+    }
+}
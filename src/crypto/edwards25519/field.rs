@@ -0,0 +1,323 @@
+// Constant-time arithmetic modulo 2^255 - 19.
+//
+// Elements are kept as sixteen signed 64-bit limbs of 16 bits each,
+// least significant first, in the style of TweetNaCl. Products of limbs
+// and their sums fit comfortably in 64 bits, so additions and
+// subtractions need no carrying, and a multiplication folds its upper
+// half back with the factor 38 = 2 * 19 before two carry passes.
+
+/// An element of the field of integers modulo 2^255 - 19. Its value is
+/// not necessarily reduced; bytes returns the canonical encoding.
+pub struct Element {
+    /// The limbs, least significant first. Exposed for the constants of
+    /// the curve; use set_bytes to construct elements.
+    pub l: [i64, ..16],
+}
+
+// 2^((p-1)/4), a square root of -1.
+static SqrtM1: Element = Element { l: [
+    0xa0b0, 0x4a0e, 0x1b27, 0xc4ee, 0xe478, 0xad2f, 0x1806, 0x2f43,
+    0xd7a7, 0x3dfb, 0x0099, 0x2b4d, 0xdf0b, 0x4fc1, 0x2480, 0x2b83,
+]};
+
+// Propagates the carries of o, leaving every limb in [0, 2^16) but for
+// the fold of the top carry into the lowest limb.
+fn carry(o: &mut [i64, ..16]) {
+    for i in range(0, 16u) {
+        let c = o[i] >> 16;
+        o[i] -= c << 16;
+        if i < 15 {
+            o[i + 1] += c;
+        } else {
+            o[0] += 38 * c;
+        }
+    }
+}
+
+// Returns all ones if cond is 1 and zero if cond is 0.
+fn mask(cond: u32) -> i64 {
+    -((cond & 1) as i64)
+}
+
+impl Element {
+    /// Returns zero.
+    pub fn zero() -> Element {
+        Element { l: [0, ..16] }
+    }
+
+    /// Returns one.
+    pub fn one() -> Element {
+        Element::from_u32(1)
+    }
+
+    /// Returns x as an element.
+    pub fn from_u32(x: u32) -> Element {
+        let mut e = Element::zero();
+        e.l[0] = (x & 0xffff) as i64;
+        e.l[1] = (x >> 16) as i64;
+        e
+    }
+
+    /// Returns the element encoded by the 32-byte little-endian b. The
+    /// most significant bit is ignored, and non-canonical values (those
+    /// not below the modulus) are accepted and reduced, as in RFC 7748.
+    /// Fails if b is not 32 bytes long.
+    pub fn set_bytes(b: &[u8]) -> Element {
+        if b.len() != 32 {
+            fail!("edwards25519: invalid field element input size");
+        }
+        let mut e = Element::zero();
+        for i in range(0, 16u) {
+            e.l[i] = b[2 * i] as i64 + (b[2 * i + 1] as i64 << 8);
+        }
+        e.l[15] &= 0x7fff;
+        e
+    }
+
+    /// Returns the canonical 32-byte little-endian encoding of v.
+    pub fn bytes(&self) -> ~[u8] {
+        let mut t = self.l;
+        carry(&mut t);
+        carry(&mut t);
+        carry(&mut t);
+        // Now t is below 2^255 + 2^16; subtract the modulus at most twice,
+        // keeping the difference whenever it does not borrow.
+        for _ in range(0, 2) {
+            let mut m = [0i64, ..16];
+            m[0] = t[0] - 0xffed;
+            for i in range(1, 15u) {
+                m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+                m[i - 1] &= 0xffff;
+            }
+            m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+            let borrow = ((m[15] >> 16) & 1) as u32;
+            m[14] &= 0xffff;
+            let mm = mask(1 - borrow);
+            for i in range(0, 16u) {
+                t[i] ^= mm & (t[i] ^ m[i]);
+            }
+        }
+        let mut out = ~[];
+        for i in range(0, 16u) {
+            out.push(t[i] as u8);
+            out.push((t[i] >> 8) as u8);
+        }
+        out
+    }
+
+    /// Returns v + u.
+    pub fn add(&self, u: &Element) -> Element {
+        let mut r = Element::zero();
+        for i in range(0, 16u) {
+            r.l[i] = self.l[i] + u.l[i];
+        }
+        r
+    }
+
+    /// Returns v - u.
+    pub fn subtract(&self, u: &Element) -> Element {
+        let mut r = Element::zero();
+        for i in range(0, 16u) {
+            r.l[i] = self.l[i] - u.l[i];
+        }
+        r
+    }
+
+    /// Returns -v.
+    pub fn negate(&self) -> Element {
+        Element::zero().subtract(self)
+    }
+
+    /// Returns v * u.
+    pub fn multiply(&self, u: &Element) -> Element {
+        let mut t = [0i64, ..31];
+        for i in range(0, 16u) {
+            for j in range(0, 16u) {
+                t[i + j] += self.l[i] * u.l[j];
+            }
+        }
+        // 2^256 = 38 mod p
+        for i in range(0, 15u) {
+            t[i] += 38 * t[i + 16];
+        }
+        let mut r = Element::zero();
+        for i in range(0, 16u) {
+            r.l[i] = t[i];
+        }
+        carry(&mut r.l);
+        carry(&mut r.l);
+        r
+    }
+
+    /// Returns v * v.
+    pub fn square(&self) -> Element {
+        self.multiply(self)
+    }
+
+    /// Returns 1/v, or zero if v is zero.
+    pub fn invert(&self) -> Element {
+        // v^(p-2), where p-2 = 2^255 - 21 has every bit set but 2 and 4.
+        let mut c = *self;
+        for i in range(0, 254u).rev() {
+            c = c.square();
+            if i != 2 && i != 4 {
+                c = c.multiply(self);
+            }
+        }
+        c
+    }
+
+    /// Returns v^((p-5)/8), with (p-5)/8 = 2^252 - 3.
+    pub fn pow22523(&self) -> Element {
+        let mut c = *self;
+        for i in range(0, 251u).rev() {
+            c = c.square();
+            if i != 1 {
+                c = c.multiply(self);
+            }
+        }
+        c
+    }
+
+    /// Returns 1 if v and u are equal and 0 otherwise.
+    pub fn equal(&self, u: &Element) -> u32 {
+        let (a, b) = (self.bytes(), u.bytes());
+        let mut d = 0u8;
+        for i in range(0, 32u) {
+            d |= a[i] ^ b[i];
+        }
+        ((d as u32 - 1) >> 31) & 1
+    }
+
+    /// Returns 1 if v is zero and 0 otherwise.
+    pub fn is_zero(&self) -> u32 {
+        self.equal(&Element::zero())
+    }
+
+    /// Returns 1 if v is negative and 0 otherwise. An element is negative
+    /// if the least significant bit of its canonical encoding is set.
+    pub fn is_negative(&self) -> u32 {
+        (self.bytes()[0] & 1) as u32
+    }
+
+    /// Returns |v|, the non-negative one of v and -v.
+    pub fn absolute(&self) -> Element {
+        Element::select(self.is_negative(), &self.negate(), self)
+    }
+
+    /// Returns a if cond is 1 and b if cond is 0.
+    pub fn select(cond: u32, a: &Element, b: &Element) -> Element {
+        let m = mask(cond);
+        let mut r = Element::zero();
+        for i in range(0, 16u) {
+            r.l[i] = b.l[i] ^ (m & (a.l[i] ^ b.l[i]));
+        }
+        r
+    }
+
+    /// Swaps a and b if cond is 1 and leaves them alone if cond is 0.
+    pub fn swap(cond: u32, a: &mut Element, b: &mut Element) {
+        let m = mask(cond);
+        for i in range(0, 16u) {
+            let t = m & (a.l[i] ^ b.l[i]);
+            a.l[i] ^= t;
+            b.l[i] ^= t;
+        }
+    }
+
+    /// Returns the non-negative square root of u/v and 1 if u/v is a
+    /// square. Otherwise it returns the non-negative square root of
+    /// sqrt(-1) * u/v and 0. If u is zero it returns zero and 1; if v is
+    /// zero and u is not, it returns zero and 0.
+    pub fn sqrt_ratio(u: &Element, v: &Element) -> (Element, u32) {
+        // r = (u * v^3) * (u * v^7)^((p-5)/8)
+        let v2 = v.square();
+        let uv3 = u.multiply(&v2.multiply(v));
+        let uv7 = uv3.multiply(&v2.square());
+        let r = uv3.multiply(&uv7.pow22523());
+
+        let check = v.multiply(&r.square());
+        let u_neg = u.negate();
+        let correct_sign_sqrt = check.equal(u);
+        let flipped_sign_sqrt = check.equal(&u_neg);
+        let flipped_sign_sqrt_i = check.equal(&u_neg.multiply(&SqrtM1));
+
+        let r = Element::select(flipped_sign_sqrt | flipped_sign_sqrt_i,
+                                &r.multiply(&SqrtM1), &r);
+        (r.absolute(), correct_sign_sqrt | flipped_sign_sqrt)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+
+    fn elem(s: &str) -> Element {
+        Element::set_bytes(s.from_hex().unwrap())
+    }
+
+    #[test]
+    fn test_arith() {
+        let two = Element::from_u32(2);
+        let three = Element::from_u32(3);
+        assert_eq!(two.add(&Element::one()).equal(&three), 1);
+        assert_eq!(two.subtract(&three).equal(&Element::one().negate()), 1);
+        assert_eq!(two.multiply(&three).equal(&Element::from_u32(6)), 1);
+        assert_eq!(three.invert().multiply(&three).equal(&Element::one()), 1);
+        assert_eq!(Element::zero().invert().is_zero(), 1);
+        assert_eq!(super::SqrtM1.square().equal(&Element::one().negate()), 1);
+        assert_eq!(Element::one().negate().is_negative(), 0);
+        assert_eq!(Element::from_u32(1).is_negative(), 1);
+        assert_eq!(Element::from_u32(0x12345678).bytes().slice_to(4).to_hex(), ~"78563412");
+
+        let mut a = two;
+        let mut b = three;
+        Element::swap(0, &mut a, &mut b);
+        assert_eq!(a.equal(&two), 1);
+        Element::swap(1, &mut a, &mut b);
+        assert_eq!(a.equal(&three), 1);
+        assert_eq!(b.equal(&two), 1);
+        assert_eq!(Element::select(1, &a, &b).equal(&three), 1);
+        assert_eq!(Element::select(0, &a, &b).equal(&two), 1);
+    }
+
+    #[test]
+    fn test_bytes() {
+        // p - 1, p, p + 1 and 2^255 - 1
+        let minus_one = "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+        assert_eq!(elem(minus_one).bytes().to_hex(), minus_one.to_owned());
+        assert_eq!(elem(minus_one).equal(&Element::one().negate()), 1);
+        let p = "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+        assert_eq!(elem(p).is_zero(), 1);
+        let p1 = "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+        assert_eq!(elem(p1).equal(&Element::one()), 1);
+        let max = "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+        assert_eq!(elem(max).bytes().to_hex(),
+                   ~"1200000000000000000000000000000000000000000000000000000000000000");
+    }
+
+    #[test]
+    fn test_sqrt_ratio() {
+        let four = Element::from_u32(4);
+        let (r, was_square) = Element::sqrt_ratio(&four, &Element::one());
+        assert_eq!(was_square, 1);
+        assert_eq!(r.equal(&Element::from_u32(2)), 1);
+
+        // 2 is not a square modulo p
+        let (r, was_square) = Element::sqrt_ratio(&Element::from_u32(2), &Element::one());
+        assert_eq!(was_square, 0);
+        assert_eq!(r.is_negative(), 0);
+
+        let (r, was_square) = Element::sqrt_ratio(&Element::zero(), &four);
+        assert_eq!((r.is_zero(), was_square), (1, 1));
+        let (r, was_square) = Element::sqrt_ratio(&four, &Element::zero());
+        assert_eq!((r.is_zero(), was_square), (1, 0));
+
+        // 9/4 = (3/2)^2
+        let (r, was_square) = Element::sqrt_ratio(&Element::from_u32(9), &four);
+        assert_eq!(was_square, 1);
+        assert_eq!(r.square().multiply(&four).equal(&Element::from_u32(9)), 1);
+    }
+}
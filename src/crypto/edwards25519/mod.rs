@@ -0,0 +1,282 @@
+// The prime order group of the twisted Edwards curve
+// -x² + y² = 1 + d x² y², with d = -121665/121666, over the field of
+// integers modulo 2^255 - 19, after Go's crypto/internal/edwards25519.
+// It is the group underlying Ed25519 (RFC 8032) and, through the
+// birational map to Curve25519, X25519 (RFC 7748).
+//
+// Points are kept in extended coordinates (X:Y:Z:T) with x = X/Z,
+// y = Y/Z and xy = T/Z, and combined with the unified addition formula
+// of Hisil, Wong, Carter and Dawson, which is complete on this curve.
+// Scalar multiplication is a constant-time Montgomery ladder.
+
+use crypto::edwards25519::field::Element;
+use crypto::edwards25519::scalar::Scalar;
+
+pub mod field;
+pub mod scalar;
+
+// d = -121665/121666
+static D: Element = Element { l: [
+    0x78a3, 0x1359, 0x4dca, 0x75eb, 0xd8ab, 0x4141, 0x0a4d, 0x0070,
+    0xe898, 0x7779, 0x4079, 0x8cc7, 0xfe73, 0x2b6f, 0x6cee, 0x5203,
+]};
+
+// 2 * d
+static D2: Element = Element { l: [
+    0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0,
+    0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406,
+]};
+
+// The canonical generator, with y = 4/5 and positive x.
+static BaseX: Element = Element { l: [
+    0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c,
+    0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169,
+]};
+static BaseY: Element = Element { l: [
+    0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+    0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+]};
+static BaseT: Element = Element { l: [
+    0xdda3, 0xa5b7, 0x8ab3, 0x6dde, 0x52f5, 0x7751, 0x9f80, 0x20f0,
+    0xe37d, 0x64ab, 0x4e8e, 0x66ea, 0x7665, 0xd78b, 0x5f0f, 0x6787,
+]};
+
+/// A point on the edwards25519 curve.
+pub struct Point {
+    x: Element,
+    y: Element,
+    z: Element,
+    t: Element,
+}
+
+impl Point {
+    /// Returns the identity element, (0, 1).
+    pub fn identity() -> Point {
+        Point { x: Element::zero(), y: Element::one(), z: Element::one(), t: Element::zero() }
+    }
+
+    /// Returns the canonical generator, the base point of Ed25519.
+    pub fn generator() -> Point {
+        Point { x: BaseX, y: BaseY, z: Element::one(), t: BaseT }
+    }
+
+    // Decodes a point as specified in RFC 8032, Section 5.1.3, optionally
+    // skipping the checks that reject non-canonical encodings.
+    fn decode(b: &[u8], canonical: bool) -> Option<Point> {
+        if b.len() != 32 {
+            return None;
+        }
+        let y = Element::set_bytes(b);
+        if canonical {
+            let mut yb = b.to_owned();
+            yb[31] &= 0x7f;
+            if y.bytes() != yb {
+                return None;
+            }
+        }
+
+        // -x² + y² = 1 + dx²y²
+        // x² + dx²y² = x²(dy² + 1) = y² - 1
+        // x² = (y² - 1) / (dy² + 1)
+        let yy = y.square();
+        let u = yy.subtract(&Element::one());
+        let v = yy.multiply(&D).add(&Element::one());
+        let (x, was_square) = Element::sqrt_ratio(&u, &v);
+        if was_square == 0 {
+            return None;
+        }
+
+        // Select the negative square root if the sign bit is set.
+        let sign = (b[31] >> 7) as u32;
+        if canonical && sign == 1 && x.is_zero() == 1 {
+            return None;
+        }
+        let x = Element::select(sign, &x.negate(), &x);
+        Some(Point { x: x, y: y, z: Element::one(), t: x.multiply(&y) })
+    }
+
+    /// Returns the point encoded by the 32 bytes of b, or None if b is
+    /// not a valid encoding. Like most implementations, and as ZIP 215
+    /// requires, it accepts the non-canonical encodings of valid points:
+    /// those with a y coordinate not below the modulus, and those of
+    /// points with x = 0 and the sign bit set.
+    pub fn set_bytes(b: &[u8]) -> Option<Point> {
+        Point::decode(b, false)
+    }
+
+    /// Returns the point encoded by the 32 bytes of b, or None if b is
+    /// not the canonical encoding of a point, following the decoding
+    /// rules of RFC 8032, Section 5.1.3.
+    pub fn set_canonical_bytes(b: &[u8]) -> Option<Point> {
+        Point::decode(b, true)
+    }
+
+    /// Returns the canonical 32-byte encoding of v, as specified in
+    /// RFC 8032, Section 5.1.2.
+    pub fn bytes(&self) -> ~[u8] {
+        let zinv = self.z.invert();
+        let x = self.x.multiply(&zinv);
+        let y = self.y.multiply(&zinv);
+        let mut out = y.bytes();
+        out[31] |= (x.is_negative() as u8) << 7;
+        out
+    }
+
+    /// Returns v + q.
+    pub fn add(&self, q: &Point) -> Point {
+        // "Twisted Edwards Curves Revisited", Section 3.1, with a = -1.
+        let a = self.y.subtract(&self.x).multiply(&q.y.subtract(&q.x));
+        let b = self.y.add(&self.x).multiply(&q.y.add(&q.x));
+        let c = self.t.multiply(&q.t).multiply(&D2);
+        let d = self.z.multiply(&q.z);
+        let d = d.add(&d);
+        let e = b.subtract(&a);
+        let f = d.subtract(&c);
+        let g = d.add(&c);
+        let h = b.add(&a);
+        Point { x: e.multiply(&f), y: h.multiply(&g), z: g.multiply(&f), t: e.multiply(&h) }
+    }
+
+    /// Returns -v.
+    pub fn negate(&self) -> Point {
+        Point { x: self.x.negate(), y: self.y, z: self.z, t: self.t.negate() }
+    }
+
+    /// Returns v - q.
+    pub fn subtract(&self, q: &Point) -> Point {
+        self.add(&q.negate())
+    }
+
+    /// Returns v + v.
+    pub fn double(&self) -> Point {
+        self.add(self)
+    }
+
+    /// Returns [8]v.
+    pub fn mult_by_cofactor(&self) -> Point {
+        self.double().double().double()
+    }
+
+    /// Returns 1 if v and q are the same point and 0 otherwise.
+    pub fn equal(&self, q: &Point) -> u32 {
+        let x1 = self.x.multiply(&q.z);
+        let x2 = q.x.multiply(&self.z);
+        let y1 = self.y.multiply(&q.z);
+        let y2 = q.y.multiply(&self.z);
+        x1.equal(&x2) & y1.equal(&y2)
+    }
+
+    /// Returns 1 if v is the identity and 0 otherwise.
+    pub fn is_identity(&self) -> u32 {
+        self.equal(&Point::identity())
+    }
+
+    /// Swaps a and b if cond is 1 and leaves them alone if cond is 0.
+    pub fn swap(cond: u32, a: &mut Point, b: &mut Point) {
+        Element::swap(cond, &mut a.x, &mut b.x);
+        Element::swap(cond, &mut a.y, &mut b.y);
+        Element::swap(cond, &mut a.z, &mut b.z);
+        Element::swap(cond, &mut a.t, &mut b.t);
+    }
+
+    /// Returns [x]q, in constant time.
+    pub fn scalar_mult(x: &Scalar, q: &Point) -> Point {
+        let s = x.bytes();
+        let mut v = Point::identity();
+        let mut r = *q;
+        // Invariant: r = v + q at every step.
+        for i in range(0, 256u).rev() {
+            let bit = ((s[i / 8] >> (i % 8)) & 1) as u32;
+            Point::swap(bit, &mut v, &mut r);
+            r = v.add(&r);
+            v = v.double();
+            Point::swap(bit, &mut v, &mut r);
+        }
+        v
+    }
+
+    /// Returns [x]B, where B is the canonical generator, in constant
+    /// time.
+    pub fn scalar_base_mult(x: &Scalar) -> Point {
+        Point::scalar_mult(x, &Point::generator())
+    }
+
+    /// Returns [a]A + [b]B, where B is the canonical generator. It is
+    /// meant for signature verification, where the inputs are public.
+    pub fn double_scalar_base_mult(a: &Scalar, p: &Point, b: &Scalar) -> Point {
+        Point::scalar_mult(a, p).add(&Point::scalar_base_mult(b))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::scalar::Scalar;
+
+    fn point(s: &str) -> Point {
+        Point::set_canonical_bytes(s.from_hex().unwrap()).unwrap()
+    }
+
+    fn scalar(x: u8) -> Scalar {
+        let mut b = [0u8, ..32];
+        b[0] = x;
+        Scalar::set_canonical_bytes(b).unwrap()
+    }
+
+    // A point of order 8.
+    static Torsion8: &'static str =
+        "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a";
+
+    #[test]
+    fn test_generator() {
+        let b = Point::generator();
+        assert_eq!(b.bytes().to_hex(),
+                   ~"5866666666666666666666666666666666666666666666666666666666666666");
+        assert_eq!(b.double().bytes().to_hex(),
+                   ~"c9a3f86aae465f0e56513864510f3997561fa2c9e85ea21dc2292309f3cd6022");
+        assert_eq!(Point::scalar_base_mult(&scalar(3)).bytes().to_hex(),
+                   ~"d4b4f5784868c3020403246717ec169ff79e26608ea126a1ab69ee77d1b16712");
+        assert_eq!(Point::set_canonical_bytes(b.bytes()).unwrap().equal(&b), 1);
+        assert_eq!(b.subtract(&b).is_identity(), 1);
+        assert_eq!(b.add(&b.negate()).bytes().to_hex(),
+                   ~"0100000000000000000000000000000000000000000000000000000000000000");
+
+        // [l-1]B = -B
+        let m1 = scalar(1).negate();
+        assert_eq!(Point::scalar_base_mult(&m1).equal(&b.negate()), 1);
+        let x = scalar(200);
+        assert_eq!(Point::double_scalar_base_mult(&x, &b, &m1)
+                   .equal(&Point::scalar_base_mult(&scalar(199))), 1);
+        assert_eq!(Point::scalar_mult(&Scalar::zero(), &b).is_identity(), 1);
+    }
+
+    #[test]
+    fn test_torsion() {
+        let t = point(Torsion8);
+        assert_eq!(t.is_identity(), 0);
+        assert_eq!(t.double().double().is_identity(), 0);
+        assert_eq!(t.mult_by_cofactor().is_identity(), 1);
+        assert_eq!(t.double().double().bytes().to_hex(),
+                   ~"ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        assert_eq!(t.double().bytes().to_hex(),
+                   ~"0000000000000000000000000000000000000000000000000000000000000080");
+    }
+
+    #[test]
+    fn test_non_canonical() {
+        // the identity, with y = p + 1
+        let y = "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+        assert_eq!(Point::set_bytes(y.from_hex().unwrap()).unwrap().is_identity(), 1);
+        assert!(Point::set_canonical_bytes(y.from_hex().unwrap()).is_none());
+        // the identity, with the sign bit set
+        let s = "0100000000000000000000000000000000000000000000000000000000000080";
+        assert_eq!(Point::set_bytes(s.from_hex().unwrap()).unwrap().is_identity(), 1);
+        assert!(Point::set_canonical_bytes(s.from_hex().unwrap()).is_none());
+        // y = 2 is not on the curve
+        let bad = "0200000000000000000000000000000000000000000000000000000000000000";
+        assert!(Point::set_bytes(bad.from_hex().unwrap()).is_none());
+        assert!(Point::set_bytes([0u8, ..31]).is_none());
+    }
+}
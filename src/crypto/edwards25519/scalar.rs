@@ -0,0 +1,199 @@
+// Arithmetic modulo the prime order of the edwards25519 base point,
+// l = 2^252 + 27742317777372353535851937790883648493.
+//
+// Scalars are stored as their canonical 32-byte little-endian encoding.
+// Reduction works on 64 signed limbs of 8 bits each, as in TweetNaCl,
+// and does not branch on the value being reduced.
+
+use crypto::subtle::constant_time_compare;
+
+// l in little-endian bytes.
+static L: [i64, ..32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+    0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+/// An integer modulo l, always kept reduced.
+pub struct Scalar {
+    s: [u8, ..32],
+}
+
+// Reduces the little-endian value x, whose limbs may exceed 8 bits,
+// modulo l.
+fn mod_l(x: &mut [i64, ..64]) -> Scalar {
+    // Fold the upper 32 limbs down one at a time, from the top, using
+    // 2^252 = -(l - 2^252) mod l, and keep the carries small and signed.
+    for i in range(32, 64u).rev() {
+        let mut c = 0i64;
+        let mut j = i - 32;
+        while j < i - 12 {
+            x[j] += c - 16 * x[i] * L[j - (i - 32)];
+            c = (x[j] + 128) >> 8;
+            x[j] -= c << 8;
+            j += 1;
+        }
+        x[j] += c;
+        x[i] = 0;
+    }
+    // Subtract the multiple of l indicated by the bits above 2^252.
+    let mut c = 0i64;
+    for j in range(0, 32u) {
+        x[j] += c - (x[31] >> 4) * L[j];
+        c = x[j] >> 8;
+        x[j] &= 255;
+    }
+    for j in range(0, 32u) {
+        x[j] -= c * L[j];
+    }
+    let mut s = [0u8, ..32];
+    for i in range(0, 32u) {
+        x[i + 1] += x[i] >> 8;
+        s[i] = x[i] as u8;
+    }
+    Scalar { s: s }
+}
+
+impl Scalar {
+    /// Returns zero.
+    pub fn zero() -> Scalar {
+        Scalar { s: [0, ..32] }
+    }
+
+    /// Returns x mod l, where x is a 64-byte little-endian integer, such
+    /// as the output of SHA-512. Fails if x is not 64 bytes long.
+    pub fn set_uniform_bytes(x: &[u8]) -> Scalar {
+        if x.len() != 64 {
+            fail!("edwards25519: invalid uniform scalar input size");
+        }
+        let mut t = [0i64, ..64];
+        for i in range(0, 64u) {
+            t[i] = x[i] as i64;
+        }
+        mod_l(&mut t)
+    }
+
+    /// Returns the scalar encoded by the 32-byte little-endian x, or None
+    /// if x is not a canonical encoding, that is, not below l.
+    pub fn set_canonical_bytes(x: &[u8]) -> Option<Scalar> {
+        if x.len() != 32 {
+            return None;
+        }
+        let mut wide = [0u8, ..64];
+        wide.mut_slice_to(32).copy_from(x);
+        let s = Scalar::set_uniform_bytes(wide);
+        if constant_time_compare(s.s, x) {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Applies the buffer pruning described in RFC 8032, Section 5.1.5
+    /// (also known as clamping) to the 32-byte x and returns the result
+    /// reduced modulo l. Fails if x is not 32 bytes long.
+    pub fn set_bytes_with_clamping(x: &[u8]) -> Scalar {
+        if x.len() != 32 {
+            fail!("edwards25519: invalid scalar input size");
+        }
+        let mut wide = [0u8, ..64];
+        wide.mut_slice_to(32).copy_from(x);
+        wide[0] &= 248;
+        wide[31] &= 127;
+        wide[31] |= 64;
+        Scalar::set_uniform_bytes(wide)
+    }
+
+    /// Returns the canonical 32-byte little-endian encoding of s.
+    pub fn bytes(&self) -> ~[u8] {
+        self.s.to_owned()
+    }
+
+    /// Returns x * y + z mod l.
+    pub fn multiply_add(x: &Scalar, y: &Scalar, z: &Scalar) -> Scalar {
+        let mut t = [0i64, ..64];
+        for i in range(0, 32u) {
+            t[i] = z.s[i] as i64;
+        }
+        for i in range(0, 32u) {
+            for j in range(0, 32u) {
+                t[i + j] += x.s[i] as i64 * y.s[j] as i64;
+            }
+        }
+        mod_l(&mut t)
+    }
+
+    /// Returns x + y mod l.
+    pub fn add(&self, y: &Scalar) -> Scalar {
+        let mut one = Scalar::zero();
+        one.s[0] = 1;
+        Scalar::multiply_add(self, &one, y)
+    }
+
+    /// Returns x * y mod l.
+    pub fn multiply(&self, y: &Scalar) -> Scalar {
+        Scalar::multiply_add(self, y, &Scalar::zero())
+    }
+
+    /// Returns -x mod l.
+    pub fn negate(&self) -> Scalar {
+        // -1 = l - 1
+        let mut minus_one = Scalar::zero();
+        for i in range(0, 32u) {
+            minus_one.s[i] = L[i] as u8;
+        }
+        minus_one.s[0] -= 1;
+        Scalar::multiply_add(self, &minus_one, &Scalar::zero())
+    }
+
+    /// Returns 1 if x and y are equal and 0 otherwise.
+    pub fn equal(&self, y: &Scalar) -> u32 {
+        constant_time_compare(self.s, y.s) as u32
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+
+    // l - 1 and l in little-endian hex
+    static MinusOne: &'static str =
+        "ecd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+    static Order: &'static str =
+        "edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+
+    #[test]
+    fn test_canonical_bytes() {
+        let m1 = Scalar::set_canonical_bytes(MinusOne.from_hex().unwrap()).unwrap();
+        assert_eq!(m1.bytes().to_hex(), MinusOne.to_owned());
+        assert!(Scalar::set_canonical_bytes(Order.from_hex().unwrap()).is_none());
+        assert!(Scalar::set_canonical_bytes([0xffu8, ..32]).is_none());
+        assert!(Scalar::set_canonical_bytes([0u8, ..31]).is_none());
+        assert_eq!(Scalar::zero().negate().equal(&Scalar::zero()), 1);
+
+        let mut one = [0u8, ..32];
+        one[0] = 1;
+        let one = Scalar::set_canonical_bytes(one).unwrap();
+        assert_eq!(one.negate().equal(&m1), 1);
+        assert_eq!(m1.add(&one).equal(&Scalar::zero()), 1);
+        assert_eq!(m1.multiply(&m1).equal(&one), 1);
+    }
+
+    #[test]
+    fn test_uniform_bytes() {
+        // 2^512 - 1 mod l, computed with Python
+        let x = Scalar::set_uniform_bytes([0xffu8, ..64]);
+        assert_eq!(x.bytes().to_hex(),
+                   ~"000f9c44e31106a447938568a71b0ed065bef517d273ecce3d9a307c1b419903");
+        let mut wide = [0u8, ..64];
+        wide.mut_slice_to(32).copy_from(Order.from_hex().unwrap());
+        assert_eq!(Scalar::set_uniform_bytes(wide).equal(&Scalar::zero()), 1);
+        // clamping sets bit 254, which is then reduced
+        let c = Scalar::set_bytes_with_clamping([0u8, ..32]);
+        assert_eq!(c.bytes().to_hex(),
+                   ~"39841ee9b0d6c8f77c291917641263c1ffffffffffffffffffffffffffffff0f");
+    }
+}
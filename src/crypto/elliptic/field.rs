@@ -0,0 +1,441 @@
+// Constant-time arithmetic modulo the primes of the NIST curves.
+//
+// Elements are kept in fixed arrays of 32-bit limbs, least significant
+// first, so that no operation allocates or branches on secret data. The
+// same code serves the coordinate fields and the scalar fields given by
+// the group orders; only the constants differ. Products are reduced with
+// Montgomery multiplication, except modulo the P-256 prime, whose special
+// form allows the faster reduction of FIPS 186-3, section D.2.3.
+
+/// The number of limbs in the largest modulus, that of P-521.
+pub static MaxLimbs: uint = 17;
+
+/// How a Field reduces products modulo its prime.
+pub enum Reduction {
+    /// Montgomery reduction. Elements are kept in Montgomery form.
+    Montgomery,
+    /// The reduction modulo the P-256 prime by additions and subtractions
+    /// of the halves of the product. Elements are kept as plain residues.
+    SolinasP256,
+}
+
+/// A prime modulus m and the constants for multiplication modulo it.
+pub struct Field {
+    /// The number of 32-bit limbs used for elements.
+    pub limbs: uint,
+    /// The bit length of the modulus.
+    pub bits: uint,
+    /// The modulus.
+    pub m: [u32, ..MaxLimbs],
+    /// -m^-1 mod 2^32.
+    pub m0inv: u32,
+    /// R^2 mod m, where R = 2^(32*limbs) for Montgomery reduction and
+    /// R = 1 otherwise.
+    pub rr: [u32, ..MaxLimbs],
+    /// The reduction used by mul.
+    pub reduction: Reduction,
+}
+
+/// An element of a Field, in Montgomery form if the field uses it. Limbs above the field's
+/// limb count are always zero.
+pub struct Elem {
+    pub l: [u32, ..MaxLimbs],
+}
+
+// Returns 0xffffffff if b is 1 and 0 if b is 0.
+#[inline]
+fn mask(b: u32) -> u32 {
+    0u32 - b
+}
+
+// Returns 1 if x is zero and 0 otherwise.
+#[inline]
+fn is_zero32(x: u32) -> u32 {
+    let x = x as u64;
+    (((x | (0u64 - x)) >> 32) as u32 & 1) ^ 1
+}
+
+// Montgomery multiplication (CIOS) of a and b modulo m with n limbs.
+fn mont_mul(n: uint, m: &[u32, ..MaxLimbs], m0inv: u32, a: &[u32, ..MaxLimbs],
+            b: &[u32, ..MaxLimbs]) -> ([u32, ..MaxLimbs], u32) {
+    let mut t = [0u32, ..MaxLimbs + 2];
+    for i in range(0, n) {
+        let mut c = 0u64;
+        for j in range(0, n) {
+            c += t[j] as u64 + a[j] as u64 * b[i] as u64;
+            t[j] = c as u32;
+            c >>= 32;
+        }
+        c += t[n] as u64;
+        t[n] = c as u32;
+        t[n + 1] = (c >> 32) as u32;
+
+        let q = t[0] * m0inv;
+        let mut c = (t[0] as u64 + q as u64 * m[0] as u64) >> 32;
+        for j in range(1, n) {
+            c += t[j] as u64 + q as u64 * m[j] as u64;
+            t[j - 1] = c as u32;
+            c >>= 32;
+        }
+        c += t[n] as u64;
+        t[n - 1] = c as u32;
+        t[n] = t[n + 1] + (c >> 32) as u32;
+    }
+    let mut r = [0u32, ..MaxLimbs];
+    for i in range(0, n) {
+        r[i] = t[i];
+    }
+    (r, t[n])
+}
+
+// Multiplication of a and b modulo the P-256 prime
+// p = 2^256 - 2^224 + 2^192 + 2^96 - 1. The 512-bit product c is reduced
+// as s1 + 2s2 + 2s3 + s4 + s5 - s6 - s7 - s8 - s9, with the terms of
+// FIPS 186-3, section D.2.3. The result is below 2^256 and so below 2p.
+fn p256_mul(a: &[u32, ..MaxLimbs], b: &[u32, ..MaxLimbs]) -> [u32, ..MaxLimbs] {
+    let mut c = [0u32, ..16];
+    for i in range(0, 8u) {
+        let mut carry = 0u64;
+        for j in range(0, 8u) {
+            carry += c[i + j] as u64 + a[i] as u64 * b[j] as u64;
+            c[i + j] = carry as u32;
+            carry >>= 32;
+        }
+        c[i + 8] = carry as u32;
+    }
+
+    let w = |i: uint| c[i] as i64;
+    let acc = [
+        w(0) + w(8) + w(9) - w(11) - w(12) - w(13) - w(14),
+        w(1) + w(9) + w(10) - w(12) - w(13) - w(14) - w(15),
+        w(2) + w(10) + w(11) - w(13) - w(14) - w(15),
+        w(3) + 2 * w(11) + 2 * w(12) + w(13) - w(15) - w(8) - w(9),
+        w(4) + 2 * w(12) + 2 * w(13) + w(14) - w(9) - w(10),
+        w(5) + 2 * w(13) + 2 * w(14) + w(15) - w(10) - w(11),
+        w(6) + 3 * w(14) + 2 * w(15) + w(13) - w(8) - w(9),
+        w(7) + 3 * w(15) + w(8) - w(10) - w(11) - w(12) - w(13),
+    ];
+    let mut t = [0u32, ..MaxLimbs];
+    let mut carry = 0i64;
+    for i in range(0, 8u) {
+        carry += acc[i];
+        t[i] = carry as u32;
+        carry >>= 32;
+    }
+
+    // The sum is between -4p and 6p. Fold the signed carry back in with
+    // 2^256 = 2^224 - 2^192 - 2^96 + 1 mod p. The first fold leaves a carry
+    // of at most one either way, and the second none.
+    for _ in range(0, 2) {
+        let f = [carry, 0, 0, -carry, 0, 0, -carry, carry];
+        carry = 0;
+        for i in range(0, 8u) {
+            carry += t[i] as i64 + f[i];
+            t[i] = carry as u32;
+            carry >>= 32;
+        }
+    }
+    t
+}
+
+impl Field {
+    /// Returns the length of the big-endian encoding of an element.
+    pub fn byte_len(&self) -> uint {
+        (self.bits + 7) / 8
+    }
+
+    /// Returns zero.
+    pub fn zero(&self) -> Elem {
+        Elem { l: [0, ..MaxLimbs] }
+    }
+
+    /// Returns one.
+    pub fn one(&self) -> Elem {
+        self.from_u32(1)
+    }
+
+    /// Returns x as an element.
+    pub fn from_u32(&self, x: u32) -> Elem {
+        let mut e = self.zero();
+        e.l[0] = x;
+        self.to_mont(&e)
+    }
+
+    // Subtracts m from the value carry:t if it is not below m. The value
+    // must be below 2m.
+    fn reduce_once(&self, t: &[u32, ..MaxLimbs], carry: u32) -> Elem {
+        let mut r = [0u32, ..MaxLimbs];
+        let mut borrow = 0u64;
+        for i in range(0, self.limbs) {
+            let d = (t[i] as u64) - (self.m[i] as u64) - borrow;
+            r[i] = d as u32;
+            borrow = (d >> 32) & 1;
+        }
+        // Keep t only if it did not overflow and subtracting m borrowed.
+        let keep = mask(!carry & borrow as u32 & 1);
+        for i in range(0, self.limbs) {
+            r[i] = (t[i] & keep) | (r[i] & !keep);
+        }
+        Elem { l: r }
+    }
+
+    /// Returns a + b.
+    pub fn add(&self, a: &Elem, b: &Elem) -> Elem {
+        let mut t = [0u32, ..MaxLimbs];
+        let mut c = 0u64;
+        for i in range(0, self.limbs) {
+            c += a.l[i] as u64 + b.l[i] as u64;
+            t[i] = c as u32;
+            c >>= 32;
+        }
+        self.reduce_once(&t, c as u32)
+    }
+
+    /// Returns a - b.
+    pub fn sub(&self, a: &Elem, b: &Elem) -> Elem {
+        let mut t = [0u32, ..MaxLimbs];
+        let mut borrow = 0u64;
+        for i in range(0, self.limbs) {
+            let d = (a.l[i] as u64) - (b.l[i] as u64) - borrow;
+            t[i] = d as u32;
+            borrow = (d >> 32) & 1;
+        }
+        let mm = mask(borrow as u32);
+        let mut c = 0u64;
+        for i in range(0, self.limbs) {
+            c += t[i] as u64 + (self.m[i] & mm) as u64;
+            t[i] = c as u32;
+            c >>= 32;
+        }
+        Elem { l: t }
+    }
+
+    /// Returns -a.
+    pub fn neg(&self, a: &Elem) -> Elem {
+        self.sub(&self.zero(), a)
+    }
+
+    /// Returns a * b.
+    pub fn mul(&self, a: &Elem, b: &Elem) -> Elem {
+        match self.reduction {
+            Montgomery => {
+                let (t, carry) = mont_mul(self.limbs, &self.m, self.m0inv, &a.l, &b.l);
+                self.reduce_once(&t, carry)
+            }
+            SolinasP256 => self.reduce_once(&p256_mul(&a.l, &b.l), 0),
+        }
+    }
+
+    /// Returns a * a.
+    pub fn square(&self, a: &Elem) -> Elem {
+        self.mul(a, a)
+    }
+
+    // Multiplying by R^2 and by 1 converts to and from Montgomery form. In a
+    // field with R = 1 both leave the element as it is.
+    fn to_mont(&self, a: &Elem) -> Elem {
+        self.mul(a, &Elem { l: self.rr })
+    }
+
+    fn from_mont(&self, a: &Elem) -> Elem {
+        self.mul(a, &self.from_limbs_raw(1))
+    }
+
+    fn from_limbs_raw(&self, x: u32) -> Elem {
+        let mut e = self.zero();
+        e.l[0] = x;
+        e
+    }
+
+    // Parses a big-endian value of exactly byte_len bytes into limbs,
+    // without reducing it.
+    fn limbs_from_bytes(&self, b: &[u8]) -> [u32, ..MaxLimbs] {
+        assert_eq!(b.len(), self.byte_len());
+        let mut l = [0u32, ..MaxLimbs];
+        for (i, &x) in b.iter().rev().enumerate() {
+            l[i / 4] |= (x as u32) << (8 * (i % 4));
+        }
+        l
+    }
+
+    /// Returns the element encoded by b, which must be byte_len bytes
+    /// long, or None if the value is not below the modulus.
+    pub fn set_bytes(&self, b: &[u8]) -> Option<Elem> {
+        if b.len() != self.byte_len() {
+            return None;
+        }
+        let l = self.limbs_from_bytes(b);
+        let mut borrow = 0u64;
+        for i in range(0, self.limbs) {
+            let d = (l[i] as u64) - (self.m[i] as u64) - borrow;
+            borrow = (d >> 32) & 1;
+        }
+        if borrow == 0 {
+            return None;
+        }
+        Some(self.to_mont(&Elem { l: l }))
+    }
+
+    /// Returns the element encoded by b, which must be byte_len bytes
+    /// long and encode a value below twice the modulus, reduced modulo m.
+    /// It is used to reduce hashes and coordinates modulo the group order.
+    pub fn set_bytes_reduced(&self, b: &[u8]) -> Elem {
+        let l = self.limbs_from_bytes(b);
+        // Values with more bits than the modulus are not below 2m.
+        assert!(self.bits % 32 == 0 || l[self.limbs - 1] >> (self.bits % 32) <= 1);
+        let r = self.reduce_once(&l, 0);
+        self.to_mont(&r)
+    }
+
+    /// Returns the big-endian encoding of a, byte_len bytes long.
+    pub fn bytes(&self, a: &Elem) -> ~[u8] {
+        let e = self.from_mont(a);
+        let n = self.byte_len();
+        let mut out = ~[];
+        for i in range(0, n).rev() {
+            out.push((e.l[i / 4] >> (8 * (i % 4))) as u8);
+        }
+        out
+    }
+
+    /// Returns 1 if a == b and 0 otherwise.
+    pub fn equal(&self, a: &Elem, b: &Elem) -> u32 {
+        let mut d = 0u32;
+        for i in range(0, self.limbs) {
+            d |= a.l[i] ^ b.l[i];
+        }
+        is_zero32(d)
+    }
+
+    /// Returns 1 if a is zero and 0 otherwise.
+    pub fn is_zero(&self, a: &Elem) -> u32 {
+        self.equal(a, &self.zero())
+    }
+
+    /// Returns 1 if the canonical value of a is odd and 0 otherwise.
+    pub fn is_odd(&self, a: &Elem) -> u32 {
+        self.from_mont(a).l[0] & 1
+    }
+
+    /// Returns a if cond is 1 and b if cond is 0.
+    pub fn select(&self, cond: u32, a: &Elem, b: &Elem) -> Elem {
+        let mm = mask(cond);
+        let mut r = [0u32, ..MaxLimbs];
+        for i in range(0, self.limbs) {
+            r[i] = (a.l[i] & mm) | (b.l[i] & !mm);
+        }
+        Elem { l: r }
+    }
+
+    // Returns a^e, where e is given in limbs. The time taken depends only
+    // on e, which is always public.
+    fn pow_vartime(&self, a: &Elem, e: &[u32]) -> Elem {
+        let mut r = self.one();
+        for i in range(0, e.len() * 32).rev() {
+            r = self.square(&r);
+            if (e[i / 32] >> (i % 32)) & 1 == 1 {
+                r = self.mul(&r, a);
+            }
+        }
+        r
+    }
+
+    // Returns the modulus plus add, shifted right by shift bits.
+    fn modulus_plus(&self, add: i64, shift: uint) -> ~[u32] {
+        let mut e = ~[];
+        let mut c = add;
+        for i in range(0, self.limbs) {
+            c += self.m[i] as i64;
+            e.push(c as u32);
+            c >>= 32;
+        }
+        e.push(c as u32);
+        for i in range(0, e.len()) {
+            let hi = if i + 1 < e.len() { e[i + 1] } else { 0 };
+            e[i] = if shift == 0 { e[i] } else { (e[i] >> shift) | (hi << (32 - shift)) };
+        }
+        e
+    }
+
+    /// Returns 1/a, or zero if a is zero.
+    pub fn invert(&self, a: &Elem) -> Elem {
+        // Fermat's little theorem: a^(m-2) = 1/a.
+        self.pow_vartime(a, self.modulus_plus(-2, 0))
+    }
+
+    /// Returns a square root of a, or None if a is not a square. The
+    /// modulus must be 3 mod 4, as for all the NIST primes.
+    pub fn sqrt(&self, a: &Elem) -> Option<Elem> {
+        let r = self.pow_vartime(a, self.modulus_plus(1, 2));
+        if self.equal(&self.square(&r), a) == 1 {
+            Some(r)
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::super::p256;
+    use super::{Field, Montgomery};
+    use super::super::super::rand;
+
+    #[test]
+    fn test_arith() {
+        let f = &p256().p;
+        let two = f.from_u32(2);
+        let three = f.from_u32(3);
+        assert_eq!(f.equal(&f.add(&two, &f.one()), &three), 1);
+        assert_eq!(f.equal(&f.sub(&two, &three), &f.neg(&f.one())), 1);
+        assert_eq!(f.equal(&f.mul(&two, &three), &f.from_u32(6)), 1);
+        assert_eq!(f.equal(&f.mul(&f.invert(&three), &three), &f.one()), 1);
+        assert_eq!(f.is_zero(&f.invert(&f.zero())), 1);
+        let four = f.from_u32(4);
+        let r = f.sqrt(&four).unwrap();
+        assert_eq!(f.equal(&f.square(&r), &four), 1);
+        // -1 is not a square modulo a prime that is 3 mod 4
+        assert!(f.sqrt(&f.neg(&f.one())).is_none());
+
+        let mut m = f.bytes(&f.neg(&f.one()));
+        assert_eq!(m[31], 0xfe);
+        assert_eq!(f.is_odd(&f.from_u32(5)), 1);
+        m[31] = 0xff;
+        assert!(f.set_bytes(m).is_none());
+        assert_eq!(f.is_zero(&f.set_bytes_reduced(m)), 1);
+        assert_eq!(f.bytes(&f.select(1, &two, &three)), f.bytes(&two));
+        assert_eq!(f.bytes(&f.select(0, &two, &three)), f.bytes(&three));
+    }
+
+    #[test]
+    fn test_p256_reduction() {
+        // The P-256 coordinate field with the generic Montgomery reduction.
+        let f = &p256().p;
+        let mut rr = [0u32, ..super::MaxLimbs];
+        let l = [0x00000003, 0x00000000, 0xffffffff, 0xfffffffb, 0xfffffffe, 0xffffffff,
+                 0xfffffffd, 0x00000004];
+        for i in range(0, l.len()) {
+            rr[i] = l[i];
+        }
+        let g = &Field { limbs: 8, bits: 256, m: f.m, m0inv: 1, rr: rr, reduction: Montgomery };
+
+        let mut vals = ~[f.bytes(&f.zero()), f.bytes(&f.one()), f.bytes(&f.neg(&f.one()))];
+        for _ in range(0, 200) {
+            let mut b = [0u8, ..32];
+            rand::read(b.as_mut_slice());
+            vals.push(b.as_slice().to_owned());
+        }
+        for x in vals.iter() {
+            for y in vals.iter() {
+                let (x, y) = (x.as_slice(), y.as_slice());
+                let (fx, fy) = (f.set_bytes_reduced(x), f.set_bytes_reduced(y));
+                let (gx, gy) = (g.set_bytes_reduced(x), g.set_bytes_reduced(y));
+                assert_eq!(f.bytes(&f.mul(&fx, &fy)), g.bytes(&g.mul(&gx, &gy)));
+            }
+        }
+        let x = vals[5].as_slice();
+        let (fx, gx) = (f.set_bytes_reduced(x), g.set_bytes_reduced(x));
+        assert_eq!(f.bytes(&f.invert(&fx)), g.bytes(&g.invert(&gx)));
+    }
+}
@@ -0,0 +1,806 @@
+// The NIST curves P-256, P-384 and P-521 over prime fields, after Go's
+// crypto/elliptic and crypto/internal/nistec.
+//
+// Points are kept in projective coordinates and combined with the
+// complete addition formulas of Renes, Costello and Batina ("Complete
+// addition formulas for prime order elliptic curves", 2015), which have
+// no exceptional cases, so point arithmetic and scalar multiplication do
+// not branch on secret data. Field arithmetic is in field.rs.
+//
+// Point is the low-level API, used by ecdsa and ecdh. The Curve methods
+// on big integers mirror Go's deprecated elliptic.Curve interface.
+
+use std::io::IoResult;
+use math::big::Int;
+use super::subtle::constant_time_eq;
+use crypto::elliptic::field::{Field, Elem, Montgomery, SolinasP256};
+
+pub mod field;
+
+/// A short Weierstrass curve y² = x³ - 3x + b over a prime field.
+pub struct Curve {
+    /// The canonical name of the curve, like "P-256".
+    pub name: &'static str,
+    /// The field of coordinates, modulo the prime p.
+    pub p: Field,
+    /// The field of scalars, modulo the order n of the base point.
+    pub n: Field,
+    b: Elem,
+    gx: Elem,
+    gy: Elem,
+}
+
+static P256Curve: Curve = Curve {
+    name: "P-256",
+    p: Field {
+        limbs: 8,
+        bits: 256,
+        m: [
+            0xffffffff, 0xffffffff, 0xffffffff, 0x00000000, 0x00000000, 0x00000000,
+            0x00000001, 0xffffffff, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        m0inv: 0x00000001,
+        rr: [
+            0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        reduction: SolinasP256,
+    },
+    n: Field {
+        limbs: 8,
+        bits: 256,
+        m: [
+            0xfc632551, 0xf3b9cac2, 0xa7179e84, 0xbce6faad, 0xffffffff, 0xffffffff,
+            0x00000000, 0xffffffff, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        m0inv: 0xee00bc4f,
+        rr: [
+            0xbe79eea2, 0x83244c95, 0x49bd6fa6, 0x4699799c, 0x2b6bec59, 0x2845b239,
+            0xf3d95620, 0x66e12d94, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        reduction: Montgomery,
+    },
+    b: Elem { l: [
+        0x27d2604b, 0x3bce3c3e, 0xcc53b0f6, 0x651d06b0, 0x769886bc, 0xb3ebbd55,
+        0xaa3a93e7, 0x5ac635d8, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+    gx: Elem { l: [
+        0xd898c296, 0xf4a13945, 0x2deb33a0, 0x77037d81, 0x63a440f2, 0xf8bce6e5,
+        0xe12c4247, 0x6b17d1f2, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+    gy: Elem { l: [
+        0x37bf51f5, 0xcbb64068, 0x6b315ece, 0x2bce3357, 0x7c0f9e16, 0x8ee7eb4a,
+        0xfe1a7f9b, 0x4fe342e2, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+};
+
+static P384Curve: Curve = Curve {
+    name: "P-384",
+    p: Field {
+        limbs: 12,
+        bits: 384,
+        m: [
+            0xffffffff, 0x00000000, 0x00000000, 0xffffffff, 0xfffffffe, 0xffffffff,
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        m0inv: 0x00000001,
+        rr: [
+            0x00000001, 0xfffffffe, 0x00000000, 0x00000002, 0x00000000, 0xfffffffe,
+            0x00000000, 0x00000002, 0x00000001, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        reduction: Montgomery,
+    },
+    n: Field {
+        limbs: 12,
+        bits: 384,
+        m: [
+            0xccc52973, 0xecec196a, 0x48b0a77a, 0x581a0db2, 0xf4372ddf, 0xc7634d81,
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        m0inv: 0xe88fdc45,
+        rr: [
+            0x19b409a9, 0x2d319b24, 0xdf1aa419, 0xff3d81e5, 0xfcb82947, 0xbc3e483a,
+            0x4aab1cc5, 0xd40d4917, 0x28266895, 0x3fb05b7a, 0x2b39bf21, 0x0c84ee01,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        reduction: Montgomery,
+    },
+    b: Elem { l: [
+        0x9d412dcc, 0x08118871, 0x7a4c32ec, 0xf729add8, 0x1920022e, 0x77f2209b,
+        0x94938ae2, 0xe3374bee, 0x1f022094, 0xb62b21f4, 0x604fbff9, 0xcd08114b,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+    gx: Elem { l: [
+        0x49c0b528, 0x3dd07566, 0xa0d6ce38, 0x20e378e2, 0x541b4d6e, 0x879c3afc,
+        0x59a30eff, 0x64548684, 0x614ede2b, 0x812ff723, 0x299e1513, 0x4d3aadc2,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+    gy: Elem { l: [
+        0x4b03a4fe, 0x23043dad, 0x7bb4a9ac, 0xa1bfa8bf, 0x2e83b050, 0x8bade756,
+        0x68f4ffd9, 0xc6c35219, 0x3969a840, 0xdd800226, 0x5a15c5e9, 0x2b78abc2,
+        0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000] },
+};
+
+static P521Curve: Curve = Curve {
+    name: "P-521",
+    p: Field {
+        limbs: 17,
+        bits: 521,
+        m: [
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0x000001ff],
+        m0inv: 0x00000001,
+        rr: [
+            0x00000000, 0x00004000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000],
+        reduction: Montgomery,
+    },
+    n: Field {
+        limbs: 17,
+        bits: 521,
+        m: [
+            0x91386409, 0xbb6fb71e, 0x899c47ae, 0x3bb5c9b8, 0xf709a5d0, 0x7fcc0148,
+            0xbf2f966b, 0x51868783, 0xfffffffa, 0xffffffff, 0xffffffff, 0xffffffff,
+            0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0x000001ff],
+        m0inv: 0x79a995c7,
+        rr: [
+            0x61c64ca7, 0x1163115a, 0x4374a642, 0x18354a56, 0x0791d9dc, 0x5d4dd6d3,
+            0xd3402705, 0x4fb35b72, 0xb7756e3a, 0xcff3d142, 0xa8e567bc, 0x5bcc6d61,
+            0x492d0d45, 0x2d8e03d1, 0x8c44383d, 0x5b5a3afe, 0x0000019a],
+        reduction: Montgomery,
+    },
+    b: Elem { l: [
+        0x8014654f, 0xea35a81f, 0x78f7a28f, 0xc41e961a, 0x839ab9ef, 0x5e9dd8df,
+        0xbd8b2960, 0xa8f63f49, 0xf0ab0c9c, 0xc8c77884, 0xf9dc5a44, 0x2dccd98a,
+        0x77516d39, 0xd05b42a0, 0x0fc94d10, 0xb0c70e4d, 0x0000015c] },
+    gx: Elem { l: [
+        0xb331a163, 0x18e172de, 0x4dfcbf3f, 0xe0c2b521, 0x6f19a459, 0x93d17fd4,
+        0x947f0ee0, 0x3bf7f3ac, 0xdd50a5af, 0xb035a69e, 0x90fc1457, 0x9c829fda,
+        0x214e3240, 0xb311cada, 0xe6cf1f65, 0x5b820274, 0x00000103] },
+    gy: Elem { l: [
+        0x28460e4a, 0x3b4fe8b3, 0x20445f4a, 0x43513961, 0xb09a9e38, 0x809fd683,
+        0x2062a85c, 0x4caf7a13, 0x164bf739, 0x8b939f33, 0x340bd7de, 0x24abcda2,
+        0xeccc7aa2, 0xda163e8d, 0x022e452f, 0x3c4d1de0, 0x000000b5] },
+};
+
+/// Returns the curve implementing NIST P-256 (FIPS 186-3, section
+/// D.2.3), also known as secp256r1 or prime256v1.
+pub fn p256() -> &'static Curve {
+    &P256Curve
+}
+
+/// Returns the curve implementing NIST P-384 (FIPS 186-3, section
+/// D.2.4), also known as secp384r1.
+pub fn p384() -> &'static Curve {
+    &P384Curve
+}
+
+/// Returns the curve implementing NIST P-521 (FIPS 186-3, section
+/// D.2.5), also known as secp521r1.
+pub fn p521() -> &'static Curve {
+    &P521Curve
+}
+
+impl Eq for Curve {
+    fn eq(&self, other: &Curve) -> bool {
+        self.name == other.name
+    }
+}
+
+/// The parameters of a curve as big integers, like Go's
+/// elliptic.CurveParams.
+#[deriving(Clone, Eq)]
+pub struct CurveParams {
+    /// The order of the underlying field.
+    pub p: Int,
+    /// The order of the base point.
+    pub n: Int,
+    /// The constant of the curve equation.
+    pub b: Int,
+    /// The x coordinate of the base point.
+    pub gx: Int,
+    /// The y coordinate of the base point.
+    pub gy: Int,
+    /// The size of the underlying field.
+    pub bit_size: uint,
+    /// The canonical name of the curve.
+    pub name: &'static str,
+}
+
+fn modulus(f: &Field) -> Int {
+    let mut b = ~[];
+    for i in range(0, f.limbs).rev() {
+        for j in range(0, 4u).rev() {
+            b.push((f.m[i] >> (8 * j)) as u8);
+        }
+    }
+    Int::from_bytes(b)
+}
+
+/// A point on a curve, in projective coordinates (X:Y:Z) with x = X/Z
+/// and y = Y/Z. The point at infinity, the identity, is (0:1:0).
+pub struct Point<'a> {
+    curve: &'a Curve,
+    x: Elem,
+    y: Elem,
+    z: Elem,
+}
+
+impl<'a> Point<'a> {
+    /// Returns the point at infinity.
+    pub fn identity(c: &'a Curve) -> Point<'a> {
+        Point { curve: c, x: c.p.zero(), y: c.p.one(), z: c.p.zero() }
+    }
+
+    /// Returns the canonical generator of the curve.
+    pub fn generator(c: &'a Curve) -> Point<'a> {
+        Point { curve: c, x: c.gx, y: c.gy, z: c.p.one() }
+    }
+
+    /// Returns the curve of the point.
+    pub fn curve(&self) -> &'a Curve {
+        self.curve
+    }
+
+    // Returns x³ - 3x + b.
+    fn polynomial(c: &Curve, x: &Elem) -> Elem {
+        let f = &c.p;
+        let x3 = f.mul(&f.square(x), x);
+        let three_x = f.add(&f.add(x, x), x);
+        f.add(&f.sub(&x3, &three_x), &c.b)
+    }
+
+    /// Returns the point encoded by b, or None if it is not a valid
+    /// encoding of a point on the curve. b can be the uncompressed form
+    /// from section 4.3.6 of ANSI X9.62, the compressed form, or the
+    /// single zero byte encoding the point at infinity.
+    pub fn set_bytes(c: &'a Curve, b: &[u8]) -> Option<Point<'a>> {
+        let f = &c.p;
+        let n = f.byte_len();
+        if b.len() == 1 && b[0] == 0 {
+            return Some(Point::identity(c));
+        }
+        if b.len() == 1 + 2 * n && b[0] == 4 {
+            let (x, y) = match (f.set_bytes(b.slice(1, 1 + n)), f.set_bytes(b.slice_from(1 + n))) {
+                (Some(x), Some(y)) => (x, y),
+                _ => return None,
+            };
+            if f.equal(&Point::polynomial(c, &x), &f.square(&y)) != 1 {
+                return None;
+            }
+            return Some(Point { curve: c, x: x, y: y, z: f.one() });
+        }
+        if b.len() == 1 + n && (b[0] == 2 || b[0] == 3) {
+            let x = match f.set_bytes(b.slice_from(1)) {
+                Some(x) => x,
+                None => return None,
+            };
+            // y² = x³ - 3x + b, with the sign of y given by the prefix
+            let y = match f.sqrt(&Point::polynomial(c, &x)) {
+                Some(y) => y,
+                None => return None,
+            };
+            let cond = f.is_odd(&y) ^ (b[0] as u32 & 1);
+            let y = f.select(cond, &f.neg(&y), &y);
+            return Some(Point { curve: c, x: x, y: y, z: f.one() });
+        }
+        None
+    }
+
+    // Returns the affine coordinates of the point, or None if it is the
+    // point at infinity.
+    fn affine(&self) -> Option<(Elem, Elem)> {
+        let f = &self.curve.p;
+        if f.is_zero(&self.z) == 1 {
+            return None;
+        }
+        let zinv = f.invert(&self.z);
+        Some((f.mul(&self.x, &zinv), f.mul(&self.y, &zinv)))
+    }
+
+    /// Returns the uncompressed or infinity encoding of the point, as
+    /// specified in section 4.3.6 of ANSI X9.62.
+    pub fn bytes(&self) -> ~[u8] {
+        let f = &self.curve.p;
+        match self.affine() {
+            None => ~[0u8],
+            Some((x, y)) => {
+                let mut out = ~[4u8];
+                out.push_all(f.bytes(&x));
+                out.push_all(f.bytes(&y));
+                out
+            }
+        }
+    }
+
+    /// Returns the x coordinate of the point, or None if it is the point
+    /// at infinity.
+    pub fn bytes_x(&self) -> Option<~[u8]> {
+        self.affine().map(|(x, _)| self.curve.p.bytes(&x))
+    }
+
+    /// Returns the compressed or infinity encoding of the point, as
+    /// specified in section 4.3.6 of ANSI X9.62.
+    pub fn bytes_compressed(&self) -> ~[u8] {
+        let f = &self.curve.p;
+        match self.affine() {
+            None => ~[0u8],
+            Some((x, y)) => {
+                let mut out = ~[2u8 | f.is_odd(&y) as u8];
+                out.push_all(f.bytes(&x));
+                out
+            }
+        }
+    }
+
+    /// Reports whether the point is the point at infinity.
+    pub fn is_identity(&self) -> bool {
+        self.curve.p.is_zero(&self.z) == 1
+    }
+
+    /// Returns a if cond is 1 and b if cond is 0.
+    pub fn select(cond: u32, a: &Point<'a>, b: &Point<'a>) -> Point<'a> {
+        let f = &a.curve.p;
+        Point {
+            curve: a.curve,
+            x: f.select(cond, &a.x, &b.x),
+            y: f.select(cond, &a.y, &b.y),
+            z: f.select(cond, &a.z, &b.z),
+        }
+    }
+
+    /// Returns self + q.
+    pub fn add(&self, q: &Point<'a>) -> Point<'a> {
+        // Complete addition formula for a = -3 from "Complete addition
+        // formulas for prime order elliptic curves"
+        // (https://eprint.iacr.org/2015/1060), Algorithm 4.
+        let f = &self.curve.p;
+        let b = &self.curve.b;
+        let (x1, y1, z1) = (&self.x, &self.y, &self.z);
+        let (x2, y2, z2) = (&q.x, &q.y, &q.z);
+
+        let mut t0 = f.mul(x1, x2);    // t0 := X1 * X2
+        let mut t1 = f.mul(y1, y2);    // t1 := Y1 * Y2
+        let mut t2 = f.mul(z1, z2);    // t2 := Z1 * Z2
+        let mut t3 = f.add(x1, y1);    // t3 := X1 + Y1
+        let mut t4 = f.add(x2, y2);    // t4 := X2 + Y2
+        t3 = f.mul(&t3, &t4);          // t3 := t3 * t4
+        t4 = f.add(&t0, &t1);          // t4 := t0 + t1
+        t3 = f.sub(&t3, &t4);          // t3 := t3 - t4
+        t4 = f.add(y1, z1);            // t4 := Y1 + Z1
+        let mut x3 = f.add(y2, z2);    // X3 := Y2 + Z2
+        t4 = f.mul(&t4, &x3);          // t4 := t4 * X3
+        x3 = f.add(&t1, &t2);          // X3 := t1 + t2
+        t4 = f.sub(&t4, &x3);          // t4 := t4 - X3
+        x3 = f.add(x1, z1);            // X3 := X1 + Z1
+        let mut y3 = f.add(x2, z2);    // Y3 := X2 + Z2
+        x3 = f.mul(&x3, &y3);          // X3 := X3 * Y3
+        y3 = f.add(&t0, &t2);          // Y3 := t0 + t2
+        y3 = f.sub(&x3, &y3);          // Y3 := X3 - Y3
+        let mut z3 = f.mul(b, &t2);    // Z3 := b * t2
+        x3 = f.sub(&y3, &z3);          // X3 := Y3 - Z3
+        z3 = f.add(&x3, &x3);          // Z3 := X3 + X3
+        x3 = f.add(&x3, &z3);          // X3 := X3 + Z3
+        z3 = f.sub(&t1, &x3);          // Z3 := t1 - X3
+        x3 = f.add(&t1, &x3);          // X3 := t1 + X3
+        y3 = f.mul(b, &y3);            // Y3 := b * Y3
+        t1 = f.add(&t2, &t2);          // t1 := t2 + t2
+        t2 = f.add(&t1, &t2);          // t2 := t1 + t2
+        y3 = f.sub(&y3, &t2);          // Y3 := Y3 - t2
+        y3 = f.sub(&y3, &t0);          // Y3 := Y3 - t0
+        t1 = f.add(&y3, &y3);          // t1 := Y3 + Y3
+        y3 = f.add(&t1, &y3);          // Y3 := t1 + Y3
+        t1 = f.add(&t0, &t0);          // t1 := t0 + t0
+        t0 = f.add(&t1, &t0);          // t0 := t1 + t0
+        t0 = f.sub(&t0, &t2);          // t0 := t0 - t2
+        t1 = f.mul(&t4, &y3);          // t1 := t4 * Y3
+        t2 = f.mul(&t0, &y3);          // t2 := t0 * Y3
+        y3 = f.mul(&x3, &z3);          // Y3 := X3 * Z3
+        y3 = f.add(&y3, &t2);          // Y3 := Y3 + t2
+        x3 = f.mul(&t3, &x3);          // X3 := t3 * X3
+        x3 = f.sub(&x3, &t1);          // X3 := X3 - t1
+        z3 = f.mul(&t4, &z3);          // Z3 := t4 * Z3
+        t1 = f.mul(&t3, &t0);          // t1 := t3 * t0
+        z3 = f.add(&z3, &t1);          // Z3 := Z3 + t1
+
+        Point { curve: self.curve, x: x3, y: y3, z: z3 }
+    }
+
+    /// Returns 2 * self.
+    pub fn double(&self) -> Point<'a> {
+        // Complete addition formula for a = -3 from "Complete addition
+        // formulas for prime order elliptic curves"
+        // (https://eprint.iacr.org/2015/1060), Algorithm 6.
+        let f = &self.curve.p;
+        let b = &self.curve.b;
+        let (x, y, z) = (&self.x, &self.y, &self.z);
+
+        let mut t0 = f.square(x);      // t0 := X ^ 2
+        let t1 = f.square(y);          // t1 := Y ^ 2
+        let mut t2 = f.square(z);      // t2 := Z ^ 2
+        let mut t3 = f.mul(x, y);      // t3 := X * Y
+        t3 = f.add(&t3, &t3);          // t3 := t3 + t3
+        let mut z3 = f.mul(x, z);      // Z3 := X * Z
+        z3 = f.add(&z3, &z3);          // Z3 := Z3 + Z3
+        let mut y3 = f.mul(b, &t2);    // Y3 := b * t2
+        y3 = f.sub(&y3, &z3);          // Y3 := Y3 - Z3
+        let mut x3 = f.add(&y3, &y3);  // X3 := Y3 + Y3
+        y3 = f.add(&x3, &y3);          // Y3 := X3 + Y3
+        x3 = f.sub(&t1, &y3);          // X3 := t1 - Y3
+        y3 = f.add(&t1, &y3);          // Y3 := t1 + Y3
+        y3 = f.mul(&x3, &y3);          // Y3 := X3 * Y3
+        x3 = f.mul(&x3, &t3);          // X3 := X3 * t3
+        t3 = f.add(&t2, &t2);          // t3 := t2 + t2
+        t2 = f.add(&t2, &t3);          // t2 := t2 + t3
+        z3 = f.mul(b, &z3);            // Z3 := b * Z3
+        z3 = f.sub(&z3, &t2);          // Z3 := Z3 - t2
+        z3 = f.sub(&z3, &t0);          // Z3 := Z3 - t0
+        t3 = f.add(&z3, &z3);          // t3 := Z3 + Z3
+        z3 = f.add(&z3, &t3);          // Z3 := Z3 + t3
+        t3 = f.add(&t0, &t0);          // t3 := t0 + t0
+        t0 = f.add(&t3, &t0);          // t0 := t3 + t0
+        t0 = f.sub(&t0, &t2);          // t0 := t0 - t2
+        t0 = f.mul(&t0, &z3);          // t0 := t0 * Z3
+        y3 = f.add(&y3, &t0);          // Y3 := Y3 + t0
+        t0 = f.mul(y, z);              // t0 := Y * Z
+        t0 = f.add(&t0, &t0);          // t0 := t0 + t0
+        z3 = f.mul(&t0, &z3);          // Z3 := t0 * Z3
+        x3 = f.sub(&x3, &z3);          // X3 := X3 - Z3
+        z3 = f.mul(&t0, &t1);          // Z3 := t0 * t1
+        z3 = f.add(&z3, &z3);          // Z3 := Z3 + Z3
+        z3 = f.add(&z3, &z3);          // Z3 := Z3 + Z3
+
+        Point { curve: self.curve, x: x3, y: y3, z: z3 }
+    }
+
+    /// Returns scalar * self, where scalar is a big-endian value of the
+    /// length of the curve's order. It runs in constant time.
+    pub fn scalar_mult(&self, scalar: &[u8]) -> Point<'a> {
+        if scalar.len() != self.curve.n.byte_len() {
+            fail!("crypto/elliptic: invalid scalar length");
+        }
+        // Compute a table for the base point self: [0]self, [1]self, ...,
+        // [15]self, and walk the scalar four bits at a time, selecting
+        // the table entry in constant time.
+        let mut table = [Point::identity(self.curve), ..16];
+        table[1] = *self;
+        for i in range(2, 16u) {
+            table[i] = if i % 2 == 0 { table[i / 2].double() } else { table[i - 1].add(self) };
+        }
+
+        let mut q = Point::identity(self.curve);
+        for &byte in scalar.iter() {
+            for &window in [byte >> 4, byte & 0xf].iter() {
+                q = q.double().double().double().double();
+                let mut t = Point::identity(self.curve);
+                for (j, entry) in table.iter().enumerate() {
+                    let cond = constant_time_eq(j as i32, window as i32) as u32;
+                    t = Point::select(cond, entry, &t);
+                }
+                q = q.add(&t);
+            }
+        }
+        q
+    }
+
+    /// Returns scalar * G, where G is the generator of the curve and
+    /// scalar is a big-endian value of the length of the curve's order.
+    pub fn scalar_base_mult(c: &'a Curve, scalar: &[u8]) -> Point<'a> {
+        Point::generator(c).scalar_mult(scalar)
+    }
+}
+
+impl Curve {
+    /// Returns the parameters of the curve as big integers.
+    pub fn params(&self) -> CurveParams {
+        CurveParams {
+            p: modulus(&self.p),
+            n: modulus(&self.n),
+            b: Int::from_bytes(self.p.bytes(&self.b)),
+            gx: Int::from_bytes(self.p.bytes(&self.gx)),
+            gy: Int::from_bytes(self.p.bytes(&self.gy)),
+            bit_size: self.p.bits,
+            name: self.name,
+        }
+    }
+
+    // Returns the point (x, y), treating (0, 0) as the point at infinity,
+    // or None if it is not on the curve.
+    fn point_from_affine<'a>(&'a self, x: &Int, y: &Int) -> Option<Point<'a>> {
+        if x.sign() == 0 && y.sign() == 0 {
+            return Some(Point::identity(self));
+        }
+        let n = self.p.byte_len();
+        if x.sign() < 0 || y.sign() < 0 || x.bit_len() > self.p.bits || y.bit_len() > self.p.bits {
+            return None;
+        }
+        let mut buf = ::std::slice::from_elem(1 + 2 * n, 0u8);
+        buf[0] = 4;
+        x.fill_bytes(buf.mut_slice(1, 1 + n));
+        y.fill_bytes(buf.mut_slice_from(1 + n));
+        Point::set_bytes(self, buf)
+    }
+
+    fn point_to_affine(&self, p: &Point) -> (Int, Int) {
+        let n = self.p.byte_len();
+        let b = p.bytes();
+        if b.len() == 1 {
+            return (Int::new(0), Int::new(0));
+        }
+        (Int::from_bytes(b.slice(1, 1 + n)), Int::from_bytes(b.slice_from(1 + n)))
+    }
+
+    fn must_point<'a>(&'a self, x: &Int, y: &Int, op: &str) -> Point<'a> {
+        match self.point_from_affine(x, y) {
+            Some(p) => p,
+            None => fail!("crypto/elliptic: {} was called on an invalid point", op),
+        }
+    }
+
+    // Reduces a scalar of any length modulo n and pads it to the length
+    // of the order.
+    fn normalize_scalar(&self, k: &[u8]) -> ~[u8] {
+        let n = self.n.byte_len();
+        let mut k = Int::from_bytes(k);
+        if k.bit_len() > self.n.bits {
+            k = k.modulo(&modulus(&self.n));
+        }
+        let mut out = ::std::slice::from_elem(n, 0u8);
+        k.fill_bytes(out);
+        out
+    }
+
+    /// Reports whether the given (x, y) lies on the curve.
+    pub fn is_on_curve(&self, x: &Int, y: &Int) -> bool {
+        // (0, 0) is the point at infinity by convention. It's ok to
+        // operate on it, although it's not on the curve.
+        !(x.sign() == 0 && y.sign() == 0) && self.point_from_affine(x, y).is_some()
+    }
+
+    /// Returns the sum of (x1, y1) and (x2, y2). Fails if either is not
+    /// on the curve.
+    pub fn add(&self, x1: &Int, y1: &Int, x2: &Int, y2: &Int) -> (Int, Int) {
+        let p1 = self.must_point(x1, y1, "Add");
+        let p2 = self.must_point(x2, y2, "Add");
+        self.point_to_affine(&p1.add(&p2))
+    }
+
+    /// Returns 2 * (x, y). Fails if it is not on the curve.
+    pub fn double(&self, x: &Int, y: &Int) -> (Int, Int) {
+        let p = self.must_point(x, y, "Double");
+        self.point_to_affine(&p.double())
+    }
+
+    /// Returns k * (x, y) where k is a big-endian integer. Fails if the
+    /// point is not on the curve.
+    pub fn scalar_mult(&self, x: &Int, y: &Int, k: &[u8]) -> (Int, Int) {
+        let p = self.must_point(x, y, "ScalarMult");
+        self.point_to_affine(&p.scalar_mult(self.normalize_scalar(k)))
+    }
+
+    /// Returns k * G, where G is the base point of the curve and k is a
+    /// big-endian integer.
+    pub fn scalar_base_mult(&self, k: &[u8]) -> (Int, Int) {
+        self.point_to_affine(&Point::scalar_base_mult(self, self.normalize_scalar(k)))
+    }
+}
+
+/// Returns a public/private key pair. The private key is generated using
+/// the given reader, which must return random data.
+pub fn generate_key<R: Reader>(curve: &Curve, rand: &mut R) -> IoResult<(~[u8], Int, Int)> {
+    let n = curve.n.byte_len();
+    let top_mask = match curve.n.bits % 8 {
+        0 => 0xffu8,
+        r => (1u8 << r) - 1,
+    };
+    let order = modulus(&curve.n);
+    loop {
+        let mut k = ::std::slice::from_elem(n, 0u8);
+        try!(rand.fill(k));
+        // We have to mask off any excess bits in the case that the size
+        // of the order is not a multiple of 8.
+        k[0] &= top_mask;
+        let v = Int::from_bytes(k);
+        if v.sign() == 0 || v >= order {
+            continue;
+        }
+        let (x, y) = curve.scalar_base_mult(k);
+        return Ok((k, x, y));
+    }
+}
+
+/// Converts a point on the curve into the uncompressed form specified in
+/// SEC 1, Version 2.0, Section 2.3.3. Fails if the point is not on the
+/// curve (or is the conventional point at infinity).
+pub fn marshal(curve: &Curve, x: &Int, y: &Int) -> ~[u8] {
+    if !curve.is_on_curve(x, y) {
+        fail!("crypto/elliptic: attempted operation on invalid point");
+    }
+    curve.must_point(x, y, "Marshal").bytes()
+}
+
+/// Converts a point on the curve into the compressed form specified in
+/// SEC 1, Version 2.0, Section 2.3.3. Fails if the point is not on the
+/// curve (or is the conventional point at infinity).
+pub fn marshal_compressed(curve: &Curve, x: &Int, y: &Int) -> ~[u8] {
+    if !curve.is_on_curve(x, y) {
+        fail!("crypto/elliptic: attempted operation on invalid point");
+    }
+    curve.must_point(x, y, "MarshalCompressed").bytes_compressed()
+}
+
+/// Converts a point, serialized by marshal, into an x, y pair. It is an
+/// error if the point is not in uncompressed form, is not on the curve,
+/// or is the point at infinity. On error, None is returned.
+pub fn unmarshal(curve: &Curve, data: &[u8]) -> Option<(Int, Int)> {
+    if data.len() == 0 || data[0] != 4 {
+        return None;
+    }
+    Point::set_bytes(curve, data).map(|p| curve.point_to_affine(&p))
+}
+
+/// Converts a point, serialized by marshal_compressed, into an x, y
+/// pair. It is an error if the point is not in compressed form, is not on
+/// the curve, or is the point at infinity. On error, None is returned.
+pub fn unmarshal_compressed(curve: &Curve, data: &[u8]) -> Option<(Int, Int)> {
+    if data.len() == 0 || (data[0] != 2 && data[0] != 3) {
+        return None;
+    }
+    Point::set_bytes(curve, data).map(|p| curve.point_to_affine(&p))
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use math::big::Int;
+    use super::*;
+    use super::super::rand::Reader;
+
+    fn int(s: &str) -> Int {
+        Int::from_str_radix(s, 16).unwrap()
+    }
+
+    fn curves() -> [&'static Curve, ..3] {
+        [p256(), p384(), p521()]
+    }
+
+    #[test]
+    fn test_scalar_base_mult() {
+        // computed with OpenSSL
+        let tests = [
+            (p256(),
+             "b00d690386cb1c0a7d4c0c8640ffea1dd37ddb68c04cff70acc148c32cc62473",
+             "4282f94e9cd5d90f560e751ab7a426ec61b82b342ba72548b4480f6fac26ae72",
+             "5c22d0f5963fb85cfaaa9bd8967ef7bbe53bb40625133ebccb2a4870678c46b5"),
+            (p384(),
+             "758e0da83ac0bfd3f5bcf6419ff54feffff4d096036f9d20bf5d16ec2f9f155f\
+              2a7fb14bf5d0518e87af872cf7839c01",
+             "e910f907be441bbaaa3c632ef5ab7f2e8be73f6be9a5f5728b78b3e3f9bdf463\
+              a6b6d05f4ba3a1178e192191735f2541",
+             "ab3f3ae05658cd14b8e332b689a93c7835c3d6f21d08404b2df1256317827fb0\
+              cd0f7927e155ac9e50fe1c13c4c65127"),
+            (p521(),
+             "00dbde59424fb024289280967ee8e636f6ea3f9252752b292fb279485532747a\
+              758160358a644bf1cc40a41c4cba817b757baa6e3fce6dcdcfab59a37349f821\
+              216d",
+             "01fb936ed817e39d4a0591bfa981e10aace898ed692913df14f98d282f6ba190\
+              30319fce5c3adc2488773df56d7a23719fd531e123aceafd2713e7cde9c9d4a7\
+              ef83",
+             "00276d6a5868842236bca4988d8cfe70a4f9655a62a0c580c720d93bdaea14ba\
+              8582fcb70156e5565857142cc57ddf2749f89c0c463092a50a64ce233e1ff8ea\
+              7214"),
+        ];
+        for &(c, k, x, y) in tests.iter() {
+            let k = k.from_hex().unwrap();
+            let p = Point::scalar_base_mult(c, k);
+            assert_eq!(p.bytes_x().unwrap().to_hex(), x.to_owned());
+            assert_eq!(p.bytes().slice_from(1).to_hex(), x.to_owned() + y);
+            assert_eq!(c.scalar_base_mult(k), (int(x), int(y)));
+            let params = c.params();
+            assert_eq!(c.scalar_mult(&params.gx, &params.gy, k), (int(x), int(y)));
+            assert!(c.is_on_curve(&int(x), &int(y)));
+            assert!(!c.is_on_curve(&int(x), &(int(y) + Int::new(1))));
+        }
+    }
+
+    #[test]
+    fn test_group_law() {
+        for &c in curves().iter() {
+            let params = c.params();
+            assert_eq!(params.n.bit_len(), params.bit_size);
+            assert!(c.is_on_curve(&params.gx, &params.gy));
+            let g = Point::generator(c);
+            let id = Point::identity(c);
+
+            // n * G is the identity, and (n-1) * G is -G
+            let n = params.n.bytes();
+            assert!(Point::scalar_base_mult(c, n).is_identity());
+            let nm1 = (params.n - Int::new(1)).bytes();
+            let neg = Point::scalar_base_mult(c, nm1);
+            assert_eq!(neg.bytes_x(), g.bytes_x());
+            assert!(neg.add(&g).is_identity());
+            assert!(g.bytes() != neg.bytes());
+
+            // the complete formulas handle the exceptional cases
+            assert_eq!(g.add(&g).bytes(), g.double().bytes());
+            assert_eq!(g.add(&id).bytes(), g.bytes());
+            assert_eq!(id.add(&g).bytes(), g.bytes());
+            assert!(id.double().is_identity());
+            assert_eq!(id.bytes(), ~[0u8]);
+            assert_eq!(id.bytes_compressed(), ~[0u8]);
+            assert!(id.bytes_x().is_none());
+
+            let mut three = ::std::slice::from_elem(c.n.byte_len(), 0u8);
+            *three.mut_last().unwrap() = 3;
+            let p3 = g.double().add(&g);
+            assert_eq!(Point::scalar_base_mult(c, three).bytes(), p3.bytes());
+            assert_eq!(c.double(&params.gx, &params.gy),
+                       c.add(&params.gx, &params.gy, &params.gx, &params.gy));
+            assert_eq!(c.add(&Int::new(0), &Int::new(0), &params.gx, &params.gy),
+                       (params.gx.clone(), params.gy.clone()));
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        for &c in curves().iter() {
+            let n = c.p.byte_len();
+            for i in range(1, 20) {
+                let k = ::std::slice::from_elem(c.n.byte_len(), i as u8);
+                let p = Point::scalar_base_mult(c, k);
+                let full = p.bytes();
+                let compressed = p.bytes_compressed();
+                assert_eq!(full.len(), 1 + 2 * n);
+                assert_eq!(compressed.len(), 1 + n);
+                assert_eq!(Point::set_bytes(c, full).unwrap().bytes(), full);
+                assert_eq!(Point::set_bytes(c, compressed).unwrap().bytes(), full);
+
+                let (x, y) = unmarshal(c, full).unwrap();
+                assert_eq!(marshal(c, &x, &y), full);
+                assert_eq!(marshal_compressed(c, &x, &y), compressed);
+                assert_eq!(unmarshal_compressed(c, compressed), Some((x, y)));
+                assert!(unmarshal(c, compressed).is_none());
+                assert!(unmarshal_compressed(c, full).is_none());
+            }
+
+            let g = Point::generator(c).bytes();
+            // off the curve
+            let mut bad = g.clone();
+            bad[1 + n] ^= 1;
+            assert!(Point::set_bytes(c, bad).is_none());
+            assert!(unmarshal(c, bad).is_none());
+            // coordinates not below p
+            let mut bad = g.clone();
+            for i in range(1, 1 + n) {
+                bad[i] = 0xff;
+            }
+            assert!(Point::set_bytes(c, bad).is_none());
+            // wrong lengths and prefixes
+            assert!(Point::set_bytes(c, g.slice_to(2 * n)).is_none());
+            assert!(Point::set_bytes(c, []).is_none());
+            let mut bad = g.clone();
+            bad[0] = 5;
+            assert!(Point::set_bytes(c, bad).is_none());
+            assert!(Point::set_bytes(c, [0u8]).unwrap().is_identity());
+            assert!(unmarshal(c, [0u8]).is_none());
+        }
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_invalid_point() {
+        let c = p256();
+        let params = c.params();
+        c.double(&params.gx, &(params.gy + Int::new(1)));
+    }
+
+    #[test]
+    fn test_generate_key() {
+        for &c in curves().iter() {
+            let (k, x, y) = generate_key(c, &mut Reader).unwrap();
+            assert_eq!(k.len(), c.n.byte_len());
+            assert!(c.is_on_curve(&x, &y));
+            assert_eq!(c.scalar_base_mult(k), (x, y));
+        }
+    }
+}
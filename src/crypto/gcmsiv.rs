@@ -0,0 +1,318 @@
+// AES-GCM-SIV, the nonce misuse-resistant AEAD of RFC 8452.
+//
+// Every message derives fresh authentication and encryption keys from the
+// key-generating key and the nonce. The tag is computed over the plaintext
+// with POLYVAL and doubles as the initial counter, so repeating a nonce
+// only reveals whether two messages were identical.
+//
+// POLYVAL is evaluated with the GHASH multiplication from the ghash
+// module, using the mapping in RFC 8452 appendix A.
+
+use std::slice;
+use super::aes::Aes;
+use super::cipher::{AEAD, Block};
+use super::cpu;
+use super::ghash::{mul_generic, mul_clmul};
+use super::subtle::constant_time_compare;
+
+/// The size of the nonce used with AES-GCM-SIV, in bytes.
+pub static NonceSize: uint = 12;
+
+/// The size of the authentication tag, in bytes.
+pub static Overhead: uint = 16;
+
+// Plaintexts and additional data are limited to 2^36 bytes.
+static maxLength: u64 = 1 << 36;
+
+/// An AEAD which runs AES in GCM-SIV mode.
+pub struct AesGcmSiv {
+    key: Aes,
+    key_size: uint,
+    clmul: bool,
+}
+
+impl AesGcmSiv {
+    /// Returns an AES-GCM-SIV AEAD with the given key-generating key,
+    /// which must be 16 or 32 bytes long to select AEAD_AES_128_GCM_SIV
+    /// or AEAD_AES_256_GCM_SIV.
+    pub fn new(key: &[u8]) -> Result<AesGcmSiv, ~str> {
+        match key.len() {
+            16 | 32 => {}
+            n => return Err(format!("gcmsiv: invalid key size {}", n)),
+        }
+        Ok(AesGcmSiv { key: try!(Aes::new(key)), key_size: key.len(),
+                       clmul: cpu::has_pclmulqdq() })
+    }
+
+    // Derives the per-nonce message authentication key and the message
+    // encryption cipher, RFC 8452 section 4.
+    fn derive_keys(&self, nonce: &[u8]) -> ([u8, ..16], Aes) {
+        let mut keys = Vec::with_capacity(16 + self.key_size);
+        let mut input = [0u8, ..16];
+        let mut output = [0u8, ..16];
+        input.mut_slice_from(4).copy_from(nonce);
+        for i in range(0, 2 + self.key_size / 8) {
+            input[0] = i as u8;
+            self.key.encrypt(output.as_mut_slice(), input.as_slice());
+            keys.push_all(output.slice_to(8));
+        }
+        let mut auth_key = [0u8, ..16];
+        auth_key.as_mut_slice().copy_from(keys.as_slice().slice_to(16));
+        let enc = Aes::new(keys.as_slice().slice_from(16)).unwrap();
+        (auth_key, enc)
+    }
+
+    fn tag(&self, auth_key: &[u8, ..16], enc: &Aes, nonce: &[u8], plaintext: &[u8],
+           additional_data: &[u8]) -> [u8, ..16] {
+        let mut p = Polyval::new(auth_key, self.clmul);
+        p.update(additional_data);
+        p.update(plaintext);
+        let mut lengths = [0u8, ..16];
+        let ad_bits = (additional_data.len() as u64) << 3;
+        let pt_bits = (plaintext.len() as u64) << 3;
+        for i in range(0, 8u) {
+            lengths[i] = (ad_bits >> (8 * i)) as u8;
+            lengths[8 + i] = (pt_bits >> (8 * i)) as u8;
+        }
+        p.update(lengths);
+
+        let mut s = p.sum();
+        for i in range(0, NonceSize) {
+            s[i] ^= nonce[i];
+        }
+        s[15] &= 0x7f;
+        let mut tag = [0u8, ..16];
+        enc.encrypt(tag.as_mut_slice(), s.as_slice());
+        tag
+    }
+}
+
+// CTR mode with the tag as the initial counter block, its top bit set and
+// a 32-bit little-endian counter in the first four bytes.
+fn counter_crypt(enc: &Aes, tag: &[u8, ..16], dst: &mut [u8], src: &[u8]) {
+    let mut counter = *tag;
+    counter[15] |= 0x80;
+    let mut mask = [0u8, ..16];
+    for (i, p) in src.chunks(16).enumerate() {
+        enc.encrypt(mask.as_mut_slice(), counter.as_slice());
+        for j in range(0, 4u) {
+            counter[j] += 1;
+            if counter[j] != 0 {
+                break;
+            }
+        }
+        for j in range(0, p.len()) {
+            dst[i * 16 + j] = p[j] ^ mask[j];
+        }
+    }
+}
+
+impl AEAD for AesGcmSiv {
+    fn nonce_size(&self) -> uint {
+        NonceSize
+    }
+
+    fn overhead(&self) -> uint {
+        Overhead
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != NonceSize {
+            fail!("gcmsiv: incorrect nonce length given to AES-GCM-SIV");
+        }
+        if plaintext.len() as u64 > maxLength || additional_data.len() as u64 > maxLength {
+            fail!("gcmsiv: message too large for AES-GCM-SIV");
+        }
+        let (auth_key, enc) = self.derive_keys(nonce);
+        let tag = self.tag(&auth_key, &enc, nonce, plaintext, additional_data);
+        let mut ct = slice::from_elem(plaintext.len(), 0u8);
+        counter_crypt(&enc, &tag, ct.as_mut_slice(), plaintext);
+
+        let mut ret = Vec::with_capacity(dst.len() + ct.len() + Overhead);
+        ret.push_all(dst);
+        ret.push_all(ct.as_slice());
+        ret.push_all(tag.as_slice());
+        ret.as_slice().to_owned()
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != NonceSize {
+            fail!("gcmsiv: incorrect nonce length given to AES-GCM-SIV");
+        }
+        if ciphertext.len() < Overhead ||
+           ciphertext.len() as u64 > maxLength + Overhead as u64 ||
+           additional_data.len() as u64 > maxLength {
+            return Err(~"gcmsiv: message authentication failed");
+        }
+        let (ct, tag) = (ciphertext.slice_to(ciphertext.len() - Overhead),
+                         ciphertext.slice_from(ciphertext.len() - Overhead));
+        let mut t = [0u8, ..16];
+        t.as_mut_slice().copy_from(tag);
+
+        let (auth_key, enc) = self.derive_keys(nonce);
+        let mut pt = slice::from_elem(ct.len(), 0u8);
+        counter_crypt(&enc, &t, pt.as_mut_slice(), ct);
+        let expected = self.tag(&auth_key, &enc, nonce, pt.as_slice(), additional_data);
+        if !constant_time_compare(expected.as_slice(), tag) {
+            return Err(~"gcmsiv: message authentication failed");
+        }
+        let mut ret = Vec::with_capacity(dst.len() + pt.len());
+        ret.push_all(dst);
+        ret.push_all(pt.as_slice());
+        Ok(ret.as_slice().to_owned())
+    }
+}
+
+// POLYVAL(H, X_1, ..., X_n) is ByteReverse(GHASH(mulX_GHASH(ByteReverse(H)),
+// ByteReverse(X_1), ..., ByteReverse(X_n))).
+struct Polyval {
+    h: [u8, ..16],
+    y: [u8, ..16],
+    clmul: bool,
+}
+
+impl Polyval {
+    fn new(key: &[u8, ..16], clmul: bool) -> Polyval {
+        let mut h = [0u8, ..16];
+        for i in range(0, 16u) {
+            h[i] = key[15 - i];
+        }
+        // multiply by x in GHASH's reflected bit order
+        let lsb = h[15] & 1;
+        for i in range(0, 16u).rev() {
+            h[i] = (h[i] >> 1) | if i > 0 { h[i - 1] << 7 } else { 0 };
+        }
+        h[0] ^= 0xe1 & (0u8 - lsb);
+        Polyval { h: h, y: [0u8, ..16], clmul: clmul }
+    }
+
+    // Absorbs data, zero padding the last block.
+    fn update(&mut self, data: &[u8]) {
+        for p in data.chunks(16) {
+            let mut b = [0u8, ..16];
+            b.mut_slice_to(p.len()).copy_from(p);
+            for i in range(0, 16u) {
+                self.y[i] ^= b[15 - i];
+            }
+            if self.clmul {
+                mul_clmul(self.y.as_mut_slice(), self.h.as_slice());
+            } else {
+                mul_generic(self.y.as_mut_slice(), self.h.as_slice());
+            }
+        }
+    }
+
+    fn sum(&self) -> [u8, ..16] {
+        let mut out = [0u8, ..16];
+        for i in range(0, 16u) {
+            out[i] = self.y[15 - i];
+        }
+        out
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::Polyval;
+    use super::super::cipher::AEAD;
+    use super::super::cpu;
+
+    // RFC 8452 appendix C
+    static tests: &'static [(&'static str, &'static str, &'static str, &'static str, &'static str)] = &[
+        // key, nonce, plaintext, additional data, ciphertext || tag
+        ("01000000000000000000000000000000",
+         "030000000000000000000000",
+         "",
+         "",
+         "dc20e2d83f25705bb49e439eca56de25"),
+        ("01000000000000000000000000000000",
+         "030000000000000000000000",
+         "0100000000000000",
+         "",
+         "b5d839330ac7b786578782fff6013b815b287c22493a364c"),
+        ("01000000000000000000000000000000",
+         "030000000000000000000000",
+         "0200000000000000",
+         "01",
+         "1e6daba35669f4273b0a1a2560969cdf790d99759abd1508"),
+        ("0100000000000000000000000000000000000000000000000000000000000000",
+         "030000000000000000000000",
+         "",
+         "",
+         "07f5f4169bbf55a8400cd47ea6fd400f"),
+        ("0100000000000000000000000000000000000000000000000000000000000000",
+         "030000000000000000000000",
+         "0100000000000000",
+         "",
+         "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28"),
+        ("0100000000000000000000000000000000000000000000000000000000000000",
+         "030000000000000000000000",
+         "0200000000000000000000000000000003000000000000000000000000000000\
+          0400000000000000",
+         "010000000000000000000000",
+         "4a010a7e49b20f5bd702e3d4637ded01b59c4ff403ab4d2144c1696f71f3a6f3\
+          119f04e827428fa9eb284570821144e55e3546fd464a5ab0"),
+        // counter wrap, RFC 8452 appendix C.3
+        ("0000000000000000000000000000000000000000000000000000000000000000",
+         "000000000000000000000000",
+         "000000000000000000000000000000004db923dc793ee6497c76dcc03a98e108",
+         "",
+         "f3f80f2cf0cb2dd9c5984fcda908456cc537703b5ba70324a6793a7bf218d3ea\
+          ffffffff000000000000000000000000"),
+    ];
+
+    fn run(clmul: bool) {
+        for &(key, nonce, pt, ad, ct) in tests.iter() {
+            let key = key.from_hex().unwrap();
+            let nonce = nonce.from_hex().unwrap();
+            let pt = pt.from_hex().unwrap();
+            let ad = ad.from_hex().unwrap();
+            let mut a = AesGcmSiv::new(key.as_slice()).unwrap();
+            a.clmul = clmul;
+            let out = a.seal([], nonce.as_slice(), pt.as_slice(), ad.as_slice());
+            assert_eq!(out.as_slice().to_hex(), ct.to_owned());
+            let back = a.open([], nonce.as_slice(), out.as_slice(), ad.as_slice()).unwrap();
+            assert_eq!(back.as_slice(), pt.as_slice());
+
+            let mut bad = out.clone();
+            let n = bad.len();
+            bad[n - 1] ^= 0x80;
+            assert_eq!(a.open([], nonce.as_slice(), bad.as_slice(), ad.as_slice()).err(),
+                       Some(~"gcmsiv: message authentication failed"));
+            let mut bad_ad = ad.clone();
+            bad_ad.push(0);
+            assert!(a.open([], nonce.as_slice(), out.as_slice(), bad_ad.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_gcmsiv_generic() {
+        run(false);
+    }
+
+    #[test]
+    fn test_gcmsiv_clmul() {
+        if cpu::has_pclmulqdq() {
+            run(true);
+        }
+    }
+
+    #[test]
+    fn test_polyval() {
+        // RFC 8452 appendix A
+        let mut h = [0u8, ..16];
+        h.as_mut_slice().copy_from("25629347589242761d31f826ba4b757b".from_hex().unwrap().as_slice());
+        let mut p = Polyval::new(&h, false);
+        p.update("4f4f95668c83dfb6401762bb2d01a262d1a24ddd2721d006bbe45f20d3c9f362".from_hex().unwrap().as_slice());
+        assert_eq!(p.sum().as_slice().to_hex(), ~"f7a3b47b846119fae5b7866cf5e5b77e");
+    }
+
+    #[test]
+    fn test_key_size() {
+        assert_eq!(AesGcmSiv::new([0u8, ..24]).err(), Some(~"gcmsiv: invalid key size 24"));
+    }
+}
@@ -0,0 +1,144 @@
+// Multiplication in GF(2^128) with GCM's bit order, shared by GHASH in
+// cipher::gcm and POLYVAL in gcmsiv.
+//
+// mul_clmul needs PCLMULQDQ and SSSE3; callers check cpu::has_pclmulqdq
+// once and pick an implementation.
+
+fn load64(b: &[u8]) -> u64 {
+    let mut v = 0u64;
+    for i in range(0, 8u) {
+        v = (v << 8) | b[i] as u64;
+    }
+    v
+}
+
+fn store64(b: &mut [u8], v: u64) {
+    for i in range(0, 8u) {
+        b[i] = (v >> (56 - 8 * i)) as u8;
+    }
+}
+
+// x = x * h in GF(2^128), SP 800-38D algorithm 1, with masks in place of
+// branches.
+pub fn mul_generic(x: &mut [u8], h: &[u8]) {
+    let (xh, xl) = (load64(x.slice_to(8)), load64(x.slice(8, 16)));
+    let (mut vh, mut vl) = (load64(h.slice_to(8)), load64(h.slice(8, 16)));
+    let (mut zh, mut zl) = (0u64, 0u64);
+    for i in range(0, 128u) {
+        let bit = if i < 64 { (xh >> (63 - i)) & 1 } else { (xl >> (127 - i)) & 1 };
+        let m = 0u64 - bit;
+        zh ^= vh & m;
+        zl ^= vl & m;
+        let lsb = vl & 1;
+        vl = (vl >> 1) | (vh << 63);
+        vh = (vh >> 1) ^ (0xe100000000000000u64 & (0u64 - lsb));
+    }
+    store64(x.mut_slice_to(8), zh);
+    store64(x.mut_slice(8, 16), zl);
+}
+
+static bswapMask: [u8, ..16] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+
+// x = x * h with PCLMULQDQ. Both operands are byte reversed, multiplied
+// into a 256-bit product, shifted left by one to undo the bit reflection
+// and reduced modulo x^128 + x^7 + x^2 + x + 1, following Gueron and
+// Kounavis, "Intel Carry-Less Multiplication Instruction and its Usage
+// for Computing the GCM Mode".
+#[cfg(target_arch = "x86_64")]
+pub fn mul_clmul(x: &mut [u8], h: &[u8]) {
+    assert!(x.len() >= 16 && h.len() >= 16);
+    unsafe {
+        asm!("movdqu ($2), %xmm7
+              movdqu ($0), %xmm0
+              movdqu ($1), %xmm1
+              pshufb %xmm7, %xmm0
+              pshufb %xmm7, %xmm1
+              movdqa %xmm0, %xmm3
+              pclmulqdq $$0x00, %xmm1, %xmm3
+              movdqa %xmm0, %xmm4
+              pclmulqdq $$0x10, %xmm1, %xmm4
+              movdqa %xmm0, %xmm5
+              pclmulqdq $$0x01, %xmm1, %xmm5
+              movdqa %xmm0, %xmm6
+              pclmulqdq $$0x11, %xmm1, %xmm6
+              pxor %xmm5, %xmm4
+              movdqa %xmm4, %xmm5
+              pslldq $$8, %xmm5
+              psrldq $$8, %xmm4
+              pxor %xmm5, %xmm3
+              pxor %xmm4, %xmm6
+              movdqa %xmm3, %xmm4
+              movdqa %xmm6, %xmm5
+              psrld $$31, %xmm4
+              psrld $$31, %xmm5
+              pslld $$1, %xmm3
+              pslld $$1, %xmm6
+              movdqa %xmm4, %xmm2
+              psrldq $$12, %xmm2
+              pslldq $$4, %xmm5
+              pslldq $$4, %xmm4
+              por %xmm4, %xmm3
+              por %xmm5, %xmm6
+              por %xmm2, %xmm6
+              movdqa %xmm3, %xmm4
+              movdqa %xmm3, %xmm5
+              movdqa %xmm3, %xmm2
+              pslld $$31, %xmm4
+              pslld $$30, %xmm5
+              pslld $$25, %xmm2
+              pxor %xmm5, %xmm4
+              pxor %xmm2, %xmm4
+              movdqa %xmm4, %xmm5
+              psrldq $$4, %xmm5
+              pslldq $$12, %xmm4
+              pxor %xmm4, %xmm3
+              movdqa %xmm3, %xmm2
+              movdqa %xmm3, %xmm4
+              movdqa %xmm3, %xmm1
+              psrld $$1, %xmm2
+              psrld $$2, %xmm4
+              psrld $$7, %xmm1
+              pxor %xmm4, %xmm2
+              pxor %xmm1, %xmm2
+              pxor %xmm5, %xmm2
+              pxor %xmm2, %xmm3
+              pxor %xmm3, %xmm6
+              pshufb %xmm7, %xmm6
+              movdqu %xmm6, ($0)"
+             :
+             : "r"(x.as_mut_ptr()), "r"(h.as_ptr()), "r"(bswapMask.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "memory"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn mul_clmul(_x: &mut [u8], _h: &[u8]) {
+    unreachable!();
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::cpu;
+
+    #[test]
+    fn test_ghash_mul() {
+        if !cpu::has_pclmulqdq() {
+            return;
+        }
+        let mut x = [0u8, ..16];
+        let mut h = [0u8, ..16];
+        for i in range(0, 100u) {
+            for j in range(0, 16u) {
+                x[j] = (i * 31 + j * 17 + 5) as u8;
+                h[j] = (i * 13 + j * 29 + 1) as u8;
+            }
+            let mut y = x;
+            mul_generic(x.as_mut_slice(), h.as_slice());
+            mul_clmul(y.as_mut_slice(), h.as_slice());
+            assert_eq!(x.as_slice(), y.as_slice());
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// HMAC-based Extract-and-Expand Key Derivation Function (HKDF) as defined
+// in RFC 5869.
+//
+// HKDF is a cryptographic key derivation function (KDF) with the goal of
+// expanding limited input keying material into one or more cryptographically
+// strong secret keys.
+
+use std::slice;
+use super::hmac::Hmac;
+use super::super::hash::*;
+
+
+/// Generates a pseudorandom key for use with `expand` from an input secret
+/// and an optional independent salt.
+///
+/// Only use this function if you need to reuse the extracted key with
+/// multiple `expand` invocations and different context values. Most common
+/// scenarios, including the generation of multiple keys, should use `key`
+/// instead.
+pub fn extract<H: Hash + Clone>(h: H, secret: &[u8], salt: &[u8]) -> ~[u8] {
+    let null_salt;
+    let salt = if salt.len() == 0 {
+        null_salt = slice::from_elem(h.size(), 0u8);
+        null_salt.as_slice()
+    } else {
+        salt
+    };
+    let mut extractor = Hmac::new(h, salt);
+    extractor.write(secret);
+    extractor.digest()
+}
+
+/// Derives a key from the given hash, key, and optional context info,
+/// returning a `key_len` byte long key.
+///
+/// The key should have been generated by `extract`, or be a uniformly
+/// random or pseudorandom cryptographically strong key. See RFC 5869,
+/// Section 3.3. Most common scenarios will want to use `key` instead.
+pub fn expand<H: Hash + Clone>(h: H, pseudorandom_key: &[u8], info: &[u8],
+                               key_len: uint) -> Result<~[u8], ~str> {
+    let limit = h.size() * 255;
+    if key_len > limit {
+        return Err(~"hkdf: requested key length too large");
+    }
+
+    let mut expander = Hmac::new(h, pseudorandom_key);
+    let mut out = Vec::with_capacity(key_len);
+    let mut buf: ~[u8] = ~[];
+    let mut counter = 0u8;
+    while out.len() < key_len {
+        counter += 1;
+        if counter > 1 {
+            expander.reset();
+        }
+        expander.write(buf.as_slice());
+        expander.write(info);
+        expander.write([counter]);
+        buf = expander.digest();
+        let remain = key_len - out.len();
+        if remain < buf.len() {
+            out.push_all(buf.slice_to(remain));
+        } else {
+            out.push_all(buf.as_slice());
+        }
+    }
+    Ok(out.as_slice().to_owned())
+}
+
+/// Derives a key from the given hash, secret, salt and context info,
+/// returning a `key_len` byte long key. The salt and info can be empty.
+pub fn key<H: Hash + Clone>(h: H, secret: &[u8], salt: &[u8], info: &[u8],
+                            key_len: uint) -> Result<~[u8], ~str> {
+    let prk = extract(h.clone(), secret, salt);
+    expand(h, prk.as_slice(), info, key_len)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::slice;
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::md5::Md5;
+    use super::super::sha1::Sha1;
+    use super::super::sha2::*;
+
+    fn range_bytes(from: uint, to: uint) -> ~[u8] {
+        range(from, to).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_hkdf_sha256() {
+        // RFC 5869, A.1
+        let ikm = slice::from_elem(22, 0x0bu8);
+        let salt = range_bytes(0x00, 0x0d);
+        let info = range_bytes(0xf0, 0xfa);
+        let prk = extract(Sha2::new(Sha_256), ikm.as_slice(), salt.as_slice());
+        assert_eq!(prk.to_hex(), ~"077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+        let okm = expand(Sha2::new(Sha_256), prk.as_slice(), info.as_slice(), 42).unwrap();
+        assert_eq!(okm.to_hex(), ~"3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+
+        // A.2
+        let ikm = range_bytes(0x00, 0x50);
+        let salt = range_bytes(0x60, 0xb0);
+        let info = range_bytes(0xb0, 0x100);
+        let okm = key(Sha2::new(Sha_256), ikm.as_slice(), salt.as_slice(), info.as_slice(), 82).unwrap();
+        assert_eq!(okm.to_hex(), ~"b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87");
+
+        // A.3, zero-length salt and info
+        let ikm = slice::from_elem(22, 0x0bu8);
+        let prk = extract(Sha2::new(Sha_256), ikm.as_slice(), []);
+        assert_eq!(prk.to_hex(), ~"19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04");
+        let okm = key(Sha2::new(Sha_256), ikm.as_slice(), [], [], 42).unwrap();
+        assert_eq!(okm.to_hex(), ~"8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8");
+    }
+
+    #[test]
+    fn test_hkdf_sha1() {
+        // RFC 5869, A.4
+        let ikm = slice::from_elem(11, 0x0bu8);
+        let salt = range_bytes(0x00, 0x0d);
+        let info = range_bytes(0xf0, 0xfa);
+        let prk = extract(Sha1::new(), ikm.as_slice(), salt.as_slice());
+        assert_eq!(prk.to_hex(), ~"9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243");
+        let okm = key(Sha1::new(), ikm.as_slice(), salt.as_slice(), info.as_slice(), 42).unwrap();
+        assert_eq!(okm.to_hex(), ~"085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896");
+
+        // A.7, salt not provided
+        let ikm = slice::from_elem(22, 0x0cu8);
+        let okm = key(Sha1::new(), ikm.as_slice(), [], [], 42).unwrap();
+        assert_eq!(okm.to_hex(), ~"2c91117204d745f3500d636a62f64f0ab3bae548aa53d423b0d1f27ebba6f5e5673a081d70cce7acfc48");
+    }
+
+    #[test]
+    fn test_hkdf_md5() {
+        let ikm = slice::from_elem(22, 0x0bu8);
+        let salt = range_bytes(0x00, 0x0d);
+        let info = range_bytes(0xf0, 0xfa);
+        let okm = key(Md5::new(), ikm.as_slice(), salt.as_slice(), info.as_slice(), 42).unwrap();
+        assert_eq!(okm.to_hex(), ~"b222c9db38d17b2fea8b3bb511c0d6d86049ef481ba7065ca5c6422618ed9cc9144900e2c72b6a863a31");
+    }
+
+    #[test]
+    fn test_hkdf_limit() {
+        let prk = slice::from_elem(16, 0x0bu8);
+        assert!(expand(Md5::new(), prk.as_slice(), [], 255 * 16).is_ok());
+        assert!(expand(Md5::new(), prk.as_slice(), [], 255 * 16 + 1).is_err());
+    }
+}
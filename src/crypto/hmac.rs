@@ -0,0 +1,195 @@
+// Keyed-Hash Message Authentication Code (HMAC) as defined in
+// U.S. Federal Information Processing Standards Publication 198.
+// An HMAC is a cryptographic hash that uses a key to sign a message.
+// The receiver verifies the hash by recomputing it using the same key.
+//
+// Receivers should be careful to use `equal` to compare MACs in order
+// to avoid timing side-channels.
+
+use std::io::IoResult;
+use std::slice;
+use super::subtle;
+use super::super::hash::*;
+
+
+pub struct Hmac<H> {
+    inner: H,
+    outer: H,
+    ipad: ~[u8],
+    opad: ~[u8],
+}
+
+// deep clone
+impl<H: Hash + Clone> Clone for Hmac<H> {
+    fn clone(&self) -> Hmac<H> {
+        Hmac {
+            inner: self.inner.clone(),
+            outer: self.outer.clone(),
+            ipad: self.ipad.clone(),
+            opad: self.opad.clone(),
+        }
+    }
+}
+
+impl<H: Hash + Clone> Hmac<H> {
+    /// Returns a new HMAC using `h` as the underlying hash and the given key.
+    /// Any data already written to `h` is discarded.
+    pub fn new(h: H, key: &[u8]) -> Hmac<H> {
+        let mut inner = h;
+        inner.reset();
+        let mut outer = inner.clone();
+        let block_size = inner.block_size();
+        let mut ipad = slice::from_elem(block_size, 0u8);
+        if key.len() > block_size {
+            // If key is too big, hash it.
+            outer.write(key);
+            let k = outer.digest();
+            outer.reset();
+            ipad.mut_slice_to(k.len()).copy_from(k.as_slice());
+        } else {
+            ipad.mut_slice_to(key.len()).copy_from(key);
+        }
+        let mut opad = ipad.clone();
+        for i in range(0, block_size) {
+            ipad[i] ^= 0x36;
+            opad[i] ^= 0x5c;
+        }
+        inner.write(ipad.as_slice());
+        Hmac {
+            inner: inner,
+            outer: outer,
+            ipad: ipad,
+            opad: opad,
+        }
+    }
+}
+
+impl<H: Hash + Clone> Writer for Hmac<H> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.inner.write(buf)
+    }
+}
+
+impl<H: Hash + Clone> Hash for Hmac<H> {
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.inner.write(self.ipad.as_slice());
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let sum = self.inner.digest();
+        let mut outer = self.outer.clone();
+        outer.write(self.opad.as_slice());
+        outer.write(sum.as_slice());
+        outer.digest()
+    }
+
+    fn size(&self) -> uint {
+        self.outer.size()
+    }
+
+    fn block_size(&self) -> uint {
+        self.inner.block_size()
+    }
+}
+
+/// Compares two MACs for equality without leaking timing information.
+pub fn equal(mac1: &[u8], mac2: &[u8]) -> bool {
+    // We don't have to be constant time if the lengths of the MACs are
+    // different as that suggests that a completely different hash function
+    // was used.
+    subtle::constant_time_compare(mac1, mac2)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::slice;
+    use super::*;
+    use super::super::md5::Md5;
+    use super::super::sha1::Sha1;
+    use super::super::sha2::*;
+    use super::super::super::hash::*;
+
+    static LongKeyData: &'static str = "Test Using Larger Than Block-Size Key - Hash Key First";
+
+    #[test]
+    fn test_hmac_md5() {
+        // RFC 2104
+        let mut h = Hmac::new(Md5::new(), slice::from_elem(16, 0x0bu8).as_slice());
+        h.write_str("Hi There");
+        assert_eq!(h.hexdigest(), ~"9294727a3638bb1c13f48ef8158bfc9d");
+        let mut h = Hmac::new(Md5::new(), bytes!("Jefe"));
+        h.write_str("what do ya want for nothing?");
+        assert_eq!(h.hexdigest(), ~"750c783e6ab0b503eaa86e310a5db738");
+        let mut h = Hmac::new(Md5::new(), slice::from_elem(80, 0xaau8).as_slice());
+        h.write_str(LongKeyData);
+        assert_eq!(h.hexdigest(), ~"6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd");
+    }
+
+    #[test]
+    fn test_hmac_sha1() {
+        // RFC 2202
+        let mut h = Hmac::new(Sha1::new(), slice::from_elem(20, 0x0bu8).as_slice());
+        h.write_str("Hi There");
+        assert_eq!(h.hexdigest(), ~"b617318655057264e28bc0b6fb378c8ef146be00");
+        let mut h = Hmac::new(Sha1::new(), bytes!("Jefe"));
+        h.write_str("what do ya want for nothing?");
+        assert_eq!(h.hexdigest(), ~"effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+        let mut h = Hmac::new(Sha1::new(), slice::from_elem(80, 0xaau8).as_slice());
+        h.write_str(LongKeyData);
+        assert_eq!(h.hexdigest(), ~"aa4ae5e15272d00e95705637ce8a3b55ed402112");
+    }
+
+    #[test]
+    fn test_hmac_sha2() {
+        // RFC 4231, test cases 1, 2 and 6
+        let tests = [
+            (Sha_224, "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+             "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+             "95e9a0db962095adaebe9b2d6f0dbce2d499f112f2d2b7273fa6870e"),
+            (Sha_256, "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+             "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+             "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+            (Sha_384, "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6",
+             "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+             "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c60c2ef6ab4030fe8296248df163f44952"),
+            (Sha_512, "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+             "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+             "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"),
+        ];
+        for &(v, hi_there, jefe, long_key) in tests.iter() {
+            let mut h = Hmac::new(Sha2::new(v), slice::from_elem(20, 0x0bu8).as_slice());
+            h.write_str("Hi There");
+            assert_eq!(h.hexdigest().as_slice(), hi_there);
+            let mut h = Hmac::new(Sha2::new(v), bytes!("Jefe"));
+            h.write_str("what do ya want for nothing?");
+            assert_eq!(h.hexdigest().as_slice(), jefe);
+            let mut h = Hmac::new(Sha2::new(v), slice::from_elem(131, 0xaau8).as_slice());
+            h.write_str(LongKeyData);
+            assert_eq!(h.hexdigest().as_slice(), long_key);
+        }
+    }
+
+    #[test]
+    fn test_hmac_reset() {
+        let mut h = Hmac::new(Sha1::new(), bytes!("Jefe"));
+        h.write_str("some garbage");
+        h.reset();
+        h.write_str("what do ya want for nothing?");
+        assert_eq!(h.size(), 20);
+        assert_eq!(h.block_size(), 64);
+        assert_eq!(h.hexdigest(), ~"effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+    }
+
+    #[test]
+    fn test_equal() {
+        let mut h = Hmac::new(Md5::new(), bytes!("Jefe"));
+        h.write_str("what do ya want for nothing?");
+        let mac = h.digest();
+        assert!(equal(mac.as_slice(), h.digest().as_slice()));
+        h.write_str("!");
+        assert!(!equal(mac.as_slice(), h.digest().as_slice()));
+        assert!(!equal(mac.as_slice(), mac.slice_to(8)));
+    }
+}
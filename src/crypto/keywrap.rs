@@ -0,0 +1,235 @@
+// AES Key Wrap, RFC 3394, and AES Key Wrap with Padding, RFC 5649 (NIST
+// SP 800-38F KW and KWP).
+//
+// Key wrap encrypts key material under a key-encryption key (KEK)
+// without a nonce. The output is 8 bytes longer than the input and
+// carries an integrity check value, which unwrap verifies.
+
+use std::slice;
+use super::cipher::Block;
+use super::subtle::constant_time_compare;
+
+// The default initial value of RFC 3394 section 2.2.3.1.
+static defaultIV: [u8, ..8] = [0xa6, 0xa6, 0xa6, 0xa6, 0xa6, 0xa6, 0xa6, 0xa6];
+
+// The first half of the alternative initial value of RFC 5649 section 3;
+// the second half is the big-endian length of the key material.
+static paddedIVPrefix: [u8, ..4] = [0xa6, 0x59, 0x59, 0xa6];
+
+fn check_block_size<B: Block>(b: &B) -> Result<(), ~str> {
+    if b.block_size() != 16 {
+        return Err(~"keywrap: key wrap requires a 128-bit block cipher");
+    }
+    Ok(())
+}
+
+// The wrapping process W of RFC 3394 section 2.2.1 with initial value
+// a, over the 64-bit blocks in r.
+fn w<B: Block>(b: &B, a: &[u8], r: &[u8]) -> ~[u8] {
+    let n = r.len() / 8;
+    let mut out = slice::from_elem(8 + r.len(), 0u8);
+    out.mut_slice_to(8).copy_from(a);
+    out.mut_slice_from(8).copy_from(r);
+    let mut block = [0u8, ..16];
+    let mut t = 0u64;
+    for _ in range(0, 6u) {
+        for i in range(1, n + 1) {
+            t += 1;
+            block.mut_slice_to(8).copy_from(out.slice_to(8));
+            block.mut_slice_from(8).copy_from(out.slice(8 * i, 8 * i + 8));
+            let input = block;
+            b.encrypt(block.as_mut_slice(), input.as_slice());
+            for j in range(0, 8u) {
+                out[j] = block[j] ^ (t >> (56 - 8 * j)) as u8;
+            }
+            out.mut_slice(8 * i, 8 * i + 8).copy_from(block.slice_from(8));
+        }
+    }
+    out
+}
+
+// The unwrapping process W^-1, returning the recovered initial value and
+// the key data.
+fn w_inverse<B: Block>(b: &B, c: &[u8]) -> ([u8, ..8], ~[u8]) {
+    let n = c.len() / 8 - 1;
+    let mut a = [0u8, ..8];
+    a.as_mut_slice().copy_from(c.slice_to(8));
+    let mut r = c.slice_from(8).to_owned();
+    let mut block = [0u8, ..16];
+    let mut t = 6 * n as u64;
+    for _ in range(0, 6u) {
+        for i in range(0, n).rev() {
+            for j in range(0, 8u) {
+                block[j] = a[j] ^ (t >> (56 - 8 * j)) as u8;
+            }
+            block.mut_slice_from(8).copy_from(r.slice(8 * i, 8 * i + 8));
+            let input = block;
+            b.decrypt(block.as_mut_slice(), input.as_slice());
+            a.as_mut_slice().copy_from(block.slice_to(8));
+            r.mut_slice(8 * i, 8 * i + 8).copy_from(block.slice_from(8));
+            t -= 1;
+        }
+    }
+    (a, r)
+}
+
+/// Wraps plaintext, which must be a multiple of 8 bytes and at least 16
+/// bytes long, with the key-encryption key b (RFC 3394).
+pub fn wrap<B: Block>(b: &B, plaintext: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_block_size(b));
+    if plaintext.len() < 16 || plaintext.len() % 8 != 0 {
+        return Err(~"keywrap: plaintext must be a multiple of 8 bytes and at least 16 bytes");
+    }
+    Ok(w(b, defaultIV.as_slice(), plaintext))
+}
+
+/// Unwraps ciphertext produced by wrap, checking its integrity.
+pub fn unwrap<B: Block>(b: &B, ciphertext: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_block_size(b));
+    if ciphertext.len() < 24 || ciphertext.len() % 8 != 0 {
+        return Err(~"keywrap: invalid ciphertext length");
+    }
+    let (a, r) = w_inverse(b, ciphertext);
+    if !constant_time_compare(a.as_slice(), defaultIV.as_slice()) {
+        return Err(~"keywrap: integrity check failed");
+    }
+    Ok(r)
+}
+
+/// Wraps plaintext of any length from 1 to 2^32-1 bytes with the
+/// key-encryption key b (RFC 5649).
+pub fn wrap_with_padding<B: Block>(b: &B, plaintext: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_block_size(b));
+    if plaintext.len() == 0 || plaintext.len() as u64 > 0xffffffff {
+        return Err(~"keywrap: invalid plaintext length");
+    }
+    let mli = plaintext.len();
+    let mut aiv = [0u8, ..8];
+    aiv.mut_slice_to(4).copy_from(paddedIVPrefix.as_slice());
+    for i in range(0, 4u) {
+        aiv[4 + i] = (mli >> (24 - 8 * i)) as u8;
+    }
+    let padded_len = (mli + 7) / 8 * 8;
+    let mut p = slice::from_elem(padded_len, 0u8);
+    p.mut_slice_to(mli).copy_from(plaintext);
+
+    if padded_len == 8 {
+        // a single block is encrypted directly
+        let mut block = [0u8, ..16];
+        block.mut_slice_to(8).copy_from(aiv.as_slice());
+        block.mut_slice_from(8).copy_from(p.as_slice());
+        let mut out = slice::from_elem(16, 0u8);
+        b.encrypt(out.as_mut_slice(), block.as_slice());
+        return Ok(out);
+    }
+    Ok(w(b, aiv.as_slice(), p.as_slice()))
+}
+
+/// Unwraps ciphertext produced by wrap_with_padding, checking its
+/// integrity and the padding.
+pub fn unwrap_with_padding<B: Block>(b: &B, ciphertext: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_block_size(b));
+    if ciphertext.len() < 16 || ciphertext.len() % 8 != 0 {
+        return Err(~"keywrap: invalid ciphertext length");
+    }
+    let (a, p) = if ciphertext.len() == 16 {
+        let mut block = [0u8, ..16];
+        b.decrypt(block.as_mut_slice(), ciphertext);
+        let mut a = [0u8, ..8];
+        a.as_mut_slice().copy_from(block.slice_to(8));
+        (a, block.slice_from(8).to_owned())
+    } else {
+        w_inverse(b, ciphertext)
+    };
+
+    // Check the prefix, that the length falls in the last block and that
+    // the padding is zero, with one error for all of them.
+    let prefix_ok = constant_time_compare(a.slice_to(4), paddedIVPrefix.as_slice());
+    let mut mli = 0u;
+    for i in range(4, 8u) {
+        mli = (mli << 8) | a[i] as uint;
+    }
+    let len_ok = mli + 8 > p.len() && mli <= p.len();
+    let mut pad = 0u8;
+    for i in range(0, p.len()) {
+        if i >= mli {
+            pad |= p[i];
+        }
+    }
+    if !(prefix_ok & len_ok & (pad == 0)) {
+        return Err(~"keywrap: integrity check failed");
+    }
+    Ok(p.slice_to(mli).to_owned())
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::aes::Aes;
+    use super::super::des::Des;
+
+    fn aes(key: &str) -> Aes {
+        Aes::new(key.from_hex().unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_wrap() {
+        // RFC 3394 sections 4.1 and 4.6
+        let tests = [
+            ("000102030405060708090a0b0c0d0e0f",
+             "00112233445566778899aabbccddeeff",
+             "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+             "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f",
+             "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21"),
+        ];
+        for &(kek, key, wrapped) in tests.iter() {
+            let c = aes(kek);
+            let key = key.from_hex().unwrap();
+            let out = wrap(&c, key.as_slice()).unwrap();
+            assert_eq!(out.as_slice().to_hex(), wrapped.to_owned());
+            assert_eq!(unwrap(&c, out.as_slice()).unwrap(), key);
+
+            let mut bad = out.clone();
+            bad[10] ^= 1;
+            assert_eq!(unwrap(&c, bad.as_slice()).err(), Some(~"keywrap: integrity check failed"));
+        }
+        let c = aes("000102030405060708090a0b0c0d0e0f");
+        assert!(wrap(&c, [0u8, ..8]).is_err());
+        assert!(wrap(&c, [0u8, ..20]).is_err());
+        assert!(unwrap(&c, [0u8, ..16]).is_err());
+    }
+
+    #[test]
+    fn test_wrap_with_padding() {
+        // RFC 5649 section 6
+        let c = aes("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8");
+        let tests = [
+            ("c37b7e6492584340bed12207808941155068f738",
+             "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a"),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ];
+        for &(key, wrapped) in tests.iter() {
+            let key = key.from_hex().unwrap();
+            let out = wrap_with_padding(&c, key.as_slice()).unwrap();
+            assert_eq!(out.as_slice().to_hex(), wrapped.to_owned());
+            assert_eq!(unwrap_with_padding(&c, out.as_slice()).unwrap(), key);
+
+            let mut bad = out.clone();
+            bad[0] ^= 1;
+            assert!(unwrap_with_padding(&c, bad.as_slice()).is_err());
+        }
+        assert!(wrap_with_padding(&c, []).is_err());
+        // the unpadded and padded forms are not interchangeable
+        let out = wrap(&c, [7u8, ..16]).unwrap();
+        assert!(unwrap_with_padding(&c, out.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_block_size() {
+        let d = Des::new([1u8, ..8]).unwrap();
+        assert!(wrap(&d, [0u8, ..16]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+// MD4 as defined in RFC 1320.
+//
+// MD4 is cryptographically broken and should only be used where an
+// existing format requires it (NTLM, eDonkey/ed2k).
+
+use std::io::IoResult;
+use std::slice;
+use super::*;
+use super::super::hash::*;
+
+pub static Size: uint = 16;
+pub static BlockSize: uint = 64;
+
+static CHUNK: uint = 64;
+static init0: u32 = 0x67452301;
+static init1: u32 = 0xEFCDAB89;
+static init2: u32 = 0x98BADCFE;
+static init3: u32 = 0x10325476;
+
+static shift1: [uint, ..4] = [3, 7, 11, 19];
+static shift2: [uint, ..4] = [3, 5, 9, 13];
+static shift3: [uint, ..4] = [3, 9, 11, 15];
+
+static xIndex2: [uint, ..16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+static xIndex3: [uint, ..16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+
+#[inline]
+fn rotl32(x: u32, n: uint) -> u32 {
+    (x << n) | (x >> (32 - n))
+}
+
+#[deriving(Eq, Show)]
+pub struct Md4 {
+    h: (u32, u32, u32, u32),
+    x: Vec<u8>,
+    len: uint,
+}
+
+// deep clone
+impl Clone for Md4 {
+    fn clone(&self) -> Md4 {
+        Md4 {
+            h: self.h,
+            x: self.x.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl Md4 {
+    pub fn new() -> Md4 {
+        let mut ret = Md4 { h: (0,0,0,0),
+                            x: Vec::new(),
+                            len: 0 };
+        ret.reset();
+        ret
+    }
+
+    fn block(&mut self, buf: &[u8]) {
+        assert!(buf.len() % CHUNK == 0);
+        let mut x = [0u32, ..16];
+        let (mut h0, mut h1, mut h2, mut h3) = self.h;
+        for p in buf.chunks(CHUNK) {
+            for i in range(0, 16u) {
+                let j = i * 4;
+                x[i] = p[j] as u32 | p[j+1] as u32 << 8 | p[j+2] as u32 << 16 | p[j+3] as u32 << 24;
+            }
+
+            let (mut a, mut b, mut c, mut d) = (h0, h1, h2, h3);
+            // round 1
+            for i in range(0, 16u) {
+                let t = rotl32(a + ((b & c) | ((! b) & d)) + x[i], shift1[i % 4]);
+                a = d; d = c; c = b; b = t;
+            }
+            // round 2
+            for i in range(0, 16u) {
+                let t = rotl32(a + ((b & c) | (b & d) | (c & d)) + x[xIndex2[i]] + 0x5a827999,
+                               shift2[i % 4]);
+                a = d; d = c; c = b; b = t;
+            }
+            // round 3
+            for i in range(0, 16u) {
+                let t = rotl32(a + (b ^ c ^ d) + x[xIndex3[i]] + 0x6ed9eba1, shift3[i % 4]);
+                a = d; d = c; c = b; b = t;
+            }
+            h0 += a;
+            h1 += b;
+            h2 += c;
+            h3 += d;
+        }
+        self.h = (h0, h1, h2, h3);
+    }
+
+    fn checksum(&mut self) -> ~[u8] {
+        let mut tmp = slice::from_elem(64, 0u8);
+        tmp[0] = 0x80;
+        let len = self.len;
+        if len % 64 < 56 {
+            self.write(tmp.slice_to(56 - len % 64));
+        } else {
+            self.write(tmp.slice_to(64 + 56 - len % 64));
+        }
+        let len = (len << 3) as u64;
+        for i in range(0, 8u) {
+            tmp[i] = (len >> 8*i) as u8;
+        }
+        self.write(tmp.slice_to(8));
+        assert_eq!(self.x.len(), 0);
+
+        let (h0, h1, h2, h3) = self.h;
+        let mut ret = slice::with_capacity(Size);
+        for &h in [h0, h1, h2, h3].iter() {
+            for j in range(0, 4u) {
+                ret.push((h >> 8*j) as u8);
+            }
+        }
+        ret
+    }
+}
+
+impl Writer for Md4 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.len += buf.len();  // total len
+        self.x.push_all(buf);
+        let mut next_x = Vec::new();
+        let buf = self.x.clone();
+        for p in buf.as_slice().chunks(CHUNK) {
+            if p.len() < CHUNK {
+                next_x.push_all(p);
+            } else {
+                self.block(p);
+            }
+        }
+        self.x = next_x;
+        Ok(())
+    }
+}
+
+impl Hash for Md4 {
+    fn reset(&mut self) {
+        self.h = (init0, init1, init2, init3);
+        self.x = Vec::new();
+        self.len = 0;
+    }
+    fn digest(&self) -> ~[u8] {
+        let mut h = self.clone();
+        h.checksum()
+    }
+
+    fn size(&self) -> uint {
+        Size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+#[test]
+fn test_md4() {
+    let mut h = Md4::new();
+    assert_eq!(h.hexdigest(), ~"31d6cfe0d16ae931b73c59d7e0c089c0");
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"a448017aaf21d8525fc10ae87aa6729d");
+    let mut h = Md4::new();
+    h.write_str("message digest");
+    assert_eq!(h.hexdigest(), ~"d9130a8164549fe818874806e1c7014b");
+    let mut h = Md4::new();
+    h.write_str("1234567890".repeat(8));
+    assert_eq!(h.hexdigest(), ~"e33b4ddc9c38f2199c3e7b164fcc0536");
+}
+
+#[test]
+fn test_md4_long() {
+    let mut h = Md4::new();
+    h.write_str("welcome to china".repeat(10001));
+    assert_eq!(h.hexdigest(), ~"8acf93b382032f855342a1d0228f2bba");
+}
@@ -4,6 +4,7 @@ use std::ptr;
 use std::cast;
 use std::slice;
 use super::*;
+use super::multibuf;
 use super::super::hash::*;
 
 pub static Size: uint = 16;
@@ -36,6 +37,11 @@ static K: [u32, ..64] = [0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee,
                          0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
                          0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
                          0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391, ];
+// the message word used by each step
+static msgIndex: [u8, ..64] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+                               1, 6, 11, 0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12,
+                               5, 8, 11, 14, 1, 4, 7, 10, 13, 0, 3, 6, 9, 12, 15, 2,
+                               0, 7, 14, 5, 12, 3, 10, 1, 8, 15, 6, 13, 4, 11, 2, 9, ];
 
 
 #[deriving(Eq, Show)]
@@ -154,6 +160,823 @@ impl Md5 {
         ret
     }
 
+    // one pass over a whole message, straight from the input slice
+    fn sum_oneshot(&mut self, msg: &[u8]) -> [u8, ..16] {
+        let full = msg.len() - msg.len() % CHUNK;
+        self.block(msg.slice_to(full));
+        let tail = msg.slice_from(full);
+        let mut tmp = [0u8, ..128];
+        for i in range(0, tail.len()) {
+            tmp[i] = tail[i];
+        }
+        tmp[tail.len()] = 0x80;
+        let n = if tail.len() < 56 { 64 } else { 128 };
+        let len = (msg.len() << 3) as u64;
+        for i in range(0, 8u) {
+            tmp[n - 8 + i] = (len >> 8*i) as u8;
+        }
+        self.block(tmp.slice_to(n));
+
+        let (h0, h1, h2, h3) = self.h;
+        let mut ret = [0u8, ..16];
+        for (i, &h) in [h0, h1, h2, h3].iter().enumerate() {
+            for j in range(0, 4u) {
+                ret[4*i + j] = (h >> 8*j) as u8;
+            }
+        }
+        ret
+    }
+}
+
+/// Returns the MD5 checksums of many independent messages.
+///
+/// Each message is hashed in one pass over the input, without the
+/// buffering done by the `Writer` interface. On x86-64 the messages are
+/// hashed 4, 8 or 16 at a time in SSE2, AVX2 or AVX-512 lanes.
+pub fn digest_many(msgs: &[&[u8]]) -> Vec<[u8, ..16]> {
+    digest_lanes(msgs, multibuf::lanes())
+}
+
+fn digest_lanes(msgs: &[&[u8]], lanes: uint) -> Vec<[u8, ..16]> {
+    let mut ret = Vec::with_capacity(msgs.len());
+    if lanes == 1 {
+        let mut h = Md5::new();
+        for msg in msgs.iter() {
+            h.reset();
+            ret.push(h.sum_oneshot(*msg));
+        }
+        return ret;
+    }
+    let init = [init0, init1, init2, init3];
+    let states = multibuf::hash_many(msgs, init.as_slice(), false, lanes, 80,
+                                     |s, w| block_lanes(lanes, s, w));
+    for st in states.iter() {
+        let mut sum = [0u8, ..16];
+        for i in range(0, 16u) {
+            sum[i] = (st[i / 4] >> 8 * (i % 4)) as u8;
+        }
+        ret.push(sum);
+    }
+    ret
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_lanes(lanes: uint, state: &mut [u32], w: &mut [u32]) {
+    match lanes {
+        4 => block_sse2(state, w),
+        8 => block_avx2(state, w),
+        _ => block_avx512(state, w),
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block_lanes(_lanes: uint, _state: &mut [u32], _w: &mut [u32]) {
+    unreachable!();
+}
+
+// The multi-buffer kernels, one message per lane. They first copy the
+// message words into rows 16-79 in the order the steps read them, so the
+// steps walk the rows and the constants together, four steps per loop
+// iteration with a, b, c and d in registers 0-3. With AVX-512 the
+// rotations are vprold; otherwise they are two shifts and an or.
+#[cfg(target_arch = "x86_64")]
+fn block_sse2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 4 * 4 && w.len() == 80 * 4);
+    unsafe {
+        asm!("xor %rcx, %rcx
+              lea 256($1), %rdx
+              1:
+              movzbl ($3,%rcx), %eax
+              shl $$4, %eax
+              movdqu ($1,%rax), %xmm4
+              movdqu %xmm4, (%rdx)
+              add $$16, %rdx
+              inc %rcx
+              cmp $$64, %rcx
+              jne 1b
+              movdqu ($0), %xmm0
+              movdqu 16($0), %xmm1
+              movdqu 32($0), %xmm2
+              movdqu 48($0), %xmm3
+              pcmpeqd %xmm7, %xmm7
+              lea 256($1), %rdx
+              mov $2, %rcx
+              mov $$4, %eax
+              2:
+              movdqa %xmm2, %xmm4
+              pxor %xmm3, %xmm4
+              pand %xmm1, %xmm4
+              pxor %xmm3, %xmm4
+              paddd %xmm4, %xmm0
+              movd (%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm0
+              movdqu (%rdx), %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm0, %xmm5
+              pslld $$7, %xmm5
+              psrld $$25, %xmm0
+              por %xmm5, %xmm0
+              paddd %xmm1, %xmm0
+              movdqa %xmm1, %xmm4
+              pxor %xmm2, %xmm4
+              pand %xmm0, %xmm4
+              pxor %xmm2, %xmm4
+              paddd %xmm4, %xmm3
+              movd 4(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm3
+              movdqu 16(%rdx), %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm3, %xmm5
+              pslld $$12, %xmm5
+              psrld $$20, %xmm3
+              por %xmm5, %xmm3
+              paddd %xmm0, %xmm3
+              movdqa %xmm0, %xmm4
+              pxor %xmm1, %xmm4
+              pand %xmm3, %xmm4
+              pxor %xmm1, %xmm4
+              paddd %xmm4, %xmm2
+              movd 8(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm2
+              movdqu 32(%rdx), %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm2, %xmm5
+              pslld $$17, %xmm5
+              psrld $$15, %xmm2
+              por %xmm5, %xmm2
+              paddd %xmm3, %xmm2
+              movdqa %xmm3, %xmm4
+              pxor %xmm0, %xmm4
+              pand %xmm2, %xmm4
+              pxor %xmm0, %xmm4
+              paddd %xmm4, %xmm1
+              movd 12(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm1
+              movdqu 48(%rdx), %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm1, %xmm5
+              pslld $$22, %xmm5
+              psrld $$10, %xmm1
+              por %xmm5, %xmm1
+              paddd %xmm2, %xmm1
+              add $$16, %rcx
+              add $$64, %rdx
+              dec %eax
+              jnz 2b
+              mov $$4, %eax
+              3:
+              movdqa %xmm1, %xmm4
+              pxor %xmm2, %xmm4
+              pand %xmm3, %xmm4
+              pxor %xmm2, %xmm4
+              paddd %xmm4, %xmm0
+              movd (%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm0
+              movdqu (%rdx), %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm0, %xmm5
+              pslld $$5, %xmm5
+              psrld $$27, %xmm0
+              por %xmm5, %xmm0
+              paddd %xmm1, %xmm0
+              movdqa %xmm0, %xmm4
+              pxor %xmm1, %xmm4
+              pand %xmm2, %xmm4
+              pxor %xmm1, %xmm4
+              paddd %xmm4, %xmm3
+              movd 4(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm3
+              movdqu 16(%rdx), %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm3, %xmm5
+              pslld $$9, %xmm5
+              psrld $$23, %xmm3
+              por %xmm5, %xmm3
+              paddd %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pxor %xmm0, %xmm4
+              pand %xmm1, %xmm4
+              pxor %xmm0, %xmm4
+              paddd %xmm4, %xmm2
+              movd 8(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm2
+              movdqu 32(%rdx), %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm2, %xmm5
+              pslld $$14, %xmm5
+              psrld $$18, %xmm2
+              por %xmm5, %xmm2
+              paddd %xmm3, %xmm2
+              movdqa %xmm2, %xmm4
+              pxor %xmm3, %xmm4
+              pand %xmm0, %xmm4
+              pxor %xmm3, %xmm4
+              paddd %xmm4, %xmm1
+              movd 12(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm1
+              movdqu 48(%rdx), %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm1, %xmm5
+              pslld $$20, %xmm5
+              psrld $$12, %xmm1
+              por %xmm5, %xmm1
+              paddd %xmm2, %xmm1
+              add $$16, %rcx
+              add $$64, %rdx
+              dec %eax
+              jnz 3b
+              mov $$4, %eax
+              4:
+              movdqa %xmm1, %xmm4
+              pxor %xmm2, %xmm4
+              pxor %xmm3, %xmm4
+              paddd %xmm4, %xmm0
+              movd (%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm0
+              movdqu (%rdx), %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm0, %xmm5
+              pslld $$4, %xmm5
+              psrld $$28, %xmm0
+              por %xmm5, %xmm0
+              paddd %xmm1, %xmm0
+              movdqa %xmm0, %xmm4
+              pxor %xmm1, %xmm4
+              pxor %xmm2, %xmm4
+              paddd %xmm4, %xmm3
+              movd 4(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm3
+              movdqu 16(%rdx), %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm3, %xmm5
+              pslld $$11, %xmm5
+              psrld $$21, %xmm3
+              por %xmm5, %xmm3
+              paddd %xmm0, %xmm3
+              movdqa %xmm3, %xmm4
+              pxor %xmm0, %xmm4
+              pxor %xmm1, %xmm4
+              paddd %xmm4, %xmm2
+              movd 8(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm2
+              movdqu 32(%rdx), %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm2, %xmm5
+              pslld $$16, %xmm5
+              psrld $$16, %xmm2
+              por %xmm5, %xmm2
+              paddd %xmm3, %xmm2
+              movdqa %xmm2, %xmm4
+              pxor %xmm3, %xmm4
+              pxor %xmm0, %xmm4
+              paddd %xmm4, %xmm1
+              movd 12(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm1
+              movdqu 48(%rdx), %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm1, %xmm5
+              pslld $$23, %xmm5
+              psrld $$9, %xmm1
+              por %xmm5, %xmm1
+              paddd %xmm2, %xmm1
+              add $$16, %rcx
+              add $$64, %rdx
+              dec %eax
+              jnz 4b
+              mov $$4, %eax
+              5:
+              movdqa %xmm3, %xmm4
+              pxor %xmm7, %xmm4
+              por %xmm1, %xmm4
+              pxor %xmm2, %xmm4
+              paddd %xmm4, %xmm0
+              movd (%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm0
+              movdqu (%rdx), %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm0, %xmm5
+              pslld $$6, %xmm5
+              psrld $$26, %xmm0
+              por %xmm5, %xmm0
+              paddd %xmm1, %xmm0
+              movdqa %xmm2, %xmm4
+              pxor %xmm7, %xmm4
+              por %xmm0, %xmm4
+              pxor %xmm1, %xmm4
+              paddd %xmm4, %xmm3
+              movd 4(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm3
+              movdqu 16(%rdx), %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm3, %xmm5
+              pslld $$10, %xmm5
+              psrld $$22, %xmm3
+              por %xmm5, %xmm3
+              paddd %xmm0, %xmm3
+              movdqa %xmm1, %xmm4
+              pxor %xmm7, %xmm4
+              por %xmm3, %xmm4
+              pxor %xmm0, %xmm4
+              paddd %xmm4, %xmm2
+              movd 8(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm2
+              movdqu 32(%rdx), %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm2, %xmm5
+              pslld $$15, %xmm5
+              psrld $$17, %xmm2
+              por %xmm5, %xmm2
+              paddd %xmm3, %xmm2
+              movdqa %xmm0, %xmm4
+              pxor %xmm7, %xmm4
+              por %xmm2, %xmm4
+              pxor %xmm3, %xmm4
+              paddd %xmm4, %xmm1
+              movd 12(%rcx), %xmm5
+              pshufd $$0, %xmm5, %xmm5
+              paddd %xmm5, %xmm1
+              movdqu 48(%rdx), %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm1, %xmm5
+              pslld $$21, %xmm5
+              psrld $$11, %xmm1
+              por %xmm5, %xmm1
+              paddd %xmm2, %xmm1
+              add $$16, %rcx
+              add $$64, %rdx
+              dec %eax
+              jnz 5b
+              movdqu ($0), %xmm4
+              paddd %xmm4, %xmm0
+              movdqu %xmm0, ($0)
+              movdqu 16($0), %xmm4
+              paddd %xmm4, %xmm1
+              movdqu %xmm1, 16($0)
+              movdqu 32($0), %xmm4
+              paddd %xmm4, %xmm2
+              movdqu %xmm2, 32($0)
+              movdqu 48($0), %xmm4
+              paddd %xmm4, %xmm3
+              movdqu %xmm3, 48($0)"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K.as_ptr()), "r"(msgIndex.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm7", "rax", "rcx", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_avx2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 4 * 8 && w.len() == 80 * 8);
+    unsafe {
+        asm!("xor %rcx, %rcx
+              lea 512($1), %rdx
+              1:
+              movzbl ($3,%rcx), %eax
+              shl $$5, %eax
+              vmovdqu ($1,%rax), %ymm4
+              vmovdqu %ymm4, (%rdx)
+              add $$32, %rdx
+              inc %rcx
+              cmp $$64, %rcx
+              jne 1b
+              vmovdqu ($0), %ymm0
+              vmovdqu 32($0), %ymm1
+              vmovdqu 64($0), %ymm2
+              vmovdqu 96($0), %ymm3
+              vpcmpeqd %ymm7, %ymm7, %ymm7
+              lea 512($1), %rdx
+              mov $2, %rcx
+              mov $$4, %eax
+              2:
+              vpxor %ymm3, %ymm2, %ymm4
+              vpand %ymm1, %ymm4, %ymm4
+              vpxor %ymm3, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpbroadcastd (%rcx), %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd (%rdx), %ymm0, %ymm0
+              vpslld $$7, %ymm0, %ymm5
+              vpsrld $$25, %ymm0, %ymm0
+              vpor %ymm5, %ymm0, %ymm0
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm2, %ymm1, %ymm4
+              vpand %ymm0, %ymm4, %ymm4
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpbroadcastd 4(%rcx), %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$12, %ymm3, %ymm5
+              vpsrld $$20, %ymm3, %ymm3
+              vpor %ymm5, %ymm3, %ymm3
+              vpaddd %ymm0, %ymm3, %ymm3
+              vpxor %ymm1, %ymm0, %ymm4
+              vpand %ymm3, %ymm4, %ymm4
+              vpxor %ymm1, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm2, %ymm2
+              vpbroadcastd 8(%rcx), %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$17, %ymm2, %ymm5
+              vpsrld $$15, %ymm2, %ymm2
+              vpor %ymm5, %ymm2, %ymm2
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm0, %ymm3, %ymm4
+              vpand %ymm2, %ymm4, %ymm4
+              vpxor %ymm0, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpbroadcastd 12(%rcx), %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$22, %ymm1, %ymm5
+              vpsrld $$10, %ymm1, %ymm1
+              vpor %ymm5, %ymm1, %ymm1
+              vpaddd %ymm2, %ymm1, %ymm1
+              add $$16, %rcx
+              add $$128, %rdx
+              dec %eax
+              jnz 2b
+              mov $$4, %eax
+              3:
+              vpxor %ymm2, %ymm1, %ymm4
+              vpand %ymm3, %ymm4, %ymm4
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpbroadcastd (%rcx), %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd (%rdx), %ymm0, %ymm0
+              vpslld $$5, %ymm0, %ymm5
+              vpsrld $$27, %ymm0, %ymm0
+              vpor %ymm5, %ymm0, %ymm0
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm1, %ymm0, %ymm4
+              vpand %ymm2, %ymm4, %ymm4
+              vpxor %ymm1, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpbroadcastd 4(%rcx), %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$9, %ymm3, %ymm5
+              vpsrld $$23, %ymm3, %ymm3
+              vpor %ymm5, %ymm3, %ymm3
+              vpaddd %ymm0, %ymm3, %ymm3
+              vpxor %ymm0, %ymm3, %ymm4
+              vpand %ymm1, %ymm4, %ymm4
+              vpxor %ymm0, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm2, %ymm2
+              vpbroadcastd 8(%rcx), %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$14, %ymm2, %ymm5
+              vpsrld $$18, %ymm2, %ymm2
+              vpor %ymm5, %ymm2, %ymm2
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm3, %ymm2, %ymm4
+              vpand %ymm0, %ymm4, %ymm4
+              vpxor %ymm3, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpbroadcastd 12(%rcx), %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$20, %ymm1, %ymm5
+              vpsrld $$12, %ymm1, %ymm1
+              vpor %ymm5, %ymm1, %ymm1
+              vpaddd %ymm2, %ymm1, %ymm1
+              add $$16, %rcx
+              add $$128, %rdx
+              dec %eax
+              jnz 3b
+              mov $$4, %eax
+              4:
+              vpxor %ymm2, %ymm1, %ymm4
+              vpxor %ymm3, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpbroadcastd (%rcx), %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd (%rdx), %ymm0, %ymm0
+              vpslld $$4, %ymm0, %ymm5
+              vpsrld $$28, %ymm0, %ymm0
+              vpor %ymm5, %ymm0, %ymm0
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm1, %ymm0, %ymm4
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpbroadcastd 4(%rcx), %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$11, %ymm3, %ymm5
+              vpsrld $$21, %ymm3, %ymm3
+              vpor %ymm5, %ymm3, %ymm3
+              vpaddd %ymm0, %ymm3, %ymm3
+              vpxor %ymm0, %ymm3, %ymm4
+              vpxor %ymm1, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm2, %ymm2
+              vpbroadcastd 8(%rcx), %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$16, %ymm2, %ymm5
+              vpsrld $$16, %ymm2, %ymm2
+              vpor %ymm5, %ymm2, %ymm2
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm3, %ymm2, %ymm4
+              vpxor %ymm0, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpbroadcastd 12(%rcx), %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$23, %ymm1, %ymm5
+              vpsrld $$9, %ymm1, %ymm1
+              vpor %ymm5, %ymm1, %ymm1
+              vpaddd %ymm2, %ymm1, %ymm1
+              add $$16, %rcx
+              add $$128, %rdx
+              dec %eax
+              jnz 4b
+              mov $$4, %eax
+              5:
+              vpxor %ymm7, %ymm3, %ymm4
+              vpor %ymm1, %ymm4, %ymm4
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpbroadcastd (%rcx), %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd (%rdx), %ymm0, %ymm0
+              vpslld $$6, %ymm0, %ymm5
+              vpsrld $$26, %ymm0, %ymm0
+              vpor %ymm5, %ymm0, %ymm0
+              vpaddd %ymm1, %ymm0, %ymm0
+              vpxor %ymm7, %ymm2, %ymm4
+              vpor %ymm0, %ymm4, %ymm4
+              vpxor %ymm1, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm3, %ymm3
+              vpbroadcastd 4(%rcx), %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$10, %ymm3, %ymm5
+              vpsrld $$22, %ymm3, %ymm3
+              vpor %ymm5, %ymm3, %ymm3
+              vpaddd %ymm0, %ymm3, %ymm3
+              vpxor %ymm7, %ymm1, %ymm4
+              vpor %ymm3, %ymm4, %ymm4
+              vpxor %ymm0, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm2, %ymm2
+              vpbroadcastd 8(%rcx), %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$15, %ymm2, %ymm5
+              vpsrld $$17, %ymm2, %ymm2
+              vpor %ymm5, %ymm2, %ymm2
+              vpaddd %ymm3, %ymm2, %ymm2
+              vpxor %ymm7, %ymm0, %ymm4
+              vpor %ymm2, %ymm4, %ymm4
+              vpxor %ymm3, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpbroadcastd 12(%rcx), %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$21, %ymm1, %ymm5
+              vpsrld $$11, %ymm1, %ymm1
+              vpor %ymm5, %ymm1, %ymm1
+              vpaddd %ymm2, %ymm1, %ymm1
+              add $$16, %rcx
+              add $$128, %rdx
+              dec %eax
+              jnz 5b
+              vpaddd ($0), %ymm0, %ymm0
+              vmovdqu %ymm0, ($0)
+              vpaddd 32($0), %ymm1, %ymm1
+              vmovdqu %ymm1, 32($0)
+              vpaddd 64($0), %ymm2, %ymm2
+              vmovdqu %ymm2, 64($0)
+              vpaddd 96($0), %ymm3, %ymm3
+              vmovdqu %ymm3, 96($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K.as_ptr()), "r"(msgIndex.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm7", "rax", "rcx", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_avx512(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 4 * 16 && w.len() == 80 * 16);
+    unsafe {
+        asm!("xor %rcx, %rcx
+              lea 1024($1), %rdx
+              1:
+              movzbl ($3,%rcx), %eax
+              shl $$6, %eax
+              vmovdqu32 ($1,%rax), %zmm4
+              vmovdqu32 %zmm4, (%rdx)
+              add $$64, %rdx
+              inc %rcx
+              cmp $$64, %rcx
+              jne 1b
+              vmovdqu32 ($0), %zmm0
+              vmovdqu32 64($0), %zmm1
+              vmovdqu32 128($0), %zmm2
+              vmovdqu32 192($0), %zmm3
+              vpternlogd $$0xff, %zmm7, %zmm7, %zmm7
+              lea 1024($1), %rdx
+              mov $2, %rcx
+              mov $$4, %eax
+              2:
+              vpxord %zmm3, %zmm2, %zmm4
+              vpandd %zmm1, %zmm4, %zmm4
+              vpxord %zmm3, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm0, %zmm0
+              vpbroadcastd (%rcx), %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd (%rdx), %zmm0, %zmm0
+              vprold $$7, %zmm0, %zmm0
+              vpaddd %zmm1, %zmm0, %zmm0
+              vpxord %zmm2, %zmm1, %zmm4
+              vpandd %zmm0, %zmm4, %zmm4
+              vpxord %zmm2, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm3, %zmm3
+              vpbroadcastd 4(%rcx), %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$12, %zmm3, %zmm3
+              vpaddd %zmm0, %zmm3, %zmm3
+              vpxord %zmm1, %zmm0, %zmm4
+              vpandd %zmm3, %zmm4, %zmm4
+              vpxord %zmm1, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm2, %zmm2
+              vpbroadcastd 8(%rcx), %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$17, %zmm2, %zmm2
+              vpaddd %zmm3, %zmm2, %zmm2
+              vpxord %zmm0, %zmm3, %zmm4
+              vpandd %zmm2, %zmm4, %zmm4
+              vpxord %zmm0, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm1, %zmm1
+              vpbroadcastd 12(%rcx), %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$22, %zmm1, %zmm1
+              vpaddd %zmm2, %zmm1, %zmm1
+              add $$16, %rcx
+              add $$256, %rdx
+              dec %eax
+              jnz 2b
+              mov $$4, %eax
+              3:
+              vpxord %zmm2, %zmm1, %zmm4
+              vpandd %zmm3, %zmm4, %zmm4
+              vpxord %zmm2, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm0, %zmm0
+              vpbroadcastd (%rcx), %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd (%rdx), %zmm0, %zmm0
+              vprold $$5, %zmm0, %zmm0
+              vpaddd %zmm1, %zmm0, %zmm0
+              vpxord %zmm1, %zmm0, %zmm4
+              vpandd %zmm2, %zmm4, %zmm4
+              vpxord %zmm1, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm3, %zmm3
+              vpbroadcastd 4(%rcx), %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$9, %zmm3, %zmm3
+              vpaddd %zmm0, %zmm3, %zmm3
+              vpxord %zmm0, %zmm3, %zmm4
+              vpandd %zmm1, %zmm4, %zmm4
+              vpxord %zmm0, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm2, %zmm2
+              vpbroadcastd 8(%rcx), %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$14, %zmm2, %zmm2
+              vpaddd %zmm3, %zmm2, %zmm2
+              vpxord %zmm3, %zmm2, %zmm4
+              vpandd %zmm0, %zmm4, %zmm4
+              vpxord %zmm3, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm1, %zmm1
+              vpbroadcastd 12(%rcx), %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$20, %zmm1, %zmm1
+              vpaddd %zmm2, %zmm1, %zmm1
+              add $$16, %rcx
+              add $$256, %rdx
+              dec %eax
+              jnz 3b
+              mov $$4, %eax
+              4:
+              vpxord %zmm2, %zmm1, %zmm4
+              vpxord %zmm3, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm0, %zmm0
+              vpbroadcastd (%rcx), %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd (%rdx), %zmm0, %zmm0
+              vprold $$4, %zmm0, %zmm0
+              vpaddd %zmm1, %zmm0, %zmm0
+              vpxord %zmm1, %zmm0, %zmm4
+              vpxord %zmm2, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm3, %zmm3
+              vpbroadcastd 4(%rcx), %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$11, %zmm3, %zmm3
+              vpaddd %zmm0, %zmm3, %zmm3
+              vpxord %zmm0, %zmm3, %zmm4
+              vpxord %zmm1, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm2, %zmm2
+              vpbroadcastd 8(%rcx), %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$16, %zmm2, %zmm2
+              vpaddd %zmm3, %zmm2, %zmm2
+              vpxord %zmm3, %zmm2, %zmm4
+              vpxord %zmm0, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm1, %zmm1
+              vpbroadcastd 12(%rcx), %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$23, %zmm1, %zmm1
+              vpaddd %zmm2, %zmm1, %zmm1
+              add $$16, %rcx
+              add $$256, %rdx
+              dec %eax
+              jnz 4b
+              mov $$4, %eax
+              5:
+              vpxord %zmm7, %zmm3, %zmm4
+              vpord %zmm1, %zmm4, %zmm4
+              vpxord %zmm2, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm0, %zmm0
+              vpbroadcastd (%rcx), %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd (%rdx), %zmm0, %zmm0
+              vprold $$6, %zmm0, %zmm0
+              vpaddd %zmm1, %zmm0, %zmm0
+              vpxord %zmm7, %zmm2, %zmm4
+              vpord %zmm0, %zmm4, %zmm4
+              vpxord %zmm1, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm3, %zmm3
+              vpbroadcastd 4(%rcx), %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$10, %zmm3, %zmm3
+              vpaddd %zmm0, %zmm3, %zmm3
+              vpxord %zmm7, %zmm1, %zmm4
+              vpord %zmm3, %zmm4, %zmm4
+              vpxord %zmm0, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm2, %zmm2
+              vpbroadcastd 8(%rcx), %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$15, %zmm2, %zmm2
+              vpaddd %zmm3, %zmm2, %zmm2
+              vpxord %zmm7, %zmm0, %zmm4
+              vpord %zmm2, %zmm4, %zmm4
+              vpxord %zmm3, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm1, %zmm1
+              vpbroadcastd 12(%rcx), %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$21, %zmm1, %zmm1
+              vpaddd %zmm2, %zmm1, %zmm1
+              add $$16, %rcx
+              add $$256, %rdx
+              dec %eax
+              jnz 5b
+              vpaddd ($0), %zmm0, %zmm0
+              vmovdqu32 %zmm0, ($0)
+              vpaddd 64($0), %zmm1, %zmm1
+              vmovdqu32 %zmm1, 64($0)
+              vpaddd 128($0), %zmm2, %zmm2
+              vmovdqu32 %zmm2, 128($0)
+              vpaddd 192($0), %zmm3, %zmm3
+              vmovdqu32 %zmm3, 192($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K.as_ptr()), "r"(msgIndex.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm7", "rax", "rcx", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
 }
 
 impl Writer for Md5 {
@@ -208,3 +1031,47 @@ fn test_md5_long() {
     h.write_str("welcome to china".repeat(10001));
     assert_eq!(h.hexdigest(), ~"91fdcbaaf79739a20635cd24dd67e532");
 }
+
+#[test]
+fn test_md5_digest_many() {
+    let data = slice::from_fn(300, |i| (i * 7) as u8);
+    let mut msgs = Vec::new();
+    for n in range(0, 200u) {
+        msgs.push(data.slice_to(n));
+    }
+    let sums = digest_many(msgs.as_slice());
+    assert_eq!(sums.len(), 200);
+    for (msg, sum) in msgs.iter().zip(sums.iter()) {
+        let mut h = Md5::new();
+        h.write(*msg);
+        assert_eq!(h.digest().as_slice(), sum.as_slice());
+    }
+    assert_eq!(digest_many([]).len(), 0);
+}
+
+#[test]
+fn test_md5_digest_lanes() {
+    let data = slice::from_fn(2000, |i| (i * 7 + 3) as u8);
+    // long and short messages mixed, so lanes finish at different times
+    let mut msgs = Vec::new();
+    for i in range(0, 150u) {
+        let n = (i * 37) % 300 + if i == 7 { 1500 } else { 0 };
+        msgs.push(data.slice(i % 13, i % 13 + n));
+    }
+    let want = digest_lanes(msgs.as_slice(), 1);
+    for &lanes in [4u, 8, 16].iter() {
+        if !multibuf::supported(lanes) {
+            continue;
+        }
+        let got = digest_lanes(msgs.as_slice(), lanes);
+        for (a, b) in want.iter().zip(got.iter()) {
+            assert_eq!(a.as_slice(), b.as_slice());
+        }
+        assert_eq!(got.len(), want.len());
+        // fewer messages than lanes
+        let few = digest_lanes(msgs.slice_to(3), lanes);
+        assert_eq!(few.len(), 3);
+        assert_eq!(few.get(2).as_slice(), want.get(2).as_slice());
+        assert_eq!(digest_lanes([], lanes).len(), 0);
+    }
+}
@@ -0,0 +1,61 @@
+// The concatenated MD5 and SHA1 digest used by TLS 1.0/1.1 for RSA
+// signatures (Go's crypto.MD5SHA1). It is only as strong as SHA1.
+
+use std::io::IoResult;
+use super::md5::Md5;
+use super::sha1::Sha1;
+use super::super::hash::*;
+
+pub static Size: uint = 36;
+pub static BlockSize: uint = 64;
+
+#[deriving(Eq, Clone, Show)]
+pub struct Md5Sha1 {
+    md5: Md5,
+    sha1: Sha1,
+}
+
+impl Md5Sha1 {
+    pub fn new() -> Md5Sha1 {
+        Md5Sha1 { md5: Md5::new(), sha1: Sha1::new() }
+    }
+}
+
+impl Writer for Md5Sha1 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        try!(self.md5.write(buf));
+        self.sha1.write(buf)
+    }
+}
+
+impl Hash for Md5Sha1 {
+    fn reset(&mut self) {
+        self.md5.reset();
+        self.sha1.reset();
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut ret = Vec::with_capacity(Size);
+        ret.push_all(self.md5.digest().as_slice());
+        ret.push_all(self.sha1.digest().as_slice());
+        ret.as_slice().to_owned()
+    }
+
+    fn size(&self) -> uint {
+        Size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+#[test]
+fn test_md5sha1() {
+    let mut h = Md5Sha1::new();
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"900150983cd24fb0d6963f7d28e17f72a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(h.digest().len(), h.size());
+    h.reset();
+    assert_eq!(h.hexdigest(), ~"d41d8cd98f00b204e9800998ecf8427eda39a3ee5e6b4b0d3255bfef95601890afd80709");
+}
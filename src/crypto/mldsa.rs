@@ -0,0 +1,1292 @@
+// The quantum-resistant digital signature algorithm ML-DSA (formerly
+// known as Dilithium), as specified in NIST FIPS 204, with the ML-DSA-44,
+// ML-DSA-65 and ML-DSA-87 parameter sets.
+//
+// Signing is hedged by default, mixing randomness into the per-signature
+// seed as FIPS 204 recommends, and sign_deterministic provides the
+// deterministic variant. Only pure ML-DSA is implemented, with an
+// optional context string; the pre-hashed HashML-DSA is not. Private keys
+// are kept in their 32-byte seed form, which is what bytes returns; the
+// expanded FIPS 204 private key format is not supported.
+//
+// Field elements are kept in [0, q) and multiplied with Montgomery
+// reduction. Decompose uses the division-free formulas of the reference
+// implementation, so that no operation on secret values depends on their
+// contents, beyond the number of rejected signing attempts.
+
+use std::any::{Any, AnyRefExt};
+use std::fmt;
+use std::io::IoResult;
+use std::slice;
+use super::{HashFunc, SignerOpts};
+use super::sha3::Shake;
+
+/// The size, in bytes, of private key seeds.
+pub static SeedSize: uint = 32;
+
+static N: uint = 256;
+static Q: u32 = 8380417;
+// The number of dropped bits of t.
+static D: uint = 13;
+
+// -q⁻¹ mod 2^32, for Montgomery reduction.
+static QNegInv: u32 = 4236238847;
+// 2^64 mod q, to leave the Montgomery domain after a multiplication.
+static RSquared: u32 = 2365951;
+// 256⁻¹ · 2^32 mod q, the scaling factor of the inverse NTT in the
+// Montgomery domain.
+static InverseNttScale: u32 = 16382;
+
+// 1753^BitRev8(i) · 2^32 mod q, the powers of the 512-th root of unity
+// used by the NTT in the Montgomery domain, in the order they are
+// consumed.
+static Zetas: [u32, ..256] = [
+    4193792, 25847, 5771523, 7861508, 237124, 7602457, 7504169, 466468,
+    1826347, 2353451, 8021166, 6288512, 3119733, 5495562, 3111497, 2680103,
+    2725464, 1024112, 7300517, 3585928, 7830929, 7260833, 2619752, 6271868,
+    6262231, 4520680, 6980856, 5102745, 1757237, 8360995, 4010497, 280005,
+    2706023, 95776, 3077325, 3530437, 6718724, 4788269, 5842901, 3915439,
+    4519302, 5336701, 3574422, 5512770, 3539968, 8079950, 2348700, 7841118,
+    6681150, 6736599, 3505694, 4558682, 3507263, 6239768, 6779997, 3699596,
+    811944, 531354, 954230, 3881043, 3900724, 5823537, 2071892, 5582638,
+    4450022, 6851714, 4702672, 5339162, 6927966, 3475950, 2176455, 6795196,
+    7122806, 1939314, 4296819, 7380215, 5190273, 5223087, 4747489, 126922,
+    3412210, 7396998, 2147896, 2715295, 5412772, 4686924, 7969390, 5903370,
+    7709315, 7151892, 8357436, 7072248, 7998430, 1349076, 1852771, 6949987,
+    5037034, 264944, 508951, 3097992, 44288, 7280319, 904516, 3958618,
+    4656075, 8371839, 1653064, 5130689, 2389356, 8169440, 759969, 7063561,
+    189548, 4827145, 3159746, 6529015, 5971092, 8202977, 1315589, 1341330,
+    1285669, 6795489, 7567685, 6940675, 5361315, 4499357, 4751448, 3839961,
+    2091667, 3407706, 2316500, 3817976, 5037939, 2244091, 5933984, 4817955,
+    266997, 2434439, 7144689, 3513181, 4860065, 4621053, 7183191, 5187039,
+    900702, 1859098, 909542, 819034, 495491, 6767243, 8337157, 7857917,
+    7725090, 5257975, 2031748, 3207046, 4823422, 7855319, 7611795, 4784579,
+    342297, 286988, 5942594, 4108315, 3437287, 5038140, 1735879, 203044,
+    2842341, 2691481, 5790267, 1265009, 4055324, 1247620, 2486353, 1595974,
+    4613401, 1250494, 2635921, 4832145, 5386378, 1869119, 1903435, 7329447,
+    7047359, 1237275, 5062207, 6950192, 7929317, 1312455, 3306115, 6417775,
+    7100756, 1917081, 5834105, 7005614, 1500165, 777191, 2235880, 3406031,
+    7838005, 5548557, 6709241, 6533464, 5796124, 4656147, 594136, 4603424,
+    6366809, 2432395, 2454455, 8215696, 1957272, 3369112, 185531, 7173032,
+    5196991, 162844, 1616392, 3014001, 810149, 1652634, 4686184, 6581310,
+    5341501, 3523897, 3866901, 269760, 2213111, 7404533, 1717735, 472078,
+    7953734, 1723600, 6577327, 1910376, 6712985, 7276084, 8119771, 4546524,
+    5441381, 6144432, 7959518, 6094090, 183443, 7403526, 1612842, 4834730,
+    7826001, 3919660, 8332111, 7018208, 3937738, 1400424, 7534263, 1976782,
+];
+
+// The values of FIPS 204, Table 1, for one parameter set.
+struct Params {
+    k: uint,
+    l: uint,
+    eta: u32,
+    tau: uint,
+    beta: u32,
+    // log2 of γ1
+    gamma1_bits: uint,
+    gamma2: u32,
+    omega: uint,
+    // the collision strength λ, in bits
+    lambda: uint,
+}
+
+static Params44: Params = Params {
+    k: 4, l: 4, eta: 2, tau: 39, beta: 78, gamma1_bits: 17, gamma2: (Q - 1) / 88,
+    omega: 80, lambda: 128,
+};
+static Params65: Params = Params {
+    k: 6, l: 5, eta: 4, tau: 49, beta: 196, gamma1_bits: 19, gamma2: (Q - 1) / 32,
+    omega: 55, lambda: 192,
+};
+static Params87: Params = Params {
+    k: 8, l: 7, eta: 2, tau: 60, beta: 120, gamma1_bits: 19, gamma2: (Q - 1) / 32,
+    omega: 75, lambda: 256,
+};
+
+impl Params {
+    fn public_key_size(&self) -> uint {
+        32 + 32 * (23 - D) * self.k
+    }
+
+    fn signature_size(&self) -> uint {
+        self.lambda / 4 + 32 * (self.gamma1_bits + 1) * self.l + self.omega + self.k
+    }
+
+    // The size of the coefficients of w1, as packed by w1Encode.
+    fn w1_bits(&self) -> uint {
+        if self.gamma2 == (Q - 1) / 32 { 4 } else { 6 }
+    }
+}
+
+/// An ML-DSA parameter set.
+#[deriving(Eq, Clone)]
+pub enum Parameters {
+    /// ML-DSA-44, aiming at NIST security category 2.
+    MLDSA44,
+    /// ML-DSA-65, aiming at NIST security category 3.
+    MLDSA65,
+    /// ML-DSA-87, aiming at NIST security category 5.
+    MLDSA87,
+}
+
+// Returns a mod q for a < 2q.
+fn field_reduce_once(a: u32) -> u32 {
+    let x = a - Q;
+    // If a < q, x underflowed and its top bit is set.
+    x + (x >> 31) * Q
+}
+
+fn field_add(a: u32, b: u32) -> u32 {
+    field_reduce_once(a + b)
+}
+
+fn field_sub(a: u32, b: u32) -> u32 {
+    field_reduce_once(a - b + Q)
+}
+
+// Returns a · 2^-32 mod q for a < q · 2^32.
+fn field_montgomery_reduce(a: u64) -> u32 {
+    let t = (a as u32) * QNegInv;
+    field_reduce_once(((a + t as u64 * Q as u64) >> 32) as u32)
+}
+
+// Returns a · b · 2^-32 mod q.
+fn field_montgomery_mul(a: u32, b: u32) -> u32 {
+    field_montgomery_reduce(a as u64 * b as u64)
+}
+
+fn field_mul(a: u32, b: u32) -> u32 {
+    field_montgomery_mul(field_montgomery_mul(a, b), RSquared)
+}
+
+// Returns 1 if the absolute value of x, taken in (-q/2, q/2), is at least
+// bound, and 0 otherwise.
+fn field_abs_at_least(x: u32, bound: u32) -> u32 {
+    let neg = ((Q - 1) / 2 - x) >> 31;
+    let abs = x ^ ((x ^ (Q - x)) & (0 - neg));
+    (bound - 1 - abs) >> 31
+}
+
+// Splits r into r1 · 2^d + r0 with r0 in (-2^(d-1), 2^(d-1)], returning
+// r1 and r0 mod q, as Power2Round of FIPS 204, Algorithm 35.
+fn power2round(r: u32) -> (u32, u32) {
+    let r1 = (r + (1 << (D - 1)) - 1) >> D;
+    (r1, field_sub(r, r1 << D))
+}
+
+// Splits r into r1 · 2γ2 + r0 with r0 in (-γ2, γ2], or into 0 and r0 - 1
+// if r1 would be (q - 1) / 2γ2, as Decompose of FIPS 204, Algorithm 36.
+// The divisions are replaced by multiplications, as in the reference
+// implementation.
+fn decompose(r: u32, gamma2: u32) -> (u32, i32) {
+    let mut r1 = (r + 127) >> 7;
+    if gamma2 == (Q - 1) / 32 {
+        r1 = (r1 * 1025 + (1 << 21)) >> 22;
+        r1 &= 15;
+    } else {
+        r1 = (r1 * 11275 + (1 << 23)) >> 24;
+        // Map 44 to 0.
+        r1 ^= ((43 - r1) >> 31) * r1;
+    }
+    let mut r0 = r as i32 - (r1 * 2 * gamma2) as i32;
+    r0 -= (((Q as i32 - 1) / 2 - r0) >> 31) & Q as i32;
+    (r1, r0)
+}
+
+fn high_bits(r: u32, gamma2: u32) -> u32 {
+    let (r1, _) = decompose(r, gamma2);
+    r1
+}
+
+// Returns the high bits of r adjusted according to the hint h, as
+// UseHint of FIPS 204, Algorithm 40. It is only used for verification.
+fn use_hint(h: u32, r: u32, gamma2: u32) -> u32 {
+    let m = (Q - 1) / (2 * gamma2);
+    let (r1, r0) = decompose(r, gamma2);
+    if h == 0 {
+        r1
+    } else if r0 > 0 {
+        (r1 + 1) % m
+    } else {
+        (r1 + m - 1) % m
+    }
+}
+
+// An element of R_q = Z_q[X]/(X^256 + 1), either as a polynomial or in
+// the NTT domain, where it is the vector of its 256 evaluations.
+struct Poly {
+    f: [u32, ..N],
+}
+
+impl Clone for Poly {
+    fn clone(&self) -> Poly {
+        *self
+    }
+}
+
+impl Poly {
+    fn zero() -> Poly {
+        Poly { f: [0, ..N] }
+    }
+
+    fn add(&self, b: &Poly) -> Poly {
+        let mut r = Poly::zero();
+        for i in range(0, N) {
+            r.f[i] = field_add(self.f[i], b.f[i]);
+        }
+        r
+    }
+
+    fn sub(&self, b: &Poly) -> Poly {
+        let mut r = Poly::zero();
+        for i in range(0, N) {
+            r.f[i] = field_sub(self.f[i], b.f[i]);
+        }
+        r
+    }
+
+    // Returns the product of two elements in the NTT domain.
+    fn ntt_mul(&self, b: &Poly) -> Poly {
+        let mut r = Poly::zero();
+        for i in range(0, N) {
+            r.f[i] = field_mul(self.f[i], b.f[i]);
+        }
+        r
+    }
+
+    // Returns the NTT representation of the polynomial, as specified in
+    // FIPS 204, Algorithm 41.
+    fn ntt(&self) -> Poly {
+        let mut f = *self;
+        let mut m = 0u;
+        let mut len = 128u;
+        while len >= 1 {
+            let mut start = 0u;
+            while start < N {
+                m += 1;
+                let zeta = Zetas[m];
+                for j in range(start, start + len) {
+                    let t = field_montgomery_mul(zeta, f.f[j + len]);
+                    f.f[j + len] = field_sub(f.f[j], t);
+                    f.f[j] = field_add(f.f[j], t);
+                }
+                start += 2 * len;
+            }
+            len /= 2;
+        }
+        f
+    }
+
+    // Returns the polynomial with the given NTT representation, as
+    // specified in FIPS 204, Algorithm 42.
+    fn inverse_ntt(&self) -> Poly {
+        let mut f = *self;
+        let mut m = N;
+        let mut len = 1u;
+        while len < N {
+            let mut start = 0u;
+            while start < N {
+                m -= 1;
+                let zeta = Q - Zetas[m];
+                for j in range(start, start + len) {
+                    let t = f.f[j];
+                    f.f[j] = field_add(t, f.f[j + len]);
+                    f.f[j + len] = field_montgomery_mul(zeta, field_sub(t, f.f[j + len]));
+                }
+                start += 2 * len;
+            }
+            len *= 2;
+        }
+        for i in range(0, N) {
+            f.f[i] = field_montgomery_mul(f.f[i], InverseNttScale);
+        }
+        f
+    }
+
+    // Returns 1 if any coefficient has an absolute value of at least
+    // bound, and 0 otherwise.
+    fn norm_at_least(&self, bound: u32) -> u32 {
+        let mut v = 0u32;
+        for &x in self.f.iter() {
+            v |= field_abs_at_least(x, bound);
+        }
+        v
+    }
+}
+
+// Appends the coefficients of f, each bits long, to out, least
+// significant bits first, as SimpleBitPack of FIPS 204, Algorithm 16.
+fn encode(out: &mut Vec<u8>, f: &Poly, bits: uint) {
+    let mut acc = 0u32;
+    let mut n = 0u;
+    for &c in f.f.iter() {
+        acc |= c << n;
+        n += bits;
+        while n >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            n -= 8;
+        }
+    }
+}
+
+// Decodes 32 * bits bytes into 256 coefficients, as SimpleBitUnpack of
+// FIPS 204, Algorithm 18.
+fn decode(b: &[u8], bits: uint) -> Poly {
+    let mut f = Poly::zero();
+    let mut acc = 0u32;
+    let mut n = 0u;
+    let mut j = 0u;
+    for i in range(0, N) {
+        while n < bits {
+            acc |= b[j] as u32 << n;
+            j += 1;
+            n += 8;
+        }
+        f.f[i] = acc & ((1 << bits) - 1);
+        acc >>= bits;
+        n -= bits;
+    }
+    f
+}
+
+// Packs the z component of a signature, as BitPack(z, γ1 - 1, γ1).
+fn encode_z(out: &mut Vec<u8>, z: &Poly, gamma1_bits: uint) {
+    let mut v = Poly::zero();
+    for i in range(0, N) {
+        v.f[i] = field_sub(1 << gamma1_bits, z.f[i]);
+    }
+    encode(out, &v, gamma1_bits + 1);
+}
+
+fn decode_z(b: &[u8], gamma1_bits: uint) -> Poly {
+    let mut z = decode(b, gamma1_bits + 1);
+    for i in range(0, N) {
+        z.f[i] = field_sub(1 << gamma1_bits, z.f[i]);
+    }
+    z
+}
+
+// Decodes the hint vector of a signature, as HintBitUnpack of FIPS 204,
+// Algorithm 21. Encodings that are not the unique one produced by
+// signing are rejected.
+fn decode_hints(p: &Params, b: &[u8]) -> Option<~[Poly]> {
+    let mut h = Vec::with_capacity(p.k);
+    let mut index = 0u;
+    for i in range(0, p.k) {
+        let limit = b[p.omega + i] as uint;
+        if limit < index || limit > p.omega {
+            return None;
+        }
+        let first = index;
+        let mut f = Poly::zero();
+        while index < limit {
+            if index > first && b[index - 1] >= b[index] {
+                return None;
+            }
+            f.f[b[index] as uint] = 1;
+            index += 1;
+        }
+        h.push(f);
+    }
+    for i in range(index, p.omega) {
+        if b[i] != 0 {
+            return None;
+        }
+    }
+    Some(h.as_slice().to_owned())
+}
+
+fn shake256(parts: &[&[u8]], n: uint) -> ~[u8] {
+    let mut h = Shake::new256();
+    for p in parts.iter() {
+        h.write(*p).unwrap();
+    }
+    let mut out = slice::from_elem(n, 0u8);
+    h.read(out.as_mut_slice()).unwrap();
+    out
+}
+
+// Samples an element of the NTT domain from SHAKE128(rho || s || r), with
+// rejection sampling, as RejNTTPoly of FIPS 204, Algorithm 30.
+fn sample_ntt(rho: &[u8], s: u8, r: u8) -> Poly {
+    let mut xof = Shake::new128();
+    xof.write(rho).unwrap();
+    xof.write([s, r]).unwrap();
+    let mut f = Poly::zero();
+    let mut n = 0u;
+    let mut buf = [0u8, ..168];
+    loop {
+        xof.read(buf).unwrap();
+        let mut off = 0u;
+        while off < buf.len() {
+            let v = buf[off] as u32 | buf[off + 1] as u32 << 8 | (buf[off + 2] as u32 & 0x7f) << 16;
+            off += 3;
+            if v < Q {
+                f.f[n] = v;
+                n += 1;
+                if n == N {
+                    return f;
+                }
+            }
+        }
+    }
+}
+
+// Samples a polynomial with coefficients in [-η, η] from
+// SHAKE256(rho || r), as RejBoundedPoly of FIPS 204, Algorithm 31.
+fn sample_bounded(rho: &[u8], r: u16, eta: u32) -> Poly {
+    let mut xof = Shake::new256();
+    xof.write(rho).unwrap();
+    xof.write([r as u8, (r >> 8) as u8]).unwrap();
+    let mut f = Poly::zero();
+    let mut n = 0u;
+    let mut buf = [0u8, ..136];
+    loop {
+        xof.read(buf).unwrap();
+        for &b in buf.iter() {
+            for &z in [b as u32 & 0xf, b as u32 >> 4].iter() {
+                // CoeffFromHalfByte of FIPS 204, Algorithm 15, with
+                // z mod 5 computed without a division.
+                if eta == 2 && z < 15 {
+                    f.f[n] = field_sub(2, z - (205 * z >> 10) * 5);
+                } else if eta == 4 && z < 9 {
+                    f.f[n] = field_sub(4, z);
+                } else {
+                    continue;
+                }
+                n += 1;
+                if n == N {
+                    return f;
+                }
+            }
+        }
+    }
+}
+
+// Samples the masking polynomial with coefficients in (-γ1, γ1] from
+// SHAKE256(rho || r), as one step of ExpandMask of FIPS 204, Algorithm 34.
+fn sample_mask(rho: &[u8], r: u16, gamma1_bits: uint) -> Poly {
+    let r = [r as u8, (r >> 8) as u8];
+    let b = shake256([rho, r.as_slice()], 32 * (gamma1_bits + 1));
+    decode_z(b, gamma1_bits)
+}
+
+// Samples a polynomial with tau coefficients equal to ±1 and the rest
+// zero from SHAKE256(seed), as SampleInBall of FIPS 204, Algorithm 29.
+fn sample_in_ball(seed: &[u8], tau: uint) -> Poly {
+    let mut xof = Shake::new256();
+    xof.write(seed).unwrap();
+    let mut s = [0u8, ..8];
+    xof.read(s).unwrap();
+    let mut signs = 0u64;
+    for i in range(0, 8u) {
+        signs |= s[i] as u64 << (8 * i);
+    }
+    let mut c = Poly::zero();
+    let mut b = [0u8];
+    for i in range(N - tau, N) {
+        loop {
+            xof.read(b).unwrap();
+            if b[0] as uint <= i {
+                break;
+            }
+        }
+        let j = b[0] as uint;
+        c.f[i] = c.f[j];
+        c.f[j] = if signs & 1 == 1 { Q - 1 } else { 1 };
+        signs >>= 1;
+    }
+    c
+}
+
+// Returns the matrix Â of FIPS 204, with Â[r][s] at index r * l + s, as
+// ExpandA of FIPS 204, Algorithm 32.
+fn sample_matrix(rho: &[u8], k: uint, l: uint) -> ~[Poly] {
+    let mut a = Vec::with_capacity(k * l);
+    for r in range(0, k) {
+        for s in range(0, l) {
+            a.push(sample_ntt(rho, s as u8, r as u8));
+        }
+    }
+    a.as_slice().to_owned()
+}
+
+impl Parameters {
+    fn params(&self) -> &'static Params {
+        match *self {
+            MLDSA44 => &Params44,
+            MLDSA65 => &Params65,
+            MLDSA87 => &Params87,
+        }
+    }
+
+    /// Returns the size, in bytes, of encoded public keys.
+    pub fn public_key_size(&self) -> uint {
+        self.params().public_key_size()
+    }
+
+    /// Returns the size, in bytes, of signatures.
+    pub fn signature_size(&self) -> uint {
+        self.params().signature_size()
+    }
+
+    /// Generates a random private key, reading randomness from rand.
+    pub fn generate_key<R: Reader>(&self, rand: &mut R) -> IoResult<PrivateKey> {
+        let mut seed = [0u8, ..32];
+        try!(rand.fill(seed));
+        Ok(self.new_private_key(seed).unwrap())
+    }
+
+    /// Derives a private key from a SeedSize-byte seed, as
+    /// ML-DSA.KeyGen_internal of FIPS 204, Algorithm 6. The seed must be
+    /// uniformly random.
+    pub fn new_private_key(&self, seed: &[u8]) -> Result<PrivateKey, ~str> {
+        if seed.len() != SeedSize {
+            return Err(~"crypto/mldsa: invalid seed length");
+        }
+        let p = self.params();
+        let (k, l) = (p.k, p.l);
+        let kl = [k as u8, l as u8];
+        let h = shake256([seed, kl.as_slice()], 128);
+        let (rho, rho_prime, big_k) = (h.slice_to(32), h.slice(32, 96), h.slice_from(96));
+        let a = sample_matrix(rho, k, l);
+
+        let mut s1 = Vec::with_capacity(l);
+        for r in range(0, l) {
+            s1.push(sample_bounded(rho_prime, r as u16, p.eta).ntt());
+        }
+        let mut s2 = Vec::with_capacity(k);
+        let mut t0 = Vec::with_capacity(k);
+        let mut t1 = Vec::with_capacity(k);
+        let mut key = Vec::from_slice(rho);
+        for r in range(0, k) {
+            let s = sample_bounded(rho_prime, (l + r) as u16, p.eta);
+            // t = NTT⁻¹(Â ∘ NTT(s1)) + s2
+            let mut acc = Poly::zero();
+            for j in range(0, l) {
+                acc = acc.add(&a[r * l + j].ntt_mul(s1.get(j)));
+            }
+            let t = acc.inverse_ntt().add(&s);
+            let (mut hi, mut lo) = (Poly::zero(), Poly::zero());
+            for i in range(0, N) {
+                let (r1, r0) = power2round(t.f[i]);
+                hi.f[i] = r1;
+                lo.f[i] = r0;
+            }
+            encode(&mut key, &hi, 23 - D);
+            s2.push(s.ntt());
+            t0.push(lo.ntt());
+            t1.push(hi);
+        }
+
+        Ok(PrivateKey {
+            seed: seed.to_owned(),
+            big_k: big_k.to_owned(),
+            s1: s1.as_slice().to_owned(),
+            s2: s2.as_slice().to_owned(),
+            t0: t0.as_slice().to_owned(),
+            public_key: PublicKey::new(*self, key.as_slice().to_owned(), t1.as_slice(), a),
+        })
+    }
+
+    /// Checks that key is an encoded public key of this parameter set and
+    /// returns a PublicKey.
+    pub fn new_public_key(&self, key: &[u8]) -> Result<PublicKey, ~str> {
+        let p = self.params();
+        if key.len() != p.public_key_size() {
+            return Err(~"crypto/mldsa: invalid public key length");
+        }
+        let size = 32 * (23 - D);
+        let mut t1 = Vec::with_capacity(p.k);
+        for i in range(0, p.k) {
+            t1.push(decode(key.slice(32 + size * i, 32 + size * (i + 1)), 23 - D));
+        }
+        let a = sample_matrix(key.slice_to(32), p.k, p.l);
+        Ok(PublicKey::new(*self, key.to_owned(), t1.as_slice(), a))
+    }
+}
+
+impl fmt::Show for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            MLDSA44 => "ML-DSA-44",
+            MLDSA65 => "ML-DSA-65",
+            MLDSA87 => "ML-DSA-87",
+        };
+        f.buf.write_str(name)
+    }
+}
+
+/// An ML-DSA public key.
+#[deriving(Clone)]
+pub struct PublicKey {
+    params: Parameters,
+    // The encoding, rho || t1.
+    key: ~[u8],
+    // NTT(t1 · 2^d), the matrix Â and tr = H(key), needed by every
+    // verification.
+    t1: ~[Poly],
+    a: ~[Poly],
+    tr: ~[u8],
+}
+
+impl PublicKey {
+    fn new(params: Parameters, key: ~[u8], t1: &[Poly], a: ~[Poly]) -> PublicKey {
+        let mut t = Vec::with_capacity(t1.len());
+        for p in t1.iter() {
+            let mut s = Poly::zero();
+            for i in range(0, N) {
+                s.f[i] = p.f[i] << D;
+            }
+            t.push(s.ntt());
+        }
+        let tr = shake256([key.as_slice()], 64);
+        PublicKey { params: params, key: key, t1: t.as_slice().to_owned(), a: a, tr: tr }
+    }
+
+    /// Returns the parameter set of the key.
+    pub fn parameters(&self) -> Parameters {
+        self.params
+    }
+
+    /// Returns the encoding of the key, as pkEncode of FIPS 204,
+    /// Algorithm 22.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.clone()
+    }
+}
+
+impl Eq for PublicKey {
+    fn eq(&self, other: &PublicKey) -> bool {
+        self.params == other.params && self.key == other.key
+    }
+}
+
+impl super::PublicKey for PublicKey {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// An ML-DSA private key.
+#[deriving(Clone)]
+pub struct PrivateKey {
+    seed: ~[u8],
+    // K, the seed of the per-signature randomness, and s1, s2 and t0 in
+    // the NTT domain.
+    big_k: ~[u8],
+    s1: ~[Poly],
+    s2: ~[Poly],
+    t0: ~[Poly],
+    public_key: PublicKey,
+}
+
+impl PrivateKey {
+    /// Returns the parameter set of the key.
+    pub fn parameters(&self) -> Parameters {
+        self.public_key.params
+    }
+
+    /// Returns the SeedSize-byte seed the key was derived from.
+    pub fn bytes(&self) -> ~[u8] {
+        self.seed.clone()
+    }
+
+    /// Returns the public key corresponding to the key.
+    pub fn public(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+}
+
+impl super::PrivateKey for PrivateKey {}
+
+impl super::Signer for PrivateKey {
+    fn public(&self) -> ~super::PublicKey {
+        ~self.public_key.clone() as ~super::PublicKey
+    }
+
+    /// Signs message with the key, hedged with entropy from rand.
+    /// opts.hash_func() must be None, as HashML-DSA is not supported, and
+    /// opts can be an Options to provide a context string.
+    fn sign(&self, rand: &mut Reader, message: &[u8], opts: &SignerOpts)
+            -> Result<~[u8], ~str> {
+        if opts.hash_func().is_some() {
+            return Err(~"crypto/mldsa: pre-hashed messages are not supported");
+        }
+        let mut rand = rand;
+        match opts.as_any().as_ref::<Options>() {
+            Some(o) => sign(&mut rand, self, message, o.context.as_slice()),
+            None => sign(&mut rand, self, message, []),
+        }
+    }
+}
+
+/// Options for signing with a PrivateKey as a Signer.
+#[deriving(Clone)]
+pub struct Options {
+    /// The context string, at most 255 bytes.
+    pub context: ~[u8],
+}
+
+impl SignerOpts for Options {
+    fn hash_func(&self) -> Option<HashFunc> {
+        None
+    }
+
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+// The message representative of FIPS 204, Algorithms 2 and 3, that is
+// H(tr || M') with M' = 0 || len(context) || context || message.
+fn message_representative(key: &PublicKey, message: &[u8], context: &[u8]) -> ~[u8] {
+    let prefix = [0u8, context.len() as u8];
+    shake256([key.tr.as_slice(), prefix.as_slice(), context, message], 64)
+}
+
+// ML-DSA.Sign_internal of FIPS 204, Algorithm 7, with the 32-byte
+// randomness rnd.
+fn sign_internal(key: &PrivateKey, message: &[u8], context: &[u8], rnd: &[u8])
+                 -> Result<~[u8], ~str> {
+    if context.len() > 255 {
+        return Err(~"crypto/mldsa: context too long");
+    }
+    let pk = &key.public_key;
+    let p = pk.params.params();
+    let (k, l) = (p.k, p.l);
+    let gamma1 = 1u32 << p.gamma1_bits;
+    let mu = message_representative(pk, message, context);
+    let rho = shake256([key.big_k.as_slice(), rnd, mu.as_slice()], 64);
+
+    let mut kappa = 0u;
+    loop {
+        let mut y = Vec::with_capacity(l);
+        for r in range(0, l) {
+            y.push(sample_mask(rho, (kappa + r) as u16, p.gamma1_bits));
+        }
+        kappa += l;
+
+        // w = NTT⁻¹(Â ∘ NTT(y))
+        let y_hat: ~[Poly] = y.iter().map(|f| f.ntt()).collect();
+        let mut w = Vec::with_capacity(k);
+        let mut w1 = Vec::with_capacity(32 * p.w1_bits() * k);
+        for r in range(0, k) {
+            let mut acc = Poly::zero();
+            for s in range(0, l) {
+                acc = acc.add(&pk.a[r * l + s].ntt_mul(&y_hat[s]));
+            }
+            let wr = acc.inverse_ntt();
+            let mut hi = Poly::zero();
+            for i in range(0, N) {
+                hi.f[i] = high_bits(wr.f[i], p.gamma2);
+            }
+            encode(&mut w1, &hi, p.w1_bits());
+            w.push(wr);
+        }
+
+        let c_tilde = shake256([mu.as_slice(), w1.as_slice()], p.lambda / 4);
+        let c = sample_in_ball(c_tilde, p.tau).ntt();
+
+        // z = y + cs1, rejected unless ‖z‖∞ < γ1 - β
+        let mut z = Vec::with_capacity(l);
+        let mut reject = 0u32;
+        for s in range(0, l) {
+            let zs = y.get(s).add(&c.ntt_mul(&key.s1[s]).inverse_ntt());
+            reject |= zs.norm_at_least(gamma1 - p.beta);
+            z.push(zs);
+        }
+
+        // r0 = LowBits(w - cs2), rejected unless ‖r0‖∞ < γ2 - β, and
+        // ct0, rejected unless ‖ct0‖∞ < γ2
+        let mut hints = Vec::with_capacity(k);
+        let mut count = 0u;
+        for r in range(0, k) {
+            let wr = w.get(r).sub(&c.ntt_mul(&key.s2[r]).inverse_ntt());
+            let ct0 = c.ntt_mul(&key.t0[r]).inverse_ntt();
+            reject |= ct0.norm_at_least(p.gamma2);
+            // h = MakeHint(-ct0, w - cs2 + ct0)
+            let mut h = Poly::zero();
+            for i in range(0, N) {
+                let (r1, r0) = decompose(wr.f[i], p.gamma2);
+                let m = r0 >> 31;
+                let abs = ((r0 ^ m) - m) as u32;
+                reject |= (p.gamma2 - p.beta - 1 - abs) >> 31;
+                let v1 = high_bits(field_add(wr.f[i], ct0.f[i]), p.gamma2);
+                h.f[i] = (r1 != v1) as u32;
+                count += h.f[i] as uint;
+            }
+            hints.push(h);
+        }
+        if reject == 1 || count > p.omega {
+            continue;
+        }
+
+        // sigEncode of FIPS 204, Algorithm 26.
+        let mut sig = Vec::with_capacity(p.signature_size());
+        sig.push_all(c_tilde.as_slice());
+        for s in range(0, l) {
+            encode_z(&mut sig, z.get(s), p.gamma1_bits);
+        }
+        let mut packed = slice::from_elem(p.omega + k, 0u8);
+        let mut index = 0u;
+        for r in range(0, k) {
+            for i in range(0, N) {
+                if hints.get(r).f[i] != 0 {
+                    packed[index] = i as u8;
+                    index += 1;
+                }
+            }
+            packed[p.omega + r] = index as u8;
+        }
+        sig.push_all(packed.as_slice());
+        return Ok(sig.as_slice().to_owned());
+    }
+}
+
+/// Signs message with the key and the given context string, which may be
+/// empty and is at most 255 bytes long. The signature is hedged with 32
+/// bytes read from rand, as recommended by FIPS 204, so that it does not
+/// rely solely on the quality of rand nor leak the key through fault
+/// attacks on deterministic signing.
+pub fn sign<R: Reader>(rand: &mut R, key: &PrivateKey, message: &[u8], context: &[u8])
+                       -> Result<~[u8], ~str> {
+    let mut rnd = [0u8, ..32];
+    match rand.fill(rnd) {
+        Ok(()) => {}
+        Err(e) => return Err(format!("crypto/mldsa: reading randomness failed: {}", e)),
+    }
+    sign_internal(key, message, context, rnd)
+}
+
+/// Signs message with the key and the given context string, using the
+/// deterministic variant of FIPS 204, which always produces the same
+/// signature for the same inputs.
+pub fn sign_deterministic(key: &PrivateKey, message: &[u8], context: &[u8])
+                          -> Result<~[u8], ~str> {
+    sign_internal(key, message, context, [0u8, ..32])
+}
+
+/// Reports whether sig is a valid signature of message and the context
+/// string by the public key, as ML-DSA.Verify of FIPS 204, Algorithm 3.
+pub fn verify(key: &PublicKey, message: &[u8], sig: &[u8], context: &[u8]) -> bool {
+    let p = key.params.params();
+    if sig.len() != p.signature_size() || context.len() > 255 {
+        return false;
+    }
+    let (k, l) = (p.k, p.l);
+
+    // sigDecode of FIPS 204, Algorithm 27.
+    let c_tilde = sig.slice_to(p.lambda / 4);
+    let size = 32 * (p.gamma1_bits + 1);
+    let mut z = Vec::with_capacity(l);
+    for s in range(0, l) {
+        let off = p.lambda / 4 + size * s;
+        let zs = decode_z(sig.slice(off, off + size), p.gamma1_bits);
+        if zs.norm_at_least((1 << p.gamma1_bits) - p.beta) == 1 {
+            return false;
+        }
+        z.push(zs.ntt());
+    }
+    let hints = match decode_hints(p, sig.slice_from(p.lambda / 4 + size * l)) {
+        Some(h) => h,
+        None => return false,
+    };
+
+    let mu = message_representative(key, message, context);
+    let c = sample_in_ball(c_tilde, p.tau).ntt();
+
+    // w1 = UseHint(h, NTT⁻¹(Â ∘ NTT(z) - NTT(c) ∘ NTT(t1 · 2^d)))
+    let mut w1 = Vec::with_capacity(32 * p.w1_bits() * k);
+    for r in range(0, k) {
+        let mut acc = Poly::zero();
+        for s in range(0, l) {
+            acc = acc.add(&key.a[r * l + s].ntt_mul(z.get(s)));
+        }
+        let w = acc.sub(&c.ntt_mul(&key.t1[r])).inverse_ntt();
+        let mut hi = Poly::zero();
+        for i in range(0, N) {
+            hi.f[i] = use_hint(hints[r].f[i], w.f[i], p.gamma2);
+        }
+        encode(&mut w1, &hi, p.w1_bits());
+    }
+    shake256([mu.as_slice(), w1.as_slice()], p.lambda / 4).as_slice() == c_tilde
+}
+
+
+#[cfg(test)]
+mod test {
+    use IoReader = std::io::Reader;
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use std::slice;
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use super::*;
+    use super::{N, Q, D, Params, Poly, decompose, encode, field_sub, sign_internal};
+    use super::super::{SHA256, Signer, SignerOpts, sign_message};
+    use super::super::rand::Reader;
+    use super::super::sha3::{Sha3, Sha3_256};
+
+    fn sha3_256(b: &[u8]) -> ~str {
+        let mut h = Sha3::new(Sha3_256);
+        h.write(b).unwrap();
+        h.hexdigest()
+    }
+
+    #[test]
+    fn test_decompose() {
+        for &gamma2 in [(Q - 1) / 88, (Q - 1) / 32].iter() {
+            let mut r = 0u32;
+            while r < Q {
+                // the definition of FIPS 204, Algorithm 36
+                let mut r0 = (r % (2 * gamma2)) as i32;
+                if r0 > gamma2 as i32 {
+                    r0 -= 2 * gamma2 as i32;
+                }
+                let want = if r as i32 - r0 == Q as i32 - 1 {
+                    (0, r0 - 1)
+                } else {
+                    ((r as i32 - r0) as u32 / (2 * gamma2), r0)
+                };
+                assert_eq!(decompose(r, gamma2), want);
+                r += if r % (2 * gamma2) < 3 || r > Q - 3 { 1 } else { 997 };
+            }
+        }
+    }
+
+    // The vectors below were computed with OpenSSL 3.5, from the seed
+    // 00 01 02 .. 1f and the message "message". The deterministic
+    // signatures have an empty context; the hedged ones have the context
+    // "ML-DSA test" and the randomness 20 21 22 .. 3f.
+    static Vectors: [(Parameters, &'static str, &'static str, &'static str), ..3] = [
+        (MLDSA44,
+         "373c7bf2cac5bd2a6c35933bab0fa1c951f22247e1333383fcb618822080373f",
+         "c0c8c7b976682de6bc54432b5cc5c4fe846512efbc760a98f75e36977381649e",
+         "98a34e98dbfba79aa6db4b7e0973a171831ada4543832854255de84128040153"),
+        (MLDSA65,
+         "1800725067e388d837d911fe4f66101cc1961b1bb755030dc574272cfb00013f",
+         "2eece2b319b2de469a9b95e06b3ba3872af5c23feadc382225765530199dc808",
+         "528e41a7fea6b4191cebe4f8edbe64f3e231338b268a36da6d4812c3a7f176bf"),
+        (MLDSA87,
+         "e6cf50a9c2fa5234f59949ff61f8161db4d629532127f4aefa8bb10811ecfb1e",
+         "17a0ecbba4b141564f4aff65d39859864f8d0aa236001c83965593fe1c5feecd",
+         "5aed69a54bfd6bb3eb6ce784c78b99bf5cc837586b57295203e04fec27d6355d"),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        let seed: ~[u8] = range(0u8, 32).collect();
+        let rnd: ~[u8] = range(32u8, 64).collect();
+        let msg = bytes!("message");
+        let ctx = bytes!("ML-DSA test");
+        for &(params, public, det, hedged) in Vectors.iter() {
+            let key = params.new_private_key(seed).unwrap();
+            let pk = key.public();
+            assert_eq!(pk.bytes().len(), params.public_key_size());
+            assert_eq!(sha3_256(pk.bytes()), public.to_owned());
+
+            let sig = sign_deterministic(&key, msg, []).unwrap();
+            assert_eq!(sig.len(), params.signature_size());
+            assert_eq!(sha3_256(sig), det.to_owned());
+            assert!(verify(&pk, msg, sig, []));
+            assert!(!verify(&pk, msg, sig, ctx));
+            assert!(!verify(&pk, bytes!("massage"), sig, []));
+
+            let sig = sign_internal(&key, msg, ctx, rnd).unwrap();
+            assert_eq!(sha3_256(sig), hedged.to_owned());
+            assert!(verify(&pk, msg, sig, ctx));
+            assert!(!verify(&pk, msg, sig, []));
+            let mut bad = sig.clone();
+            bad[10] ^= 1;
+            assert!(!verify(&pk, msg, bad, ctx));
+        }
+    }
+
+    // Vectors in the shape of the NIST ACVP ML-DSA tests for the external
+    // interface: keyGen from a seed, checked against both the public key
+    // and the expanded private key; sigGen, deterministic (empty rnd) and
+    // hedged, with a context; and sigVer, with the negative cases built in
+    // test_acvp_sig_ver from the first sigGen signature. Large values are
+    // given by their SHA3-256. They were computed with a Python model of
+    // FIPS 204 and checked against OpenSSL 3.5, including every sigVer
+    // case.
+
+    struct AcvpVectors {
+        params: Parameters,
+        // seed, SHA3-256(pk), SHA3-256(sk)
+        key_gen: &'static [(&'static str, &'static str, &'static str)],
+        // with the first key: rnd, message, context, SHA3-256(signature)
+        sig_gen: &'static [(&'static str, &'static str, &'static str, &'static str)],
+    }
+
+    static Acvp: &'static [AcvpVectors] = &[
+        AcvpVectors {
+            params: MLDSA44,
+            key_gen: &[
+                ("7fdb445da3e43eb179c4f85415895139ad8e15ee2758d319fa5851383011f8dd",
+                 "ac45a59d55aef6134308fc849841cf000caa186f4ea76d726358b0aa126560dd",
+                 "a42b2be2789362a8048074999443bbe3a66827449d670fb2619e5740c8cd2359"),
+                ("54fc9ae49b6da216eaedf610b19ad355cbb0a4d56e013a5644ccee6fb3fb1b33",
+                 "bf706775b122abc0db4ec33f8239dccbba8fe4324ff7d1c1b076bbad83b26736",
+                 "8f5de7592550cf5e3e8913a0c6b0517e9f00472d8bd7fbfdd1e3860340e64829"),
+            ],
+            sig_gen: &[
+                ("",
+                 "d088d1ac207f92d3844c17db5ec28426015b02e796ae6979facad9a7d489cdf8\
+                  39",
+                 "",
+                 "767e8263575b940a161fd827f27c659f75a62b749842fb6b3c6556fd5ea140cf"),
+                ("e97145e7b1947520459f3588941f69ae9fe3b2f6d11edafda0c9f78b7f6de384",
+                 "29b99219d6234160cf3e5608a1840cf7da",
+                 "14a53caeed43876b77",
+                 "56b300963a36c980b3f068eeefd906467cd2b49233445ebbbf386a05221345c1"),
+                ("",
+                 "93",
+                 "6bc4d26478",
+                 "afdf2d89e399c03342579eeb8a4a56f631b2958c41b914b82e57178e8b864140"),
+            ],
+        },
+        AcvpVectors {
+            params: MLDSA65,
+            key_gen: &[
+                ("16671f5c0dc6c7041171aaa391024e51da588aa5af84e47f164d286e32edfd1c",
+                 "bd5fdd0543b4909a7213a030dd0045f7b6448485a190f88d5a58cc28d1adbac8",
+                 "b5514d924f79fb11292944e05ccc268f95a45a06965869817d67f09e12ba1b18"),
+                ("a8160228210cfb24074fafbcd8f55b042d800d3d94e6b5fe4eb29606267e676e",
+                 "4bc5ce9f77ab0f2f9e59b8badebdcb3e9e97a8a698653383825d3a7287c696b4",
+                 "9c7d6b0cb94fe15a0f05613625cb98e5cfefb7476b53c45910d551446c40d1b4"),
+            ],
+            sig_gen: &[
+                ("",
+                 "fe838b1f198f11071b4ca07cde77404bc8aa9c3ffcc843d43992a8f5323f4cfa\
+                  3f",
+                 "",
+                 "31426548719dbd9d2f849c0f1dd38a40ee5e53d58a03dddf6ad318cfd760b25c"),
+                ("598a2739da06859a48d3a655e760530b2588d5d0f87a4820ce1d50d961b6cf41",
+                 "0cec8f7f2f3d1db246b961c300bb488fec",
+                 "19cd28aa6ff63a7c64",
+                 "70a14561060f7eaddaafa49f96c2f3835f9b898854f01841df0fca9fe95509c0"),
+                ("",
+                 "c1",
+                 "8be40c864e",
+                 "92f66615ad382c295926329eb3bbb0f049e2fa0882dc50501a4a2e3acde594b1"),
+            ],
+        },
+        AcvpVectors {
+            params: MLDSA87,
+            key_gen: &[
+                ("7e1e282aefc5558132073b31c87e49b5ac77a2a3f089d08ff31d37e5095b07ff",
+                 "f969faf6115b7836192cf59f0c7d437d570e42f0b26d69e2ddddfc7a4ede81d4",
+                 "471e8a1210aa25ceb0ccf618f0c222192a359621d1f573d3f91d983d7edbfaac"),
+                ("31de1639251bb4d1701e41ba7ff6362b5cd7639e0e1af115e4c35c679fd8060b",
+                 "a61f96b67991e1785ab1263501ee6628c951310af5734f2b113ca385fe7b3497",
+                 "1d515fe64e64819c4695fe177b144734f21b5abee0faa7387cc9896a2cc6f8b5"),
+            ],
+            sig_gen: &[
+                ("",
+                 "cef44c87ab9063ba8ff59a608727972309873764624656ad5f564f584eed79d2\
+                  27",
+                 "",
+                 "d9e6e57cf3d82fe42a58e8757af32388a2f177c5ecc5c230f8630a0994b2fdeb"),
+                ("607998506cc71e867b763f74a0f0b1dd8c87dc1ecabe1ff1fa3eaa278802794a",
+                 "dc1b12e5bfa888cd88558be3a26e68798a",
+                 "056d3524a193c3ca4e",
+                 "b58f1034f64d6321127691c7658adb5f78456483a89833009a7524b503025b3e"),
+                ("",
+                 "d5",
+                 "d174a75408",
+                 "97150fce6fd93c7213c5d2a9cd911cd0d575a041aecb4ceab5fe914bd88a12f1"),
+            ],
+        },
+    ];
+
+    // The expanded private key of FIPS 204, skEncode, Algorithm 24, which
+    // ACVP uses. The package, like Go's, only exposes the seed.
+    fn expanded_private_key(key: &PrivateKey) -> ~[u8] {
+        let p = key.public_key.params.params();
+        let bits = if p.eta == 2 { 3 } else { 4 };
+        let mut out = Vec::new();
+        out.push_all(key.public_key.key.slice_to(32));
+        out.push_all(key.big_k.as_slice());
+        out.push_all(key.public_key.tr.as_slice());
+        for s in key.s1.iter().chain(key.s2.iter()) {
+            let f = s.inverse_ntt();
+            let mut v = Poly::zero();
+            for i in range(0, N) {
+                v.f[i] = field_sub(p.eta, f.f[i]);
+            }
+            encode(&mut out, &v, bits);
+        }
+        for t in key.t0.iter() {
+            let f = t.inverse_ntt();
+            let mut v = Poly::zero();
+            for i in range(0, N) {
+                v.f[i] = field_sub(1 << (D - 1), f.f[i]);
+            }
+            encode(&mut out, &v, D);
+        }
+        out.as_slice().to_owned()
+    }
+
+    fn sig_gen(key: &PrivateKey, rnd: &str, msg: &str, ctx: &str) -> ~[u8] {
+        let rnd = if rnd.len() == 0 {
+            slice::from_elem(32, 0u8)
+        } else {
+            rnd.from_hex().unwrap()
+        };
+        sign_internal(key, msg.from_hex().unwrap(), ctx.from_hex().unwrap(), rnd).unwrap()
+    }
+
+    #[test]
+    fn test_acvp_key_gen() {
+        for v in Acvp.iter() {
+            for &(seed, pk, sk) in v.key_gen.iter() {
+                let key = v.params.new_private_key(seed.from_hex().unwrap()).unwrap();
+                assert_eq!(sha3_256(key.public().bytes()), pk.to_owned());
+                assert_eq!(sha3_256(expanded_private_key(&key)), sk.to_owned());
+            }
+        }
+    }
+
+    #[test]
+    fn test_acvp_sig_gen() {
+        for v in Acvp.iter() {
+            let (seed, _, _) = v.key_gen[0];
+            let key = v.params.new_private_key(seed.from_hex().unwrap()).unwrap();
+            for &(rnd, msg, ctx, want) in v.sig_gen.iter() {
+                let sig = sig_gen(&key, rnd, msg, ctx);
+                assert_eq!(sha3_256(sig), want.to_owned());
+                assert!(verify(&key.public(), msg.from_hex().unwrap(), sig,
+                               ctx.from_hex().unwrap()));
+            }
+        }
+    }
+
+    // Sets the first coefficient of z to γ1 - x, its encoding being x.
+    fn set_z(sig: &mut [u8], p: &Params, x: u32) {
+        let off = p.lambda / 4;
+        let mask = (1u8 << (p.gamma1_bits + 1 - 16)) - 1;
+        sig[off] = x as u8;
+        sig[off + 1] = (x >> 8) as u8;
+        sig[off + 2] = (sig[off + 2] & !mask) | (x >> 16) as u8;
+    }
+
+    #[test]
+    fn test_acvp_sig_ver() {
+        for v in Acvp.iter() {
+            let p = v.params.params();
+            let (seed, _, _) = v.key_gen[0];
+            let key = v.params.new_private_key(seed.from_hex().unwrap()).unwrap();
+            let pk = key.public();
+            let (rnd, msg, ctx, _) = v.sig_gen[0];
+            let sig = sig_gen(&key, rnd, msg, ctx);
+            let (msg, ctx) = (msg.from_hex().unwrap(), ctx.from_hex().unwrap());
+            assert!(verify(&pk, msg, sig, ctx));
+
+            assert!(!verify(&pk, msg + bytes!("!"), sig, ctx));
+            assert!(!verify(&pk, msg, sig, ctx + bytes!("!")));
+            let mut bad = sig.clone();
+            bad[0] ^= 1;
+            assert!(!verify(&pk, msg, bad, ctx));
+
+            // z out of range: ‖z‖∞ must be below γ1 - β
+            let gamma1 = 1u32 << p.gamma1_bits;
+            let mut bad = sig.clone();
+            set_z(bad.as_mut_slice(), p, p.beta);
+            assert!(!verify(&pk, msg, bad, ctx));
+            let mut bad = sig.clone();
+            set_z(bad.as_mut_slice(), p, 2 * gamma1 - p.beta);
+            assert!(!verify(&pk, msg, bad, ctx));
+
+            // Malformed hint encodings. The vectors were picked so that the
+            // first polynomial has at least two hints and fewer than ω
+            // hints are used in total.
+            let (n, k) = (sig.len(), p.k);
+            let hints = n - p.omega - k;
+            let counts = sig.slice_from(n - k).to_owned();
+            assert!(counts[0] >= 2 && (counts[k - 1] as uint) < p.omega);
+            let mut bad = sig.clone();
+            bad[n - 1] = p.omega as u8 + 1;
+            assert!(!verify(&pk, msg, bad, ctx));
+            let mut bad = sig.clone();
+            bad[n - k] = counts[1] + 1;
+            assert!(!verify(&pk, msg, bad, ctx));
+            let mut bad = sig.clone();
+            bad.swap(hints, hints + 1);
+            assert!(!verify(&pk, msg, bad, ctx));
+            let mut bad = sig.clone();
+            bad[hints + 1] = bad[hints];
+            assert!(!verify(&pk, msg, bad, ctx));
+            let mut bad = sig.clone();
+            bad[hints + p.omega - 1] = 1;
+            assert!(!verify(&pk, msg, bad, ctx));
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let key = MLDSA44.new_private_key([1u8, ..32]).unwrap();
+        let pk = key.public();
+        let msg = bytes!("message");
+        let sig = sign_deterministic(&key, msg, []).unwrap();
+        assert!(!verify(&pk, msg, sig.slice_from(1), []));
+        assert!(!verify(&pk, msg, sig + bytes!("!"), []));
+
+        // the hint counts must be increasing and at most ω
+        let n = sig.len();
+        let mut bad = sig.clone();
+        bad[n - 1] = 81;
+        assert!(!verify(&pk, msg, bad, []));
+        let mut bad = sig.clone();
+        bad[n - 4] = bad[n - 3] + 1;
+        assert!(!verify(&pk, msg, bad, []));
+
+        assert!(sign_deterministic(&key, msg, [0u8, ..256]).is_err());
+        assert!(!verify(&pk, msg, sig, [0u8, ..256]));
+        assert!(MLDSA44.new_private_key([1u8, ..31]).is_err());
+        assert!(MLDSA65.new_public_key(pk.bytes()).is_err());
+        assert!(MLDSA44.new_public_key(pk.bytes()).unwrap() == pk);
+        let other = MLDSA87.new_private_key([1u8, ..32]).unwrap();
+        assert!(!verify(&other.public(), msg, sig, []));
+    }
+
+    #[test]
+    fn test_generate_key() {
+        for &params in [MLDSA44, MLDSA65, MLDSA87].iter() {
+            let key = params.generate_key(&mut Reader).unwrap();
+            assert_eq!(key.parameters(), params);
+            assert_eq!(key.bytes().len(), SeedSize);
+            assert!(params.new_private_key(key.bytes()).unwrap().public() == key.public());
+            let msg = bytes!("hello, world");
+            let sig = sign(&mut Reader, &key, msg, bytes!("ctx")).unwrap();
+            assert!(verify(&key.public(), msg, sig, bytes!("ctx")));
+            let sig1 = sign(&mut Reader, &key, msg, bytes!("ctx")).unwrap();
+            assert!(sig != sig1);
+        }
+        assert_eq!(format!("{} {}", MLDSA44, MLDSA87), ~"ML-DSA-44 ML-DSA-87");
+    }
+
+    #[test]
+    fn test_signer() {
+        let key = MLDSA65.new_private_key([7u8, ..32]).unwrap();
+        let signer = &key as &Signer;
+        let mut rand = Reader;
+        let rand = &mut rand as &mut IoReader;
+
+        let public = signer.public();
+        assert!(public.as_any().as_ref::<PublicKey>() == Some(&key.public()));
+        let public = key.public();
+        let public = &public as &PublicKeyTrait;
+        assert!(public.as_any().as_ref::<PublicKey>().is_some());
+
+        let msg = bytes!("firmware image");
+        let opts = Options { context: bytes!("v1").to_owned() };
+        let sig = signer.sign(rand, msg, &opts as &SignerOpts).unwrap();
+        assert!(verify(&key.public(), msg, sig, bytes!("v1")));
+        let sig = sign_message(signer, rand, msg, &opts as &SignerOpts).unwrap();
+        assert!(verify(&key.public(), msg, sig, bytes!("v1")));
+        assert!(signer.sign(rand, msg, &SHA256 as &SignerOpts).is_err());
+    }
+}
@@ -0,0 +1,1048 @@
+// The quantum-resistant key encapsulation method ML-KEM (formerly known as
+// Kyber), as specified in NIST FIPS 203, after Go's crypto/mlkem.
+//
+// Only the ML-KEM-768 and ML-KEM-1024 parameter sets are provided, as in
+// Go. Decapsulation keys are kept in their 64-byte seed form, d || z,
+// which is what bytes returns; the expanded FIPS 203 decapsulation key
+// format is not supported.
+//
+// Field elements are reduced with Barrett reduction and compressed
+// without divisions, so that no operation on secret values depends on
+// their contents. Decapsulation of an invalid ciphertext implicitly
+// rejects it, returning a pseudorandom shared key rather than an error.
+
+use std::any::Any;
+use std::io::IoResult;
+use hash::Hash;
+use super::sha3::{Sha3, Sha3_256, Sha3_512, Shake};
+use super::subtle::{constant_time_compare, constant_time_copy};
+
+/// The size, in bytes, of shared keys produced by ML-KEM.
+pub static SharedKeySize: uint = 32;
+/// The size, in bytes, of the seeds from which decapsulation keys are
+/// derived.
+pub static SeedSize: uint = 64;
+/// The size, in bytes, of ML-KEM-768 ciphertexts.
+pub static CiphertextSize768: uint = 1088;
+/// The size, in bytes, of ML-KEM-768 encapsulation keys.
+pub static EncapsulationKeySize768: uint = 1184;
+/// The size, in bytes, of ML-KEM-1024 ciphertexts.
+pub static CiphertextSize1024: uint = 1568;
+/// The size, in bytes, of ML-KEM-1024 encapsulation keys.
+pub static EncapsulationKeySize1024: uint = 1568;
+
+static N: uint = 256;
+static Q: u16 = 3329;
+
+// 2^24 / q, for Barrett reduction of values up to 2q².
+static BarrettMultiplier: u64 = 5039;
+static BarrettShift: uint = 24;
+
+// 128⁻¹ mod q, the scaling factor of the inverse NTT.
+static InverseNttScale: u16 = 3303;
+
+// 17^BitRev7(i) mod q, the powers of the 256-th root of unity used by the
+// NTT, in the order they are consumed.
+static Zetas: [u16, ..128] = [
+    1, 1729, 2580, 3289, 2642, 630, 1897, 848, 1062, 1919, 193, 797, 2786, 3260, 569, 1746,
+    296, 2447, 1339, 1476, 3046, 56, 2240, 1333, 1426, 2094, 535, 2882, 2393, 2879, 1974, 821,
+    289, 331, 3253, 1756, 1197, 2304, 2277, 2055, 650, 1977, 2513, 632, 2865, 33, 1320, 1915,
+    2319, 1435, 807, 452, 1438, 2868, 1534, 2402, 2647, 2617, 1481, 648, 2474, 3110, 1227, 910,
+    17, 2761, 583, 2649, 1637, 723, 2288, 1100, 1409, 2662, 3281, 233, 756, 2156, 3015, 3050,
+    1703, 1651, 2789, 1789, 1847, 952, 1461, 2687, 939, 2308, 2437, 2388, 733, 2337, 268, 641,
+    1584, 2298, 2037, 3220, 375, 2549, 2090, 1645, 1063, 319, 2773, 757, 2099, 561, 2466, 2594,
+    2804, 1092, 403, 1026, 1143, 2150, 2775, 886, 1722, 1212, 1874, 1029, 2110, 2935, 885, 2154,
+];
+
+// 17^(2 BitRev7(i) + 1) mod q, the moduli of the degree-two factors used
+// when multiplying in the NTT domain.
+static Gammas: [u16, ..128] = [
+    17, 3312, 2761, 568, 583, 2746, 2649, 680, 1637, 1692, 723, 2606, 2288, 1041, 1100, 2229,
+    1409, 1920, 2662, 667, 3281, 48, 233, 3096, 756, 2573, 2156, 1173, 3015, 314, 3050, 279,
+    1703, 1626, 1651, 1678, 2789, 540, 1789, 1540, 1847, 1482, 952, 2377, 1461, 1868, 2687, 642,
+    939, 2390, 2308, 1021, 2437, 892, 2388, 941, 733, 2596, 2337, 992, 268, 3061, 641, 2688,
+    1584, 1745, 2298, 1031, 2037, 1292, 3220, 109, 375, 2954, 2549, 780, 2090, 1239, 1645, 1684,
+    1063, 2266, 319, 3010, 2773, 556, 757, 2572, 2099, 1230, 561, 2768, 2466, 863, 2594, 735,
+    2804, 525, 1092, 2237, 403, 2926, 1026, 2303, 1143, 2186, 2150, 1179, 2775, 554, 886, 2443,
+    1722, 1607, 1212, 2117, 1874, 1455, 1029, 2300, 2110, 1219, 2935, 394, 885, 2444, 2154, 1175,
+];
+
+// The parameters that differ between ML-KEM-768 and ML-KEM-1024. Both use
+// η₁ = η₂ = 2.
+struct Params {
+    k: uint,
+    du: uint,
+    dv: uint,
+}
+
+static Params768: Params = Params { k: 3, du: 10, dv: 4 };
+static Params1024: Params = Params { k: 4, du: 11, dv: 5 };
+
+impl Params {
+    fn encapsulation_key_size(&self) -> uint {
+        384 * self.k + 32
+    }
+
+    fn ciphertext_size(&self) -> uint {
+        32 * (self.du * self.k + self.dv)
+    }
+}
+
+// Returns a mod q for a < 2q.
+fn field_reduce_once(a: u16) -> u16 {
+    let x = a - Q;
+    // If a < q, x underflowed and its top bit is set.
+    x + (x >> 15) * Q
+}
+
+fn field_add(a: u16, b: u16) -> u16 {
+    field_reduce_once(a + b)
+}
+
+fn field_sub(a: u16, b: u16) -> u16 {
+    field_reduce_once(a - b + Q)
+}
+
+// Returns a mod q for a < 2q², with Barrett reduction.
+fn field_reduce(a: u32) -> u16 {
+    let quotient = ((a as u64 * BarrettMultiplier) >> BarrettShift) as u32;
+    field_reduce_once((a - quotient * Q as u32) as u16)
+}
+
+fn field_mul(a: u16, b: u16) -> u16 {
+    field_reduce(a as u32 * b as u32)
+}
+
+// Returns a * b + c * d mod q.
+fn field_add_mul(a: u16, b: u16, c: u16, d: u16) -> u16 {
+    field_reduce(a as u32 * b as u32 + c as u32 * d as u32)
+}
+
+// Maps x to round(2^d / q * x) mod 2^d, as Compress_d of FIPS 203,
+// Section 4.2.1, without divisions.
+fn compress(x: u16, d: uint) -> u16 {
+    let dividend = x as u32 << d;
+    let mut quotient = ((dividend as u64 * BarrettMultiplier) >> BarrettShift) as u32;
+    let remainder = dividend - quotient * Q as u32;
+    // The remainder is in [0, 2q), so round up once if it is above q/2 and
+    // once more if it is above q + q/2. If remainder > x, x - remainder
+    // underflows and has its top bit set.
+    quotient += ((Q as u32 / 2 - remainder) >> 31) & 1;
+    quotient += ((Q as u32 + Q as u32 / 2 - remainder) >> 31) & 1;
+    (quotient & ((1 << d) - 1)) as u16
+}
+
+// Maps y to round(q / 2^d * y), as Decompress_d of FIPS 203, Section
+// 4.2.1.
+fn decompress(y: u16, d: uint) -> u16 {
+    let dividend = y as u32 * Q as u32;
+    let quotient = (dividend >> d) + ((dividend >> (d - 1)) & 1);
+    quotient as u16
+}
+
+// An element of R_q = Z_q[X]/(X^256 + 1), either as a polynomial or in
+// the NTT domain, where it is the vector of its residues modulo the 128
+// degree-two factors of X^256 + 1.
+struct Poly {
+    f: [u16, ..N],
+}
+
+impl Clone for Poly {
+    fn clone(&self) -> Poly {
+        *self
+    }
+}
+
+impl Poly {
+    fn zero() -> Poly {
+        Poly { f: [0, ..N] }
+    }
+
+    fn add(&self, b: &Poly) -> Poly {
+        let mut r = Poly::zero();
+        for i in range(0, N) {
+            r.f[i] = field_add(self.f[i], b.f[i]);
+        }
+        r
+    }
+
+    fn sub(&self, b: &Poly) -> Poly {
+        let mut r = Poly::zero();
+        for i in range(0, N) {
+            r.f[i] = field_sub(self.f[i], b.f[i]);
+        }
+        r
+    }
+
+    // Returns the NTT representation of the polynomial, as specified in
+    // FIPS 203, Algorithm 9.
+    fn ntt(&self) -> Poly {
+        let mut f = *self;
+        let mut k = 1u;
+        let mut len = 128u;
+        while len >= 2 {
+            let mut start = 0u;
+            while start < N {
+                let zeta = Zetas[k];
+                k += 1;
+                for j in range(start, start + len) {
+                    let t = field_mul(zeta, f.f[j + len]);
+                    f.f[j + len] = field_sub(f.f[j], t);
+                    f.f[j] = field_add(f.f[j], t);
+                }
+                start += 2 * len;
+            }
+            len /= 2;
+        }
+        f
+    }
+
+    // Returns the polynomial with the given NTT representation, as
+    // specified in FIPS 203, Algorithm 10.
+    fn inverse_ntt(&self) -> Poly {
+        let mut f = *self;
+        let mut k = 127u;
+        let mut len = 2u;
+        while len <= 128 {
+            let mut start = 0u;
+            while start < N {
+                let zeta = Zetas[k];
+                k -= 1;
+                for j in range(start, start + len) {
+                    let t = f.f[j];
+                    f.f[j] = field_add(t, f.f[j + len]);
+                    f.f[j + len] = field_mul(zeta, field_sub(f.f[j + len], t));
+                }
+                start += 2 * len;
+            }
+            len *= 2;
+        }
+        for i in range(0, N) {
+            f.f[i] = field_mul(f.f[i], InverseNttScale);
+        }
+        f
+    }
+
+    // Returns the product of two elements in the NTT domain, as specified
+    // in FIPS 203, Algorithms 11 and 12.
+    fn ntt_mul(&self, g: &Poly) -> Poly {
+        let mut h = Poly::zero();
+        let mut i = 0u;
+        while i < N {
+            let (a0, a1) = (self.f[i], self.f[i + 1]);
+            let (b0, b1) = (g.f[i], g.f[i + 1]);
+            h.f[i] = field_add_mul(a0, b0, field_mul(a1, b1), Gammas[i / 2]);
+            h.f[i + 1] = field_add_mul(a0, b1, a1, b0);
+            i += 2;
+        }
+        h
+    }
+}
+
+// Appends the coefficients of f, each d bits long, to out, least
+// significant bits first, as ByteEncode_d of FIPS 203, Algorithm 5.
+fn encode(out: &mut Vec<u8>, f: &Poly, d: uint) {
+    let mut acc = 0u32;
+    let mut bits = 0u;
+    for &c in f.f.iter() {
+        acc |= c as u32 << bits;
+        bits += d;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+}
+
+// Decodes 32 * d bytes into 256 coefficients of d bits each, as
+// ByteDecode_d of FIPS 203, Algorithm 6, without the reduction modulo q.
+fn decode(b: &[u8], d: uint) -> Poly {
+    let mut f = Poly::zero();
+    let mut acc = 0u32;
+    let mut bits = 0u;
+    let mut j = 0u;
+    for i in range(0, N) {
+        while bits < d {
+            acc |= b[j] as u32 << bits;
+            j += 1;
+            bits += 8;
+        }
+        f.f[i] = (acc & ((1 << d) - 1)) as u16;
+        acc >>= d;
+        bits -= d;
+    }
+    f
+}
+
+// Decodes a 384-byte vector of 12-bit coefficients, or returns None if
+// any of them is not below q, as required by the modulus check of FIPS
+// 203, Section 7.2.
+fn decode12(b: &[u8]) -> Option<Poly> {
+    let f = decode(b, 12);
+    for &c in f.f.iter() {
+        if c >= Q {
+            return None;
+        }
+    }
+    Some(f)
+}
+
+fn compress_and_encode(out: &mut Vec<u8>, f: &Poly, d: uint) {
+    let mut c = Poly::zero();
+    for i in range(0, N) {
+        c.f[i] = compress(f.f[i], d);
+    }
+    encode(out, &c, d);
+}
+
+fn decode_and_decompress(b: &[u8], d: uint) -> Poly {
+    let mut f = decode(b, d);
+    for i in range(0, N) {
+        f.f[i] = decompress(f.f[i], d);
+    }
+    f
+}
+
+// Samples an element of the NTT domain from SHAKE128(rho || i || j), with
+// rejection sampling, as specified in FIPS 203, Algorithm 7.
+fn sample_ntt(rho: &[u8], i: u8, j: u8) -> Poly {
+    let mut xof = Shake::new128();
+    xof.write(rho).unwrap();
+    xof.write([i, j]).unwrap();
+    let mut f = Poly::zero();
+    let mut n = 0u;
+    let mut buf = [0u8, ..168];
+    loop {
+        xof.read(buf).unwrap();
+        let mut off = 0u;
+        while off < buf.len() {
+            let d1 = buf[off] as u16 | (buf[off + 1] as u16 & 0xf) << 8;
+            let d2 = buf[off + 1] as u16 >> 4 | buf[off + 2] as u16 << 4;
+            off += 3;
+            if d1 < Q {
+                f.f[n] = d1;
+                n += 1;
+            }
+            if n == N {
+                return f;
+            }
+            if d2 < Q {
+                f.f[n] = d2;
+                n += 1;
+            }
+            if n == N {
+                return f;
+            }
+        }
+    }
+}
+
+// Samples a polynomial from the centered binomial distribution with η = 2,
+// using PRF(s, b) = SHAKE256(s || b), as specified in FIPS 203,
+// Algorithms 8 and 2.
+fn sample_cbd(s: &[u8], b: u8) -> Poly {
+    let mut prf = Shake::new256();
+    prf.write(s).unwrap();
+    prf.write([b]).unwrap();
+    let mut buf = [0u8, ..128];
+    prf.read(buf).unwrap();
+    let mut f = Poly::zero();
+    for i in range(0, N / 2) {
+        let x = buf[i] as u16;
+        let bit = |n: uint| (x >> n) & 1;
+        f.f[2 * i] = field_sub(bit(0) + bit(1), bit(2) + bit(3));
+        f.f[2 * i + 1] = field_sub(bit(4) + bit(5), bit(6) + bit(7));
+    }
+    f
+}
+
+// Returns the matrix Â of FIPS 203, with Â[i][j] at index i * k + j.
+fn sample_matrix(rho: &[u8], k: uint) -> ~[Poly] {
+    let mut a = Vec::with_capacity(k * k);
+    for i in range(0, k) {
+        for j in range(0, k) {
+            a.push(sample_ntt(rho, j as u8, i as u8));
+        }
+    }
+    a.as_slice().to_owned()
+}
+
+// G of FIPS 203, SHA3-512, split in two 32-byte halves.
+fn g(parts: &[&[u8]]) -> (~[u8], ~[u8]) {
+    let mut h = Sha3::new(Sha3_512);
+    for p in parts.iter() {
+        h.write(*p).unwrap();
+    }
+    let d = h.digest();
+    (d.slice_to(32).to_owned(), d.slice_from(32).to_owned())
+}
+
+// H of FIPS 203, SHA3-256.
+fn h(b: &[u8]) -> ~[u8] {
+    let mut h = Sha3::new(Sha3_256);
+    h.write(b).unwrap();
+    h.digest()
+}
+
+// The parsed form of an encapsulation key, with the matrix Â expanded
+// from rho and the hash of the encoding, both needed by every
+// encapsulation.
+#[deriving(Clone)]
+struct EncapsulationKey {
+    params: &'static Params,
+    t: ~[Poly],
+    a: ~[Poly],
+    rho: ~[u8],
+    h: ~[u8],
+}
+
+#[deriving(Clone)]
+struct DecapsulationKey {
+    d: ~[u8],
+    z: ~[u8],
+    s: ~[Poly],
+    ek: EncapsulationKey,
+}
+
+impl EncapsulationKey {
+    fn parse(params: &'static Params, b: &[u8]) -> Result<EncapsulationKey, ~str> {
+        if b.len() != params.encapsulation_key_size() {
+            return Err(~"crypto/mlkem: invalid encapsulation key length");
+        }
+        let k = params.k;
+        let mut t = Vec::with_capacity(k);
+        for i in range(0, k) {
+            match decode12(b.slice(384 * i, 384 * (i + 1))) {
+                Some(f) => t.push(f),
+                None => return Err(~"crypto/mlkem: invalid encapsulation key encoding"),
+            }
+        }
+        let rho = b.slice_from(384 * k);
+        Ok(EncapsulationKey {
+            params: params,
+            t: t.as_slice().to_owned(),
+            a: sample_matrix(rho, k),
+            rho: rho.to_owned(),
+            h: h(b),
+        })
+    }
+
+    fn bytes(&self) -> ~[u8] {
+        let mut out = Vec::with_capacity(self.params.encapsulation_key_size());
+        for t in self.t.iter() {
+            encode(&mut out, t, 12);
+        }
+        out.push_all(self.rho.as_slice());
+        out.as_slice().to_owned()
+    }
+
+    // K-PKE.Encrypt of FIPS 203, Algorithm 14, with the 32-byte message m
+    // and randomness r.
+    fn encrypt(&self, m: &[u8], r: &[u8]) -> ~[u8] {
+        let Params { k, du, dv } = *self.params;
+        let mut n = 0u8;
+        let mut y = Vec::with_capacity(k);
+        for _ in range(0, k) {
+            y.push(sample_cbd(r, n).ntt());
+            n += 1;
+        }
+        let mut e1 = Vec::with_capacity(k);
+        for _ in range(0, k) {
+            e1.push(sample_cbd(r, n));
+            n += 1;
+        }
+        let e2 = sample_cbd(r, n);
+
+        let mut out = Vec::with_capacity(self.params.ciphertext_size());
+        // u = NTT⁻¹(Âᵀ ∘ ŷ) + e1
+        for i in range(0, k) {
+            let mut acc = Poly::zero();
+            for j in range(0, k) {
+                acc = acc.add(&self.a[j * k + i].ntt_mul(y.get(j)));
+            }
+            let u = acc.inverse_ntt().add(e1.get(i));
+            compress_and_encode(&mut out, &u, du);
+        }
+        // v = NTT⁻¹(t̂ᵀ ∘ ŷ) + e2 + μ
+        let mu = decode_and_decompress(m, 1);
+        let mut acc = Poly::zero();
+        for i in range(0, k) {
+            acc = acc.add(&self.t[i].ntt_mul(y.get(i)));
+        }
+        let v = acc.inverse_ntt().add(&e2).add(&mu);
+        compress_and_encode(&mut out, &v, dv);
+        out.as_slice().to_owned()
+    }
+
+    // ML-KEM.Encaps_internal of FIPS 203, Algorithm 17, returning the
+    // shared key and the ciphertext.
+    fn encapsulate(&self, m: &[u8]) -> (~[u8], ~[u8]) {
+        let (key, r) = g([m, self.h.as_slice()]);
+        let c = self.encrypt(m, r);
+        (key, c)
+    }
+}
+
+impl DecapsulationKey {
+    // ML-KEM.KeyGen_internal of FIPS 203, Algorithm 16, together with
+    // K-PKE.KeyGen, Algorithm 13.
+    fn new(params: &'static Params, seed: &[u8]) -> Result<DecapsulationKey, ~str> {
+        if seed.len() != SeedSize {
+            return Err(~"crypto/mlkem: invalid seed length");
+        }
+        let (d, z) = (seed.slice_to(32), seed.slice_from(32));
+        let k = params.k;
+        let kb = [k as u8];
+        let (rho, sigma) = g([d, kb.as_slice()]);
+        let a = sample_matrix(rho, k);
+
+        let mut n = 0u8;
+        let mut s = Vec::with_capacity(k);
+        for _ in range(0, k) {
+            s.push(sample_cbd(sigma, n).ntt());
+            n += 1;
+        }
+        // t̂ = Â ∘ ŝ + ê
+        let mut t = Vec::with_capacity(k);
+        for i in range(0, k) {
+            let mut acc = sample_cbd(sigma, n).ntt();
+            n += 1;
+            for j in range(0, k) {
+                acc = acc.add(&a[i * k + j].ntt_mul(s.get(j)));
+            }
+            t.push(acc);
+        }
+
+        let mut ek = EncapsulationKey {
+            params: params,
+            t: t.as_slice().to_owned(),
+            a: a,
+            rho: rho,
+            h: ~[],
+        };
+        ek.h = h(ek.bytes());
+        Ok(DecapsulationKey {
+            d: d.to_owned(),
+            z: z.to_owned(),
+            s: s.as_slice().to_owned(),
+            ek: ek,
+        })
+    }
+
+    fn bytes(&self) -> ~[u8] {
+        self.d + self.z
+    }
+
+    // K-PKE.Decrypt of FIPS 203, Algorithm 15.
+    fn decrypt(&self, c: &[u8]) -> ~[u8] {
+        let Params { k, du, dv } = *self.ek.params;
+        // w = v - NTT⁻¹(ŝᵀ ∘ NTT(u))
+        let mut acc = Poly::zero();
+        for i in range(0, k) {
+            let u = decode_and_decompress(c.slice(32 * du * i, 32 * du * (i + 1)), du);
+            acc = acc.add(&self.s[i].ntt_mul(&u.ntt()));
+        }
+        let v = decode_and_decompress(c.slice_from(32 * du * k), dv);
+        let w = v.sub(&acc.inverse_ntt());
+        let mut out = Vec::with_capacity(32);
+        compress_and_encode(&mut out, &w, 1);
+        out.as_slice().to_owned()
+    }
+
+    // ML-KEM.Decaps_internal of FIPS 203, Algorithm 18. The ciphertext
+    // must have the right length.
+    fn decapsulate(&self, c: &[u8]) -> ~[u8] {
+        let m = self.decrypt(c);
+        let (key, r) = g([m.as_slice(), self.ek.h.as_slice()]);
+
+        // The implicit rejection key, J(z || c).
+        let mut j = Shake::new256();
+        j.write(self.z.as_slice()).unwrap();
+        j.write(c).unwrap();
+        let mut out = [0u8, ..SharedKeySize];
+        j.read(out).unwrap();
+
+        let c1 = self.ek.encrypt(m, r);
+        constant_time_copy(constant_time_compare(c, c1) as int, out, key);
+        out.to_owned()
+    }
+}
+
+/// The private key of an ML-KEM-768 key pair. It can be used to
+/// decapsulate shared keys from ciphertexts made with the corresponding
+/// encapsulation key.
+#[deriving(Clone)]
+pub struct DecapsulationKey768 {
+    key: DecapsulationKey,
+}
+
+impl DecapsulationKey768 {
+    /// Derives a decapsulation key from a SeedSize-byte seed in the
+    /// "d || z" form. The seed must be uniformly random.
+    pub fn new(seed: &[u8]) -> Result<DecapsulationKey768, ~str> {
+        let key = try!(DecapsulationKey::new(&Params768, seed));
+        Ok(DecapsulationKey768 { key: key })
+    }
+
+    /// Returns the decapsulation key as a 64-byte seed in the "d || z"
+    /// form.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.bytes()
+    }
+
+    /// Returns the public encapsulation key necessary to produce
+    /// ciphertexts.
+    pub fn encapsulation_key(&self) -> EncapsulationKey768 {
+        EncapsulationKey768 { key: self.key.ek.clone() }
+    }
+
+    /// Produces a shared key from a ciphertext. An error is returned only
+    /// if the ciphertext has the wrong length; other invalid ciphertexts
+    /// produce an unrelated, pseudorandom shared key.
+    pub fn decapsulate(&self, ciphertext: &[u8]) -> Result<~[u8], ~str> {
+        if ciphertext.len() != CiphertextSize768 {
+            return Err(~"crypto/mlkem: invalid ciphertext length");
+        }
+        Ok(self.key.decapsulate(ciphertext))
+    }
+}
+
+impl super::PrivateKey for DecapsulationKey768 {}
+
+/// The public key of an ML-KEM-768 key pair. It can be used to
+/// encapsulate shared keys.
+#[deriving(Clone)]
+pub struct EncapsulationKey768 {
+    key: EncapsulationKey,
+}
+
+impl EncapsulationKey768 {
+    /// Parses an encapsulation key from its encoded form. An error is
+    /// returned if the encoding is not valid.
+    pub fn new(b: &[u8]) -> Result<EncapsulationKey768, ~str> {
+        let key = try!(EncapsulationKey::parse(&Params768, b));
+        Ok(EncapsulationKey768 { key: key })
+    }
+
+    /// Returns the encapsulation key as a byte slice.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.bytes()
+    }
+
+    /// Generates a shared key and an associated ciphertext, using
+    /// entropy from rand. The shared key must be kept secret.
+    pub fn encapsulate<R: Reader>(&self, rand: &mut R) -> IoResult<(~[u8], ~[u8])> {
+        let mut m = [0u8, ..32];
+        try!(rand.fill(m));
+        Ok(self.key.encapsulate(m))
+    }
+}
+
+impl super::PublicKey for EncapsulationKey768 {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// Generates a new ML-KEM-768 decapsulation key, using entropy from rand.
+/// The decapsulation key must be kept secret.
+pub fn generate_key768<R: Reader>(rand: &mut R) -> IoResult<DecapsulationKey768> {
+    let mut seed = [0u8, ..64];
+    try!(rand.fill(seed));
+    Ok(DecapsulationKey768::new(seed).unwrap())
+}
+
+/// The private key of an ML-KEM-1024 key pair. It can be used to
+/// decapsulate shared keys from ciphertexts made with the corresponding
+/// encapsulation key.
+#[deriving(Clone)]
+pub struct DecapsulationKey1024 {
+    key: DecapsulationKey,
+}
+
+impl DecapsulationKey1024 {
+    /// Derives a decapsulation key from a SeedSize-byte seed in the
+    /// "d || z" form. The seed must be uniformly random.
+    pub fn new(seed: &[u8]) -> Result<DecapsulationKey1024, ~str> {
+        let key = try!(DecapsulationKey::new(&Params1024, seed));
+        Ok(DecapsulationKey1024 { key: key })
+    }
+
+    /// Returns the decapsulation key as a 64-byte seed in the "d || z"
+    /// form.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.bytes()
+    }
+
+    /// Returns the public encapsulation key necessary to produce
+    /// ciphertexts.
+    pub fn encapsulation_key(&self) -> EncapsulationKey1024 {
+        EncapsulationKey1024 { key: self.key.ek.clone() }
+    }
+
+    /// Produces a shared key from a ciphertext. An error is returned only
+    /// if the ciphertext has the wrong length; other invalid ciphertexts
+    /// produce an unrelated, pseudorandom shared key.
+    pub fn decapsulate(&self, ciphertext: &[u8]) -> Result<~[u8], ~str> {
+        if ciphertext.len() != CiphertextSize1024 {
+            return Err(~"crypto/mlkem: invalid ciphertext length");
+        }
+        Ok(self.key.decapsulate(ciphertext))
+    }
+}
+
+impl super::PrivateKey for DecapsulationKey1024 {}
+
+/// The public key of an ML-KEM-1024 key pair. It can be used to
+/// encapsulate shared keys.
+#[deriving(Clone)]
+pub struct EncapsulationKey1024 {
+    key: EncapsulationKey,
+}
+
+impl EncapsulationKey1024 {
+    /// Parses an encapsulation key from its encoded form. An error is
+    /// returned if the encoding is not valid.
+    pub fn new(b: &[u8]) -> Result<EncapsulationKey1024, ~str> {
+        let key = try!(EncapsulationKey::parse(&Params1024, b));
+        Ok(EncapsulationKey1024 { key: key })
+    }
+
+    /// Returns the encapsulation key as a byte slice.
+    pub fn bytes(&self) -> ~[u8] {
+        self.key.bytes()
+    }
+
+    /// Generates a shared key and an associated ciphertext, using
+    /// entropy from rand. The shared key must be kept secret.
+    pub fn encapsulate<R: Reader>(&self, rand: &mut R) -> IoResult<(~[u8], ~[u8])> {
+        let mut m = [0u8, ..32];
+        try!(rand.fill(m));
+        Ok(self.key.encapsulate(m))
+    }
+}
+
+impl super::PublicKey for EncapsulationKey1024 {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// Generates a new ML-KEM-1024 decapsulation key, using entropy from
+/// rand. The decapsulation key must be kept secret.
+pub fn generate_key1024<R: Reader>(rand: &mut R) -> IoResult<DecapsulationKey1024> {
+    let mut seed = [0u8, ..64];
+    try!(rand.fill(seed));
+    Ok(DecapsulationKey1024::new(seed).unwrap())
+}
+
+
+#[cfg(test)]
+mod test {
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use std::slice;
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use super::*;
+    use super::{compress, decompress, encode, decode12, h, Params, Params768, Params1024,
+                DecapsulationKey, EncapsulationKey};
+    use super::super::rand::Reader;
+    use super::super::sha3::{Sha3, Sha3_256};
+
+    fn sha3_256(b: &[u8]) -> ~str {
+        let mut h = Sha3::new(Sha3_256);
+        h.write(b).unwrap();
+        h.hexdigest()
+    }
+
+    #[test]
+    fn test_compress_decompress() {
+        let q = 3329u32;
+        for &d in [1u, 4, 5, 10, 11].iter() {
+            for x in range(0, q) {
+                // round(2^d / q * x) mod 2^d, rounding halves up
+                let want = (((x << (d + 1)) + q) / (2 * q)) & ((1 << d) - 1);
+                assert_eq!(compress(x as u16, d) as u32, want);
+            }
+            for y in range(0, 1u32 << d) {
+                let want = (2 * y * q + (1 << d)) >> (d + 1);
+                assert_eq!(decompress(y as u16, d) as u32, want);
+            }
+        }
+    }
+
+    // The vectors below were computed with OpenSSL 3.5, from the seed
+    // 00 01 02 .. 3f and the encapsulation randomness m = 40 41 42 .. 5f.
+    // The implicit rejection key is for the ciphertext with the lowest
+    // bit of its last byte flipped.
+
+    fn seed() -> ~[u8] {
+        range(0u8, 64).collect()
+    }
+
+    fn message() -> ~[u8] {
+        range(64u8, 96).collect()
+    }
+
+    #[test]
+    fn test_vectors768() {
+        let dk = DecapsulationKey768::new(seed()).unwrap();
+        let ek = dk.encapsulation_key();
+        assert_eq!(ek.bytes().slice_to(16).to_hex(), ~"298aa10d423c8dda069d02bc59e6cdf0");
+        assert_eq!(sha3_256(ek.bytes()),
+                   ~"a24e16d8f8f9383a95b77050f4d9fd2f5733eec1d63ef3c23ebf9918173669a7");
+
+        let (key, mut c) = ek.key.encapsulate(message());
+        assert_eq!(c.len(), CiphertextSize768);
+        assert_eq!(c.slice_to(16).to_hex(), ~"695a60d9c79f08343ed9ff5802582063");
+        assert_eq!(sha3_256(c),
+                   ~"b4cfbd24cef67afd3764276c6980e0f88f8e9ca57f59b7f12fe1a9c1e72f4710");
+        assert_eq!(key.to_hex(),
+                   ~"9cddd089ffe70e3996e76f7c8d06746df34d07e8657bc0fcf2bb0e1c3084aea1");
+        assert_eq!(dk.decapsulate(c).unwrap(), key);
+
+        c[CiphertextSize768 - 1] ^= 1;
+        assert_eq!(dk.decapsulate(c).unwrap().to_hex(),
+                   ~"1f39ae51991196b33dbc7c6031f9f35fd3347d577ebb4dea93028bcd9ab5dabe");
+    }
+
+    #[test]
+    fn test_vectors1024() {
+        let dk = DecapsulationKey1024::new(seed()).unwrap();
+        let ek = dk.encapsulation_key();
+        assert_eq!(ek.bytes().slice_to(16).to_hex(), ~"4b94c29450111191823b3514c9ac1ea3");
+        assert_eq!(sha3_256(ek.bytes()),
+                   ~"61349e5c131a7e116a0463861d7d18663c5627c38c7147ddaadfd48acd7a4535");
+
+        let (key, mut c) = ek.key.encapsulate(message());
+        assert_eq!(c.len(), CiphertextSize1024);
+        assert_eq!(c.slice_to(16).to_hex(), ~"3ce00ba803c544b01150a120e4afe3c9");
+        assert_eq!(sha3_256(c),
+                   ~"c1579fa02c614f3762b2a799b51e41cebb8f820f34fa736af02c56de2460ce3c");
+        assert_eq!(key.to_hex(),
+                   ~"0ad8d1ea1b8dd788979b4379581218df9321bdce5567eca42ae6be7d395f1a54");
+        assert_eq!(dk.decapsulate(c).unwrap(), key);
+
+        c[CiphertextSize1024 - 1] ^= 1;
+        assert_eq!(dk.decapsulate(c).unwrap().to_hex(),
+                   ~"9d20ec8bd82507657af2e7573571c146ea7c0c9281182f016c4774944172285a");
+    }
+
+    // Vectors in the shape of the NIST ACVP ML-KEM tests: keyGen from d
+    // and z, encapsulation from m, decapsulation of valid and implicitly
+    // rejected ciphertexts with the expanded decapsulation key, and the
+    // encapsulation and decapsulation key checks. Large values are given
+    // by their SHA3-256. They were computed with a Python model of FIPS
+    // 203 and checked against OpenSSL 3.5, including its import of
+    // expanded keys.
+
+    struct AcvpVectors {
+        // d, z, SHA3-256(ek), SHA3-256(dk)
+        key_gen: &'static [(&'static str, &'static str, &'static str, &'static str)],
+        // encapsulation with the first key: m, SHA3-256(c), K
+        m: &'static str,
+        c: &'static str,
+        k: &'static str,
+        // the shared keys for c, c with its first 32 bytes zeroed, and
+        // the all-zero ciphertext
+        decap: &'static [(&'static str, &'static str)],
+    }
+
+    static Acvp768: AcvpVectors = AcvpVectors {
+        key_gen: &[
+            ("115869ee1c52c08d82ce0cae0bc2bc462774bb0ba18b8138dbe49a49925f6607",
+             "3fb923186cba6053c55b44a766f6f29d9e0da969b08be89d34a6c5e81090418a",
+             "8e715f4ecf5f70722c0a037bf947d5e6585a65f5e00beaedb8f57ff54c381fba",
+             "a14436dc005440f779a5fd95dba801e74d151621b87ab93a009601c894f996ec"),
+            ("988851227cf0385341956ff59c7db1b37fc36144f7ebc6b08c058ae9260233ec",
+             "15daf1f1d44d100db1b35fb1a90e6fda53d7127931c26d4cb77b28427057f71a",
+             "49b1f2f2f638de6b5dfb2a249510b016b0bb15cd406789bb47a00918649b46d6",
+             "046c8a0339c589414a59fed7f7c648222d4352683c78bb9f59233189483de107"),
+        ],
+        m: "1ab7687eda74a617f3ac9a12a5ed3b3e68568690df02ab2d5f5ce11a9dd9b91d",
+        c: "5a5504361bdb1a5c37925ce1ffe2146efdf7d75e72a1faa0de86c058686ddf03",
+        k: "02adea6b8980f7ba722808fecc9184ca310addab8b97795853ab5e175cc426db",
+        decap: &[
+            ("valid", "02adea6b8980f7ba722808fecc9184ca310addab8b97795853ab5e175cc426db"),
+            ("first 32 bytes zeroed",
+             "5b100e6657beb922217f82dc36f57cce819e2b6d82a28863a4005a505b6e0058"),
+            ("all zero", "8e92a9da6a73ad276f687029055a95003c351abf7ae874f85444b0a1ceb3e343"),
+        ],
+    };
+
+    static Acvp1024: AcvpVectors = AcvpVectors {
+        key_gen: &[
+            ("2c9743c198fc6d1441c7eb5cf6b5adacf984b82274aa1fc49076e8da9221bda9",
+             "609c0641821c57999050f96cf3f1eab9a68486db89baeb3c02390852137c7342",
+             "d6c2526ee88d74c0d84713e616667a10d7d826efbf763d52410464044a503726",
+             "433983b76c5150e2817bb1935fd8c743d1847f0f8afdd1cb64c86f0a1f033d36"),
+            ("fccfe6cf8f07f369b9b0d18442e21d7038d9db07e17a97f52742662b45ef9539",
+             "c22adb389aec4fff5bed360b1d6d0ab31f16ea16816df73382b047ef46307870",
+             "35494571a75df7e2f4659271f70519fdd0bab9948a0a178d30f3b203166e15cb",
+             "e71ccb1fd5ce89649203ab50fe3e1a6e97e6f498bda550700dc8aead9e73f96e"),
+        ],
+        m: "a4e74b50b9df034daca933d420a5d3b7c1543a1d0918baf7ea56189ef36ac5a1",
+        c: "30998c885718f058e32f7acb39d5d514e4a4db45a428b8b00ce02b3c6beac643",
+        k: "52dfcfb713d6539f5bbcbeac1f5fb3d22d23f0586d978dfd6ef01c72a7492eeb",
+        decap: &[
+            ("valid", "52dfcfb713d6539f5bbcbeac1f5fb3d22d23f0586d978dfd6ef01c72a7492eeb"),
+            ("first 32 bytes zeroed",
+             "05e71421f9b2a12a9044b3469059bd60f5c9b60a12f6e7d91b57e8f17bba97b1"),
+            ("all zero", "4ebb5b3126109a54fc5013b9b955af940524e38921bfdc5184688b55f80aecc6"),
+        ],
+    };
+
+    // The expanded decapsulation key of FIPS 203, ByteEncode12(ŝ) || ek ||
+    // H(ek) || z, which ACVP uses. The package, like Go's, only exposes
+    // the seed form.
+    fn expanded_bytes(dk: &DecapsulationKey) -> ~[u8] {
+        let mut out = Vec::new();
+        for s in dk.s.iter() {
+            encode(&mut out, s, 12);
+        }
+        out.push_all(dk.ek.bytes().as_slice());
+        out.push_all(dk.ek.h.as_slice());
+        out.push_all(dk.z.as_slice());
+        out.as_slice().to_owned()
+    }
+
+    // Parses an expanded decapsulation key with the checks of FIPS 203,
+    // Section 7.3, and the modulus check of ŝ.
+    fn parse_expanded(params: &'static Params, b: &[u8]) -> Result<DecapsulationKey, ~str> {
+        let k = params.k;
+        if b.len() != 768 * k + 96 {
+            return Err(~"crypto/mlkem: invalid decapsulation key length");
+        }
+        let mut s = Vec::with_capacity(k);
+        for i in range(0, k) {
+            match decode12(b.slice(384 * i, 384 * (i + 1))) {
+                Some(f) => s.push(f),
+                None => return Err(~"crypto/mlkem: invalid decapsulation key encoding"),
+            }
+        }
+        let ek = try!(EncapsulationKey::parse(params, b.slice(384 * k, 768 * k + 32)));
+        if ek.h.as_slice() != b.slice(768 * k + 32, 768 * k + 64) {
+            return Err(~"crypto/mlkem: invalid decapsulation key hash");
+        }
+        Ok(DecapsulationKey {
+            d: ~[],
+            z: b.slice_from(768 * k + 64).to_owned(),
+            s: s.as_slice().to_owned(),
+            ek: ek,
+        })
+    }
+
+    // Sets the last coefficient of the 12-bit encoded polynomial at off.
+    fn set_last_coefficient(b: &mut [u8], off: uint, x: u16) {
+        b[off + 382] = (b[off + 382] & 0x0f) | (x << 4) as u8;
+        b[off + 383] = (x >> 4) as u8;
+    }
+
+    fn check_acvp(params: &'static Params, v: &AcvpVectors) {
+        let k = params.k;
+        for &(d, z, ek, dk) in v.key_gen.iter() {
+            let seed = d.from_hex().unwrap() + z.from_hex().unwrap();
+            let key = DecapsulationKey::new(params, seed).unwrap();
+            assert_eq!(sha3_256(key.ek.bytes()), ek.to_owned());
+            assert_eq!(sha3_256(expanded_bytes(&key)), dk.to_owned());
+        }
+
+        let (d, z, _, _) = v.key_gen[0];
+        let seed = d.from_hex().unwrap() + z.from_hex().unwrap();
+        let dkb = expanded_bytes(&DecapsulationKey::new(params, seed).unwrap());
+        let dk = parse_expanded(params, dkb).unwrap();
+        let (key, c) = dk.ek.encapsulate(v.m.from_hex().unwrap());
+        assert_eq!(sha3_256(c), v.c.to_owned());
+        assert_eq!(key.to_hex(), v.k.to_owned());
+
+        let mut zeroed = c.clone();
+        for i in range(0u, 32) {
+            zeroed[i] = 0;
+        }
+        let cs = [c.clone(), zeroed, slice::from_elem(c.len(), 0u8)];
+        for (c, &(comment, want)) in cs.iter().zip(v.decap.iter()) {
+            assert!(dk.decapsulate(c.as_slice()).to_hex() == want.to_owned(), "{}", comment);
+        }
+
+        // decapsulation key check: length, H(ek) and the ek it covers
+        assert!(parse_expanded(params, dkb.slice_from(1)).is_err());
+        let mut bad = dkb.clone();
+        bad[768 * k + 32] ^= 1;
+        assert!(parse_expanded(params, bad).is_err());
+        let mut bad = dkb.clone();
+        bad[384 * k] ^= 1;
+        assert!(parse_expanded(params, bad).is_err());
+        let mut bad = dkb.clone();
+        set_last_coefficient(bad.as_mut_slice(), 0, 0xfff);
+        assert!(parse_expanded(params, bad).is_err());
+
+        // encapsulation key check: every coefficient of t̂ must be below q,
+        // also when the hash in the decapsulation key matches
+        let mut ek = dk.ek.bytes();
+        set_last_coefficient(ek.as_mut_slice(), 384 * (k - 1), 3328);
+        assert!(EncapsulationKey::parse(params, ek).is_ok());
+        set_last_coefficient(ek.as_mut_slice(), 384 * (k - 1), 3329);
+        assert!(EncapsulationKey::parse(params, ek).is_err());
+        let mut bad = dkb.slice_to(384 * k).to_owned();
+        bad.push_all(ek);
+        bad.push_all(h(ek));
+        bad.push_all(dkb.slice_from(768 * k + 64));
+        assert!(parse_expanded(params, bad).is_err());
+    }
+
+    #[test]
+    fn test_acvp768() {
+        check_acvp(&Params768, &Acvp768);
+    }
+
+    #[test]
+    fn test_acvp1024() {
+        check_acvp(&Params1024, &Acvp1024);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dk = generate_key768(&mut Reader).unwrap();
+        assert_eq!(dk.bytes().len(), SeedSize);
+        let ek = EncapsulationKey768::new(dk.encapsulation_key().bytes()).unwrap();
+        assert_eq!(ek.bytes().len(), EncapsulationKeySize768);
+        let (key, c) = ek.encapsulate(&mut Reader).unwrap();
+        assert_eq!(key.len(), SharedKeySize);
+        assert_eq!(dk.decapsulate(c).unwrap(), key);
+        let dk1 = DecapsulationKey768::new(dk.bytes()).unwrap();
+        assert_eq!(dk1.decapsulate(c).unwrap(), key);
+
+        let dk = generate_key1024(&mut Reader).unwrap();
+        let ek = EncapsulationKey1024::new(dk.encapsulation_key().bytes()).unwrap();
+        assert_eq!(ek.bytes().len(), EncapsulationKeySize1024);
+        let (key, c) = ek.encapsulate(&mut Reader).unwrap();
+        assert_eq!(dk.decapsulate(c).unwrap(), key);
+
+        let ek = dk.encapsulation_key();
+        let public = &ek as &PublicKeyTrait;
+        assert!(public.as_any().as_ref::<EncapsulationKey1024>().is_some());
+        assert!(public.as_any().as_ref::<EncapsulationKey768>().is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(DecapsulationKey768::new([0u8, ..63]).is_err());
+        assert!(DecapsulationKey1024::new([0u8, ..65]).is_err());
+
+        let dk = DecapsulationKey768::new(seed()).unwrap();
+        let ek = dk.encapsulation_key().bytes();
+        assert!(EncapsulationKey768::new(ek.slice_from(1)).is_err());
+        assert!(EncapsulationKey1024::new(ek).is_err());
+        // a coefficient of 0xfff is not below q
+        let mut bad = ek.clone();
+        bad[0] = 0xff;
+        bad[1] |= 0x0f;
+        assert!(EncapsulationKey768::new(bad).is_err());
+
+        let (_, c) = dk.encapsulation_key().encapsulate(&mut Reader).unwrap();
+        assert!(dk.decapsulate(c.slice_from(1)).is_err());
+        assert!(dk.decapsulate(c + bytes!("!")).is_err());
+    }
+}
@@ -1,7 +1,327 @@
+use std::any::Any;
+use std::fmt;
+use hash::Hash;
+
+/// A private key of an unspecified algorithm, like Go's
+/// crypto.PrivateKey. Private keys used for signing or decryption also
+/// implement Signer or Decrypter.
 pub trait PrivateKey {}
-pub trait PublicKey {}
 
+/// A public key of an unspecified algorithm, like Go's crypto.PublicKey.
+pub trait PublicKey {
+    /// Returns the key as Any, so that callers can recover the concrete
+    /// key type, as a Go type switch would.
+    fn as_any<'a>(&'a self) -> &'a Any;
+}
+
+/// An interface for an opaque private key that can be used for signing
+/// operations. For example, an RSA key kept in a hardware module or held
+/// by an agent process.
+pub trait Signer {
+    /// Returns the public key corresponding to the opaque, private key.
+    fn public(&self) -> ~PublicKey;
+
+    /// Signs digest with the private key, possibly using entropy from
+    /// rand. For an RSA key, the resulting signature should be either a
+    /// PKCS #1 v1.5 or PSS signature (as indicated by opts).
+    ///
+    /// Hash is set to the hash function used to produce digest, and
+    /// digest must be the result of hashing a larger message; if
+    /// opts.hash_func() is None, digest is the message itself, for
+    /// algorithms like Ed25519 that hash internally.
+    fn sign(&self, rand: &mut Reader, digest: &[u8], opts: &SignerOpts) -> Result<~[u8], ~str>;
+
+    /// Returns the signer as a MessageSigner, if it is one. sign_message
+    /// uses this in place of Go's interface upgrade.
+    fn as_message_signer<'a>(&'a self) -> Option<&'a MessageSigner> {
+        None
+    }
+}
+
+/// An interface for an opaque private key that can be used for signing
+/// operations where the message is not pre-hashed by the caller. It is a
+/// superset of the Signer interface so that it can be passed to APIs
+/// which accept Signer, which may try to do an interface upgrade.
+///
+/// sign_message signs msg with the private key, possibly using entropy
+/// from rand. opts.hash_func() returns the hash function to use, and
+/// implementations must do the hashing themselves.
+pub trait MessageSigner: Signer {
+    fn sign_message(&self, rand: &mut Reader, msg: &[u8], opts: &SignerOpts)
+                    -> Result<~[u8], ~str>;
+}
+
+/// Signs msg with signer. If signer is a MessageSigner, sign_message is
+/// called directly. Otherwise, msg is hashed with opts.hash_func() and
+/// signed with sign.
+pub fn sign_message(signer: &Signer, rand: &mut Reader, msg: &[u8], opts: &SignerOpts)
+                    -> Result<~[u8], ~str> {
+    match signer.as_message_signer() {
+        Some(ms) => return ms.sign_message(rand, msg, opts),
+        None => {}
+    }
+    match opts.hash_func() {
+        Some(h) => {
+            let mut d = h.new();
+            d.write(msg).unwrap();
+            signer.sign(rand, d.digest(), opts)
+        }
+        None => signer.sign(rand, msg, opts),
+    }
+}
+
+/// Contains options for signing with a Signer.
+pub trait SignerOpts {
+    /// Returns the hash function used to produce the message passed to
+    /// Signer::sign, or None to indicate that no hashing was done.
+    fn hash_func(&self) -> Option<HashFunc>;
+
+    /// Returns the options as Any, so that a signer can recover
+    /// algorithm-specific options such as rsa::PSSOptions.
+    fn as_any<'a>(&'a self) -> &'a Any;
+}
+
+/// An interface for an opaque private key that can be used for
+/// asymmetric decryption operations. An example would be an RSA key kept
+/// in a hardware module.
+pub trait Decrypter {
+    /// Returns the public key corresponding to the opaque, private key.
+    fn public(&self) -> ~PublicKey;
+
+    /// Decrypts msg. The opts argument should be appropriate for the
+    /// primitive used, or None for its default.
+    fn decrypt(&self, rand: &mut Reader, msg: &[u8], opts: Option<&DecrypterOpts>)
+               -> Result<~[u8], ~str>;
+}
+
+/// Options for Decrypter::decrypt, which are specific to each algorithm;
+/// see rsa::OAEPOptions and rsa::PKCS1v15DecryptOptions.
+pub trait DecrypterOpts {
+    /// Returns the options as Any, so that a decrypter can recover their
+    /// concrete type.
+    fn as_any<'a>(&'a self) -> &'a Any;
+}
+
+/// Identifies a hash function implemented in this crate. The values
+/// match Go's crypto.Hash.
+#[deriving(Eq, Clone)]
+pub enum HashFunc {
+    MD4 = 1,
+    MD5,
+    SHA1,
+    SHA224,
+    SHA256,
+    SHA384,
+    SHA512,
+    MD5SHA1,
+    RIPEMD160,
+    SHA3_224,
+    SHA3_256,
+    SHA3_384,
+    SHA3_512,
+    SHA512_224,
+    SHA512_256,
+    BLAKE2s_256,
+    BLAKE2b_256,
+    BLAKE2b_384,
+    BLAKE2b_512,
+}
+
+impl HashFunc {
+    /// Returns the length, in bytes, of a digest resulting from the given
+    /// hash function.
+    pub fn size(&self) -> uint {
+        match *self {
+            MD4 | MD5 => 16,
+            SHA1 | RIPEMD160 => 20,
+            SHA224 | SHA3_224 | SHA512_224 => 28,
+            SHA256 | SHA3_256 | SHA512_256 | BLAKE2s_256 | BLAKE2b_256 => 32,
+            SHA384 | SHA3_384 | BLAKE2b_384 => 48,
+            SHA512 | SHA3_512 | BLAKE2b_512 => 64,
+            MD5SHA1 => 36,
+        }
+    }
+
+    /// Reports whether the given hash function is linked into the binary.
+    pub fn available(&self) -> bool {
+        true
+    }
+
+    /// Returns a new hash calculating the given hash function.
+    pub fn new(&self) -> ~Hash {
+        match *self {
+            MD4 => ~md4::Md4::new() as ~Hash,
+            MD5 => ~md5::Md5::new() as ~Hash,
+            SHA1 => ~sha1::Sha1::new() as ~Hash,
+            SHA224 => ~sha2::Sha2::new(sha2::Sha_224) as ~Hash,
+            SHA256 => ~sha2::Sha2::new(sha2::Sha_256) as ~Hash,
+            SHA384 => ~sha2::Sha2::new(sha2::Sha_384) as ~Hash,
+            SHA512 => ~sha2::Sha2::new(sha2::Sha_512) as ~Hash,
+            MD5SHA1 => ~md5sha1::Md5Sha1::new() as ~Hash,
+            RIPEMD160 => ~ripemd160::Ripemd160::new() as ~Hash,
+            SHA3_224 => ~sha3::Sha3::new(sha3::Sha3_224) as ~Hash,
+            SHA3_256 => ~sha3::Sha3::new(sha3::Sha3_256) as ~Hash,
+            SHA3_384 => ~sha3::Sha3::new(sha3::Sha3_384) as ~Hash,
+            SHA3_512 => ~sha3::Sha3::new(sha3::Sha3_512) as ~Hash,
+            SHA512_224 => ~sha2::Sha2::new(sha2::Sha_512_224) as ~Hash,
+            SHA512_256 => ~sha2::Sha2::new(sha2::Sha_512_256) as ~Hash,
+            BLAKE2s_256 => ~blake2s::Blake2s::new256([]).unwrap() as ~Hash,
+            BLAKE2b_256 => ~blake2b::Blake2b::new256([]).unwrap() as ~Hash,
+            BLAKE2b_384 => ~blake2b::Blake2b::new384([]).unwrap() as ~Hash,
+            BLAKE2b_512 => ~blake2b::Blake2b::new512([]).unwrap() as ~Hash,
+        }
+    }
+}
 
+/// A HashFunc is itself the simplest SignerOpts.
+impl SignerOpts for HashFunc {
+    fn hash_func(&self) -> Option<HashFunc> {
+        Some(*self)
+    }
+
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+impl fmt::Show for HashFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            MD4 => "MD4",
+            MD5 => "MD5",
+            SHA1 => "SHA-1",
+            SHA224 => "SHA-224",
+            SHA256 => "SHA-256",
+            SHA384 => "SHA-384",
+            SHA512 => "SHA-512",
+            MD5SHA1 => "MD5+SHA1",
+            RIPEMD160 => "RIPEMD-160",
+            SHA3_224 => "SHA3-224",
+            SHA3_256 => "SHA3-256",
+            SHA3_384 => "SHA3-384",
+            SHA3_512 => "SHA3-512",
+            SHA512_224 => "SHA-512/224",
+            SHA512_256 => "SHA-512/256",
+            BLAKE2s_256 => "BLAKE2s-256",
+            BLAKE2b_256 => "BLAKE2b-256",
+            BLAKE2b_384 => "BLAKE2b-384",
+            BLAKE2b_512 => "BLAKE2b-512",
+        };
+        f.buf.write_str(name)
+    }
+}
+
+
+pub mod subtle;
+mod cpu;
+mod ghash;
+mod multibuf;
 pub mod sha1;
 pub mod sha2;
 pub mod md5;
+pub mod hmac;
+pub mod hkdf;
+pub mod pbkdf2;
+pub mod sha3;
+pub mod blake2b;
+pub mod blake2s;
+pub mod blake3;
+pub mod md4;
+pub mod ripemd160;
+pub mod md5sha1;
+pub mod cipher;
+pub mod aes;
+pub mod chacha20;
+pub mod poly1305;
+pub mod chacha20poly1305;
+pub mod siv;
+pub mod gcmsiv;
+pub mod des;
+pub mod rc4;
+pub mod cmac;
+pub mod keywrap;
+pub mod rand;
+pub mod rsa;
+pub mod elliptic;
+pub mod ecdsa;
+pub mod edwards25519;
+pub mod ed25519;
+pub mod ecdh;
+pub mod mlkem;
+pub mod mldsa;
+
+#[cfg(test)]
+mod test {
+    use std::any::Any;
+    use super::*;
+
+    // Signs by echoing its input, tagged with the method that was used.
+    struct EchoSigner {
+        message_signer: bool,
+    }
+
+    impl PublicKey for EchoSigner {
+        fn as_any<'a>(&'a self) -> &'a Any {
+            self as &Any
+        }
+    }
+
+    impl Signer for EchoSigner {
+        fn public(&self) -> ~PublicKey {
+            ~EchoSigner { message_signer: self.message_signer } as ~PublicKey
+        }
+
+        fn sign(&self, _: &mut Reader, digest: &[u8], _: &SignerOpts) -> Result<~[u8], ~str> {
+            Ok(~[0u8] + digest)
+        }
+
+        fn as_message_signer<'a>(&'a self) -> Option<&'a MessageSigner> {
+            if self.message_signer { Some(self as &MessageSigner) } else { None }
+        }
+    }
+
+    impl MessageSigner for EchoSigner {
+        fn sign_message(&self, _: &mut Reader, msg: &[u8], _: &SignerOpts)
+                        -> Result<~[u8], ~str> {
+            Ok(~[1u8] + msg)
+        }
+    }
+
+    #[test]
+    fn test_hash_func() {
+        let all = [MD4, MD5, SHA1, SHA224, SHA256, SHA384, SHA512, MD5SHA1, RIPEMD160,
+                   SHA3_224, SHA3_256, SHA3_384, SHA3_512, SHA512_224, SHA512_256,
+                   BLAKE2s_256, BLAKE2b_256, BLAKE2b_384, BLAKE2b_512];
+        for (i, h) in all.iter().enumerate() {
+            assert_eq!(*h as uint, i + 1);
+            assert!(h.available());
+            let mut d = h.new();
+            d.write_str("abc");
+            assert_eq!(d.size(), h.size());
+            assert_eq!(d.digest().len(), h.size());
+        }
+        let mut d = RIPEMD160.new();
+        d.write_str("abc");
+        assert_eq!(d.hexdigest(), ~"8eb208f7e05d987a9b044a8e98c6b087f15a0bfc");
+        assert_eq!(format!("{}", SHA512_256), ~"SHA-512/256");
+        assert_eq!(format!("{}", MD5SHA1), ~"MD5+SHA1");
+    }
+
+    #[test]
+    fn test_sign_message() {
+        let mut rand = super::rand::Reader;
+        let rand = &mut rand as &mut Reader;
+        let mut d = SHA256.new();
+        d.write_str("abc");
+        let hashed = d.digest();
+
+        let opts = &SHA256 as &SignerOpts;
+        let signer = EchoSigner { message_signer: false };
+        assert_eq!(sign_message(&signer as &Signer, rand, bytes!("abc"), opts).unwrap(),
+                   ~[0u8] + hashed);
+        let signer = EchoSigner { message_signer: true };
+        assert_eq!(sign_message(&signer as &Signer, rand, bytes!("abc"), opts).unwrap(),
+                   ~[1u8] + bytes!("abc"));
+        assert_eq!(SHA256.hash_func(), Some(SHA256));
+    }
+}
@@ -0,0 +1,148 @@
+// Multi-buffer hashing for the digest_many functions of md5, sha1 and
+// sha2: each SIMD lane works through a different message, so a compression
+// function with 4, 8 or 16 lanes hashes that many messages at once. A lane
+// that finishes its message picks up the next one, so long and short
+// messages can be mixed freely.
+//
+// The compression functions see the state and the message transposed: row
+// i holds word i of every lane, one lane after the other. The message
+// words are already converted from the hash's byte order, and the rows
+// after the first sixteen are scratch space for the message schedule.
+//
+// Only x86-64 has lane code. There is no NEON version of the 4-lane
+// compression functions yet, so elsewhere, AArch64 included, lanes() is 1
+// and digest_many hashes one message at a time. On AArch64 that still uses
+// the ARMv8 SHA instructions for sha1 and sha2 where the CPU has them; md5
+// runs in portable code.
+
+use std::slice;
+use super::cpu;
+
+/// Returns the widest number of lanes the CPU supports, or 1 if there is
+/// no multi-buffer code for it.
+#[cfg(target_arch = "x86_64")]
+pub fn lanes() -> uint {
+    if cpu::has_avx512f() {
+        16
+    } else if cpu::has_avx2() {
+        8
+    } else {
+        4
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn lanes() -> uint {
+    1
+}
+
+/// Reports whether compression functions with n lanes can run: 1 is the
+/// scalar code, 4 is SSE2, 8 is AVX2 and 16 is AVX-512.
+#[cfg(target_arch = "x86_64")]
+pub fn supported(n: uint) -> bool {
+    match n {
+        1 | 4 => true,
+        8 => cpu::has_avx2(),
+        16 => cpu::has_avx512f(),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn supported(n: uint) -> bool {
+    n == 1
+}
+
+// Writes the padded final one or two blocks of msg to tail.
+fn pad(msg: &[u8], tail: &mut [u8], big_endian: bool) {
+    let rem = msg.slice_from(msg.len() - msg.len() % 64);
+    for i in range(0, 128u) {
+        tail[i] = 0;
+    }
+    for i in range(0, rem.len()) {
+        tail[i] = rem[i];
+    }
+    tail[rem.len()] = 0x80;
+    let n = if rem.len() < 56 { 64 } else { 128 };
+    let len = (msg.len() as u64) << 3;
+    for i in range(0, 8u) {
+        let shift = if big_endian { 56 - 8 * i } else { 8 * i };
+        tail[n - 8 + i] = (len >> shift) as u8;
+    }
+}
+
+/// Hashes msgs with a compression function over the given number of lanes
+/// and returns the final state words of each message.
+///
+/// init is the initial state, big_endian the byte order of the message
+/// words and the length, and scratch the number of message rows compress
+/// is given (at least 16).
+pub fn hash_many(msgs: &[&[u8]], init: &[u32], big_endian: bool, lanes: uint, scratch: uint,
+                 compress: |&mut [u32], &mut [u32]|) -> ~[~[u32]] {
+    assert!(scratch >= 16);
+    let words = init.len();
+    let mut out = slice::from_elem(msgs.len(), ~[]);
+    let mut state = slice::from_elem(words * lanes, 0u32);
+    let mut w = slice::from_elem(scratch * lanes, 0u32);
+    // the message in each lane, its next block and its padded tail
+    let mut cur = slice::from_elem(lanes, None::<uint>);
+    let mut blk = slice::from_elem(lanes, 0u);
+    let mut tail = slice::from_elem(128 * lanes, 0u8);
+    let mut next = 0u;
+    loop {
+        let mut busy = false;
+        for l in range(0, lanes) {
+            if cur[l].is_none() && next < msgs.len() {
+                pad(msgs[next], tail.mut_slice(128 * l, 128 * l + 128), big_endian);
+                for i in range(0, words) {
+                    state[i * lanes + l] = init[i];
+                }
+                cur[l] = Some(next);
+                blk[l] = 0;
+                next += 1;
+            }
+            busy = busy || cur[l].is_some();
+        }
+        if !busy {
+            break;
+        }
+
+        // Idle lanes hash whatever was left in their rows.
+        for l in range(0, lanes) {
+            let msg = match cur[l] {
+                Some(m) => msgs[m],
+                None => continue,
+            };
+            let (b, full) = (blk[l], msg.len() / 64);
+            let p = if b < full {
+                msg.slice(64 * b, 64 * b + 64)
+            } else {
+                let off = 128 * l + 64 * (b - full);
+                tail.slice(off, off + 64)
+            };
+            for i in range(0, 16u) {
+                let q = p.slice(4 * i, 4 * i + 4);
+                w[i * lanes + l] = if big_endian {
+                    q[0] as u32 << 24 | q[1] as u32 << 16 | q[2] as u32 << 8 | q[3] as u32
+                } else {
+                    q[3] as u32 << 24 | q[2] as u32 << 16 | q[1] as u32 << 8 | q[0] as u32
+                };
+            }
+        }
+        compress(state.as_mut_slice(), w.as_mut_slice());
+
+        for l in range(0, lanes) {
+            let m = match cur[l] {
+                Some(m) => m,
+                None => continue,
+            };
+            let len = msgs[m].len();
+            blk[l] += 1;
+            if blk[l] == len / 64 + if len % 64 < 56 { 1 } else { 2 } {
+                out[m] = slice::from_fn(words, |i| state[i * lanes + l]);
+                cur[l] = None;
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,83 @@
+// Key derivation function PBKDF2 as defined in RFC 8018 (PKCS #5 v2.1).
+//
+// A key derivation function is useful when encrypting data based on a
+// password or any other not-fully-random data. It uses a pseudorandom
+// function to derive a secure encryption key based on the password.
+
+use super::hmac::Hmac;
+use super::super::hash::*;
+
+
+/// Derives a key from the password, salt and iteration count, returning a
+/// `key_len` byte long key suitable for use as an AES-256 key etc.
+///
+/// The underlying pseudorandom function is HMAC over `h`. Using a higher
+/// iteration count will increase the cost of an exhaustive search but will
+/// also make derivation proportionally slower.
+pub fn key<H: Hash + Clone>(password: &[u8], salt: &[u8], iter: uint,
+                            key_len: uint, h: H) -> ~[u8] {
+    assert!(iter > 0);
+    let mut prf = Hmac::new(h, password);
+    let hash_len = prf.size();
+    let num_blocks = (key_len + hash_len - 1) / hash_len;
+
+    let mut dk = Vec::with_capacity(num_blocks * hash_len);
+    for block in range(1u, num_blocks + 1) {
+        // N.B.: || means concatenation, ^ means XOR
+        // for each block T_i = U_1 ^ U_2 ^ ... ^ U_iter
+        // U_1 = PRF(password, salt || uint(i))
+        prf.reset();
+        prf.write(salt);
+        prf.write([(block >> 24) as u8, (block >> 16) as u8, (block >> 8) as u8, block as u8]);
+        let mut u = prf.digest();
+        let mut t = u.clone();
+
+        // U_n = PRF(password, U_(n-1))
+        for _ in range(1, iter) {
+            prf.reset();
+            prf.write(u.as_slice());
+            u = prf.digest();
+            for i in range(0, hash_len) {
+                t[i] ^= u[i];
+            }
+        }
+        dk.push_all(t.as_slice());
+    }
+    dk.as_slice().slice_to(key_len).to_owned()
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::md5::Md5;
+    use super::super::sha1::Sha1;
+    use super::super::sha2::*;
+
+    #[test]
+    fn test_pbkdf2_sha1() {
+        // RFC 6070
+        let dk = key(bytes!("password"), bytes!("salt"), 1, 20, Sha1::new());
+        assert_eq!(dk.to_hex(), ~"0c60c80f961f0e71f3a9b524af6012062fe037a6");
+        let dk = key(bytes!("password"), bytes!("salt"), 2, 20, Sha1::new());
+        assert_eq!(dk.to_hex(), ~"ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+        let dk = key(bytes!("password"), bytes!("salt"), 4096, 20, Sha1::new());
+        assert_eq!(dk.to_hex(), ~"4b007901b765489abead49d926f721d065a429c1");
+        let dk = key(bytes!("passwordPASSWORDpassword"), bytes!("saltSALTsaltSALTsaltSALTsaltSALTsalt"),
+                     4096, 25, Sha1::new());
+        assert_eq!(dk.to_hex(), ~"3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038");
+        let dk = key(bytes!("pass", 0, "word"), bytes!("sa", 0, "lt"), 4096, 16, Sha1::new());
+        assert_eq!(dk.to_hex(), ~"56fa6aa75548099dcc37d7f03425e0c3");
+    }
+
+    #[test]
+    fn test_pbkdf2_other_hashes() {
+        let dk = key(bytes!("password"), bytes!("salt"), 4096, 32, Sha2::new(Sha_256));
+        assert_eq!(dk.to_hex(), ~"c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a");
+        let dk = key(bytes!("password"), bytes!("salt"), 1, 64, Sha2::new(Sha_512));
+        assert_eq!(dk.to_hex(), ~"867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce");
+        let dk = key(bytes!("password"), bytes!("salt"), 4096, 20, Md5::new());
+        assert_eq!(dk.to_hex(), ~"15001f89b9c29ee6998c520d1a0629e893cc3f99");
+    }
+}
@@ -0,0 +1,276 @@
+// The Poly1305 one-time authenticator, RFC 8439 section 2.5, after
+// golang.org/x/crypto/poly1305.
+//
+// Poly1305 is a fast, one-time authentication function. It is infeasible
+// for an attacker to generate an authenticator for a message without the
+// key. However, a key must only be used for a single message.
+// Authenticating two different messages with the same key allows an
+// attacker to forge authenticators for other messages with the same key.
+//
+// The arithmetic uses five 26-bit limbs, as in poly1305-donna-32, so that
+// every product fits in a u64 and nothing branches on secret data.
+
+use std::cmp::min;
+use std::io::IoResult;
+use super::subtle::constant_time_compare;
+
+/// The size of a Poly1305 authenticator, in bytes.
+pub static TagSize: uint = 16;
+
+/// The size of a Poly1305 key, in bytes.
+pub static KeySize: uint = 32;
+
+#[inline]
+fn le32(b: &[u8]) -> u32 {
+    b[0] as u32 | b[1] as u32 << 8 | b[2] as u32 << 16 | b[3] as u32 << 24
+}
+
+/// A Mac computes the Poly1305 authenticator of the data written to it.
+/// A Mac must not be used with more than one key.
+#[deriving(Clone)]
+pub struct Mac {
+    r: [u32, ..5],
+    s: [u32, ..4],
+    h: [u32, ..5],
+    buf: [u8, ..16],
+    buf_len: uint,
+}
+
+impl Mac {
+    /// Creates a new Mac computing the authenticator with the given key.
+    pub fn new(key: &[u8, ..32]) -> Mac {
+        let k = key.as_slice();
+        // clamp r as required by the spec
+        let r = [
+            le32(k.slice_from(0)) & 0x3ffffff,
+            (le32(k.slice_from(3)) >> 2) & 0x3ffff03,
+            (le32(k.slice_from(6)) >> 4) & 0x3ffc0ff,
+            (le32(k.slice_from(9)) >> 6) & 0x3f03fff,
+            (le32(k.slice_from(12)) >> 8) & 0x00fffff,
+        ];
+        let s = [le32(k.slice_from(16)), le32(k.slice_from(20)),
+                 le32(k.slice_from(24)), le32(k.slice_from(28))];
+        Mac { r: r, s: s, h: [0u32, ..5], buf: [0u8, ..16], buf_len: 0 }
+    }
+
+    // Adds one 16-byte block to the accumulator and multiplies by r. hibit
+    // is 2^128 in limb form for full blocks and zero for the padded final
+    // block, which carries its own 0x01 byte.
+    fn block(&mut self, m: &[u8], hibit: u32) {
+        let (r0, r1, r2, r3, r4) = (self.r[0], self.r[1], self.r[2], self.r[3], self.r[4]);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h0 = self.h[0] + (le32(m.slice_from(0)) & 0x3ffffff);
+        let h1 = self.h[1] + ((le32(m.slice_from(3)) >> 2) & 0x3ffffff);
+        let h2 = self.h[2] + ((le32(m.slice_from(6)) >> 4) & 0x3ffffff);
+        let h3 = self.h[3] + ((le32(m.slice_from(9)) >> 6) & 0x3ffffff);
+        let h4 = self.h[4] + ((le32(m.slice_from(12)) >> 8) | hibit);
+
+        let (h0, h1, h2, h3, h4) = (h0 as u64, h1 as u64, h2 as u64, h3 as u64, h4 as u64);
+        let (r0, r1, r2, r3, r4) = (r0 as u64, r1 as u64, r2 as u64, r3 as u64, r4 as u64);
+        let (s1, s2, s3, s4) = (s1 as u64, s2 as u64, s3 as u64, s4 as u64);
+
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        // partial reduction mod 2^130 - 5
+        let mut c = d0 >> 26;
+        let mut h0 = (d0 & 0x3ffffff) as u32;
+        d1 += c;
+        c = d1 >> 26;
+        let h1 = (d1 & 0x3ffffff) as u32;
+        d2 += c;
+        c = d2 >> 26;
+        let h2 = (d2 & 0x3ffffff) as u32;
+        d3 += c;
+        c = d3 >> 26;
+        let h3 = (d3 & 0x3ffffff) as u32;
+        d4 += c;
+        c = d4 >> 26;
+        let h4 = (d4 & 0x3ffffff) as u32;
+        h0 += c as u32 * 5;
+        let c = h0 >> 26;
+        h0 &= 0x3ffffff;
+        self.h = [h0, h1 + c, h2, h3, h4];
+    }
+
+    /// Returns the authenticator of the data written so far. It does not
+    /// change the state, so more data may be written afterwards.
+    pub fn sum(&self) -> [u8, ..16] {
+        let mut m = self.clone();
+        if m.buf_len > 0 {
+            let mut last = [0u8, ..16];
+            last.mut_slice_to(m.buf_len).copy_from(m.buf.slice_to(m.buf_len));
+            last[m.buf_len] = 1;
+            m.block(last.as_slice(), 0);
+        }
+        let (mut h0, mut h1, mut h2, mut h3, mut h4) = (m.h[0], m.h[1], m.h[2], m.h[3], m.h[4]);
+
+        // fully carry h
+        let mut c = h1 >> 26;
+        h1 &= 0x3ffffff;
+        h2 += c;
+        c = h2 >> 26;
+        h2 &= 0x3ffffff;
+        h3 += c;
+        c = h3 >> 26;
+        h3 &= 0x3ffffff;
+        h4 += c;
+        c = h4 >> 26;
+        h4 &= 0x3ffffff;
+        h0 += c * 5;
+        c = h0 >> 26;
+        h0 &= 0x3ffffff;
+        h1 += c;
+
+        // compute h + -p
+        let mut g0 = h0 + 5;
+        c = g0 >> 26;
+        g0 &= 0x3ffffff;
+        let mut g1 = h1 + c;
+        c = g1 >> 26;
+        g1 &= 0x3ffffff;
+        let mut g2 = h2 + c;
+        c = g2 >> 26;
+        g2 &= 0x3ffffff;
+        let mut g3 = h3 + c;
+        c = g3 >> 26;
+        g3 &= 0x3ffffff;
+        let g4 = h4 + c - (1 << 26);
+
+        // select h if h < p, or h + -p if h >= p
+        let mask = (g4 >> 31) - 1;
+        let nmask = !mask;
+        h0 = (h0 & nmask) | (g0 & mask);
+        h1 = (h1 & nmask) | (g1 & mask);
+        h2 = (h2 & nmask) | (g2 & mask);
+        h3 = (h3 & nmask) | (g3 & mask);
+        h4 = (h4 & nmask) | (g4 & mask);
+
+        // h = h % 2^128
+        let h0 = h0 | (h1 << 26);
+        let h1 = (h1 >> 6) | (h2 << 20);
+        let h2 = (h2 >> 12) | (h3 << 14);
+        let h3 = (h3 >> 18) | (h4 << 8);
+
+        // tag = (h + s) % 2^128
+        let words = [h0, h1, h2, h3];
+        let mut out = [0u8, ..16];
+        let mut f = 0u64;
+        for i in range(0, 4u) {
+            f = words[i] as u64 + m.s[i] as u64 + (f >> 32);
+            for j in range(0, 4u) {
+                out[4 * i + j] = (f >> (8 * j)) as u8;
+            }
+        }
+        out
+    }
+
+    /// Reports whether expected is the authenticator of the data written
+    /// so far, in constant time.
+    pub fn verify(&self, expected: &[u8]) -> bool {
+        let tag = self.sum();
+        constant_time_compare(tag.as_slice(), expected)
+    }
+}
+
+impl Writer for Mac {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let mut p = buf;
+        if self.buf_len > 0 {
+            let n = min(16 - self.buf_len, p.len());
+            self.buf.mut_slice(self.buf_len, self.buf_len + n).copy_from(p.slice_to(n));
+            self.buf_len += n;
+            p = p.slice_from(n);
+            if self.buf_len < 16 {
+                return Ok(());
+            }
+            let b = self.buf;
+            self.block(b.as_slice(), 1 << 24);
+            self.buf_len = 0;
+        }
+        while p.len() >= 16 {
+            self.block(p.slice_to(16), 1 << 24);
+            p = p.slice_from(16);
+        }
+        if p.len() > 0 {
+            self.buf.mut_slice_to(p.len()).copy_from(p);
+            self.buf_len = p.len();
+        }
+        Ok(())
+    }
+}
+
+/// Returns the authenticator of msg using a one-time key.
+pub fn sum(msg: &[u8], key: &[u8, ..32]) -> [u8, ..16] {
+    let mut m = Mac::new(key);
+    m.write(msg).unwrap();
+    m.sum()
+}
+
+/// Returns true if mac is a valid authenticator for msg with the given key.
+pub fn verify(mac: &[u8], msg: &[u8], key: &[u8, ..32]) -> bool {
+    let mut m = Mac::new(key);
+    m.write(msg).unwrap();
+    m.verify(mac)
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+
+    fn key(s: &str) -> [u8, ..32] {
+        let mut k = [0u8, ..32];
+        k.as_mut_slice().copy_from(s.from_hex().unwrap().as_slice());
+        k
+    }
+
+    #[test]
+    fn test_rfc8439() {
+        // RFC 8439 section 2.5.2
+        let k = key("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let msg = bytes!("Cryptographic Forum Research Group");
+        let tag = sum(msg, &k);
+        assert_eq!(tag.as_slice().to_hex(), ~"a8061dc1305136c6c22b8baf0c0127a9");
+        assert!(verify(tag.as_slice(), msg, &k));
+        assert!(!verify(tag.slice_to(15), msg, &k));
+        assert!(!verify(tag.as_slice(), msg.slice_to(33), &k));
+    }
+
+    #[test]
+    fn test_lengths() {
+        let k = key("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let msg: ~[u8] = range(0, 100u).map(|i| (i * 7) as u8).collect();
+        let tests = [
+            (0u, "101112131415161718191a1b1c1d1e1f"),
+            (1, "1f11131517191b1d1f21232527292b2d"),
+            (15, "db2f72e4c451c969711eaf617425b37b"),
+            (16, "9db5bbf195e6218651c2168d63d82996"),
+            (17, "112484156f2f6d27b63a103cdea12d94"),
+            (100, "6c17082f1eb4a98538a182daf142d627"),
+        ];
+        for &(n, expected) in tests.iter() {
+            assert_eq!(sum(msg.slice_to(n), &k).as_slice().to_hex(), expected.to_owned());
+
+            // the same tag when written in pieces
+            let mut m = Mac::new(&k);
+            for chunk in msg.slice_to(n).chunks(7) {
+                m.write(chunk).unwrap();
+            }
+            assert_eq!(m.sum().as_slice().to_hex(), expected.to_owned());
+        }
+    }
+
+    #[test]
+    fn test_final_reduction() {
+        // h ends up above 2^130 - 5 and must be reduced before adding s
+        let k = [0xffu8, ..32];
+        let msg = [0xffu8, ..64];
+        assert_eq!(sum(msg, &k).as_slice().to_hex(), ~"900fe32bc15fa8d7bca8efe4c7e37eb1");
+    }
+}
@@ -0,0 +1,391 @@
+// Deterministic random bit generators from NIST SP 800-90A Rev. 1:
+// HMAC_DRBG, and CTR_DRBG with AES-256 and no derivation function.
+//
+// A generator created with new is seeded from the operating system and
+// reseeds itself from it when required. One created with new_seeded is
+// entirely determined by its inputs, which makes it suitable for known
+// answer tests and for deriving reproducible streams; it refuses to
+// produce output once a reseed is due until reseed is called.
+
+use std::io;
+use std::io::IoResult;
+use std::slice;
+use super::read;
+use super::super::aes::Aes;
+use super::super::cipher::Block;
+use super::super::hmac::Hmac;
+use super::super::super::hash::Hash;
+
+/// The number of generate requests after which a reseed is required.
+pub static ReseedInterval: u64 = 1 << 48;
+
+/// The largest number of bytes a single generate request may return.
+pub static MaxBytesPerRequest: uint = 1 << 16;
+
+/// The seed length of CTR_DRBG with AES-256, in bytes: a key and a block.
+pub static SeedSize: uint = 48;
+
+fn reseed_required() -> ~str {
+    ~"drbg: reseed required"
+}
+
+fn check_request(n: uint) -> Result<(), ~str> {
+    if n > MaxBytesPerRequest {
+        return Err(format!("drbg: request of {} bytes exceeds the maximum of {}",
+                           n, MaxBytesPerRequest));
+    }
+    Ok(())
+}
+
+fn to_io_error(e: ~str) -> io::IoError {
+    io::IoError { kind: io::OtherIoError, desc: "drbg: generate failed", detail: Some(e) }
+}
+
+/// HMAC_DRBG (SP 800-90A section 10.1.2) over the hash function H.
+pub struct HmacDrbg<H> {
+    h: H,
+    k: ~[u8],
+    v: ~[u8],
+    reseed_counter: u64,
+    from_os: bool,
+}
+
+impl<H: Hash + Clone> HmacDrbg<H> {
+    /// Returns an HMAC_DRBG instantiated with the given entropy input,
+    /// nonce and personalization string. The output depends on nothing
+    /// else.
+    pub fn new_seeded(h: H, entropy: &[u8], nonce: &[u8],
+                      personalization: &[u8]) -> HmacDrbg<H> {
+        let n = h.size();
+        let mut d = HmacDrbg {
+            h: h,
+            k: slice::from_elem(n, 0u8),
+            v: slice::from_elem(n, 1u8),
+            reseed_counter: 1,
+            from_os: false,
+        };
+        d.update([entropy, nonce, personalization]);
+        d
+    }
+
+    /// Returns an HMAC_DRBG seeded from the operating system, with an
+    /// entropy input of the hash's output size and a nonce of half that.
+    pub fn new(h: H, personalization: &[u8]) -> HmacDrbg<H> {
+        let n = h.size();
+        let mut seed = slice::from_elem(n + n / 2, 0u8);
+        read(seed.as_mut_slice());
+        let mut d = HmacDrbg::new_seeded(h, seed.slice_to(n), seed.slice_from(n),
+                                         personalization);
+        d.from_os = true;
+        d
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> ~[u8] {
+        let mut m = Hmac::new(self.h.clone(), self.k.as_slice());
+        for p in parts.iter() {
+            m.write(*p).unwrap();
+        }
+        m.digest()
+    }
+
+    // HMAC_DRBG_Update, with the provided data given as the concatenation
+    // of parts.
+    fn update(&mut self, parts: &[&[u8]]) {
+        let provided = parts.iter().any(|p| p.len() > 0);
+        for &sep in [0u8, 1].iter() {
+            if sep == 1 && !provided {
+                break;
+            }
+            let sep = [sep];
+            let k = {
+                let mut input = Vec::with_capacity(parts.len() + 2);
+                input.push(self.v.as_slice());
+                input.push(sep.as_slice());
+                input.push_all(parts);
+                self.mac(input.as_slice())
+            };
+            self.k = k;
+            self.v = self.mac([self.v.as_slice()]);
+        }
+    }
+
+    /// Mixes fresh entropy input and optional additional input into the
+    /// state and resets the reseed counter.
+    pub fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        self.update([entropy, additional]);
+        self.reseed_counter = 1;
+    }
+
+    /// Fills out with pseudorandom bytes, mixing in the optional
+    /// additional input. At most MaxBytesPerRequest bytes may be requested
+    /// at once.
+    pub fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ~str> {
+        try!(check_request(out.len()));
+        if self.reseed_counter > ReseedInterval {
+            if !self.from_os {
+                return Err(reseed_required());
+            }
+            let mut entropy = slice::from_elem(self.h.size(), 0u8);
+            read(entropy.as_mut_slice());
+            self.reseed(entropy.as_slice(), []);
+        }
+        if additional.len() > 0 {
+            self.update([additional]);
+        }
+        let mut n = 0u;
+        while n < out.len() {
+            self.v = self.mac([self.v.as_slice()]);
+            let m = ::std::cmp::min(self.v.len(), out.len() - n);
+            out.mut_slice(n, n + m).copy_from(self.v.slice_to(m));
+            n += m;
+        }
+        self.update([additional]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl<H: Hash + Clone> io::Reader for HmacDrbg<H> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        for chunk in buf.mut_chunks(MaxBytesPerRequest) {
+            try!(self.generate(chunk, []).map_err(to_io_error));
+        }
+        Ok(buf.len())
+    }
+}
+
+/// CTR_DRBG (SP 800-90A section 10.2.1) with AES-256 and no derivation
+/// function, so the entropy input is used directly as the seed and must
+/// be full entropy.
+pub struct CtrDrbg {
+    c: Aes,
+    v: [u8, ..16],
+    reseed_counter: u64,
+    from_os: bool,
+}
+
+impl CtrDrbg {
+    /// Returns a CTR_DRBG instantiated with the given entropy input and a
+    /// personalization string of at most SeedSize bytes. The output
+    /// depends on nothing else.
+    pub fn new_seeded(entropy: &[u8, ..48], personalization: &[u8]) -> Result<CtrDrbg, ~str> {
+        if personalization.len() > SeedSize {
+            return Err(~"drbg: personalization string too long");
+        }
+        let mut d = CtrDrbg {
+            c: Aes::new([0u8, ..32]).unwrap(),
+            v: [0u8, ..16],
+            reseed_counter: 1,
+            from_os: false,
+        };
+        let mut seed = *entropy;
+        xor(seed.as_mut_slice(), personalization);
+        d.update(&seed);
+        Ok(d)
+    }
+
+    /// Returns a CTR_DRBG seeded from the operating system.
+    pub fn new(personalization: &[u8]) -> Result<CtrDrbg, ~str> {
+        let mut entropy = [0u8, ..48];
+        read(entropy.as_mut_slice());
+        let mut d = try!(CtrDrbg::new_seeded(&entropy, personalization));
+        d.from_os = true;
+        Ok(d)
+    }
+
+    // Increments V as a 128-bit big-endian counter.
+    fn increment(&mut self) {
+        for i in range(0, 16u).rev() {
+            self.v[i] += 1;
+            if self.v[i] != 0 {
+                break;
+            }
+        }
+    }
+
+    // CTR_DRBG_Update.
+    fn update(&mut self, provided: &[u8, ..48]) {
+        let mut temp = [0u8, ..48];
+        for block in temp.mut_chunks(16) {
+            self.increment();
+            self.c.encrypt(block, self.v.as_slice());
+        }
+        xor(temp.as_mut_slice(), provided.as_slice());
+        self.c = Aes::new(temp.slice_to(32)).unwrap();
+        self.v.as_mut_slice().copy_from(temp.slice_from(32));
+    }
+
+    /// Mixes fresh entropy input and additional input of at most SeedSize
+    /// bytes into the state and resets the reseed counter.
+    pub fn reseed(&mut self, entropy: &[u8, ..48], additional: &[u8]) -> Result<(), ~str> {
+        if additional.len() > SeedSize {
+            return Err(~"drbg: additional input too long");
+        }
+        let mut seed = *entropy;
+        xor(seed.as_mut_slice(), additional);
+        self.update(&seed);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fills out with pseudorandom bytes, mixing in the optional
+    /// additional input of at most SeedSize bytes. At most
+    /// MaxBytesPerRequest bytes may be requested at once.
+    pub fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), ~str> {
+        try!(check_request(out.len()));
+        if additional.len() > SeedSize {
+            return Err(~"drbg: additional input too long");
+        }
+        if self.reseed_counter > ReseedInterval {
+            if !self.from_os {
+                return Err(reseed_required());
+            }
+            let mut entropy = [0u8, ..48];
+            read(entropy.as_mut_slice());
+            try!(self.reseed(&entropy, []));
+        }
+        let mut add = [0u8, ..48];
+        add.mut_slice_to(additional.len()).copy_from(additional);
+        if additional.len() > 0 {
+            self.update(&add);
+        }
+        let mut block = [0u8, ..16];
+        for chunk in out.mut_chunks(16) {
+            self.increment();
+            self.c.encrypt(block.as_mut_slice(), self.v.as_slice());
+            let m = chunk.len();
+            chunk.copy_from(block.slice_to(m));
+        }
+        self.update(&add);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl io::Reader for CtrDrbg {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        for chunk in buf.mut_chunks(MaxBytesPerRequest) {
+            try!(self.generate(chunk, []).map_err(to_io_error));
+        }
+        Ok(buf.len())
+    }
+}
+
+fn xor(dst: &mut [u8], src: &[u8]) {
+    for i in range(0, src.len()) {
+        dst[i] ^= src[i];
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::super::sha2::*;
+
+    fn seq(from: u8, to: u8) -> ~[u8] {
+        range(from, to).collect()
+    }
+
+    #[test]
+    fn test_hmac_drbg_cavp() {
+        // CAVP HMAC_DRBG SHA-256, no reseed, no personalization or
+        // additional input, COUNT = 0
+        let entropy = "ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488".from_hex().unwrap();
+        let nonce = "659ba96c601dc69fc902940805ec0ca8".from_hex().unwrap();
+        let mut d = HmacDrbg::new_seeded(Sha2::new(Sha_256), entropy.as_slice(), nonce.as_slice(), []);
+        let mut out = [0u8, ..128];
+        d.generate(out.as_mut_slice(), []).unwrap();
+        d.generate(out.as_mut_slice(), []).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89\
+                     d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc1\
+                     07694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668\
+                     961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8");
+    }
+
+    #[test]
+    fn test_hmac_drbg() {
+        let e1 = seq(0, 32);
+        let nonce = seq(32, 48);
+        let mut d = HmacDrbg::new_seeded(Sha2::new(Sha_256), e1.as_slice(), nonce.as_slice(),
+                                         bytes!("personal"));
+        let mut out = [0u8, ..40];
+        d.generate(out.as_mut_slice(), []).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"6ba707545a0fa0a2fddb2e19742bfa234d5fb6991fd35651980fcb4ce23a59ef942e7042ba0a68df");
+        d.generate(out.as_mut_slice(), bytes!("extra")).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"5cd42b8598bd05fa758dfd4ad188221678189a8e88190d3afa65892885e3a062a9feef645a115bcb");
+        d.reseed(seq(48, 80).as_slice(), bytes!("reseed"));
+        let mut out = [0u8, ..32];
+        d.generate(out.as_mut_slice(), []).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"ca2816d34c868709d772044333ed123265a90289faf392e85e1468009f64add6");
+    }
+
+    #[test]
+    fn test_ctr_drbg() {
+        let mut e1 = [0u8, ..48];
+        e1.as_mut_slice().copy_from(seq(0, 48).as_slice());
+        let mut d = CtrDrbg::new_seeded(&e1, bytes!("personal")).unwrap();
+        let mut out = [0u8, ..100];
+        d.generate(out.as_mut_slice(), []).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"e6ce17ef7b916a77bfc679af21332803c8950a98bf66d74c7a27d212fb86e564\
+                     4e67b5cc3826d89372fcad798f08b82a0ab32d213e338b0b3d024a7c4da51467\
+                     084d859741b1934e60b9974f9dffe561b2de367cce0af00f6969f051c267f6ae\
+                     cc873ffb");
+        let mut out = [0u8, ..64];
+        d.generate(out.as_mut_slice(), bytes!("extra")).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"bb82af6e70a4f8141294e89f01ce9ad3578bfeb526b49bd9c5bec42d9ad1f25b\
+                     346e179f3f103296a85982c0d8acce6a7fa9742cb2695468b7645a3831894cd5");
+        let mut e2 = [0u8, ..48];
+        e2.as_mut_slice().copy_from(seq(48, 96).as_slice());
+        d.reseed(&e2, bytes!("reseed")).unwrap();
+        let mut out = [0u8, ..32];
+        d.generate(out.as_mut_slice(), []).unwrap();
+        assert_eq!(out.as_slice().to_hex(),
+                   ~"8be7b55d3cd86f589cb92a5fed62486782743c15be3e1027c2e6a4ee768b97f3");
+
+        assert!(CtrDrbg::new_seeded(&e1, [0u8, ..49]).is_err());
+        assert!(d.generate(out.as_mut_slice(), [0u8, ..49]).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut d = HmacDrbg::new_seeded(Sha2::new(Sha_256), [1u8, ..32], [], []);
+        let mut big = Vec::from_elem(MaxBytesPerRequest + 1, 0u8);
+        assert!(d.generate(big.as_mut_slice(), []).is_err());
+        // the Reader interface splits large reads into several requests
+        assert_eq!(d.read(big.as_mut_slice()).unwrap(), MaxBytesPerRequest + 1);
+
+        d.reseed_counter = ReseedInterval + 1;
+        let mut out = [0u8, ..16];
+        assert_eq!(d.generate(out.as_mut_slice(), []).err(), Some(~"drbg: reseed required"));
+        assert!(d.read(out.as_mut_slice()).is_err());
+        d.reseed([2u8, ..32], []);
+        assert!(d.generate(out.as_mut_slice(), []).is_ok());
+
+        let mut c = CtrDrbg::new([]).unwrap();
+        c.reseed_counter = ReseedInterval + 1;
+        assert!(c.generate(out.as_mut_slice(), []).is_ok());
+        assert_eq!(c.reseed_counter, 2);
+    }
+
+    #[test]
+    fn test_os_seeded() {
+        let mut a = HmacDrbg::new(Sha2::new(Sha_256), bytes!("app"));
+        let mut b = HmacDrbg::new(Sha2::new(Sha_256), bytes!("app"));
+        let x = a.read_exact(32).unwrap();
+        let y = b.read_exact(32).unwrap();
+        assert!(x != y);
+
+        let mut c = CtrDrbg::new([]).unwrap();
+        let mut z = [0u8, ..32];
+        c.read(z.as_mut_slice()).unwrap();
+        assert!(z.as_slice() != x.as_slice());
+    }
+}
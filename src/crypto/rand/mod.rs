@@ -0,0 +1,285 @@
+// A cryptographically secure random number generator, after Go's
+// crypto/rand.
+//
+// On Linux x86, x86-64, ARM and AArch64, Reader uses getrandom(2) and
+// falls back to /dev/urandom on kernels without the system call.
+// Elsewhere it reads /dev/urandom.
+//
+// The drbg module has the NIST SP 800-90A deterministic generators, which
+// can be seeded from Reader or, for reproducible tests, with fixed input.
+
+use std::io;
+use std::io::{File, IoResult};
+use IoReader = std::io::Reader;
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+#[cfg(target_os = "linux", target_arch = "x86")]
+#[cfg(target_os = "linux", target_arch = "arm")]
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+use std::os;
+use math::big::Int;
+
+pub mod drbg;
+
+/// A global, shared instance of a cryptographically secure random number
+/// generator. It is stateless; any number of them may be used at once.
+pub struct Reader;
+
+// getrandom(2) is only called on the architectures whose system call
+// number is listed here; any other Linux target reads /dev/urandom.
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+static SYS_getrandom: ::std::libc::c_long = 318;
+#[cfg(target_os = "linux", target_arch = "x86")]
+static SYS_getrandom: ::std::libc::c_long = 355;
+#[cfg(target_os = "linux", target_arch = "arm")]
+static SYS_getrandom: ::std::libc::c_long = 384;
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+static SYS_getrandom: ::std::libc::c_long = 278;
+
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+#[cfg(target_os = "linux", target_arch = "x86")]
+#[cfg(target_os = "linux", target_arch = "arm")]
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+extern {
+    fn syscall(number: ::std::libc::c_long, ...) -> ::std::libc::c_long;
+}
+
+// The generic errno values; the architectures above all use them.
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+#[cfg(target_os = "linux", target_arch = "x86")]
+#[cfg(target_os = "linux", target_arch = "arm")]
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+static EINTR: int = 4;
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+#[cfg(target_os = "linux", target_arch = "x86")]
+#[cfg(target_os = "linux", target_arch = "arm")]
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+static ENOSYS: int = 38;
+
+// Fills b with getrandom(2). Returns false, having read nothing, if the
+// kernel does not have the system call.
+#[cfg(target_os = "linux", target_arch = "x86_64")]
+#[cfg(target_os = "linux", target_arch = "x86")]
+#[cfg(target_os = "linux", target_arch = "arm")]
+#[cfg(target_os = "linux", target_arch = "aarch64")]
+fn getrandom(b: &mut [u8]) -> IoResult<bool> {
+    let mut n = 0u;
+    while n < b.len() {
+        let r = unsafe {
+            syscall(SYS_getrandom, b.as_mut_ptr().offset(n as int),
+                    (b.len() - n) as ::std::libc::size_t, 0 as ::std::libc::c_uint)
+        };
+        if r < 0 {
+            match os::errno() {
+                EINTR => continue,
+                ENOSYS if n == 0 => return Ok(false),
+                _ => return Err(io::IoError::last_error()),
+            }
+        }
+        n += r as uint;
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+#[cfg(target_os = "linux", not(target_arch = "x86_64"), not(target_arch = "x86"),
+      not(target_arch = "arm"), not(target_arch = "aarch64"))]
+fn getrandom(_b: &mut [u8]) -> IoResult<bool> {
+    Ok(false)
+}
+
+fn urandom(b: &mut [u8]) -> IoResult<()> {
+    let mut f = try!(File::open(&Path::new("/dev/urandom")));
+    let mut n = 0u;
+    while n < b.len() {
+        n += try!(f.read(b.mut_slice_from(n)));
+    }
+    Ok(())
+}
+
+impl IoReader for Reader {
+    /// Fills buf entirely with random bytes.
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        if !try!(getrandom(buf)) {
+            try!(urandom(buf));
+        }
+        Ok(buf.len())
+    }
+}
+
+/// Fills b with cryptographically secure random bytes. It never returns
+/// an error: if the operating system cannot supply randomness, there is
+/// no safe way to continue and the task fails.
+pub fn read(b: &mut [u8]) {
+    match Reader.read(b) {
+        Ok(_) => {}
+        Err(e) => fail!("crypto/rand: failed to read random data: {}", e),
+    }
+}
+
+static base32alphabet: &'static [u8] = bytes!("ABCDEFGHIJKLMNOPQRSTUVWXYZ234567");
+
+/// Returns a cryptographically random string using the standard RFC 4648
+/// base32 alphabet, for use when a secret string, token, password, or
+/// other text is needed. The result contains at least 128 bits of
+/// randomness, enough to prevent brute force guessing attacks and to make
+/// the likelihood of collisions vanishingly small.
+pub fn text() -> ~str {
+    // 32 is a power of two, so the modulo is unbiased: 26 characters of 5
+    // bits each are 130 bits.
+    let mut src = [0u8, ..26];
+    read(src.as_mut_slice());
+    let mut s = ~"";
+    for &b in src.iter() {
+        s.push_char(base32alphabet[(b % 32) as uint] as char);
+    }
+    s
+}
+
+/// Returns a uniform random value in [0, max). Fails if max <= 0.
+pub fn int<R: IoReader>(rand: &mut R, max: &Int) -> IoResult<Int> {
+    if max.sign() <= 0 {
+        fail!("crypto/rand: argument to int is <= 0");
+    }
+    let n = *max - Int::new(1);
+    // bit_len is the maximum bit length needed to encode a value < max
+    let bit_len = n.bit_len();
+    if bit_len == 0 {
+        return Ok(n);
+    }
+    let k = (bit_len + 7) / 8;
+    let b = match bit_len % 8 {
+        0 => 8,
+        b => b,
+    };
+    loop {
+        let mut bytes = try!(rand.read_exact(k));
+        // Clear bits in the first byte to increase the probability that
+        // the candidate is < max.
+        bytes[0] &= ((1u << b) - 1) as u8;
+        let n = Int::from_bytes(bytes);
+        if n < *max {
+            return Ok(n);
+        }
+    }
+}
+
+/// Returns a number of the given bit length that is prime with high
+/// probability. It fails with InvalidInput if bits < 2.
+pub fn prime<R: IoReader>(rand: &mut R, bits: uint) -> IoResult<Int> {
+    if bits < 2 {
+        return Err(io::IoError {
+            kind: io::InvalidInput,
+            desc: "crypto/rand: prime size must be at least 2-bit",
+            detail: None,
+        });
+    }
+    let b = match bits % 8 {
+        0 => 8,
+        b => b,
+    };
+    loop {
+        let mut bytes = try!(rand.read_exact((bits + 7) / 8));
+        // Clear bits in the first byte to make sure the candidate has a
+        // size <= bits.
+        bytes[0] &= ((1u << b) - 1) as u8;
+        // Don't let the value be too small, i.e. set the most significant
+        // two bits. Setting the top two bits, rather than just the top
+        // bit, means that when two of these values are multiplied
+        // together, the result isn't ever one bit short.
+        if b >= 2 {
+            bytes[0] |= 3 << (b - 2);
+        } else {
+            // Here b == 1, because b cannot be zero.
+            bytes[0] |= 1;
+            if bytes.len() > 1 {
+                bytes[1] |= 0x80;
+            }
+        }
+        // Make the value odd since an even number this large certainly
+        // isn't prime.
+        let last = bytes.len() - 1;
+        bytes[last] |= 1;
+        let p = Int::from_bytes(bytes);
+        if p.probably_prime(20) {
+            return Ok(p);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use IoReader = std::io::Reader;
+    use math::big::Int;
+    use super::*;
+    use super::drbg::CtrDrbg;
+
+    #[test]
+    fn test_read() {
+        let mut a = [0u8, ..64];
+        let mut b = [0u8, ..64];
+        read(a.as_mut_slice());
+        read(b.as_mut_slice());
+        assert!(a.as_slice() != b.as_slice());
+        assert!(a.iter().any(|&x| x != 0));
+
+        let mut r = Reader;
+        assert_eq!(r.read([]).unwrap(), 0);
+        // larger than one getrandom call returns for a single request
+        let mut big = Vec::from_elem(1 << 20, 0u8);
+        assert_eq!(r.read(big.as_mut_slice()).unwrap(), 1 << 20);
+        assert!(big.as_slice().slice_from(1000000).iter().any(|&x| x != 0));
+    }
+
+    #[test]
+    fn test_urandom() {
+        let mut a = [0u8, ..32];
+        super::urandom(a.as_mut_slice()).unwrap();
+        assert!(a.iter().any(|&x| x != 0));
+    }
+
+    #[test]
+    fn test_text() {
+        let a = text();
+        let b = text();
+        assert_eq!(a.len(), 26);
+        assert!(a != b);
+        for c in a.chars() {
+            assert!((c >= 'A' && c <= 'Z') || (c >= '2' && c <= '7'));
+        }
+    }
+
+    #[test]
+    fn test_int() {
+        let max = Int::from_str_radix("0x1000000000000000000000001", 0).unwrap();
+        for _ in range(0, 100u) {
+            let n = int(&mut Reader, &max).unwrap();
+            assert!(n.sign() >= 0 && n < max);
+        }
+        assert_eq!(int(&mut Reader, &Int::new(1)).unwrap(), Int::new(0));
+        // small ranges are covered
+        let mut seen = [false, ..3];
+        for _ in range(0, 200u) {
+            seen[int(&mut Reader, &Int::new(3)).unwrap().to_u64() as uint] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn test_prime() {
+        for &bits in [2u, 3, 8, 9, 64, 130, 512].iter() {
+            let p = prime(&mut Reader, bits).unwrap();
+            assert_eq!(p.bit_len(), bits);
+            assert!(p.probably_prime(20));
+        }
+        assert!(prime(&mut Reader, 1).is_err());
+
+        // a seeded generator gives the same prime every time
+        let mut a = CtrDrbg::new_seeded(&[7u8, ..48], []).unwrap();
+        let mut b = CtrDrbg::new_seeded(&[7u8, ..48], []).unwrap();
+        assert_eq!(prime(&mut a, 256).unwrap(), prime(&mut b, 256).unwrap());
+    }
+}
@@ -0,0 +1,134 @@
+// RC4 encryption, as defined in Bruce Schneier's Applied Cryptography,
+// after Go's crypto/rc4.
+//
+// RC4 is cryptographically broken and should not be used for secure
+// applications. Its key stream is measurably biased, especially in the
+// first bytes. It is here only to read data produced by legacy systems.
+
+use super::cipher::Stream;
+
+/// An instance of RC4 using a particular key.
+pub struct Cipher {
+    s: [u8, ..256],
+    i: u8,
+    j: u8,
+}
+
+impl Cipher {
+    /// Creates and returns a new Cipher. The key argument should be the
+    /// RC4 key, at least 1 byte and at most 256 bytes.
+    ///
+    /// RC4 is broken; use it only to interoperate with legacy systems.
+    #[deprecated = "RC4 is insecure; use it only for legacy interoperability"]
+    pub fn new(key: &[u8]) -> Result<Cipher, ~str> {
+        let k = key.len();
+        if k < 1 || k > 256 {
+            return Err(format!("crypto/rc4: invalid key size {}", k));
+        }
+        let mut s = [0u8, ..256];
+        for i in range(0, 256u) {
+            s[i] = i as u8;
+        }
+        let mut j = 0u8;
+        for i in range(0, 256u) {
+            j += s[i] + key[i % k];
+            s.swap(i, j as uint);
+        }
+        Ok(Cipher { s: s, i: 0, j: 0 })
+    }
+
+    /// Zeros the key data so that it will no longer appear in the
+    /// process's memory.
+    pub fn reset(&mut self) {
+        for i in range(0, 256u) {
+            self.s[i] = 0;
+        }
+        self.i = 0;
+        self.j = 0;
+    }
+}
+
+impl Stream for Cipher {
+    fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
+        if dst.len() < src.len() {
+            fail!("crypto/rc4: output smaller than input");
+        }
+        let (mut i, mut j) = (self.i, self.j);
+        for k in range(0, src.len()) {
+            i += 1;
+            j += self.s[i as uint];
+            self.s.swap(i as uint, j as uint);
+            let x = self.s[i as uint] + self.s[j as uint];
+            dst[k] = src[k] ^ self.s[x as uint];
+        }
+        self.i = i;
+        self.j = j;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::cipher::Stream;
+
+    // RFC 6229, selected offsets of the key streams for a 40-bit and a
+    // 256-bit key
+    static tests: &'static [(&'static str, &'static [(uint, &'static str)])] = &[
+        ("0102030405", &[
+            (0, "b2396305f03dc027ccc3524a0a1118a8"),
+            (16, "6982944f18fc82d589c403a47a0d0919"),
+            (240, "28cb1132c96ce286421dcaadb8b69eae"),
+            (256, "1cfcf62b03eddb641d77dfcf7f8d8c93"),
+            (1520, "3294f744d8f9790507e70f62e5bbceea"),
+            (1536, "d8729db41882259bee4f825325f5a130"),
+            (4080, "068326a2118416d21f9d04b2cd1ca050"),
+            (4096, "ff25b58995996707e51fbdf08b34d875"),
+        ]),
+        ("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20", &[
+            (0, "eaa6bd25880bf93d3f5d1e4ca2611d91"),
+            (16, "cfa45c9f7e714b54bdfa80027cb14380"),
+            (240, "114ae344ded71b35f2e60febad727fd8"),
+            (256, "02e1e7056b0f623900496422943e97b6"),
+            (1520, "40f250b26d1f096a4afd4c340a588815"),
+            (1536, "3e34135c79db010200767651cf263073"),
+            (4080, "a13a7c79c7e119b5ab0296ab28c300b9"),
+            (4096, "f3e4c0a2e02d1d01f7f0a74618af2b48"),
+        ]),
+    ];
+
+    #[test]
+    fn test_rfc6229() {
+        for &(key, offsets) in tests.iter() {
+            let mut c = Cipher::new(key.from_hex().unwrap().as_slice()).unwrap();
+            let mut ks = [0u8, ..4112];
+            // uneven writes must continue the same stream
+            c.xor_key_stream(ks.mut_slice_to(1000), [0u8, ..1000]);
+            c.xor_key_stream(ks.mut_slice_from(1000), [0u8, ..3112]);
+            for &(off, expected) in offsets.iter() {
+                assert_eq!(ks.slice(off, off + 16).to_hex(), expected.to_owned());
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut c = Cipher::new(bytes!("Key")).unwrap();
+        let mut ct = [0u8, ..9];
+        c.xor_key_stream(ct.as_mut_slice(), bytes!("Plaintext"));
+        assert_eq!(ct.as_slice().to_hex(), ~"bbf316e8d940af0ad3");
+
+        let mut c = Cipher::new(bytes!("Key")).unwrap();
+        let mut pt = [0u8, ..9];
+        c.xor_key_stream(pt.as_mut_slice(), ct.as_slice());
+        assert_eq!(pt.as_slice(), bytes!("Plaintext"));
+    }
+
+    #[test]
+    fn test_key_size() {
+        assert_eq!(Cipher::new([]).err(), Some(~"crypto/rc4: invalid key size 0"));
+        assert!(Cipher::new([0u8, ..257]).is_err());
+        assert!(Cipher::new([0u8, ..256]).is_ok());
+    }
+}
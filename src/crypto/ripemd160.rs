@@ -0,0 +1,203 @@
+// RIPEMD-160 as defined by Dobbertin, Bosselaers and Preneel.
+//
+// Kept for formats that need it (Bitcoin addresses, OpenPGP); new
+// designs should use SHA-256 or SHA-3.
+
+use std::io::IoResult;
+use std::slice;
+use super::*;
+use super::super::hash::*;
+
+pub static Size: uint = 20;
+pub static BlockSize: uint = 64;
+
+static CHUNK: uint = 64;
+static init0: u32 = 0x67452301;
+static init1: u32 = 0xEFCDAB89;
+static init2: u32 = 0x98BADCFE;
+static init3: u32 = 0x10325476;
+static init4: u32 = 0xC3D2E1F0;
+
+// word selection and rotation amounts for the left and right lines
+static r1: [uint, ..80] = [ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+                            7,  4, 13,  1, 10,  6, 15,  3, 12,  0,  9,  5,  2, 14, 11,  8,
+                            3, 10, 14,  4,  9, 15,  8,  1,  2,  7,  0,  6, 13, 11,  5, 12,
+                            1,  9, 11, 10,  0,  8, 12,  4, 13,  3,  7, 15, 14,  5,  6,  2,
+                            4,  0,  5,  9,  7, 12,  2, 10, 14,  1,  3,  8, 11,  6, 15, 13];
+static r2: [uint, ..80] = [ 5, 14,  7,  0,  9,  2, 11,  4, 13,  6, 15,  8,  1, 10,  3, 12,
+                            6, 11,  3,  7,  0, 13,  5, 10, 14, 15,  8, 12,  4,  9,  1,  2,
+                           15,  5,  1,  3,  7, 14,  6,  9, 11,  8, 12,  2, 10,  0,  4, 13,
+                            8,  6,  4,  1,  3, 11, 15,  0,  5, 12,  2, 13,  9,  7, 10, 14,
+                           12, 15, 10,  4,  1,  5,  8,  7,  6,  2, 13, 14,  0,  3,  9, 11];
+static s1: [uint, ..80] = [11, 14, 15, 12,  5,  8,  7,  9, 11, 13, 14, 15,  6,  7,  9,  8,
+                            7,  6,  8, 13, 11,  9,  7, 15,  7, 12, 15,  9, 11,  7, 13, 12,
+                           11, 13,  6,  7, 14,  9, 13, 15, 14,  8, 13,  6,  5, 12,  7,  5,
+                           11, 12, 14, 15, 14, 15,  9,  8,  9, 14,  5,  6,  8,  6,  5, 12,
+                            9, 15,  5, 11,  6,  8, 13, 12,  5, 12, 13, 14, 11,  8,  5,  6];
+static s2: [uint, ..80] = [ 8,  9,  9, 11, 13, 15, 15,  5,  7,  7,  8, 11, 14, 14, 12,  6,
+                            9, 13, 15,  7, 12,  8,  9, 11,  7,  7, 12,  7,  6, 15, 13, 11,
+                            9,  7, 15, 11,  8,  6,  6, 14, 12, 13,  5, 14, 13, 13,  7,  5,
+                           15,  5,  8, 11, 14, 14,  6, 14,  6,  9, 12,  9, 12,  5, 15,  8,
+                            8,  5, 12,  9, 12,  5, 14,  6,  8, 13,  6,  5, 15, 13, 11, 11];
+
+static k1: [u32, ..5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+static k2: [u32, ..5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+#[inline]
+fn rotl32(x: u32, n: uint) -> u32 {
+    (x << n) | (x >> (32 - n))
+}
+
+// the five boolean functions, indexed by step; the right line uses them
+// in reverse order
+#[inline]
+fn f(j: uint, x: u32, y: u32, z: u32) -> u32 {
+    match j / 16 {
+        0 => x ^ y ^ z,
+        1 => (x & y) | ((! x) & z),
+        2 => (x | (! y)) ^ z,
+        3 => (x & z) | (y & (! z)),
+        4 => x ^ (y | (! z)),
+        _ => unreachable!(),
+    }
+}
+
+#[deriving(Eq, Show)]
+pub struct Ripemd160 {
+    h: [u32, ..5],
+    x: Vec<u8>,
+    len: uint,
+}
+
+// deep clone
+impl Clone for Ripemd160 {
+    fn clone(&self) -> Ripemd160 {
+        Ripemd160 {
+            h: self.h,
+            x: self.x.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl Ripemd160 {
+    pub fn new() -> Ripemd160 {
+        let mut ret = Ripemd160 { h: [0u32, ..5],
+                                  x: Vec::new(),
+                                  len: 0 };
+        ret.reset();
+        ret
+    }
+
+    fn block(&mut self, buf: &[u8]) {
+        assert!(buf.len() % CHUNK == 0);
+        let mut x = [0u32, ..16];
+        for p in buf.chunks(CHUNK) {
+            for i in range(0, 16u) {
+                let j = i * 4;
+                x[i] = p[j] as u32 | p[j+1] as u32 << 8 | p[j+2] as u32 << 16 | p[j+3] as u32 << 24;
+            }
+
+            let h = self.h;
+            let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+            let (mut aa, mut bb, mut cc, mut dd, mut ee) = (h[0], h[1], h[2], h[3], h[4]);
+            for j in range(0, 80u) {
+                let t = rotl32(a + f(j, b, c, d) + x[r1[j]] + k1[j / 16], s1[j]) + e;
+                a = e; e = d; d = rotl32(c, 10); c = b; b = t;
+
+                let t = rotl32(aa + f(79 - j, bb, cc, dd) + x[r2[j]] + k2[j / 16], s2[j]) + ee;
+                aa = ee; ee = dd; dd = rotl32(cc, 10); cc = bb; bb = t;
+            }
+            let t = h[1] + c + dd;
+            self.h[1] = h[2] + d + ee;
+            self.h[2] = h[3] + e + aa;
+            self.h[3] = h[4] + a + bb;
+            self.h[4] = h[0] + b + cc;
+            self.h[0] = t;
+        }
+    }
+
+    fn checksum(&mut self) -> ~[u8] {
+        let mut tmp = slice::from_elem(64, 0u8);
+        tmp[0] = 0x80;
+        let len = self.len;
+        if len % 64 < 56 {
+            self.write(tmp.slice_to(56 - len % 64));
+        } else {
+            self.write(tmp.slice_to(64 + 56 - len % 64));
+        }
+        let len = (len << 3) as u64;
+        for i in range(0, 8u) {
+            tmp[i] = (len >> 8*i) as u8;
+        }
+        self.write(tmp.slice_to(8));
+        assert_eq!(self.x.len(), 0);
+
+        let mut ret = slice::with_capacity(Size);
+        for &h in self.h.iter() {
+            for j in range(0, 4u) {
+                ret.push((h >> 8*j) as u8);
+            }
+        }
+        ret
+    }
+}
+
+impl Writer for Ripemd160 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.len += buf.len();  // total len
+        self.x.push_all(buf);
+        let mut next_x = Vec::new();
+        let buf = self.x.clone();
+        for p in buf.as_slice().chunks(CHUNK) {
+            if p.len() < CHUNK {
+                next_x.push_all(p);
+            } else {
+                self.block(p);
+            }
+        }
+        self.x = next_x;
+        Ok(())
+    }
+}
+
+impl Hash for Ripemd160 {
+    fn reset(&mut self) {
+        self.h = [init0, init1, init2, init3, init4];
+        self.x = Vec::new();
+        self.len = 0;
+    }
+    fn digest(&self) -> ~[u8] {
+        let mut h = self.clone();
+        h.checksum()
+    }
+
+    fn size(&self) -> uint {
+        Size
+    }
+
+    fn block_size(&self) -> uint {
+        BlockSize
+    }
+}
+
+#[test]
+fn test_ripemd160() {
+    let mut h = Ripemd160::new();
+    assert_eq!(h.hexdigest(), ~"9c1185a5c5e9fc54612808977ee8f548b2258d31");
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"8eb208f7e05d987a9b044a8e98c6b087f15a0bfc");
+    let mut h = Ripemd160::new();
+    h.write_str("message digest");
+    assert_eq!(h.hexdigest(), ~"5d0689ef49d2fae572b881b123a85ffa21595f36");
+}
+
+#[test]
+fn test_ripemd160_long() {
+    let mut h = Ripemd160::new();
+    h.write_str("welcome to china".repeat(10001));
+    assert_eq!(h.hexdigest(), ~"cb486c6e2dfacc6baa5ede3aa29f5b577b8fcc29");
+    let mut h = Ripemd160::new();
+    h.write_str("a".repeat(1000000));
+    assert_eq!(h.hexdigest(), ~"52783243c1697bdbe16d37f97f68f08325dc1528");
+}
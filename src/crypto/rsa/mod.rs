@@ -0,0 +1,870 @@
+// RSA encryption and signatures as specified in PKCS #1 (RFC 8017), after
+// Go's crypto/rsa.
+//
+// RSA is a single, fundamental operation that is used here to implement
+// either public-key encryption or public-key signatures. The original
+// specification for both, PKCS #1 v1.5, is still widely supported; the
+// newer OAEP encryption and PSS signatures should be preferred for new
+// protocols.
+//
+// Private key operations use the Chinese remainder theorem when the key
+// has been precomputed. They are blinded with a random value drawn from
+// the given reader, so that their timing does not reveal the key, and
+// their results are checked with the public key before being returned.
+
+use std::any::{Any, AnyRefExt};
+use std::slice;
+use hash::Hash;
+use math::big::Int;
+use super::{HashFunc, SignerOpts, DecrypterOpts};
+use super::rand;
+use super::subtle::{constant_time_byte_eq, constant_time_compare, constant_time_select};
+
+pub use crypto::rsa::pkcs1v15::{PKCS1v15DecryptOptions, encrypt_pkcs1v15, decrypt_pkcs1v15,
+                                decrypt_pkcs1v15_session_key, sign_pkcs1v15, verify_pkcs1v15};
+pub use crypto::rsa::pss::{PSSOptions, PSSSaltLengthAuto, PSSSaltLengthEqualsHash,
+                           sign_pss, verify_pss};
+
+mod pkcs1v15;
+mod pss;
+
+/// Returned when a decryption fails. It is deliberately vague to avoid
+/// adaptive attacks.
+pub static ErrDecryption: &'static str = "crypto/rsa: decryption error";
+
+/// Returned when a signature fails to verify. It is deliberately vague to
+/// avoid adaptive attacks.
+pub static ErrVerification: &'static str = "crypto/rsa: verification error";
+
+/// Returned when trying to encrypt or sign a message which is too large
+/// for the size of the key.
+pub static ErrMessageTooLong: &'static str = "crypto/rsa: message too long for RSA key size";
+
+/// The public part of an RSA key.
+#[deriving(Clone, Eq)]
+pub struct PublicKey {
+    /// The modulus.
+    pub n: Int,
+    /// The public exponent.
+    pub e: uint,
+}
+
+impl PublicKey {
+    /// Returns the modulus size in bytes. Raw signatures and ciphertexts
+    /// for or by this public key have the same size.
+    pub fn size(&self) -> uint {
+        (self.n.bit_len() + 7) / 8
+    }
+}
+
+impl super::PublicKey for PublicKey {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// Values for the Chinese remainder theorem for the third and subsequent
+/// primes of a multi-prime key.
+#[deriving(Clone)]
+pub struct CRTValue {
+    /// D mod (prime-1).
+    pub exp: Int,
+    /// R·Coeff ≡ 1 mod Prime.
+    pub coeff: Int,
+    /// The product of the primes prior to this one (inc p and q).
+    pub r: Int,
+}
+
+/// Values that speed up private key operations.
+#[deriving(Clone)]
+pub struct PrecomputedValues {
+    /// D mod (P-1)
+    pub dp: Int,
+    /// D mod (Q-1)
+    pub dq: Int,
+    /// Q^-1 mod P
+    pub qinv: Int,
+    /// CRT values for the primes after the first two, which are used in
+    /// three-and-more-prime RSA.
+    pub crt_values: ~[CRTValue],
+}
+
+/// An RSA key.
+#[deriving(Clone)]
+pub struct PrivateKey {
+    pub public_key: PublicKey,
+    /// The private exponent.
+    pub d: Int,
+    /// The prime factors of N, with at least two entries.
+    pub primes: ~[Int],
+    /// Set by precompute; private key operations are much faster with it.
+    pub precomputed: Option<PrecomputedValues>,
+}
+
+impl super::PrivateKey for PrivateKey {}
+
+impl super::Signer for PrivateKey {
+    fn public(&self) -> ~super::PublicKey {
+        ~self.public_key.clone() as ~super::PublicKey
+    }
+
+    /// Signs digest with the key, producing a PSS signature if opts is a
+    /// PSSOptions and a PKCS #1 v1.5 signature otherwise.
+    fn sign(&self, rand: &mut Reader, digest: &[u8], opts: &SignerOpts) -> Result<~[u8], ~str> {
+        let mut rand = rand;
+        match opts.as_any().as_ref::<PSSOptions>() {
+            Some(pss_opts) => match pss_opts.hash {
+                Some(hash) => sign_pss(&mut rand, self, hash, digest, Some(pss_opts)),
+                None => Err(~"crypto/rsa: PSSOptions.hash must be set"),
+            },
+            None => match opts.hash_func() {
+                Some(hash) => sign_pkcs1v15(&mut rand, self, hash, digest),
+                None => Err(~"crypto/rsa: unsupported hash function"),
+            },
+        }
+    }
+}
+
+impl super::Decrypter for PrivateKey {
+    fn public(&self) -> ~super::PublicKey {
+        ~self.public_key.clone() as ~super::PublicKey
+    }
+
+    /// Decrypts ciphertext with the key. If opts is None or a
+    /// PKCS1v15DecryptOptions then PKCS #1 v1.5 decryption is performed;
+    /// if it is an OAEPOptions, OAEP decryption is done.
+    fn decrypt(&self, rand: &mut Reader, ciphertext: &[u8], opts: Option<&DecrypterOpts>)
+               -> Result<~[u8], ~str> {
+        let mut rand = rand;
+        let opts = match opts {
+            Some(opts) => opts,
+            None => return decrypt_pkcs1v15(&mut rand, self, ciphertext),
+        };
+        match opts.as_any().as_ref::<OAEPOptions>() {
+            Some(o) => return decrypt_oaep(o.hash, &mut rand, self, ciphertext, o.label),
+            None => {}
+        }
+        match opts.as_any().as_ref::<PKCS1v15DecryptOptions>() {
+            Some(o) if o.session_key_len > 0 => {
+                let mut plaintext = slice::from_elem(o.session_key_len, 0u8);
+                match rand.fill(plaintext) {
+                    Ok(()) => {}
+                    Err(e) => return Err(format!("crypto/rsa: {}", e)),
+                }
+                try!(decrypt_pkcs1v15_session_key(&mut rand, self, ciphertext, plaintext));
+                Ok(plaintext)
+            }
+            Some(_) => decrypt_pkcs1v15(&mut rand, self, ciphertext),
+            None => Err(~"crypto/rsa: invalid options for Decrypt"),
+        }
+    }
+}
+
+/// Options for OAEP decryption with the Decrypter interface.
+#[deriving(Clone)]
+pub struct OAEPOptions {
+    /// The hash function used when generating the mask.
+    pub hash: HashFunc,
+    /// An arbitrary byte string that must be equal to the value used
+    /// when encrypting.
+    pub label: ~[u8],
+}
+
+impl DecrypterOpts for OAEPOptions {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+fn check_pub(key: &PublicKey) -> Result<(), ~str> {
+    if key.n.sign() <= 0 {
+        return Err(~"crypto/rsa: missing public modulus");
+    }
+    if key.e < 2 {
+        return Err(~"crypto/rsa: public exponent too small");
+    }
+    if key.e > (1 << 31) - 1 {
+        return Err(~"crypto/rsa: public exponent too large");
+    }
+    Ok(())
+}
+
+impl PrivateKey {
+    /// Returns the public key corresponding to this key.
+    pub fn public<'a>(&'a self) -> &'a PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the modulus size in bytes.
+    pub fn size(&self) -> uint {
+        self.public_key.size()
+    }
+
+    /// Performs basic sanity checks on the key.
+    pub fn validate(&self) -> Result<(), ~str> {
+        try!(check_pub(&self.public_key));
+        if self.primes.len() < 2 {
+            return Err(~"crypto/rsa: invalid number of primes");
+        }
+
+        // Check that Πprimes == n.
+        let one = Int::new(1);
+        let mut modulus = one.clone();
+        for prime in self.primes.iter() {
+            // Any primes ≤ 1 will cause divide-by-zero panics later.
+            if *prime <= one {
+                return Err(~"crypto/rsa: invalid prime value");
+            }
+            modulus = modulus * *prime;
+        }
+        if modulus != self.public_key.n {
+            return Err(~"crypto/rsa: invalid modulus");
+        }
+
+        // The CRT needs the primes to be pairwise coprime: each must have
+        // an inverse modulo the product of the ones before it.
+        let mut r = self.primes[0].clone();
+        for prime in self.primes.slice_from(1).iter() {
+            if prime.mod_inverse(&r).is_none() {
+                return Err(~"crypto/rsa: primes are not pairwise coprime");
+            }
+            r = r * *prime;
+        }
+
+        // Check that de ≡ 1 mod p-1, for each prime. This implies that
+        // e is coprime to each p-1 as e has a multiplicative inverse.
+        // Therefore e is coprime to lcm(p-1,q-1,r-1,...) = exponent(ℤ/nℤ).
+        // It also implies that a^de ≡ a mod p as a^(p-1) ≡ 1 mod p. Thus
+        // a^de ≡ a mod n for all a coprime to n, as required.
+        let de = Int::from_u64(self.public_key.e as u64) * self.d;
+        for prime in self.primes.iter() {
+            let cong = de.modulo(&(*prime - one));
+            if cong != one {
+                return Err(~"crypto/rsa: invalid exponents");
+            }
+        }
+        Ok(())
+    }
+
+    /// Performs some calculations that speed up private key operations
+    /// in the future. If the primes are not pairwise coprime there are no
+    /// CRT values; the key is left as it was, and validate reports the
+    /// problem.
+    pub fn precompute(&mut self) {
+        if self.precomputed.is_some() || self.primes.len() < 2 {
+            return;
+        }
+        let one = Int::new(1);
+        let (p, q) = (&self.primes[0], &self.primes[1]);
+        let dp = self.d.modulo(&(*p - one));
+        let dq = self.d.modulo(&(*q - one));
+        let qinv = match q.mod_inverse(p) {
+            Some(qinv) => qinv,
+            None => return,
+        };
+
+        let mut r = *p * *q;
+        let mut crt_values = ~[];
+        for prime in self.primes.slice_from(2).iter() {
+            let coeff = match r.mod_inverse(prime) {
+                Some(coeff) => coeff,
+                None => return,
+            };
+            crt_values.push(CRTValue {
+                exp: self.d.modulo(&(*prime - one)),
+                coeff: coeff,
+                r: r.clone(),
+            });
+            r = r * *prime;
+        }
+        self.precomputed = Some(PrecomputedValues {
+            dp: dp, dq: dq, qinv: qinv, crt_values: crt_values,
+        });
+    }
+}
+
+/// Generates an RSA keypair of the given bit size using the random
+/// source rand (for example, crypto::rand::Reader).
+pub fn generate_key<R: Reader>(rand: &mut R, bits: uint) -> Result<PrivateKey, ~str> {
+    generate_multi_prime_key(rand, 2, bits)
+}
+
+/// Generates a multi-prime RSA keypair of the given bit size and the
+/// given random source.
+///
+/// Table 1 in "On the Security of Multi-prime RSA" suggests maximum
+/// numbers of primes for a given bit size. Although the public keys are
+/// compatible (actually, indistinguishable) from the 2-prime case, the
+/// private keys are not: they may not be usable with other
+/// implementations.
+pub fn generate_multi_prime_key<R: Reader>(rand: &mut R, nprimes: uint, bits: uint)
+                                           -> Result<PrivateKey, ~str> {
+    let e = 65537u;
+    if nprimes < 2 {
+        return Err(~"crypto/rsa: generate_multi_prime_key: nprimes must be >= 2");
+    }
+    if bits < 64 {
+        let prime_limit = (1u64 << (bits / nprimes)) as f64;
+        // pi approximates the number of primes less than prime_limit
+        let mut pi = prime_limit / (prime_limit.ln() - 1.0);
+        // Generated primes start with 11 (in binary) so we can only use
+        // a quarter of them.
+        pi /= 4.0;
+        // Use a factor of two to ensure that key generation terminates in
+        // a reasonable amount of time.
+        pi /= 2.0;
+        if pi <= nprimes as f64 {
+            return Err(~"crypto/rsa: too few primes of given length to generate an RSA key");
+        }
+    }
+
+    let one = Int::new(1);
+    'next: loop {
+        let mut todo = bits;
+        // rand::prime sets the top two bits in each prime. For fewer than
+        // seven primes this ensures the product has the requested length;
+        // above that, the top bits may not be enough, so the primes are
+        // made slightly larger.
+        if nprimes >= 7 {
+            todo += (nprimes - 2) / 5;
+        }
+        let mut primes = ~[];
+        for i in range(0, nprimes) {
+            let p = match rand::prime(rand, todo / (nprimes - i)) {
+                Ok(p) => p,
+                Err(e) => return Err(format!("crypto/rsa: {}", e)),
+            };
+            todo -= p.bit_len();
+            primes.push(p);
+        }
+
+        // Make sure that the primes are pairwise unequal.
+        for i in range(0, primes.len()) {
+            for j in range(0, i) {
+                if primes[i] == primes[j] {
+                    continue 'next;
+                }
+            }
+        }
+
+        let mut n = one.clone();
+        let mut totient = one.clone();
+        for prime in primes.iter() {
+            n = n * *prime;
+            totient = totient * (*prime - one);
+        }
+        if n.bit_len() != bits {
+            // This should never happen for nprimes == 2 because
+            // rand::prime should set the top two bits in each prime.
+            // For nprimes > 2 we hope it does not happen often.
+            continue 'next;
+        }
+
+        match Int::from_u64(e as u64).mod_inverse(&totient) {
+            Some(d) => {
+                let mut key = PrivateKey {
+                    public_key: PublicKey { n: n, e: e },
+                    d: d,
+                    primes: primes,
+                    precomputed: None,
+                };
+                key.precompute();
+                return Ok(key);
+            }
+            None => {}
+        }
+    }
+}
+
+// Increments a four byte, big-endian counter.
+fn inc_counter(c: &mut [u8, ..4]) {
+    for i in range(0, 4u).rev() {
+        c[i] += 1;
+        if c[i] != 0 {
+            return;
+        }
+    }
+}
+
+// XORs the bytes in out with a mask generated using the MGF1 function
+// specified in PKCS #1 v2.1.
+fn mgf1_xor(out: &mut [u8], h: &mut ~Hash, seed: &[u8]) {
+    let mut counter = [0u8, ..4];
+    let mut done = 0u;
+    while done < out.len() {
+        h.reset();
+        h.write(seed).unwrap();
+        h.write(counter.as_slice()).unwrap();
+        let digest = h.digest();
+        let mut i = 0u;
+        while i < digest.len() && done < out.len() {
+            out[done] ^= digest[i];
+            done += 1;
+            i += 1;
+        }
+        inc_counter(&mut counter);
+    }
+}
+
+fn e_int(key: &PublicKey) -> Int {
+    Int::from_u64(key.e as u64)
+}
+
+// The raw public key operation, m^e mod n.
+fn encrypt(key: &PublicKey, m: &Int) -> Int {
+    m.exp(&e_int(key), Some(&key.n)).unwrap()
+}
+
+// The raw private key operation, c^d mod n, using the CRT values if the
+// key was precomputed and blinding c with a random value from rand.
+fn decrypt<R: Reader>(rand: &mut R, key: &PrivateKey, c: &Int) -> Result<Int, ~str> {
+    let n = &key.public_key.n;
+    if n.sign() == 0 || *c >= *n {
+        return Err(ErrDecryption.to_owned());
+    }
+
+    // Blinding: c is multiplied by r^e for a random r, so that the private
+    // key operation works on a value unknown to an attacker, and the
+    // result is multiplied by r^-1 afterwards.
+    let one = Int::new(1);
+    let mut r = one.clone();
+    let mut ir = None;
+    while ir.is_none() {
+        r = match rand::int(rand, n) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("crypto/rsa: {}", e)),
+        };
+        if r.sign() == 0 {
+            r = one.clone();
+        }
+        ir = r.mod_inverse(n);
+    }
+    let ir = ir.unwrap();
+    let rpowe = encrypt(&key.public_key, &r);
+    let c = (*c * rpowe).modulo(n);
+
+    let m = match key.precomputed {
+        None => c.exp(&key.d, Some(n)).unwrap(),
+        Some(ref pre) => {
+            // m = m2 + q·(qinv·(m1 - m2) mod p), and similarly for each
+            // further prime
+            let (p, q) = (&key.primes[0], &key.primes[1]);
+            let m1 = c.exp(&pre.dp, Some(p)).unwrap();
+            let m2 = c.exp(&pre.dq, Some(q)).unwrap();
+            let mut m = ((m1 - m2) * pre.qinv).modulo(p) * *q + m2;
+            for (i, values) in pre.crt_values.iter().enumerate() {
+                let prime = &key.primes[2 + i];
+                let mi = c.exp(&values.exp, Some(prime)).unwrap();
+                let h = ((mi - m) * values.coeff).modulo(prime);
+                m = m + h * values.r;
+            }
+            m
+        }
+    };
+    Ok((m * ir).modulo(n))
+}
+
+// Like decrypt, and verifies the result with the public key to guard
+// against faults in the private key operation.
+fn decrypt_and_check<R: Reader>(rand: &mut R, key: &PrivateKey, c: &Int) -> Result<Int, ~str> {
+    let m = try!(decrypt(rand, key, c));
+    if encrypt(&key.public_key, &m) != *c {
+        return Err(~"crypto/rsa: internal error");
+    }
+    Ok(m)
+}
+
+/// Encrypts the given message with RSA-OAEP.
+///
+/// OAEP is parameterised by a hash function that is used as a random
+/// oracle. Encryption and decryption of a given message must use the
+/// same hash function, and SHA-256 is a reasonable choice.
+///
+/// The random parameter is used as a source of entropy to ensure that
+/// encrypting the same message twice doesn't result in the same
+/// ciphertext.
+///
+/// The label parameter may contain arbitrary data that will not be
+/// encrypted, but which gives important context to the message. For
+/// example, if a given public key is used to encrypt two types of
+/// messages then distinct label values could be used to ensure that a
+/// ciphertext for one purpose cannot be used for another by an attacker.
+/// If not required it can be empty.
+///
+/// The message must be no longer than the length of the public modulus
+/// minus twice the hash length, minus a further 2.
+pub fn encrypt_oaep<R: Reader>(hash: HashFunc, rand: &mut R, key: &PublicKey, msg: &[u8],
+                               label: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_pub(key));
+    let mut h = hash.new();
+    let k = key.size();
+    let hlen = h.size();
+    if msg.len() + 2 * hlen + 2 > k {
+        return Err(ErrMessageTooLong.to_owned());
+    }
+
+    h.write(label).unwrap();
+    let lhash = h.digest();
+
+    let mut em = slice::from_elem(k, 0u8);
+    {
+        let (seed, db) = em.mut_slice_from(1).mut_split_at(hlen);
+        db.mut_slice_to(hlen).copy_from(lhash);
+        let dblen = db.len();
+        db[dblen - msg.len() - 1] = 1;
+        db.mut_slice_from(dblen - msg.len()).copy_from(msg);
+
+        match rand.fill(seed) {
+            Ok(()) => {}
+            Err(e) => return Err(format!("crypto/rsa: {}", e)),
+        }
+        mgf1_xor(db, &mut h, seed);
+        mgf1_xor(seed, &mut h, db);
+    }
+
+    let c = encrypt(key, &Int::from_bytes(em));
+    let mut out = slice::from_elem(k, 0u8);
+    c.fill_bytes(out);
+    Ok(out)
+}
+
+/// Decrypts ciphertext using RSA-OAEP. The hash and label must match the
+/// values used to encrypt. rand is used for blinding.
+///
+/// The error does not reveal which step of the decryption failed, to
+/// prevent adaptive chosen-ciphertext attacks.
+pub fn decrypt_oaep<R: Reader>(hash: HashFunc, rand: &mut R, key: &PrivateKey,
+                               ciphertext: &[u8], label: &[u8]) -> Result<~[u8], ~str> {
+    try!(check_pub(&key.public_key));
+    let mut h = hash.new();
+    let k = key.size();
+    let hlen = h.size();
+    if ciphertext.len() > k || k < hlen * 2 + 2 {
+        return Err(ErrDecryption.to_owned());
+    }
+
+    let m = try!(decrypt_and_check(rand, key, &Int::from_bytes(ciphertext)));
+
+    h.write(label).unwrap();
+    let lhash = h.digest();
+
+    let mut em = slice::from_elem(k, 0u8);
+    m.fill_bytes(em);
+
+    let first_byte_is_zero = constant_time_byte_eq(em[0], 0);
+
+    let (seed, db) = em.mut_slice_from(1).mut_split_at(hlen);
+    mgf1_xor(seed, &mut h, db);
+    mgf1_xor(db, &mut h, seed);
+
+    let lhash2_good = constant_time_compare(lhash, db.slice_to(hlen)) as int;
+
+    // The remainder of the plaintext must be zero or more 0x00, followed
+    // by 0x01, followed by the message.
+    //   looking_for_index: 1 iff we are still looking for the 0x01
+    //   index: the offset of the first 0x01 byte
+    //   invalid: 1 iff we saw a non-zero byte before the 0x01.
+    let mut looking_for_index = 1;
+    let mut index = 0;
+    let mut invalid = 0;
+    let rest = db.slice_from(hlen);
+    for i in range(0, rest.len()) {
+        let equals0 = constant_time_byte_eq(rest[i], 0);
+        let equals1 = constant_time_byte_eq(rest[i], 1);
+        index = constant_time_select(looking_for_index & equals1, i as int, index);
+        looking_for_index = constant_time_select(equals1, 0, looking_for_index);
+        invalid = constant_time_select(looking_for_index & (equals0 ^ 1), 1, invalid);
+    }
+
+    if first_byte_is_zero & lhash2_good & (invalid ^ 1) & (looking_for_index ^ 1) != 1 {
+        return Err(ErrDecryption.to_owned());
+    }
+    Ok(rest.slice_from(index as uint + 1).to_owned())
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use IoReader = std::io::Reader;
+    use PublicKeyTrait = crypto::PublicKey;
+    use std::any::AnyRefExt;
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use math::big::Int;
+    use super::*;
+    use super::super::{SHA1, SHA256, Signer, SignerOpts, Decrypter, DecrypterOpts, sign_message};
+    use super::super::rand::Reader;
+    use super::super::rand::drbg::CtrDrbg;
+
+    fn int(s: &str) -> Int {
+        Int::from_str_radix(s, 16).unwrap()
+    }
+
+    // A 1024-bit test key, generated with OpenSSL.
+    pub fn test_key() -> PrivateKey {
+        let mut key = PrivateKey {
+            public_key: PublicKey {
+                n: int("98cd10c462823715748b4935f967e96e19b50291872b9b914ab8f72b491aeb08\
+                        4664df6d1e0d22ca348e950a3e5d898bcb4f8e5fb6c8a3718faf669dee39f1f2\
+                        abb8c79c2c57707cf236ce3a39d0772ccc9b34a34e84f9cc1cbfb0f0751378fd\
+                        e5c6475879ac85121a3092cff12059ffae1072d22b1ec0893a43f9b2cd4f0a89"),
+                e: 65537,
+            },
+            d: int("759ee51946fb158bde7a999082bc0a644645a00486187b580c3cf586cc6f7c1c\
+                    929373a97b48814f45c5bbd476cd69e3c24d2b3b62686fe82ac2ca2b280652c7\
+                    5a097daf66a61a7b9b0264186375d26cbe16002d22075bd878ef884fc3f6ed6e\
+                    a0eed00ea3ef5fa3693637d9c118c2038e81a491b228e4b9ddc51193f4a81d01"),
+            primes: ~[
+                int("c6bc492f1209cd5c4a5f3471a1a3c2db3160894725383ac590a8b3179948c07a\
+                     0ce77bc4b211e836d3066f1565fddd205e37fda586831ab9ab7bdfb6891160a5"),
+                int("c4d46d3fdb451b33ded1d7310d050d74843cded1f5e1d391717fce073d06cbcf\
+                     86e0fb7e67e357d42b59103e1c74e1e4fe23b5231bfd0b34cac9cf52de0a1915"),
+            ],
+            precomputed: None,
+        };
+        key.precompute();
+        key
+    }
+
+    fn check_key(key: &PrivateKey) {
+        assert!(key.validate().is_ok());
+        let m = Int::new(42);
+        let c = encrypt(key.public(), &m);
+        let mut no_crt = key.clone();
+        no_crt.precomputed = None;
+        assert_eq!(super::decrypt(&mut Reader, key, &c).unwrap(), m);
+        assert_eq!(super::decrypt(&mut Reader, &no_crt, &c).unwrap(), m);
+    }
+
+    #[test]
+    fn test_key_generation() {
+        let key = generate_key(&mut Reader, 512).unwrap();
+        assert_eq!(key.public().n.bit_len(), 512);
+        assert_eq!(key.size(), 64);
+        check_key(&key);
+    }
+
+    #[test]
+    fn test_multi_prime_key_generation() {
+        for &(nprimes, bits) in [(3u, 768u), (4, 768), (8, 1024)].iter() {
+            let key = generate_multi_prime_key(&mut Reader, nprimes, bits).unwrap();
+            assert_eq!(key.primes.len(), nprimes);
+            assert_eq!(key.public().n.bit_len(), bits);
+            check_key(&key);
+        }
+        assert!(generate_multi_prime_key(&mut Reader, 1, 512).is_err());
+        assert!(generate_multi_prime_key(&mut Reader, 8, 32).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let key = test_key();
+        check_key(&key);
+
+        let mut bad = key.clone();
+        bad.d = bad.d + Int::new(2);
+        assert_eq!(bad.validate(), Err(~"crypto/rsa: invalid exponents"));
+        let mut bad = key.clone();
+        bad.primes[0] = bad.primes[0] + Int::new(2);
+        assert_eq!(bad.validate(), Err(~"crypto/rsa: invalid modulus"));
+        let mut bad = key.clone();
+        bad.public_key.e = 1;
+        assert_eq!(bad.validate(), Err(~"crypto/rsa: public exponent too small"));
+
+        // With a repeated prime there is no CRT coefficient: precompute
+        // must leave the key alone rather than fail, and validate must
+        // reject it even though de ≡ 1 mod p-1 holds.
+        let p = key.primes[0].clone();
+        let one = Int::new(1);
+        let mut bad = PrivateKey {
+            public_key: PublicKey { n: p * p, e: 65537 },
+            d: Int::new(65537).mod_inverse(&(p - one)).unwrap(),
+            primes: ~[p.clone(), p.clone()],
+            precomputed: None,
+        };
+        bad.precompute();
+        assert!(bad.precomputed.is_none());
+        assert_eq!(bad.validate(), Err(~"crypto/rsa: primes are not pairwise coprime"));
+    }
+
+    #[test]
+    fn test_decrypt_out_of_range() {
+        let key = test_key();
+        assert!(super::decrypt(&mut Reader, &key, &key.public().n).is_err());
+    }
+
+    #[test]
+    fn test_oaep() {
+        let key = test_key();
+        // encrypted by OpenSSL
+        let tests = [
+            (SHA256, "label",
+             "055e0e24ddbda3020e416fa4ddde4ceea4e26f04e1b9c59900ba4a7b57e647e8\
+              f0497cac90c36818499ad5c62a3fd30acf9c94a9a5b66b40dc4f0cccf1e693a5\
+              2eb7d21f27708c7bce05be7f4f033b8a9b209e8ceb48aab834de7e60ce29e6a6\
+              2494579cfd805848343750039a0d418b0f97481984476ec895a6902a21587a8e"),
+            (SHA1, "",
+             "3e3529c2836d3ed41452945b76d056ef1a5504892878ae2a8e8dbf2c6635f084\
+              9f2172a09ba55efbebbac230543b4cb1397549ba7e0e715ad86cb1d6f17521cc\
+              76545d55ffe050406cab0d4342021292a05efd4f90befa6c28773f41b2dad378\
+              a5c1d3d52e6fbe172998a76e289af0f1c5a9c45a21f402667ea783bc6f8ab0fd"),
+        ];
+        for &(hash, label, ct) in tests.iter() {
+            let ct = ct.from_hex().unwrap();
+            let pt = decrypt_oaep(hash, &mut Reader, &key, ct.as_slice(), label.as_bytes()).unwrap();
+            assert_eq!(pt.as_slice(), bytes!("hello, world"));
+
+            assert_eq!(decrypt_oaep(hash, &mut Reader, &key, ct.as_slice(), bytes!("other")).err(),
+                       Some(ErrDecryption.to_owned()));
+            let mut bad = ct.clone();
+            bad[100] ^= 0x40;
+            assert!(decrypt_oaep(hash, &mut Reader, &key, bad.as_slice(), label.as_bytes()).is_err());
+
+            // a fault in the CRT half mod p must not get past the check
+            let mut faulty = key.clone();
+            match faulty.precomputed {
+                Some(ref mut pre) => pre.dp = pre.dp + Int::new(2),
+                None => fail!(),
+            }
+            assert!(decrypt_oaep(hash, &mut Reader, &faulty, ct.as_slice(),
+                                 label.as_bytes()).is_err());
+        }
+
+        // round trip, including the longest message that fits
+        let mut rng = CtrDrbg::new_seeded(&[1u8, ..48], []).unwrap();
+        for &len in [0u, 1, 128 - 2 * 32 - 2].iter() {
+            let msg = Vec::from_elem(len, 0x5au8);
+            let ct = encrypt_oaep(SHA256, &mut rng, key.public(), msg.as_slice(), []).unwrap();
+            assert_eq!(ct.len(), 128);
+            let pt = decrypt_oaep(SHA256, &mut rng, &key, ct.as_slice(), []).unwrap();
+            assert_eq!(pt.as_slice(), msg.as_slice());
+        }
+        let long = [0u8, ..128 - 2 * 32 - 1];
+        assert_eq!(encrypt_oaep(SHA256, &mut rng, key.public(), long, []).err(),
+                   Some(ErrMessageTooLong.to_owned()));
+
+        // encryption is randomized
+        let a = encrypt_oaep(SHA256, &mut rng, key.public(), bytes!("x"), []).unwrap();
+        let b = encrypt_oaep(SHA256, &mut rng, key.public(), bytes!("x"), []).unwrap();
+        assert!(a.as_slice().to_hex() != b.as_slice().to_hex());
+    }
+
+    #[test]
+    fn test_oaep_encodings() {
+        // Invalid paddings of the kind Project Wycheproof's rsa_oaep tests
+        // check for (SHA-256, empty label), encrypted with the raw public
+        // key operation and cross-checked against Python's cryptography.
+        // Every invalid padding must give the same error.
+        let key = test_key();
+        let tests = [
+            ("valid", true, "68656c6c6f2c20776f726c64",
+             "7c93e784ce07c5d9de20e1c5ac90507a9e7eac99e927d4c8daf7b8951b42f1ad\
+              ec8e8577a4a5cc53124b3877e8a2f7200279457fc9169370aa1cf61b12075752\
+              a25427a37b3657f0b5acedaf8e74d2f01a2628e1186d7f6ff7e8f30cfda1fb88\
+              588c467814f8dcb70c3f2a4241a0de0943281507060491231d872ac5152dee54"),
+            ("valid, empty message", true, "",
+             "32723e180c402ffeee1968b55f55dd346d1bcf630a4d4227cf2951c8a870767c\
+              7d2b327eae15b5ab58a513a63ed986d06b1434b88ebe3b8cac308a45be52c16e\
+              cc28541e64eabcfdf69f156a257eea2f9179b6a3530774cd7353095fc0ea17e4\
+              8c9cdc8808ccf7808be3e79e0e4764376a2d78e92bfa4d068e38fa5678fb3b7f"),
+            ("valid, longest message", true, "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d",
+             "73db3958b57b7754c0e40475d712eb9c9cf5b841c701642c2d6f9cbfb1d06aa0\
+              877115a2e216fc5cddc82fbeb121e70adc534c45c8604263a4a58a3922eb87fb\
+              356f0e718d5981fca8ec27bc278ade2d9ac326d52d49026ca378932ffd152747\
+              d32e39ee1d7a29896453d5f5e2c53df0a67fa39ba4920d3d0d5895fd4162c497"),
+            ("first byte 1", false, "",
+             "69bb7792dad1de69ddf7329110c0a1847b8f3583f8ba94a4218339723f91a7f6\
+              4e7e2e9b43ace4740fb2cebbd46da515a1a95dd6f0a033b4eaa0b4d7c89d33f6\
+              5101b15979a6d6ee6fc0a5245130d2847bf62acd6ec09d7ef90bc931451f6977\
+              4f35d8fc4750e9c00f770da85dcc3159a3b3fa564328fc0e21836e72c0ca4510"),
+            ("wrong label hash", false, "",
+             "4558bc9accac5c040e5f4ba97c70dde73f45556ab542ed2d27ece474b35bf8d2\
+              0f1b9a3186b4295f17b1f65a3b3974f3f588036d37c508da82d69011a9901c75\
+              a24404746cc5d8d2505daa598aa88cd71952f9caba51851fbaeef90ede60dc07\
+              79d48a5520029996ae2ace9f18735e0d4467849681f58b9458ee368e8246dd16"),
+            ("separator 0x02", false, "",
+             "73875d45dc368777cd535811e1892f6d94d62cb7946f30ef8d197df87b7a8461\
+              98c1efc2a230db43b3033b4f6a7b50f1e13babfa8cd88c9549068d763011a549\
+              ece1069729f9155161528a22f2a0e0858edbbd994e7f2d6acc5b8247f13f0e93\
+              458a7ad4b295b28e722d7cde627f86d82e2d8048fcb7de37b03a96316f615eb7"),
+            ("no separator", false, "",
+             "3220f2bca7e15b66963b466b8b6ebb1e9f06a0ae61099fa38a9ab2b70bcc55d1\
+              4e6899155794bf4d65eaba9ee992e295ac92f2f2284260c2c91690b7b57b259b\
+              20c60822129a196589cc041e4b5a5ee7d4d46bb3294a9fb28b26552159e93e87\
+              df78d5dcbefccca38c268bcb490d431c71b08f171a7a879fc1835dc7f26d9e4a"),
+            ("nonzero padding byte", false, "",
+             "60ca16389fc595cd86d5cb87b47192b10c1a89e96f7af2497be37f540f7a7bb1\
+              c5c7853ed8cce82e1df94b523ea578f86360a2c0577f0a24c48bbded6d9864ea\
+              d81eef08ed3d085d10b47c9406945eb692e6b3d521926aa1f5c517b1bd2dab2f\
+              a4ea33d221011bb0e6abc45d137ecab66b2e6c60c4114e46f34dbbc27eb7dbd8"),
+        ];
+        for &(comment, valid, msg, ct) in tests.iter() {
+            let ct = ct.from_hex().unwrap();
+            match decrypt_oaep(SHA256, &mut Reader, &key, ct.as_slice(), []) {
+                Ok(pt) => {
+                    assert!(valid, "{}", comment);
+                    assert_eq!(pt.as_slice().to_hex(), msg.to_owned());
+                }
+                Err(e) => {
+                    assert!(!valid, "{}", comment);
+                    assert_eq!(e, ErrDecryption.to_owned());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_signer() {
+        let key = test_key();
+        let signer = &key as &Signer;
+        let mut rand = Reader;
+        let rand = &mut rand as &mut IoReader;
+
+        let public = signer.public();
+        assert!(public.as_any().as_ref::<PublicKey>() == Some(key.public()));
+
+        let msg = bytes!("hello, world");
+        let mut d = SHA256.new();
+        d.write(msg).unwrap();
+        let hashed = d.digest();
+        let sig = signer.sign(rand, hashed, &SHA256 as &SignerOpts).unwrap();
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, sig), Ok(()));
+        let sig2 = sign_message(signer, rand, msg, &SHA256 as &SignerOpts).unwrap();
+        assert_eq!(sig2, sig);
+
+        let opts = PSSOptions { salt_length: PSSSaltLengthEqualsHash, hash: Some(SHA256) };
+        let sig = sign_message(signer, rand, msg, &opts as &SignerOpts).unwrap();
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&opts)), Ok(()));
+        let opts = PSSOptions { salt_length: PSSSaltLengthEqualsHash, hash: None };
+        assert!(signer.sign(rand, hashed, &opts as &SignerOpts).is_err());
+    }
+
+    #[test]
+    fn test_decrypter() {
+        let key = test_key();
+        let decrypter = &key as &Decrypter;
+        let mut rand = Reader;
+        let rand = &mut rand as &mut IoReader;
+        let msg = bytes!("hello");
+
+        let ct = encrypt_pkcs1v15(&mut Reader, key.public(), msg).unwrap();
+        assert_eq!(decrypter.decrypt(rand, ct, None).unwrap().as_slice(), msg);
+        let opts = PKCS1v15DecryptOptions { session_key_len: 0 };
+        let pt = decrypter.decrypt(rand, ct, Some(&opts as &DecrypterOpts)).unwrap();
+        assert_eq!(pt.as_slice(), msg);
+        let opts = PKCS1v15DecryptOptions { session_key_len: 5 };
+        let pt = decrypter.decrypt(rand, ct, Some(&opts as &DecrypterOpts)).unwrap();
+        assert_eq!(pt.as_slice(), msg);
+        // a session key of the wrong length is replaced with random bytes
+        let opts = PKCS1v15DecryptOptions { session_key_len: 16 };
+        let pt = decrypter.decrypt(rand, ct, Some(&opts as &DecrypterOpts)).unwrap();
+        assert_eq!(pt.len(), 16);
+
+        let ct = encrypt_oaep(SHA256, &mut Reader, key.public(), msg, bytes!("label")).unwrap();
+        let opts = OAEPOptions { hash: SHA256, label: bytes!("label").to_owned() };
+        let pt = decrypter.decrypt(rand, ct, Some(&opts as &DecrypterOpts)).unwrap();
+        assert_eq!(pt.as_slice(), msg);
+        let opts = OAEPOptions { hash: SHA256, label: ~[] };
+        assert!(decrypter.decrypt(rand, ct, Some(&opts as &DecrypterOpts)).is_err());
+    }
+}
@@ -0,0 +1,573 @@
+// RSA encryption and signatures with PKCS #1 v1.5 padding, after Go's
+// crypto/rsa/pkcs1v15.go.
+
+use std::any::Any;
+use std::slice;
+use math::big::Int;
+use super::super::{DecrypterOpts, HashFunc, MD5, SHA1, SHA224, SHA256, SHA384, SHA512, MD5SHA1, RIPEMD160,
+                   SHA3_224, SHA3_256, SHA3_384, SHA3_512, SHA512_224, SHA512_256};
+use super::super::subtle::{constant_time_byte_eq, constant_time_compare, constant_time_copy,
+                           constant_time_eq, constant_time_less_or_eq, constant_time_select};
+use super::{PublicKey, PrivateKey, ErrDecryption, ErrVerification, ErrMessageTooLong};
+use super::{check_pub, encrypt, decrypt_and_check};
+
+/// Options for PKCS #1 v1.5 decryption with the Decrypter interface.
+#[deriving(Clone)]
+pub struct PKCS1v15DecryptOptions {
+    /// The length of the session key that is being decrypted. If not
+    /// zero, a padding error during decryption will cause a random
+    /// plaintext of this length to be returned rather than an error.
+    /// These alternatives happen in constant time.
+    pub session_key_len: uint,
+}
+
+impl DecrypterOpts for PKCS1v15DecryptOptions {
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+/// Encrypts the given message with RSA and the padding scheme from
+/// PKCS #1 v1.5. The message must be no longer than the length of the
+/// public modulus minus 11 bytes.
+///
+/// The rand parameter is used as a source of entropy to ensure that
+/// encrypting the same message twice doesn't result in the same
+/// ciphertext.
+///
+/// WARNING: use of this function to encrypt plaintexts other than
+/// session keys is dangerous. Use RSA OAEP in new protocols.
+pub fn encrypt_pkcs1v15<R: Reader>(rand: &mut R, key: &PublicKey, msg: &[u8])
+                                   -> Result<~[u8], ~str> {
+    try!(check_pub(key));
+    let k = key.size();
+    if msg.len() + 11 > k {
+        return Err(ErrMessageTooLong.to_owned());
+    }
+
+    // EM = 0x00 || 0x02 || PS || 0x00 || M
+    let mut em = slice::from_elem(k, 0u8);
+    em[1] = 2;
+    {
+        let (ps, mm) = em.mut_slice_from(2).mut_split_at(k - msg.len() - 3);
+        match non_zero_random_bytes(ps, rand) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
+        mm[0] = 0;
+        mm.mut_slice_from(1).copy_from(msg);
+    }
+
+    let c = encrypt(key, &Int::from_bytes(em));
+    let mut out = slice::from_elem(k, 0u8);
+    c.fill_bytes(out);
+    Ok(out)
+}
+
+/// Decrypts a plaintext using RSA and the padding scheme from
+/// PKCS #1 v1.5. rand is used for blinding.
+///
+/// Note that whether this function returns an error or not discloses
+/// secret information. If an attacker can cause this function to run
+/// repeatedly and learn whether each instance returned an error then they
+/// can decrypt and forge signatures as if they had the private key. See
+/// decrypt_pkcs1v15_session_key for a way of solving this problem.
+pub fn decrypt_pkcs1v15<R: Reader>(rand: &mut R, key: &PrivateKey, ciphertext: &[u8])
+                                   -> Result<~[u8], ~str> {
+    try!(check_pub(&key.public_key));
+    let (valid, em, index) = try!(decrypt_pkcs1v15_inner(rand, key, ciphertext));
+    if valid == 0 {
+        return Err(ErrDecryption.to_owned());
+    }
+    Ok(em.slice_from(index).to_owned())
+}
+
+/// Decrypts a session key using RSA and the padding scheme from
+/// PKCS #1 v1.5. rand is used for blinding.
+///
+/// It returns an error if the ciphertext is the wrong length or if the
+/// ciphertext is greater than the public modulus. Otherwise, no error is
+/// returned. If the padding is valid, the resulting plaintext message is
+/// copied into key. Otherwise, key is unchanged. These alternatives occur
+/// in constant time. It is intended that the user of this function
+/// generate a random session key beforehand and continue the protocol
+/// with the resulting value.
+///
+/// Note that if the session key is too small then it may be possible for
+/// an attacker to brute-force it. If they can do that then they can learn
+/// whether a random value was used (because it'll be different for the
+/// same ciphertext) and thus whether the padding was correct. This also
+/// defeats the point of this function. Using at least a 16-byte key will
+/// protect against this attack.
+///
+/// This function performs no length checks on the plaintext and does not
+/// protect against Bleichenbacher-style attacks on its own; see RFC 3218
+/// section 2.3.2.
+pub fn decrypt_pkcs1v15_session_key<R: Reader>(rand: &mut R, key: &PrivateKey,
+                                               ciphertext: &[u8], session_key: &mut [u8])
+                                               -> Result<(), ~str> {
+    try!(check_pub(&key.public_key));
+    let k = key.size();
+    if k < session_key.len() + 3 + 8 {
+        return Err(ErrDecryption.to_owned());
+    }
+
+    let (valid, em, index) = try!(decrypt_pkcs1v15_inner(rand, key, ciphertext));
+    if em.len() != k {
+        // This should be impossible because decrypt_pkcs1v15_inner
+        // always returns the full slice.
+        return Err(ErrDecryption.to_owned());
+    }
+
+    let valid = valid & constant_time_eq((em.len() - index) as i32, session_key.len() as i32);
+    let n = session_key.len();
+    constant_time_copy(valid, session_key, em.slice_from(em.len() - n));
+    Ok(())
+}
+
+// Decrypts using PKCS #1 v1.5 padding. It returns one or zero in valid
+// that indicates whether the plaintext was correctly structured. In
+// either case, the plaintext is returned in em so that it may be read
+// independently of whether it was valid in order to maintain constant
+// memory access patterns. If the plaintext was valid then index contains
+// the index of the original message in em, to allow constant time
+// padding removal.
+fn decrypt_pkcs1v15_inner<R: Reader>(rand: &mut R, key: &PrivateKey, ciphertext: &[u8])
+                                     -> Result<(int, ~[u8], uint), ~str> {
+    let k = key.size();
+    if k < 11 || ciphertext.len() > k {
+        return Err(ErrDecryption.to_owned());
+    }
+
+    let m = try!(decrypt_and_check(rand, key, &Int::from_bytes(ciphertext)));
+    let mut em = slice::from_elem(k, 0u8);
+    m.fill_bytes(em);
+
+    let first_byte_is_zero = constant_time_byte_eq(em[0], 0);
+    let second_byte_is_two = constant_time_byte_eq(em[1], 2);
+
+    // The remainder of the plaintext must be a string of non-zero random
+    // octets, followed by a 0, followed by the message.
+    //   looking_for_index: 1 iff we are still looking for the zero.
+    //   index: the offset of the first zero byte.
+    let mut looking_for_index = 1;
+    let mut index = 0;
+
+    for i in range(2, em.len()) {
+        let equals0 = constant_time_byte_eq(em[i], 0);
+        index = constant_time_select(looking_for_index & equals0, i as int, index);
+        looking_for_index = constant_time_select(equals0, 0, looking_for_index);
+    }
+
+    // The PS padding must be at least 8 bytes long, and it starts two
+    // bytes into em.
+    let valid_ps = constant_time_less_or_eq(2 + 8, index);
+
+    let valid = first_byte_is_zero & second_byte_is_two & (!looking_for_index & 1) & valid_ps;
+    let index = constant_time_select(valid, index + 1, 0);
+    Ok((valid, em, index as uint))
+}
+
+// Fills the given slice with non-zero random octets.
+fn non_zero_random_bytes<R: Reader>(s: &mut [u8], rand: &mut R) -> Result<(), ~str> {
+    match rand.fill(s) {
+        Ok(()) => {}
+        Err(e) => return Err(format!("crypto/rsa: {}", e)),
+    }
+    for i in range(0, s.len()) {
+        while s[i] == 0 {
+            match rand.read_byte() {
+                Ok(b) => s[i] = b,
+                Err(e) => return Err(format!("crypto/rsa: {}", e)),
+            }
+        }
+    }
+    Ok(())
+}
+
+// These are ASN1 DER structures:
+//   DigestInfo ::= SEQUENCE {
+//     digestAlgorithm AlgorithmIdentifier,
+//     digest OCTET STRING
+//   }
+// For performance, we don't use the generic ASN1 encoder. Rather, we
+// precompute a prefix of the digest value that makes a valid ASN1 DER
+// string with the correct contents.
+static hashPrefixMD5: &'static [u8] = &[
+    0x30, 0x20, 0x30, 0x0c, 0x06, 0x08, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x05, 0x05,
+    0x00, 0x04, 0x10];
+static hashPrefixSHA1: &'static [u8] = &[
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14];
+static hashPrefixSHA224: &'static [u8] = &[
+    0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04,
+    0x05, 0x00, 0x04, 0x1c];
+static hashPrefixSHA256: &'static [u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+    0x05, 0x00, 0x04, 0x20];
+static hashPrefixSHA384: &'static [u8] = &[
+    0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+    0x05, 0x00, 0x04, 0x30];
+static hashPrefixSHA512: &'static [u8] = &[
+    0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+    0x05, 0x00, 0x04, 0x40];
+static hashPrefixSHA512_224: &'static [u8] = &[
+    0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x05,
+    0x05, 0x00, 0x04, 0x1c];
+static hashPrefixSHA512_256: &'static [u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x06,
+    0x05, 0x00, 0x04, 0x20];
+static hashPrefixSHA3_224: &'static [u8] = &[
+    0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x07,
+    0x05, 0x00, 0x04, 0x1c];
+static hashPrefixSHA3_256: &'static [u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x08,
+    0x05, 0x00, 0x04, 0x20];
+static hashPrefixSHA3_384: &'static [u8] = &[
+    0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x09,
+    0x05, 0x00, 0x04, 0x30];
+static hashPrefixSHA3_512: &'static [u8] = &[
+    0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x0a,
+    0x05, 0x00, 0x04, 0x40];
+// special case, used by TLS 1.0 and 1.1
+static hashPrefixMD5SHA1: &'static [u8] = &[];
+static hashPrefixRIPEMD160: &'static [u8] = &[
+    0x30, 0x20, 0x30, 0x08, 0x06, 0x06, 0x28, 0xcf, 0x06, 0x03, 0x00, 0x31, 0x04, 0x14];
+
+fn pkcs1v15_hash_info(hash: HashFunc, in_len: uint) -> Result<(uint, &'static [u8]), ~str> {
+    let hash_len = hash.size();
+    if in_len != hash_len {
+        return Err(~"crypto/rsa: input must be hashed message");
+    }
+    let prefix = match hash {
+        MD5 => hashPrefixMD5,
+        SHA1 => hashPrefixSHA1,
+        SHA224 => hashPrefixSHA224,
+        SHA256 => hashPrefixSHA256,
+        SHA384 => hashPrefixSHA384,
+        SHA512 => hashPrefixSHA512,
+        SHA512_224 => hashPrefixSHA512_224,
+        SHA512_256 => hashPrefixSHA512_256,
+        SHA3_224 => hashPrefixSHA3_224,
+        SHA3_256 => hashPrefixSHA3_256,
+        SHA3_384 => hashPrefixSHA3_384,
+        SHA3_512 => hashPrefixSHA3_512,
+        MD5SHA1 => hashPrefixMD5SHA1,
+        RIPEMD160 => hashPrefixRIPEMD160,
+        _ => return Err(~"crypto/rsa: unsupported hash function"),
+    };
+    Ok((hash_len, prefix))
+}
+
+/// Calculates the signature of hashed using RSASSA-PKCS1-V1_5-SIGN from
+/// RSA PKCS #1 v1.5. Note that hashed must be the result of hashing the
+/// input message using the given hash function. If hash is MD5SHA1,
+/// hashed is signed directly, which isn't advisable except for
+/// interoperability.
+///
+/// rand is used for blinding; the signature itself is deterministic.
+pub fn sign_pkcs1v15<R: Reader>(rand: &mut R, key: &PrivateKey, hash: HashFunc, hashed: &[u8])
+                                -> Result<~[u8], ~str> {
+    let (hash_len, prefix) = try!(pkcs1v15_hash_info(hash, hashed.len()));
+
+    let t_len = prefix.len() + hash_len;
+    let k = key.size();
+    if k < t_len + 11 {
+        return Err(ErrMessageTooLong.to_owned());
+    }
+
+    // EM = 0x00 || 0x01 || PS || 0x00 || T
+    let mut em = slice::from_elem(k, 0xffu8);
+    em[0] = 0;
+    em[1] = 1;
+    em[k - t_len - 1] = 0;
+    em.mut_slice(k - t_len, k - hash_len).copy_from(prefix);
+    em.mut_slice_from(k - hash_len).copy_from(hashed);
+
+    let c = try!(decrypt_and_check(rand, key, &Int::from_bytes(em)));
+    let mut out = slice::from_elem(k, 0u8);
+    c.fill_bytes(out);
+    Ok(out)
+}
+
+/// Verifies an RSA PKCS #1 v1.5 signature. hashed is the result of
+/// hashing the input message using the given hash function and sig is
+/// the signature. A valid signature is indicated by returning Ok(()). If
+/// hash is MD5SHA1 then hashed is used directly. This isn't advisable
+/// except for interoperability.
+pub fn verify_pkcs1v15(key: &PublicKey, hash: HashFunc, hashed: &[u8], sig: &[u8])
+                       -> Result<(), ~str> {
+    try!(check_pub(key));
+    let (hash_len, prefix) = try!(pkcs1v15_hash_info(hash, hashed.len()));
+
+    let t_len = prefix.len() + hash_len;
+    let k = key.size();
+    if k < t_len + 11 {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // RFC 8017 Section 8.2.2: If the length of the signature S is not k
+    // octets (where k is the length in octets of the RSA modulus n),
+    // output "invalid signature" and stop.
+    if k != sig.len() {
+        return Err(ErrVerification.to_owned());
+    }
+
+    let c = Int::from_bytes(sig);
+    if c >= key.n {
+        return Err(ErrVerification.to_owned());
+    }
+    let m = encrypt(key, &c);
+    let mut em = slice::from_elem(k, 0u8);
+    m.fill_bytes(em);
+
+    // EM = 0x00 || 0x01 || PS || 0x00 || T
+    let mut ok = constant_time_byte_eq(em[0], 0);
+    ok &= constant_time_byte_eq(em[1], 1);
+    ok &= constant_time_compare(em.slice_from(k - hash_len), hashed) as int;
+    ok &= constant_time_compare(em.slice(k - t_len, k - hash_len), prefix) as int;
+    ok &= constant_time_byte_eq(em[k - t_len - 1], 0);
+
+    for i in range(2, k - t_len - 1) {
+        ok &= constant_time_byte_eq(em[i], 0xff);
+    }
+
+    if ok != 1 {
+        return Err(ErrVerification.to_owned());
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use super::super::*;
+    use super::super::test::test_key;
+    use super::super::super::{HashFunc, SHA1, SHA224, SHA256, SHA512, SHA3_256, SHA512_256,
+                              MD5SHA1, BLAKE2b_256};
+    use super::super::super::rand::Reader;
+
+    fn hash(h: HashFunc, msg: &[u8]) -> ~[u8] {
+        let mut d = h.new();
+        d.write(msg).unwrap();
+        d.digest()
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let key = test_key();
+        let msg = bytes!("hello, world");
+        // signed by OpenSSL
+        let tests = [
+            (SHA256,
+             "3a489af70a7c109856bf7d1e9bc062a3291af720b981ea682d9f5887d55b4849\
+              d2eed1fb3e82445cfaed0b4edd321e52050e24644600dc3a68a0f7b3cf916fca\
+              47c55ca665145380cd8991f628c1f3231a3930380461f286a6f72c5f83f4918b\
+              308954e8e32fd36774730239d7069601f832cfe54e4cfe9ba28ee6608181b2fb"),
+            (SHA1,
+             "49d529c42814e327868fe73c6d35b210d32390d9b9ab4cedee662e92d81c8819\
+              28e8ab9e08528c8cf2b9fe81305cce0482178272a973c0c18012a6768794d7f1\
+              dc49a8410b1fc27acf1025cb24626bf35065be5d247dfbb0dd49aa261a6fe861\
+              82e6e1e68cf441f4ad9946948ad3d6df3d1d1283a92e7c9e858d71a1635343f6"),
+            (SHA512,
+             "5a4cb1b0d52fae2e856bcc0ffcdd56f7f69638c5987237ae8324da0dd2e3d0f0\
+              cd731edc6e16b9088fa0f3b96b72d90ad50732ab55b81daaa9bb6771728dbe0d\
+              c311f46643b5d4782d2d3e50c09ace3ce18cf8d2f9645141fbe8cbb13bb3c7fc\
+              dd0d5e560e3cadc50d4ef0bdc25bc6fe440add0a4dcaaa282cd734f677cac8bc"),
+            (SHA224,
+             "83e1a7a60317cdb9c0d9d049e893c0ae404849fdc27b5c794742d10e95218eb5\
+              8ed8ad9501740f0d9bb48923467d933181d362d89535cb0ac7d1fe65e73fa183\
+              c3ba471b2de940eae93d88afc484c7be1bba4bd6933711faf6634de303b24562\
+              58007cbc6740a799e53f93cd689f6684c4d02be44eb468f229229593c038c01c"),
+            (SHA3_256,
+             "6b2c54445da668bce677c7d0a9be3341fc695882855a9ce9fd611eca1b05b440\
+              06cd9351df1f20fc8e7d0000a24366600816af48910b0d01c7a3f64bcf33a35c\
+              da85b55f1ba77e4c0fb10042a7830e84d643fa76198e5a7f9c94a76b7fed6445\
+              d4dcdd785c24e6e58186bf5a3756900e4072f460282d38622e3f3cfcff871612"),
+            (SHA512_256,
+             "4635f603c07ddc93689be6253f4bbc5f15df35af144c5e34961bc252f2c62004\
+              dbccf2bb6975b230ebd1b0184e26faebb0fb1901aea6841d7ad4a4ac805657e8\
+              205cc743b06fc5ef70380a422f8e5a191ac1c40a2827125f778f3e05a60a1663\
+              a05a78eedac40bddb8262591c76115eb617ff977bc9b8805e9f6b0650b3bb4fb"),
+        ];
+        for &(h, want) in tests.iter() {
+            let hashed = hash(h, msg);
+            let sig = sign_pkcs1v15(&mut Reader, &key, h, hashed).unwrap();
+            assert_eq!(sig.as_slice().to_hex(), want.to_owned());
+            assert_eq!(verify_pkcs1v15(key.public(), h, hashed, sig), Ok(()));
+        }
+
+        let hashed = hash(MD5SHA1, msg);
+        let sig = sign_pkcs1v15(&mut Reader, &key, MD5SHA1, hashed).unwrap();
+        assert_eq!(verify_pkcs1v15(key.public(), MD5SHA1, hashed, sig), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_invalid() {
+        let key = test_key();
+        let hashed = hash(SHA256, bytes!("hello, world"));
+        let sig = sign_pkcs1v15(&mut Reader, &key, SHA256, hashed).unwrap();
+        let err = Err(ErrVerification.to_owned());
+
+        // wrong message and wrong hash function
+        let other = hash(SHA256, bytes!("hello, world!"));
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, other, sig), err);
+        assert_eq!(verify_pkcs1v15(key.public(), SHA3_256, hashed, sig), err);
+
+        // tampered, truncated and zero-extended signatures
+        for i in range(0, sig.len()) {
+            let mut bad = sig.clone();
+            bad[i] ^= 1;
+            assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, bad), err);
+        }
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, sig.slice_from(1)), err);
+        let mut long = ~[0u8];
+        long.push_all(sig);
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, long), err);
+
+        // s = 0, s = 1, s = n-1 and s = n
+        let zero = [0u8, ..128];
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, zero), err);
+        let mut one = [0u8, ..128];
+        one[127] = 1;
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, one), err);
+        let n = key.public().n.bytes();
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, n), err);
+        let mut nm1 = n.clone();
+        nm1[127] -= 1;
+        assert_eq!(verify_pkcs1v15(key.public(), SHA256, hashed, nm1), err);
+
+        // bad input lengths and unsupported hashes
+        assert!(sign_pkcs1v15(&mut Reader, &key, SHA256, hashed.slice_from(1)).is_err());
+        assert!(verify_pkcs1v15(key.public(), SHA1, hashed, sig).is_err());
+        assert!(sign_pkcs1v15(&mut Reader, &key, BLAKE2b_256, hashed).is_err());
+    }
+
+    #[test]
+    fn test_verify_encodings() {
+        // Malformed encodings of the kind Project Wycheproof's
+        // rsa_signature tests check for, signed with the raw private key
+        // operation and cross-checked against Python's cryptography.
+        let key = test_key();
+        let hashed = hash(SHA256, bytes!("hello, world"));
+        let tests = [
+            ("valid", true,
+             "3a489af70a7c109856bf7d1e9bc062a3291af720b981ea682d9f5887d55b4849\
+              d2eed1fb3e82445cfaed0b4edd321e52050e24644600dc3a68a0f7b3cf916fca\
+              47c55ca665145380cd8991f628c1f3231a3930380461f286a6f72c5f83f4918b\
+              308954e8e32fd36774730239d7069601f832cfe54e4cfe9ba28ee6608181b2fb"),
+            ("DigestInfo without NULL parameters", false,
+             "6689a5c9640cf1dabcd1a0713554d0f3c9732bf26f276ad33258b6892a0383f7\
+              ce615e5e841e8edcb17255d95169edbe3ad00ff2175b2878baf1a1e286886c9f\
+              e100524ead82c81c1be6a335b35b8c50e6553349e685530e35a87e254914dd03\
+              e5b06c154d70c87713578751e4351eeb8cec2d73c5fb8c1f74e5ef926357b977"),
+            ("long form length in DigestInfo", false,
+             "42628eb46b26b170c7ab5b077a20216d83493398d24d20a180458556a3fff71d\
+              a9af1f9de682e8ff09f5550a5d87524deb6286cfbffbc62481f93063f3993fdc\
+              d13e60b515d31835e4ad117e6852455909569d7f0e7d467cfdc141d767379d50\
+              f3ed5479f3e0f19ab901eb6bcd4b3054730a366476faf0b7b761e456f63ae6ce"),
+            ("SHA-384 OID", false,
+             "4ca94cd948e168f4fba4ea68dd8f30191d4a7baeaa7c72123c292886aa20a62e\
+              6d836488d4b3657ffdc3c4f390bbe540f081ec415823c440896e88f0f0ca10e5\
+              9e86733ff5b1650f83c39bc76cf27a2ce7c8cc84cea3f8ce408f6d69e7f9c320\
+              9177fe79315f0f8f25fafa8e68e0cfde0548360f49572dc702743f81327c60a6"),
+            ("trailing byte after DigestInfo", false,
+             "07b8bc19d2b8d79e27f5c5e044c5f77ae077411fea863e1f73d24200bd6f8025\
+              5f01423781d52e951aa14334ca7564d52aff094f042eae66bba0b0fcecc04264\
+              cc0848e68fd5922ee6fc01d06eec118d39823df20067b857a8f5ae688590ccfc\
+              992cc87f9994ecc11853e949ee4c9d1f0b92f346ec6a2c0d5aa429fd5dd4c909"),
+            ("padding with 0x00 instead of 0xff", false,
+             "0224890a8c61e669e0d260a37af9daa13465ff19d85db79b9c697c54fff8f8bf\
+              42d42cebfe22a960bbb20a1eca75ed138b772f5b30be22c518fbd8a31f038e8e\
+              d15e164a8886d10a10fcd133927304c745ed57f162d7df6143482b574ffa1787\
+              247ac005658f525c17e71d872b8ac43209d641cec6857a9dbc5bf6f68650cf36"),
+            ("block type 2", false,
+             "6d4f667778422d8ecc20e53d3abd40d97e2ef8bcd86afb43821974ec9b57069d\
+              ddaffeb6496cfdb5d8b09a1a423ae3b4ff2b93e95592cd8d9d7b51ab0727f524\
+              ca19b2775dffb50eacf635d0fe534100cd013f00ccba8da179f42b5f4502f70f\
+              444947abc36f8658df0f00e852b98f6d0eb118ae6120c1673c52fca924a3deaf"),
+            ("no zero separator", false,
+             "7d2aa36b96af2cd0f7f9f1991f998290a169095f4cb56b979982261f5576ad19\
+              4bb73de75424e0f8d52c3c8e8949716fa844e61d0764231087877ecae78ff6c4\
+              dbcf210496259cde4256b08d63af404d1a1ac8cafae67c901520509142c037a3\
+              5782be6505dc442ee61c7fce2a79c722ca647387cbf6b27b263c7944e7e6080f"),
+            ("first byte 1", false,
+             "8ac33aefa65a7d01ef13cf800685401b095f14e630da308c510f538ddd25677c\
+              1e729c6fe0f57eb2bbfd399ed7a026c2ee5236acdcfdc331172a31835ecf0351\
+              fb9a0acb1d1be110c04f4ba01cc13943cbeb84c2cdc2aac250ffe30368c38762\
+              6f818323d894b7c85e92970674d5f02ea66a45fc39a27fb9f4c33a2f4750a00a"),
+            ("short padding", false,
+             "1baf350814dee9a110da2c45eac2303883aa1deb95176d9fc5d8c41540912bcb\
+              ebe095d9b4af01934d75dd7a708344b70ca2e82219329ebf8b349542671d454d\
+              af497061bbc85897050e620b9fb363898f69b29694ed479012f6fb5a6d8becde\
+              44bed47c63fcb86ee368751b8b9d1a24fd1c46aaaff44c5981b22279cc1392d4"),
+            ("digest of another message", false,
+             "4d9d25e4f3c9c93fb53524167a4fa7beacdb6c1f36f07145ee1a74d0f8ccc836\
+              04bf0a3127d0480f784d522b04fe58d80c1e1e9fa81429f9e398aa096510d978\
+              d66c13c8a9fc69152d2ebe03f6d1c63e60fea51b9d14754b26b60ea3fbd7592a\
+              9e6e9b1ba46d4ef55b6aa4638e4cc2d8a09de6baa833809e55ee532a74815adb"),
+        ];
+        for &(comment, valid, sig) in tests.iter() {
+            let sig = sig.from_hex().unwrap();
+            let res = verify_pkcs1v15(key.public(), SHA256, hashed, sig);
+            assert!(res.is_ok() == valid, "{}: {}", comment, res);
+        }
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let key = test_key();
+        // encrypted by OpenSSL
+        let ct = "6b90db113002835c88e99179c57a68a6b07ae5d6c765502731d0d4a24ddab615\
+                  570662c756c04f7ee0611aab7d44381ba229f666b220e940f4e5d65ef070d3e9\
+                  4eb899c981c0e50cd1e2c58b4e8cb1f854cdf2fe85b3ed90770ea8591f542408\
+                  203760620cf6e820517fc0b34bf9a8b6da02bb7821b19ed703fb859b866e4016"
+                  .from_hex().unwrap();
+        let pt = decrypt_pkcs1v15(&mut Reader, &key, ct).unwrap();
+        assert_eq!(pt.as_slice(), bytes!("hello, world"));
+
+        let mut bad = ct.clone();
+        bad[5] ^= 0x80;
+        assert_eq!(decrypt_pkcs1v15(&mut Reader, &key, bad).err(), Some(ErrDecryption.to_owned()));
+        assert!(decrypt_pkcs1v15(&mut Reader, &key, key.public().n.bytes()).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = test_key();
+        for &len in [0u, 1, 16, 128 - 11].iter() {
+            let msg = Vec::from_elem(len, 0xa5u8);
+            let ct = encrypt_pkcs1v15(&mut Reader, key.public(), msg.as_slice()).unwrap();
+            assert_eq!(ct.len(), 128);
+            let pt = decrypt_pkcs1v15(&mut Reader, &key, ct).unwrap();
+            assert_eq!(pt.as_slice(), msg.as_slice());
+        }
+        assert_eq!(encrypt_pkcs1v15(&mut Reader, key.public(), [0u8, ..128 - 10]).err(),
+                   Some(ErrMessageTooLong.to_owned()));
+    }
+
+    #[test]
+    fn test_decrypt_session_key() {
+        let key = test_key();
+        let ct = encrypt_pkcs1v15(&mut Reader, key.public(), [7u8, ..16]).unwrap();
+        let mut session_key = [1u8, ..16];
+        decrypt_pkcs1v15_session_key(&mut Reader, &key, ct, session_key).unwrap();
+        assert_eq!(session_key.as_slice(), [7u8, ..16].as_slice());
+
+        // a plaintext of the wrong length leaves the key untouched
+        let ct = encrypt_pkcs1v15(&mut Reader, key.public(), [7u8, ..15]).unwrap();
+        let mut session_key = [1u8, ..16];
+        decrypt_pkcs1v15_session_key(&mut Reader, &key, ct, session_key).unwrap();
+        assert_eq!(session_key.as_slice(), [1u8, ..16].as_slice());
+
+        // as does a ciphertext with broken padding
+        let mut bad = ct.clone();
+        bad[127] ^= 0x01;
+        decrypt_pkcs1v15_session_key(&mut Reader, &key, bad, session_key).unwrap();
+        assert_eq!(session_key.as_slice(), [1u8, ..16].as_slice());
+
+        let mut too_long = [0u8, ..128 - 10];
+        assert!(decrypt_pkcs1v15_session_key(&mut Reader, &key, ct, too_long).is_err());
+    }
+}
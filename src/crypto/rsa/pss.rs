@@ -0,0 +1,490 @@
+// RSASSA-PSS signatures, as specified in RFC 8017 section 8.1, after Go's
+// crypto/rsa/pss.go.
+
+use std::any::Any;
+use std::slice;
+use hash::Hash;
+use math::big::Int;
+use super::super::{HashFunc, SignerOpts};
+use super::super::subtle::constant_time_compare;
+use super::{PublicKey, PrivateKey, ErrVerification, ErrMessageTooLong};
+use super::{check_pub, encrypt, decrypt_and_check, mgf1_xor};
+
+/// Causes the salt in a PSS signature to be as large as possible when
+/// signing, and to be auto-detected when verifying.
+pub static PSSSaltLengthAuto: int = 0;
+
+/// Causes the salt length to equal the length of the hash used in the
+/// signature.
+pub static PSSSaltLengthEqualsHash: int = -1;
+
+/// Options for creating and verifying PSS signatures.
+#[deriving(Clone)]
+pub struct PSSOptions {
+    /// The length of the salt used in the PSS signature. It is either a
+    /// positive number of bytes or one of the special PSSSaltLength
+    /// constants.
+    pub salt_length: int,
+    /// The hash function used to generate the message digest. If not
+    /// None, it overrides the hash function passed to sign_pss.
+    pub hash: Option<HashFunc>,
+}
+
+impl SignerOpts for PSSOptions {
+    fn hash_func(&self) -> Option<HashFunc> {
+        self.hash
+    }
+
+    fn as_any<'a>(&'a self) -> &'a Any {
+        self as &Any
+    }
+}
+
+impl PSSOptions {
+    fn salt_length(opts: Option<&PSSOptions>) -> int {
+        match opts {
+            Some(o) => o.salt_length,
+            None => PSSSaltLengthAuto,
+        }
+    }
+}
+
+fn emsa_pss_encode(mhash: &[u8], em_bits: uint, salt: &[u8], hash: &mut ~Hash)
+                   -> Result<~[u8], ~str> {
+    // See RFC 8017, Section 9.1.1.
+
+    let hlen = hash.size();
+    let slen = salt.len();
+    let em_len = (em_bits + 7) / 8;
+
+    // 1.  If the length of M is greater than the input limitation for the
+    //     hash function (2^61 - 1 octets for SHA-1), output "message too
+    //     long" and stop.
+    //
+    // 2.  Let mHash = Hash(M), an octet string of length hLen.
+
+    if mhash.len() != hlen {
+        return Err(~"crypto/rsa: input must be hashed with given hash");
+    }
+
+    // 3.  If emLen < hLen + sLen + 2, output "encoding error" and stop.
+
+    if em_len < hlen + slen + 2 {
+        return Err(ErrMessageTooLong.to_owned());
+    }
+
+    let mut em = slice::from_elem(em_len, 0u8);
+    let ps_len = em_len - slen - hlen - 2;
+    {
+        let (db, rest) = em.mut_split_at(ps_len + 1 + slen);
+
+        // 4.  Generate a random octet string salt of length sLen; if sLen
+        //     = 0, then salt is the empty string.
+        //
+        // 5.  Let
+        //       M' = (0x)00 00 00 00 00 00 00 00 || mHash || salt;
+        //
+        //     M' is an octet string of length 8 + hLen + sLen with eight
+        //     initial zero octets.
+        //
+        // 6.  Let H = Hash(M'), an octet string of length hLen.
+
+        hash.reset();
+        hash.write([0u8, ..8]).unwrap();
+        hash.write(mhash).unwrap();
+        hash.write(salt).unwrap();
+        let h = hash.digest();
+        rest.mut_slice_to(hlen).copy_from(h);
+
+        // 7.  Generate an octet string PS consisting of emLen - sLen - hLen
+        //     - 2 zero octets. The length of PS may be 0.
+        //
+        // 8.  Let DB = PS || 0x01 || salt; DB is an octet string of length
+        //     emLen - hLen - 1.
+
+        db[ps_len] = 1;
+        db.mut_slice_from(ps_len + 1).copy_from(salt);
+
+        // 9.  Let dbMask = MGF(H, emLen - hLen - 1).
+        //
+        // 10. Let maskedDB = DB \xor dbMask.
+
+        mgf1_xor(db, hash, h);
+
+        // 11. Set the leftmost 8 * emLen - emBits bits of the leftmost
+        //     octet in maskedDB to zero.
+
+        db[0] &= 0xff >> (8 * em_len - em_bits);
+
+        // 12. Let EM = maskedDB || H || 0xbc.
+        rest[hlen] = 0xbc;
+    }
+
+    // 13. Output EM.
+    Ok(em)
+}
+
+fn emsa_pss_verify(mhash: &[u8], em: &mut [u8], em_bits: uint, salt_length: int,
+                   hash: &mut ~Hash) -> Result<(), ~str> {
+    // See RFC 8017, Section 9.1.2.
+
+    let hlen = hash.size();
+    let slen = if salt_length == PSSSaltLengthEqualsHash {
+        Some(hlen)
+    } else if salt_length == PSSSaltLengthAuto {
+        None
+    } else {
+        Some(salt_length as uint)
+    };
+    let em_len = (em_bits + 7) / 8;
+    if em_len != em.len() {
+        return Err(~"crypto/rsa: internal error: inconsistent length");
+    }
+
+    // 1.  If the length of M is greater than the input limitation for the
+    //     hash function (2^61 - 1 octets for SHA-1), output "inconsistent"
+    //     and stop.
+    //
+    // 2.  Let mHash = Hash(M), an octet string of length hLen.
+    if hlen != mhash.len() {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // 3.  If emLen < hLen + sLen + 2, output "inconsistent" and stop.
+    if em_len < hlen + slen.unwrap_or(0) + 2 {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // 4.  If the rightmost octet of EM does not have hexadecimal value
+    //     0xbc, output "inconsistent" and stop.
+    if em[em_len - 1] != 0xbc {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // 5.  Let maskedDB be the leftmost emLen - hLen - 1 octets of EM, and
+    //     let H be the next hLen octets.
+    let (db, rest) = em.mut_split_at(em_len - hlen - 1);
+    let h = rest.slice_to(hlen);
+
+    // 6.  If the leftmost 8 * emLen - emBits bits of the leftmost octet in
+    //     maskedDB are not all equal to zero, output "inconsistent" and
+    //     stop.
+    let bit_mask = 0xffu8 >> (8 * em_len - em_bits);
+    if db[0] & !bit_mask != 0 {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // 7.  Let dbMask = MGF(H, emLen - hLen - 1).
+    //
+    // 8.  Let DB = maskedDB \xor dbMask.
+    mgf1_xor(db, hash, h);
+
+    // 9.  Set the leftmost 8 * emLen - emBits bits of the leftmost octet in
+    //     DB to zero.
+    db[0] &= bit_mask;
+
+    // If we don't know the salt length, look for the 0x01 delimiter.
+    let slen = match slen {
+        Some(slen) => slen,
+        None => match db.iter().position(|&b| b == 0x01) {
+            Some(ps_len) => db.len() - ps_len - 1,
+            None => return Err(ErrVerification.to_owned()),
+        },
+    };
+
+    // 10. If the emLen - hLen - sLen - 2 leftmost octets of DB are not
+    //     zero or if the octet at position emLen - hLen - sLen - 1 (the
+    //     leftmost position is "position 1") does not have hexadecimal
+    //     value 0x01, output "inconsistent" and stop.
+    let ps_len = em_len - hlen - slen - 2;
+    if db.slice_to(ps_len).iter().any(|&b| b != 0) || db[ps_len] != 0x01 {
+        return Err(ErrVerification.to_owned());
+    }
+
+    // 11. Let salt be the last sLen octets of DB.
+    let salt = db.slice_from(db.len() - slen);
+
+    // 12. Let
+    //         M' = (0x)00 00 00 00 00 00 00 00 || mHash || salt ;
+    //     M' is an octet string of length 8 + hLen + sLen with eight
+    //     initial zero octets.
+    //
+    // 13. Let H' = Hash(M'), an octet string of length hLen.
+    hash.reset();
+    hash.write([0u8, ..8]).unwrap();
+    hash.write(mhash).unwrap();
+    hash.write(salt).unwrap();
+    let h0 = hash.digest();
+
+    // 14. If H = H', output "consistent." Otherwise, output "inconsistent."
+    if !constant_time_compare(h0, h) {
+        return Err(ErrVerification.to_owned());
+    }
+    Ok(())
+}
+
+// Calculates the signature of hashed using PSS with the given salt. Note
+// that hashed must be the result of hashing the input message using the
+// given hash function. salt is a random sequence of bytes whose length
+// will be later used to verify the signature.
+fn sign_pss_with_salt<R: Reader>(rand: &mut R, key: &PrivateKey, hash: HashFunc,
+                                 hashed: &[u8], salt: &[u8]) -> Result<~[u8], ~str> {
+    let em_bits = key.public_key.n.bit_len() - 1;
+    let em = try!(emsa_pss_encode(hashed, em_bits, salt, &mut hash.new()));
+
+    // RFC 8017: "Note that the octet length of EM will be one less than k
+    // if modBits - 1 is divisible by 8 and equal to k otherwise, where k
+    // is the length in octets of the RSA modulus n." The big integer
+    // absorbs the missing leading zero.
+    let c = try!(decrypt_and_check(rand, key, &Int::from_bytes(em)));
+    let mut out = slice::from_elem(key.size(), 0u8);
+    c.fill_bytes(out);
+    Ok(out)
+}
+
+/// Calculates the signature of digest using PSS.
+///
+/// digest must be the result of hashing the input message using the
+/// given hash function. The opts argument may be None, in which case
+/// sensible defaults are used. If opts.hash is set, it overrides hash.
+///
+/// The signature is randomized depending on the message, key, and salt
+/// size, using bytes from rand; rand is also used for blinding.
+pub fn sign_pss<R: Reader>(rand: &mut R, key: &PrivateKey, hash: HashFunc, digest: &[u8],
+                           opts: Option<&PSSOptions>) -> Result<~[u8], ~str> {
+    let hash = match opts {
+        Some(&PSSOptions { hash: Some(h), .. }) => h,
+        _ => hash,
+    };
+
+    let salt_length = match PSSOptions::salt_length(opts) {
+        s if s == PSSSaltLengthAuto => {
+            let em_len = (key.public_key.n.bit_len() - 1 + 7) / 8;
+            if em_len < 2 + hash.size() {
+                return Err(ErrMessageTooLong.to_owned());
+            }
+            em_len - 2 - hash.size()
+        }
+        s if s == PSSSaltLengthEqualsHash => hash.size(),
+        s if s > 0 => s as uint,
+        _ => return Err(~"crypto/rsa: PSSOptions.salt_length cannot be negative"),
+    };
+
+    let mut salt = slice::from_elem(salt_length, 0u8);
+    match rand.fill(salt) {
+        Ok(()) => {}
+        Err(e) => return Err(format!("crypto/rsa: {}", e)),
+    }
+    sign_pss_with_salt(rand, key, hash, digest, salt)
+}
+
+/// Verifies a PSS signature.
+///
+/// A valid signature is indicated by returning Ok(()). digest must be the
+/// result of hashing the input message using the given hash function.
+/// The opts argument may be None, in which case the salt length is
+/// auto-detected. opts.hash is ignored.
+pub fn verify_pss(key: &PublicKey, hash: HashFunc, digest: &[u8], sig: &[u8],
+                  opts: Option<&PSSOptions>) -> Result<(), ~str> {
+    try!(check_pub(key));
+    if sig.len() != key.size() {
+        return Err(ErrVerification.to_owned());
+    }
+    // Salt length must be either one of the special constants (-1 or 0)
+    // or else positive.
+    let salt_length = PSSOptions::salt_length(opts);
+    if salt_length < PSSSaltLengthEqualsHash {
+        return Err(~"crypto/rsa: PSSOptions.salt_length cannot be negative");
+    }
+
+    let c = Int::from_bytes(sig);
+    if c >= key.n {
+        return Err(ErrVerification.to_owned());
+    }
+    let em_bits = key.n.bit_len() - 1;
+    let em_len = (em_bits + 7) / 8;
+    let mut em = slice::from_elem(key.size(), 0u8);
+    encrypt(key, &c).fill_bytes(em);
+
+    // Like in sign_pss_with_salt, deal with mismatches between em_len and
+    // the size of the modulus.
+    let start = em.len() - em_len;
+    if start > 0 && em[0] != 0 {
+        return Err(ErrVerification.to_owned());
+    }
+    emsa_pss_verify(digest, em.mut_slice_from(start), em_bits, salt_length, &mut hash.new())
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use hash::Hash;
+    use super::super::*;
+    use super::super::test::test_key;
+    use super::super::super::{HashFunc, SHA1, SHA256, SHA384};
+    use super::super::super::rand::Reader;
+
+    fn hash(h: HashFunc, msg: &[u8]) -> ~[u8] {
+        let mut d = h.new();
+        d.write(msg).unwrap();
+        d.digest()
+    }
+
+    #[test]
+    fn test_sign_with_salt() {
+        // checked with Python's cryptography
+        let key = test_key();
+        let hashed = hash(SHA256, bytes!("hello, world"));
+        let salt = Vec::from_fn(32, |i| i as u8);
+        let sig = super::sign_pss_with_salt(&mut Reader, &key, SHA256, hashed,
+                                            salt.as_slice()).unwrap();
+        assert_eq!(sig.as_slice().to_hex(),
+                   ~"614fec5db2921e4476a0b0af0ae52b8ddecb2c1e813821b3a9eb5f83073f782a\
+                     e8695b007f1c7f8b8b5cb0fff1fc3886591a129e89e736c58f17d89b64917770\
+                     e7d5a05495bad3f7ce30bf9f17b200fff6e55d32996d67c4a21b4bc5f55e8268\
+                     0a6cb3340c8bd87df1102c1dfda28237732a608e11c38853233641e65ce487bd");
+        let opts = PSSOptions { salt_length: 32, hash: None };
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&opts)), Ok(()));
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, None), Ok(()));
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = test_key();
+        let hashed = hash(SHA256, bytes!("hello, world"));
+        let err = Err(ErrVerification.to_owned());
+        let auto = PSSOptions { salt_length: PSSSaltLengthAuto, hash: None };
+        let eq_hash = PSSOptions { salt_length: PSSSaltLengthEqualsHash, hash: None };
+
+        // signed by OpenSSL with the maximum salt length
+        let sig = "2c35c384df8732b0cae32547509b55b77b1b2805104b7a7c56f924333f2040fa\
+                   bb518f65a5b9d7dbc18fbf72065ca48f9baee042ca096d6275af15316f9716bd\
+                   fb148877e351d57856625baaa06b9c96539a597eb44a5b58d1cb63570a67e027\
+                   741d920991abde7713b35b6935c0a0a61e7362ca15fa9190c71803f485c2715b"
+                  .from_hex().unwrap();
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, None), Ok(()));
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&auto)), Ok(()));
+        let max = PSSOptions { salt_length: 128 - 2 - 32, hash: None };
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&max)), Ok(()));
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&eq_hash)), err);
+
+        // signed by OpenSSL with a 32-byte salt
+        let sig = "117e53a9d59b597e4ac4b3468e8d001333bc080db006bcba58de7f3a43ec679e\
+                   cbd8893643caf8b8ce518ed479905cd526d2ee003c134adea8781bb1ff587c26\
+                   9a661998b43f5fd96075992ba5a9372f3d1c2dbd15e969aba37da21521fc5aea\
+                   9e075a4c3b7466ebc485b5ae33fb8bf99c7817d6d567961562c13fe31140a4f9"
+                  .from_hex().unwrap();
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, None), Ok(()));
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&eq_hash)), Ok(()));
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig, Some(&max)), err);
+
+        // wrong digest, wrong hash, tampered and badly sized signatures
+        let other = hash(SHA256, bytes!("hello, world!"));
+        assert_eq!(verify_pss(key.public(), SHA256, other, sig, None), err);
+        assert_eq!(verify_pss(key.public(), SHA1, hash(SHA1, bytes!("hello, world")), sig, None),
+                   err);
+        for &i in [0u, 1, 64, 94, 95, 96, 126, 127].iter() {
+            let mut bad = sig.clone();
+            bad[i] ^= 0x10;
+            assert_eq!(verify_pss(key.public(), SHA256, hashed, bad, None), err);
+        }
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, sig.slice_to(127), None), err);
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, key.public().n.bytes(), None), err);
+        assert_eq!(verify_pss(key.public(), SHA256, hashed, [0u8, ..128], None), err);
+
+        let negative = PSSOptions { salt_length: -2, hash: None };
+        assert!(verify_pss(key.public(), SHA256, hashed, sig, Some(&negative)).is_err());
+    }
+
+    #[test]
+    fn test_verify_encodings() {
+        // Malformed encodings of the kind Project Wycheproof's rsa_pss
+        // tests check for (SHA-256, MGF1 with SHA-256), signed with the raw
+        // private key operation and cross-checked against Python's
+        // cryptography.
+        let key = test_key();
+        let hashed = hash(SHA256, bytes!("hello, world"));
+        let tests = [
+            ("valid", true,
+             "614fec5db2921e4476a0b0af0ae52b8ddecb2c1e813821b3a9eb5f83073f782a\
+              e8695b007f1c7f8b8b5cb0fff1fc3886591a129e89e736c58f17d89b64917770\
+              e7d5a05495bad3f7ce30bf9f17b200fff6e55d32996d67c4a21b4bc5f55e8268\
+              0a6cb3340c8bd87df1102c1dfda28237732a608e11c38853233641e65ce487bd"),
+            ("valid, empty salt", true,
+             "1233f2839f969e795f48665b94247d7cb80f88c0834574b46120dbbc70a2289c\
+              b96fcdc4b45ddbf5a8a80c6a4074cbfe4df21df89efb847c5a59ffd66b4cc22e\
+              f9d2465a6d2fd154703a6463e7c59329e26ea4b672b06f3809112d2043983eb9\
+              96bc7d2d7427d14c50c0f2687e066ff1277cd78bd709c995d66a8734694b979c"),
+            ("trailer byte 0xbd", false,
+             "7ecb09e6a8bea4e3f3551b4b9d64a934c885724a4e51631161a2f091f46e8296\
+              1595d1aa3e03633be3c2ab0ad9be0fdd08fa300b6ab4f7c7f5239f903cfc3ac6\
+              9dc6eb47207cc049f92d80426b692fd7d53a3fbce64c150c83d97baf7730de59\
+              dbeb2c9aa216f239eaa66e84d56da1dc454b258f6451a361f79df252f4b7d51d"),
+            ("leftmost bit set", false,
+             "0eca09adb31a23f0cfe35422653ce3a40502bbe7f6fdc443e1750599823ac3ab\
+              f6cc36478e2c135e4918f0347d2044e472727017dd7ccec793af4696fda30e55\
+              6f788f6e3d3bfad79cd303482e4d50af6495a1ea12598a98da99bccf75287cc3\
+              a834edf0b1de6db6d43a23e958b1c0d579a146f26fa66a627e9b911ab96ec66b"),
+            ("separator 0x02", false,
+             "5994b67435d6e8f46dddbf7950866937543689df138b45e94403a324293fb7c7\
+              380d1bc3a649e43a330e4957d131598080128719fc808e1085419fd386ea6cfe\
+              0942a3845debf0d2b87ec3d3cb3cd0e34a5e03599dd813b84ab364c12cabf0c7\
+              41c896266a106c96f441bce5db1fdb6b614e11976a1ff3987cf773da24380645"),
+            ("nonzero padding byte", false,
+             "1bd81751c16b79e20c25ef20ca20c2a5ecc2d1561c39ff889d440b66ed2eaf33\
+              00ee793ed7a291eca2fb844855b30051a4efd84f6d2e4df9bc8a7ec1835b15ab\
+              3f15029d2bb62cf938ffbf3b76ff89689632963572b4555e924e1a961b9aca96\
+              02ae4c874de1b22fb9b78cd59e4e30e594a15fdeccdcba182645d4a0a6d98216"),
+            ("modified hash", false,
+             "196103d309e90dbb230f11b3d86614e512e60b273a639a6c72965a8f91de252e\
+              25145891f3f80b696118015b51efe0359445feeeeba6a1678d95581826223ef0\
+              0457dabd9079334b9a0aeae3f6f548879c4de203da8bc979f37a2c88859a2478\
+              6e2dc3fc164432cd196c1b991685358f42972bc264bdfe86421eac05e2c16ff4"),
+        ];
+        for &(comment, valid, sig) in tests.iter() {
+            let sig = sig.from_hex().unwrap();
+            let res = verify_pss(key.public(), SHA256, hashed, sig, None);
+            assert!(res.is_ok() == valid, "{}: {}", comment, res);
+        }
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        // A modulus whose bit length is 1 mod 8 has an encoded message one
+        // byte shorter than the signature.
+        let keys = [test_key(), generate_key(&mut Reader, 513).unwrap()];
+        for key in keys.iter() {
+            for &h in [SHA1, SHA256].iter() {
+                let hashed = hash(h, bytes!("testing"));
+                for &salt_length in [PSSSaltLengthAuto, PSSSaltLengthEqualsHash, 1, 20].iter() {
+                    let opts = PSSOptions { salt_length: salt_length, hash: None };
+                    let sig = sign_pss(&mut Reader, key, h, hashed, Some(&opts)).unwrap();
+                    assert_eq!(sig.len(), key.size());
+                    assert_eq!(verify_pss(key.public(), h, hashed, sig, Some(&opts)), Ok(()));
+                    assert_eq!(verify_pss(key.public(), h, hashed, sig, None), Ok(()));
+                }
+            }
+        }
+
+        // opts.hash overrides the hash argument
+        let key = test_key();
+        let hashed = hash(SHA384, bytes!("testing"));
+        let opts = PSSOptions { salt_length: PSSSaltLengthEqualsHash, hash: Some(SHA384) };
+        let sig = sign_pss(&mut Reader, &key, SHA1, hashed, Some(&opts)).unwrap();
+        assert_eq!(verify_pss(key.public(), SHA384, hashed, sig, None), Ok(()));
+
+        // signatures are randomized
+        let hashed = hash(SHA256, bytes!("testing"));
+        let a = sign_pss(&mut Reader, &key, SHA256, hashed, None).unwrap();
+        let b = sign_pss(&mut Reader, &key, SHA256, hashed, None).unwrap();
+        assert!(a != b);
+
+        // the salt must fit
+        let too_long = PSSOptions { salt_length: 128 - 2 - 32 + 1, hash: None };
+        assert_eq!(sign_pss(&mut Reader, &key, SHA256, hashed, Some(&too_long)).err(),
+                   Some(ErrMessageTooLong.to_owned()));
+        let negative = PSSOptions { salt_length: -2, hash: None };
+        assert!(sign_pss(&mut Reader, &key, SHA256, hashed, Some(&negative)).is_err());
+    }
+}
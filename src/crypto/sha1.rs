@@ -4,6 +4,8 @@ use std::ptr;
 use std::cast;
 use std::slice;
 use super::*;
+use super::cpu;
+use super::multibuf;
 use super::super::hash::*;
 
 pub static Size: uint = 20;
@@ -23,12 +25,95 @@ static _K1 : u32 = 0x6ED9EBA1;
 static _K2 : u32 = 0x8F1BBCDC;
 static _K3 : u32 = 0xCA62C1D6;
 
+static bswapMask: [u8, ..16] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+
+// The disturbance vectors of the collision detection, from Marc Stevens and
+// Dan Shumow's sha1collisiondetection (MIT licence): the type (I or II), K
+// and b of DV_type(K, b), and the step whose saved state recompression
+// starts from. Bit i of the ubc_check mask stands for dvs[i].
+static dvs: [(u8, u8, u8, u8), ..32] = [
+    (1, 43, 0, 58), (1, 44, 0, 58), (1, 45, 0, 58), (1, 46, 0, 58), (1, 46, 2, 58), (1, 47, 0, 58),
+    (1, 47, 2, 58), (1, 48, 0, 58), (1, 48, 2, 58), (1, 49, 0, 58), (1, 49, 2, 58), (1, 50, 0, 65),
+    (1, 50, 2, 65), (1, 51, 0, 65), (1, 51, 2, 65), (1, 52, 0, 65), (2, 45, 0, 58), (2, 46, 0, 58),
+    (2, 46, 2, 58), (2, 47, 0, 58), (2, 48, 0, 58), (2, 49, 0, 58), (2, 49, 2, 58), (2, 50, 0, 65),
+    (2, 50, 2, 65), (2, 51, 0, 65), (2, 51, 2, 65), (2, 52, 0, 65), (2, 53, 0, 65), (2, 54, 0, 65),
+    (2, 55, 0, 65), (2, 56, 0, 65),
+];
+
+// The unavoidable bit conditions of the same library, as (word, bit, word,
+// bit, value, mask): when the two bits of the expanded message xor to
+// value, no DV in mask can be the one the block follows.
+static ubcConds: [(u8, u8, u8, u8, u8, u32), ..156] = [
+    (35, 1, 36, 6, 0, 0x410), (35, 3, 39, 28, 1, 0x82000), (35, 4, 39, 29, 1, 0x80084),
+    (35, 5, 39, 30, 1, 0x4000), (35, 30, 40, 28, 0, 0x100000), (36, 0, 41, 30, 0, 0x400000),
+    (36, 1, 37, 6, 0, 0x41040), (36, 3, 40, 28, 1, 0x100000), (36, 4, 37, 4, 0, 0x800),
+    (36, 4, 38, 4, 0, 0x28000000), (36, 4, 40, 29, 1, 0x110208), (36, 30, 41, 28, 0, 0x200000),
+    (37, 0, 42, 30, 0, 0x1000000), (37, 1, 37, 6, 1, 0x4000), (37, 1, 38, 6, 0, 0x4100),
+    (37, 3, 41, 28, 1, 0x200000), (37, 4, 38, 4, 0, 0x2000), (37, 4, 39, 4, 0, 0x50000001),
+    (37, 4, 40, 29, 1, 0x50020021), (37, 4, 41, 29, 1, 0x200800), (37, 5, 41, 30, 1, 0x400000),
+    (37, 30, 42, 28, 0, 0x800000), (38, 0, 43, 30, 0, 0x4000000), (38, 1, 40, 1, 0, 0x400),
+    (38, 3, 42, 28, 1, 0x800000), (38, 4, 39, 4, 0, 0x8000), (38, 4, 40, 4, 0, 0xa0000002),
+    (38, 4, 41, 29, 1, 0xa0080082), (38, 4, 42, 29, 1, 0x802000), (38, 5, 42, 30, 1, 0x1000000),
+    (38, 30, 43, 28, 0, 0x2000000), (39, 1, 40, 6, 0, 0x401010), (39, 3, 43, 28, 1, 0x2000000),
+    (39, 4, 41, 4, 0, 0x40000005), (39, 4, 42, 29, 1, 0x40100205), (39, 4, 43, 29, 1, 0x2008000),
+    (39, 5, 43, 30, 1, 0x4000000), (39, 6, 40, 1, 1, 0x400), (39, 30, 44, 28, 0, 0x8000000),
+    (40, 1, 41, 6, 0, 0x1004040), (40, 3, 44, 28, 1, 0x8000000), (40, 4, 42, 4, 0, 0x8000000a),
+    (40, 4, 43, 29, 1, 0x8020080a), (40, 4, 44, 29, 1, 0x8000000), (40, 6, 41, 1, 1, 0x401000),
+    (40, 6, 42, 6, 1, 0x10), (40, 29, 41, 29, 1, 0x800a00a2), (41, 1, 42, 6, 0, 0x4040100),
+    (41, 3, 45, 28, 1, 0x10000000), (41, 4, 44, 29, 1, 0x812025), (41, 4, 45, 29, 1, 0x10000000),
+    (41, 6, 42, 1, 1, 0x1004000), (41, 6, 43, 6, 1, 0x40), (41, 29, 42, 29, 1, 0x180284),
+    (42, 1, 50, 1, 0, 0x400), (42, 3, 46, 28, 1, 0x20000000), (42, 4, 45, 29, 1, 0x202808a),
+    (42, 4, 46, 29, 1, 0x20000000), (42, 6, 43, 1, 1, 0x4040000), (42, 6, 44, 6, 1, 0x110),
+    (42, 29, 43, 29, 1, 0x300a08), (43, 1, 51, 1, 0, 0x1000), (43, 3, 47, 28, 1, 0x40000000),
+    (43, 4, 46, 29, 1, 0x8080225), (43, 4, 47, 29, 1, 0x40000000), (43, 6, 45, 6, 1, 0x440),
+    (43, 29, 44, 29, 1, 0xa12820), (44, 1, 45, 6, 0, 0x404000), (44, 3, 48, 28, 1, 0x80000000),
+    (44, 4, 47, 29, 1, 0x1010088a), (44, 4, 48, 29, 1, 0x80000000), (44, 6, 46, 6, 1, 0x1110),
+    (44, 29, 45, 29, 1, 0x283a080), (44, 29, 46, 29, 0, 0x25), (45, 1, 47, 1, 0, 0x1000000),
+    (45, 4, 48, 29, 1, 0x20202224), (45, 6, 46, 1, 1, 0x400000), (45, 6, 47, 6, 1, 0x4440),
+    (45, 29, 46, 29, 1, 0xa0a8200), (45, 29, 47, 29, 0, 0x8a), (46, 1, 48, 1, 0, 0x4000000),
+    (46, 4, 49, 29, 1, 0x40808888), (46, 6, 47, 1, 1, 0x1000010), (46, 6, 48, 6, 1, 0x1100),
+    (46, 29, 47, 29, 1, 0x18180801), (46, 29, 48, 29, 0, 0x224), (47, 1, 51, 1, 0, 0x40000),
+    (47, 4, 50, 29, 1, 0x82012220), (47, 6, 48, 1, 1, 0x4000040), (47, 6, 49, 6, 1, 0x4400),
+    (47, 29, 48, 29, 1, 0x30302002), (47, 29, 49, 29, 0, 0x888), (48, 4, 51, 29, 1, 0x8028880),
+    (48, 6, 49, 1, 1, 0x100), (48, 6, 50, 6, 1, 0x41000), (48, 29, 49, 29, 1, 0x60a08004),
+    (48, 29, 50, 29, 0, 0x2220), (48, 29, 55, 29, 0, 0xa000), (49, 4, 52, 29, 1, 0x10092200),
+    (49, 6, 50, 1, 1, 0x400), (49, 6, 51, 6, 1, 0x4000), (49, 29, 50, 29, 1, 0xc2810008),
+    (49, 29, 51, 29, 0, 0x8880), (50, 1, 54, 1, 0, 0x400000), (50, 4, 53, 29, 1, 0x20128800),
+    (50, 6, 51, 1, 1, 0x41000), (50, 29, 51, 29, 1, 0x8a020020), (50, 29, 52, 29, 0, 0x12200),
+    (51, 1, 55, 1, 0, 0x1000000), (51, 4, 54, 29, 1, 0x40282000), (51, 6, 52, 1, 1, 0x4000),
+    (51, 6, 53, 6, 1, 0x400000), (51, 29, 52, 29, 1, 0x18080080), (51, 29, 54, 29, 0, 0xa0800),
+    (52, 1, 56, 1, 0, 0x4000000), (52, 4, 55, 29, 1, 0x80908000), (52, 6, 54, 6, 1, 0x1000000),
+    (52, 29, 53, 29, 1, 0x30110200), (53, 4, 56, 29, 1, 0x2200000), (53, 6, 54, 1, 1, 0x400000),
+    (53, 6, 55, 6, 1, 0x4000000), (53, 29, 54, 29, 1, 0x60220800), (53, 29, 56, 29, 0, 0x308000),
+    (54, 4, 57, 29, 1, 0x8800000), (54, 6, 55, 1, 1, 0x1000000), (54, 29, 55, 29, 1, 0xc0882000),
+    (55, 4, 58, 29, 1, 0x12000000), (55, 6, 56, 1, 1, 0x4000000), (55, 29, 56, 29, 1, 0x82108000),
+    (55, 29, 58, 29, 0, 0x800000), (56, 4, 59, 29, 1, 0x28000000), (56, 29, 57, 29, 1, 0x8200000),
+    (56, 29, 59, 29, 0, 0xa000000), (57, 4, 59, 29, 1, 0x40000000), (57, 4, 61, 29, 1, 0x10000000),
+    (57, 29, 58, 29, 1, 0x10800000), (58, 0, 63, 30, 0, 0x1), (58, 4, 62, 29, 1, 0x20000000),
+    (58, 29, 59, 29, 1, 0x22000000), (58, 29, 61, 29, 0, 0x10000000), (59, 0, 64, 30, 0, 0x2),
+    (59, 4, 63, 29, 1, 0x40000000), (59, 5, 63, 30, 1, 0x1), (59, 29, 60, 29, 1, 0x8000000),
+    (60, 0, 61, 5, 0, 0x10004), (60, 4, 64, 29, 1, 0x80000000), (60, 5, 64, 30, 1, 0x2),
+    (61, 0, 62, 5, 0, 0x20008), (61, 1, 62, 6, 0, 0x1), (61, 2, 62, 7, 0, 0x40010),
+    (62, 0, 63, 5, 0, 0x80020), (62, 1, 63, 6, 0, 0x2), (62, 2, 63, 7, 0, 0x40),
+    (63, 0, 64, 5, 0, 0x100080), (63, 1, 64, 6, 0, 0x10004), (63, 2, 64, 7, 0, 0x100),
+];
+
 
 #[deriving(Eq, Show)]
 pub struct Sha1 {
     h: (u32, u32, u32, u32, u32),
     x: Vec<u8>,
     len: uint,
+    // whether block uses the SHA extensions, the AVX2 message schedule or
+    // the ARMv8 SHA1 instructions
+    shani: bool,
+    avx2: bool,
+    armv8: bool,
+    // whether block looks for collision attacks and, on a hit, gives the
+    // safe hash; collision records a hit
+    dc: bool,
+    safe_hash: bool,
+    collision: bool,
 }
 
 // deep clone
@@ -38,6 +123,12 @@ impl Clone for Sha1 {
             h: self.h,
             x: self.x.clone(),
             len: self.len,
+            shani: self.shani,
+            avx2: self.avx2,
+            armv8: self.armv8,
+            dc: self.dc,
+            safe_hash: self.safe_hash,
+            collision: self.collision,
         }
     }
 }
@@ -46,59 +137,69 @@ impl Sha1 {
     pub fn new() -> Sha1 {
         let mut ret = Sha1 { h: (0,0,0,0,0),
                              x: Vec::new(),
-                             len: 0 };
+                             len: 0,
+                             shani: cpu::has_sha(),
+                             avx2: cpu::has_avx2(),
+                             armv8: cpu::has_sha1(),
+                             dc: false,
+                             safe_hash: false,
+                             collision: false };
         ret.reset();
         ret
     }
 
+    /// Returns a Sha1 that checks every block for the near-collision
+    /// attacks on SHA-1, such as SHAttered and Shambles, with Stevens and
+    /// Shumow's counter-cryptanalysis as git does. A message with such a
+    /// block gets the "safe hash" instead of its SHA-1, so it no longer
+    /// collides with its pair. Hashing is several times slower.
+    pub fn new_collision_detecting() -> Sha1 {
+        let mut ret = Sha1::new();
+        ret.dc = true;
+        ret.safe_hash = true;
+        ret
+    }
+
+    /// Sets whether a detected collision changes the digest to the safe
+    /// hash. Without collision detection it has no effect.
+    pub fn set_safe_hash(&mut self, safe: bool) {
+        self.safe_hash = safe;
+    }
+
+    /// Reports whether the message written so far, with its padding,
+    /// contains a block of a collision attack. It is always false without
+    /// collision detection.
+    pub fn collision_detected(&self) -> bool {
+        if !self.dc || self.collision {
+            return self.collision;
+        }
+        let mut h = self.clone();
+        h.checksum();
+        h.collision
+    }
+
+    // Collision detection needs the states of steps 58 and 65, so it always
+    // takes the scalar path.
     fn block(&mut self, buf: &[u8]) {
         assert!(buf.len() % CHUNK == 0);
-        let mut w = [0u32, ..80]; // 0-15, 16-79
-        let (mut h0, mut h1, mut h2, mut h3, mut h4) = self.h;
-        for p in buf.chunks(CHUNK) {
-            // break chunk into sixteen 32-bit big-endian words w[i], 0 ≤ i ≤ 15
-            for i in range(0u, 16u) {
-                let j = i * 4;
-                w[i] = p[j] as u32 << 24 | p[j+1] as u32 << 16 | p[j+2] as u32 << 8 | p[j+3] as u32;
-            }
-            // Extend the sixteen 32-bit words into eighty 32-bit words:
-            for i in range(16u, 80u) {
-                let mut t = w[i-3] ^ w[i-8] ^ w[i-14] ^ w[i-16];
-                unsafe { asm!("roll $0" : "=r"(t) : "0"(t) ) };
-                w[i] = t;
+        let (h0, h1, h2, h3, h4) = self.h;
+        let mut h = [h0, h1, h2, h3, h4];
+        if self.dc {
+            for p in buf.chunks(CHUNK) {
+                if block_dc(&mut h, p, self.safe_hash) {
+                    self.collision = true;
+                }
             }
-            // Initialize hash value for this chunk:
-            let (mut a, mut b, mut c, mut d, mut e) = (h0, h1, h2, h3, h4);
-            // Main loop
-            for i in range(0, 80u) {
-                let (f, k) : (u32, u32) = if 0 <= i && i <= 19 {
-                    ((b & c) | ((! b) & d), _K0)
-                } else if 20 <= i && i <= 39 {
-                    (b ^ c ^ d, _K1)
-                } else if 40 <= i && i <= 59 {
-                    ((b & c) | (b & d) | (c & d), _K2)
-                } else if 60 <= i && i <= 79 {
-                    (b ^ c ^ d, _K3)
-                } else {
-                    unreachable!()
-                };
-                let mut temp = a;
-                unsafe { asm!("roll $$5, $0" : "=r"(temp) : "0"(temp) ) };
-                temp += f + e + k + w[i];
-                e = d;
-                d = c;
-                unsafe { asm!("roll $$30, $1\n movl $1, $0" : "=r"(c) : "r"(b) ) };
-                b = a;
-                a = temp;
-            }
-            // Add this chunk's hash to result so far:
-            h0 = h0 + a;
-            h1 = h1 + b;
-            h2 = h2 + c;
-            h3 = h3 + d;
-            h4 = h4 + e;
+        } else if self.shani {
+            block_shani(&mut h, buf);
+        } else if self.armv8 {
+            block_armv8(&mut h, buf);
+        } else if self.avx2 {
+            block_avx2_schedule(&mut h, buf);
+        } else {
+            block_generic(&mut h, buf);
         }
-        self.h = (h0, h1, h2, h3, h4);
+        self.h = (h[0], h[1], h[2], h[3], h[4]);
     }
 
      fn checksum(&mut self) -> ~[u8] {
@@ -134,6 +235,1624 @@ impl Sha1 {
          }
          ret
      }
+
+    // one pass over a whole message, straight from the input slice
+    fn sum_oneshot(&mut self, msg: &[u8]) -> [u8, ..20] {
+        let full = msg.len() - msg.len() % CHUNK;
+        self.block(msg.slice_to(full));
+        let tail = msg.slice_from(full);
+        let mut tmp = [0u8, ..128];
+        for i in range(0, tail.len()) {
+            tmp[i] = tail[i];
+        }
+        tmp[tail.len()] = 0x80;
+        let n = if tail.len() < 56 { 64 } else { 128 };
+        let len = (msg.len() << 3) as u64;
+        for i in range(0, 8u) {
+            tmp[n - 8 + i] = (len >> (56 - 8*i)) as u8;
+        }
+        self.block(tmp.slice_to(n));
+
+        let (h0, h1, h2, h3, h4) = self.h;
+        let mut ret = [0u8, ..20];
+        for (i, &h) in [h0, h1, h2, h3, h4].iter().enumerate() {
+            for j in range(0, 4u) {
+                ret[4*i + j] = (h >> (24 - 8*j)) as u8;
+            }
+        }
+        ret
+    }
+}
+
+fn block_generic(h: &mut [u32, ..5], buf: &[u8]) {
+    let mut w = [0u32, ..80]; // 0-15, 16-79
+    for p in buf.chunks(CHUNK) {
+        // break chunk into sixteen 32-bit big-endian words w[i], 0 ≤ i ≤ 15
+        for i in range(0u, 16u) {
+            let j = i * 4;
+            w[i] = p[j] as u32 << 24 | p[j+1] as u32 << 16 | p[j+2] as u32 << 8 | p[j+3] as u32;
+        }
+        // Extend the sixteen 32-bit words into eighty 32-bit words:
+        for i in range(16u, 80u) {
+            w[i] = rotl32(w[i-3] ^ w[i-8] ^ w[i-14] ^ w[i-16], 1);
+        }
+        rounds(h, w.as_slice(), 0, 1);
+    }
+}
+
+// The 80 rounds of one block, with message word i in w[lanes*i + lane].
+fn rounds(h: &mut [u32, ..5], w: &[u32], lane: uint, lanes: uint) {
+    // Initialize hash value for this chunk:
+    let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+    // Main loop
+    for i in range(0, 80u) {
+        let (f, k) : (u32, u32) = if 0 <= i && i <= 19 {
+            ((b & c) | ((! b) & d), _K0)
+        } else if 20 <= i && i <= 39 {
+            (b ^ c ^ d, _K1)
+        } else if 40 <= i && i <= 59 {
+            ((b & c) | (b & d) | (c & d), _K2)
+        } else if 60 <= i && i <= 79 {
+            (b ^ c ^ d, _K3)
+        } else {
+            unreachable!()
+        };
+        let temp = rotl32(a, 5) + f + e + k + w[lanes * i + lane];
+        e = d;
+        d = c;
+        c = rotl32(b, 30);
+        b = a;
+        a = temp;
+    }
+    // Add this chunk's hash to result so far:
+    h[0] += a;
+    h[1] += b;
+    h[2] += c;
+    h[3] += d;
+    h[4] += e;
+}
+
+#[inline]
+fn rotl32(x: u32, n: uint) -> u32 {
+    (x << n) | (x >> (32 - n))
+}
+
+// The boolean function and constant of step t.
+#[inline]
+fn round(t: uint, b: u32, c: u32, d: u32) -> (u32, u32) {
+    if t < 20 {
+        ((b & c) | ((!b) & d), _K0)
+    } else if t < 40 {
+        (b ^ c ^ d, _K1)
+    } else if t < 60 {
+        ((b & c) | (b & d) | (c & d), _K2)
+    } else {
+        (b ^ c ^ d, _K3)
+    }
+}
+
+// Step t of the compression function on s = [a, b, c, d, e], and its
+// inverse.
+fn step(s: &mut [u32, ..5], t: uint, w: u32) {
+    let (f, k) = round(t, s[1], s[2], s[3]);
+    let a = rotl32(s[0], 5) + f + s[4] + k + w;
+    *s = [a, s[0], rotl32(s[1], 30), s[2], s[3]];
+}
+
+fn unstep(s: &mut [u32, ..5], t: uint, w: u32) {
+    let (a, b, c, d) = (s[1], rotl32(s[2], 2), s[3], s[4]);
+    let (f, k) = round(t, b, c, d);
+    let e = s[0] - (rotl32(a, 5) + f + k + w);
+    *s = [a, b, c, d, e];
+}
+
+// Returns the message difference of DV_typ(k, b): the DV is extended both
+// ways with the message expansion, and each step's word combines the
+// corrections of the local collisions started in the five steps before.
+fn dv_diff(typ: u8, k: uint, b: uint) -> [u32, ..80] {
+    // d[i + 5] is word i of the DV, from -5 to 79
+    let mut d = [0u32, ..85];
+    d[k + 20] = 1 << b;
+    if typ == 2 {
+        d[k + 6] = rotl32(1 << b, 31);
+        d[k + 8] = rotl32(1 << b, 31);
+    }
+    for j in range(k + 21, 85) {
+        d[j] = rotl32(d[j - 3] ^ d[j - 8] ^ d[j - 14] ^ d[j - 16], 1);
+    }
+    for j in range(0, k + 5).rev() {
+        d[j] = rotl32(d[j + 16], 31) ^ d[j + 13] ^ d[j + 8] ^ d[j + 2];
+    }
+    let mut dm = [0u32, ..80];
+    for i in range(0, 80u) {
+        let j = i + 5;
+        dm[i] = d[j] ^ rotl32(d[j - 1], 5) ^ d[j - 2] ^
+            rotl32(d[j - 3] ^ d[j - 4] ^ d[j - 5], 30);
+    }
+    dm
+}
+
+// Returns the DVs of dvs whose unavoidable bit conditions w meets.
+fn ubc_check(w: &[u32, ..80]) -> u32 {
+    let mut mask = !0u32;
+    for &(i, a, j, b, v, m) in ubcConds.iter() {
+        if (w[i as uint] >> a as uint ^ w[j as uint] >> b as uint) & 1 == v as u32 {
+            mask &= !m;
+        }
+    }
+    mask
+}
+
+// Compresses the block p into h like block_generic and reports whether it
+// is one half of a near-collision. For each DV the bit conditions leave
+// open, the message with the DV's difference applied is recompressed from
+// the state saved at the DV's test step, backwards to the chaining input
+// and forwards to the output; if both meet h, the block has a colliding
+// partner. With safe set, a hit compresses p twice more, which gives the
+// safe hash.
+fn block_dc(h: &mut [u32, ..5], p: &[u8], safe: bool) -> bool {
+    let mut w = [0u32, ..80];
+    for i in range(0u, 16u) {
+        let j = i * 4;
+        w[i] = p[j] as u32 << 24 | p[j+1] as u32 << 16 | p[j+2] as u32 << 8 | p[j+3] as u32;
+    }
+    for i in range(16u, 80u) {
+        w[i] = rotl32(w[i-3] ^ w[i-8] ^ w[i-14] ^ w[i-16], 1);
+    }
+    let mut s = *h;
+    let (mut s58, mut s65) = (s, s);
+    for t in range(0, 80u) {
+        if t == 58 {
+            s58 = s;
+        } else if t == 65 {
+            s65 = s;
+        }
+        step(&mut s, t, w[t]);
+    }
+    for i in range(0, 5u) {
+        h[i] += s[i];
+    }
+
+    let mask = ubc_check(&w);
+    for (i, &(typ, k, b, test)) in dvs.iter().enumerate() {
+        if mask & (1 << i) == 0 {
+            continue;
+        }
+        let dm = dv_diff(typ, k as uint, b as uint);
+        let mut w2 = [0u32, ..80];
+        for t in range(0, 80u) {
+            w2[t] = w[t] ^ dm[t];
+        }
+        let test = test as uint;
+        let mut back = if test == 58 { s58 } else { s65 };
+        let mut fwd = back;
+        for t in range(0, test).rev() {
+            unstep(&mut back, t, w2[t]);
+        }
+        for t in range(test, 80) {
+            step(&mut fwd, t, w2[t]);
+        }
+        if range(0, 5u).all(|j| back[j] + fwd[j] == h[j]) {
+            if safe {
+                block_generic(h, p);
+                block_generic(h, p);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+// The SHA extensions keep A-D in xmm0, with A in the top word, and E in
+// the top word of xmm1 or xmm2, which take turns: sha1nexte adds the
+// rotated E of four rounds back to the next four message words, and
+// sha1rnds4 runs four rounds. sha1msg1, pxor and sha1msg2 expand the
+// message schedule four words at a time. This follows Intel's sample code
+// for the SHA extensions.
+#[cfg(target_arch = "x86_64")]
+fn block_shani(h: &mut [u32, ..5], buf: &[u8]) {
+    if buf.len() == 0 {
+        return;
+    }
+    unsafe {
+        asm!("mov $1, %rax
+              movdqu ($0), %xmm0
+              pshufd $$0x1b, %xmm0, %xmm0
+              movd 16($0), %xmm1
+              pslldq $$12, %xmm1
+              movdqu ($3), %xmm7
+              1:
+              movdqa %xmm1, %xmm8
+              movdqa %xmm0, %xmm9
+              movdqu (%rax), %xmm3
+              pshufb %xmm7, %xmm3
+              paddd %xmm3, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1rnds4 $$0, %xmm1, %xmm0
+              movdqu 16(%rax), %xmm4
+              pshufb %xmm7, %xmm4
+              sha1nexte %xmm4, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1rnds4 $$0, %xmm2, %xmm0
+              sha1msg1 %xmm4, %xmm3
+              movdqu 32(%rax), %xmm5
+              pshufb %xmm7, %xmm5
+              sha1nexte %xmm5, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1rnds4 $$0, %xmm1, %xmm0
+              sha1msg1 %xmm5, %xmm4
+              pxor %xmm5, %xmm3
+              movdqu 48(%rax), %xmm6
+              pshufb %xmm7, %xmm6
+              sha1nexte %xmm6, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm6, %xmm3
+              sha1rnds4 $$0, %xmm2, %xmm0
+              sha1msg1 %xmm6, %xmm5
+              pxor %xmm6, %xmm4
+              sha1nexte %xmm3, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm3, %xmm4
+              sha1rnds4 $$0, %xmm1, %xmm0
+              sha1msg1 %xmm3, %xmm6
+              pxor %xmm3, %xmm5
+              sha1nexte %xmm4, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm4, %xmm5
+              sha1rnds4 $$1, %xmm2, %xmm0
+              sha1msg1 %xmm4, %xmm3
+              pxor %xmm4, %xmm6
+              sha1nexte %xmm5, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm5, %xmm6
+              sha1rnds4 $$1, %xmm1, %xmm0
+              sha1msg1 %xmm5, %xmm4
+              pxor %xmm5, %xmm3
+              sha1nexte %xmm6, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm6, %xmm3
+              sha1rnds4 $$1, %xmm2, %xmm0
+              sha1msg1 %xmm6, %xmm5
+              pxor %xmm6, %xmm4
+              sha1nexte %xmm3, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm3, %xmm4
+              sha1rnds4 $$1, %xmm1, %xmm0
+              sha1msg1 %xmm3, %xmm6
+              pxor %xmm3, %xmm5
+              sha1nexte %xmm4, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm4, %xmm5
+              sha1rnds4 $$1, %xmm2, %xmm0
+              sha1msg1 %xmm4, %xmm3
+              pxor %xmm4, %xmm6
+              sha1nexte %xmm5, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm5, %xmm6
+              sha1rnds4 $$2, %xmm1, %xmm0
+              sha1msg1 %xmm5, %xmm4
+              pxor %xmm5, %xmm3
+              sha1nexte %xmm6, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm6, %xmm3
+              sha1rnds4 $$2, %xmm2, %xmm0
+              sha1msg1 %xmm6, %xmm5
+              pxor %xmm6, %xmm4
+              sha1nexte %xmm3, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm3, %xmm4
+              sha1rnds4 $$2, %xmm1, %xmm0
+              sha1msg1 %xmm3, %xmm6
+              pxor %xmm3, %xmm5
+              sha1nexte %xmm4, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm4, %xmm5
+              sha1rnds4 $$2, %xmm2, %xmm0
+              sha1msg1 %xmm4, %xmm3
+              pxor %xmm4, %xmm6
+              sha1nexte %xmm5, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm5, %xmm6
+              sha1rnds4 $$2, %xmm1, %xmm0
+              sha1msg1 %xmm5, %xmm4
+              pxor %xmm5, %xmm3
+              sha1nexte %xmm6, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm6, %xmm3
+              sha1rnds4 $$3, %xmm2, %xmm0
+              sha1msg1 %xmm6, %xmm5
+              pxor %xmm6, %xmm4
+              sha1nexte %xmm3, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm3, %xmm4
+              sha1rnds4 $$3, %xmm1, %xmm0
+              sha1msg1 %xmm3, %xmm6
+              pxor %xmm3, %xmm5
+              sha1nexte %xmm4, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1msg2 %xmm4, %xmm5
+              sha1rnds4 $$3, %xmm2, %xmm0
+              pxor %xmm4, %xmm6
+              sha1nexte %xmm5, %xmm1
+              movdqa %xmm0, %xmm2
+              sha1msg2 %xmm5, %xmm6
+              sha1rnds4 $$3, %xmm1, %xmm0
+              sha1nexte %xmm6, %xmm2
+              movdqa %xmm0, %xmm1
+              sha1rnds4 $$3, %xmm2, %xmm0
+              sha1nexte %xmm8, %xmm1
+              paddd %xmm9, %xmm0
+              add $$64, %rax
+              cmp $2, %rax
+              jne 1b
+              pshufd $$0x1b, %xmm0, %xmm0
+              movdqu %xmm0, ($0)
+              psrldq $$12, %xmm1
+              movd %xmm1, 16($0)"
+             :
+             : "r"(h.as_mut_ptr()), "r"(buf.as_ptr()),
+               "r"(buf.as_ptr().offset(buf.len() as int)), "r"(bswapMask.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8",
+               "xmm9", "rax", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block_shani(_h: &mut [u32, ..5], _buf: &[u8]) {
+    unreachable!();
+}
+
+// Without the SHA extensions, AVX2 expands the message schedules of up to
+// eight blocks at once, one block per lane as in the multi-buffer code,
+// and the rounds then run on each block in turn.
+fn block_avx2_schedule(h: &mut [u32, ..5], buf: &[u8]) {
+    let mut w = [0u32, ..80 * 8];
+    for run in buf.chunks(8 * CHUNK) {
+        let n = run.len() / CHUNK;
+        for k in range(0, n) {
+            for i in range(0u, 16u) {
+                let j = CHUNK * k + 4 * i;
+                w[8 * i + k] = run[j] as u32 << 24 | run[j+1] as u32 << 16 |
+                    run[j+2] as u32 << 8 | run[j+3] as u32;
+            }
+        }
+        schedule_avx2(w.as_mut_slice());
+        for k in range(0, n) {
+            rounds(h, w.as_slice(), k, 8);
+        }
+    }
+}
+
+// Rows 16-79 of the message schedule of eight lanes, from rows 0-15.
+#[cfg(target_arch = "x86_64")]
+fn schedule_avx2(w: &mut [u32]) {
+    assert!(w.len() == 80 * 8);
+    unsafe {
+        asm!("lea 512($0), %rdx
+              mov $$64, %eax
+              1:
+              vmovdqu -96(%rdx), %ymm0
+              vpxor -256(%rdx), %ymm0, %ymm0
+              vpxor -448(%rdx), %ymm0, %ymm0
+              vpxor -512(%rdx), %ymm0, %ymm0
+              vpslld $$1, %ymm0, %ymm1
+              vpsrld $$31, %ymm0, %ymm0
+              vpor %ymm1, %ymm0, %ymm0
+              vmovdqu %ymm0, (%rdx)
+              add $$32, %rdx
+              dec %eax
+              jnz 1b
+              vzeroupper"
+             :
+             : "r"(w.as_mut_ptr())
+             : "xmm0", "xmm1", "rax", "rdx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn schedule_avx2(_w: &mut [u32]) {
+    unreachable!();
+}
+
+// The ARMv8 SHA1 instructions keep A-D in v0, with A in the low word, and
+// E in the low word of v1, then v2 and v3 in turn: sha1h gives the rotated
+// A that is the E of the next four rounds, and sha1c, sha1p and sha1m run
+// four rounds. sha1su0 and sha1su1 expand the message schedule four words
+// at a time, with v16-v19 holding the round constants.
+#[cfg(target_arch = "aarch64")]
+fn block_armv8(h: &mut [u32, ..5], buf: &[u8]) {
+    if buf.len() == 0 {
+        return;
+    }
+    let k = [_K0, _K1, _K2, _K3];
+    unsafe {
+        asm!("ld1 {v0.4s}, [$0]
+              ldr s1, [$0, #16]
+              ld1r {v16.4s}, [$3]
+              add x10, $3, #4
+              ld1r {v17.4s}, [x10]
+              add x10, $3, #8
+              ld1r {v18.4s}, [x10]
+              add x10, $3, #12
+              ld1r {v19.4s}, [x10]
+              mov x9, $1
+              1:
+              ld1 {v4.16b, v5.16b, v6.16b, v7.16b}, [x9], #64
+              rev32 v4.16b, v4.16b
+              rev32 v5.16b, v5.16b
+              rev32 v6.16b, v6.16b
+              rev32 v7.16b, v7.16b
+              mov v22.16b, v0.16b
+              add v20.4s, v16.4s, v4.4s
+              sha1su0 v4.4s, v5.4s, v6.4s
+              sha1su1 v4.4s, v7.4s
+              sha1h s3, s0
+              sha1c q0, s1, v20.4s
+              add v20.4s, v16.4s, v5.4s
+              sha1su0 v5.4s, v6.4s, v7.4s
+              sha1su1 v5.4s, v4.4s
+              sha1h s2, s0
+              sha1c q0, s3, v20.4s
+              add v20.4s, v16.4s, v6.4s
+              sha1su0 v6.4s, v7.4s, v4.4s
+              sha1su1 v6.4s, v5.4s
+              sha1h s3, s0
+              sha1c q0, s2, v20.4s
+              add v20.4s, v16.4s, v7.4s
+              sha1su0 v7.4s, v4.4s, v5.4s
+              sha1su1 v7.4s, v6.4s
+              sha1h s2, s0
+              sha1c q0, s3, v20.4s
+              add v20.4s, v16.4s, v4.4s
+              sha1su0 v4.4s, v5.4s, v6.4s
+              sha1su1 v4.4s, v7.4s
+              sha1h s3, s0
+              sha1c q0, s2, v20.4s
+              add v20.4s, v17.4s, v5.4s
+              sha1su0 v5.4s, v6.4s, v7.4s
+              sha1su1 v5.4s, v4.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v20.4s, v17.4s, v6.4s
+              sha1su0 v6.4s, v7.4s, v4.4s
+              sha1su1 v6.4s, v5.4s
+              sha1h s3, s0
+              sha1p q0, s2, v20.4s
+              add v20.4s, v17.4s, v7.4s
+              sha1su0 v7.4s, v4.4s, v5.4s
+              sha1su1 v7.4s, v6.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v20.4s, v17.4s, v4.4s
+              sha1su0 v4.4s, v5.4s, v6.4s
+              sha1su1 v4.4s, v7.4s
+              sha1h s3, s0
+              sha1p q0, s2, v20.4s
+              add v20.4s, v17.4s, v5.4s
+              sha1su0 v5.4s, v6.4s, v7.4s
+              sha1su1 v5.4s, v4.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v20.4s, v18.4s, v6.4s
+              sha1su0 v6.4s, v7.4s, v4.4s
+              sha1su1 v6.4s, v5.4s
+              sha1h s3, s0
+              sha1m q0, s2, v20.4s
+              add v20.4s, v18.4s, v7.4s
+              sha1su0 v7.4s, v4.4s, v5.4s
+              sha1su1 v7.4s, v6.4s
+              sha1h s2, s0
+              sha1m q0, s3, v20.4s
+              add v20.4s, v18.4s, v4.4s
+              sha1su0 v4.4s, v5.4s, v6.4s
+              sha1su1 v4.4s, v7.4s
+              sha1h s3, s0
+              sha1m q0, s2, v20.4s
+              add v20.4s, v18.4s, v5.4s
+              sha1su0 v5.4s, v6.4s, v7.4s
+              sha1su1 v5.4s, v4.4s
+              sha1h s2, s0
+              sha1m q0, s3, v20.4s
+              add v20.4s, v18.4s, v6.4s
+              sha1su0 v6.4s, v7.4s, v4.4s
+              sha1su1 v6.4s, v5.4s
+              sha1h s3, s0
+              sha1m q0, s2, v20.4s
+              add v20.4s, v19.4s, v7.4s
+              sha1su0 v7.4s, v4.4s, v5.4s
+              sha1su1 v7.4s, v6.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v20.4s, v19.4s, v4.4s
+              sha1h s3, s0
+              sha1p q0, s2, v20.4s
+              add v20.4s, v19.4s, v5.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v20.4s, v19.4s, v6.4s
+              sha1h s3, s0
+              sha1p q0, s2, v20.4s
+              add v20.4s, v19.4s, v7.4s
+              sha1h s2, s0
+              sha1p q0, s3, v20.4s
+              add v1.4s, v1.4s, v2.4s
+              add v0.4s, v0.4s, v22.4s
+              cmp x9, $2
+              b.ne 1b
+              st1 {v0.4s}, [$0]
+              str s1, [$0, #16]"
+             :
+             : "r"(h.as_mut_ptr()), "r"(buf.as_ptr()),
+               "r"(buf.as_ptr().offset(buf.len() as int)), "r"(k.as_ptr())
+             : "v0", "v1", "v2", "v3", "v4", "v5", "v6", "v7", "v16", "v17", "v18", "v19",
+               "v20", "v22", "x9", "x10", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn block_armv8(_h: &mut [u32, ..5], _buf: &[u8]) {
+    unreachable!();
+}
+
+/// Returns the SHA1 checksums of many independent messages.
+///
+/// See `md5::digest_many`; this is the same batch interface. Without AVX2
+/// a CPU with the SHA extensions hashes one message at a time, which is
+/// faster than four SSE2 lanes.
+pub fn digest_many(msgs: &[&[u8]]) -> Vec<[u8, ..20]> {
+    match multibuf::lanes() {
+        4 if cpu::has_sha() => digest_lanes(msgs, 1),
+        lanes => digest_lanes(msgs, lanes),
+    }
+}
+
+fn digest_lanes(msgs: &[&[u8]], lanes: uint) -> Vec<[u8, ..20]> {
+    let mut ret = Vec::with_capacity(msgs.len());
+    if lanes == 1 {
+        let mut h = Sha1::new();
+        for msg in msgs.iter() {
+            h.reset();
+            ret.push(h.sum_oneshot(*msg));
+        }
+        return ret;
+    }
+    let init = [init0, init1, init2, init3, init4];
+    let states = multibuf::hash_many(msgs, init.as_slice(), true, lanes, 80,
+                                     |s, w| block_lanes(lanes, s, w));
+    for st in states.iter() {
+        let mut sum = [0u8, ..20];
+        for i in range(0, 20u) {
+            sum[i] = (st[i / 4] >> (24 - 8 * (i % 4))) as u8;
+        }
+        ret.push(sum);
+    }
+    ret
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_lanes(lanes: uint, state: &mut [u32], w: &mut [u32]) {
+    match lanes {
+        4 => block_sse2(state, w),
+        8 => block_avx2(state, w),
+        _ => block_avx512(state, w),
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block_lanes(_lanes: uint, _state: &mut [u32], _w: &mut [u32]) {
+    unreachable!();
+}
+
+// The multi-buffer kernels, one message per lane. They expand the message
+// schedule into rows 16-79 first, then run each group of twenty rounds as
+// four loop iterations of five rounds, with a-e in registers 0-4 and the
+// round constant in register 8. With AVX-512 the rotations are vprold;
+// otherwise they are two shifts and an or.
+#[cfg(target_arch = "x86_64")]
+fn block_sse2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 5 * 4 && w.len() == 80 * 4);
+    let k = [_K0, _K1, _K2, _K3];
+    unsafe {
+        asm!("lea 256($1), %rdx
+              mov $$64, %eax
+              1:
+              movdqu -48(%rdx), %xmm0
+              movdqu -128(%rdx), %xmm1
+              pxor %xmm1, %xmm0
+              movdqu -224(%rdx), %xmm1
+              pxor %xmm1, %xmm0
+              movdqu -256(%rdx), %xmm1
+              pxor %xmm1, %xmm0
+              movdqa %xmm0, %xmm1
+              pslld $$1, %xmm1
+              psrld $$31, %xmm0
+              por %xmm1, %xmm0
+              movdqu %xmm0, (%rdx)
+              add $$16, %rdx
+              dec %eax
+              jnz 1b
+              movdqu ($0), %xmm0
+              movdqu 16($0), %xmm1
+              movdqu 32($0), %xmm2
+              movdqu 48($0), %xmm3
+              movdqu 64($0), %xmm4
+              mov $1, %rdx
+              movd ($2), %xmm8
+              pshufd $$0, %xmm8, %xmm8
+              mov $$4, %eax
+              2:
+              movdqa %xmm2, %xmm5
+              pxor %xmm3, %xmm5
+              pand %xmm1, %xmm5
+              pxor %xmm3, %xmm5
+              paddd %xmm5, %xmm4
+              paddd %xmm8, %xmm4
+              movdqu (%rdx), %xmm6
+              paddd %xmm6, %xmm4
+              movdqa %xmm0, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm0, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm4
+              movdqa %xmm1, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm1
+              por %xmm6, %xmm1
+              movdqa %xmm1, %xmm5
+              pxor %xmm2, %xmm5
+              pand %xmm0, %xmm5
+              pxor %xmm2, %xmm5
+              paddd %xmm5, %xmm3
+              paddd %xmm8, %xmm3
+              movdqu 16(%rdx), %xmm6
+              paddd %xmm6, %xmm3
+              movdqa %xmm4, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm4, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm0, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm0
+              por %xmm6, %xmm0
+              movdqa %xmm0, %xmm5
+              pxor %xmm1, %xmm5
+              pand %xmm4, %xmm5
+              pxor %xmm1, %xmm5
+              paddd %xmm5, %xmm2
+              paddd %xmm8, %xmm2
+              movdqu 32(%rdx), %xmm6
+              paddd %xmm6, %xmm2
+              movdqa %xmm3, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm3, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm4, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm4
+              por %xmm6, %xmm4
+              movdqa %xmm4, %xmm5
+              pxor %xmm0, %xmm5
+              pand %xmm3, %xmm5
+              pxor %xmm0, %xmm5
+              paddd %xmm5, %xmm1
+              paddd %xmm8, %xmm1
+              movdqu 48(%rdx), %xmm6
+              paddd %xmm6, %xmm1
+              movdqa %xmm2, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm2, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm3, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm3
+              por %xmm6, %xmm3
+              movdqa %xmm3, %xmm5
+              pxor %xmm4, %xmm5
+              pand %xmm2, %xmm5
+              pxor %xmm4, %xmm5
+              paddd %xmm5, %xmm0
+              paddd %xmm8, %xmm0
+              movdqu 64(%rdx), %xmm6
+              paddd %xmm6, %xmm0
+              movdqa %xmm1, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm1, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm2, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm2
+              por %xmm6, %xmm2
+              add $$80, %rdx
+              dec %eax
+              jnz 2b
+              movd 4($2), %xmm8
+              pshufd $$0, %xmm8, %xmm8
+              mov $$4, %eax
+              3:
+              movdqa %xmm1, %xmm5
+              pxor %xmm2, %xmm5
+              pxor %xmm3, %xmm5
+              paddd %xmm5, %xmm4
+              paddd %xmm8, %xmm4
+              movdqu (%rdx), %xmm6
+              paddd %xmm6, %xmm4
+              movdqa %xmm0, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm0, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm4
+              movdqa %xmm1, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm1
+              por %xmm6, %xmm1
+              movdqa %xmm0, %xmm5
+              pxor %xmm1, %xmm5
+              pxor %xmm2, %xmm5
+              paddd %xmm5, %xmm3
+              paddd %xmm8, %xmm3
+              movdqu 16(%rdx), %xmm6
+              paddd %xmm6, %xmm3
+              movdqa %xmm4, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm4, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm0, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm0
+              por %xmm6, %xmm0
+              movdqa %xmm4, %xmm5
+              pxor %xmm0, %xmm5
+              pxor %xmm1, %xmm5
+              paddd %xmm5, %xmm2
+              paddd %xmm8, %xmm2
+              movdqu 32(%rdx), %xmm6
+              paddd %xmm6, %xmm2
+              movdqa %xmm3, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm3, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm4, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm4
+              por %xmm6, %xmm4
+              movdqa %xmm3, %xmm5
+              pxor %xmm4, %xmm5
+              pxor %xmm0, %xmm5
+              paddd %xmm5, %xmm1
+              paddd %xmm8, %xmm1
+              movdqu 48(%rdx), %xmm6
+              paddd %xmm6, %xmm1
+              movdqa %xmm2, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm2, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm3, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm3
+              por %xmm6, %xmm3
+              movdqa %xmm2, %xmm5
+              pxor %xmm3, %xmm5
+              pxor %xmm4, %xmm5
+              paddd %xmm5, %xmm0
+              paddd %xmm8, %xmm0
+              movdqu 64(%rdx), %xmm6
+              paddd %xmm6, %xmm0
+              movdqa %xmm1, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm1, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm2, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm2
+              por %xmm6, %xmm2
+              add $$80, %rdx
+              dec %eax
+              jnz 3b
+              movd 8($2), %xmm8
+              pshufd $$0, %xmm8, %xmm8
+              mov $$4, %eax
+              4:
+              movdqa %xmm1, %xmm5
+              por %xmm2, %xmm5
+              pand %xmm3, %xmm5
+              movdqa %xmm1, %xmm6
+              pand %xmm2, %xmm6
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm4
+              paddd %xmm8, %xmm4
+              movdqu (%rdx), %xmm6
+              paddd %xmm6, %xmm4
+              movdqa %xmm0, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm0, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm4
+              movdqa %xmm1, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm1
+              por %xmm6, %xmm1
+              movdqa %xmm0, %xmm5
+              por %xmm1, %xmm5
+              pand %xmm2, %xmm5
+              movdqa %xmm0, %xmm6
+              pand %xmm1, %xmm6
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm3
+              paddd %xmm8, %xmm3
+              movdqu 16(%rdx), %xmm6
+              paddd %xmm6, %xmm3
+              movdqa %xmm4, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm4, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm0, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm0
+              por %xmm6, %xmm0
+              movdqa %xmm4, %xmm5
+              por %xmm0, %xmm5
+              pand %xmm1, %xmm5
+              movdqa %xmm4, %xmm6
+              pand %xmm0, %xmm6
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm2
+              paddd %xmm8, %xmm2
+              movdqu 32(%rdx), %xmm6
+              paddd %xmm6, %xmm2
+              movdqa %xmm3, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm3, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm4, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm4
+              por %xmm6, %xmm4
+              movdqa %xmm3, %xmm5
+              por %xmm4, %xmm5
+              pand %xmm0, %xmm5
+              movdqa %xmm3, %xmm6
+              pand %xmm4, %xmm6
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm1
+              paddd %xmm8, %xmm1
+              movdqu 48(%rdx), %xmm6
+              paddd %xmm6, %xmm1
+              movdqa %xmm2, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm2, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm3, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm3
+              por %xmm6, %xmm3
+              movdqa %xmm2, %xmm5
+              por %xmm3, %xmm5
+              pand %xmm4, %xmm5
+              movdqa %xmm2, %xmm6
+              pand %xmm3, %xmm6
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm0
+              paddd %xmm8, %xmm0
+              movdqu 64(%rdx), %xmm6
+              paddd %xmm6, %xmm0
+              movdqa %xmm1, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm1, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm2, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm2
+              por %xmm6, %xmm2
+              add $$80, %rdx
+              dec %eax
+              jnz 4b
+              movd 12($2), %xmm8
+              pshufd $$0, %xmm8, %xmm8
+              mov $$4, %eax
+              5:
+              movdqa %xmm1, %xmm5
+              pxor %xmm2, %xmm5
+              pxor %xmm3, %xmm5
+              paddd %xmm5, %xmm4
+              paddd %xmm8, %xmm4
+              movdqu (%rdx), %xmm6
+              paddd %xmm6, %xmm4
+              movdqa %xmm0, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm0, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm4
+              movdqa %xmm1, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm1
+              por %xmm6, %xmm1
+              movdqa %xmm0, %xmm5
+              pxor %xmm1, %xmm5
+              pxor %xmm2, %xmm5
+              paddd %xmm5, %xmm3
+              paddd %xmm8, %xmm3
+              movdqu 16(%rdx), %xmm6
+              paddd %xmm6, %xmm3
+              movdqa %xmm4, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm4, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm3
+              movdqa %xmm0, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm0
+              por %xmm6, %xmm0
+              movdqa %xmm4, %xmm5
+              pxor %xmm0, %xmm5
+              pxor %xmm1, %xmm5
+              paddd %xmm5, %xmm2
+              paddd %xmm8, %xmm2
+              movdqu 32(%rdx), %xmm6
+              paddd %xmm6, %xmm2
+              movdqa %xmm3, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm3, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm2
+              movdqa %xmm4, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm4
+              por %xmm6, %xmm4
+              movdqa %xmm3, %xmm5
+              pxor %xmm4, %xmm5
+              pxor %xmm0, %xmm5
+              paddd %xmm5, %xmm1
+              paddd %xmm8, %xmm1
+              movdqu 48(%rdx), %xmm6
+              paddd %xmm6, %xmm1
+              movdqa %xmm2, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm2, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm3, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm3
+              por %xmm6, %xmm3
+              movdqa %xmm2, %xmm5
+              pxor %xmm3, %xmm5
+              pxor %xmm4, %xmm5
+              paddd %xmm5, %xmm0
+              paddd %xmm8, %xmm0
+              movdqu 64(%rdx), %xmm6
+              paddd %xmm6, %xmm0
+              movdqa %xmm1, %xmm6
+              pslld $$5, %xmm6
+              movdqa %xmm1, %xmm5
+              psrld $$27, %xmm5
+              por %xmm6, %xmm5
+              paddd %xmm5, %xmm0
+              movdqa %xmm2, %xmm6
+              pslld $$30, %xmm6
+              psrld $$2, %xmm2
+              por %xmm6, %xmm2
+              add $$80, %rdx
+              dec %eax
+              jnz 5b
+              movdqu ($0), %xmm5
+              paddd %xmm5, %xmm0
+              movdqu %xmm0, ($0)
+              movdqu 16($0), %xmm5
+              paddd %xmm5, %xmm1
+              movdqu %xmm1, 16($0)
+              movdqu 32($0), %xmm5
+              paddd %xmm5, %xmm2
+              movdqu %xmm2, 32($0)
+              movdqu 48($0), %xmm5
+              paddd %xmm5, %xmm3
+              movdqu %xmm3, 48($0)
+              movdqu 64($0), %xmm5
+              paddd %xmm5, %xmm4
+              movdqu %xmm4, 64($0)"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(k.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm8", "rax", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_avx2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 5 * 8 && w.len() == 80 * 8);
+    let k = [_K0, _K1, _K2, _K3];
+    unsafe {
+        asm!("lea 512($1), %rdx
+              mov $$64, %eax
+              1:
+              vmovdqu -96(%rdx), %ymm0
+              vpxor -256(%rdx), %ymm0, %ymm0
+              vpxor -448(%rdx), %ymm0, %ymm0
+              vpxor -512(%rdx), %ymm0, %ymm0
+              vpslld $$1, %ymm0, %ymm1
+              vpsrld $$31, %ymm0, %ymm0
+              vpor %ymm1, %ymm0, %ymm0
+              vmovdqu %ymm0, (%rdx)
+              add $$32, %rdx
+              dec %eax
+              jnz 1b
+              vmovdqu ($0), %ymm0
+              vmovdqu 32($0), %ymm1
+              vmovdqu 64($0), %ymm2
+              vmovdqu 96($0), %ymm3
+              vmovdqu 128($0), %ymm4
+              mov $1, %rdx
+              vpbroadcastd ($2), %ymm8
+              mov $$4, %eax
+              2:
+              vpxor %ymm3, %ymm2, %ymm5
+              vpand %ymm1, %ymm5, %ymm5
+              vpxor %ymm3, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpaddd (%rdx), %ymm4, %ymm4
+              vpslld $$5, %ymm0, %ymm6
+              vpsrld $$27, %ymm0, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpslld $$30, %ymm1, %ymm6
+              vpsrld $$2, %ymm1, %ymm1
+              vpor %ymm6, %ymm1, %ymm1
+              vpxor %ymm2, %ymm1, %ymm5
+              vpand %ymm0, %ymm5, %ymm5
+              vpxor %ymm2, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$5, %ymm4, %ymm6
+              vpsrld $$27, %ymm4, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpslld $$30, %ymm0, %ymm6
+              vpsrld $$2, %ymm0, %ymm0
+              vpor %ymm6, %ymm0, %ymm0
+              vpxor %ymm1, %ymm0, %ymm5
+              vpand %ymm4, %ymm5, %ymm5
+              vpxor %ymm1, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$5, %ymm3, %ymm6
+              vpsrld $$27, %ymm3, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpslld $$30, %ymm4, %ymm6
+              vpsrld $$2, %ymm4, %ymm4
+              vpor %ymm6, %ymm4, %ymm4
+              vpxor %ymm0, %ymm4, %ymm5
+              vpand %ymm3, %ymm5, %ymm5
+              vpxor %ymm0, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$5, %ymm2, %ymm6
+              vpsrld $$27, %ymm2, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpslld $$30, %ymm3, %ymm6
+              vpsrld $$2, %ymm3, %ymm3
+              vpor %ymm6, %ymm3, %ymm3
+              vpxor %ymm4, %ymm3, %ymm5
+              vpand %ymm2, %ymm5, %ymm5
+              vpxor %ymm4, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd %ymm8, %ymm0, %ymm0
+              vpaddd 128(%rdx), %ymm0, %ymm0
+              vpslld $$5, %ymm1, %ymm6
+              vpsrld $$27, %ymm1, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpslld $$30, %ymm2, %ymm6
+              vpsrld $$2, %ymm2, %ymm2
+              vpor %ymm6, %ymm2, %ymm2
+              add $$160, %rdx
+              dec %eax
+              jnz 2b
+              vpbroadcastd 4($2), %ymm8
+              mov $$4, %eax
+              3:
+              vpxor %ymm2, %ymm1, %ymm5
+              vpxor %ymm3, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpaddd (%rdx), %ymm4, %ymm4
+              vpslld $$5, %ymm0, %ymm6
+              vpsrld $$27, %ymm0, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpslld $$30, %ymm1, %ymm6
+              vpsrld $$2, %ymm1, %ymm1
+              vpor %ymm6, %ymm1, %ymm1
+              vpxor %ymm1, %ymm0, %ymm5
+              vpxor %ymm2, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$5, %ymm4, %ymm6
+              vpsrld $$27, %ymm4, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpslld $$30, %ymm0, %ymm6
+              vpsrld $$2, %ymm0, %ymm0
+              vpor %ymm6, %ymm0, %ymm0
+              vpxor %ymm0, %ymm4, %ymm5
+              vpxor %ymm1, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$5, %ymm3, %ymm6
+              vpsrld $$27, %ymm3, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpslld $$30, %ymm4, %ymm6
+              vpsrld $$2, %ymm4, %ymm4
+              vpor %ymm6, %ymm4, %ymm4
+              vpxor %ymm4, %ymm3, %ymm5
+              vpxor %ymm0, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$5, %ymm2, %ymm6
+              vpsrld $$27, %ymm2, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpslld $$30, %ymm3, %ymm6
+              vpsrld $$2, %ymm3, %ymm3
+              vpor %ymm6, %ymm3, %ymm3
+              vpxor %ymm3, %ymm2, %ymm5
+              vpxor %ymm4, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd %ymm8, %ymm0, %ymm0
+              vpaddd 128(%rdx), %ymm0, %ymm0
+              vpslld $$5, %ymm1, %ymm6
+              vpsrld $$27, %ymm1, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpslld $$30, %ymm2, %ymm6
+              vpsrld $$2, %ymm2, %ymm2
+              vpor %ymm6, %ymm2, %ymm2
+              add $$160, %rdx
+              dec %eax
+              jnz 3b
+              vpbroadcastd 8($2), %ymm8
+              mov $$4, %eax
+              4:
+              vpor %ymm2, %ymm1, %ymm5
+              vpand %ymm3, %ymm5, %ymm5
+              vpand %ymm2, %ymm1, %ymm6
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpaddd (%rdx), %ymm4, %ymm4
+              vpslld $$5, %ymm0, %ymm6
+              vpsrld $$27, %ymm0, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpslld $$30, %ymm1, %ymm6
+              vpsrld $$2, %ymm1, %ymm1
+              vpor %ymm6, %ymm1, %ymm1
+              vpor %ymm1, %ymm0, %ymm5
+              vpand %ymm2, %ymm5, %ymm5
+              vpand %ymm1, %ymm0, %ymm6
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$5, %ymm4, %ymm6
+              vpsrld $$27, %ymm4, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpslld $$30, %ymm0, %ymm6
+              vpsrld $$2, %ymm0, %ymm0
+              vpor %ymm6, %ymm0, %ymm0
+              vpor %ymm0, %ymm4, %ymm5
+              vpand %ymm1, %ymm5, %ymm5
+              vpand %ymm0, %ymm4, %ymm6
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$5, %ymm3, %ymm6
+              vpsrld $$27, %ymm3, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpslld $$30, %ymm4, %ymm6
+              vpsrld $$2, %ymm4, %ymm4
+              vpor %ymm6, %ymm4, %ymm4
+              vpor %ymm4, %ymm3, %ymm5
+              vpand %ymm0, %ymm5, %ymm5
+              vpand %ymm4, %ymm3, %ymm6
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$5, %ymm2, %ymm6
+              vpsrld $$27, %ymm2, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpslld $$30, %ymm3, %ymm6
+              vpsrld $$2, %ymm3, %ymm3
+              vpor %ymm6, %ymm3, %ymm3
+              vpor %ymm3, %ymm2, %ymm5
+              vpand %ymm4, %ymm5, %ymm5
+              vpand %ymm3, %ymm2, %ymm6
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd %ymm8, %ymm0, %ymm0
+              vpaddd 128(%rdx), %ymm0, %ymm0
+              vpslld $$5, %ymm1, %ymm6
+              vpsrld $$27, %ymm1, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpslld $$30, %ymm2, %ymm6
+              vpsrld $$2, %ymm2, %ymm2
+              vpor %ymm6, %ymm2, %ymm2
+              add $$160, %rdx
+              dec %eax
+              jnz 4b
+              vpbroadcastd 12($2), %ymm8
+              mov $$4, %eax
+              5:
+              vpxor %ymm2, %ymm1, %ymm5
+              vpxor %ymm3, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpaddd (%rdx), %ymm4, %ymm4
+              vpslld $$5, %ymm0, %ymm6
+              vpsrld $$27, %ymm0, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm4, %ymm4
+              vpslld $$30, %ymm1, %ymm6
+              vpsrld $$2, %ymm1, %ymm1
+              vpor %ymm6, %ymm1, %ymm1
+              vpxor %ymm1, %ymm0, %ymm5
+              vpxor %ymm2, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpaddd 32(%rdx), %ymm3, %ymm3
+              vpslld $$5, %ymm4, %ymm6
+              vpsrld $$27, %ymm4, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm3, %ymm3
+              vpslld $$30, %ymm0, %ymm6
+              vpsrld $$2, %ymm0, %ymm0
+              vpor %ymm6, %ymm0, %ymm0
+              vpxor %ymm0, %ymm4, %ymm5
+              vpxor %ymm1, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpaddd 64(%rdx), %ymm2, %ymm2
+              vpslld $$5, %ymm3, %ymm6
+              vpsrld $$27, %ymm3, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm2, %ymm2
+              vpslld $$30, %ymm4, %ymm6
+              vpsrld $$2, %ymm4, %ymm4
+              vpor %ymm6, %ymm4, %ymm4
+              vpxor %ymm4, %ymm3, %ymm5
+              vpxor %ymm0, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpaddd 96(%rdx), %ymm1, %ymm1
+              vpslld $$5, %ymm2, %ymm6
+              vpsrld $$27, %ymm2, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpslld $$30, %ymm3, %ymm6
+              vpsrld $$2, %ymm3, %ymm3
+              vpor %ymm6, %ymm3, %ymm3
+              vpxor %ymm3, %ymm2, %ymm5
+              vpxor %ymm4, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpaddd %ymm8, %ymm0, %ymm0
+              vpaddd 128(%rdx), %ymm0, %ymm0
+              vpslld $$5, %ymm1, %ymm6
+              vpsrld $$27, %ymm1, %ymm5
+              vpor %ymm6, %ymm5, %ymm5
+              vpaddd %ymm5, %ymm0, %ymm0
+              vpslld $$30, %ymm2, %ymm6
+              vpsrld $$2, %ymm2, %ymm2
+              vpor %ymm6, %ymm2, %ymm2
+              add $$160, %rdx
+              dec %eax
+              jnz 5b
+              vpaddd ($0), %ymm0, %ymm0
+              vmovdqu %ymm0, ($0)
+              vpaddd 32($0), %ymm1, %ymm1
+              vmovdqu %ymm1, 32($0)
+              vpaddd 64($0), %ymm2, %ymm2
+              vmovdqu %ymm2, 64($0)
+              vpaddd 96($0), %ymm3, %ymm3
+              vmovdqu %ymm3, 96($0)
+              vpaddd 128($0), %ymm4, %ymm4
+              vmovdqu %ymm4, 128($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(k.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm8", "rax", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_avx512(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 5 * 16 && w.len() == 80 * 16);
+    let k = [_K0, _K1, _K2, _K3];
+    unsafe {
+        asm!("lea 1024($1), %rdx
+              mov $$64, %eax
+              1:
+              vmovdqu32 -192(%rdx), %zmm0
+              vpxord -512(%rdx), %zmm0, %zmm0
+              vpxord -896(%rdx), %zmm0, %zmm0
+              vpxord -1024(%rdx), %zmm0, %zmm0
+              vprold $$1, %zmm0, %zmm0
+              vmovdqu32 %zmm0, (%rdx)
+              add $$64, %rdx
+              dec %eax
+              jnz 1b
+              vmovdqu32 ($0), %zmm0
+              vmovdqu32 64($0), %zmm1
+              vmovdqu32 128($0), %zmm2
+              vmovdqu32 192($0), %zmm3
+              vmovdqu32 256($0), %zmm4
+              mov $1, %rdx
+              vpbroadcastd ($2), %zmm8
+              mov $$4, %eax
+              2:
+              vpxord %zmm3, %zmm2, %zmm5
+              vpandd %zmm1, %zmm5, %zmm5
+              vpxord %zmm3, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vpaddd %zmm8, %zmm4, %zmm4
+              vpaddd (%rdx), %zmm4, %zmm4
+              vprold $$5, %zmm0, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vprold $$30, %zmm1, %zmm1
+              vpxord %zmm2, %zmm1, %zmm5
+              vpandd %zmm0, %zmm5, %zmm5
+              vpxord %zmm2, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd %zmm8, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$5, %zmm4, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vprold $$30, %zmm0, %zmm0
+              vpxord %zmm1, %zmm0, %zmm5
+              vpandd %zmm4, %zmm5, %zmm5
+              vpxord %zmm1, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd %zmm8, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$5, %zmm3, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vprold $$30, %zmm4, %zmm4
+              vpxord %zmm0, %zmm4, %zmm5
+              vpandd %zmm3, %zmm5, %zmm5
+              vpxord %zmm0, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd %zmm8, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$5, %zmm2, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vprold $$30, %zmm3, %zmm3
+              vpxord %zmm4, %zmm3, %zmm5
+              vpandd %zmm2, %zmm5, %zmm5
+              vpxord %zmm4, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd %zmm8, %zmm0, %zmm0
+              vpaddd 256(%rdx), %zmm0, %zmm0
+              vprold $$5, %zmm1, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vprold $$30, %zmm2, %zmm2
+              add $$320, %rdx
+              dec %eax
+              jnz 2b
+              vpbroadcastd 4($2), %zmm8
+              mov $$4, %eax
+              3:
+              vpxord %zmm2, %zmm1, %zmm5
+              vpxord %zmm3, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vpaddd %zmm8, %zmm4, %zmm4
+              vpaddd (%rdx), %zmm4, %zmm4
+              vprold $$5, %zmm0, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vprold $$30, %zmm1, %zmm1
+              vpxord %zmm1, %zmm0, %zmm5
+              vpxord %zmm2, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd %zmm8, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$5, %zmm4, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vprold $$30, %zmm0, %zmm0
+              vpxord %zmm0, %zmm4, %zmm5
+              vpxord %zmm1, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd %zmm8, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$5, %zmm3, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vprold $$30, %zmm4, %zmm4
+              vpxord %zmm4, %zmm3, %zmm5
+              vpxord %zmm0, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd %zmm8, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$5, %zmm2, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vprold $$30, %zmm3, %zmm3
+              vpxord %zmm3, %zmm2, %zmm5
+              vpxord %zmm4, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd %zmm8, %zmm0, %zmm0
+              vpaddd 256(%rdx), %zmm0, %zmm0
+              vprold $$5, %zmm1, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vprold $$30, %zmm2, %zmm2
+              add $$320, %rdx
+              dec %eax
+              jnz 3b
+              vpbroadcastd 8($2), %zmm8
+              mov $$4, %eax
+              4:
+              vpord %zmm2, %zmm1, %zmm5
+              vpandd %zmm3, %zmm5, %zmm5
+              vpandd %zmm2, %zmm1, %zmm6
+              vpord %zmm6, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vpaddd %zmm8, %zmm4, %zmm4
+              vpaddd (%rdx), %zmm4, %zmm4
+              vprold $$5, %zmm0, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vprold $$30, %zmm1, %zmm1
+              vpord %zmm1, %zmm0, %zmm5
+              vpandd %zmm2, %zmm5, %zmm5
+              vpandd %zmm1, %zmm0, %zmm6
+              vpord %zmm6, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd %zmm8, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$5, %zmm4, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vprold $$30, %zmm0, %zmm0
+              vpord %zmm0, %zmm4, %zmm5
+              vpandd %zmm1, %zmm5, %zmm5
+              vpandd %zmm0, %zmm4, %zmm6
+              vpord %zmm6, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd %zmm8, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$5, %zmm3, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vprold $$30, %zmm4, %zmm4
+              vpord %zmm4, %zmm3, %zmm5
+              vpandd %zmm0, %zmm5, %zmm5
+              vpandd %zmm4, %zmm3, %zmm6
+              vpord %zmm6, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd %zmm8, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$5, %zmm2, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vprold $$30, %zmm3, %zmm3
+              vpord %zmm3, %zmm2, %zmm5
+              vpandd %zmm4, %zmm5, %zmm5
+              vpandd %zmm3, %zmm2, %zmm6
+              vpord %zmm6, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd %zmm8, %zmm0, %zmm0
+              vpaddd 256(%rdx), %zmm0, %zmm0
+              vprold $$5, %zmm1, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vprold $$30, %zmm2, %zmm2
+              add $$320, %rdx
+              dec %eax
+              jnz 4b
+              vpbroadcastd 12($2), %zmm8
+              mov $$4, %eax
+              5:
+              vpxord %zmm2, %zmm1, %zmm5
+              vpxord %zmm3, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vpaddd %zmm8, %zmm4, %zmm4
+              vpaddd (%rdx), %zmm4, %zmm4
+              vprold $$5, %zmm0, %zmm5
+              vpaddd %zmm5, %zmm4, %zmm4
+              vprold $$30, %zmm1, %zmm1
+              vpxord %zmm1, %zmm0, %zmm5
+              vpxord %zmm2, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vpaddd %zmm8, %zmm3, %zmm3
+              vpaddd 64(%rdx), %zmm3, %zmm3
+              vprold $$5, %zmm4, %zmm5
+              vpaddd %zmm5, %zmm3, %zmm3
+              vprold $$30, %zmm0, %zmm0
+              vpxord %zmm0, %zmm4, %zmm5
+              vpxord %zmm1, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vpaddd %zmm8, %zmm2, %zmm2
+              vpaddd 128(%rdx), %zmm2, %zmm2
+              vprold $$5, %zmm3, %zmm5
+              vpaddd %zmm5, %zmm2, %zmm2
+              vprold $$30, %zmm4, %zmm4
+              vpxord %zmm4, %zmm3, %zmm5
+              vpxord %zmm0, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vpaddd %zmm8, %zmm1, %zmm1
+              vpaddd 192(%rdx), %zmm1, %zmm1
+              vprold $$5, %zmm2, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vprold $$30, %zmm3, %zmm3
+              vpxord %zmm3, %zmm2, %zmm5
+              vpxord %zmm4, %zmm5, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vpaddd %zmm8, %zmm0, %zmm0
+              vpaddd 256(%rdx), %zmm0, %zmm0
+              vprold $$5, %zmm1, %zmm5
+              vpaddd %zmm5, %zmm0, %zmm0
+              vprold $$30, %zmm2, %zmm2
+              add $$320, %rdx
+              dec %eax
+              jnz 5b
+              vpaddd ($0), %zmm0, %zmm0
+              vmovdqu32 %zmm0, ($0)
+              vpaddd 64($0), %zmm1, %zmm1
+              vmovdqu32 %zmm1, 64($0)
+              vpaddd 128($0), %zmm2, %zmm2
+              vmovdqu32 %zmm2, 128($0)
+              vpaddd 192($0), %zmm3, %zmm3
+              vmovdqu32 %zmm3, 192($0)
+              vpaddd 256($0), %zmm4, %zmm4
+              vmovdqu32 %zmm4, 256($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(k.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm8", "rax", "rdx",
+               "memory", "cc"
+             : "volatile");
+    }
 }
 
 impl Writer for Sha1 {
@@ -159,6 +1878,7 @@ impl Hash for Sha1 {
         self.h = (init0, init1, init2, init3, init4);
         self.x = Vec::new();
         self.len = 0;
+        self.collision = false;
     }
     fn digest(&self) -> ~[u8] {
         let mut h = self.clone();
@@ -192,3 +1912,168 @@ fn test_sha1_long() {
     h.write_str("welcome to china".repeat(10001));
     assert_eq!(h.hexdigest(), ~"da3884df7c84378ebf72b86e3fe43b2a4664d73a");
 }
+
+#[test]
+fn test_sha1_digest_many() {
+    let data = slice::from_fn(300, |i| (i * 7) as u8);
+    let mut msgs = Vec::new();
+    for n in range(0, 200u) {
+        msgs.push(data.slice_to(n));
+    }
+    let sums = digest_many(msgs.as_slice());
+    assert_eq!(sums.len(), 200);
+    for (msg, sum) in msgs.iter().zip(sums.iter()) {
+        let mut h = Sha1::new();
+        h.write(*msg);
+        assert_eq!(h.digest().as_slice(), sum.as_slice());
+    }
+    assert_eq!(digest_many([]).len(), 0);
+}
+
+#[test]
+fn test_sha1_digest_lanes() {
+    let data = slice::from_fn(2000, |i| (i * 7 + 3) as u8);
+    // long and short messages mixed, so lanes finish at different times
+    let mut msgs = Vec::new();
+    for i in range(0, 150u) {
+        let n = (i * 37) % 300 + if i == 7 { 1500 } else { 0 };
+        msgs.push(data.slice(i % 13, i % 13 + n));
+    }
+    let want = digest_lanes(msgs.as_slice(), 1);
+    for &lanes in [4u, 8, 16].iter() {
+        if !multibuf::supported(lanes) {
+            continue;
+        }
+        let got = digest_lanes(msgs.as_slice(), lanes);
+        for (a, b) in want.iter().zip(got.iter()) {
+            assert_eq!(a.as_slice(), b.as_slice());
+        }
+        assert_eq!(got.len(), want.len());
+        // fewer messages than lanes
+        let few = digest_lanes(msgs.slice_to(3), lanes);
+        assert_eq!(few.len(), 3);
+        assert_eq!(few.get(2).as_slice(), want.get(2).as_slice());
+        assert_eq!(digest_lanes([], lanes).len(), 0);
+    }
+}
+
+#[test]
+fn test_sha1_block_shani() {
+    if !cpu::has_sha() {
+        return;
+    }
+    let data = slice::from_fn(1000, |i| (i * 31 + 7) as u8);
+    for &n in [1u, 3, 55, 57, 63, 65, 127, 129, 191, 999].iter() {
+        let mut a = Sha1::new();
+        let mut b = Sha1::new();
+        a.shani = false;
+        a.avx2 = false;
+        b.shani = true;
+        a.write(data.slice_to(n));
+        b.write(data.slice_to(n));
+        assert_eq!(a.digest(), b.digest());
+        // several blocks in one call
+        a.reset();
+        b.reset();
+        assert_eq!(a.sum_oneshot(data.slice_to(n)).as_slice(),
+                   b.sum_oneshot(data.slice_to(n)).as_slice());
+    }
+}
+
+#[test]
+fn test_sha1_block_avx2_armv8() {
+    let data = slice::from_fn(1100, |i| (i * 31 + 7) as u8);
+    let h = [init0, init1, init2, init3, init4];
+    // runs of blocks around the eight lanes of the AVX2 schedule
+    for &n in [0u, 1, 7, 8, 9, 16, 17].iter() {
+        let mut want = h;
+        block_generic(&mut want, data.slice_to(64 * n));
+        if cpu::has_avx2() {
+            let mut got = h;
+            block_avx2_schedule(&mut got, data.slice_to(64 * n));
+            assert_eq!(got.as_slice(), want.as_slice());
+        }
+        if cpu::has_sha1() {
+            let mut got = h;
+            block_armv8(&mut got, data.slice_to(64 * n));
+            assert_eq!(got.as_slice(), want.as_slice());
+        }
+    }
+    if cpu::has_avx2() {
+        let mut a = Sha1::new();
+        let mut b = Sha1::new();
+        a.shani = false;
+        a.avx2 = false;
+        b.shani = false;
+        b.avx2 = true;
+        a.write(data.as_slice());
+        b.write(data.as_slice());
+        assert_eq!(a.digest(), b.digest());
+    }
+}
+
+#[test]
+fn test_sha1_collision_detection() {
+    use serialize::hex::FromHex;
+    // The first 320 bytes of the two SHAttered PDFs (shattered.io): a
+    // common prefix, then two blocks that differ between the files but
+    // leave SHA-1 in the same state.
+    let prefix = concat!(
+        "255044462d312e330a25e2e3cfd30a0a0a312030206f626a0a3c3c2f57696474",
+        "682032203020522f4865696768742033203020522f547970652034203020522f",
+        "537562747970652035203020522f46696c7465722036203020522f436f6c6f72",
+        "53706163652037203020522f4c656e6774682038203020522f42697473506572",
+        "436f6d706f6e656e7420383e3e0a73747265616d0affd8fffe00245348412d31",
+        "20697320646561642121212121852fec092339759c39b1a1c63c4c97e1fffe01").from_hex().unwrap();
+    let mut one = prefix.clone();
+    one.push_all(concat!(
+        "7346dc9166b67e118f029ab621b2560ff9ca67cca8c7f85ba84c79030c2b3de2",
+        "18f86db3a90901d5df45c14f26fedfb3dc38e96ac22fe7bd728f0e45bce046d2",
+        "3c570feb141398bb552ef5a0a82be331fea48037b8b5d71f0e332edf93ac3500",
+        "eb4ddc0decc1a864790c782c76215660dd309791d06bd0af3f98cda4bc4629b1").from_hex().unwrap());
+    let mut two = prefix.clone();
+    two.push_all(concat!(
+        "7f46dc93a6b67e013b029aaa1db2560b45ca67d688c7f84b8c4c791fe02b3df6",
+        "14f86db1690901c56b45c1530afedfb76038e972722fe7ad728f0e4904e046c2",
+        "30570fe9d41398abe12ef5bc942be33542a4802d98b5d70f2a332ec37fac3514",
+        "e74ddc0f2cc1a874cd0c78305a21566461309789606bd0bf3f98cda8044629a1").from_hex().unwrap());
+
+    let mut h = Sha1::new();
+    h.write(one.as_slice());
+    assert_eq!(h.hexdigest(), ~"f92d74e3874587aaf443d1db961d4e26dde13e9c");
+    assert!(!h.collision_detected());
+    h.reset();
+    h.write(two.as_slice());
+    assert_eq!(h.hexdigest(), ~"f92d74e3874587aaf443d1db961d4e26dde13e9c");
+
+    // either half is caught and gets its own safe hash
+    let mut a = Sha1::new_collision_detecting();
+    let mut b = Sha1::new_collision_detecting();
+    a.write(one.as_slice());
+    b.write(two.as_slice());
+    assert!(a.collision_detected());
+    assert!(b.collision_detected());
+    assert_eq!(a.hexdigest(), ~"7117b3cb9225aaf0d8ef1a40e493957b0bf8693d");
+    assert_eq!(b.hexdigest(), ~"29f38ae9fd98e2931120fa0bf213e024250d3f6a");
+    let mut d = Sha1::new_collision_detecting();
+    assert_eq!(d.sum_oneshot(one.as_slice()).as_slice(), a.digest().as_slice());
+    assert!(d.collision);
+
+    // without the safe hash the digest is plain SHA-1
+    a.reset();
+    assert!(!a.collision_detected());
+    a.set_safe_hash(false);
+    a.write(two.as_slice());
+    assert!(a.collision_detected());
+    assert_eq!(a.hexdigest(), ~"f92d74e3874587aaf443d1db961d4e26dde13e9c");
+
+    // ordinary messages are left alone
+    let mut c = Sha1::new_collision_detecting();
+    c.write(prefix.as_slice());
+    assert!(!c.collision_detected());
+    assert_eq!(c.hexdigest(), ~"c09e9957c11f0ef315d34b567c9284d12a9e5f48");
+    c.reset();
+    c.write_str("welcome to china".repeat(10001));
+    assert!(!c.collision_detected());
+    assert_eq!(c.hexdigest(), ~"da3884df7c84378ebf72b86e3fe43b2a4664d73a");
+}
@@ -1,6 +1,13 @@
+use std::io::IoResult;
 use std::mem;
+use std::slice;
+use super::*;
+use super::cpu;
+use super::multibuf;
+use super::super::hash::*;
 
 
+#[deriving(Eq, Clone, Show)]
 pub enum Sha2Varient {
     Sha_224,
     Sha_256,
@@ -10,6 +17,1992 @@ pub enum Sha2Varient {
     Sha_512_256,
 }
 
+impl Sha2Varient {
+    /// Length of the digest in bytes.
+    pub fn size(&self) -> uint {
+        match *self {
+            Sha_224 | Sha_512_224 => 28,
+            Sha_256 | Sha_512_256 => 32,
+            Sha_384 => 48,
+            Sha_512 => 64,
+        }
+    }
+
+    /// SHA-224 and SHA-256 work on 512-bit blocks, the SHA-512 family on 1024-bit ones.
+    pub fn block_size(&self) -> uint {
+        match *self {
+            Sha_224 | Sha_256 => 64,
+            _ => 128,
+        }
+    }
+
+    // 32-bit variants keep their state words in the low half.
+    fn init(&self) -> [u64, ..8] {
+        match *self {
+            Sha_224 => init224,
+            Sha_256 => init256,
+            Sha_384 => init384,
+            Sha_512 => init512,
+            Sha_512_224 => init512_224,
+            Sha_512_256 => init512_256,
+        }
+    }
+}
+
+
+static init224: [u64, ..8] = [0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939,
+                              0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4];
+static init256: [u64, ..8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                              0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+static init384: [u64, ..8] = [0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+                              0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4];
+static init512: [u64, ..8] = [0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+                              0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179];
+static init512_224: [u64, ..8] = [0x8c3d37c819544da2, 0x73e1996689dcd4d6, 0x1dfab7ae32ff9c82, 0x679dd514582f9fcf,
+                                  0x0f6d2b697bd44da8, 0x77e36f7304c48942, 0x3f9d85a86a1d36c8, 0x1112e6ad91d692a1];
+static init512_256: [u64, ..8] = [0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151, 0x963877195940eabd,
+                                  0x96283ee2a88effe3, 0xbe5e1e2553863992, 0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2];
+
+static K256: [u32, ..64] = [0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+                            0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+                            0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+                            0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+                            0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+                            0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+                            0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+                            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+                            0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+                            0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+                            0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+                            0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+                            0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+                            0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+                            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+                            0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2];
+
+static K512: [u64, ..80] = [0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+                            0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+                            0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+                            0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+                            0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+                            0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+                            0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+                            0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+                            0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+                            0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+                            0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+                            0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+                            0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+                            0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+                            0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+                            0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+                            0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+                            0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+                            0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+                            0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817];
+
+static bswap32Mask: [u8, ..16] = [3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8, 15, 14, 13, 12];
+
+#[inline]
+fn rotr32(x: u32, n: uint) -> u32 {
+    (x >> n) | (x << (32 - n))
+}
+
+#[inline]
+fn rotr64(x: u64, n: uint) -> u64 {
+    (x >> n) | (x << (64 - n))
+}
+
+
+pub struct Sha2 {
+    varient: Sha2Varient,
+    h: [u64, ..8],
+    x: Vec<u8>,
+    len: uint,
+    // whether block256 uses the SHA extensions, the AVX2 message schedule
+    // or the ARMv8 SHA256 instructions
+    shani: bool,
+    avx2: bool,
+    armv8: bool,
+}
+
+// deep clone
+impl Clone for Sha2 {
+    fn clone(&self) -> Sha2 {
+        Sha2 {
+            varient: self.varient,
+            h: self.h,
+            x: self.x.clone(),
+            len: self.len,
+            shani: self.shani,
+            avx2: self.avx2,
+            armv8: self.armv8,
+        }
+    }
+}
+
+impl Sha2 {
+    pub fn new(varient: Sha2Varient) -> Sha2 {
+        let mut ret = Sha2 { varient: varient,
+                             h: [0u64, ..8],
+                             x: Vec::new(),
+                             len: 0,
+                             shani: cpu::has_sha(),
+                             avx2: cpu::has_avx2(),
+                             armv8: cpu::has_sha2() };
+        ret.reset();
+        ret
+    }
+
+    pub fn varient(&self) -> Sha2Varient {
+        self.varient
+    }
+
+    fn block(&mut self, buf: &[u8]) {
+        match self.varient {
+            Sha_224 | Sha_256 => self.block256(buf),
+            _ => self.block512(buf),
+        }
+    }
+
+    fn block256(&mut self, buf: &[u8]) {
+        assert!(buf.len() % 64 == 0);
+        let mut h = [0u32, ..8];
+        for i in range(0, 8u) {
+            h[i] = self.h[i] as u32;
+        }
+        if self.shani {
+            block256_shani(&mut h, buf);
+        } else if self.armv8 {
+            block256_armv8(&mut h, buf);
+        } else if self.avx2 {
+            block256_avx2_schedule(&mut h, buf);
+        } else {
+            block256_generic(&mut h, buf);
+        }
+        for i in range(0, 8u) {
+            self.h[i] = h[i] as u64;
+        }
+    }
+
+    fn block512(&mut self, buf: &[u8]) {
+        assert!(buf.len() % 128 == 0);
+        let mut w = [0u64, ..80];
+        let mut h = self.h;
+        for p in buf.chunks(128) {
+            for i in range(0u, 16u) {
+                let j = i * 8;
+                let mut v = 0u64;
+                for k in range(0u, 8u) {
+                    v = v << 8 | p[j+k] as u64;
+                }
+                w[i] = v;
+            }
+            for i in range(16u, 80u) {
+                let s0 = rotr64(w[i-15], 1) ^ rotr64(w[i-15], 8) ^ (w[i-15] >> 7);
+                let s1 = rotr64(w[i-2], 19) ^ rotr64(w[i-2], 61) ^ (w[i-2] >> 6);
+                w[i] = w[i-16] + s0 + w[i-7] + s1;
+            }
+            let (mut a, mut b, mut c, mut d) = (h[0], h[1], h[2], h[3]);
+            let (mut e, mut f, mut g, mut hh) = (h[4], h[5], h[6], h[7]);
+            for i in range(0, 80u) {
+                let t1 = hh + (rotr64(e, 14) ^ rotr64(e, 18) ^ rotr64(e, 41)) +
+                    ((e & f) ^ ((! e) & g)) + K512[i] + w[i];
+                let t2 = (rotr64(a, 28) ^ rotr64(a, 34) ^ rotr64(a, 39)) +
+                    ((a & b) ^ (a & c) ^ (b & c));
+                hh = g;
+                g = f;
+                f = e;
+                e = d + t1;
+                d = c;
+                c = b;
+                b = a;
+                a = t1 + t2;
+            }
+            h[0] += a;
+            h[1] += b;
+            h[2] += c;
+            h[3] += d;
+            h[4] += e;
+            h[5] += f;
+            h[6] += g;
+            h[7] += hh;
+        }
+        self.h = h;
+    }
+
+    fn checksum(&mut self) -> ~[u8] {
+        let block_size = self.varient.block_size();
+        // the message length is appended as a 64-bit or 128-bit big-endian integer
+        let len_size = block_size / 8;
+        let mut tmp = slice::from_elem(block_size, 0u8);
+        tmp[0] = 0x80;
+        let len = self.len;
+        let rem = len % block_size;
+        if rem < block_size - len_size {
+            self.write(tmp.slice_to(block_size - len_size - rem));
+        } else {
+            self.write(tmp.slice_to(2 * block_size - len_size - rem));
+        }
+        let len = (len as u64) << 3;
+        for i in range(0, len_size) {
+            tmp[i] = if i + 8 < len_size { 0 } else { (len >> (8 * (len_size - 1 - i))) as u8 };
+        }
+        self.write(tmp.slice_to(len_size));
+        assert_eq!(self.x.len(), 0);
+
+        let word = if block_size == 64 { 4u } else { 8u };
+        let mut ret = Vec::with_capacity(8 * word);
+        for i in range(0, 8u) {
+            for j in range(0, word) {
+                ret.push((self.h[i] >> (8 * (word - 1 - j))) as u8);
+            }
+        }
+        ret.as_slice().slice_to(self.varient.size()).to_owned()
+    }
+
+    // one pass over a whole SHA-256 message, straight from the input slice
+    fn sum256_oneshot(&mut self, msg: &[u8]) -> [u8, ..32] {
+        let full = msg.len() - msg.len() % 64;
+        self.block256(msg.slice_to(full));
+        let tail = msg.slice_from(full);
+        let mut tmp = [0u8, ..128];
+        for i in range(0, tail.len()) {
+            tmp[i] = tail[i];
+        }
+        tmp[tail.len()] = 0x80;
+        let n = if tail.len() < 56 { 64 } else { 128 };
+        let len = (msg.len() as u64) << 3;
+        for i in range(0, 8u) {
+            tmp[n - 8 + i] = (len >> (56 - 8*i)) as u8;
+        }
+        self.block256(tmp.slice_to(n));
+
+        let mut ret = [0u8, ..32];
+        for i in range(0, 8u) {
+            for j in range(0, 4u) {
+                ret[4*i + j] = (self.h[i] >> (24 - 8*j)) as u8;
+            }
+        }
+        ret
+    }
+}
+
+fn block256_generic(h: &mut [u32, ..8], buf: &[u8]) {
+    let mut w = [0u32, ..64];
+    for p in buf.chunks(64) {
+        for i in range(0u, 16u) {
+            let j = i * 4;
+            w[i] = p[j] as u32 << 24 | p[j+1] as u32 << 16 | p[j+2] as u32 << 8 | p[j+3] as u32;
+        }
+        for i in range(16u, 64u) {
+            let s0 = rotr32(w[i-15], 7) ^ rotr32(w[i-15], 18) ^ (w[i-15] >> 3);
+            let s1 = rotr32(w[i-2], 17) ^ rotr32(w[i-2], 19) ^ (w[i-2] >> 10);
+            w[i] = w[i-16] + s0 + w[i-7] + s1;
+        }
+        rounds256(h, w.as_slice(), 0, 1);
+    }
+}
+
+// The 64 rounds of one block, with message word i in w[lanes*i + lane].
+fn rounds256(h: &mut [u32, ..8], w: &[u32], lane: uint, lanes: uint) {
+    let (mut a, mut b, mut c, mut d) = (h[0], h[1], h[2], h[3]);
+    let (mut e, mut f, mut g, mut hh) = (h[4], h[5], h[6], h[7]);
+    for i in range(0, 64u) {
+        let t1 = hh + (rotr32(e, 6) ^ rotr32(e, 11) ^ rotr32(e, 25)) +
+            ((e & f) ^ ((! e) & g)) + K256[i] + w[lanes * i + lane];
+        let t2 = (rotr32(a, 2) ^ rotr32(a, 13) ^ rotr32(a, 22)) +
+            ((a & b) ^ (a & c) ^ (b & c));
+        hh = g;
+        g = f;
+        f = e;
+        e = d + t1;
+        d = c;
+        c = b;
+        b = a;
+        a = t1 + t2;
+    }
+    h[0] += a;
+    h[1] += b;
+    h[2] += c;
+    h[3] += d;
+    h[4] += e;
+    h[5] += f;
+    h[6] += g;
+    h[7] += hh;
+}
+
+// The SHA extensions keep the state as ABEF in xmm1 and CDGH in xmm2.
+// sha256rnds2 runs two rounds with the message words plus constants in
+// the low half of xmm0, so each group of four rounds is two of them with
+// pshufd moving the upper words down in between. sha256msg1, palignr,
+// paddd and sha256msg2 expand the message schedule four words at a time.
+// This follows Intel's sample code for the SHA extensions.
+#[cfg(target_arch = "x86_64")]
+fn block256_shani(h: &mut [u32, ..8], buf: &[u8]) {
+    if buf.len() == 0 {
+        return;
+    }
+    unsafe {
+        asm!("mov $1, %rax
+              movdqu ($0), %xmm1
+              movdqu 16($0), %xmm2
+              pshufd $$0xb1, %xmm1, %xmm1
+              pshufd $$0x1b, %xmm2, %xmm2
+              movdqa %xmm1, %xmm7
+              palignr $$8, %xmm2, %xmm1
+              pblendw $$0xf0, %xmm7, %xmm2
+              movdqu ($3), %xmm8
+              1:
+              movdqa %xmm1, %xmm9
+              movdqa %xmm2, %xmm10
+              movdqu (%rax), %xmm0
+              pshufb %xmm8, %xmm0
+              movdqa %xmm0, %xmm3
+              paddd ($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              movdqu 16(%rax), %xmm0
+              pshufb %xmm8, %xmm0
+              movdqa %xmm0, %xmm4
+              paddd 16($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm4, %xmm3
+              movdqu 32(%rax), %xmm0
+              pshufb %xmm8, %xmm0
+              movdqa %xmm0, %xmm5
+              paddd 32($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm5, %xmm4
+              movdqu 48(%rax), %xmm0
+              pshufb %xmm8, %xmm0
+              movdqa %xmm0, %xmm6
+              paddd 48($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm6, %xmm7
+              palignr $$4, %xmm5, %xmm7
+              paddd %xmm7, %xmm3
+              sha256msg2 %xmm6, %xmm3
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm6, %xmm5
+              movdqa %xmm3, %xmm0
+              paddd 64($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm3, %xmm7
+              palignr $$4, %xmm6, %xmm7
+              paddd %xmm7, %xmm4
+              sha256msg2 %xmm3, %xmm4
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm3, %xmm6
+              movdqa %xmm4, %xmm0
+              paddd 80($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm4, %xmm7
+              palignr $$4, %xmm3, %xmm7
+              paddd %xmm7, %xmm5
+              sha256msg2 %xmm4, %xmm5
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm4, %xmm3
+              movdqa %xmm5, %xmm0
+              paddd 96($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm5, %xmm7
+              palignr $$4, %xmm4, %xmm7
+              paddd %xmm7, %xmm6
+              sha256msg2 %xmm5, %xmm6
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm5, %xmm4
+              movdqa %xmm6, %xmm0
+              paddd 112($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm6, %xmm7
+              palignr $$4, %xmm5, %xmm7
+              paddd %xmm7, %xmm3
+              sha256msg2 %xmm6, %xmm3
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm6, %xmm5
+              movdqa %xmm3, %xmm0
+              paddd 128($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm3, %xmm7
+              palignr $$4, %xmm6, %xmm7
+              paddd %xmm7, %xmm4
+              sha256msg2 %xmm3, %xmm4
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm3, %xmm6
+              movdqa %xmm4, %xmm0
+              paddd 144($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm4, %xmm7
+              palignr $$4, %xmm3, %xmm7
+              paddd %xmm7, %xmm5
+              sha256msg2 %xmm4, %xmm5
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm4, %xmm3
+              movdqa %xmm5, %xmm0
+              paddd 160($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm5, %xmm7
+              palignr $$4, %xmm4, %xmm7
+              paddd %xmm7, %xmm6
+              sha256msg2 %xmm5, %xmm6
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm5, %xmm4
+              movdqa %xmm6, %xmm0
+              paddd 176($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm6, %xmm7
+              palignr $$4, %xmm5, %xmm7
+              paddd %xmm7, %xmm3
+              sha256msg2 %xmm6, %xmm3
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm6, %xmm5
+              movdqa %xmm3, %xmm0
+              paddd 192($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm3, %xmm7
+              palignr $$4, %xmm6, %xmm7
+              paddd %xmm7, %xmm4
+              sha256msg2 %xmm3, %xmm4
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              sha256msg1 %xmm3, %xmm6
+              movdqa %xmm4, %xmm0
+              paddd 208($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm4, %xmm7
+              palignr $$4, %xmm3, %xmm7
+              paddd %xmm7, %xmm5
+              sha256msg2 %xmm4, %xmm5
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              movdqa %xmm5, %xmm0
+              paddd 224($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              movdqa %xmm5, %xmm7
+              palignr $$4, %xmm4, %xmm7
+              paddd %xmm7, %xmm6
+              sha256msg2 %xmm5, %xmm6
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              movdqa %xmm6, %xmm0
+              paddd 240($4), %xmm0
+              sha256rnds2 %xmm1, %xmm2
+              pshufd $$0x0e, %xmm0, %xmm0
+              sha256rnds2 %xmm2, %xmm1
+              paddd %xmm9, %xmm1
+              paddd %xmm10, %xmm2
+              add $$64, %rax
+              cmp $2, %rax
+              jne 1b
+              pshufd $$0x1b, %xmm1, %xmm1
+              pshufd $$0xb1, %xmm2, %xmm2
+              movdqa %xmm1, %xmm7
+              pblendw $$0xf0, %xmm2, %xmm1
+              palignr $$8, %xmm7, %xmm2
+              movdqu %xmm1, ($0)
+              movdqu %xmm2, 16($0)"
+             :
+             : "r"(h.as_mut_ptr()), "r"(buf.as_ptr()),
+               "r"(buf.as_ptr().offset(buf.len() as int)), "r"(bswap32Mask.as_ptr()),
+               "r"(K256.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8",
+               "xmm9", "xmm10", "rax", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block256_shani(_h: &mut [u32, ..8], _buf: &[u8]) {
+    unreachable!();
+}
+
+// Without the SHA extensions, AVX2 expands the message schedules of up to
+// eight blocks at once, one block per lane as in the multi-buffer code,
+// and the rounds then run on each block in turn.
+fn block256_avx2_schedule(h: &mut [u32, ..8], buf: &[u8]) {
+    let mut w = [0u32, ..64 * 8];
+    for run in buf.chunks(8 * 64) {
+        let n = run.len() / 64;
+        for k in range(0, n) {
+            for i in range(0u, 16u) {
+                let j = 64 * k + 4 * i;
+                w[8 * i + k] = run[j] as u32 << 24 | run[j+1] as u32 << 16 |
+                    run[j+2] as u32 << 8 | run[j+3] as u32;
+            }
+        }
+        schedule256_avx2(w.as_mut_slice());
+        for k in range(0, n) {
+            rounds256(h, w.as_slice(), k, 8);
+        }
+    }
+}
+
+// Rows 16-63 of the message schedule of eight lanes, from rows 0-15.
+#[cfg(target_arch = "x86_64")]
+fn schedule256_avx2(w: &mut [u32]) {
+    assert!(w.len() == 64 * 8);
+    unsafe {
+        asm!("lea 512($0), %rdx
+              mov $$48, %eax
+              1:
+              vmovdqu -480(%rdx), %ymm0
+              vpslld $$25, %ymm0, %ymm3
+              vpsrld $$7, %ymm0, %ymm1
+              vpor %ymm3, %ymm1, %ymm1
+              vpslld $$14, %ymm0, %ymm3
+              vpsrld $$18, %ymm0, %ymm2
+              vpor %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vpsrld $$3, %ymm0, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vmovdqu -64(%rdx), %ymm0
+              vpslld $$15, %ymm0, %ymm3
+              vpsrld $$17, %ymm0, %ymm4
+              vpor %ymm3, %ymm4, %ymm4
+              vpslld $$13, %ymm0, %ymm3
+              vpsrld $$19, %ymm0, %ymm2
+              vpor %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm4, %ymm4
+              vpsrld $$10, %ymm0, %ymm2
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpaddd -224(%rdx), %ymm1, %ymm1
+              vpaddd -512(%rdx), %ymm1, %ymm1
+              vmovdqu %ymm1, (%rdx)
+              add $$32, %rdx
+              dec %eax
+              jnz 1b
+              vzeroupper"
+             :
+             : "r"(w.as_mut_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "rax", "rdx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn schedule256_avx2(_w: &mut [u32]) {
+    unreachable!();
+}
+
+// The ARMv8 SHA256 instructions keep the state as ABCD in v0 and EFGH in
+// v1. Each group of four rounds adds the constants to four message words,
+// runs sha256h and sha256h2 on them, with v2 keeping ABCD for the second,
+// and expands the next four words with sha256su0 and sha256su1.
+#[cfg(target_arch = "aarch64")]
+fn block256_armv8(h: &mut [u32, ..8], buf: &[u8]) {
+    if buf.len() == 0 {
+        return;
+    }
+    unsafe {
+        asm!("ld1 {v0.4s, v1.4s}, [$0]
+              mov x9, $1
+              1:
+              mov x10, $3
+              ld1 {v4.16b, v5.16b, v6.16b, v7.16b}, [x9], #64
+              rev32 v4.16b, v4.16b
+              rev32 v5.16b, v5.16b
+              rev32 v6.16b, v6.16b
+              rev32 v7.16b, v7.16b
+              mov v18.16b, v0.16b
+              mov v19.16b, v1.16b
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v4.4s
+              sha256su0 v4.4s, v5.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v4.4s, v6.4s, v7.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v5.4s
+              sha256su0 v5.4s, v6.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v5.4s, v7.4s, v4.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v6.4s
+              sha256su0 v6.4s, v7.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v6.4s, v4.4s, v5.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v7.4s
+              sha256su0 v7.4s, v4.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v7.4s, v5.4s, v6.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v4.4s
+              sha256su0 v4.4s, v5.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v4.4s, v6.4s, v7.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v5.4s
+              sha256su0 v5.4s, v6.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v5.4s, v7.4s, v4.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v6.4s
+              sha256su0 v6.4s, v7.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v6.4s, v4.4s, v5.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v7.4s
+              sha256su0 v7.4s, v4.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v7.4s, v5.4s, v6.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v4.4s
+              sha256su0 v4.4s, v5.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v4.4s, v6.4s, v7.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v5.4s
+              sha256su0 v5.4s, v6.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v5.4s, v7.4s, v4.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v6.4s
+              sha256su0 v6.4s, v7.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v6.4s, v4.4s, v5.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v7.4s
+              sha256su0 v7.4s, v4.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              sha256su1 v7.4s, v5.4s, v6.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v4.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v5.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v6.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              ld1 {v16.4s}, [x10], #16
+              add v16.4s, v16.4s, v7.4s
+              mov v2.16b, v0.16b
+              sha256h q0, q1, v16.4s
+              sha256h2 q1, q2, v16.4s
+              add v0.4s, v0.4s, v18.4s
+              add v1.4s, v1.4s, v19.4s
+              cmp x9, $2
+              b.ne 1b
+              st1 {v0.4s, v1.4s}, [$0]"
+             :
+             : "r"(h.as_mut_ptr()), "r"(buf.as_ptr()),
+               "r"(buf.as_ptr().offset(buf.len() as int)), "r"(K256.as_ptr())
+             : "v0", "v1", "v2", "v4", "v5", "v6", "v7", "v16", "v18", "v19", "x9", "x10",
+               "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn block256_armv8(_h: &mut [u32, ..8], _buf: &[u8]) {
+    unreachable!();
+}
+
+/// Returns the SHA-256 checksums of many independent messages.
+///
+/// See `md5::digest_many`; this is the same batch interface. Without AVX2
+/// a CPU with the SHA extensions hashes one message at a time, which is
+/// faster than four SSE2 lanes.
+pub fn digest_many(msgs: &[&[u8]]) -> Vec<[u8, ..32]> {
+    match multibuf::lanes() {
+        4 if cpu::has_sha() => digest_lanes(msgs, 1),
+        lanes => digest_lanes(msgs, lanes),
+    }
+}
+
+fn digest_lanes(msgs: &[&[u8]], lanes: uint) -> Vec<[u8, ..32]> {
+    let mut ret = Vec::with_capacity(msgs.len());
+    if lanes == 1 {
+        let mut h = Sha2::new(Sha_256);
+        for msg in msgs.iter() {
+            h.reset();
+            ret.push(h.sum256_oneshot(*msg));
+        }
+        return ret;
+    }
+    let mut init = [0u32, ..8];
+    for i in range(0, 8u) {
+        init[i] = init256[i] as u32;
+    }
+    let states = multibuf::hash_many(msgs, init.as_slice(), true, lanes, 64,
+                                     |s, w| block256_lanes(lanes, s, w));
+    for st in states.iter() {
+        let mut sum = [0u8, ..32];
+        for i in range(0, 32u) {
+            sum[i] = (st[i / 4] >> (24 - 8 * (i % 4))) as u8;
+        }
+        ret.push(sum);
+    }
+    ret
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block256_lanes(lanes: uint, state: &mut [u32], w: &mut [u32]) {
+    match lanes {
+        4 => block256_sse2(state, w),
+        8 => block256_avx2(state, w),
+        _ => block256_avx512(state, w),
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block256_lanes(_lanes: uint, _state: &mut [u32], _w: &mut [u32]) {
+    unreachable!();
+}
+
+// The multi-buffer kernels, one message per lane. They expand the message
+// schedule into rows 16-63 first, then run the rounds eight at a time in a
+// loop, with a-h in registers 0-7 and 8-11 as temporaries. With AVX-512
+// the rotations are vprord; otherwise they are two shifts and an or.
+#[cfg(target_arch = "x86_64")]
+fn block256_sse2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 8 * 4 && w.len() == 64 * 4);
+    unsafe {
+        asm!("lea 256($1), %rdx
+              mov $$48, %eax
+              1:
+              movdqu -240(%rdx), %xmm0
+              movdqa %xmm0, %xmm3
+              pslld $$25, %xmm3
+              movdqa %xmm0, %xmm1
+              psrld $$7, %xmm1
+              por %xmm3, %xmm1
+              movdqa %xmm0, %xmm3
+              pslld $$14, %xmm3
+              movdqa %xmm0, %xmm2
+              psrld $$18, %xmm2
+              por %xmm3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqa %xmm0, %xmm2
+              psrld $$3, %xmm2
+              pxor %xmm2, %xmm1
+              movdqu -32(%rdx), %xmm0
+              movdqa %xmm0, %xmm3
+              pslld $$15, %xmm3
+              movdqa %xmm0, %xmm4
+              psrld $$17, %xmm4
+              por %xmm3, %xmm4
+              movdqa %xmm0, %xmm3
+              pslld $$13, %xmm3
+              movdqa %xmm0, %xmm2
+              psrld $$19, %xmm2
+              por %xmm3, %xmm2
+              pxor %xmm2, %xmm4
+              movdqa %xmm0, %xmm2
+              psrld $$10, %xmm2
+              pxor %xmm2, %xmm4
+              paddd %xmm4, %xmm1
+              movdqu -112(%rdx), %xmm3
+              paddd %xmm3, %xmm1
+              movdqu -256(%rdx), %xmm3
+              paddd %xmm3, %xmm1
+              movdqu %xmm1, (%rdx)
+              add $$16, %rdx
+              dec %eax
+              jnz 1b
+              movdqu ($0), %xmm0
+              movdqu 16($0), %xmm1
+              movdqu 32($0), %xmm2
+              movdqu 48($0), %xmm3
+              movdqu 64($0), %xmm4
+              movdqu 80($0), %xmm5
+              movdqu 96($0), %xmm6
+              movdqu 112($0), %xmm7
+              mov $1, %rdx
+              mov $2, %rcx
+              mov $$8, %eax
+              2:
+              movdqa %xmm4, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm4, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm4, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm4, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm4, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm4, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm5, %xmm9
+              pxor %xmm6, %xmm9
+              pand %xmm4, %xmm9
+              pxor %xmm6, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm7
+              movd (%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm7
+              movdqu (%rdx), %xmm9
+              paddd %xmm9, %xmm7
+              paddd %xmm7, %xmm3
+              movdqa %xmm0, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm0, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm0, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm0, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm0, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm0, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm0, %xmm9
+              por %xmm1, %xmm9
+              pand %xmm2, %xmm9
+              movdqa %xmm0, %xmm11
+              pand %xmm1, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm7
+              movdqa %xmm3, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm3, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm3, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm3, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm3, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm3, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm4, %xmm9
+              pxor %xmm5, %xmm9
+              pand %xmm3, %xmm9
+              pxor %xmm5, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm6
+              movd 4(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm6
+              movdqu 16(%rdx), %xmm9
+              paddd %xmm9, %xmm6
+              paddd %xmm6, %xmm2
+              movdqa %xmm7, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm7, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm7, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm7, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm7, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm7, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm7, %xmm9
+              por %xmm0, %xmm9
+              pand %xmm1, %xmm9
+              movdqa %xmm7, %xmm11
+              pand %xmm0, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm6
+              movdqa %xmm2, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm2, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm2, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm2, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm2, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm2, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm3, %xmm9
+              pxor %xmm4, %xmm9
+              pand %xmm2, %xmm9
+              pxor %xmm4, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm5
+              movd 8(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm5
+              movdqu 32(%rdx), %xmm9
+              paddd %xmm9, %xmm5
+              paddd %xmm5, %xmm1
+              movdqa %xmm6, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm6, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm6, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm6, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm6, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm6, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm6, %xmm9
+              por %xmm7, %xmm9
+              pand %xmm0, %xmm9
+              movdqa %xmm6, %xmm11
+              pand %xmm7, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm5
+              movdqa %xmm1, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm1, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm1, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm1, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm1, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm1, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm2, %xmm9
+              pxor %xmm3, %xmm9
+              pand %xmm1, %xmm9
+              pxor %xmm3, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm4
+              movd 12(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm4
+              movdqu 48(%rdx), %xmm9
+              paddd %xmm9, %xmm4
+              paddd %xmm4, %xmm0
+              movdqa %xmm5, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm5, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm5, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm5, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm5, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm5, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm5, %xmm9
+              por %xmm6, %xmm9
+              pand %xmm7, %xmm9
+              movdqa %xmm5, %xmm11
+              pand %xmm6, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm4
+              movdqa %xmm0, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm0, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm0, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm0, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm0, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm0, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm1, %xmm9
+              pxor %xmm2, %xmm9
+              pand %xmm0, %xmm9
+              pxor %xmm2, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm3
+              movd 16(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm3
+              movdqu 64(%rdx), %xmm9
+              paddd %xmm9, %xmm3
+              paddd %xmm3, %xmm7
+              movdqa %xmm4, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm4, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm4, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm4, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm4, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm4, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm4, %xmm9
+              por %xmm5, %xmm9
+              pand %xmm6, %xmm9
+              movdqa %xmm4, %xmm11
+              pand %xmm5, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm3
+              movdqa %xmm7, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm7, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm7, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm7, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm7, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm7, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm0, %xmm9
+              pxor %xmm1, %xmm9
+              pand %xmm7, %xmm9
+              pxor %xmm1, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm2
+              movd 20(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm2
+              movdqu 80(%rdx), %xmm9
+              paddd %xmm9, %xmm2
+              paddd %xmm2, %xmm6
+              movdqa %xmm3, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm3, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm3, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm3, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm3, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm3, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm3, %xmm9
+              por %xmm4, %xmm9
+              pand %xmm5, %xmm9
+              movdqa %xmm3, %xmm11
+              pand %xmm4, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm2
+              movdqa %xmm6, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm6, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm6, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm6, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm6, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm6, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm7, %xmm9
+              pxor %xmm0, %xmm9
+              pand %xmm6, %xmm9
+              pxor %xmm0, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm1
+              movd 24(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm1
+              movdqu 96(%rdx), %xmm9
+              paddd %xmm9, %xmm1
+              paddd %xmm1, %xmm5
+              movdqa %xmm2, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm2, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm2, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm2, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm2, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm2, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm2, %xmm9
+              por %xmm3, %xmm9
+              pand %xmm4, %xmm9
+              movdqa %xmm2, %xmm11
+              pand %xmm3, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm1
+              movdqa %xmm5, %xmm10
+              pslld $$26, %xmm10
+              movdqa %xmm5, %xmm8
+              psrld $$6, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm5, %xmm10
+              pslld $$21, %xmm10
+              movdqa %xmm5, %xmm9
+              psrld $$11, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm5, %xmm10
+              pslld $$7, %xmm10
+              movdqa %xmm5, %xmm9
+              psrld $$25, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm6, %xmm9
+              pxor %xmm7, %xmm9
+              pand %xmm5, %xmm9
+              pxor %xmm7, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm0
+              movd 28(%rcx), %xmm9
+              pshufd $$0, %xmm9, %xmm9
+              paddd %xmm9, %xmm0
+              movdqu 112(%rdx), %xmm9
+              paddd %xmm9, %xmm0
+              paddd %xmm0, %xmm4
+              movdqa %xmm1, %xmm10
+              pslld $$30, %xmm10
+              movdqa %xmm1, %xmm8
+              psrld $$2, %xmm8
+              por %xmm10, %xmm8
+              movdqa %xmm1, %xmm10
+              pslld $$19, %xmm10
+              movdqa %xmm1, %xmm9
+              psrld $$13, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm1, %xmm10
+              pslld $$10, %xmm10
+              movdqa %xmm1, %xmm9
+              psrld $$22, %xmm9
+              por %xmm10, %xmm9
+              pxor %xmm9, %xmm8
+              movdqa %xmm1, %xmm9
+              por %xmm2, %xmm9
+              pand %xmm3, %xmm9
+              movdqa %xmm1, %xmm11
+              pand %xmm2, %xmm11
+              por %xmm11, %xmm9
+              paddd %xmm9, %xmm8
+              paddd %xmm8, %xmm0
+              add $$32, %rcx
+              add $$128, %rdx
+              dec %eax
+              jnz 2b
+              movdqu ($0), %xmm8
+              paddd %xmm8, %xmm0
+              movdqu %xmm0, ($0)
+              movdqu 16($0), %xmm8
+              paddd %xmm8, %xmm1
+              movdqu %xmm1, 16($0)
+              movdqu 32($0), %xmm8
+              paddd %xmm8, %xmm2
+              movdqu %xmm2, 32($0)
+              movdqu 48($0), %xmm8
+              paddd %xmm8, %xmm3
+              movdqu %xmm3, 48($0)
+              movdqu 64($0), %xmm8
+              paddd %xmm8, %xmm4
+              movdqu %xmm4, 64($0)
+              movdqu 80($0), %xmm8
+              paddd %xmm8, %xmm5
+              movdqu %xmm5, 80($0)
+              movdqu 96($0), %xmm8
+              paddd %xmm8, %xmm6
+              movdqu %xmm6, 96($0)
+              movdqu 112($0), %xmm8
+              paddd %xmm8, %xmm7
+              movdqu %xmm7, 112($0)"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K256.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9",
+               "xmm10", "xmm11", "rax", "rcx", "rdx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block256_avx2(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 8 * 8 && w.len() == 64 * 8);
+    unsafe {
+        asm!("lea 512($1), %rdx
+              mov $$48, %eax
+              1:
+              vmovdqu -480(%rdx), %ymm0
+              vpslld $$25, %ymm0, %ymm3
+              vpsrld $$7, %ymm0, %ymm1
+              vpor %ymm3, %ymm1, %ymm1
+              vpslld $$14, %ymm0, %ymm3
+              vpsrld $$18, %ymm0, %ymm2
+              vpor %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vpsrld $$3, %ymm0, %ymm2
+              vpxor %ymm2, %ymm1, %ymm1
+              vmovdqu -64(%rdx), %ymm0
+              vpslld $$15, %ymm0, %ymm3
+              vpsrld $$17, %ymm0, %ymm4
+              vpor %ymm3, %ymm4, %ymm4
+              vpslld $$13, %ymm0, %ymm3
+              vpsrld $$19, %ymm0, %ymm2
+              vpor %ymm3, %ymm2, %ymm2
+              vpxor %ymm2, %ymm4, %ymm4
+              vpsrld $$10, %ymm0, %ymm2
+              vpxor %ymm2, %ymm4, %ymm4
+              vpaddd %ymm4, %ymm1, %ymm1
+              vpaddd -224(%rdx), %ymm1, %ymm1
+              vpaddd -512(%rdx), %ymm1, %ymm1
+              vmovdqu %ymm1, (%rdx)
+              add $$32, %rdx
+              dec %eax
+              jnz 1b
+              vmovdqu ($0), %ymm0
+              vmovdqu 32($0), %ymm1
+              vmovdqu 64($0), %ymm2
+              vmovdqu 96($0), %ymm3
+              vmovdqu 128($0), %ymm4
+              vmovdqu 160($0), %ymm5
+              vmovdqu 192($0), %ymm6
+              vmovdqu 224($0), %ymm7
+              mov $1, %rdx
+              mov $2, %rcx
+              mov $$8, %eax
+              2:
+              vpslld $$26, %ymm4, %ymm10
+              vpsrld $$6, %ymm4, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm4, %ymm10
+              vpsrld $$11, %ymm4, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm4, %ymm10
+              vpsrld $$25, %ymm4, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm6, %ymm5, %ymm9
+              vpand %ymm4, %ymm9, %ymm9
+              vpxor %ymm6, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm7, %ymm7
+              vpbroadcastd (%rcx), %ymm9
+              vpaddd %ymm9, %ymm7, %ymm7
+              vpaddd (%rdx), %ymm7, %ymm7
+              vpaddd %ymm7, %ymm3, %ymm3
+              vpslld $$30, %ymm0, %ymm10
+              vpsrld $$2, %ymm0, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm0, %ymm10
+              vpsrld $$13, %ymm0, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm0, %ymm10
+              vpsrld $$22, %ymm0, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm1, %ymm0, %ymm9
+              vpand %ymm2, %ymm9, %ymm9
+              vpand %ymm1, %ymm0, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm7, %ymm7
+              vpslld $$26, %ymm3, %ymm10
+              vpsrld $$6, %ymm3, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm3, %ymm10
+              vpsrld $$11, %ymm3, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm3, %ymm10
+              vpsrld $$25, %ymm3, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm5, %ymm4, %ymm9
+              vpand %ymm3, %ymm9, %ymm9
+              vpxor %ymm5, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm6, %ymm6
+              vpbroadcastd 4(%rcx), %ymm9
+              vpaddd %ymm9, %ymm6, %ymm6
+              vpaddd 32(%rdx), %ymm6, %ymm6
+              vpaddd %ymm6, %ymm2, %ymm2
+              vpslld $$30, %ymm7, %ymm10
+              vpsrld $$2, %ymm7, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm7, %ymm10
+              vpsrld $$13, %ymm7, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm7, %ymm10
+              vpsrld $$22, %ymm7, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm0, %ymm7, %ymm9
+              vpand %ymm1, %ymm9, %ymm9
+              vpand %ymm0, %ymm7, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm6, %ymm6
+              vpslld $$26, %ymm2, %ymm10
+              vpsrld $$6, %ymm2, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm2, %ymm10
+              vpsrld $$11, %ymm2, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm2, %ymm10
+              vpsrld $$25, %ymm2, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm4, %ymm3, %ymm9
+              vpand %ymm2, %ymm9, %ymm9
+              vpxor %ymm4, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm5, %ymm5
+              vpbroadcastd 8(%rcx), %ymm9
+              vpaddd %ymm9, %ymm5, %ymm5
+              vpaddd 64(%rdx), %ymm5, %ymm5
+              vpaddd %ymm5, %ymm1, %ymm1
+              vpslld $$30, %ymm6, %ymm10
+              vpsrld $$2, %ymm6, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm6, %ymm10
+              vpsrld $$13, %ymm6, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm6, %ymm10
+              vpsrld $$22, %ymm6, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm7, %ymm6, %ymm9
+              vpand %ymm0, %ymm9, %ymm9
+              vpand %ymm7, %ymm6, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm5, %ymm5
+              vpslld $$26, %ymm1, %ymm10
+              vpsrld $$6, %ymm1, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm1, %ymm10
+              vpsrld $$11, %ymm1, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm1, %ymm10
+              vpsrld $$25, %ymm1, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm3, %ymm2, %ymm9
+              vpand %ymm1, %ymm9, %ymm9
+              vpxor %ymm3, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpbroadcastd 12(%rcx), %ymm9
+              vpaddd %ymm9, %ymm4, %ymm4
+              vpaddd 96(%rdx), %ymm4, %ymm4
+              vpaddd %ymm4, %ymm0, %ymm0
+              vpslld $$30, %ymm5, %ymm10
+              vpsrld $$2, %ymm5, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm5, %ymm10
+              vpsrld $$13, %ymm5, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm5, %ymm10
+              vpsrld $$22, %ymm5, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm6, %ymm5, %ymm9
+              vpand %ymm7, %ymm9, %ymm9
+              vpand %ymm6, %ymm5, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm4, %ymm4
+              vpslld $$26, %ymm0, %ymm10
+              vpsrld $$6, %ymm0, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm0, %ymm10
+              vpsrld $$11, %ymm0, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm0, %ymm10
+              vpsrld $$25, %ymm0, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm2, %ymm1, %ymm9
+              vpand %ymm0, %ymm9, %ymm9
+              vpxor %ymm2, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpbroadcastd 16(%rcx), %ymm9
+              vpaddd %ymm9, %ymm3, %ymm3
+              vpaddd 128(%rdx), %ymm3, %ymm3
+              vpaddd %ymm3, %ymm7, %ymm7
+              vpslld $$30, %ymm4, %ymm10
+              vpsrld $$2, %ymm4, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm4, %ymm10
+              vpsrld $$13, %ymm4, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm4, %ymm10
+              vpsrld $$22, %ymm4, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm5, %ymm4, %ymm9
+              vpand %ymm6, %ymm9, %ymm9
+              vpand %ymm5, %ymm4, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm3, %ymm3
+              vpslld $$26, %ymm7, %ymm10
+              vpsrld $$6, %ymm7, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm7, %ymm10
+              vpsrld $$11, %ymm7, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm7, %ymm10
+              vpsrld $$25, %ymm7, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm1, %ymm0, %ymm9
+              vpand %ymm7, %ymm9, %ymm9
+              vpxor %ymm1, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpbroadcastd 20(%rcx), %ymm9
+              vpaddd %ymm9, %ymm2, %ymm2
+              vpaddd 160(%rdx), %ymm2, %ymm2
+              vpaddd %ymm2, %ymm6, %ymm6
+              vpslld $$30, %ymm3, %ymm10
+              vpsrld $$2, %ymm3, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm3, %ymm10
+              vpsrld $$13, %ymm3, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm3, %ymm10
+              vpsrld $$22, %ymm3, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm4, %ymm3, %ymm9
+              vpand %ymm5, %ymm9, %ymm9
+              vpand %ymm4, %ymm3, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm2, %ymm2
+              vpslld $$26, %ymm6, %ymm10
+              vpsrld $$6, %ymm6, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm6, %ymm10
+              vpsrld $$11, %ymm6, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm6, %ymm10
+              vpsrld $$25, %ymm6, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm0, %ymm7, %ymm9
+              vpand %ymm6, %ymm9, %ymm9
+              vpxor %ymm0, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpbroadcastd 24(%rcx), %ymm9
+              vpaddd %ymm9, %ymm1, %ymm1
+              vpaddd 192(%rdx), %ymm1, %ymm1
+              vpaddd %ymm1, %ymm5, %ymm5
+              vpslld $$30, %ymm2, %ymm10
+              vpsrld $$2, %ymm2, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm2, %ymm10
+              vpsrld $$13, %ymm2, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm2, %ymm10
+              vpsrld $$22, %ymm2, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm3, %ymm2, %ymm9
+              vpand %ymm4, %ymm9, %ymm9
+              vpand %ymm3, %ymm2, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm1, %ymm1
+              vpslld $$26, %ymm5, %ymm10
+              vpsrld $$6, %ymm5, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$21, %ymm5, %ymm10
+              vpsrld $$11, %ymm5, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$7, %ymm5, %ymm10
+              vpsrld $$25, %ymm5, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpxor %ymm7, %ymm6, %ymm9
+              vpand %ymm5, %ymm9, %ymm9
+              vpxor %ymm7, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm0, %ymm0
+              vpbroadcastd 28(%rcx), %ymm9
+              vpaddd %ymm9, %ymm0, %ymm0
+              vpaddd 224(%rdx), %ymm0, %ymm0
+              vpaddd %ymm0, %ymm4, %ymm4
+              vpslld $$30, %ymm1, %ymm10
+              vpsrld $$2, %ymm1, %ymm8
+              vpor %ymm10, %ymm8, %ymm8
+              vpslld $$19, %ymm1, %ymm10
+              vpsrld $$13, %ymm1, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpslld $$10, %ymm1, %ymm10
+              vpsrld $$22, %ymm1, %ymm9
+              vpor %ymm10, %ymm9, %ymm9
+              vpxor %ymm9, %ymm8, %ymm8
+              vpor %ymm2, %ymm1, %ymm9
+              vpand %ymm3, %ymm9, %ymm9
+              vpand %ymm2, %ymm1, %ymm11
+              vpor %ymm11, %ymm9, %ymm9
+              vpaddd %ymm9, %ymm8, %ymm8
+              vpaddd %ymm8, %ymm0, %ymm0
+              add $$32, %rcx
+              add $$256, %rdx
+              dec %eax
+              jnz 2b
+              vpaddd ($0), %ymm0, %ymm0
+              vmovdqu %ymm0, ($0)
+              vpaddd 32($0), %ymm1, %ymm1
+              vmovdqu %ymm1, 32($0)
+              vpaddd 64($0), %ymm2, %ymm2
+              vmovdqu %ymm2, 64($0)
+              vpaddd 96($0), %ymm3, %ymm3
+              vmovdqu %ymm3, 96($0)
+              vpaddd 128($0), %ymm4, %ymm4
+              vmovdqu %ymm4, 128($0)
+              vpaddd 160($0), %ymm5, %ymm5
+              vmovdqu %ymm5, 160($0)
+              vpaddd 192($0), %ymm6, %ymm6
+              vmovdqu %ymm6, 192($0)
+              vpaddd 224($0), %ymm7, %ymm7
+              vmovdqu %ymm7, 224($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K256.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9",
+               "xmm10", "xmm11", "rax", "rcx", "rdx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block256_avx512(state: &mut [u32], w: &mut [u32]) {
+    assert!(state.len() == 8 * 16 && w.len() == 64 * 16);
+    unsafe {
+        asm!("lea 1024($1), %rdx
+              mov $$48, %eax
+              1:
+              vmovdqu32 -960(%rdx), %zmm0
+              vprord $$7, %zmm0, %zmm1
+              vprord $$18, %zmm0, %zmm2
+              vpxord %zmm2, %zmm1, %zmm1
+              vpsrld $$3, %zmm0, %zmm2
+              vpxord %zmm2, %zmm1, %zmm1
+              vmovdqu32 -128(%rdx), %zmm0
+              vprord $$17, %zmm0, %zmm4
+              vprord $$19, %zmm0, %zmm2
+              vpxord %zmm2, %zmm4, %zmm4
+              vpsrld $$10, %zmm0, %zmm2
+              vpxord %zmm2, %zmm4, %zmm4
+              vpaddd %zmm4, %zmm1, %zmm1
+              vpaddd -448(%rdx), %zmm1, %zmm1
+              vpaddd -1024(%rdx), %zmm1, %zmm1
+              vmovdqu32 %zmm1, (%rdx)
+              add $$64, %rdx
+              dec %eax
+              jnz 1b
+              vmovdqu32 ($0), %zmm0
+              vmovdqu32 64($0), %zmm1
+              vmovdqu32 128($0), %zmm2
+              vmovdqu32 192($0), %zmm3
+              vmovdqu32 256($0), %zmm4
+              vmovdqu32 320($0), %zmm5
+              vmovdqu32 384($0), %zmm6
+              vmovdqu32 448($0), %zmm7
+              mov $1, %rdx
+              mov $2, %rcx
+              mov $$8, %eax
+              2:
+              vprord $$6, %zmm4, %zmm8
+              vprord $$11, %zmm4, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm4, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm6, %zmm5, %zmm9
+              vpandd %zmm4, %zmm9, %zmm9
+              vpxord %zmm6, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm7, %zmm7
+              vpbroadcastd (%rcx), %zmm9
+              vpaddd %zmm9, %zmm7, %zmm7
+              vpaddd (%rdx), %zmm7, %zmm7
+              vpaddd %zmm7, %zmm3, %zmm3
+              vprord $$2, %zmm0, %zmm8
+              vprord $$13, %zmm0, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm0, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm1, %zmm0, %zmm9
+              vpandd %zmm2, %zmm9, %zmm9
+              vpandd %zmm1, %zmm0, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm7, %zmm7
+              vprord $$6, %zmm3, %zmm8
+              vprord $$11, %zmm3, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm3, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm5, %zmm4, %zmm9
+              vpandd %zmm3, %zmm9, %zmm9
+              vpxord %zmm5, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm6, %zmm6
+              vpbroadcastd 4(%rcx), %zmm9
+              vpaddd %zmm9, %zmm6, %zmm6
+              vpaddd 64(%rdx), %zmm6, %zmm6
+              vpaddd %zmm6, %zmm2, %zmm2
+              vprord $$2, %zmm7, %zmm8
+              vprord $$13, %zmm7, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm7, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm0, %zmm7, %zmm9
+              vpandd %zmm1, %zmm9, %zmm9
+              vpandd %zmm0, %zmm7, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm6, %zmm6
+              vprord $$6, %zmm2, %zmm8
+              vprord $$11, %zmm2, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm2, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm4, %zmm3, %zmm9
+              vpandd %zmm2, %zmm9, %zmm9
+              vpxord %zmm4, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm5, %zmm5
+              vpbroadcastd 8(%rcx), %zmm9
+              vpaddd %zmm9, %zmm5, %zmm5
+              vpaddd 128(%rdx), %zmm5, %zmm5
+              vpaddd %zmm5, %zmm1, %zmm1
+              vprord $$2, %zmm6, %zmm8
+              vprord $$13, %zmm6, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm6, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm7, %zmm6, %zmm9
+              vpandd %zmm0, %zmm9, %zmm9
+              vpandd %zmm7, %zmm6, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm5, %zmm5
+              vprord $$6, %zmm1, %zmm8
+              vprord $$11, %zmm1, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm1, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm3, %zmm2, %zmm9
+              vpandd %zmm1, %zmm9, %zmm9
+              vpxord %zmm3, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm4, %zmm4
+              vpbroadcastd 12(%rcx), %zmm9
+              vpaddd %zmm9, %zmm4, %zmm4
+              vpaddd 192(%rdx), %zmm4, %zmm4
+              vpaddd %zmm4, %zmm0, %zmm0
+              vprord $$2, %zmm5, %zmm8
+              vprord $$13, %zmm5, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm5, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm6, %zmm5, %zmm9
+              vpandd %zmm7, %zmm9, %zmm9
+              vpandd %zmm6, %zmm5, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm4, %zmm4
+              vprord $$6, %zmm0, %zmm8
+              vprord $$11, %zmm0, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm0, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm2, %zmm1, %zmm9
+              vpandd %zmm0, %zmm9, %zmm9
+              vpxord %zmm2, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm3, %zmm3
+              vpbroadcastd 16(%rcx), %zmm9
+              vpaddd %zmm9, %zmm3, %zmm3
+              vpaddd 256(%rdx), %zmm3, %zmm3
+              vpaddd %zmm3, %zmm7, %zmm7
+              vprord $$2, %zmm4, %zmm8
+              vprord $$13, %zmm4, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm4, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm5, %zmm4, %zmm9
+              vpandd %zmm6, %zmm9, %zmm9
+              vpandd %zmm5, %zmm4, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm3, %zmm3
+              vprord $$6, %zmm7, %zmm8
+              vprord $$11, %zmm7, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm7, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm1, %zmm0, %zmm9
+              vpandd %zmm7, %zmm9, %zmm9
+              vpxord %zmm1, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm2, %zmm2
+              vpbroadcastd 20(%rcx), %zmm9
+              vpaddd %zmm9, %zmm2, %zmm2
+              vpaddd 320(%rdx), %zmm2, %zmm2
+              vpaddd %zmm2, %zmm6, %zmm6
+              vprord $$2, %zmm3, %zmm8
+              vprord $$13, %zmm3, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm3, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm4, %zmm3, %zmm9
+              vpandd %zmm5, %zmm9, %zmm9
+              vpandd %zmm4, %zmm3, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm2, %zmm2
+              vprord $$6, %zmm6, %zmm8
+              vprord $$11, %zmm6, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm6, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm0, %zmm7, %zmm9
+              vpandd %zmm6, %zmm9, %zmm9
+              vpxord %zmm0, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm1, %zmm1
+              vpbroadcastd 24(%rcx), %zmm9
+              vpaddd %zmm9, %zmm1, %zmm1
+              vpaddd 384(%rdx), %zmm1, %zmm1
+              vpaddd %zmm1, %zmm5, %zmm5
+              vprord $$2, %zmm2, %zmm8
+              vprord $$13, %zmm2, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm2, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm3, %zmm2, %zmm9
+              vpandd %zmm4, %zmm9, %zmm9
+              vpandd %zmm3, %zmm2, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm1, %zmm1
+              vprord $$6, %zmm5, %zmm8
+              vprord $$11, %zmm5, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$25, %zmm5, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpxord %zmm7, %zmm6, %zmm9
+              vpandd %zmm5, %zmm9, %zmm9
+              vpxord %zmm7, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm0, %zmm0
+              vpbroadcastd 28(%rcx), %zmm9
+              vpaddd %zmm9, %zmm0, %zmm0
+              vpaddd 448(%rdx), %zmm0, %zmm0
+              vpaddd %zmm0, %zmm4, %zmm4
+              vprord $$2, %zmm1, %zmm8
+              vprord $$13, %zmm1, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vprord $$22, %zmm1, %zmm9
+              vpxord %zmm9, %zmm8, %zmm8
+              vpord %zmm2, %zmm1, %zmm9
+              vpandd %zmm3, %zmm9, %zmm9
+              vpandd %zmm2, %zmm1, %zmm11
+              vpord %zmm11, %zmm9, %zmm9
+              vpaddd %zmm9, %zmm8, %zmm8
+              vpaddd %zmm8, %zmm0, %zmm0
+              add $$32, %rcx
+              add $$512, %rdx
+              dec %eax
+              jnz 2b
+              vpaddd ($0), %zmm0, %zmm0
+              vmovdqu32 %zmm0, ($0)
+              vpaddd 64($0), %zmm1, %zmm1
+              vmovdqu32 %zmm1, 64($0)
+              vpaddd 128($0), %zmm2, %zmm2
+              vmovdqu32 %zmm2, 128($0)
+              vpaddd 192($0), %zmm3, %zmm3
+              vmovdqu32 %zmm3, 192($0)
+              vpaddd 256($0), %zmm4, %zmm4
+              vmovdqu32 %zmm4, 256($0)
+              vpaddd 320($0), %zmm5, %zmm5
+              vmovdqu32 %zmm5, 320($0)
+              vpaddd 384($0), %zmm6, %zmm6
+              vmovdqu32 %zmm6, 384($0)
+              vpaddd 448($0), %zmm7, %zmm7
+              vmovdqu32 %zmm7, 448($0)
+              vzeroupper"
+             :
+             : "r"(state.as_mut_ptr()), "r"(w.as_mut_ptr()), "r"(K256.as_ptr())
+             : "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9",
+               "xmm11", "rax", "rcx", "rdx", "memory", "cc"
+             : "volatile");
+    }
+}
+
+impl Writer for Sha2 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let block_size = self.varient.block_size();
+        self.len += buf.len();  // total len
+        self.x.push_all(buf);
+        let mut next_x = Vec::new();
+        let buf = self.x.clone(); // how to avoid clone?
+        for p in buf.as_slice().chunks(block_size) {
+            if p.len() < block_size {
+                next_x.push_all(p);
+            } else {
+                self.block(p);
+            }
+        }
+        self.x = next_x;
+        Ok(())
+    }
+}
+
+impl Hash for Sha2 {
+    fn reset(&mut self) {
+        self.h = self.varient.init();
+        self.x = Vec::new();
+        self.len = 0;
+    }
+    fn digest(&self) -> ~[u8] {
+        let mut h = self.clone();
+        h.checksum()
+    }
+
+    fn size(&self) -> uint {
+        self.varient.size()
+    }
+
+    fn block_size(&self) -> uint {
+        self.varient.block_size()
+    }
+}
+
 
 pub trait DigestState {
     fn reset(&mut self);
@@ -25,3 +2018,149 @@ pub trait DigestState {
 pub trait Digester {
 
 }
+
+
+#[test]
+fn test_sha256() {
+    let mut h = Sha2::new(Sha_256);
+    assert_eq!(h.hexdigest(), ~"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    let mut h = Sha2::new(Sha_224);
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+}
+
+#[test]
+fn test_sha512() {
+    let mut h = Sha2::new(Sha_512);
+    assert_eq!(h.hexdigest(), ~"cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e");
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+    let mut h = Sha2::new(Sha_384);
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7");
+    let mut h = Sha2::new(Sha_512_224);
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"4634270f707b6a54daae7530460842e20e37ed265ceee9a43e8924aa");
+    let mut h = Sha2::new(Sha_512_256);
+    h.write_str("abc");
+    assert_eq!(h.hexdigest(), ~"53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23");
+}
+
+#[test]
+fn test_sha2_long() {
+    let mut h = Sha2::new(Sha_256);
+    h.write_str("welcome to china".repeat(10001));
+    assert_eq!(h.hexdigest(), ~"4514ce0f4e956706c04607ce8dcb165d3e6d6566cbb4f9629b166d4ef39d5836");
+    let mut h = Sha2::new(Sha_512);
+    h.write_str("welcome to china".repeat(10001));
+    assert_eq!(h.hexdigest(), ~"b79394093a92b1223afe263a8aa64656adad91f5fd175ca1a57d0744a396cf21df172225294607586eb24596300e3b15b6a353619703ac9ee703b56d0ee08bc4");
+}
+
+#[test]
+fn test_sha256_digest_many() {
+    let data = slice::from_fn(300, |i| (i * 7) as u8);
+    let mut msgs = Vec::new();
+    for n in range(0, 200u) {
+        msgs.push(data.slice_to(n));
+    }
+    let sums = digest_many(msgs.as_slice());
+    assert_eq!(sums.len(), 200);
+    for (msg, sum) in msgs.iter().zip(sums.iter()) {
+        let mut h = Sha2::new(Sha_256);
+        h.write(*msg);
+        assert_eq!(h.digest().as_slice(), sum.as_slice());
+    }
+    assert_eq!(digest_many([]).len(), 0);
+}
+
+#[test]
+fn test_sha256_digest_lanes() {
+    let data = slice::from_fn(2000, |i| (i * 7 + 3) as u8);
+    // long and short messages mixed, so lanes finish at different times
+    let mut msgs = Vec::new();
+    for i in range(0, 150u) {
+        let n = (i * 37) % 300 + if i == 7 { 1500 } else { 0 };
+        msgs.push(data.slice(i % 13, i % 13 + n));
+    }
+    let want = digest_lanes(msgs.as_slice(), 1);
+    for &lanes in [4u, 8, 16].iter() {
+        if !multibuf::supported(lanes) {
+            continue;
+        }
+        let got = digest_lanes(msgs.as_slice(), lanes);
+        for (a, b) in want.iter().zip(got.iter()) {
+            assert_eq!(a.as_slice(), b.as_slice());
+        }
+        assert_eq!(got.len(), want.len());
+        // fewer messages than lanes
+        let few = digest_lanes(msgs.slice_to(3), lanes);
+        assert_eq!(few.len(), 3);
+        assert_eq!(few.get(2).as_slice(), want.get(2).as_slice());
+        assert_eq!(digest_lanes([], lanes).len(), 0);
+    }
+}
+
+#[test]
+fn test_sha256_block_shani() {
+    if !cpu::has_sha() {
+        return;
+    }
+    let data = slice::from_fn(1000, |i| (i * 31 + 7) as u8);
+    for &n in [1u, 3, 55, 57, 63, 65, 127, 129, 191, 999].iter() {
+        for &v in [Sha_224, Sha_256].iter() {
+            let mut a = Sha2::new(v);
+            let mut b = Sha2::new(v);
+            a.shani = false;
+            a.avx2 = false;
+            b.shani = true;
+            a.write(data.slice_to(n));
+            b.write(data.slice_to(n));
+            assert_eq!(a.digest(), b.digest());
+        }
+        // several blocks in one call
+        let mut a = Sha2::new(Sha_256);
+        let mut b = Sha2::new(Sha_256);
+        a.shani = false;
+        a.avx2 = false;
+        b.shani = true;
+        assert_eq!(a.sum256_oneshot(data.slice_to(n)).as_slice(),
+                   b.sum256_oneshot(data.slice_to(n)).as_slice());
+    }
+}
+
+#[test]
+fn test_sha256_block_avx2_armv8() {
+    let data = slice::from_fn(1100, |i| (i * 31 + 7) as u8);
+    let mut h = [0u32, ..8];
+    for i in range(0, 8u) {
+        h[i] = init256[i] as u32;
+    }
+    // runs of blocks around the eight lanes of the AVX2 schedule
+    for &n in [0u, 1, 7, 8, 9, 16, 17].iter() {
+        let mut want = h;
+        block256_generic(&mut want, data.slice_to(64 * n));
+        if cpu::has_avx2() {
+            let mut got = h;
+            block256_avx2_schedule(&mut got, data.slice_to(64 * n));
+            assert_eq!(got.as_slice(), want.as_slice());
+        }
+        if cpu::has_sha2() {
+            let mut got = h;
+            block256_armv8(&mut got, data.slice_to(64 * n));
+            assert_eq!(got.as_slice(), want.as_slice());
+        }
+    }
+    if cpu::has_avx2() {
+        let mut a = Sha2::new(Sha_224);
+        let mut b = Sha2::new(Sha_224);
+        a.shani = false;
+        a.avx2 = false;
+        b.shani = false;
+        b.avx2 = true;
+        a.write(data.as_slice());
+        b.write(data.as_slice());
+        assert_eq!(a.digest(), b.digest());
+    }
+}
@@ -0,0 +1,519 @@
+// The SHA-3 hash algorithms and the SHAKE and cSHAKE extendable-output
+// functions defined in FIPS 202 and NIST SP 800-185, plus KMAC.
+//
+// All of them are built on the Keccak-f[1600] permutation and only differ
+// in the rate, the domain separation byte and what gets absorbed first.
+
+use std::io::IoResult;
+use std::slice;
+use super::super::hash::*;
+
+
+#[deriving(Eq, Clone, Show)]
+pub enum Sha3Varient {
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+}
+
+impl Sha3Varient {
+    /// Length of the digest in bytes.
+    pub fn size(&self) -> uint {
+        match *self {
+            Sha3_224 => 28,
+            Sha3_256 => 32,
+            Sha3_384 => 48,
+            Sha3_512 => 64,
+        }
+    }
+
+    /// The rate of the sponge, 200 bytes minus twice the digest size.
+    pub fn block_size(&self) -> uint {
+        200 - 2 * self.size()
+    }
+}
+
+
+// Domain separation bytes, with the first bit of the pad10*1 padding.
+static dsbyteSha3: u8 = 0x06;
+static dsbyteShake: u8 = 0x1f;
+static dsbyteCShake: u8 = 0x04;
+
+static rateK256: uint = 136;
+static rateK512: uint = 168;
+
+static RC: [u64, ..24] = [0x0000000000000001, 0x0000000000008082, 0x800000000000808a,
+                          0x8000000080008000, 0x000000000000808b, 0x0000000080000001,
+                          0x8000000080008081, 0x8000000000008009, 0x000000000000008a,
+                          0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+                          0x000000008000808b, 0x800000000000008b, 0x8000000000008089,
+                          0x8000000000008003, 0x8000000000008002, 0x8000000000000080,
+                          0x000000000000800a, 0x800000008000000a, 0x8000000080008081,
+                          0x8000000000008080, 0x0000000080000001, 0x8000000080008008];
+
+// rotation offsets of the ρ step, indexed by x + 5*y
+static Rotc: [uint, ..25] = [ 0,  1, 62, 28, 27,
+                             36, 44,  6, 55, 20,
+                              3, 10, 43, 25, 39,
+                             41, 45, 15, 21,  8,
+                             18,  2, 61, 56, 14];
+
+
+#[inline]
+fn rotl64(x: u64, n: uint) -> u64 {
+    if n == 0 { x } else { (x << n) | (x >> (64 - n)) }
+}
+
+/// Applies the Keccak-f[1600] permutation to the state.
+pub fn keccak_f1600(a: &mut [u64, ..25]) {
+    let mut b = [0u64, ..25];
+    let mut c = [0u64, ..5];
+    for round in range(0, 24u) {
+        // θ step
+        for x in range(0, 5u) {
+            c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in range(0, 5u) {
+            let d = c[(x + 4) % 5] ^ rotl64(c[(x + 1) % 5], 1);
+            for y in range(0, 5u) {
+                a[x + 5 * y] ^= d;
+            }
+        }
+        // ρ and π steps
+        for x in range(0, 5u) {
+            for y in range(0, 5u) {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = rotl64(a[x + 5 * y], Rotc[x + 5 * y]);
+            }
+        }
+        // χ step
+        for y in range(0, 5u) {
+            for x in range(0, 5u) {
+                a[x + 5 * y] = b[x + 5 * y] ^ ((! b[(x + 1) % 5 + 5 * y]) & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+        // ι step
+        a[0] ^= RC[round];
+    }
+}
+
+
+// The sponge shared by every function in this module.
+struct State {
+    a: [u64, ..25],
+    n: uint,        // position in the rate part of a
+    rate: uint,
+    dsbyte: u8,
+    squeezing: bool,
+}
+
+impl Clone for State {
+    fn clone(&self) -> State {
+        State {
+            a: self.a,
+            n: self.n,
+            rate: self.rate,
+            dsbyte: self.dsbyte,
+            squeezing: self.squeezing,
+        }
+    }
+}
+
+impl State {
+    fn new(rate: uint, dsbyte: u8) -> State {
+        State { a: [0u64, ..25], n: 0, rate: rate, dsbyte: dsbyte, squeezing: false }
+    }
+
+    fn reset(&mut self) {
+        self.a = [0u64, ..25];
+        self.n = 0;
+        self.squeezing = false;
+    }
+
+    #[inline]
+    fn xor_byte(&mut self, i: uint, b: u8) {
+        self.a[i / 8] ^= (b as u64) << (8 * (i % 8));
+    }
+
+    fn absorb(&mut self, p: &[u8]) {
+        if self.squeezing {
+            fail!("sha3: write to sponge after read");
+        }
+        for b in p.iter() {
+            let n = self.n;
+            self.xor_byte(n, *b);
+            self.n += 1;
+            if self.n == self.rate {
+                keccak_f1600(&mut self.a);
+                self.n = 0;
+            }
+        }
+    }
+
+    fn pad_and_permute(&mut self) {
+        let (n, rate, dsbyte) = (self.n, self.rate, self.dsbyte);
+        self.xor_byte(n, dsbyte);
+        self.xor_byte(rate - 1, 0x80);
+        keccak_f1600(&mut self.a);
+        self.n = 0;
+        self.squeezing = true;
+    }
+
+    fn squeeze(&mut self, out: &mut [u8]) {
+        if !self.squeezing {
+            self.pad_and_permute();
+        }
+        for i in range(0, out.len()) {
+            if self.n == self.rate {
+                keccak_f1600(&mut self.a);
+                self.n = 0;
+            }
+            out[i] = (self.a[self.n / 8] >> (8 * (self.n % 8))) as u8;
+            self.n += 1;
+        }
+    }
+}
+
+
+pub struct Sha3 {
+    varient: Sha3Varient,
+    state: State,
+}
+
+impl Clone for Sha3 {
+    fn clone(&self) -> Sha3 {
+        Sha3 { varient: self.varient, state: self.state.clone() }
+    }
+}
+
+impl Sha3 {
+    pub fn new(varient: Sha3Varient) -> Sha3 {
+        Sha3 { varient: varient, state: State::new(varient.block_size(), dsbyteSha3) }
+    }
+
+    pub fn varient(&self) -> Sha3Varient {
+        self.varient
+    }
+}
+
+impl Writer for Sha3 {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.state.absorb(buf);
+        Ok(())
+    }
+}
+
+impl Hash for Sha3 {
+    fn reset(&mut self) {
+        self.state.reset();
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut s = self.state.clone();
+        let mut ret = slice::from_elem(self.varient.size(), 0u8);
+        s.squeeze(ret.as_mut_slice());
+        ret
+    }
+
+    fn size(&self) -> uint {
+        self.varient.size()
+    }
+
+    fn block_size(&self) -> uint {
+        self.varient.block_size()
+    }
+}
+
+
+// Encodings from NIST SP 800-185, section 2.3.
+fn left_encode(x: u64) -> ~[u8] {
+    let mut n = 1u;
+    while n < 8 && (x >> (8 * n)) != 0 {
+        n += 1;
+    }
+    let mut ret = Vec::with_capacity(n + 1);
+    ret.push(n as u8);
+    for i in range(0, n) {
+        ret.push((x >> (8 * (n - 1 - i))) as u8);
+    }
+    ret.as_slice().to_owned()
+}
+
+fn right_encode(x: u64) -> ~[u8] {
+    let mut ret = Vec::from_slice(left_encode(x).slice_from(1));
+    let n = ret.len();
+    ret.push(n as u8);
+    ret.as_slice().to_owned()
+}
+
+fn encode_string(s: &[u8]) -> ~[u8] {
+    let mut ret = Vec::from_slice(left_encode(8 * s.len() as u64).as_slice());
+    ret.push_all(s);
+    ret.as_slice().to_owned()
+}
+
+fn bytepad(data: &[u8], rate: uint) -> ~[u8] {
+    let mut ret = Vec::from_slice(left_encode(rate as u64).as_slice());
+    ret.push_all(data);
+    while ret.len() % rate != 0 {
+        ret.push(0u8);
+    }
+    ret.as_slice().to_owned()
+}
+
+
+/// SHAKE and cSHAKE extendable-output functions. Write the input, then read
+/// as much output as needed. Writing after the first read fails.
+pub struct Shake {
+    state: State,
+    // absorbed again on every reset, the bytepad prefix for cSHAKE
+    init: ~[u8],
+}
+
+impl Clone for Shake {
+    fn clone(&self) -> Shake {
+        Shake { state: self.state.clone(), init: self.init.clone() }
+    }
+}
+
+impl Shake {
+    /// SHAKE128, providing up to 128 bits of security.
+    pub fn new128() -> Shake {
+        Shake { state: State::new(rateK512, dsbyteShake), init: ~[] }
+    }
+
+    /// SHAKE256, providing up to 256 bits of security.
+    pub fn new256() -> Shake {
+        Shake { state: State::new(rateK256, dsbyteShake), init: ~[] }
+    }
+
+    /// cSHAKE128 with function name `n` and customization string `s`.
+    /// When both are empty this is equivalent to SHAKE128.
+    pub fn new_cshake128(n: &[u8], s: &[u8]) -> Shake {
+        Shake::new_cshake(rateK512, n, s)
+    }
+
+    /// cSHAKE256 with function name `n` and customization string `s`.
+    /// When both are empty this is equivalent to SHAKE256.
+    pub fn new_cshake256(n: &[u8], s: &[u8]) -> Shake {
+        Shake::new_cshake(rateK256, n, s)
+    }
+
+    fn new_cshake(rate: uint, n: &[u8], s: &[u8]) -> Shake {
+        if n.len() == 0 && s.len() == 0 {
+            return Shake { state: State::new(rate, dsbyteShake), init: ~[] };
+        }
+        let mut prefix = Vec::from_slice(encode_string(n).as_slice());
+        prefix.push_all(encode_string(s).as_slice());
+        let mut ret = Shake { state: State::new(rate, dsbyteCShake),
+                              init: bytepad(prefix.as_slice(), rate) };
+        ret.reset();
+        ret
+    }
+
+    /// Discards the input and any output, as if newly created.
+    pub fn reset(&mut self) {
+        self.state.reset();
+        self.state.absorb(self.init.as_slice());
+    }
+
+    /// The rate of the underlying sponge.
+    pub fn block_size(&self) -> uint {
+        self.state.rate
+    }
+}
+
+impl Writer for Shake {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.state.absorb(buf);
+        Ok(())
+    }
+}
+
+impl Reader for Shake {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        self.state.squeeze(buf);
+        Ok(buf.len())
+    }
+}
+
+
+/// KMAC from NIST SP 800-185, a MAC built on cSHAKE with a fixed
+/// output length.
+pub struct Kmac {
+    shake: Shake,
+    output_len: uint,
+}
+
+impl Clone for Kmac {
+    fn clone(&self) -> Kmac {
+        Kmac { shake: self.shake.clone(), output_len: self.output_len }
+    }
+}
+
+impl Kmac {
+    /// KMAC128 with the given key, output length in bytes and
+    /// customization string.
+    pub fn new128(key: &[u8], output_len: uint, s: &[u8]) -> Kmac {
+        Kmac::new(Shake::new_cshake128(bytes!("KMAC"), s), key, output_len)
+    }
+
+    /// KMAC256 with the given key, output length in bytes and
+    /// customization string.
+    pub fn new256(key: &[u8], output_len: uint, s: &[u8]) -> Kmac {
+        Kmac::new(Shake::new_cshake256(bytes!("KMAC"), s), key, output_len)
+    }
+
+    fn new(shake: Shake, key: &[u8], output_len: uint) -> Kmac {
+        let mut shake = shake;
+        let rate = shake.block_size();
+        let mut init = Vec::from_slice(shake.init.as_slice());
+        init.push_all(bytepad(encode_string(key).as_slice(), rate).as_slice());
+        shake.init = init.as_slice().to_owned();
+        shake.reset();
+        Kmac { shake: shake, output_len: output_len }
+    }
+
+    /// Finishes the MAC in KMACXOF mode, returning a reader for an
+    /// arbitrary amount of output. The output length given on creation
+    /// is ignored.
+    pub fn xof(&self) -> Shake {
+        let mut s = self.shake.clone();
+        s.write(right_encode(0).as_slice());
+        s
+    }
+}
+
+impl Writer for Kmac {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.shake.write(buf)
+    }
+}
+
+impl Hash for Kmac {
+    fn reset(&mut self) {
+        self.shake.reset();
+    }
+
+    fn digest(&self) -> ~[u8] {
+        let mut s = self.shake.clone();
+        s.write(right_encode(8 * self.output_len as u64).as_slice());
+        let mut ret = slice::from_elem(self.output_len, 0u8);
+        s.state.squeeze(ret.as_mut_slice());
+        ret
+    }
+
+    fn size(&self) -> uint {
+        self.output_len
+    }
+
+    fn block_size(&self) -> uint {
+        self.shake.block_size()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::ToHex;
+    use super::*;
+    use super::super::super::hash::*;
+
+    fn read_hex<R: Reader>(r: &mut R, n: uint) -> ~str {
+        r.read_bytes(n).unwrap().to_hex()
+    }
+
+    #[test]
+    fn test_sha3() {
+        let tests = [
+            (Sha3_224, "6b4e03423667dbb73b6e15454f0eb1abd4597f9a1b078e3f5b5a6bc7",
+             "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"),
+            (Sha3_256, "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+             "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"),
+            (Sha3_384, "0c63a75b845e4f7d01107d852e4c2485c51a50aaaa94fc61995e71bbee983a2ac3713831264adb47fb6bd1e058d5f004",
+             "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25"),
+            (Sha3_512, "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26",
+             "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"),
+        ];
+        for &(v, empty, abc) in tests.iter() {
+            let mut h = Sha3::new(v);
+            assert_eq!(h.hexdigest().as_slice(), empty);
+            h.write_str("abc");
+            assert_eq!(h.hexdigest().as_slice(), abc);
+            h.reset();
+            assert_eq!(h.hexdigest().as_slice(), empty);
+        }
+    }
+
+    #[test]
+    fn test_sha3_long() {
+        let mut h = Sha3::new(Sha3_256);
+        h.write_str("welcome to china".repeat(10001));
+        assert_eq!(h.hexdigest(), ~"cbcf8a83b7e13ab9dfe16cf762625a570d1713430b28ee1ebdc7c8e7a94726a6");
+    }
+
+    #[test]
+    fn test_shake() {
+        let mut h = Shake::new128();
+        assert_eq!(read_hex(&mut h, 32), ~"7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26");
+        let mut h = Shake::new256();
+        assert_eq!(read_hex(&mut h, 64), ~"46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be");
+
+        // output spanning several permutations, read in pieces
+        let mut h = Shake::new128();
+        h.write_str("welcome to china".repeat(10001));
+        h.read_bytes(168).unwrap();
+        read_hex(&mut h, 1);
+        assert_eq!(read_hex(&mut h, 31), ~"46e1e13a4f0aaa8beec90544fae6cb54a650dfe1cc83d38f8fb1e61ab220cebf".slice_from(2).to_owned());
+    }
+
+    #[test]
+    fn test_cshake() {
+        // NIST SP 800-185 samples
+        let data: ~[u8] = range(0u, 200).map(|i| i as u8).collect();
+        let mut h = Shake::new_cshake128([], bytes!("Email Signature"));
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(read_hex(&mut h, 32), ~"c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5");
+        let mut h = Shake::new_cshake128([], bytes!("Email Signature"));
+        h.write(data.as_slice());
+        assert_eq!(read_hex(&mut h, 32), ~"c5221d50e4f822d96a2e8881a961420f294b7b24fe3d2094baed2c6524cc166b");
+        let mut h = Shake::new_cshake256([], bytes!("Email Signature"));
+        h.write(data.as_slice());
+        assert_eq!(read_hex(&mut h, 64), ~"07dc27b11e51fbac75bc7b3c1d983e8b4b85fb1defaf218912ac86430273091727f42b17ed1df63e8ec118f04b23633c1dfb1574c8fb55cb45da8e25afb092bb");
+
+        // empty N and S fall back to plain SHAKE
+        let mut h = Shake::new_cshake128([], []);
+        assert_eq!(read_hex(&mut h, 32), ~"7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26");
+    }
+
+    #[test]
+    fn test_kmac() {
+        // NIST SP 800-185 samples
+        let key: ~[u8] = range(0x40u, 0x60).map(|i| i as u8).collect();
+        let data: ~[u8] = range(0u, 200).map(|i| i as u8).collect();
+        let mut h = Kmac::new128(key.as_slice(), 32, []);
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(h.hexdigest(), ~"e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e");
+        let mut h = Kmac::new128(key.as_slice(), 32, bytes!("My Tagged Application"));
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(h.hexdigest(), ~"3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5");
+        h.reset();
+        h.write(data.as_slice());
+        assert_eq!(h.hexdigest(), ~"1f5b4e6cca02209e0dcb5ca635b89a15e271ecc760071dfd805faa38f9729230");
+        let mut h = Kmac::new256(key.as_slice(), 64, bytes!("My Tagged Application"));
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(h.size(), 64);
+        assert_eq!(h.hexdigest(), ~"20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd");
+    }
+
+    #[test]
+    fn test_kmac_xof() {
+        let key: ~[u8] = range(0x40u, 0x60).map(|i| i as u8).collect();
+        let mut h = Kmac::new128(key.as_slice(), 32, []);
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(read_hex(&mut h.xof(), 32), ~"cd83740bbd92ccc8cf032b1481a0f4460e7ca9dd12b08a0c4031178bacd6ec35");
+        let mut h = Kmac::new256(key.as_slice(), 64, bytes!("My Tagged Application"));
+        h.write([0u8, 1, 2, 3]);
+        assert_eq!(read_hex(&mut h.xof(), 64), ~"1755133f1534752aad0748f2c706fb5c784512cab835cd15676b16c0c6647fa96faa7af634a0bf8ff6df39374fa00fad9a39e322a7c92065a64eb1fb0801eb2b");
+    }
+}
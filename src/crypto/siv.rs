@@ -0,0 +1,262 @@
+// AES-SIV, the Synthetic Initialization Vector mode of RFC 5297.
+//
+// SIV derives the IV from the associated data, the nonce and the plaintext
+// with S2V, a CMAC-based PRF over a vector of strings, and then encrypts in
+// CTR mode with it. Reusing a nonce, or using none at all, only reveals
+// whether two messages were identical, so the mode is suitable for
+// deterministic encryption.
+
+use std::slice;
+use super::aes::Aes;
+use super::cipher::{AEAD, Stream};
+use super::cipher::ctr::Ctr;
+use super::cmac::Cmac;
+use super::subtle::constant_time_compare;
+use super::super::hash::Hash;
+
+static blockSize: uint = 16;
+
+/// The size of the synthetic IV prepended to every ciphertext, in bytes.
+pub static Overhead: uint = 16;
+
+/// An AEAD which runs AES in SIV mode. The first half of the key is used
+/// for S2V and the second half for CTR.
+pub struct AesSiv {
+    mac: Cmac<Aes>,
+    ctr: Aes,
+    nonce_size: uint,
+}
+
+impl AesSiv {
+    /// Returns a deterministic AES-SIV AEAD with the given key, which must
+    /// be 32, 48 or 64 bytes long to select AES-SIV-256, -384 or -512.
+    ///
+    /// nonce_size() is zero, and seal and open must be given an empty
+    /// nonce. Encrypting the same plaintext with the same additional data
+    /// always produces the same ciphertext.
+    pub fn new(key: &[u8]) -> Result<AesSiv, ~str> {
+        AesSiv::new_with_sizes(key, 0)
+    }
+
+    /// Like new, but the AEAD takes a nonce of the given length, which is
+    /// passed to S2V as the last component before the plaintext. The
+    /// nonce should be unique, but repeating it is not catastrophic.
+    pub fn new_with_nonce_size(key: &[u8], size: uint) -> Result<AesSiv, ~str> {
+        if size == 0 {
+            return Err(~"siv: the nonce can't have zero length");
+        }
+        AesSiv::new_with_sizes(key, size)
+    }
+
+    fn new_with_sizes(key: &[u8], nonce_size: uint) -> Result<AesSiv, ~str> {
+        match key.len() {
+            32 | 48 | 64 => {}
+            n => return Err(format!("siv: invalid key size {}", n)),
+        }
+        let half = key.len() / 2;
+        let mac = try!(Cmac::new(try!(Aes::new(key.slice_to(half)))));
+        let ctr = try!(Aes::new(key.slice_from(half)));
+        Ok(AesSiv { mac: mac, ctr: ctr, nonce_size: nonce_size })
+    }
+
+    /// Encrypts plaintext as in RFC 5297 with any number of associated
+    /// data components, and appends the synthetic IV and the ciphertext to
+    /// dst. A nonce, if any, should be the last component.
+    pub fn seal_components(&self, dst: &[u8], additional_data: &[&[u8]],
+                           plaintext: &[u8]) -> ~[u8] {
+        if additional_data.len() > 126 {
+            fail!("siv: too many associated data components");
+        }
+        let v = self.s2v(additional_data, plaintext);
+        let mut ct = slice::from_elem(plaintext.len(), 0u8);
+        self.crypt(&v, ct.as_mut_slice(), plaintext);
+
+        let mut ret = Vec::with_capacity(dst.len() + Overhead + ct.len());
+        ret.push_all(dst);
+        ret.push_all(v.as_slice());
+        ret.push_all(ct.as_slice());
+        ret.as_slice().to_owned()
+    }
+
+    /// Decrypts and authenticates ciphertext produced by seal_components
+    /// with the same associated data components and, if successful,
+    /// appends the plaintext to dst.
+    pub fn open_components(&self, dst: &[u8], additional_data: &[&[u8]],
+                           ciphertext: &[u8]) -> Result<~[u8], ~str> {
+        if ciphertext.len() < Overhead || additional_data.len() > 126 {
+            return Err(~"siv: message authentication failed");
+        }
+        let mut v = [0u8, ..16];
+        v.as_mut_slice().copy_from(ciphertext.slice_to(Overhead));
+        let ct = ciphertext.slice_from(Overhead);
+        let mut pt = slice::from_elem(ct.len(), 0u8);
+        self.crypt(&v, pt.as_mut_slice(), ct);
+
+        let expected = self.s2v(additional_data, pt.as_slice());
+        if !constant_time_compare(expected.as_slice(), v.as_slice()) {
+            return Err(~"siv: message authentication failed");
+        }
+        let mut ret = Vec::with_capacity(dst.len() + pt.len());
+        ret.push_all(dst);
+        ret.push_all(pt.as_slice());
+        Ok(ret.as_slice().to_owned())
+    }
+
+    // CTR mode with the IV's 31st and 63rd bits (from the right) cleared,
+    // so that implementations may use 32 or 64-bit counters.
+    fn crypt(&self, v: &[u8, ..16], dst: &mut [u8], src: &[u8]) {
+        let mut q = *v;
+        q[8] &= 0x7f;
+        q[12] &= 0x7f;
+        let mut s = Ctr::new(self.ctr.clone(), q.as_slice());
+        s.xor_key_stream(dst, src);
+    }
+
+    fn cmac(&self, msg: &[u8]) -> [u8, ..16] {
+        let mut h = self.mac.clone();
+        h.reset();
+        h.write(msg).unwrap();
+        let mut out = [0u8, ..16];
+        out.as_mut_slice().copy_from(h.digest().as_slice());
+        out
+    }
+
+    fn s2v(&self, components: &[&[u8]], plaintext: &[u8]) -> [u8, ..16] {
+        let mut d = self.cmac([0u8, ..16]);
+        for s in components.iter() {
+            dbl(&mut d);
+            let m = self.cmac(*s);
+            xor(d.as_mut_slice(), m.as_slice());
+        }
+        if plaintext.len() >= blockSize {
+            // xorend: D is xored into the last block of the plaintext
+            let mut t = plaintext.to_owned();
+            let off = t.len() - blockSize;
+            xor(t.mut_slice_from(off), d.as_slice());
+            self.cmac(t.as_slice())
+        } else {
+            dbl(&mut d);
+            let mut t = [0u8, ..16];
+            t.mut_slice_to(plaintext.len()).copy_from(plaintext);
+            t[plaintext.len()] = 0x80;
+            xor(t.as_mut_slice(), d.as_slice());
+            self.cmac(t.as_slice())
+        }
+    }
+}
+
+impl AEAD for AesSiv {
+    fn nonce_size(&self) -> uint {
+        self.nonce_size
+    }
+
+    fn overhead(&self) -> uint {
+        Overhead
+    }
+
+    fn seal(&self, dst: &[u8], nonce: &[u8], plaintext: &[u8], additional_data: &[u8]) -> ~[u8] {
+        if nonce.len() != self.nonce_size {
+            fail!("siv: incorrect nonce length given to AES-SIV");
+        }
+        if self.nonce_size == 0 {
+            self.seal_components(dst, [additional_data], plaintext)
+        } else {
+            self.seal_components(dst, [additional_data, nonce], plaintext)
+        }
+    }
+
+    fn open(&self, dst: &[u8], nonce: &[u8], ciphertext: &[u8], additional_data: &[u8])
+            -> Result<~[u8], ~str> {
+        if nonce.len() != self.nonce_size {
+            fail!("siv: incorrect nonce length given to AES-SIV");
+        }
+        if self.nonce_size == 0 {
+            self.open_components(dst, [additional_data], ciphertext)
+        } else {
+            self.open_components(dst, [additional_data, nonce], ciphertext)
+        }
+    }
+}
+
+fn xor(dst: &mut [u8], src: &[u8]) {
+    for i in range(0, src.len()) {
+        dst[i] ^= src[i];
+    }
+}
+
+// Multiplies by x in GF(2^128) with the polynomial x^128 + x^7 + x^2 + x
+// + 1, in constant time.
+fn dbl(b: &mut [u8, ..16]) {
+    let msb = b[0] >> 7;
+    for i in range(0, 15u) {
+        b[i] = (b[i] << 1) | (b[i + 1] >> 7);
+    }
+    b[15] = (b[15] << 1) ^ (0x87 & (0u8 - msb));
+}
+
+
+#[cfg(test)]
+mod test {
+    use serialize::hex::{ToHex, FromHex};
+    use super::*;
+    use super::super::cipher::AEAD;
+
+    #[test]
+    fn test_deterministic() {
+        // RFC 5297 appendix A.1
+        let key = "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".from_hex().unwrap();
+        let ad = "101112131415161718191a1b1c1d1e1f2021222324252627".from_hex().unwrap();
+        let pt = "112233445566778899aabbccddee".from_hex().unwrap();
+        let a = AesSiv::new(key.as_slice()).unwrap();
+        assert_eq!(a.nonce_size(), 0);
+        let out = a.seal([], [], pt.as_slice(), ad.as_slice());
+        assert_eq!(out.as_slice().to_hex(), ~"85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c");
+        assert_eq!(a.open([], [], out.as_slice(), ad.as_slice()).unwrap().as_slice(), pt.as_slice());
+
+        let mut bad = out.clone();
+        bad[20] ^= 1;
+        assert_eq!(a.open([], [], bad.as_slice(), ad.as_slice()).err(),
+                   Some(~"siv: message authentication failed"));
+        assert!(a.open([], [], out.as_slice(), []).is_err());
+        assert!(a.open([], [], out.slice_to(15), ad.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_nonce_based() {
+        // RFC 5297 appendix A.2
+        let key = "7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f".from_hex().unwrap();
+        let ad1 = "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100".from_hex().unwrap();
+        let ad2 = "102030405060708090a0".from_hex().unwrap();
+        let nonce = "09f911029d74e35bd84156c5635688c0".from_hex().unwrap();
+        let pt = "7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553".from_hex().unwrap();
+        let expected = "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+                        dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d";
+        let a = AesSiv::new(key.as_slice()).unwrap();
+        let ads = [ad1.as_slice(), ad2.as_slice(), nonce.as_slice()];
+        let out = a.seal_components(bytes!("x"), ads, pt.as_slice());
+        assert_eq!(out.slice_from(1).to_hex(), expected.to_owned());
+        let back = a.open_components([], ads, out.slice_from(1)).unwrap();
+        assert_eq!(back.as_slice(), pt.as_slice());
+        assert!(a.open_components([], ads.slice_to(2), out.slice_from(1)).is_err());
+    }
+
+    #[test]
+    fn test_with_nonce() {
+        let key = [3u8, ..64];
+        let a = AesSiv::new_with_nonce_size(key, 16).unwrap();
+        let n1 = [1u8, ..16];
+        let n2 = [2u8, ..16];
+        let c1 = a.seal([], n1, bytes!("attack at dawn"), []);
+        let c2 = a.seal([], n2, bytes!("attack at dawn"), []);
+        assert!(c1 != c2);
+        assert_eq!(a.open([], n1, c1.as_slice(), []).unwrap().as_slice(), bytes!("attack at dawn"));
+        assert!(a.open([], n2, c1.as_slice(), []).is_err());
+    }
+
+    #[test]
+    fn test_key_size() {
+        assert_eq!(AesSiv::new([0u8, ..16]).err(), Some(~"siv: invalid key size 16"));
+        assert!(AesSiv::new([0u8, ..48]).is_ok());
+        assert!(AesSiv::new_with_nonce_size([0u8, ..32], 0).is_err());
+    }
+}
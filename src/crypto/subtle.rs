@@ -0,0 +1,91 @@
+// Functions that are often useful in cryptographic code but require careful
+// thought to use correctly. None of them branch on the contents of secrets.
+
+
+/// Returns true if the two slices have equal contents. The time taken is
+/// independent of the contents, but not of the lengths.
+pub fn constant_time_compare(x: &[u8], y: &[u8]) -> bool {
+    if x.len() != y.len() {
+        return false;
+    }
+    let mut v = 0u8;
+    for i in range(0, x.len()) {
+        v |= x[i] ^ y[i];
+    }
+    constant_time_byte_eq(v, 0) == 1
+}
+
+/// Returns 1 if x == y and 0 otherwise.
+pub fn constant_time_byte_eq(x: u8, y: u8) -> int {
+    ((((x ^ y) as u32) - 1) >> 31) as int
+}
+
+/// Returns 1 if x == y and 0 otherwise.
+pub fn constant_time_eq(x: i32, y: i32) -> int {
+    ((((x ^ y) as u32 as u64) - 1) >> 63) as int
+}
+
+/// Returns x if v == 1 and y if v == 0.
+pub fn constant_time_select(v: int, x: int, y: int) -> int {
+    !(v - 1) & x | (v - 1) & y
+}
+
+/// Copies the contents of y into x if v == 1. If v == 0, x is left unchanged.
+pub fn constant_time_copy(v: int, x: &mut [u8], y: &[u8]) {
+    if x.len() != y.len() {
+        fail!("subtle: slices have different lengths");
+    }
+    let xmask = (v - 1) as u8;
+    let ymask = !(v - 1) as u8;
+    for i in range(0, x.len()) {
+        x[i] = x[i] & xmask | y[i] & ymask;
+    }
+}
+
+/// Returns 1 if x <= y and 0 otherwise. Undefined if either is negative
+/// or greater than 2**31 - 1.
+pub fn constant_time_less_or_eq(x: int, y: int) -> int {
+    let x = x as i32;
+    let y = y as i32;
+    (((x - y - 1) >> 31) & 1) as int
+}
+
+/// Sets dst[i] = x[i] ^ y[i] for i < n = min(x.len(), y.len()),
+/// returning n.
+pub fn xor_bytes(dst: &mut [u8], x: &[u8], y: &[u8]) -> uint {
+    let n = if x.len() < y.len() { x.len() } else { y.len() };
+    if dst.len() < n {
+        fail!("subtle: dst too short");
+    }
+    for i in range(0, n) {
+        dst[i] = x[i] ^ y[i];
+    }
+    n
+}
+
+
+#[test]
+fn test_constant_time_compare() {
+    assert!(constant_time_compare(bytes!("welcome"), bytes!("welcome")));
+    assert!(!constant_time_compare(bytes!("welcome"), bytes!("welcomE")));
+    assert!(!constant_time_compare(bytes!("welcome"), bytes!("welcome to china")));
+    assert!(constant_time_compare([], []));
+}
+
+#[test]
+fn test_constant_time_select() {
+    assert_eq!(constant_time_byte_eq(0, 0), 1);
+    assert_eq!(constant_time_byte_eq(0, 0xff), 0);
+    assert_eq!(constant_time_eq(-1, -1), 1);
+    assert_eq!(constant_time_eq(7, 8), 0);
+    assert_eq!(constant_time_select(1, 2, 3), 2);
+    assert_eq!(constant_time_select(0, 2, 3), 3);
+    assert_eq!(constant_time_less_or_eq(4, 5), 1);
+    assert_eq!(constant_time_less_or_eq(5, 5), 1);
+    assert_eq!(constant_time_less_or_eq(6, 5), 0);
+    let mut x = [1u8, 2, 3];
+    constant_time_copy(0, x, [4, 5, 6]);
+    assert_eq!(x.as_slice(), &[1u8, 2, 3]);
+    constant_time_copy(1, x, [4, 5, 6]);
+    assert_eq!(x.as_slice(), &[4u8, 5, 6]);
+}
@@ -16,6 +16,7 @@ pub use crypto::*;
 
 mod crypto;
 pub mod hash;
+pub mod math;
 
 
 #[cfg(test)]
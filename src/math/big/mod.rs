@@ -0,0 +1,727 @@
+// Arbitrary-precision arithmetic (big numbers), after Go's math/big.
+//
+// Int is a signed integer and Rat a rational number of arbitrary size.
+// Unlike Go, operations return new values instead of setting a receiver;
+// the arithmetic operators are implemented for Int and Rat.
+//
+// Multiplication of large operands uses the Karatsuba method. Exp with an
+// odd modulus uses Montgomery multiplication and a fixed window, and is
+// the only operation intended to run in time independent of its inputs'
+// values, as needed for RSA and Diffie-Hellman private keys.
+
+use std::cmp::{Ordering, Less, Equal, Greater};
+use std::fmt;
+use std::from_str::FromStr;
+
+pub use math::big::rat::Rat;
+
+mod nat;
+mod prime;
+pub mod rat;
+
+/// The largest base accepted by from_str_radix and text.
+pub static MaxBase: uint = nat::MaxBase;
+
+/// A signed multi-precision integer. The zero value is never negative.
+#[deriving(Clone, Eq, TotalEq)]
+pub struct Int {
+    neg: bool,
+    abs: ~[nat::Word],
+}
+
+// Returns sign * abs, keeping zero non-negative.
+fn make(neg: bool, abs: ~[nat::Word]) -> Int {
+    Int { neg: neg && abs.len() > 0, abs: abs }
+}
+
+impl Int {
+    /// Returns a new Int set to x.
+    pub fn new(x: i64) -> Int {
+        if x < 0 {
+            // -x overflows for the most negative i64, but its bit pattern
+            // read as u64 is still the right magnitude
+            make(true, nat::from_u64((-x) as u64))
+        } else {
+            make(false, nat::from_u64(x as u64))
+        }
+    }
+
+    /// Returns a new Int set to x.
+    pub fn from_u64(x: u64) -> Int {
+        make(false, nat::from_u64(x))
+    }
+
+    /// Interprets buf as the bytes of a big-endian unsigned integer and
+    /// returns it.
+    pub fn from_bytes(buf: &[u8]) -> Int {
+        make(false, nat::from_bytes(buf))
+    }
+
+    /// Parses s as an integer in the given base, like Go's SetString. An
+    /// optional sign may precede the digits.
+    ///
+    /// For base 0 the prefix selects the base: "0b" or "0B" for 2, "0",
+    /// "0o" or "0O" for 8, "0x" or "0X" for 16, and 10 otherwise; an
+    /// underscore may then separate successive digits. Otherwise base must
+    /// be between 2 and MaxBase. For bases up to 36 letters are case
+    /// insensitive; above that, 'a' to 'z' are 10 to 35 and 'A' to 'Z' are
+    /// 36 to 61.
+    pub fn from_str_radix(s: &str, base: uint) -> Option<Int> {
+        let (neg, s) = if s.starts_with("-") {
+            (true, s.slice_from(1))
+        } else if s.starts_with("+") {
+            (false, s.slice_from(1))
+        } else {
+            (false, s)
+        };
+        let abs = if base == 0 {
+            let (base, prefix) = match s.slice_to(::std::cmp::min(2, s.len())) {
+                "0x" | "0X" => (16, 2),
+                "0b" | "0B" => (2, 2),
+                "0o" | "0O" => (8, 2),
+                _ if s.len() > 1 && s.starts_with("0") => (8, 1),
+                _ => (10, 0),
+            };
+            // an underscore may also follow the prefix
+            let mut digits = s.slice_from(prefix);
+            if prefix > 0 && digits.starts_with("_") {
+                digits = digits.slice_from(1);
+            }
+            nat::parse(digits, base, true)
+        } else if base >= 2 && base <= MaxBase {
+            nat::parse(s, base, false)
+        } else {
+            None
+        };
+        abs.map(|abs| make(neg, abs))
+    }
+
+    /// Returns the string representation of x in the given base, which
+    /// must be between 2 and MaxBase. Negative values have a '-' prefix.
+    pub fn text(&self, base: uint) -> ~str {
+        let t = nat::text(self.abs, base);
+        if self.neg { format!("-{}", t) } else { t }
+    }
+
+    /// Returns the absolute value of x as a big-endian byte slice.
+    pub fn bytes(&self) -> ~[u8] {
+        nat::to_bytes(self.abs)
+    }
+
+    /// Sets buf to the absolute value of x as a zero-extended big-endian
+    /// byte slice. Fails if the value does not fit.
+    pub fn fill_bytes(&self, buf: &mut [u8]) {
+        nat::fill_bytes(self.abs, buf)
+    }
+
+    /// Returns -1, 0 or +1 for x < 0, x == 0 and x > 0.
+    pub fn sign(&self) -> int {
+        if self.abs.len() == 0 {
+            0
+        } else if self.neg {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// Returns |x|.
+    pub fn abs(&self) -> Int {
+        make(false, self.abs.clone())
+    }
+
+    /// Compares |x| and |y|.
+    pub fn cmp_abs(&self, y: &Int) -> Ordering {
+        nat::cmp(self.abs, y.abs)
+    }
+
+    /// Returns the low 64 bits of x as an i64, in two's complement.
+    pub fn to_i64(&self) -> i64 {
+        let v = nat::to_u64(self.abs) as i64;
+        if self.neg { -v } else { v }
+    }
+
+    /// Returns the low 64 bits of |x|.
+    pub fn to_u64(&self) -> u64 {
+        nat::to_u64(self.abs)
+    }
+
+    /// Reports whether x can be represented as an i64.
+    pub fn is_i64(&self) -> bool {
+        if self.abs.len() > 2 {
+            return false;
+        }
+        let w = nat::to_u64(self.abs);
+        w < 1 << 63 || (self.neg && w == 1 << 63)
+    }
+
+    /// Reports whether x can be represented as a u64.
+    pub fn is_u64(&self) -> bool {
+        !self.neg && self.abs.len() <= 2
+    }
+
+    /// Returns the length of |x| in bits. The bit length of 0 is 0.
+    pub fn bit_len(&self) -> uint {
+        nat::bit_len(self.abs)
+    }
+
+    /// Returns the number of consecutive least significant zero bits of
+    /// |x|, or 0 for x == 0.
+    pub fn trailing_zero_bits(&self) -> uint {
+        if self.abs.len() == 0 { 0 } else { nat::trailing_zero_bits(self.abs) }
+    }
+
+    /// Returns bit i of x, which must not be negative.
+    pub fn bit(&self, i: uint) -> uint {
+        if self.neg {
+            fail!("math/big: bit of negative value");
+        }
+        nat::bit(self.abs, i)
+    }
+
+    /// Returns x << n.
+    pub fn shl(&self, n: uint) -> Int {
+        make(self.neg, nat::shl(self.abs, n))
+    }
+
+    /// Returns x >> n, rounding towards negative infinity like an
+    /// arithmetic shift.
+    pub fn shr(&self, n: uint) -> Int {
+        if self.neg {
+            // (-x) >> s == ^(x-1) >> s == ^((x-1) >> s) == -(((x-1) >> s) + 1)
+            let t = nat::shr(nat::sub(self.abs, [1]), n);
+            make(true, nat::add(t, [1]))
+        } else {
+            make(false, nat::shr(self.abs, n))
+        }
+    }
+
+    /// Returns the quotient and remainder of x / y with truncated
+    /// division, like Go's QuoRem: the remainder has the sign of x. Fails
+    /// if y is zero.
+    pub fn quo_rem(&self, y: &Int) -> (Int, Int) {
+        let (q, r) = nat::div(self.abs, y.abs);
+        (make(self.neg != y.neg, q), make(self.neg, r))
+    }
+
+    /// Returns x / y rounded towards zero.
+    pub fn quo(&self, y: &Int) -> Int {
+        let (q, _) = self.quo_rem(y);
+        q
+    }
+
+    /// Returns x - y * x.quo(y).
+    pub fn rem(&self, y: &Int) -> Int {
+        let (_, r) = self.quo_rem(y);
+        r
+    }
+
+    /// Returns the quotient and modulus of x / y with Euclidean division,
+    /// like Go's DivMod: the modulus m satisfies 0 <= m < |y|. Fails if y
+    /// is zero.
+    pub fn div_mod(&self, y: &Int) -> (Int, Int) {
+        let (q, r) = self.quo_rem(y);
+        if !r.neg {
+            return (q, r);
+        }
+        let one = Int::new(1);
+        let q = if y.neg { q + one } else { q - one };
+        (q, r + y.abs())
+    }
+
+    /// Returns the Euclidean quotient of x / y.
+    pub fn div(&self, y: &Int) -> Int {
+        let (d, _) = self.div_mod(y);
+        d
+    }
+
+    /// Returns the Euclidean modulus of x / y, which is never negative.
+    pub fn modulo(&self, y: &Int) -> Int {
+        let (_, m) = self.div_mod(y);
+        m
+    }
+
+    /// Returns x^y mod |m|. If m is None or zero, the result is x^y, or 1
+    /// for y <= 0. If y < 0 and m is given, the result is the inverse of
+    /// x raised to -y, or None if x and m are not relatively prime. With
+    /// a modulus the result is never negative.
+    ///
+    /// For an odd modulus the computation takes time independent of the
+    /// values of x and y.
+    pub fn exp(&self, y: &Int, m: Option<&Int>) -> Option<Int> {
+        let m = match m {
+            Some(m) if m.abs.len() > 0 => Some(m),
+            _ => None,
+        };
+        let mut x = self.clone();
+        if y.neg {
+            match m {
+                None => return Some(Int::new(1)),
+                Some(m) => match x.mod_inverse(m) {
+                    Some(inv) => x = inv,
+                    None => return None,
+                },
+            }
+        }
+        let mabs: &[nat::Word] = match m {
+            Some(m) => m.abs.as_slice(),
+            None => &[],
+        };
+        let odd = y.abs.len() > 0 && y.abs[0] & 1 == 1;
+        let z = make(x.neg && odd, nat::exp(x.abs, y.abs, mabs));
+        if z.neg && mabs.len() > 0 {
+            return Some(make(false, nat::sub(mabs, z.abs)));
+        }
+        Some(z)
+    }
+
+    /// Returns the greatest common divisor d of a = self and b, together
+    /// with x and y such that d = a*x + b*y. a and b may be positive, zero
+    /// or negative; d is never negative. If a and b are both zero, so are
+    /// d, x and y. If only a is zero, d = |b|, x = 0 and y = sign(b).
+    pub fn gcd(&self, b: &Int) -> (Int, Int, Int) {
+        let zero = Int::new(0);
+        if self.abs.len() == 0 && b.abs.len() == 0 {
+            return (zero.clone(), zero.clone(), zero);
+        }
+        // the extended Euclidean algorithm on |a| and |b|
+        let (mut r0, mut r1) = (self.abs(), b.abs());
+        let (mut s0, mut s1) = (Int::new(1), zero.clone());
+        let (mut t0, mut t1) = (zero.clone(), Int::new(1));
+        while r1.abs.len() > 0 {
+            let (q, r) = r0.quo_rem(&r1);
+            r0 = r1;
+            r1 = r;
+            let s = s0 - q * s1;
+            s0 = s1;
+            s1 = s;
+            let t = t0 - q * t1;
+            t0 = t1;
+            t1 = t;
+        }
+        let x = if self.neg { -s0 } else { s0 };
+        let y = if b.neg { -t0 } else { t0 };
+        (r0, x, y)
+    }
+
+    /// Returns the multiplicative inverse of x in the ring of integers
+    /// modulo |n|, or None if x and n are not relatively prime.
+    pub fn mod_inverse(&self, n: &Int) -> Option<Int> {
+        let n = n.abs();
+        let g = if self.neg { self.modulo(&n) } else { self.clone() };
+        let (d, x, _) = g.gcd(&n);
+        if d != Int::new(1) {
+            return None;
+        }
+        if x.neg {
+            Some(x + n)
+        } else {
+            Some(x)
+        }
+    }
+
+    /// Returns the floor of the square root of x. Fails if x is negative.
+    pub fn sqrt(&self) -> Int {
+        if self.neg {
+            fail!("square root of negative number");
+        }
+        make(false, nat::sqrt(self.abs))
+    }
+
+    /// Reports whether x is probably prime, applying the Miller-Rabin
+    /// test with n pseudorandomly chosen bases as well as a Baillie-PSW
+    /// test.
+    ///
+    /// If x is prime, it returns true. If x is chosen randomly and not
+    /// prime, it probably returns false; the probability of returning true
+    /// for a randomly chosen non-prime is at most 1/4^n. It is 100%
+    /// accurate for inputs less than 2^64. It is not suitable for judging
+    /// primes that an adversary may have crafted to fool the test.
+    pub fn probably_prime(&self, n: uint) -> bool {
+        !self.neg && prime::probably_prime(self.abs, n)
+    }
+}
+
+/// Returns the Jacobi symbol (x/y), either +1, -1, or 0. Fails if y is
+/// not odd.
+pub fn jacobi(x: &Int, y: &Int) -> int {
+    if y.abs.len() == 0 || y.abs[0] & 1 == 0 {
+        fail!("math/big: invalid 2nd argument to jacobi: need odd integer but got {}", y);
+    }
+    let j = if x.neg && y.neg { -1 } else { 1 };
+    let b = y.abs();
+    let a = x.modulo(&b);
+    j * prime::jacobi(a.abs, b.abs)
+}
+
+impl Add<Int, Int> for Int {
+    fn add(&self, y: &Int) -> Int {
+        if self.neg == y.neg {
+            return make(self.neg, nat::add(self.abs, y.abs));
+        }
+        // x + (-y) == x - y == -(y - x)
+        // (-x) + y == y - x == -(x - y)
+        if nat::cmp(self.abs, y.abs) != Less {
+            make(self.neg, nat::sub(self.abs, y.abs))
+        } else {
+            make(!self.neg, nat::sub(y.abs, self.abs))
+        }
+    }
+}
+
+impl Sub<Int, Int> for Int {
+    fn sub(&self, y: &Int) -> Int {
+        *self + (-*y)
+    }
+}
+
+impl Mul<Int, Int> for Int {
+    fn mul(&self, y: &Int) -> Int {
+        make(self.neg != y.neg, nat::mul(self.abs, y.abs))
+    }
+}
+
+impl Neg<Int> for Int {
+    fn neg(&self) -> Int {
+        make(!self.neg, self.abs.clone())
+    }
+}
+
+impl Ord for Int {
+    fn lt(&self, y: &Int) -> bool {
+        self.cmp(y) == Less
+    }
+}
+
+impl TotalOrd for Int {
+    fn cmp(&self, y: &Int) -> Ordering {
+        match (self.neg, y.neg) {
+            (false, true) => Greater,
+            (true, false) => Less,
+            (false, false) => nat::cmp(self.abs, y.abs),
+            (true, true) => nat::cmp(y.abs, self.abs),
+        }
+    }
+}
+
+impl FromStr for Int {
+    fn from_str(s: &str) -> Option<Int> {
+        Int::from_str_radix(s, 10)
+    }
+}
+
+impl fmt::Show for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.buf.write_str(self.text(10))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::cmp::{Less, Equal, Greater};
+    use std::from_str::from_str;
+    use std::i64;
+    use super::*;
+
+    fn int(s: &str) -> Int {
+        Int::from_str_radix(s, 0).unwrap()
+    }
+
+    #[test]
+    fn test_arith() {
+        let a = int("123456789012345678901234567890");
+        let b = int("-987654321098765432109876543210");
+        assert_eq!((a + b).text(10), ~"-864197532086419753208641975320");
+        assert_eq!((a - b).text(10), ~"1111111110111111111011111111100");
+        assert_eq!((a * b).text(10),
+                   ~"-121932631137021795226185032733622923332237463801111263526900");
+        assert_eq!((b - b).sign(), 0);
+        assert_eq!((-a).sign(), -1);
+        assert_eq!(a + (-a), Int::new(0));
+        assert_eq!(Int::new(-5).abs(), Int::new(5));
+        assert_eq!(Int::new(i64::MIN).text(10), ~"-9223372036854775808");
+        assert_eq!(Int::new(i64::MIN).to_i64(), i64::MIN);
+        assert!(Int::new(i64::MIN).is_i64());
+        assert!(!(-Int::new(i64::MIN)).is_i64());
+        assert!((-Int::new(i64::MIN)).is_u64());
+    }
+
+    #[test]
+    fn test_quo_rem_div_mod() {
+        // Go's Int.QuoRem and Int.DivMod examples
+        let tests = [
+            (5i64, 3i64, 1i64, 2i64, 1i64, 2i64),
+            (-5, 3, -1, -2, -2, 1),
+            (5, -3, -1, 2, -1, 2),
+            (-5, -3, 1, -2, 2, 1),
+        ];
+        for &(x, y, q, r, d, m) in tests.iter() {
+            let (x, y) = (Int::new(x), Int::new(y));
+            assert_eq!(x.quo_rem(&y), (Int::new(q), Int::new(r)));
+            assert_eq!(x.div_mod(&y), (Int::new(d), Int::new(m)));
+        }
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_div_by_zero() {
+        Int::new(1).quo(&Int::new(0));
+    }
+
+    #[test]
+    fn test_cmp() {
+        assert_eq!(Int::new(-3).cmp(&Int::new(2)), Less);
+        assert_eq!(Int::new(-3).cmp(&Int::new(-4)), Greater);
+        assert_eq!(int("0x100000000").cmp(&Int::from_u64(1 << 32)), Equal);
+        assert!(Int::new(-1) < Int::new(0));
+        assert_eq!(Int::new(-7).cmp_abs(&Int::new(7)), Equal);
+    }
+
+    #[test]
+    fn test_string() {
+        let tests = [
+            ("0", 0u, Some("0")),
+            ("-0", 0, Some("0")),
+            ("+10", 0, Some("10")),
+            ("0x_ff_ff", 0, Some("65535")),
+            ("0x__ff", 0, None),
+            ("0_17", 0, Some("15")),
+            ("0xffff_ffff", 0, Some("4294967295")),
+            ("0b101", 0, Some("5")),
+            ("0o17", 0, Some("15")),
+            ("017", 0, Some("15")),
+            ("0x", 0, None),
+            ("1_000", 10, None),
+            ("-zz", 36, Some("-1295")),
+            ("ZZ", 36, Some("1295")),
+            ("ZZ", 62, Some("3843")),
+            ("12", 1, None),
+            ("12", 63, None),
+            ("", 10, None),
+            ("-", 10, None),
+        ];
+        for &(s, base, want) in tests.iter() {
+            assert_eq!(Int::from_str_radix(s, base).map(|x| x.text(10)), want.map(|w| w.to_owned()));
+        }
+        let x = int("-0x123456789abcdef0123456789abcdef");
+        assert_eq!(x.text(16), ~"-123456789abcdef0123456789abcdef");
+        assert_eq!(format!("{}", Int::new(-42)), ~"-42");
+        assert_eq!(from_str::<Int>("987654321987654321"), Some(int("987654321987654321")));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let x = Int::from_bytes([0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(x.text(16), ~"10203040506070809");
+        assert_eq!(x.bytes(), ~[1u8, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let mut buf = [0xffu8, ..12];
+        x.fill_bytes(buf);
+        assert_eq!(buf.as_slice(), &[0u8, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(Int::new(0).bytes(), ~[]);
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(Int::new(1).shl(70).text(16), ~"400000000000000000");
+        assert_eq!(Int::new(-1).shl(70).shr(70), Int::new(-1));
+        assert_eq!(Int::new(-7).shr(1), Int::new(-4));
+        assert_eq!(Int::new(-8).shr(1), Int::new(-4));
+        assert_eq!(Int::new(-1).shr(100), Int::new(-1));
+        assert_eq!(Int::new(7).shr(1), Int::new(3));
+        assert_eq!(Int::new(96).trailing_zero_bits(), 5);
+        assert_eq!(Int::new(96).bit_len(), 7);
+        assert_eq!(Int::new(96).bit(5), 1);
+    }
+
+    #[test]
+    fn test_exp() {
+        let tests = [
+            ("5", "0", "", "1"),
+            ("-5", "0", "", "1"),
+            ("-5", "-2", "", "1"),
+            ("5", "1", "1", "0"),
+            ("-5", "3", "", "-125"),
+            ("-5", "3", "7", "1"),
+            ("2", "-1", "7", "4"),
+            ("0x8000000000000000", "2", "", "0x40000000000000000000000000000000"),
+            ("0x8000000000000000", "2", "6719", "4944"),
+            ("0x8000000000000000", "3", "6719", "5447"),
+            ("0x8000000000000000", "1000", "6719", "1603"),
+            ("0x8000000000000000", "1000000", "6719", "3199"),
+            ("2", "0x10", "0x1000000000000000000000000000000000000000000000000000000000000001",
+             "0x10000"),
+            ("0xa73c8245e9ce4f3e4d9f6c4e7c6e6e1a7a1ab2c3d4e5f60718293a4b5c6d7e8f",
+             "0x10001",
+             "0xf2b3e3fb1a2d77e1f6f0aa5d3b6f1c7d9d0b7c4e1a2f3e4d5c6b7a8998877665",
+             "0x2e041047b7f3f05f4ac327faba190714dbe386cdadc58cafa09481ce23fd4656"),
+        ];
+        for &(x, y, m, want) in tests.iter() {
+            let m = if m.len() == 0 { None } else { Some(int(m)) };
+            let z = int(x).exp(&int(y), m.as_ref()).unwrap();
+            assert_eq!(z, int(want));
+        }
+        // no inverse
+        assert_eq!(Int::new(2).exp(&Int::new(-1), Some(&Int::new(4))), None);
+    }
+
+    #[test]
+    fn test_gcd() {
+        let tests = [
+            (0i64, 0i64, 0i64),
+            (0, 5, 5),
+            (0, -5, 5),
+            (5, 0, 5),
+            (-5, 0, 5),
+            (12, 18, 6),
+            (-12, 18, 6),
+            (12, -18, 6),
+            (-12, -18, 6),
+            (935, 1045, 55),
+        ];
+        for &(a, b, d) in tests.iter() {
+            let (a, b) = (Int::new(a), Int::new(b));
+            let (g, x, y) = a.gcd(&b);
+            assert_eq!(g, Int::new(d));
+            assert_eq!(a * x + b * y, g);
+        }
+        assert_eq!(Int::new(0).gcd(&Int::new(-5)), (Int::new(5), Int::new(0), Int::new(-1)));
+
+        let a = int("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+        let b = int("123456789123456789123456789");
+        let (g, x, y) = a.gcd(&b);
+        assert_eq!(g, Int::new(1));
+        assert_eq!(a * x + b * y, g);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        let tests = [
+            ("1234567", "458948883992"),
+            ("239487239847", "2410312426921032588552076022197566074856950548502459942654116941958108831682612228890093858261341614673227141477904012196503648957050582631942730706805009223062734745341073406696246014589361659774041027169249453200378729434170325843778659198143763193776859869524088940195577346119843545301547043747207749969763750084308926339295559968882457872412993810129130294592999947926365264059284647209730384947211681434464714438488520940127459844288859336526896320919633919"),
+            ("-10", "13"),
+            ("10", "-13"),
+            ("-17", "-13"),
+        ];
+        for &(g, n) in tests.iter() {
+            let (g, n) = (int(g), int(n));
+            let inv = g.mod_inverse(&n).unwrap();
+            assert_eq!((g * inv).modulo(&n), Int::new(1));
+        }
+        assert_eq!(Int::new(4).mod_inverse(&Int::new(6)), None);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let x = int("0x10000000000000000000000000000000000000000");
+        assert_eq!(x.sqrt().text(16), ~"100000000000000000000");
+        assert_eq!((x - Int::new(1)).sqrt().text(16), ~"ffffffffffffffffffff");
+    }
+
+    #[test]
+    fn test_jacobi() {
+        let tests = [
+            (0i64, 1i64, 1),
+            (0, -1, 1),
+            (1, 1, 1),
+            (1, -1, 1),
+            (0, 5, 0),
+            (1, 5, 1),
+            (2, 5, -1),
+            (-2, 5, -1),
+            (2, -5, -1),
+            (-2, -5, 1),
+            (3, 5, -1),
+            (5, 5, 0),
+            (-5, 5, 0),
+            (6, 5, 1),
+            (6, -5, 1),
+            (-6, 5, 1),
+            (-6, -5, -1),
+        ];
+        for &(x, y, want) in tests.iter() {
+            assert_eq!(jacobi(&Int::new(x), &Int::new(y)), want);
+        }
+    }
+
+    #[test]
+    fn test_probably_prime() {
+        let primes = [
+            "2", "3", "5", "7", "11",
+            "13756265695458089029",
+            "13496181268022124907",
+            "10953742525620032441",
+            "17908251027575790097",
+            // https://golang.org/issue/638
+            "18699199384836356663",
+            "98920366548084643601728869055592650835572950932266967461790948584315647051443",
+            "94560208308847015747498523884063394671606671904944666360068158221458669711639",
+            // https://primes.utm.edu/lists/small/small3.html
+            "449417999055441493994709297093108513015373787049558499205492347871729927573118262811508386655998299074566974373711472560655026288668094291699357843464363003144674940345912431129144354948751003607115263071543163",
+            "230975859993204150666423538988557839555560243929065415434980904258310530753006723857139742334640122533598517597674807096648905501653461687601339782814316124971547968912893214002992086353183070342498989426570593",
+            // ECC primes: https://tools.ietf.org/html/draft-ladd-safecurves-02
+            "3618502788666131106986593281521497120414687020801267626233049500247285301239",
+            "57896044618658097711785492504343953926634992332820282019728792003956564819949",
+            "9850501549098619803069760025035903451269934817616361666987073351061430442874302652853566563721228910201656997576599",
+            "42307582002575910332922579714097346549017899709713998034217522897561970639123926132812109468141778230245837569601494931472367",
+            "6864797660130609714981900799081393217269435300143305409394463459185543183397655394245057746333217197532963996371363321113864768612440380340372808892707005449",
+        ];
+        for p in primes.iter() {
+            let p = int(*p);
+            assert!(p.probably_prime(20));
+            assert!(p.probably_prime(0));
+        }
+        let composites = [
+            "0", "1", "4", "9",
+            "21284175091214687912771199898307297748211672914763848041968395774954376176754",
+            "6084766654921918907427900243509372380954290099172559290432744450051395395951",
+            "84594350493221918389213352992032324280367711247940675652888030554255915464401",
+            "82793403787388584738507275144194252681",
+            // Arnault, "Rabin-Miller Primality Test: Composite Numbers Which
+            // Pass It", Mathematics of Computation, 64(209) (January 1995),
+            // pp. 335-361: strong pseudoprime to prime bases 2 through 29
+            "1195068768795265792518361315725116351898245581",
+            // a strong Lucas pseudoprime and a strong pseudoprime to base 2
+            "989",
+            "3239",
+            "5777",
+            "10877",
+            "27971",
+            "29681",
+            "30739",
+            "31631",
+            "39059",
+            "72389",
+            "73919",
+            "75077",
+            "100127",
+            "113573",
+            "125249",
+            "137549",
+            "137801",
+            "153931",
+            "155819",
+            "2047",
+            "3277",
+            "4033",
+            "4681",
+            "8321",
+            "15841",
+            "29341",
+            "42799",
+            "49141",
+            "52633",
+            "65281",
+            "74665",
+            "80581",
+            "85489",
+            "88357",
+            "90751",
+        ];
+        for c in composites.iter() {
+            assert!(!int(*c).probably_prime(20), "{} is composite", *c);
+            assert!(!int(*c).probably_prime(0), "{} is composite", *c);
+        }
+        assert!(!Int::new(-7).probably_prime(20));
+    }
+}
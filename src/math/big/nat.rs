@@ -0,0 +1,705 @@
+// Unsigned arbitrary-precision arithmetic on little-endian vectors of
+// 32-bit words. A normalized nat has no most significant zero words, so
+// zero is the empty vector.
+
+use std::cmp::{Ordering, Less, Equal, Greater};
+use std::slice;
+
+pub type Word = u32;
+
+pub static WordBits: uint = 32;
+
+static WordMask: u64 = 0xffffffff;
+
+// Operands with fewer words than this are multiplied with the schoolbook
+// method.
+static KaratsubaThreshold: uint = 40;
+
+pub fn norm(mut z: ~[Word]) -> ~[Word] {
+    let mut n = z.len();
+    while n > 0 && z[n - 1] == 0 {
+        n -= 1;
+    }
+    z.truncate(n);
+    z
+}
+
+pub fn from_u64(x: u64) -> ~[Word] {
+    norm(~[x as Word, (x >> 32) as Word])
+}
+
+pub fn to_u64(x: &[Word]) -> u64 {
+    let mut z = 0u64;
+    if x.len() > 0 {
+        z = x[0] as u64;
+    }
+    if x.len() > 1 {
+        z |= x[1] as u64 << 32;
+    }
+    z
+}
+
+pub fn cmp(x: &[Word], y: &[Word]) -> Ordering {
+    if x.len() != y.len() {
+        return if x.len() < y.len() { Less } else { Greater };
+    }
+    for i in range(0, x.len()).rev() {
+        if x[i] != y[i] {
+            return if x[i] < y[i] { Less } else { Greater };
+        }
+    }
+    Equal
+}
+
+pub fn add(x: &[Word], y: &[Word]) -> ~[Word] {
+    if x.len() < y.len() {
+        return add(y, x);
+    }
+    let mut z = slice::from_elem(x.len() + 1, 0 as Word);
+    let mut c = 0u64;
+    for i in range(0, x.len()) {
+        let yi = if i < y.len() { y[i] as u64 } else { 0 };
+        let s = x[i] as u64 + yi + c;
+        z[i] = s as Word;
+        c = s >> 32;
+    }
+    z[x.len()] = c as Word;
+    norm(z)
+}
+
+// Returns x - y; x must not be smaller than y.
+pub fn sub(x: &[Word], y: &[Word]) -> ~[Word] {
+    let mut z = slice::from_elem(x.len(), 0 as Word);
+    let mut borrow = 0u64;
+    for i in range(0, x.len()) {
+        let yi = if i < y.len() { y[i] as u64 } else { 0 };
+        let t = x[i] as u64 + (1 << 32) - yi - borrow;
+        z[i] = t as Word;
+        borrow = 1 - (t >> 32);
+    }
+    if borrow != 0 {
+        fail!("math/big: underflow");
+    }
+    norm(z)
+}
+
+// z += x * y, returning the carry out of the top of z, which must be at
+// least as long as x.
+fn add_mul_vvw(z: &mut [Word], x: &[Word], y: Word) -> Word {
+    let mut c = 0u64;
+    for i in range(0, x.len()) {
+        let t = x[i] as u64 * y as u64 + z[i] as u64 + c;
+        z[i] = t as Word;
+        c = t >> 32;
+    }
+    c as Word
+}
+
+// Adds x into z starting at word offset i, propagating the carry.
+fn add_at(z: &mut [Word], x: &[Word], i: uint) {
+    let mut c = 0u64;
+    let mut j = 0u;
+    while j < x.len() || c != 0 {
+        let xj = if j < x.len() { x[j] as u64 } else { 0 };
+        let s = z[i + j] as u64 + xj + c;
+        z[i + j] = s as Word;
+        c = s >> 32;
+        j += 1;
+    }
+}
+
+fn basic_mul(x: &[Word], y: &[Word]) -> ~[Word] {
+    let mut z = slice::from_elem(x.len() + y.len(), 0 as Word);
+    for j in range(0, y.len()) {
+        if y[j] != 0 {
+            let c = add_mul_vvw(z.mut_slice(j, j + x.len()), x, y[j]);
+            z[j + x.len()] = c;
+        }
+    }
+    norm(z)
+}
+
+pub fn mul(x: &[Word], y: &[Word]) -> ~[Word] {
+    if x.len() == 0 || y.len() == 0 {
+        return ~[];
+    }
+    if x.len() < KaratsubaThreshold || y.len() < KaratsubaThreshold {
+        return basic_mul(x, y);
+    }
+    karatsuba(x, y)
+}
+
+// Karatsuba multiplication: with x = x1*b + x0 and y = y1*b + y0 for
+// b = 2^(32k), x*y = z2*b^2 + z1*b + z0 where z0 = x0*y0, z2 = x1*y1 and
+// z1 = (x0+x1)(y0+y1) - z0 - z2, so three half-size products suffice.
+fn karatsuba(x: &[Word], y: &[Word]) -> ~[Word] {
+    let k = ::std::cmp::max(x.len(), y.len()) / 2;
+    let (x0, x1) = split(x, k);
+    let (y0, y1) = split(y, k);
+    let z0 = mul(x0, y0);
+    let z2 = mul(x1, y1);
+    let z1 = mul(add(x0, x1), add(y0, y1));
+    let z1 = sub(sub(z1, z0), z2);
+
+    let mut z = slice::from_elem(x.len() + y.len() + 1, 0 as Word);
+    add_at(z, z0, 0);
+    add_at(z, z1, k);
+    add_at(z, z2, 2 * k);
+    norm(z)
+}
+
+// Splits x into its low k words and the rest, both normalized.
+fn split(x: &[Word], k: uint) -> (~[Word], ~[Word]) {
+    if x.len() <= k {
+        (norm(x.to_owned()), ~[])
+    } else {
+        (norm(x.slice_to(k).to_owned()), x.slice_from(k).to_owned())
+    }
+}
+
+fn nlz(x: Word) -> uint {
+    let mut n = 0u;
+    let mut x = x;
+    if x == 0 {
+        return WordBits;
+    }
+    while x & 0x80000000 == 0 {
+        x <<= 1;
+        n += 1;
+    }
+    n
+}
+
+pub fn bit_len(x: &[Word]) -> uint {
+    if x.len() == 0 {
+        return 0;
+    }
+    x.len() * WordBits - nlz(x[x.len() - 1])
+}
+
+// Returns the number of consecutive least significant zero bits of x,
+// which must not be zero.
+pub fn trailing_zero_bits(x: &[Word]) -> uint {
+    let mut i = 0u;
+    while x[i] == 0 {
+        i += 1;
+    }
+    let mut w = x[i];
+    let mut n = i * WordBits;
+    while w & 1 == 0 {
+        w >>= 1;
+        n += 1;
+    }
+    n
+}
+
+pub fn bit(x: &[Word], i: uint) -> uint {
+    let j = i / WordBits;
+    if j >= x.len() {
+        return 0;
+    }
+    ((x[j] >> (i % WordBits)) & 1) as uint
+}
+
+pub fn shl(x: &[Word], s: uint) -> ~[Word] {
+    if x.len() == 0 {
+        return ~[];
+    }
+    let (ws, bs) = (s / WordBits, s % WordBits);
+    let mut z = slice::from_elem(x.len() + ws + 1, 0 as Word);
+    for i in range(0, x.len()) {
+        let t = (x[i] as u64) << bs;
+        z[i + ws] |= t as Word;
+        z[i + ws + 1] = (t >> 32) as Word;
+    }
+    norm(z)
+}
+
+pub fn shr(x: &[Word], s: uint) -> ~[Word] {
+    let (ws, bs) = (s / WordBits, s % WordBits);
+    if ws >= x.len() {
+        return ~[];
+    }
+    let n = x.len() - ws;
+    let mut z = slice::from_elem(n, 0 as Word);
+    for i in range(0, n) {
+        let hi = if i + ws + 1 < x.len() { x[i + ws + 1] as u64 } else { 0 };
+        let t = (hi << 32 | x[i + ws] as u64) >> bs;
+        z[i] = t as Word;
+    }
+    norm(z)
+}
+
+// Returns x / d and x % d for a single word d.
+pub fn div_w(x: &[Word], d: Word) -> (~[Word], Word) {
+    if d == 0 {
+        fail!("division by zero");
+    }
+    let mut q = slice::from_elem(x.len(), 0 as Word);
+    let mut r = 0u64;
+    for i in range(0, x.len()).rev() {
+        let t = r << 32 | x[i] as u64;
+        q[i] = (t / d as u64) as Word;
+        r = t % d as u64;
+    }
+    (norm(q), r as Word)
+}
+
+// Returns x * y + r for single words y and r.
+pub fn mul_add_ww(x: &[Word], y: Word, r: Word) -> ~[Word] {
+    let mut z = slice::from_elem(x.len() + 1, 0 as Word);
+    let mut c = r as u64;
+    for i in range(0, x.len()) {
+        let t = x[i] as u64 * y as u64 + c;
+        z[i] = t as Word;
+        c = t >> 32;
+    }
+    z[x.len()] = c as Word;
+    norm(z)
+}
+
+/// Returns the quotient and remainder of u / v, with Knuth's Algorithm D
+/// (The Art of Computer Programming, Vol. 2, 4.3.1).
+pub fn div(u: &[Word], v: &[Word]) -> (~[Word], ~[Word]) {
+    if v.len() == 0 {
+        fail!("division by zero");
+    }
+    if cmp(u, v) == Less {
+        return (~[], u.to_owned());
+    }
+    if v.len() == 1 {
+        let (q, r) = div_w(u, v[0]);
+        return (q, from_u64(r as u64));
+    }
+
+    // Normalize so that the top bit of the divisor is set, which keeps
+    // the quotient digit estimates within two of the true value.
+    let n = v.len();
+    let m = u.len() - n;
+    let s = nlz(v[n - 1]);
+    let vn = shl(v, s);
+    let mut un = shl(u, s);
+    un.grow(u.len() + 1 - un.len(), &(0 as Word));
+    let mut q = slice::from_elem(m + 1, 0 as Word);
+
+    let (vtop, vnext) = (vn[n - 1] as u64, vn[n - 2] as u64);
+    for j in range(0, m + 1).rev() {
+        let num = (un[j + n] as u64) << 32 | un[j + n - 1] as u64;
+        let mut qhat = num / vtop;
+        let mut rhat = num % vtop;
+        while qhat > WordMask || qhat * vnext > (rhat << 32 | un[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vtop;
+            if rhat > WordMask {
+                break;
+            }
+        }
+
+        // un[j..j+n+1] -= qhat * vn
+        let mut carry = 0u64;
+        let mut borrow = 0u64;
+        for i in range(0, n) {
+            let p = qhat * vn[i] as u64 + carry;
+            carry = p >> 32;
+            let t = un[i + j] as u64 + (1 << 32) - (p & WordMask) - borrow;
+            un[i + j] = t as Word;
+            borrow = 1 - (t >> 32);
+        }
+        let t = un[j + n] as u64 + (1 << 32) - carry - borrow;
+        un[j + n] = t as Word;
+
+        if t >> 32 == 0 {
+            // qhat was one too large: add the divisor back
+            qhat -= 1;
+            let mut c = 0u64;
+            for i in range(0, n) {
+                let s = un[i + j] as u64 + vn[i] as u64 + c;
+                un[i + j] = s as Word;
+                c = s >> 32;
+            }
+            un[j + n] += c as Word;
+        }
+        q[j] = qhat as Word;
+    }
+    let r = shr(norm(un.slice_to(n).to_owned()), s);
+    (norm(q), r)
+}
+
+pub fn modulo(u: &[Word], v: &[Word]) -> ~[Word] {
+    let (_, r) = div(u, v);
+    r
+}
+
+/// Returns the floor of the square root of x, by Newton's method.
+pub fn sqrt(x: &[Word]) -> ~[Word] {
+    if cmp(x, [1]) != Greater {
+        return x.to_owned();
+    }
+    // Start with a value known to be too large and repeat
+    // z = (z + x/z) / 2 until it stops getting smaller.
+    let mut z1 = shl([1], (bit_len(x) + 1) / 2);
+    loop {
+        let (q, _) = div(x, z1);
+        let z2 = shr(add(q, z1), 1);
+        if cmp(z2, z1) != Less {
+            return z1;
+        }
+        z1 = z2;
+    }
+}
+
+pub fn from_bytes(b: &[u8]) -> ~[Word] {
+    let mut z = slice::from_elem((b.len() + 3) / 4, 0 as Word);
+    for (i, &x) in b.iter().rev().enumerate() {
+        z[i / 4] |= x as Word << (8 * (i % 4));
+    }
+    norm(z)
+}
+
+// Writes x big-endian into the end of buf, which must be large enough,
+// and zeroes the rest.
+pub fn fill_bytes(x: &[Word], buf: &mut [u8]) {
+    for b in buf.mut_iter() {
+        *b = 0;
+    }
+    let n = buf.len();
+    for i in range(0, x.len() * 4) {
+        let b = (x[i / 4] >> (8 * (i % 4))) as u8;
+        if i < n {
+            buf[n - 1 - i] = b;
+        } else if b != 0 {
+            fail!("math/big: buffer too small to fit value");
+        }
+    }
+}
+
+pub fn to_bytes(x: &[Word]) -> ~[u8] {
+    let mut buf = slice::from_elem((bit_len(x) + 7) / 8, 0u8);
+    fill_bytes(x, buf);
+    buf
+}
+
+// Montgomery multiplication: returns x * y * 2^(-32n) mod m, where x and
+// y are n words long and less than m, m is odd and k0 = -m^(-1) mod 2^32.
+// The result is also n words long and is computed without branching on
+// the values involved.
+fn mont_mul(x: &[Word], y: &[Word], m: &[Word], k0: Word) -> ~[Word] {
+    let n = m.len();
+    let mut t = slice::from_elem(n + 2, 0 as Word);
+    for i in range(0, n) {
+        // t += x[i] * y
+        let mut c = 0u64;
+        for j in range(0, n) {
+            let s = t[j] as u64 + x[i] as u64 * y[j] as u64 + c;
+            t[j] = s as Word;
+            c = s >> 32;
+        }
+        let s = t[n] as u64 + c;
+        t[n] = s as Word;
+        t[n + 1] = (s >> 32) as Word;
+
+        // t = (t + mq * m) / 2^32, where mq makes the low word vanish
+        let mq = t[0] * k0;
+        let s = t[0] as u64 + mq as u64 * m[0] as u64;
+        let mut c = s >> 32;
+        for j in range(1, n) {
+            let s = t[j] as u64 + mq as u64 * m[j] as u64 + c;
+            t[j - 1] = s as Word;
+            c = s >> 32;
+        }
+        let s = t[n] as u64 + c;
+        t[n - 1] = s as Word;
+        t[n] = t[n + 1] + (s >> 32) as Word;
+        t[n + 1] = 0;
+    }
+
+    // t < 2m; subtract m if t >= m, selecting the result with a mask
+    let mut d = slice::from_elem(n, 0 as Word);
+    let mut borrow = 0u64;
+    for i in range(0, n) {
+        let s = t[i] as u64 + (1 << 32) - m[i] as u64 - borrow;
+        d[i] = s as Word;
+        borrow = 1 - (s >> 32);
+    }
+    let s = t[n] as u64 + (1 << 32) - borrow;
+    let keep = ((s >> 32) as Word) - 1; // all ones if t < m
+    let mut z = slice::from_elem(n, 0 as Word);
+    for i in range(0, n) {
+        z[i] = (t[i] & keep) | (d[i] & !keep);
+    }
+    z
+}
+
+fn pad(x: &[Word], n: uint) -> ~[Word] {
+    let mut z = slice::from_elem(n, 0 as Word);
+    z.mut_slice_to(x.len()).copy_from(x);
+    z
+}
+
+/// Returns x^y mod m. If m is odd the computation uses Montgomery
+/// multiplication with a fixed 4-bit window and constant-time table
+/// lookups, so that its timing depends only on the lengths of the
+/// operands. An empty m means no reduction.
+pub fn exp(x: &[Word], y: &[Word], m: &[Word]) -> ~[Word] {
+    if m.len() == 1 && m[0] == 1 {
+        return ~[];
+    }
+    if y.len() == 0 {
+        return ~[1];
+    }
+    if m.len() > 0 && m[0] & 1 == 1 {
+        return exp_montgomery(x, y, m);
+    }
+
+    let reduce = |a: ~[Word]| if m.len() == 0 { a } else { modulo(a, m) };
+    let x = reduce(x.to_owned());
+    let mut z = ~[1 as Word];
+    for i in range(0, bit_len(y)).rev() {
+        z = reduce(mul(z, z));
+        if bit(y, i) == 1 {
+            z = reduce(mul(z, x));
+        }
+    }
+    z
+}
+
+fn exp_montgomery(x: &[Word], y: &[Word], m: &[Word]) -> ~[Word] {
+    let n = m.len();
+    // k0 = -m^(-1) mod 2^32, by Newton's iteration: each step doubles the
+    // number of correct low bits
+    let mut inv = 1 as Word;
+    for _ in range(0, 5u) {
+        inv *= 2 - m[0] * inv;
+    }
+    let k0 = 0 - inv;
+
+    // rr = 2^(64n) mod m converts into the Montgomery domain
+    let rr = pad(modulo(shl([1], 2 * n * WordBits), m), n);
+    let one = pad([1], n);
+    let x = pad(modulo(x, m), n);
+
+    let mut table = Vec::with_capacity(16);
+    table.push(mont_mul(one, rr, m, k0));
+    table.push(mont_mul(x, rr, m, k0));
+    for i in range(2, 16u) {
+        let t = mont_mul(table.get(i - 1).as_slice(), table.get(1).as_slice(), m, k0);
+        table.push(t);
+    }
+
+    let mut z = table.get(0).clone();
+    for i in range(0, y.len()).rev() {
+        let yi = y[i];
+        for j in range(0, WordBits / 4).rev() {
+            for _ in range(0, 4u) {
+                z = mont_mul(z, z, m, k0);
+            }
+            // select table[w] without a data-dependent memory access
+            let w = (yi >> (4 * j)) & 0xf;
+            let mut t = slice::from_elem(n, 0 as Word);
+            for (k, entry) in table.iter().enumerate() {
+                let mask = eq_mask(k as Word, w);
+                for l in range(0, n) {
+                    t[l] |= (*entry)[l] & mask;
+                }
+            }
+            z = mont_mul(z, t, m, k0);
+        }
+    }
+    norm(mont_mul(z, one, m, k0))
+}
+
+// Returns all ones if x == y and zero otherwise, without branching.
+fn eq_mask(x: Word, y: Word) -> Word {
+    let d = (x ^ y) as u64;
+    // d - 1 borrows into the high half only when d is zero
+    ((d - 1) >> 32) as Word
+}
+
+static digits: &'static [u8] = bytes!("0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ");
+
+/// The largest supported base for text conversion.
+pub static MaxBase: uint = 62;
+
+pub fn text(x: &[Word], base: uint) -> ~str {
+    if base < 2 || base > MaxBase {
+        fail!("math/big: invalid base {}", base);
+    }
+    if x.len() == 0 {
+        return ~"0";
+    }
+    // Divide by the largest power of base that fits in a word, and
+    // convert each remainder into ndigits digits.
+    let mut bb = base as u64;
+    let mut ndigits = 1u;
+    while bb * base as u64 <= WordMask {
+        bb *= base as u64;
+        ndigits += 1;
+    }
+    let mut out = ~[];
+    let mut q = x.to_owned();
+    while q.len() > 0 {
+        let (nq, mut r) = div_w(q, bb as Word);
+        q = nq;
+        for _ in range(0, ndigits) {
+            if q.len() == 0 && r == 0 {
+                break;
+            }
+            out.push(digits[(r % base as Word) as uint]);
+            r /= base as Word;
+        }
+    }
+    out.reverse();
+    ::std::str::from_utf8_owned(out).unwrap()
+}
+
+fn digit_value(c: char, base: uint) -> Option<uint> {
+    let d = match c {
+        '0'..'9' => c as uint - '0' as uint,
+        'a'..'z' => c as uint - 'a' as uint + 10,
+        'A'..'Z' if base <= 36 => c as uint - 'A' as uint + 10,
+        'A'..'Z' => c as uint - 'A' as uint + 36,
+        _ => return None,
+    };
+    if d < base { Some(d) } else { None }
+}
+
+/// Parses the digits of s in the given base, which must be between 2 and
+/// MaxBase. An underscore may separate successive digits if underscores
+/// is set.
+pub fn parse(s: &str, base: uint, underscores: bool) -> Option<~[Word]> {
+    let mut z = ~[];
+    let mut any = false;
+    let mut prev_digit = false;
+    for c in s.chars() {
+        if c == '_' && underscores && prev_digit {
+            prev_digit = false;
+            continue;
+        }
+        match digit_value(c, base) {
+            Some(d) => z = mul_add_ww(z, base as Word, d as Word),
+            None => return None,
+        }
+        any = true;
+        prev_digit = true;
+    }
+    if !any || !prev_digit {
+        return None;
+    }
+    Some(z)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::cmp::{Less, Equal, Greater};
+    use super::*;
+
+    fn n(s: &str) -> ~[Word] {
+        parse(s, 16, false).unwrap()
+    }
+
+    #[test]
+    fn test_add_sub() {
+        assert_eq!(add(n("ffffffffffffffff"), [1]), n("10000000000000000"));
+        assert_eq!(sub(n("10000000000000000"), [1]), n("ffffffffffffffff"));
+        assert_eq!(sub([5], [5]), ~[]);
+        assert_eq!(cmp(n("100000000"), n("ffffffff")), Greater);
+        assert_eq!(cmp([], []), Equal);
+        assert_eq!(cmp([1, 2], [2, 2]), Less);
+    }
+
+    #[test]
+    fn test_karatsuba() {
+        // operands long enough to take the Karatsuba path must agree
+        // with schoolbook multiplication
+        let mut x = ~[];
+        let mut y = ~[];
+        let mut s = 0x12345678u32;
+        for i in range(0, 150u) {
+            s = s * 1664525 + 1013904223;
+            x.push(s);
+            if i < 97 {
+                y.push(s ^ 0xdeadbeef);
+            }
+        }
+        let x = norm(x);
+        let y = norm(y);
+        assert_eq!(mul(x, y), super::basic_mul(x, y));
+        assert_eq!(mul(x, x), super::basic_mul(x, x));
+        let (q, r) = div(mul(x, y), y);
+        assert_eq!(q, x);
+        assert_eq!(r, ~[]);
+    }
+
+    #[test]
+    fn test_div() {
+        // needs the add-back step of Algorithm D
+        let u = n("7fffffff800000010000000000000000");
+        let v = n("800000008000000200000005");
+        let (q, r) = div(u, v);
+        assert_eq!(add(mul(q, v), r), u);
+        assert_eq!(cmp(r, v), Less);
+
+        let (q, r) = div(n("123456789abcdef0123456789"), n("fedcba987"));
+        assert_eq!(text(q, 16), ~"124924924998d0e9");
+        assert_eq!(text(r, 16), ~"a14026baa");
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(shl([1], 100), n("10000000000000000000000000"));
+        assert_eq!(shr(n("10000000000000000000000000"), 100), ~[1]);
+        assert_eq!(shr([1], 1), ~[]);
+        assert_eq!(bit_len(n("10000000000000000000000000")), 101);
+        assert_eq!(trailing_zero_bits(n("10000000000000000000000000")), 100);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(sqrt([0x10]), ~[4]);
+        assert_eq!(sqrt([0x11]), ~[4]);
+        let x = n("fffffffffffffffe0000000000000001");
+        assert_eq!(sqrt(x), n("ffffffffffffffff"));
+        assert_eq!(sqrt(sub(x, [1])), n("fffffffffffffffe"));
+    }
+
+    #[test]
+    fn test_exp_montgomery() {
+        let m = n("f2b3e3fb1a2d77e1f6f0aa5d3b6f1c7d9d0b7c4e1a2f3e4d5c6b7a8998877665");
+        let x = n("123456789abcdef0fedcba9876543210");
+        let y = n("10001");
+        assert_eq!(text(exp(x, y, m), 16),
+                   ~"beff18494fbf56f74baa7133baa7bcbd97d37beadbe5c9647e6273a7f8c86d2a");
+        assert_eq!(exp(x, [], m), ~[1]);
+        assert_eq!(exp(m, y, m), ~[]);
+        // single-word moduli take the same path
+        assert_eq!(exp([3], [200], [1000003]), ~[333986]);
+        assert_eq!(exp([0xfffffffe], [0x12345], [0xfffffffb]), ~[3098425734]);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let x = from_bytes([0, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(x, ~[0x02030405, 0x01]);
+        assert_eq!(to_bytes(x), ~[1u8, 2, 3, 4, 5]);
+        let mut buf = [0xffu8, ..8];
+        fill_bytes(x, buf);
+        assert_eq!(buf.as_slice(), &[0u8, 0, 0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(text([], 10), ~"0");
+        assert_eq!(text(n("ffffffffffffffff"), 10), ~"18446744073709551615");
+        assert_eq!(text(n("ffffffffffffffff"), 2).len(), 64);
+        assert_eq!(text([61], 62), ~"Z");
+        assert_eq!(parse("1_000", 10, true), Some(~[1000]));
+        assert_eq!(parse("1_000", 10, false), None);
+        assert_eq!(parse("1__0", 10, true), None);
+        assert_eq!(parse("10_", 10, true), None);
+        assert_eq!(parse("", 10, false), None);
+        assert_eq!(parse("12a", 10, false), None);
+        assert_eq!(parse("FF", 16, false), Some(~[255]));
+        assert_eq!(parse("Z", 62, false), Some(~[61]));
+    }
+}
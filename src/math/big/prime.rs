@@ -0,0 +1,277 @@
+// Primality testing: Miller-Rabin and the Baillie-PSW test, after Go's
+// math/big/prime.go.
+
+use std::cmp::{Less, Equal};
+use std::slice;
+use super::nat;
+use super::nat::Word;
+
+static primeBitMask: u64 = 1 << 2 | 1 << 3 | 1 << 5 | 1 << 7 | 1 << 11 | 1 << 13 | 1 << 17 |
+    1 << 19 | 1 << 23 | 1 << 29 | 1 << 31 | 1 << 37 | 1 << 41 | 1 << 43 | 1 << 47 |
+    1 << 53 | 1 << 59 | 1 << 61;
+
+static primesA: Word = 3 * 5 * 7 * 11 * 13 * 17 * 19 * 23 * 37;
+static primesB: Word = 29 * 31 * 41 * 43 * 47 * 53;
+
+/// Reports whether x is probably prime: n+1 Miller-Rabin rounds, the last
+/// with base 2, and a Lucas test, which together make the Baillie-PSW
+/// test. See Int::probably_prime.
+pub fn probably_prime(x: &[Word], n: uint) -> bool {
+    if x.len() == 0 {
+        return false;
+    }
+    let w = x[0];
+    if x.len() == 1 && w < 64 {
+        return primeBitMask & (1 << w) != 0;
+    }
+    if w & 1 == 0 {
+        return false; // x is even
+    }
+
+    let (_, ra) = nat::div_w(x, primesA);
+    let (_, rb) = nat::div_w(x, primesB);
+    if ra % 3 == 0 || ra % 5 == 0 || ra % 7 == 0 || ra % 11 == 0 || ra % 13 == 0 ||
+        ra % 17 == 0 || ra % 19 == 0 || ra % 23 == 0 || ra % 37 == 0 ||
+        rb % 29 == 0 || rb % 31 == 0 || rb % 41 == 0 || rb % 43 == 0 || rb % 47 == 0 ||
+        rb % 53 == 0 {
+        return false;
+    }
+
+    probably_prime_miller_rabin(x, n + 1, true) && probably_prime_lucas(x)
+}
+
+// A xorshift generator for the Miller-Rabin bases. They need not be
+// unpredictable, only well spread, and seeding from x makes the test
+// deterministic.
+struct XorShift {
+    s: u64,
+}
+
+impl XorShift {
+    fn next(&mut self) -> Word {
+        self.s ^= self.s << 13;
+        self.s ^= self.s >> 7;
+        self.s ^= self.s << 17;
+        (self.s >> 32) as Word
+    }
+
+    // Returns a uniformly distributed value in [0, limit).
+    fn below(&mut self, limit: &[Word]) -> ~[Word] {
+        let bits = nat::bit_len(limit);
+        let top = bits % nat::WordBits;
+        loop {
+            let mut z = slice::from_elem(limit.len(), 0 as Word);
+            for w in z.mut_iter() {
+                *w = self.next();
+            }
+            if top != 0 {
+                z[limit.len() - 1] &= (1 << top) - 1;
+            }
+            let z = nat::norm(z);
+            if nat::cmp(z, limit) == Less {
+                return z;
+            }
+        }
+    }
+}
+
+// Reports whether n passes reps rounds of the Miller-Rabin primality
+// test, using pseudo-randomly chosen bases. If force2 is true, one of the
+// rounds is forced to use base 2.
+fn probably_prime_miller_rabin(n: &[Word], reps: uint, force2: bool) -> bool {
+    let nm1 = nat::sub(n, [1]);
+    // determine q, k such that nm1 = q << k
+    let k = nat::trailing_zero_bits(nm1);
+    let q = nat::shr(nm1, k);
+    let nm3 = nat::sub(nm1, [2]);
+    let mut rand = XorShift { s: n[0] as u64 ^ 0x9e3779b97f4a7c15 };
+
+    'next: for i in range(0, reps) {
+        let x = if i == reps - 1 && force2 {
+            ~[2 as Word]
+        } else {
+            nat::add(rand.below(nm3), [2])
+        };
+        let mut y = nat::exp(x, q, n);
+        if nat::cmp(y, [1]) == Equal || nat::cmp(y, nm1) == Equal {
+            continue;
+        }
+        for _ in range(1, k) {
+            y = nat::modulo(nat::mul(y, y), n);
+            if nat::cmp(y, nm1) == Equal {
+                continue 'next;
+            }
+            if nat::cmp(y, [1]) == Equal {
+                return false;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Reports whether n passes the "almost extra strong" Lucas probable prime
+// test, using Baillie-OEIS parameter selection. This corresponds to
+// "AESLPSP" on Jacobsen's tables (link below). The combination of this
+// test and a Miller-Rabin/Fermat test with base 2 gives a Baillie-PSW
+// test.
+//
+// References:
+//
+// Baillie and Wagstaff, "Lucas Pseudoprimes", Mathematics of Computation
+// 35(152), October 1980, pp. 1391-1417, especially page 1401.
+//
+// Grantham, "Frobenius Pseudoprimes", Mathematics of Computation 70(234),
+// March 2000, pp. 873-891.
+//
+// Jacobsen, "Pseudoprime Statistics, Tables, and Data",
+// http://ntheory.org/pseudoprimes.html.
+fn probably_prime_lucas(n: &[Word]) -> bool {
+    // Discard 0, 1.
+    if n.len() == 0 || nat::cmp(n, [1]) == Equal {
+        return false;
+    }
+    // Two is the only even prime.
+    if n[0] & 1 == 0 {
+        return nat::cmp(n, [2]) == Equal;
+    }
+
+    // Baillie-OEIS "method C" for choosing D, P, Q: try increasing P >= 3
+    // such that D = P^2 - 4 (so Q = 1) until Jacobi(D, n) = -1.
+    let mut p = 3 as Word;
+    loop {
+        if p > 10000 {
+            // This is widely believed to be impossible.
+            fail!("math/big: internal error: cannot find (D/n) = -1 for {}", nat::text(n, 10));
+        }
+        let d = nat::from_u64((p * p - 4) as u64);
+        let j = jacobi(d, n);
+        if j == -1 {
+            break;
+        }
+        if j == 0 {
+            // d = p^2-4 = (p-2)(p+2). If (d/n) == 0 then d shares a prime
+            // factor with n. Since the loop proceeds in increasing p and
+            // starts with p-2 == 1, the shared prime factor must be p+2.
+            // If p+2 == n, then n is prime; otherwise p+2 is a proper
+            // factor of n.
+            return n.len() == 1 && n[0] == p + 2;
+        }
+        if p == 40 {
+            // We'll never find (d/n) = -1 if n is a square. If n is a
+            // non-square we expect to find a d in just a few attempts on
+            // average. After 40 attempts, check whether n is a square.
+            let t = nat::sqrt(n);
+            if nat::cmp(nat::mul(t, t), n) == Equal {
+                return false;
+            }
+        }
+        p += 1;
+    }
+
+    // Grantham's definition of "extra strong Lucas pseudoprime", after
+    // Thm 2.3 on p. 876 (D, P, Q above have become Delta, b, 1):
+    //
+    // Let U_n = U_n(b, 1), V_n = V_n(b, 1), and Delta = b^2-4. An extra
+    // strong Lucas pseudoprime to base b is a composite
+    // n = 2^r s + Jacobi(Delta, n), where s is odd and gcd(n, 2*Delta) = 1,
+    // such that either (i) U_s = 0 mod n and V_s = +/-2 mod n, or (ii)
+    // V_{2^t s} = 0 mod n for some 0 <= t < r-1.
+    //
+    // Arrange s = (n - Jacobi(Delta, n)) / 2^r = (n+1) / 2^r.
+    let s = nat::add(n, [1]);
+    let r = nat::trailing_zero_bits(s);
+    let s = nat::shr(s, r);
+    let nm2 = nat::sub(n, [2]);
+    let natp = ~[p];
+
+    // Compute the Lucas sequence V_s(b, 1), where V(0) = 2, V(1) = P and
+    // V(k) = P V(k-1) - Q V(k-2). Since V(j+k) = V(j)V(k) - V(k-j),
+    //
+    //     V(2k) = V(k)^2 - 2
+    //     V(2k+1) = V(k) V(k+1) - P
+    //
+    // so we can start with k=0 and build up to k=s in log2(s) steps.
+    let mut vk = ~[2 as Word];
+    let mut vk1 = ~[p];
+    for i in range(0, nat::bit_len(s) + 1).rev() {
+        // V(2k+1) = V(k) V(k+1) - P
+        let odd = nat::modulo(nat::sub(nat::add(nat::mul(vk, vk1), n), natp), n);
+        if nat::bit(s, i) != 0 {
+            // k' = 2k+1, and V(k'+1) = V(2k+2) = V(k+1)^2 - 2
+            vk1 = nat::modulo(nat::add(nat::mul(vk1, vk1), nm2), n);
+            vk = odd;
+        } else {
+            // k' = 2k, and V(k') = V(2k) = V(k)^2 - 2
+            vk = nat::modulo(nat::add(nat::mul(vk, vk), nm2), n);
+            vk1 = odd;
+        }
+    }
+
+    // Now k=s, so vk = V(s). Check V(s) = +/-2 (mod n).
+    if nat::cmp(vk, [2]) == Equal || nat::cmp(vk, nm2) == Equal {
+        // Check U(s) = 0. By Crandall and Pomerance equation 3.13,
+        // U(k) = D^-1 (2 V(k+1) - P V(k)), so it suffices to check that
+        // P V(k) - 2 V(k+1) == 0 mod n.
+        let mut t1 = nat::mul(vk, natp);
+        let mut t2 = nat::shl(vk1, 1);
+        if nat::cmp(t1, t2) == Less {
+            let t = t1;
+            t1 = t2;
+            t2 = t;
+        }
+        if nat::modulo(nat::sub(t1, t2), n).len() == 0 {
+            return true;
+        }
+    }
+
+    // Check V(2^t s) = 0 mod n for some 0 <= t < r-1.
+    for _ in range(0, r - 1) {
+        if vk.len() == 0 {
+            return true;
+        }
+        // V(k) = 2 is a fixed point for V(k') = V(k)^2 - 2, so if V(k) = 2
+        // we will never find a future V(k) == 0.
+        if vk.len() == 1 && vk[0] == 2 {
+            return false;
+        }
+        // k' = 2k, V(k') = V(2k) = V(k)^2 - 2
+        vk = nat::modulo(nat::sub(nat::mul(vk, vk), [2]), n);
+    }
+    false
+}
+
+/// Returns the Jacobi symbol (a/b) for a >= 0 and odd b > 0.
+pub fn jacobi(a: &[Word], b: &[Word]) -> int {
+    let mut a = a.to_owned();
+    let mut b = b.to_owned();
+    let mut j = 1;
+    loop {
+        if nat::cmp(b, [1]) == Equal {
+            return j;
+        }
+        if a.len() == 0 {
+            return 0;
+        }
+        a = nat::modulo(a, b);
+        if a.len() == 0 {
+            return 0;
+        }
+        // handle factors of 2 in a
+        let s = nat::trailing_zero_bits(a);
+        if s & 1 != 0 {
+            let bmod8 = b[0] & 7;
+            if bmod8 == 3 || bmod8 == 5 {
+                j = -j;
+            }
+        }
+        let c = nat::shr(a, s); // a = 2^s * c
+
+        // swap numerator and denominator
+        if b[0] & 3 == 3 && c[0] & 3 == 3 {
+            j = -j;
+        }
+        a = b;
+        b = c;
+    }
+}
@@ -0,0 +1,350 @@
+// Multi-precision rational numbers, after Go's math/big Rat.
+
+use std::cmp::{Ordering, Less};
+use std::fmt;
+use std::from_str::{FromStr, from_str};
+use super::Int;
+
+/// A quotient a/b of arbitrary precision. It is always kept normalized:
+/// b is positive and shares no factor with a, and zero is 0/1.
+#[deriving(Clone, Eq, TotalEq)]
+pub struct Rat {
+    a: Int,
+    b: Int,
+}
+
+// Returns a/b in lowest terms. b must not be zero.
+fn norm(a: Int, b: Int) -> Rat {
+    if b.sign() == 0 {
+        fail!("division by zero");
+    }
+    if a.sign() == 0 {
+        return Rat { a: a, b: Int::new(1) };
+    }
+    let (g, _, _) = a.gcd(&b);
+    let (a, b) = (a.quo(&g), b.quo(&g));
+    if b.sign() < 0 {
+        Rat { a: -a, b: -b }
+    } else {
+        Rat { a: a, b: b }
+    }
+}
+
+fn ten_pow(n: uint) -> Int {
+    Int::new(10).exp(&Int::from_u64(n as u64), None).unwrap()
+}
+
+impl Rat {
+    /// Returns a new Rat set to a/b. Fails if b is zero.
+    pub fn new(a: i64, b: i64) -> Rat {
+        norm(Int::new(a), Int::new(b))
+    }
+
+    /// Returns a/b, like Go's SetFrac. Fails if b is zero.
+    pub fn from_frac(a: &Int, b: &Int) -> Rat {
+        norm(a.clone(), b.clone())
+    }
+
+    /// Returns x as a Rat.
+    pub fn from_int(x: &Int) -> Rat {
+        Rat { a: x.clone(), b: Int::new(1) }
+    }
+
+    // Parses a fraction or a decimal number; see FromStr.
+    fn parse(s: &str) -> Option<Rat> {
+        if s.len() == 0 {
+            return None;
+        }
+        match s.find('/') {
+            Some(i) => {
+                let den = s.slice_from(i + 1);
+                if den.starts_with("-") || den.starts_with("+") {
+                    return None;
+                }
+                let a = match Int::from_str_radix(s.slice_to(i), 0) {
+                    Some(a) => a,
+                    None => return None,
+                };
+                match Int::from_str_radix(den, 0) {
+                    Some(ref b) if b.sign() != 0 => Some(norm(a, b.clone())),
+                    _ => None,
+                }
+            }
+            None => Rat::from_decimal(s),
+        }
+    }
+
+    fn from_decimal(s: &str) -> Option<Rat> {
+        let (mant, exp) = match s.find(|c: char| c == 'e' || c == 'E') {
+            Some(i) => {
+                let e = s.slice_from(i + 1);
+                let e = if e.starts_with("+") { e.slice_from(1) } else { e };
+                match from_str::<i64>(e) {
+                    Some(e) => (s.slice_to(i), e),
+                    None => return None,
+                }
+            }
+            None => (s, 0),
+        };
+        let (int_part, frac_part) = match mant.find('.') {
+            Some(i) => (mant.slice_to(i), mant.slice_from(i + 1)),
+            None => (mant, ""),
+        };
+        let (neg, int_part) = if int_part.starts_with("-") {
+            (true, int_part.slice_from(1))
+        } else if int_part.starts_with("+") {
+            (false, int_part.slice_from(1))
+        } else {
+            (false, int_part)
+        };
+        if int_part.len() + frac_part.len() == 0 ||
+            !int_part.chars().chain(frac_part.chars()).all(|c| c >= '0' && c <= '9') {
+            return None;
+        }
+        let digits = int_part.to_owned() + frac_part;
+        let a = Int::from_str_radix(digits, 10).unwrap();
+        let a = if neg { -a } else { a };
+        let exp = exp - frac_part.len() as i64;
+        if exp >= 0 {
+            Some(Rat::from_int(&(a * ten_pow(exp as uint))))
+        } else {
+            Some(norm(a, ten_pow((-exp) as uint)))
+        }
+    }
+
+    /// Returns the numerator of x; it may be <= 0.
+    pub fn num(&self) -> Int {
+        self.a.clone()
+    }
+
+    /// Returns the denominator of x; it is always > 0.
+    pub fn denom(&self) -> Int {
+        self.b.clone()
+    }
+
+    /// Returns -1, 0 or +1 for x < 0, x == 0 and x > 0.
+    pub fn sign(&self) -> int {
+        self.a.sign()
+    }
+
+    /// Reports whether the denominator of x is 1.
+    pub fn is_int(&self) -> bool {
+        self.b == Int::new(1)
+    }
+
+    /// Returns |x|.
+    pub fn abs(&self) -> Rat {
+        Rat { a: self.a.abs(), b: self.b.clone() }
+    }
+
+    /// Returns 1/x. Fails if x is zero.
+    pub fn inv(&self) -> Rat {
+        norm(self.b.clone(), self.a.clone())
+    }
+
+    /// Returns x/y. Fails if y is zero.
+    pub fn quo(&self, y: &Rat) -> Rat {
+        norm(self.a * y.b, self.b * y.a)
+    }
+
+    /// Returns x as "a/b", or as "a" if the denominator is 1.
+    pub fn rat_string(&self) -> ~str {
+        if self.is_int() {
+            self.a.text(10)
+        } else {
+            format!("{}", *self)
+        }
+    }
+
+    /// Returns x in decimal form with prec digits of precision after the
+    /// radix point. The last digit is rounded to nearest, with halves
+    /// rounded away from zero.
+    pub fn float_string(&self, prec: uint) -> ~str {
+        let mut buf = ~"";
+        if self.is_int() {
+            buf.push_str(self.a.text(10));
+            if prec > 0 {
+                buf.push_char('.');
+                for _ in range(0, prec) {
+                    buf.push_char('0');
+                }
+            }
+            return buf;
+        }
+
+        let (mut q, r) = self.a.abs().quo_rem(&self.b);
+        let p = ten_pow(prec);
+        let (mut r, r2) = (r * p).quo_rem(&self.b);
+        // see if we need to round up
+        if self.b <= r2 + r2 {
+            r = r + Int::new(1);
+            if r >= p {
+                q = q + Int::new(1);
+                r = r - p;
+            }
+        }
+        if self.a.sign() < 0 {
+            buf.push_char('-');
+        }
+        buf.push_str(q.text(10));
+        if prec > 0 {
+            buf.push_char('.');
+            let rs = r.text(10);
+            for _ in range(rs.len(), prec) {
+                buf.push_char('0');
+            }
+            buf.push_str(rs);
+        }
+        buf
+    }
+}
+
+impl Add<Rat, Rat> for Rat {
+    fn add(&self, y: &Rat) -> Rat {
+        norm(self.a * y.b + y.a * self.b, self.b * y.b)
+    }
+}
+
+impl Sub<Rat, Rat> for Rat {
+    fn sub(&self, y: &Rat) -> Rat {
+        norm(self.a * y.b - y.a * self.b, self.b * y.b)
+    }
+}
+
+impl Mul<Rat, Rat> for Rat {
+    fn mul(&self, y: &Rat) -> Rat {
+        norm(self.a * y.a, self.b * y.b)
+    }
+}
+
+impl Neg<Rat> for Rat {
+    fn neg(&self) -> Rat {
+        Rat { a: -self.a, b: self.b.clone() }
+    }
+}
+
+impl Ord for Rat {
+    fn lt(&self, y: &Rat) -> bool {
+        self.cmp(y) == Less
+    }
+}
+
+impl TotalOrd for Rat {
+    fn cmp(&self, y: &Rat) -> Ordering {
+        (self.a * y.b).cmp(&(y.a * self.b))
+    }
+}
+
+/// Parses s as a fraction "a/b" or as a decimal number with an optional
+/// exponent, like "-1.25e-3", like Go's SetString. The parts of a fraction
+/// may use base prefixes as in Int::from_str_radix with base 0; only the
+/// numerator may be signed.
+impl FromStr for Rat {
+    fn from_str(s: &str) -> Option<Rat> {
+        Rat::parse(s)
+    }
+}
+
+impl fmt::Show for Rat {
+    /// Formats x as "a/b", even if b is 1.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f.buf, "{}/{}", self.a, self.b)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::cmp::{Less, Greater};
+    use std::from_str::from_str;
+    use super::*;
+    use super::super::Int;
+
+    fn rat(s: &str) -> Rat {
+        from_str::<Rat>(s).unwrap()
+    }
+
+    #[test]
+    fn test_set_string() {
+        let tests = [
+            ("0", Some("0/1")),
+            ("-0", Some("0/1")),
+            ("1", Some("1/1")),
+            ("-1", Some("-1/1")),
+            ("1.", Some("1/1")),
+            ("1e0", Some("1/1")),
+            ("1.e1", Some("10/1")),
+            ("1/0", None),
+            ("4/3/2", None),
+            ("4/-3", None),
+            ("-4/3", Some("-4/3")),
+            ("4/6", Some("2/3")),
+            ("0x10/0x20", Some("1/2")),
+            (".25", Some("1/4")),
+            ("-1.25", Some("-5/4")),
+            ("12.5e-3", Some("1/80")),
+            ("1.5E+2", Some("150/1")),
+            ("-.e1", None),
+            ("1e", None),
+            ("a", None),
+            ("", None),
+        ];
+        for &(s, want) in tests.iter() {
+            let got = from_str::<Rat>(s).map(|x| format!("{}", x));
+            assert_eq!(got, want.map(|w| w.to_owned()));
+        }
+    }
+
+    #[test]
+    fn test_arith() {
+        let a = rat("1/3");
+        let b = rat("-1/6");
+        assert_eq!(a + b, rat("1/6"));
+        assert_eq!(a - b, rat("1/2"));
+        assert_eq!(a * b, rat("-1/18"));
+        assert_eq!(a.quo(&b), rat("-2"));
+        assert_eq!(b.inv(), Rat::new(-6, 1));
+        assert_eq!((-b).abs(), b.abs());
+        assert_eq!(Rat::new(6, -4), rat("-3/2"));
+        assert_eq!(Rat::new(6, -4).num(), Int::new(-3));
+        assert_eq!(Rat::new(6, -4).denom(), Int::new(2));
+        assert!(Rat::new(4, 2).is_int());
+        assert_eq!((a - a).sign(), 0);
+        assert_eq!(a.cmp(&b), Greater);
+        assert_eq!(b.cmp(&a), Less);
+        assert!(rat("0.333") < a);
+        assert_eq!(Rat::from_frac(&Int::new(10), &Int::new(-4)).rat_string(), ~"-5/2");
+        assert_eq!(Rat::from_int(&Int::new(7)).rat_string(), ~"7");
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_zero_denominator() {
+        Rat::new(1, 0);
+    }
+
+    #[test]
+    fn test_float_string() {
+        // from Go's TestFloatString
+        let tests = [
+            ("0", 0u, "0"),
+            ("0", 4, "0.0000"),
+            ("1", 0, "1"),
+            ("1", 2, "1.00"),
+            ("-1", 0, "-1"),
+            ("0.05", 1, "0.1"),
+            ("-0.05", 1, "-0.1"),
+            (".25", 2, "0.25"),
+            (".25", 1, "0.3"),
+            (".25", 3, "0.250"),
+            ("-1/3", 3, "-0.333"),
+            ("-2/3", 4, "-0.6667"),
+            ("0.96", 1, "1.0"),
+            ("0.999", 2, "1.00"),
+            ("0.9", 0, "1"),
+        ];
+        for &(s, prec, want) in tests.iter() {
+            assert_eq!(rat(s).float_string(prec), want.to_owned());
+        }
+    }
+}
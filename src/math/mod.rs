@@ -0,0 +1,4 @@
+// Basic mathematical types beyond the standard library, after Go's math
+// packages.
+
+pub mod big;